@@ -69,15 +69,16 @@ def outline(text_file: typer.FileText) -> None:
 def tidy_up(
     markdown_file: list[Path] = typer.Argument(
         ...,
-        help="Markdown files to tidy up",
+        help="Markdown files, or directories with Markdown files, to tidy up",
         exists=True,
         file_okay=True,
-        dir_okay=False,
+        dir_okay=True,
         writable=True,
     ),
 ) -> None:
     """Tidy up your Markdown files by removing empty bullets and double spaces in any block."""
-    for each_file in markdown_file:
+    files = [Path(each_file) for path in markdown_file for each_file in rust_ext.markdown_files(path)]
+    for each_file in files:
         changed = []
         old_contents = each_file.read_text()
 
@@ -90,7 +91,7 @@ def tidy_up(
         if rm_double_spaces != rm_empty_bullets:
             changed.append("double spaces")
         if changed:
-            each_file.write_text(rm_double_spaces)
+            rust_ext.write_file(each_file, rm_double_spaces)
 
         if rust_ext.tidy_up(each_file):
             changed.append("brackets")
@@ -0,0 +1,32 @@
+/*
+ * Logseq Doctor: heal your Markdown files
+ *
+ * C ABI of the Rust library. Strings are NUL-terminated UTF-8; the strings returned belong to
+ * the caller, who must release them with lsd_string_free(). On failure, functions return NULL
+ * and lsd_last_error() describes what went wrong. A panic of the library doesn't unwind into
+ * the caller: it is a failure too.
+ */
+#ifndef LOGSEQ_DOCTOR_H
+#define LOGSEQ_DOCTOR_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Fixes a page given as text with all the auto-fixable built-in rules, returns the fixed text */
+char *lsd_fix_string(const char *text);
+
+/* Lints a Markdown file with the rules of its .lsd.toml if any, returns a JSON report */
+char *lsd_lint_file(const char *path);
+
+/* The error of the last call that returned NULL on this thread, owned by the library */
+const char *lsd_last_error(void);
+
+/* Frees a string returned by the library; does nothing with NULL */
+void lsd_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,143 @@
+//! Logseq Doctor: heal your Markdown files
+//!
+//! C ABI of the Rust library, so tools written in Go, Node, Swift or any language with a C
+//! foreign function interface can embed it without Python. The functions are declared in
+//! `include/logseq_doctor.h`.
+//!
+//! Strings go in and out as NUL-terminated UTF-8. The strings returned belong to the caller,
+//! who must release them with [lsd_string_free]. On failure, functions return NULL and
+//! [lsd_last_error] describes what went wrong. A panic of the library doesn't unwind into the
+//! caller: it is a failure too.
+use logseq::batch::lint_file;
+use logseq::lint::{LintConfig, RuleSet};
+use logseq::report::Report;
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+thread_local! {
+    /// The error of the last call that failed on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Keeps the error of a failed call for [lsd_last_error], and returns NULL
+fn fail(message: impl Into<String>) -> *mut c_char {
+    let message = message.into().replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+    ptr::null_mut()
+}
+
+/// The message of a panic
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload.downcast_ref::<String>().map_or("", String::as_str),
+    };
+    format!("the library panicked: {}", message)
+}
+
+/// Runs the body of a function of the C ABI, turning a panic into a failure
+fn guard<T>(failed: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        fail(panic_message(payload.as_ref()));
+        failed
+    })
+}
+
+/// Hands a string over to the caller, who frees it with [lsd_string_free]
+fn give(text: String) -> *mut c_char {
+    match CString::new(text) {
+        Ok(text) => text.into_raw(),
+        Err(_) => fail("the result contains a NUL character"),
+    }
+}
+
+/// Reads a string of the caller
+///
+/// # Safety
+///
+/// `text` must be NULL or point to a NUL-terminated string.
+unsafe fn take<'a>(text: *const c_char, name: &str) -> Result<&'a str, String> {
+    if text.is_null() {
+        return Err(format!("{} is NULL", name));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| format!("{} isn't valid UTF-8", name))
+}
+
+/// The rules for a file: the ones of the `.lsd.toml` found in its directory or its parents,
+/// like `lsd lint` does, or else all built-in rules
+fn rules_for(path: &Path) -> logseq::Result<RuleSet> {
+    match LintConfig::discover(path.parent().unwrap_or(Path::new(".")))? {
+        Some(config) => config.rule_set(),
+        None => Ok(RuleSet::builtin()),
+    }
+}
+
+/// Fixes a page given as text with all the auto-fixable built-in rules, and returns the
+/// fixed text; NULL if the text isn't valid UTF-8.
+///
+/// # Safety
+///
+/// `text` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lsd_fix_string(text: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || match take(text, "text") {
+        Ok(text) => give(RuleSet::builtin().fix(text).into_owned()),
+        Err(message) => fail(message),
+    })
+}
+
+/// Lints a Markdown file without changing it, with the rules of its `.lsd.toml` if any, and
+/// returns the JSON report of `lsd lint --format json`; NULL if the file or its config can't be
+/// read.
+///
+/// # Safety
+///
+/// `path` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lsd_lint_file(path: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let path = match take(path, "path") {
+            Ok(path) => Path::new(path),
+            Err(message) => return fail(message),
+        };
+        let report = rules_for(path).and_then(|rules| lint_file(path, &rules));
+        match report {
+            Ok(result) => give(Report::from_lint(Some(path), &result).to_json()),
+            Err(error) => fail(error.to_string()),
+        }
+    })
+}
+
+/// The error of the last call that returned NULL on this thread, or NULL if none failed.
+/// The string belongs to the library and stays valid until the next failure on the thread.
+#[no_mangle]
+pub extern "C" fn lsd_last_error() -> *const c_char {
+    catch_unwind(|| {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |error| error.as_ptr())
+        })
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Frees a string returned by the library; does nothing with NULL.
+///
+/// # Safety
+///
+/// `text` must be NULL or a string returned by this library, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn lsd_string_free(text: *mut c_char) {
+    guard((), || {
+        if !text.is_null() {
+            drop(CString::from_raw(text));
+        }
+    })
+}
@@ -0,0 +1,51 @@
+//! Integration tests for the C ABI
+use logseq_doctor_ffi::{lsd_fix_string, lsd_last_error, lsd_lint_file, lsd_string_free};
+use std::ffi::{CStr, CString};
+use std::fs;
+
+/// Copies a string returned by the library, and frees it
+fn owned(text: *mut std::ffi::c_char) -> Option<String> {
+    if text.is_null() {
+        return None;
+    }
+    let copy = unsafe { CStr::from_ptr(text) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    unsafe { lsd_string_free(text) };
+    Some(copy)
+}
+
+fn last_error() -> String {
+    let error = lsd_last_error();
+    assert!(!error.is_null());
+    unsafe { CStr::from_ptr(error) }
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_fix_string_and_lint_file() -> Result<(), Box<dyn std::error::Error>> {
+    let text = CString::new("- a  b")?;
+    assert_eq!(
+        owned(unsafe { lsd_fix_string(text.as_ptr()) }).as_deref(),
+        Some("- a b")
+    );
+    assert_eq!(owned(unsafe { lsd_fix_string(std::ptr::null()) }), None);
+    assert_eq!(last_error(), "text is NULL");
+
+    let dir = std::env::temp_dir().join("lsd-ffi-test");
+    fs::create_dir_all(&dir)?;
+    let page = dir.join("page.md");
+    fs::write(&page, "- a  b\n")?;
+    let path = CString::new(page.to_str().unwrap())?;
+    let report = owned(unsafe { lsd_lint_file(path.as_ptr()) }).unwrap();
+    assert!(report.starts_with(r#"{"findings":[{"rule":"consecutive-spaces","#));
+
+    let missing = CString::new(dir.join("missing.md").to_str().unwrap())?;
+    assert_eq!(owned(unsafe { lsd_lint_file(missing.as_ptr()) }), None);
+    assert!(last_error().contains("missing.md"));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Python classes for the backlinks of a graph
+
+use logseq::graph::backlinks::build_backlinks;
+use logseq::graph::{page_key, Graph};
+use pyo3::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// A reference to a page: a link, tag, embed or property value
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct Reference {
+    /// The name of the referenced page, as written
+    target: String,
+    /// The file with the reference
+    path: PathBuf,
+    /// The name of the page with the reference
+    page: String,
+    /// True if the page with the reference is a journal
+    journal: bool,
+    /// Line number, starting at 1
+    line: usize,
+    /// Column, starting at 1
+    column: usize,
+    /// `link`, `tag`, `embed` or `property`
+    kind: String,
+}
+
+impl From<&logseq::graph::backlinks::Reference> for Reference {
+    fn from(reference: &logseq::graph::backlinks::Reference) -> Self {
+        Reference {
+            target: reference.target.clone(),
+            path: reference.path.clone(),
+            page: reference.page.clone(),
+            journal: reference.journal,
+            line: reference.line,
+            column: reference.column,
+            kind: reference.kind.to_string(),
+        }
+    }
+}
+
+#[pymethods]
+impl Reference {
+    fn __repr__(&self) -> String {
+        format!(
+            "Reference(target={:?}, page={:?}, line={}, column={}, kind={:?})",
+            self.target, self.page, self.line, self.column, self.kind
+        )
+    }
+}
+
+/// Every reference to every page of a graph; page names are compared like Logseq does
+#[pyclass(module = "rust_ext", frozen)]
+pub struct Backlinks {
+    backlinks: HashMap<String, Vec<logseq::graph::backlinks::Reference>>,
+    /// Keys of the pages (and aliases) that have a file
+    files: HashSet<String>,
+}
+
+impl Backlinks {
+    /// The backlinks of a graph
+    pub fn new(graph: &Graph) -> Self {
+        let files = graph
+            .pages
+            .iter()
+            .flat_map(|page| std::iter::once(page.name.clone()).chain(page.aliases()))
+            .map(|name| page_key(&name))
+            .collect();
+        Backlinks {
+            backlinks: build_backlinks(graph),
+            files,
+        }
+    }
+}
+
+#[pymethods]
+impl Backlinks {
+    /// The references to a page, in the order of the files and lines
+    fn references(&self, page: &str) -> Vec<Reference> {
+        self.backlinks
+            .get(&page_key(page))
+            .map(|references| references.iter().map(Reference::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// The referenced pages, as first written, sorted
+    fn pages(&self) -> Vec<String> {
+        let mut pages: Vec<String> = self
+            .backlinks
+            .values()
+            .filter_map(|references| references.first())
+            .map(|reference| reference.target.clone())
+            .collect();
+        pages.sort_by_key(|name| name.to_lowercase());
+        pages
+    }
+
+    /// True if the page (or a page with this alias) has a file in the graph
+    fn has_file(&self, page: &str) -> bool {
+        self.files.contains(&page_key(page))
+    }
+
+    fn __contains__(&self, page: &str) -> bool {
+        self.backlinks.contains_key(&page_key(page))
+    }
+
+    fn __len__(&self) -> usize {
+        self.backlinks.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Backlinks(pages={})", self.backlinks.len())
+    }
+}
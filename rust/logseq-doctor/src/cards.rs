@@ -0,0 +1,48 @@
+//! Python classes for flashcards
+
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+/// A block tagged `#card`, with its children as the answer
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct Flashcard {
+    /// File of the page with the card
+    path: PathBuf,
+    /// Line of the block, starting at 1
+    line: usize,
+    /// Name of the page with the card
+    page: String,
+    /// Text of the block, without the tag and properties
+    question: String,
+    /// The children of the block as a Markdown list
+    answer: String,
+    /// The hidden parts of the question, from `{{cloze ...}}` macros
+    clozes: Vec<String>,
+    /// The UUID of the block's `id::` property, if it has one
+    id: Option<String>,
+}
+
+impl From<logseq::cards::Flashcard> for Flashcard {
+    fn from(card: logseq::cards::Flashcard) -> Self {
+        Flashcard {
+            path: card.path,
+            line: card.line,
+            page: card.page,
+            question: card.question,
+            answer: card.answer,
+            clozes: card.clozes,
+            id: card.id,
+        }
+    }
+}
+
+#[pymethods]
+impl Flashcard {
+    fn __repr__(&self) -> String {
+        format!(
+            "Flashcard(page={:?}, line={}, question={:?}, clozes={:?})",
+            self.page, self.line, self.question, self.clozes
+        )
+    }
+}
@@ -0,0 +1,411 @@
+//! Python classes for the pages and blocks of a graph: the graph is read once, and pages and
+//! blocks refer to it instead of copying their contents
+
+use crate::backlinks::Backlinks;
+use chrono::Datelike;
+use logseq::graph::Graph;
+use logseq::outline::{self, Outline};
+use logseq::properties::Properties;
+use pyo3::prelude::*;
+use pyo3::types::PyDate;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Where the outline of a block lives
+#[derive(Clone)]
+enum Source {
+    /// A page of a graph, by index
+    Page(Arc<Graph>, usize),
+    /// An outline of its own, like the text a Python rule lints
+    Outline(Arc<Outline>),
+}
+
+impl Source {
+    fn outline(&self) -> &Outline {
+        match self {
+            Source::Page(graph, index) => &graph.pages[*index].outline,
+            Source::Outline(outline) => outline,
+        }
+    }
+}
+
+/// The properties as written, by key
+fn raw_properties(properties: &Properties) -> HashMap<String, String> {
+    properties
+        .iter()
+        .map(|property| (property.key.clone(), property.raw.clone()))
+        .collect()
+}
+
+/// A block of a page, or of the text linted by a Python rule
+#[pyclass(module = "rust_ext", frozen)]
+pub struct Block {
+    source: Source,
+    /// Indexes of the block and its ancestors among their siblings, from the top
+    path: Vec<usize>,
+}
+
+impl Block {
+    /// The blocks of an outline, depth first, as Python objects sharing it
+    pub fn all_in(outline: Arc<Outline>) -> Vec<Block> {
+        all_blocks(&Source::Outline(outline))
+    }
+
+    fn block(&self) -> &outline::Block {
+        let mut blocks = &self.source.outline().blocks;
+        let mut block = &blocks[self.path[0]];
+        for &index in &self.path[1..] {
+            blocks = &block.children;
+            block = &blocks[index];
+        }
+        block
+    }
+}
+
+/// Blocks of a parent, with the path of the parent
+fn blocks_of(source: &Source, parent: &[usize], count: usize) -> Vec<Block> {
+    (0..count)
+        .map(|index| {
+            let mut path = parent.to_vec();
+            path.push(index);
+            Block {
+                source: source.clone(),
+                path,
+            }
+        })
+        .collect()
+}
+
+/// All the blocks of the outline of a source, depth first
+fn all_blocks(source: &Source) -> Vec<Block> {
+    let mut all = Vec::new();
+    let mut stack = blocks_of(source, &[], source.outline().blocks.len());
+    stack.reverse();
+    while let Some(block) = stack.pop() {
+        let mut children = blocks_of(source, &block.path, block.block().children.len());
+        children.reverse();
+        stack.extend(children);
+        all.push(block);
+    }
+    all
+}
+
+#[pymethods]
+impl Block {
+    /// Line number of the bullet, starting at 1
+    #[getter]
+    fn line(&self) -> usize {
+        self.block().line
+    }
+
+    /// Nesting level, 0 for top-level blocks
+    #[getter]
+    fn level(&self) -> usize {
+        self.block().level
+    }
+
+    /// Text after the bullet, with continuation lines
+    #[getter]
+    fn content(&self) -> String {
+        self.block().content.clone()
+    }
+
+    /// The first line of the content
+    #[getter]
+    fn first_line(&self) -> String {
+        self.block().first_line().to_string()
+    }
+
+    /// The `key:: value` properties of the block, as written
+    #[getter]
+    fn properties(&self) -> HashMap<String, String> {
+        raw_properties(&self.block().properties())
+    }
+
+    /// The UUID of the `id::` property, in lowercase, if the block has one
+    #[getter]
+    fn uuid(&self) -> Option<String> {
+        self.block()
+            .properties()
+            .get("id")
+            .map(|id| id.raw.trim().to_lowercase())
+    }
+
+    /// The child blocks
+    #[getter]
+    fn children(&self) -> Vec<Block> {
+        blocks_of(&self.source, &self.path, self.block().children.len())
+    }
+
+    /// Number of children of the block
+    #[getter]
+    fn child_count(&self) -> usize {
+        self.block().children.len()
+    }
+
+    /// The page of the block, or None for text linted on its own
+    #[getter]
+    fn page(&self) -> Option<Page> {
+        match &self.source {
+            Source::Page(graph, index) => Some(Page {
+                graph: graph.clone(),
+                index: *index,
+            }),
+            Source::Outline(_) => None,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let block = self.block();
+        format!(
+            "Block(line={}, level={}, first_line={:?})",
+            block.line,
+            block.level,
+            block.first_line()
+        )
+    }
+}
+
+/// A page or journal of a graph
+#[pyclass(module = "rust_ext", frozen)]
+pub struct Page {
+    graph: Arc<Graph>,
+    index: usize,
+}
+
+impl Page {
+    fn page(&self) -> &logseq::graph::GraphPage {
+        &self.graph.pages[self.index]
+    }
+
+    fn source(&self) -> Source {
+        Source::Page(self.graph.clone(), self.index)
+    }
+}
+
+#[pymethods]
+impl Page {
+    /// Name of the page, as shown in Logseq
+    #[getter]
+    fn name(&self) -> String {
+        self.page().name.clone()
+    }
+
+    /// Full path to the file
+    #[getter]
+    fn path(&self) -> PathBuf {
+        self.page().path.clone()
+    }
+
+    /// Date of the journal, or None for regular pages
+    #[getter]
+    fn journal_date<'py>(&self, python: Python<'py>) -> PyResult<Option<Bound<'py, PyDate>>> {
+        self.page()
+            .journal_date
+            .map(|date| {
+                PyDate::new_bound(python, date.year(), date.month() as u8, date.day() as u8)
+            })
+            .transpose()
+    }
+
+    /// Names of the page given by its `alias::` property
+    #[getter]
+    fn aliases(&self) -> Vec<String> {
+        self.page().aliases()
+    }
+
+    /// The `key:: value` page properties, as written
+    #[getter]
+    fn properties(&self) -> HashMap<String, String> {
+        raw_properties(&self.page().outline.page_properties())
+    }
+
+    /// The top-level blocks
+    fn blocks(&self) -> Vec<Block> {
+        blocks_of(&self.source(), &[], self.page().outline.blocks.len())
+    }
+
+    /// All the blocks, depth first
+    fn all_blocks(&self) -> Vec<Block> {
+        all_blocks(&self.source())
+    }
+
+    /// Names of the pages this page refers to through links, tags, embeds and properties
+    fn referenced_names(&self) -> Vec<String> {
+        self.page().referenced_names()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Page(name={:?}, path={:?})",
+            self.page().name,
+            self.page().path
+        )
+    }
+}
+
+/// Yields the blocks of a graph one at a time, depth first in the order of the pages; only the
+/// paths of the blocks still to visit on the current page are kept
+#[pyclass(module = "rust_ext")]
+pub struct BlockIterator {
+    graph: Arc<Graph>,
+    /// Index of the current page; the next page is read once its blocks are done
+    page: usize,
+    /// Paths of the blocks still to visit on the current page, the next one last
+    pending: Vec<Vec<usize>>,
+    /// The callable a block must satisfy to be yielded, if any
+    filter: Option<Py<PyAny>>,
+}
+
+impl BlockIterator {
+    /// The next block of the graph, before filtering
+    fn next_block(&mut self) -> Option<Block> {
+        while self.pending.is_empty() {
+            if self.page >= self.graph.pages.len() {
+                return None;
+            }
+            let count = self.graph.pages[self.page].outline.blocks.len();
+            if count == 0 {
+                self.page += 1;
+                continue;
+            }
+            self.pending = (0..count).rev().map(|index| vec![index]).collect();
+        }
+        let path = self.pending.pop()?;
+        let block = Block {
+            source: Source::Page(self.graph.clone(), self.page),
+            path,
+        };
+        let children = block.block().children.len();
+        self.pending.extend((0..children).rev().map(|index| {
+            let mut path = block.path.clone();
+            path.push(index);
+            path
+        }));
+        if self.pending.is_empty() {
+            self.page += 1;
+        }
+        Some(block)
+    }
+}
+
+#[pymethods]
+impl BlockIterator {
+    fn __iter__(iterator: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        iterator
+    }
+
+    fn __next__(&mut self, python: Python<'_>) -> PyResult<Option<Py<Block>>> {
+        while let Some(block) = self.next_block() {
+            let block = Py::new(python, block)?;
+            let keep = match &self.filter {
+                Some(filter) => filter
+                    .call1(python, (block.clone_ref(python),))?
+                    .is_truthy(python)?,
+                None => true,
+            };
+            if keep {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The pages and journals of a graph, see `read_graph`
+#[pyclass(module = "rust_ext", frozen)]
+pub struct LogseqGraph {
+    graph: Arc<Graph>,
+}
+
+impl LogseqGraph {
+    /// A graph that was read
+    pub fn new(graph: Graph) -> Self {
+        LogseqGraph {
+            graph: Arc::new(graph),
+        }
+    }
+
+    fn pages_where(&self, keep: impl Fn(&logseq::graph::GraphPage) -> bool) -> Vec<Page> {
+        self.graph
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| keep(page))
+            .map(|(index, _)| Page {
+                graph: self.graph.clone(),
+                index,
+            })
+            .collect()
+    }
+}
+
+#[pymethods]
+impl LogseqGraph {
+    /// Root directory of the graph
+    #[getter]
+    fn root(&self) -> PathBuf {
+        self.graph.root.clone()
+    }
+
+    /// The pages and journals, sorted by path
+    fn pages(&self) -> Vec<Page> {
+        self.pages_where(|_| true)
+    }
+
+    /// The journals, sorted by date
+    fn journals(&self) -> Vec<Page> {
+        let mut journals = self.pages_where(|page| page.journal_date.is_some());
+        journals.sort_by_key(|journal| journal.page().journal_date);
+        journals
+    }
+
+    /// The page with a name or alias, compared like Logseq does, or None
+    fn page(&self, name: &str) -> Option<Page> {
+        let found = self.graph.find_page(name)?;
+        let index = self
+            .graph
+            .pages
+            .iter()
+            .position(|page| std::ptr::eq(page, found))?;
+        Some(Page {
+            graph: self.graph.clone(),
+            index,
+        })
+    }
+
+    /// An iterator over the blocks of all pages, depth first in path order, creating each
+    /// Block when it is reached. With `filter`, only the blocks for which `filter(block)` is
+    /// true are yielded.
+    #[pyo3(signature = (filter = None))]
+    fn iter_blocks(&self, filter: Option<Py<PyAny>>) -> BlockIterator {
+        BlockIterator {
+            graph: self.graph.clone(),
+            page: 0,
+            pending: Vec::new(),
+            filter,
+        }
+    }
+
+    /// Every reference to every page of the graph
+    fn backlinks(&self) -> Backlinks {
+        Backlinks::new(&self.graph)
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.graph.find_page(name).is_some()
+    }
+
+    fn __len__(&self) -> usize {
+        self.graph.pages.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "LogseqGraph(root={:?}, pages={})",
+            self.graph.root,
+            self.graph.pages.len()
+        )
+    }
+}
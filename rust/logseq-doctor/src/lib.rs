@@ -1,50 +1,151 @@
 //! Logseq Doctor: heal your Markdown files
 //!
 //! Python extension written in Rust, until the whole project is ported to Rust.
-use chrono::NaiveDate;
+#![cfg(feature = "python")]
+use chrono::{Datelike, NaiveDate};
+use logseq::dates::DateFormat;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError};
 use pyo3::prelude::*;
-use pyo3::types::PyDate;
+use pyo3::types::{PyBytes, PyDate, PyString};
+use report::Report;
+use std::borrow::Cow;
 use std::path::PathBuf;
 
+mod backlinks;
+mod cards;
+mod graph;
+mod report;
+mod rules;
+mod templates;
+
+create_exception!(
+    rust_ext,
+    LsdError,
+    PyException,
+    "Base class for errors raised by Logseq Doctor."
+);
+create_exception!(
+    rust_ext,
+    ParseError,
+    LsdError,
+    "The contents of a file or a value couldn't be parsed."
+);
+create_exception!(
+    rust_ext,
+    IoError,
+    LsdError,
+    "A file couldn't be read or written."
+);
+
+/// Convert an error of the Rust library into the matching Python exception
+fn to_py_err(error: logseq::Error) -> PyErr {
+    let message = error.to_string();
+    match error {
+        logseq::Error::Io { .. } => IoError::new_err(message),
+        logseq::Error::Parse { .. } => ParseError::new_err(message),
+    }
+}
+
+/// Borrow text given as `str` or UTF-8 `bytes`, without copying it
+fn text<'a>(value: &'a Bound<'_, PyAny>) -> PyResult<&'a str> {
+    if let Ok(bytes) = value.downcast::<PyBytes>() {
+        return std::str::from_utf8(bytes.as_bytes())
+            .map_err(|error| ParseError::new_err(format!("Invalid UTF-8: {}", error)));
+    }
+    match value.downcast::<PyString>() {
+        Ok(string) => string.to_str(),
+        Err(_) => Err(PyTypeError::new_err(format!(
+            "expected str or bytes, not {}",
+            value.get_type().name()?
+        ))),
+    }
+}
+
 #[pymodule]
-fn rust_ext(_python: Python, module: &PyModule) -> PyResult<()> {
+fn rust_ext(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let python = module.py();
+    module.add("LsdError", python.get_type_bound::<LsdError>())?;
+    module.add("ParseError", python.get_type_bound::<ParseError>())?;
+    module.add("IoError", python.get_type_bound::<IoError>())?;
+    module.add_class::<report::Finding>()?;
+    module.add_class::<report::FileSummary>()?;
+    module.add_class::<Report>()?;
+    module.add_class::<cards::Flashcard>()?;
+    module.add_class::<backlinks::Reference>()?;
+    module.add_class::<backlinks::Backlinks>()?;
+    module.add_class::<graph::LogseqGraph>()?;
+    module.add_class::<graph::Page>()?;
+    module.add_class::<graph::Block>()?;
+    module.add_class::<graph::BlockIterator>()?;
+    module.add_class::<rules::RuleInfo>()?;
+    module.add_class::<templates::Template>()?;
     module.add_function(wrap_pyfunction!(remove_consecutive_spaces, module)?)?;
     module.add_function(wrap_pyfunction!(add_content, module)?)?;
     module.add_function(wrap_pyfunction!(tidy_up, module)?)?;
+    module.add_function(wrap_pyfunction!(markdown_files, module)?)?;
+    module.add_function(wrap_pyfunction!(run_lints, module)?)?;
+    module.add_function(wrap_pyfunction!(check_links, module)?)?;
+    module.add_function(wrap_pyfunction!(check_block_refs, module)?)?;
+    module.add_function(wrap_pyfunction!(fix_files, module)?)?;
+    module.add_function(wrap_pyfunction!(fix_text, module)?)?;
+    module.add_function(wrap_pyfunction!(fix_file_in_place, module)?)?;
+    module.add_function(wrap_pyfunction!(write_file, module)?)?;
+    module.add_function(wrap_pyfunction!(flat_markdown_to_outline, module)?)?;
+    module.add_function(wrap_pyfunction!(extract_flashcards, module)?)?;
+    module.add_function(wrap_pyfunction!(export_flashcards, module)?)?;
+    module.add_function(wrap_pyfunction!(build_backlinks, module)?)?;
+    module.add_function(wrap_pyfunction!(read_graph, module)?)?;
+    module.add_function(wrap_pyfunction!(list_templates, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_journal_date, module)?)?;
+    module.add_function(wrap_pyfunction!(format_journal_date, module)?)?;
+    module.add_function(wrap_pyfunction!(today, module)?)?;
+    module.add_function(wrap_pyfunction!(rules::register_rule, module)?)?;
+    module.add_function(wrap_pyfunction!(rules::unregister_rule, module)?)?;
+    module.add_function(wrap_pyfunction!(rules::list_rules, module)?)?;
     Ok(())
 }
 
 #[pyfunction]
-fn remove_consecutive_spaces(file_contents: String) -> PyResult<String> {
-    Ok(logseq::remove_consecutive_spaces(file_contents).unwrap())
+#[pyo3(signature = (file_contents, skip_code_blocks = true))]
+fn remove_consecutive_spaces(
+    python: Python<'_>,
+    file_contents: &Bound<'_, PyAny>,
+    skip_code_blocks: bool,
+) -> PyResult<String> {
+    let file_contents = text(file_contents)?;
+    python
+        .allow_threads(|| logseq::remove_consecutive_spaces_with(file_contents, skip_code_blocks))
+        .map(Cow::into_owned)
+        .map_err(to_py_err)
 }
 
 #[pyfunction]
 fn add_content(
+    python: Python<'_>,
     graph_path: PathBuf,
-    markdown: String,
+    markdown: &Bound<'_, PyAny>,
     prepend: bool,
-    parsed_date: Option<&PyDate>,
+    parsed_date: Option<&Bound<'_, PyDate>>,
 ) -> PyResult<()> {
     let naive_date = match parsed_date {
         None => None,
-        Some(pydate) => pydate_to_naivedate(pydate)
-            .unwrap_or_else(|err| panic!("Error {} while parsing date {:?}", err, pydate)),
+        Some(pydate) => Some(
+            pydate_to_naivedate(pydate)?
+                .ok_or_else(|| ParseError::new_err(format!("Invalid date {}", pydate)))?,
+        ),
     };
+    let markdown = text(markdown)?.to_string();
     let journal = logseq::Journal::new(graph_path, naive_date);
-    if prepend {
-        journal
-            .prepend(markdown)
-            .expect("Failed to prepend content to journal");
-        return Ok(());
-    }
-    journal
-        .append(markdown)
-        .expect("Failed to append content to journal");
-    Ok(())
+    python
+        .allow_threads(|| match prepend {
+            true => journal.prepend(markdown),
+            false => journal.append(markdown),
+        })
+        .map_err(to_py_err)
 }
 
-fn pydate_to_naivedate(pydate: &PyDate) -> PyResult<Option<NaiveDate>> {
+fn pydate_to_naivedate(pydate: &Bound<'_, PyDate>) -> PyResult<Option<NaiveDate>> {
     let year = pydate.getattr("year")?.extract::<i32>()?;
     let month = pydate.getattr("month")?.extract::<u32>()?;
     let day = pydate.getattr("day")?.extract::<u32>()?;
@@ -53,7 +154,334 @@ fn pydate_to_naivedate(pydate: &PyDate) -> PyResult<Option<NaiveDate>> {
 }
 
 #[pyfunction]
-fn tidy_up(page_path: PathBuf) -> PyResult<bool> {
+fn tidy_up(python: Python<'_>, page_path: PathBuf) -> PyResult<bool> {
     let page = logseq::Page::new(page_path.as_path());
-    Ok(page.tidy_up().unwrap())
+    python.allow_threads(|| page.tidy_up()).map_err(to_py_err)
+}
+
+/// The Markdown files of a path: the file itself, or the ones of a directory, found in parallel.
+/// Hidden entries like `.git` and the files of `.gitignore`, `.ignore` and `.lsdignore` files
+/// are skipped; symbolic links are followed.
+#[pyfunction]
+fn markdown_files(python: Python<'_>, path: PathBuf) -> PyResult<Vec<PathBuf>> {
+    python
+        .allow_threads(|| logseq::graph::markdown_files(&path))
+        .map_err(to_py_err)
+}
+
+/// Lint the contents of a page, returning a Report of the problems found
+#[pyfunction]
+#[pyo3(signature = (file_contents, rules = None))]
+fn run_lints(
+    python: Python<'_>,
+    file_contents: &Bound<'_, PyAny>,
+    rules: Option<Vec<String>>,
+) -> PyResult<Report> {
+    let file_contents = text(file_contents)?;
+    let (rule_set, error) = select_rules(python, rules);
+    let result = python.allow_threads(|| rule_set.lint(file_contents));
+    error.raise()?;
+    Ok(logseq::report::Report::from_lint(None, &result).into())
+}
+
+/// The built-in and registered Python rules, or only the ones with the given ids, with where
+/// the exceptions of the Python rules go
+fn select_rules(
+    python: Python<'_>,
+    rules: Option<Vec<String>>,
+) -> (logseq::lint::RuleSet, rules::CallbackError) {
+    let mut rule_set = logseq::lint::RuleSet::builtin();
+    let error = rules::CallbackError::default();
+    rules::add_python_rules(python, &mut rule_set, &error);
+    let rule_set = match rules {
+        Some(ids) => {
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            rule_set.select(&ids)
+        }
+        None => rule_set,
+    };
+    (rule_set, error)
+}
+
+/// Calls a Python `progress(done, total, path)` callable, if any, with the GIL; its first
+/// exception is kept in `error`, and the callable isn't called after it
+fn report_progress(
+    progress: &Option<Py<PyAny>>,
+    error: &rules::CallbackError,
+    update: logseq::batch::Progress<'_>,
+) {
+    let Some(callback) = progress else {
+        return;
+    };
+    Python::with_gil(|python| {
+        if error.is_set() {
+            return;
+        }
+        if let Err(exception) = callback.call1(python, (update.done, update.total, update.path)) {
+            error.keep(exception);
+        }
+    });
+}
+
+/// Read a graph with its cached index, calling `progress(done, total, path)` for the files parsed
+fn scan_graph(
+    python: Python<'_>,
+    graph_path: &std::path::Path,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<logseq::graph::Graph> {
+    let error = rules::CallbackError::default();
+    let graph = python
+        .allow_threads(|| {
+            logseq::graph::Graph::scan_cached_with_progress(graph_path, &|update| {
+                report_progress(&progress, &error, update)
+            })
+        })
+        .map_err(to_py_err)?;
+    error.raise()?;
+    Ok(graph)
+}
+
+/// Find links to missing pages in a graph, returning a Report with a `missing-page` finding for each.
+/// `progress(done, total, path)` is called for each file read.
+#[pyfunction]
+#[pyo3(signature = (graph_path, progress = None))]
+fn check_links(
+    python: Python<'_>,
+    graph_path: PathBuf,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<Report> {
+    let graph = scan_graph(python, &graph_path, progress)?;
+    let links = python.allow_threads(|| graph.check_links());
+    Ok(
+        logseq::report::Report::from_broken_links("missing-page", &links, |target| {
+            format!("Missing page [[{}]]", target)
+        })
+        .into(),
+    )
+}
+
+/// Find references to missing block ids in a graph, returning a Report with a `missing-block` finding for each.
+/// `progress(done, total, path)` is called for each file read.
+#[pyfunction]
+#[pyo3(signature = (graph_path, progress = None))]
+fn check_block_refs(
+    python: Python<'_>,
+    graph_path: PathBuf,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<Report> {
+    let graph = scan_graph(python, &graph_path, progress)?;
+    let block_refs = python.allow_threads(|| graph.check_block_refs());
+    Ok(
+        logseq::report::Report::from_broken_links("missing-block", &block_refs, |target| {
+            format!("Missing block (({}))", target)
+        })
+        .into(),
+    )
+}
+
+/// Fix files in place in parallel, without holding the GIL.
+/// With `dry_run=True`, no file is written and the report tells what would change.
+/// With `strip_bom=True`, byte order marks are removed; with `transcode=True`, files that aren't
+/// valid UTF-8 are rewritten from Latin-1 instead of being skipped.
+/// Errors are reported per file in `Report.files` instead of being raised.
+/// `progress(done, total, path)` is called as each file is done, with the GIL; files are done in
+/// parallel, so `path` is the last one done, and an exception it raises is raised at the end.
+#[pyfunction]
+#[pyo3(signature = (paths, rules = None, dry_run = false, strip_bom = false, transcode = false, progress = None))]
+fn fix_files(
+    python: Python<'_>,
+    paths: Vec<PathBuf>,
+    rules: Option<Vec<String>>,
+    dry_run: bool,
+    strip_bom: bool,
+    transcode: bool,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<Report> {
+    let (rule_set, error) = select_rules(python, rules);
+    let encoding = logseq::encoding::EncodingOptions {
+        strip_bom,
+        transcode,
+    };
+    let reports = python.allow_threads(|| {
+        logseq::batch::fix_files_with_progress(&paths, &rule_set, dry_run, &encoding, &|update| {
+            report_progress(&progress, &error, update)
+        })
+    });
+    error.raise()?;
+    Ok(logseq::report::Report::from_file_reports(&reports).into())
+}
+
+/// Fix one file in place; the new contents replace the file atomically, and with `backup=True`
+/// the original is kept as a `.bak` file next to it.
+#[pyfunction]
+#[pyo3(signature = (path, rules = None, backup = false))]
+fn fix_file_in_place(
+    python: Python<'_>,
+    path: PathBuf,
+    rules: Option<Vec<String>>,
+    backup: bool,
+) -> PyResult<Report> {
+    let (rule_set, error) = select_rules(python, rules);
+    let report = python
+        .allow_threads(|| logseq::batch::fix_file_in_place(&path, &rule_set, backup))
+        .map_err(to_py_err)?;
+    error.raise()?;
+    Ok(logseq::report::Report::from_file_reports(&[report]).into())
+}
+
+/// Write text to a file atomically: a crash while writing never leaves a truncated file behind
+#[pyfunction]
+#[pyo3(signature = (path, contents, backup = false))]
+fn write_file(
+    python: Python<'_>,
+    path: PathBuf,
+    contents: &Bound<'_, PyAny>,
+    backup: bool,
+) -> PyResult<()> {
+    let contents = text(contents)?;
+    python
+        .allow_threads(|| logseq::write_file(&path, contents, backup))
+        .map_err(to_py_err)
+}
+
+/// Fix the contents of a page with the lint rules, without writing anything.
+/// Returns the fixed text and a unified diff from the original, empty if nothing changed.
+#[pyfunction]
+#[pyo3(signature = (file_contents, rules = None, label = "page.md"))]
+fn fix_text(
+    python: Python<'_>,
+    file_contents: &Bound<'_, PyAny>,
+    rules: Option<Vec<String>>,
+    label: &str,
+) -> PyResult<(String, String)> {
+    let file_contents = text(file_contents)?;
+    let (rule_set, error) = select_rules(python, rules);
+    let outcome = python.allow_threads(|| rule_set.fix_outcome(label, file_contents));
+    error.raise()?;
+    Ok((outcome.fixed, outcome.diff))
+}
+
+/// Convert flat Markdown (headings, paragraphs and lists) into a Logseq outline
+#[pyfunction]
+fn flat_markdown_to_outline(
+    python: Python<'_>,
+    markdown_contents: &Bound<'_, PyAny>,
+) -> PyResult<String> {
+    let markdown_contents = text(markdown_contents)?;
+    Ok(python.allow_threads(|| logseq::markdown::flat_markdown_to_outline(markdown_contents)))
+}
+
+/// Find the flashcards of a graph: blocks tagged `#card`, with their children as the answer
+#[pyfunction]
+fn extract_flashcards(python: Python<'_>, graph_path: PathBuf) -> PyResult<Vec<cards::Flashcard>> {
+    let cards = python
+        .allow_threads(|| {
+            logseq::graph::Graph::scan_cached(&graph_path)
+                .map(|graph| logseq::cards::extract_flashcards(&graph))
+        })
+        .map_err(to_py_err)?;
+    Ok(cards.into_iter().map(cards::Flashcard::from).collect())
+}
+
+/// Write the flashcards of a graph as `json`, `csv` or `anki-tsv`
+#[pyfunction]
+#[pyo3(signature = (graph_path, format = "json"))]
+fn export_flashcards(python: Python<'_>, graph_path: PathBuf, format: &str) -> PyResult<String> {
+    let format: logseq::cards::CardFormat = format.parse().map_err(to_py_err)?;
+    python
+        .allow_threads(|| {
+            logseq::graph::Graph::scan_cached(&graph_path)
+                .map(|graph| format.render(&logseq::cards::extract_flashcards(&graph)))
+        })
+        .map_err(to_py_err)
+}
+
+/// Find the templates of a graph: blocks with a `template::` property
+#[pyfunction]
+fn list_templates(python: Python<'_>, graph_path: PathBuf) -> PyResult<Vec<templates::Template>> {
+    let templates = python
+        .allow_threads(|| {
+            logseq::graph::Graph::scan_cached(&graph_path)
+                .map(|graph| logseq::templates::list_templates(&graph))
+        })
+        .map_err(to_py_err)?;
+    Ok(templates
+        .into_iter()
+        .map(templates::Template::from)
+        .collect())
+}
+
+/// Read a graph with its cached index, as a LogseqGraph with its pages and blocks.
+/// `progress(done, total, path)` is called for each file parsed.
+#[pyfunction]
+#[pyo3(signature = (graph_path, progress = None))]
+fn read_graph(
+    python: Python<'_>,
+    graph_path: PathBuf,
+    progress: Option<Py<PyAny>>,
+) -> PyResult<graph::LogseqGraph> {
+    scan_graph(python, &graph_path, progress).map(graph::LogseqGraph::new)
+}
+
+/// Find every link, tag, embed and property value that refers to each page of a graph
+#[pyfunction]
+fn build_backlinks(python: Python<'_>, graph_path: PathBuf) -> PyResult<backlinks::Backlinks> {
+    python
+        .allow_threads(|| {
+            logseq::graph::Graph::scan_cached(&graph_path)
+                .map(|graph| backlinks::Backlinks::new(&graph))
+        })
+        .map_err(to_py_err)
+}
+
+/// A title format, `MMM do, yyyy` by default, with the names of a language, English by default
+fn date_format(format: Option<&str>, locale: Option<&str>) -> PyResult<DateFormat> {
+    let format = DateFormat::new(format.unwrap_or(logseq::dates::DEFAULT_TITLE_FORMAT));
+    match locale {
+        Some(locale) => Ok(format.with_locale(locale.parse().map_err(to_py_err)?)),
+        None => Ok(format),
+    }
+}
+
+fn naivedate_to_pydate<'py>(python: Python<'py>, date: NaiveDate) -> PyResult<Bound<'py, PyDate>> {
+    PyDate::new_bound(python, date.year(), date.month() as u8, date.day() as u8)
+}
+
+/// Read the date of a journal link like `[[Jan 3rd, 2024]]` or `[[2024-01-03]]`, in a
+/// `:journal/page-title-format` with month names in a language like `de`, or None
+#[pyfunction]
+#[pyo3(signature = (text, format = None, locale = None))]
+fn parse_journal_date<'py>(
+    python: Python<'py>,
+    text: &str,
+    format: Option<&str>,
+    locale: Option<&str>,
+) -> PyResult<Option<Bound<'py, PyDate>>> {
+    logseq::dates::parse_date_link(text, &date_format(format, locale)?)
+        .map(|date| naivedate_to_pydate(python, date))
+        .transpose()
+}
+
+/// Write a date as the title of its journal, in a `:journal/page-title-format` and language
+#[pyfunction]
+#[pyo3(signature = (date, format = None, locale = None))]
+fn format_journal_date(
+    date: &Bound<'_, PyDate>,
+    format: Option<&str>,
+    locale: Option<&str>,
+) -> PyResult<String> {
+    let date = pydate_to_naivedate(date)?
+        .ok_or_else(|| ParseError::new_err(format!("Invalid date {}", date)))?;
+    Ok(date_format(format, locale)?.format(date))
+}
+
+/// Today's date in the time zone of an offset from UTC like `+02:00`, or in the local one
+#[pyfunction]
+#[pyo3(signature = (utc_offset = None))]
+fn today<'py>(python: Python<'py>, utc_offset: Option<&str>) -> PyResult<Bound<'py, PyDate>> {
+    let offset = utc_offset
+        .map(logseq::dates::parse_utc_offset)
+        .transpose()
+        .map_err(to_py_err)?;
+    naivedate_to_pydate(python, logseq::dates::today(offset))
 }
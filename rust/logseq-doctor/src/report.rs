@@ -0,0 +1,160 @@
+//! Python classes for the reports of the lint engine
+
+use crate::to_py_err;
+use logseq::report::format::Format;
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+/// A boolean the way Python writes it
+fn py_bool(value: bool) -> &'static str {
+    if value {
+        "True"
+    } else {
+        "False"
+    }
+}
+
+/// A problem found in a file, and whether it was fixed automatically
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct Finding {
+    /// Id of the rule that found the problem
+    rule: String,
+    /// How serious the problem is: `error`, `warning` or `info`
+    severity: String,
+    /// The file with the problem, or None if the text didn't come from a file
+    path: Option<PathBuf>,
+    /// Line number, starting at 1
+    line: usize,
+    /// Column, starting at 1
+    column: usize,
+    /// Human-readable description of the problem
+    message: String,
+    /// True if the problem was fixed automatically
+    fixed: bool,
+    /// Identifies the finding across runs whatever the lines around it
+    fingerprint: String,
+}
+
+#[pymethods]
+impl Finding {
+    fn __repr__(&self) -> String {
+        format!(
+            "Finding(rule={:?}, severity={:?}, path={:?}, line={}, column={}, message={:?}, fixed={})",
+            self.rule,
+            self.severity,
+            self.path,
+            self.line,
+            self.column,
+            self.message,
+            py_bool(self.fixed)
+        )
+    }
+}
+
+impl From<&logseq::report::Finding> for Finding {
+    fn from(finding: &logseq::report::Finding) -> Self {
+        Finding {
+            rule: finding.rule.clone(),
+            severity: finding.severity.to_string(),
+            path: finding.path.clone(),
+            line: finding.line,
+            column: finding.column,
+            message: finding.message.clone(),
+            fixed: finding.fixed,
+            fingerprint: finding.fingerprint.clone(),
+        }
+    }
+}
+
+/// What happened to a file that was processed
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct FileSummary {
+    /// The file
+    path: PathBuf,
+    /// True if the file was (or, in a dry run, would be) rewritten
+    changed: bool,
+    /// A unified diff of the fixes, empty if nothing changed
+    diff: String,
+    /// The error that prevented the file from being processed, if any
+    error: Option<String>,
+}
+
+#[pymethods]
+impl FileSummary {
+    fn __repr__(&self) -> String {
+        format!(
+            "FileSummary(path={:?}, changed={}, error={:?})",
+            self.path,
+            py_bool(self.changed),
+            self.error
+        )
+    }
+}
+
+/// Everything found (and fixed) in one or more files
+#[pyclass(module = "rust_ext", frozen)]
+pub struct Report {
+    report: logseq::report::Report,
+}
+
+impl From<logseq::report::Report> for Report {
+    fn from(mut report: logseq::report::Report) -> Self {
+        report.normalize();
+        Report { report }
+    }
+}
+
+#[pymethods]
+impl Report {
+    /// Problems found, in the order of the files, then by position
+    #[getter]
+    fn findings(&self) -> Vec<Finding> {
+        self.report.findings.iter().map(Finding::from).collect()
+    }
+
+    /// Problems found where `lsd-disable` comments or properties suppress their rule
+    #[getter]
+    fn suppressed(&self) -> Vec<Finding> {
+        self.report.suppressed.iter().map(Finding::from).collect()
+    }
+
+    /// The files that were processed, if any
+    #[getter]
+    fn files(&self) -> Vec<FileSummary> {
+        self.report
+            .files
+            .iter()
+            .map(|file| FileSummary {
+                path: file.path.clone(),
+                changed: file.changed,
+                diff: file.diff.clone(),
+                error: file.error.clone(),
+            })
+            .collect()
+    }
+
+    /// Serialize the report as a JSON object with `findings` and `files`
+    fn to_json(&self) -> String {
+        self.report.to_json()
+    }
+
+    /// Write the report as `text`, `json`, `jsonl` or `sarif`
+    fn render(&self, format: &str) -> PyResult<String> {
+        let format: Format = format.parse().map_err(to_py_err)?;
+        Ok(format.render(&self.report))
+    }
+
+    fn __len__(&self) -> usize {
+        self.report.findings.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Report(findings={}, files={})",
+            self.report.findings.len(),
+            self.report.files.len()
+        )
+    }
+}
@@ -0,0 +1,222 @@
+//! Lint rules written in Python: callables registered with an id, called for each block
+
+use crate::graph::Block;
+use logseq::lint::{Diagnostic, Rule, RuleDoc, RuleSet};
+use logseq::outline::Outline;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+use std::sync::{Arc, Mutex};
+
+/// The registered rules, as (id, callable), in the order they were registered
+static REGISTRY: Mutex<Vec<(&'static str, Py<PyAny>)>> = Mutex::new(Vec::new());
+
+/// The first exception raised by a Python callback, a rule or a progress callable, while the
+/// Rust code runs; raised once it is done
+#[derive(Clone, Default)]
+pub struct CallbackError(Arc<Mutex<Option<PyErr>>>);
+
+impl CallbackError {
+    /// Keeps an exception, unless one was kept already
+    pub fn keep(&self, error: PyErr) {
+        self.0.lock().unwrap().get_or_insert(error);
+    }
+
+    /// Whether an exception was kept
+    pub fn is_set(&self) -> bool {
+        self.0.lock().unwrap().is_some()
+    }
+
+    /// Raises the exception of a Python rule, if one failed
+    pub fn raise(&self) -> PyResult<()> {
+        match self.0.lock().unwrap().take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A registered Python callable, as a rule of the lint engine
+struct PythonRule {
+    id: &'static str,
+    /// The first line of the docstring of the callable
+    description: String,
+    callback: Py<PyAny>,
+    error: CallbackError,
+}
+
+impl PythonRule {
+    /// The diagnostics returned by the callable for a block: None, a message, or an iterable of
+    /// messages and `(line, column, message)` tuples
+    fn call(
+        &self,
+        python: Python<'_>,
+        block: &logseq::outline::Block,
+        python_block: Block,
+    ) -> PyResult<Vec<Diagnostic>> {
+        let result = self.callback.call1(python, (python_block,))?;
+        let result = result.bind(python);
+        let at_block =
+            |message: String| Diagnostic::new(self.id, block.line, block.source_column(1), message);
+        if result.is_none() {
+            return Ok(Vec::new());
+        }
+        if let Ok(message) = result.downcast::<PyString>() {
+            return Ok(vec![at_block(message.to_string())]);
+        }
+        let mut diagnostics = Vec::new();
+        for item in result.iter()? {
+            let item = item?;
+            if let Ok(message) = item.extract::<String>() {
+                diagnostics.push(at_block(message));
+            } else if let Ok((line, column, message)) = item.extract::<(usize, usize, String)>() {
+                diagnostics.push(Diagnostic::new(self.id, line, column, message));
+            } else {
+                return Err(PyTypeError::new_err(format!(
+                    "rule {} must return messages or (line, column, message) tuples, not {}",
+                    self.id,
+                    item.get_type().name()?
+                )));
+            }
+        }
+        Ok(diagnostics)
+    }
+}
+
+impl Rule for PythonRule {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: &self.description,
+            ..RuleDoc::default()
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        Python::with_gil(|python| {
+            let mut diagnostics = Vec::new();
+            let python_blocks = Block::all_in(Arc::new(outline.clone()));
+            for (block, python_block) in outline.iter().zip(python_blocks) {
+                match self.call(python, block, python_block) {
+                    Ok(found) => diagnostics.extend(found),
+                    Err(error) => {
+                        self.error.keep(error);
+                        break;
+                    }
+                }
+            }
+            diagnostics
+        })
+    }
+}
+
+/// Adds the registered Python rules to a rule set; their exceptions go to `error`
+pub fn add_python_rules(python: Python<'_>, rule_set: &mut RuleSet, error: &CallbackError) {
+    for (id, callback) in REGISTRY.lock().unwrap().iter() {
+        let doc = callback
+            .getattr(python, "__doc__")
+            .and_then(|doc| doc.extract::<Option<String>>(python))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let description = doc.trim().lines().next().unwrap_or_default().to_string();
+        rule_set.add(PythonRule {
+            id,
+            description,
+            callback: callback.clone_ref(python),
+            error: error.clone(),
+        });
+    }
+}
+
+/// Register a Python callable as a lint rule. It is called with each Block of the linted pages
+/// and returns None, a message, or a list of messages and `(line, column, message)` tuples;
+/// messages alone are reported at the bullet of the block. The first line of its docstring is
+/// the description of the rule in list_rules(). Registering an id again replaces the callable.
+#[pyfunction]
+pub fn register_rule(rule_id: &str, callback: Bound<'_, PyAny>) -> PyResult<()> {
+    if !callback.is_callable() {
+        return Err(PyTypeError::new_err("callback must be callable"));
+    }
+    if logseq::lint::RuleSet::builtin().ids().contains(&rule_id) {
+        return Err(PyValueError::new_err(format!(
+            "{} is a built-in rule",
+            rule_id
+        )));
+    }
+    let mut registry = REGISTRY.lock().unwrap();
+    match registry.iter_mut().find(|(id, _)| *id == rule_id) {
+        Some((_, existing)) => *existing = callback.unbind(),
+        // Rule ids are static, so registered ids are leaked
+        None => registry.push((Box::leak(rule_id.into()), callback.unbind())),
+    }
+    Ok(())
+}
+
+/// Unregister a Python rule; returns False if no rule had this id
+#[pyfunction]
+pub fn unregister_rule(rule_id: &str) -> bool {
+    let mut registry = REGISTRY.lock().unwrap();
+    let count = registry.len();
+    registry.retain(|(id, _)| *id != rule_id);
+    registry.len() != count
+}
+
+/// What a lint rule is about, as listed by `lsd rules`
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct RuleInfo {
+    /// Id of the rule
+    id: String,
+    /// What the rule finds
+    description: String,
+    /// Default severity of the rule: `error`, `warning` or `info`
+    severity: String,
+    /// True if the rule fixes some of the problems it finds
+    fixable: bool,
+    /// Example pages with problems the rule finds
+    examples: Vec<String>,
+    /// The documentation as text, with what the rule finds and fixes in its examples, like
+    /// `lsd explain`
+    explanation: String,
+}
+
+#[pymethods]
+impl RuleInfo {
+    fn __repr__(&self) -> String {
+        format!(
+            "RuleInfo(id={:?}, severity={:?}, fixable={})",
+            self.id,
+            self.severity,
+            if self.fixable { "True" } else { "False" }
+        )
+    }
+}
+
+impl From<logseq::lint::RuleInfo> for RuleInfo {
+    fn from(info: logseq::lint::RuleInfo) -> Self {
+        RuleInfo {
+            explanation: info.to_text(),
+            id: info.id,
+            description: info.description,
+            severity: info.severity.to_string(),
+            fixable: info.fixable,
+            examples: info
+                .examples
+                .into_iter()
+                .map(|example| example.page)
+                .collect(),
+        }
+    }
+}
+
+/// The built-in rules and the registered Python rules, in the order they run
+#[pyfunction]
+pub fn list_rules(python: Python<'_>) -> Vec<RuleInfo> {
+    let mut rule_set = RuleSet::builtin();
+    add_python_rules(python, &mut rule_set, &CallbackError::default());
+    rule_set.infos().into_iter().map(RuleInfo::from).collect()
+}
@@ -0,0 +1,42 @@
+//! Python classes for templates
+
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+/// A block with a `template::` property
+#[pyclass(module = "rust_ext", get_all, frozen)]
+#[derive(Clone)]
+pub struct Template {
+    /// File of the page with the template
+    path: PathBuf,
+    /// Line of the block, starting at 1
+    line: usize,
+    /// Name of the page with the template
+    page: String,
+    /// Name of the template
+    name: String,
+    /// False if only the children of the block are inserted
+    including_parent: bool,
+}
+
+impl From<logseq::templates::Template> for Template {
+    fn from(template: logseq::templates::Template) -> Self {
+        Template {
+            path: template.path,
+            line: template.line,
+            page: template.page,
+            name: template.name,
+            including_parent: template.including_parent,
+        }
+    }
+}
+
+#[pymethods]
+impl Template {
+    fn __repr__(&self) -> String {
+        format!(
+            "Template(name={:?}, page={:?}, line={})",
+            self.name, self.page, self.line
+        )
+    }
+}
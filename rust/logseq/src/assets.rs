@@ -0,0 +1,408 @@
+//! Cross-reference the files of the `assets` directory with the links to them, and merge the
+//! files that are exact duplicates
+
+use crate::dedupe::fnv1a;
+use crate::graph::{collect_files, collect_files_matching, percent_decode, Graph};
+use crate::refs::{blank_code_spans, lines_outside_regions, CODE_REGIONS};
+use crate::rename::{write_edits, FileEdit};
+use crate::{
+    error, undo, Error, Result, SUBDIR_ASSETS, SUBDIR_JOURNALS, SUBDIR_PAGES, SUBDIR_WHITEBOARDS,
+};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+static TARGET_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:\]\(|\{\{\s*[\w-]+\s+)((?:\.\.?/)*assets/[^)\s}]+)").unwrap());
+static OTHER_TARGET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\[\[(?:file:)?|url\(\s*["']?|")((?:\.\.?/)*assets/[^"')\]\s]+)"#).unwrap()
+});
+
+/// A link to a file of the `assets` directory, like `![image](../assets/image.png)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetLink {
+    /// File containing the link
+    pub path: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column of the link target, starting at 1
+    pub column: usize,
+    /// The link target, as written
+    pub target: String,
+    /// Path of the asset relative to the `assets` directory
+    pub asset: PathBuf,
+}
+
+/// Finds the links to assets in a line, as (column, target) pairs: Markdown links and images
+/// like `![alt](../assets/image.png)`, and macros like `{{pdf ../assets/file.pdf}}`
+///
+/// # Examples
+///
+/// ```
+/// use logseq::assets::asset_targets;
+/// let targets = asset_targets("![cat](../assets/cat.png) {{pdf ../assets/My%20File.pdf}} [site](https://x.com/assets/a.png)");
+/// assert_eq!(targets, vec![(8, "../assets/cat.png".to_string()), (33, "../assets/My%20File.pdf".to_string())]);
+/// assert!(asset_targets("`![cat](../assets/cat.png)`").is_empty());
+/// ```
+pub fn asset_targets(line: &str) -> Vec<(usize, String)> {
+    target_ranges(line)
+        .into_iter()
+        .map(|range| {
+            (
+                line[..range.start].chars().count() + 1,
+                line[range].to_string(),
+            )
+        })
+        .collect()
+}
+
+/// The byte ranges of the links to assets of a line, see [asset_targets]
+fn target_ranges(line: &str) -> Vec<Range<usize>> {
+    let searchable = blank_code_spans(line);
+    TARGET_RE
+        .captures_iter(&searchable)
+        .map(|captures| captures.get(1).unwrap().range())
+        .collect()
+}
+
+/// The byte ranges of the links to assets of a line of a file that isn't a Markdown page, see
+/// [other_asset_files]: the `:src "../assets/image.png"` of whiteboards, the
+/// `[[file:../assets/image.png]]` links of Org pages and the `url(../assets/font.woff)` of CSS
+fn other_target_ranges(line: &str) -> Vec<Range<usize>> {
+    OTHER_TARGET_RE
+        .captures_iter(line)
+        .map(|captures| captures.get(1).unwrap().range())
+        .collect()
+}
+
+/// The files of a graph besides its Markdown pages that can link to assets, sorted: the
+/// whiteboards, the Org pages and journals, and `logseq/custom.css`
+fn other_asset_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files(&root.join(SUBDIR_WHITEBOARDS), "edn", &mut files)?;
+    for subdir in [SUBDIR_PAGES, SUBDIR_JOURNALS] {
+        collect_files(&root.join(subdir), "org", &mut files)?;
+    }
+    let css = root.join("logseq").join("custom.css");
+    if css.is_file() {
+        files.push(css);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The path of an asset relative to the `assets` directory, from a link target
+pub(crate) fn asset_path(target: &str) -> PathBuf {
+    let decoded = percent_decode(target);
+    Path::new(&decoded)
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .skip(1)
+        .collect()
+}
+
+/// The width and height in pixels of a PNG, GIF, JPEG or WebP image, from its contents; None
+/// for other files and truncated images
+///
+/// # Examples
+///
+/// ```
+/// use logseq::assets::image_size;
+/// let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+/// png.extend([0, 0, 3, 32, 0, 0, 2, 88]);
+/// assert_eq!(image_size(&png), Some((800, 600)));
+/// assert_eq!(image_size(b"GIF89a\x40\x01\xc8\x00"), Some((320, 200)));
+/// assert_eq!(image_size(b"not an image"), None);
+/// ```
+pub fn image_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| {
+        Some(u32::from(u16::from_be_bytes(
+            bytes.get(at..at + 2)?.try_into().ok()?,
+        )))
+    };
+    let le16 = |at: usize| {
+        Some(u32::from(u16::from_le_bytes(
+            bytes.get(at..at + 2)?.try_into().ok()?,
+        )))
+    };
+    let be32 = |at: usize| Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+    let le24 = |at: usize| {
+        let b = bytes.get(at..at + 3)?;
+        Some(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16)
+    };
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some((be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        return match bytes.get(12..16)? {
+            b"VP8 " => Some((le16(26)? & 0x3fff, le16(28)? & 0x3fff)),
+            b"VP8L" => {
+                let b: Vec<u32> = bytes.get(21..25)?.iter().map(|&b| u32::from(b)).collect();
+                let width = 1 + (b[0] | (b[1] & 0x3f) << 8);
+                let height = 1 + (b[1] >> 6 | b[2] << 2 | (b[3] & 0x0f) << 10);
+                Some((width, height))
+            }
+            b"VP8X" => Some((1 + le24(24)?, 1 + le24(27)?)),
+            _ => None,
+        };
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        // JPEG segments, up to the start of frame that holds the size
+        let mut at = 2;
+        while *bytes.get(at)? == 0xff {
+            let marker = *bytes.get(at + 1)?;
+            match marker {
+                0xff => at += 1,
+                0x01 | 0xd0..=0xd7 => at += 2,
+                0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                    return Some((be16(at + 7)?, be16(at + 5)?));
+                }
+                _ => at += 2 + usize::try_from(be16(at + 2)?).ok()?,
+            }
+        }
+    }
+    None
+}
+
+/// Links to missing assets, and assets that nothing links to
+#[derive(Debug, Default)]
+pub struct AssetReport {
+    /// Links to files that don't exist in the `assets` directory
+    pub missing: Vec<AssetLink>,
+    /// Full paths of the files of the `assets` directory that no page, whiteboard or
+    /// `custom.css` links to
+    pub orphans: Vec<PathBuf>,
+}
+
+/// Checks the links to assets of all pages and journals against the `assets` directory of the
+/// graph, and the ones of its whiteboards, Org pages and `logseq/custom.css`
+pub fn check_assets(graph: &Graph) -> Result<AssetReport> {
+    let assets_dir = graph.root.join(SUBDIR_ASSETS);
+    let mut report = AssetReport::default();
+    let mut linked = HashSet::new();
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            for (line, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                for (column, target) in asset_targets(text) {
+                    let asset = asset_path(&target);
+                    if !assets_dir.join(&asset).is_file() {
+                        report.missing.push(AssetLink {
+                            path: page.path.clone(),
+                            line,
+                            column: block.source_column(column),
+                            target,
+                            asset: asset.clone(),
+                        });
+                    }
+                    linked.insert(asset);
+                }
+            }
+        }
+    }
+    for path in other_asset_files(&graph.root)? {
+        let text = error::read_to_string(&path)?;
+        for (index, line) in text.lines().enumerate() {
+            for range in other_target_ranges(line) {
+                let target = line[range.clone()].to_string();
+                let asset = asset_path(&target);
+                if !assets_dir.join(&asset).is_file() {
+                    report.missing.push(AssetLink {
+                        path: path.clone(),
+                        line: index + 1,
+                        column: line[..range.start].chars().count() + 1,
+                        target,
+                        asset: asset.clone(),
+                    });
+                }
+                linked.insert(asset);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    collect_files_matching(&assets_dir, &|_| true, &mut files)?;
+    files.sort();
+    report.orphans = files
+        .into_iter()
+        .filter(|file| {
+            file.strip_prefix(&assets_dir)
+                .is_ok_and(|asset| !linked.contains(asset))
+        })
+        .collect();
+    Ok(report)
+}
+
+/// Deletes the assets that nothing links to
+pub fn delete_orphans(report: &AssetReport) -> Result<()> {
+    for orphan in &report.orphans {
+        undo::delete_file(orphan)?;
+    }
+    Ok(())
+}
+
+/// Files of the `assets` directory with the same contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateAssets {
+    /// Full path of the file kept: the first in path order, which for the `image_<timestamp>`
+    /// files Logseq creates when pasting is the oldest
+    pub canonical: PathBuf,
+    /// Full paths of the other files with the same contents, to delete; there is always at
+    /// least one
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// Finds the files of the `assets` directory of the graph that are exact duplicates: files of
+/// the same size are hashed, and the ones with the same hash compared byte for byte. Sorted by
+/// canonical file.
+pub fn find_duplicate_assets(graph: &Graph) -> Result<Vec<DuplicateAssets>> {
+    let mut files = Vec::new();
+    collect_files_matching(&graph.root.join(SUBDIR_ASSETS), &|_| true, &mut files)?;
+    files.sort();
+    let mut by_size: BTreeMap<u64, Vec<PathBuf>> = BTreeMap::new();
+    for file in files {
+        let size = std::fs::metadata(&file)
+            .map_err(|source| Error::io(&file, source))?
+            .len();
+        by_size.entry(size).or_default().push(file);
+    }
+    let mut groups = Vec::new();
+    for same_size in by_size.into_values().filter(|files| files.len() > 1) {
+        // The distinct contents of this size, as (hash, contents, files)
+        let mut distinct: Vec<(u64, Vec<u8>, Vec<PathBuf>)> = Vec::new();
+        for file in same_size {
+            let bytes = std::fs::read(&file).map_err(|source| Error::io(&file, source))?;
+            let hash = fnv1a(&bytes);
+            match distinct
+                .iter_mut()
+                .find(|(other_hash, other, _)| *other_hash == hash && *other == bytes)
+            {
+                Some((_, _, files)) => files.push(file),
+                None => distinct.push((hash, bytes, vec![file])),
+            }
+        }
+        groups.extend(
+            distinct
+                .into_iter()
+                .filter(|(_, _, files)| files.len() > 1)
+                .map(|(_, _, mut files)| DuplicateAssets {
+                    canonical: files.remove(0),
+                    duplicates: files,
+                }),
+        );
+    }
+    groups.sort_by(|a, b| a.canonical.cmp(&b.canonical));
+    Ok(groups)
+}
+
+/// A link target to an asset, keeping the `../assets/` prefix of the target it replaces
+///
+/// The path is percent-encoded where a link target needs it: `%`, spaces and parentheses.
+fn relink(target: &str, asset: &Path) -> String {
+    let prefix = format!("{}/", SUBDIR_ASSETS);
+    let prefix = &target[..target.find(&prefix).map_or(0, |start| start + prefix.len())];
+    let path: Vec<String> = asset
+        .components()
+        .map(|component| {
+            component
+                .as_os_str()
+                .to_string_lossy()
+                .replace('%', "%25")
+                .replace(' ', "%20")
+                .replace('(', "%28")
+                .replace(')', "%29")
+        })
+        .collect();
+    format!("{}{}", prefix, path.join("/"))
+}
+
+/// Plans rewriting the links to duplicate assets of all pages and journals, ignoring code, and
+/// of the whiteboards, Org pages and `logseq/custom.css`, so that they link to the canonical
+/// file of their group. Nothing is written or deleted; see
+/// [dedupe_assets].
+pub fn plan_asset_dedupe(graph: &Graph, groups: &[DuplicateAssets]) -> Result<Vec<FileEdit>> {
+    let assets_dir = graph.root.join(SUBDIR_ASSETS);
+    let relative = |file: &Path| file.strip_prefix(&assets_dir).ok().map(Path::to_path_buf);
+    let mut canonical: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for group in groups {
+        let Some(kept) = relative(&group.canonical) else {
+            continue;
+        };
+        for duplicate in group.duplicates.iter().filter_map(|file| relative(file)) {
+            canonical.insert(duplicate, kept.clone());
+        }
+    }
+
+    let relink_line = |line: &mut String, ranges: Vec<Range<usize>>| {
+        let mut rewritten = String::with_capacity(line.len());
+        let mut last = 0;
+        for range in ranges {
+            let target = &line[range.clone()];
+            let Some(kept) = canonical.get(&asset_path(target)) else {
+                continue;
+            };
+            rewritten.push_str(&line[last..range.start]);
+            rewritten.push_str(&relink(target, kept));
+            last = range.end;
+        }
+        if last > 0 {
+            rewritten.push_str(&line[last..]);
+            *line = rewritten;
+        }
+    };
+
+    let mut edits = Vec::new();
+    let mut edit = |path: &Path, original: String, lines: Vec<String>| {
+        let updated = lines.join("\n");
+        if updated != original {
+            edits.push(FileEdit {
+                path: path.to_path_buf(),
+                original,
+                updated,
+            });
+        }
+    };
+    for page in &graph.pages {
+        let original = error::read_to_string(&page.path)?;
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        for block in page.outline.iter() {
+            for (line, _) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                let ranges = target_ranges(&lines[line - 1]);
+                relink_line(&mut lines[line - 1], ranges);
+            }
+        }
+        edit(&page.path, original, lines);
+    }
+    for path in other_asset_files(&graph.root)? {
+        let original = error::read_to_string(&path)?;
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        for line in &mut lines {
+            let ranges = other_target_ranges(line);
+            relink_line(line, ranges);
+        }
+        edit(&path, original, lines);
+    }
+    Ok(edits)
+}
+
+/// Merges duplicate assets: rewrites the links to them (see [plan_asset_dedupe]), then deletes
+/// the duplicates, keeping the canonical file of each group. With `dry_run`, only plans the
+/// changes. Returns the files that change.
+pub fn dedupe_assets(
+    graph: &Graph,
+    groups: &[DuplicateAssets],
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_asset_dedupe(graph, groups)?;
+    if !dry_run {
+        write_edits(&edits)?;
+        for duplicate in groups.iter().flat_map(|group| &group.duplicates) {
+            undo::delete_file(duplicate)?;
+        }
+    }
+    Ok(edits)
+}
@@ -0,0 +1,258 @@
+//! Process many files in parallel
+
+use crate::diff::Hunk;
+use crate::encoding::{decode, read_text, Decoded, EncodingOptions};
+use crate::graph::page_name_from_path;
+use crate::lint::{Diagnostic, LintResult, RuleSet};
+use crate::{error, undo, Error, Result};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How far a batch of files has got, given to progress callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress<'a> {
+    /// Files done so far, this one included
+    pub done: usize,
+    /// Files in the batch
+    pub total: usize,
+    /// The file just done
+    pub path: &'a Path,
+}
+
+/// A callback told about each file done, see [Progress]. Files are processed in parallel, so it
+/// is called from several threads, in no particular order of the files.
+pub type ProgressCallback<'a> = &'a (dyn Fn(Progress<'_>) + Sync);
+
+/// A progress callback that does nothing
+pub(crate) fn no_progress(_: Progress<'_>) {}
+
+/// Counts the files done in a batch and tells a progress callback about each one
+pub(crate) struct ProgressCounter<'a> {
+    done: AtomicUsize,
+    total: usize,
+    callback: ProgressCallback<'a>,
+}
+
+impl<'a> ProgressCounter<'a> {
+    pub(crate) fn new(total: usize, callback: ProgressCallback<'a>) -> Self {
+        ProgressCounter {
+            done: AtomicUsize::new(0),
+            total,
+            callback,
+        }
+    }
+
+    /// Tells the callback that a file is done
+    pub(crate) fn tick(&self, path: &Path) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        (self.callback)(Progress {
+            done,
+            total: self.total,
+            path,
+        });
+    }
+}
+
+/// The outcome of fixing a single file
+#[derive(Debug)]
+pub struct FileReport {
+    /// The file that was processed
+    pub path: PathBuf,
+    /// True if the file was rewritten
+    pub changed: bool,
+    /// Problems found in the file before fixing it
+    pub diagnostics: Vec<Diagnostic>,
+    /// Problems left after fixing it
+    pub remaining: Vec<Diagnostic>,
+    /// Problems found before fixing it where their rule is suppressed
+    pub suppressed: Vec<Diagnostic>,
+    /// A unified diff of the fixes, empty if nothing changed
+    pub diff: String,
+    /// The error that prevented the file from being processed, if any
+    pub error: Option<Error>,
+}
+
+/// Fix a single file in place with the given rules, returning what was found
+pub fn fix_file(path: &Path, rules: &RuleSet) -> Result<FileReport> {
+    fix_file_in_place(path, rules, false)
+}
+
+/// Fix a single file in place with the given rules.
+/// The fixed text is written to a temporary file that then replaces the original, so the page
+/// is never left half-written; with `backup`, the original is kept as a `.bak` file next to it.
+pub fn fix_file_in_place(path: &Path, rules: &RuleSet, backup: bool) -> Result<FileReport> {
+    process_file(path, rules, false, backup, &EncodingOptions::default())
+}
+
+/// Fix a single file with the given rules; with `dry_run`, the file is left untouched
+/// and the report only tells what would change
+pub fn fix_file_with(path: &Path, rules: &RuleSet, dry_run: bool) -> Result<FileReport> {
+    process_file(path, rules, dry_run, false, &EncodingOptions::default())
+}
+
+/// Fix a single file with the given rules like [fix_file_with], with options for the files
+/// that aren't plain UTF-8: see [EncodingOptions]. Their encoding is reported as a diagnostic,
+/// fixed when the file is rewritten to plain UTF-8.
+pub fn fix_file_with_encoding(
+    path: &Path,
+    rules: &RuleSet,
+    dry_run: bool,
+    encoding: &EncodingOptions,
+) -> Result<FileReport> {
+    process_file(path, rules, dry_run, false, encoding)
+}
+
+/// Fix a single file in place like [fix_file_with_encoding], keeping only the changes that
+/// `accept` approves, see [RuleSet::fix_selected]. Returns true if the file was rewritten.
+pub fn fix_file_selected(
+    path: &Path,
+    rules: &RuleSet,
+    options: &EncodingOptions,
+    accept: impl FnMut(&'static str, &Hunk) -> bool,
+) -> Result<bool> {
+    let Decoded { text, encoding } = read_text(path, options)?;
+    let fixed = rules.fix_selected(&text, accept);
+    let changed = matches!(fixed, Cow::Owned(_)) || encoding.is_fixed_by(options);
+    if changed {
+        let contents = encoding.encode(&fixed, options);
+        error::write_atomically(path, &contents, false)?;
+        undo::record(
+            path,
+            &encoding.encode(&text, &EncodingOptions::default()),
+            &contents,
+        )?;
+    }
+    Ok(changed)
+}
+
+fn process_file(
+    path: &Path,
+    rules: &RuleSet,
+    dry_run: bool,
+    backup: bool,
+    options: &EncodingOptions,
+) -> Result<FileReport> {
+    let Decoded {
+        text: original,
+        encoding,
+    } = read_text(path, options)?;
+    let name = page_name_from_path(path);
+    let LintResult {
+        mut diagnostics,
+        suppressed,
+    } = rules.lint_page(&original, Some(&name));
+    let outcome = rules.fix_page_outcome(&path.to_string_lossy(), &original, Some(&name));
+    let changed = outcome.changed() || encoding.is_fixed_by(options);
+    let mut remaining = if outcome.changed() {
+        rules.lint_page(&outcome.fixed, Some(&name)).diagnostics
+    } else {
+        diagnostics.clone()
+    };
+    if let Some(diagnostic) = encoding.diagnostic() {
+        if !encoding.is_fixed_by(options) {
+            remaining.insert(0, diagnostic.clone());
+        }
+        diagnostics.insert(0, diagnostic);
+    }
+    if changed && !dry_run {
+        let contents = encoding.encode(&outcome.fixed, options);
+        error::write_atomically(path, &contents, backup)?;
+        let previous = encoding.encode(&original, &EncodingOptions::default());
+        undo::record(path, &previous, &contents)?;
+    }
+    Ok(FileReport {
+        path: path.to_path_buf(),
+        changed,
+        diagnostics,
+        remaining,
+        suppressed,
+        diff: outcome.diff,
+        error: None,
+    })
+}
+
+/// Lint a single file without changing it. A byte order mark is ignored and a file that isn't
+/// valid UTF-8 is read as Latin-1, each with a diagnostic about its encoding first.
+pub fn lint_file(path: &Path, rules: &RuleSet) -> Result<LintResult> {
+    let bytes = fs::read(path).map_err(|source| Error::io(path, source))?;
+    let Decoded { text, encoding } = decode(&bytes);
+    let mut result = rules.lint_page(&text, Some(&page_name_from_path(path)));
+    if let Some(diagnostic) = encoding.diagnostic() {
+        result.diagnostics.insert(0, diagnostic);
+    }
+    Ok(result)
+}
+
+/// Fix many files in place, in parallel.
+/// A file that can't be read or written doesn't stop the others; its report carries the error.
+/// Reports are returned in the same order as the paths.
+pub fn fix_files(paths: &[PathBuf], rules: &RuleSet) -> Vec<FileReport> {
+    fix_files_with(paths, rules, false)
+}
+
+/// Fix many files in parallel like [fix_files]; with `dry_run`, no file is written
+pub fn fix_files_with(paths: &[PathBuf], rules: &RuleSet, dry_run: bool) -> Vec<FileReport> {
+    fix_files_with_encoding(paths, rules, dry_run, &EncodingOptions::default())
+}
+
+/// Fix many files in parallel like [fix_files_with], with options for the files that aren't
+/// plain UTF-8, see [fix_file_with_encoding]
+pub fn fix_files_with_encoding(
+    paths: &[PathBuf],
+    rules: &RuleSet,
+    dry_run: bool,
+    encoding: &EncodingOptions,
+) -> Vec<FileReport> {
+    fix_files_with_progress(paths, rules, dry_run, encoding, &no_progress)
+}
+
+/// Fix many files like [fix_files_with_encoding], telling `progress` about each file done
+///
+/// # Examples
+///
+/// ```
+/// use logseq::batch::fix_files_with_progress;
+/// use logseq::encoding::EncodingOptions;
+/// use logseq::lint::RuleSet;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// let temp = assert_fs::TempDir::new().unwrap();
+/// let paths: Vec<_> = ["a.md", "b.md"].iter().map(|name| temp.path().join(name)).collect();
+/// for path in &paths {
+///     std::fs::write(path, "- a  b\n").unwrap();
+/// }
+/// let done = AtomicUsize::new(0);
+/// let progress = |progress: logseq::batch::Progress| {
+///     assert_eq!(progress.total, 2);
+///     done.fetch_max(progress.done, Ordering::Relaxed);
+/// };
+/// fix_files_with_progress(&paths, &RuleSet::builtin(), true, &EncodingOptions::default(), &progress);
+/// assert_eq!(done.into_inner(), 2);
+/// ```
+pub fn fix_files_with_progress(
+    paths: &[PathBuf],
+    rules: &RuleSet,
+    dry_run: bool,
+    encoding: &EncodingOptions,
+    progress: ProgressCallback<'_>,
+) -> Vec<FileReport> {
+    let counter = ProgressCounter::new(paths.len(), progress);
+    paths
+        .par_iter()
+        .map(|path| {
+            let report = fix_file_with_encoding(path, rules, dry_run, encoding);
+            counter.tick(path);
+            report.unwrap_or_else(|error| FileReport {
+                path: path.clone(),
+                changed: false,
+                diagnostics: Vec::new(),
+                remaining: Vec::new(),
+                suppressed: Vec::new(),
+                diff: String::new(),
+                error: Some(error),
+            })
+        })
+        .collect()
+}
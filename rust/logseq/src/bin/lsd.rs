@@ -0,0 +1,2694 @@
+//! Logseq Doctor command line: heal your Markdown files without Python
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use clap::{Args, Parser, Subcommand};
+use logseq::assets::{check_assets, dedupe_assets, delete_orphans, find_duplicate_assets};
+use logseq::batch::{fix_file_selected, fix_files_with_progress, lint_file, Progress};
+use logseq::cards::{extract_flashcards, CardFormat};
+use logseq::clippings::clean_clipping_files;
+use logseq::config::{check_config, ConfigProblem, GraphConfig};
+use logseq::date_links::{link_dates, DateLinkOptions};
+use logseq::dates::{self, parse_utc_offset, Locale};
+use logseq::dedupe::find_duplicate_blocks;
+use logseq::diff::{unified_diff, Hunk};
+use logseq::doctor::{graph_health, HealthFormat, HealthOptions, TOP_OFFENDERS};
+use logseq::embeds::{unembed_missing, EmbedLimits, EmbedProblem};
+use logseq::encoding::EncodingOptions;
+use logseq::export::database::{dump_graph, DumpFormat};
+use logseq::export::export_graph;
+use logseq::export::links::{link_graph, LinkFilter, LinkFormat};
+use logseq::filenames::plan_file_name_repair;
+use logseq::git::keep_changed;
+use logseq::graph::{
+    find_graph_root, journal_title_format, markdown_files, AliasConflictKind, Graph, UnreadableFile,
+};
+use logseq::highlights::{check_highlights, HighlightProblem};
+use logseq::ids::repair_duplicate_ids;
+use logseq::inline::{inline_refs, InlineTarget};
+use logseq::interop::db_version::{convert_graph, DbProblem};
+use logseq::interop::obsidian::import_vault;
+use logseq::interop::roam::import_export;
+use logseq::journal::{
+    apply_journal_cleanup, check_journals, create_journals, journal_gaps, plan_journal_cleanup,
+    rename_journals, JournalProblem,
+};
+use logseq::lint::{LintConfig, RuleSet, RulesFormat, Severity};
+use logseq::lsp::Server;
+use logseq::markdown::explode_files;
+use logseq::merge::merge_pages;
+use logseq::namespaces::{check_namespaces, create_parent_stubs, reencode_files, NamespaceProblem};
+use logseq::normalize::plan_normalization;
+use logseq::org::migrate_graph;
+use logseq::property_rename::{
+    normalize_property_keys, rename_property, rename_property_value, KeyStyle, PropertyRename,
+};
+use logseq::rename::rename_page;
+use logseq::report::baseline::{Baseline, BASELINE_FILE_NAME};
+use logseq::report::format::Format;
+use logseq::report::{Report, Thresholds};
+use logseq::search::{search_graph, PropertyFilter, SearchFormat, SearchQuery};
+use logseq::sentences::{split_block_files, BlockLimits};
+use logseq::split::{split_page, SplitLeave, SPLIT_LEVEL};
+#[cfg(feature = "index-sqlite")]
+use logseq::sqlite::QueryFormat;
+use logseq::stats::{graph_stats, StatsFormat, TOP_PAGES};
+use logseq::stubs::{delete_stub_pages, find_stub_pages};
+use logseq::suggest::apply_suggestions;
+use logseq::sync_conflicts::{find_sync_conflicts, resolve_sync_conflicts};
+use logseq::tasks::archive::{plan_archive, ArchiveDestination, ARCHIVE_AFTER_DAYS};
+use logseq::tasks::done::backfill_done;
+use logseq::tasks::report::{task_report, TaskFormat, STALE_DAYS};
+use logseq::templates::{
+    check_templates, list_templates, render_date_variables, template_body, TemplateProblem,
+    INCLUDING_PARENT_KEY,
+};
+use logseq::timestamps::{backfill_timestamps, TimestampSource};
+use logseq::toc::{refresh_tocs, TocOptions, MIN_HEADINGS};
+use logseq::undo::{start_session, undo};
+use logseq::watch::{Watcher, DEBOUNCE};
+use logseq::whiteboards::check_whiteboards;
+use logseq::{remove_consecutive_spaces_in_file, remove_consecutive_spaces_stream};
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Logseq Doctor: heal your Markdown files
+#[derive(Parser)]
+#[command(
+    name = "lsd",
+    version,
+    about,
+    after_help = "Exit codes: 0 when there is nothing to report, 1 when problems were found \
+                  (or files would be fixed), 2 on errors, 3 when files were fixed."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// Which rules run, overriding the `.lsd.toml` file of the graph
+#[derive(Args)]
+struct RuleOptions {
+    /// Config file to use instead of the `.lsd.toml` found in the first path or its parents
+    #[arg(long, conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+    /// Ignore any `.lsd.toml` file
+    #[arg(long)]
+    no_config: bool,
+    /// Only run these rules (comma-separated ids), whatever the config file says
+    #[arg(long, value_delimiter = ',')]
+    enable: Option<Vec<String>>,
+    /// Don't run these rules (comma-separated ids)
+    #[arg(long, value_delimiter = ',')]
+    disable: Vec<String>,
+    /// Only process files that are modified or staged in the git repository of the first path
+    #[arg(long)]
+    changed: bool,
+    /// Only process the files this glob matches, like the `include` globs of the config file
+    #[arg(long)]
+    include: Vec<String>,
+    /// Don't process the files this glob matches, like the `ignore` globs of the config file
+    #[arg(long)]
+    exclude: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fix Markdown files in place with all the auto-fixable rules
+    Fix {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Print a diff of the fixes instead of writing them
+        #[arg(long)]
+        diff: bool,
+        /// Only list the files that would be fixed, without writing them
+        #[arg(long, conflicts_with_all = ["diff", "watch"])]
+        check: bool,
+        /// Keep running, and fix files again whenever they change
+        #[arg(long, conflicts_with = "diff")]
+        watch: bool,
+        /// Show each change and ask whether to make it, like `git add -p`
+        #[arg(long, short, conflicts_with_all = ["diff", "check", "watch"])]
+        interactive: bool,
+        /// Remove the UTF-8 byte order mark of the files that start with one
+        #[arg(long)]
+        strip_bom: bool,
+        /// Rewrite the files that aren't valid UTF-8 from Latin-1 to UTF-8, instead of skipping
+        /// them
+        #[arg(long)]
+        transcode: bool,
+        #[command(flatten)]
+        rules: RuleOptions,
+        /// Output format: text, json, jsonl or sarif
+        #[arg(long, default_value = "text")]
+        format: Format,
+    },
+    /// Roll back the changes of the last runs of lsd, from the undo log of the graph
+    Undo {
+        /// Root directory of the Logseq graph, or the directory of the files fixed outside a
+        /// graph
+        path: PathBuf,
+        /// How many runs to roll back, the most recent first
+        #[arg(long, default_value_t = 1)]
+        last: usize,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Split blocks with too many sentences or characters, like pasted paragraphs: the first
+    /// sentences stay in the block, with its properties and the tags that end it, and the next
+    /// ones become child blocks
+    SplitBlocks {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// The most sentences a block keeps
+        #[arg(long, default_value_t = logseq::sentences::MAX_SENTENCES)]
+        max_sentences: usize,
+        /// The most characters a block keeps, unless it is a single sentence
+        #[arg(long, default_value_t = logseq::sentences::MAX_CHARS)]
+        max_chars: usize,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Explode blocks that are pasted documents, with headings or several paragraphs, into
+    /// child blocks nested by heading level and list indentation
+    ExplodeBlocks {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Clean up pages made by web clippers: HTML tags become Markdown or are removed, `<br>` and
+    /// `<div>` wrappers start new blocks, HTML entities are decoded, and flat pages are folded
+    /// into an outline with their headings promoted
+    CleanClippings {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Turn the bare URLs of blocks into `[Title](url)` links, with the titles of the web pages
+    #[cfg(feature = "network")]
+    TitleUrls {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Show the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Only use the titles fetched by earlier runs
+        #[arg(long)]
+        offline: bool,
+        /// Milliseconds between two requests
+        #[arg(long, default_value_t = logseq::titles::FETCH_DELAY.as_millis() as u64)]
+        delay_ms: u64,
+    },
+    /// Report problems in Markdown files without changing them
+    Lint {
+        /// Markdown files or directories (a graph root, or any directory with Markdown files)
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        #[command(flatten)]
+        rules: RuleOptions,
+        /// Output format: text, json, jsonl or sarif
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// The least severity of the problems that fail: error, warning or info
+        #[arg(long, default_value = "warning")]
+        fail_on: Severity,
+        /// Fail on more warnings than this, even when only errors fail; below it, warnings
+        /// don't fail
+        #[arg(long)]
+        max_warnings: Option<usize>,
+        /// Baseline file of the known findings, left out of the report [default: .lsd-baseline.json
+        /// next to the config file]
+        #[arg(long)]
+        baseline: Option<PathBuf>,
+        /// Record the findings in the baseline file instead of reporting them
+        #[arg(long)]
+        update_baseline: bool,
+    },
+    /// List the built-in rules, with what they find and whether they fix it
+    Rules {
+        /// Output format: text, or json with the examples of each rule
+        #[arg(long, default_value = "text")]
+        format: RulesFormat,
+    },
+    /// Explain a rule: what it finds, and what it finds and fixes in its examples
+    Explain {
+        /// Id of the rule, as listed by `lsd rules`
+        rule: String,
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: RulesFormat,
+    },
+    /// Remove consecutive spaces in the bullets of Markdown files, one line at a time, so memory
+    /// stays flat however large the files are
+    RemoveSpaces {
+        /// Markdown files or directories, or `-` to read stdin and write stdout
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Remove them in code blocks too
+        #[arg(long)]
+        in_code_blocks: bool,
+    },
+    /// Run a language server on stdin and stdout, for editors: lint diagnostics while typing,
+    /// fixes as code actions, and go to definition for `[[page]]` links
+    Lsp {
+        /// Directory where the config file is found, and the graph where links are looked up
+        /// until the editor opens a workspace in a graph
+        #[arg(default_value = ".")]
+        graph: PathBuf,
+        #[command(flatten)]
+        rules: RuleOptions,
+    },
+    /// Report links to missing pages and references to missing blocks, in pages and in the portals
+    /// of whiteboards
+    CheckLinks {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: text, json, jsonl or sarif
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// Replace links to missing pages with the page they were probably meant for
+        #[arg(long)]
+        apply_suggestions: bool,
+        /// With --apply-suggestions, show the changes without writing them
+        #[arg(long, requires = "apply_suggestions")]
+        dry_run: bool,
+    },
+    /// Report the dead `http(s)` links of pages, checked with HEAD requests; results are cached
+    /// so that running again only checks new links
+    #[cfg(feature = "network")]
+    CheckWebLinks {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: text, json, jsonl or sarif
+        #[arg(long, default_value = "text")]
+        format: Format,
+        /// How many links are checked at the same time
+        #[arg(long, default_value_t = logseq::weblinks::CONCURRENCY)]
+        concurrency: usize,
+        /// How many times a link is checked again when the server or the network fails
+        #[arg(long, default_value_t = logseq::weblinks::RETRIES)]
+        retries: u32,
+        /// Hours the result of a link is cached; 0 checks every link again
+        #[arg(long, default_value_t = logseq::weblinks::CACHE_TTL.as_secs() / 3600)]
+        ttl_hours: u64,
+    },
+    /// Report embeds of missing pages, of tiny pages, and of large pages embedded many times
+    CheckEmbeds {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Embedded pages with fewer words are tiny
+        #[arg(long, default_value_t = logseq::embeds::TINY_WORDS)]
+        tiny_words: usize,
+        /// Embedded pages with at least as many words are large
+        #[arg(long, default_value_t = logseq::embeds::LARGE_WORDS)]
+        large_words: usize,
+        /// Large pages embedded at least as many times are reported
+        #[arg(long, default_value_t = logseq::embeds::MAX_EMBEDS)]
+        max_embeds: usize,
+        /// Turn the embeds of missing pages into links
+        #[arg(long)]
+        fix_missing: bool,
+        /// With --fix-missing, show the changes without writing them
+        #[arg(long, requires = "fix_missing")]
+        dry_run: bool,
+    },
+    /// Report links to missing assets and assets that nothing links to
+    CheckAssets {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Delete the assets that nothing links to
+        #[arg(long)]
+        delete_orphans: bool,
+    },
+    /// Merge the files of the `assets` directory with the same contents: keep one, link to it
+    /// instead of the others, and delete them
+    DedupeAssets {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Show the duplicates and the changes without writing or deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report PDF highlight pages (`hls__`) whose PDF, page numbers or area screenshots are
+    /// broken, usually after the PDF was moved or renamed
+    CheckHighlights {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+    },
+    /// Report `:favorites` of `config.edn` that aren't pages and `:default-queries` that are
+    /// invalid or refer to missing pages or properties, often left behind by renames
+    CheckConfig {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+    },
+    /// Report aliases that are the name or an alias of another page, which merges their backlinks
+    CheckAliases {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+    },
+    /// Report page files that Logseq sees as the same page, and the file to merge them into
+    Duplicates {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+    },
+    /// Report block ids shared by several blocks, usually because the blocks were copy-pasted
+    DuplicateIds {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Give the copies new ids; the block in the oldest file keeps its id
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report the copies of pages left by sync tools, like Syncthing's `.sync-conflict-*` files,
+    /// with how they differ from their page
+    SyncConflicts {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Append the blocks of the copies that their pages don't have, then delete the copies
+        #[arg(long)]
+        merge: bool,
+        /// With --merge, print the changes without writing them
+        #[arg(long, requires = "merge")]
+        dry_run: bool,
+    },
+    /// Report blocks duplicated across the graph, word for word or nearly, usually by sync
+    /// conflicts or copy-pasting
+    DuplicateBlocks {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Ignore blocks with fewer words
+        #[arg(long, default_value_t = 5)]
+        min_words: usize,
+    },
+    /// List the pages that nothing links to, with the date they were last modified
+    Orphans {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+    },
+    /// List the pages and journals with nothing in them, only empty bullets or only the
+    /// properties Logseq writes by itself, leaving out the ones something refers to
+    Stubs {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Delete the stub pages
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Search the content of blocks, and print each block found with its page, its parents and
+    /// its children, exiting with 1 if none matches
+    Grep {
+        /// Regular expression searched in the content of blocks, across their lines
+        pattern: String,
+        /// Root directory of the Logseq graph
+        #[arg(default_value = ".")]
+        graph: PathBuf,
+        /// Search the pattern as text, not as a regular expression
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+        /// Ignore case
+        #[arg(short, long)]
+        ignore_case: bool,
+        /// Only search blocks with this property, as key or key=value, set on the block or its
+        /// page; can be repeated
+        #[arg(long = "property", value_name = "KEY[=VALUE]")]
+        properties: Vec<PropertyFilter>,
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: SearchFormat,
+    },
+    /// Count the pages, journals, blocks, words, tasks and assets of a graph, and list the
+    /// pages referenced the most
+    Stats {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: table or json
+        #[arg(long, default_value = "table")]
+        format: StatsFormat,
+        /// How many of the most referenced pages to list
+        #[arg(long, default_value_t = TOP_PAGES)]
+        top: usize,
+    },
+    /// Run all the enabled checks on a graph and summarize its health: a score by category
+    /// (links, tasks, properties, assets, hygiene), with counts and the files with the most
+    /// findings
+    Doctor {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        #[command(flatten)]
+        rules: RuleOptions,
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: HealthFormat,
+        /// How many of the files with the most findings to list for each category
+        #[arg(long, default_value_t = TOP_OFFENDERS)]
+        top: usize,
+        /// Days without a journal entry or a logbook clock after which a task is stale
+        #[arg(long, default_value_t = STALE_DAYS)]
+        stale_days: i64,
+        /// Date to compare deadlines with, as YYYY-MM-DD, instead of the current date
+        #[arg(long)]
+        today: Option<NaiveDate>,
+        /// Only fail when the score of the graph is below this, instead of on any finding
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_score: Option<u8>,
+    },
+    /// Report journal files whose name doesn't match the graph's date format or their contents
+    CheckJournals {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Rename the files that have a known correct name
+        #[arg(long)]
+        rename: bool,
+    },
+    /// List the days without a journal between the first and the last journal of a graph
+    JournalGaps {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Create the missing journals, empty unless `--template` is given
+        #[arg(long)]
+        create: bool,
+        /// Only the gaps from this date on, e.g. 2024-01-31
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Only the gaps until this date, included
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Create the journals from the default journal template of `config.edn`
+        #[arg(long, requires = "create")]
+        template: bool,
+    },
+    /// Report blocks of journals that are exact copies of a sibling, left by interrupted syncs
+    JournalDuplicates {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Also sort the top-level entries that start with a time, like `**09:15**`
+        #[arg(long)]
+        sort: bool,
+        /// Remove the copies (and sort the entries) instead of reporting them
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report namespaced pages whose parent page is missing, and file names that encode `/`
+    /// differently from the rest of the graph
+    Namespaces {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Create a stub page for each missing parent
+        #[arg(long)]
+        create_parents: bool,
+        /// Rename the files to the graph's encoding of `/`
+        #[arg(long)]
+        reencode: bool,
+    },
+    /// Report file names, links, tags and aliases that aren't in Unicode NFC, like the
+    /// decomposed file names macOS writes
+    NormalizeNames {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Rename the files and rewrite the links to NFC
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report page file names that aren't encoded like Logseq encodes them, like `what?.md`
+    /// instead of `what%3F.md`, which makes Logseq show another name than links use
+    FileNames {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Rename the files and rewrite the links to their pages
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Report templates with the same name, dynamic variables that Logseq won't replace, and
+    /// misused `template-including-parent::` properties
+    Templates {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// List the templates of the graph
+        #[arg(long)]
+        list: bool,
+    },
+    /// Rename a page and rewrite every link, tag, embed and alias that refers to it
+    RenamePage {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Current name of the page
+        old: String,
+        /// New name of the page
+        new: String,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename a property key on every page and block, leaving alone those that already have the
+    /// new key
+    RenameProperty {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Current key of the property
+        old: String,
+        /// New key of the property
+        new: String,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename a value of a property on every page and block, including the references to it in
+    /// lists like `tags::`
+    RenamePropertyValue {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Key of the property
+        key: String,
+        /// Current value
+        old: String,
+        /// New value
+        new: String,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename the property keys of a graph to one style, like `Book_Author::` to `book-author::`,
+    /// and print the keys renamed
+    NormalizePropertyKeys {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Style of the keys: kebab or snake
+        #[arg(long, default_value = "kebab")]
+        style: KeyStyle,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Merge a page into another one: its blocks are appended to the target, its name becomes an
+    /// alias of the target, links to it are rewritten, and its file is deleted
+    Merge {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Name of the page to merge, deleted afterwards
+        source: String,
+        /// Name of the page that receives the blocks
+        target: String,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Split a page into sub-pages: each top-level heading moves with its children to a page
+    /// named `Page/Heading`, and is replaced by a link to it
+    Split {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Name of the page to split
+        page: String,
+        /// The deepest heading level that starts a section
+        #[arg(long, default_value_t = SPLIT_LEVEL)]
+        level: usize,
+        /// Replace the sections with embeds of the new pages instead of links
+        #[arg(long)]
+        embed: bool,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Replace the `((uuid))` references to a block, or to the blocks of a page, with the text of
+    /// the block, before deleting it
+    InlineRefs {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// UUID of the block whose references to inline
+        #[arg(long, conflicts_with = "page", required_unless_present = "page")]
+        block: Option<String>,
+        /// Name of the page whose blocks' references to inline
+        #[arg(long)]
+        page: Option<String>,
+        /// Follow the inlined text with a link to the page of the block
+        #[arg(long)]
+        keep_link: bool,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add or refresh a table of contents at the top of the pages with an `lsd-toc:: true` (or
+    /// `numbered`) page property: a block with a `((ref))` to each heading, nested like them
+    Toc {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Also the pages without the property that have enough headings, unless their
+        /// property is `lsd-toc:: false`
+        #[arg(long)]
+        all: bool,
+        /// How many headings a page needs for --all
+        #[arg(long, default_value_t = MIN_HEADINGS, requires = "all")]
+        min_headings: usize,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Turn the dates written in plain text in journals, like `next Tuesday`, `2024/03/05` or
+    /// `5 March`, into links to their journals; relative dates are from the date of the journal
+    LinkDates {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Also the pages that aren't journals, where relative dates are from the current date
+        #[arg(long)]
+        all_pages: bool,
+        /// Language of the dates, besides English: en, de, fr, es, pt, it or nl
+        #[arg(long, default_value = "en", value_parser = parse_locale)]
+        language: Box<Locale>,
+        /// Date relative dates are from outside journals, as YYYY-MM-DD, instead of the current
+        /// date
+        #[arg(long, requires = "all_pages")]
+        today: Option<NaiveDate>,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add the missing `created-at::` and `updated-at::` page properties, from the times of the
+    /// page files
+    BackfillTimestamps {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Take the times of the first and last git commits of the files instead
+        #[arg(long)]
+        git: bool,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert the pages of a graph to the conventions of Logseq's database version, and list
+    /// what must be changed by hand
+    DbVersion {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Convert org-mode pages and journals of a graph into Markdown
+    MigrateOrg {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Delete the org files after converting them
+        #[arg(long)]
+        delete: bool,
+    },
+    /// Import notes from another app into a Logseq graph
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Index a graph into SQLite and run a SQL query on it, see the tables of
+    /// `logseq::sqlite::SCHEMA`
+    #[cfg(feature = "index-sqlite")]
+    Query {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// The SQL to run, e.g. "SELECT original_name FROM pages WHERE file IS NULL"
+        sql: String,
+        /// Write the index to this SQLite file, replacing it, instead of keeping it in memory
+        #[arg(long)]
+        database: Option<PathBuf>,
+        /// Output format: tsv or json
+        #[arg(long, default_value = "tsv")]
+        format: QueryFormat,
+    },
+    /// Export the pages of a graph to another format
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+    /// Work with the flashcards of a graph: blocks tagged #card
+    Cards {
+        #[command(subcommand)]
+        action: CardsAction,
+    },
+    /// Work with the tasks of a graph: blocks starting with TODO, DOING, LATER...
+    Tasks {
+        #[command(subcommand)]
+        action: TasksAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum CardsAction {
+    /// Print the flashcards, with their children as the answer
+    Export {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: json, csv or anki-tsv (for Anki's "Import File")
+        #[arg(long, default_value = "json")]
+        format: CardFormat,
+    },
+}
+
+#[derive(Subcommand)]
+enum TasksAction {
+    /// List the open tasks that are overdue or stale, exiting with 1 if there are any
+    Report {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: table or json
+        #[arg(long, default_value = "table")]
+        format: TaskFormat,
+        /// Days without a journal entry or a logbook clock after which a task is stale
+        #[arg(long, default_value_t = STALE_DAYS)]
+        stale_days: i64,
+        /// Date to compare deadlines with, as YYYY-MM-DD, instead of the current date
+        #[arg(long)]
+        today: Option<NaiveDate>,
+        /// Offset from UTC of the time zone of the current date, like +02:00, instead of the
+        /// local one
+        #[arg(long, value_parser = parse_utc_offset)]
+        utc_offset: Option<FixedOffset>,
+    },
+    /// Move the DONE tasks of pages to the journal of the day they were done
+    Archive {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Days since a task was done (its last logbook clock or its done:: timestamp, or when
+        /// its file changed)
+        #[arg(long, default_value_t = ARCHIVE_AFTER_DAYS)]
+        older_than: i64,
+        /// Move the tasks to this page instead of journals, e.g. Archive
+        #[arg(long)]
+        page: Option<String>,
+        /// Date to compare with, as YYYY-MM-DD, instead of the current date
+        #[arg(long)]
+        today: Option<NaiveDate>,
+        /// Print the tasks to move without moving them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Record when the DONE tasks without a CLOCK entry (or a done:: property, without time
+    /// tracking) were done
+    BackfillDone {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Take the times the first lines of the tasks were last committed to git instead of
+        /// when their files were modified
+        #[arg(long)]
+        git: bool,
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportFormat {
+    /// Export pages and journals as standard Markdown, for static site generators
+    Markdown {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Directory to write the Markdown files to
+        #[arg(long)]
+        output: PathBuf,
+        /// Inline embedded pages and blocks instead of linking to them
+        #[arg(long)]
+        inline_embeds: bool,
+    },
+    /// Dump the pages, blocks, properties and references of a graph, for scripts to query
+    Graph {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: json, or edn for Datascript-like entities
+        #[arg(long, default_value = "json")]
+        format: DumpFormat,
+    },
+    /// Print the links between pages as a graph to draw, like with `dot -Tsvg`
+    Dot {
+        /// Root directory of the Logseq graph
+        graph: PathBuf,
+        /// Output format: dot for Graphviz, or graphml
+        #[arg(long, default_value = "dot")]
+        format: LinkFormat,
+        /// Only the pages of this namespace, and the namespace page
+        #[arg(long)]
+        namespace: Option<String>,
+        /// Only the pages with this page in their tags:: property
+        #[arg(long)]
+        tag: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// Import an Obsidian vault: notes become pages and journals, attachments go to `assets`
+    Obsidian {
+        /// Root directory of the vault
+        vault: PathBuf,
+        /// Root directory of the Logseq graph to import into
+        #[arg(long, default_value = ".")]
+        graph: PathBuf,
+    },
+    /// Import a Roam Research JSON export: pages, daily pages as journals, and block references
+    Roam {
+        /// The JSON file of the export
+        export: PathBuf,
+        /// Root directory of the Logseq graph to import into
+        #[arg(long, default_value = ".")]
+        graph: PathBuf,
+    },
+}
+
+/// What a command found, which gives the exit code along with errors (exit code 2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Nothing to report
+    Clean,
+    /// Problems were found, or files would be fixed, and left as they are
+    Violations,
+    /// Files were fixed
+    Fixed,
+}
+
+impl From<bool> for Status {
+    fn from(clean: bool) -> Self {
+        if clean {
+            Status::Clean
+        } else {
+            Status::Violations
+        }
+    }
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Clean => ExitCode::SUCCESS,
+            Status::Violations => ExitCode::FAILURE,
+            Status::Fixed => ExitCode::from(3),
+        }
+    }
+}
+
+fn expand(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        files.extend(markdown_files(path)?);
+    }
+    Ok(files)
+}
+
+/// The rules that look at other pages of the graph, which is scanned only for them
+const GRAPH_RULES: &[&str] = &[
+    "done-metadata",
+    "link-style",
+    "queries",
+    "image-props",
+    "citations",
+    "spelling",
+];
+
+/// The config and the rules to run, from the config file and the options
+fn config_and_rules(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+) -> anyhow::Result<(LintConfig, RuleSet)> {
+    let first = paths
+        .first()
+        .map(PathBuf::as_path)
+        .unwrap_or(Path::new("."));
+    let mut config = match &options.config {
+        Some(config_path) => LintConfig::read(config_path)?,
+        None if options.no_config => LintConfig::new(Path::new(".")),
+        None => LintConfig::discover(first)?.unwrap_or_else(|| LintConfig::new(Path::new("."))),
+    };
+    if let Some(enable) = &options.enable {
+        // Rules enabled on the command line run even if the config file disables them
+        config.enable = Some(enable.clone());
+        config.disable.clear();
+    }
+    config.disable.extend(options.disable.iter().cloned());
+    config.add_globs(&options.include, &options.exclude)?;
+    let mut rules = config.rule_set()?;
+    if let Some(root) = find_graph_root(first) {
+        // A config.edn that can't be read is reported by check-config
+        if let Ok(graph_config) = GraphConfig::read(&root) {
+            rules.use_graph_config(&graph_config);
+        }
+        // The files that can't be read are reported when they are fixed or linted
+        if GRAPH_RULES.iter().any(|id| rules.get(id).is_some()) {
+            rules.use_graph(&scan_graph_quietly(&root)?);
+        }
+    }
+    Ok((config, rules))
+}
+
+/// The files to check and the rules to run on them, from the config file and the options
+fn files_and_rules(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+) -> anyhow::Result<(Vec<PathBuf>, RuleSet)> {
+    let (config, rules) = config_and_rules(paths, options)?;
+    Ok((selected_files(paths, options, &config)?, rules))
+}
+
+/// The Markdown files of the paths that aren't ignored, and are changed with `--changed`
+fn selected_files(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+    config: &LintConfig,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let files = config.remove_ignored(expand(paths)?);
+    if !options.changed {
+        return Ok(files);
+    }
+    let first = paths.first().map_or(Path::new("."), PathBuf::as_path);
+    Ok(keep_changed(files, first)?)
+}
+
+fn encoding_options(strip_bom: bool, transcode: bool) -> EncodingOptions {
+    EncodingOptions {
+        strip_bom,
+        transcode,
+    }
+}
+
+fn fix(
+    paths: &[PathBuf],
+    diff: bool,
+    check: bool,
+    options: &RuleOptions,
+    encoding: &EncodingOptions,
+    format: Format,
+) -> anyhow::Result<Status> {
+    let (files, rules) = files_and_rules(paths, options)?;
+    let dry_run = diff || check;
+    let bar = ProgressBar::new("Fixing");
+    let reports = fix_files_with_progress(&files, &rules, dry_run, encoding, &|progress| {
+        bar.update(progress)
+    });
+    let errors = reports
+        .iter()
+        .filter(|report| report.error.is_some())
+        .count();
+    // Like other formatters, pending changes are a failure when only showing them
+    let status = match reports.iter().any(|report| report.changed) {
+        false => Status::Clean,
+        true if dry_run => Status::Violations,
+        true => Status::Fixed,
+    };
+    if format != Format::Text {
+        let mut report = Report::from_file_reports(&reports);
+        report.normalize();
+        print!("{}", format.render(&report));
+    } else {
+        for report in &reports {
+            if let Some(error) = &report.error {
+                eprintln!("Error: {}", error);
+            } else if report.changed && diff {
+                print!("{}", report.diff);
+            } else if report.changed && check {
+                println!("{}: would be fixed", report.path.display());
+            } else if report.changed {
+                println!("{}: fixed", report.path.display());
+            }
+        }
+    }
+    if errors > 0 {
+        anyhow::bail!("{} of {} files could not be processed", errors, files.len());
+    }
+    Ok(status)
+}
+
+fn undo_command(path: &Path, last: usize, dry_run: bool) -> anyhow::Result<bool> {
+    let root = find_graph_root(path).unwrap_or_else(|| path.to_path_buf());
+    let report = undo(&root, last, dry_run)?;
+    for edit in &report.edits {
+        match dry_run {
+            true => print!(
+                "{}",
+                unified_diff(&edit.path.to_string_lossy(), &edit.original, &edit.updated)
+            ),
+            false => println!("{}: restored", edit.path.display()),
+        }
+    }
+    let action = if dry_run { "would delete" } else { "deleted" };
+    for path in &report.deleted {
+        println!("{}: {}, moved back", path.display(), action);
+    }
+    for conflict in &report.conflicts {
+        println!("{}: changed since, not restored", conflict.display());
+    }
+    for path in &report.lost {
+        println!("{}: deleted, not text, not restored", path.display());
+    }
+    Ok(report.conflicts.is_empty() && report.lost.is_empty())
+}
+
+/// The lines of a diff in color: removed lines in red, added lines in green, hunk headers in cyan
+fn colored_diff(diff: &str) -> String {
+    diff.split_inclusive('\n')
+        .map(|line| {
+            let color = match line.as_bytes().first() {
+                Some(b'-') => "31",
+                Some(b'+') => "32",
+                Some(b'@') => "36",
+                _ => return line.to_string(),
+            };
+            format!("\x1b[{}m{}\x1b[0m\n", color, line.trim_end_matches('\n'))
+        })
+        .collect()
+}
+
+const INTERACTIVE_HELP: &str = "y - make this change
+n - skip this change
+a - make this change and all later changes of the rule
+d - skip this change and all later changes of the rule
+q - quit, skipping the changes left";
+
+/// Fixes the files, asking before each change
+fn fix_interactively(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+    encoding: &EncodingOptions,
+) -> anyhow::Result<bool> {
+    let (files, rules) = files_and_rules(paths, options)?;
+    let color = io::stdout().is_terminal();
+    let mut input = io::stdin().lock();
+    // The rules whose later changes are all made (true) or all skipped (false)
+    let mut decided: HashMap<&'static str, bool> = HashMap::new();
+    let mut quit = false;
+    let mut errors = 0;
+    for path in &files {
+        let mut header = Some(path.display().to_string());
+        let ask = |rule: &'static str, hunk: &Hunk| {
+            if quit {
+                return false;
+            }
+            if let Some(&accept) = decided.get(rule) {
+                return accept;
+            }
+            if let Some(header) = header.take() {
+                println!("{}", header);
+            }
+            match color {
+                true => print!("{}", colored_diff(&hunk.diff)),
+                false => print!("{}", hunk.diff),
+            }
+            loop {
+                print!("Make this {} change [y,n,a,d,q,?]? ", rule);
+                let _ = io::stdout().flush();
+                let mut answer = String::new();
+                if input.read_line(&mut answer).unwrap_or(0) == 0 {
+                    println!();
+                    quit = true;
+                    return false;
+                }
+                match answer.trim() {
+                    "y" => return true,
+                    "n" => return false,
+                    "a" => {
+                        decided.insert(rule, true);
+                        return true;
+                    }
+                    "d" => {
+                        decided.insert(rule, false);
+                        return false;
+                    }
+                    "q" => {
+                        quit = true;
+                        return false;
+                    }
+                    _ => println!("{}", INTERACTIVE_HELP),
+                }
+            }
+        };
+        match fix_file_selected(path, &rules, encoding, ask) {
+            Ok(true) => println!("{}: fixed", path.display()),
+            Ok(false) => {}
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                errors += 1;
+            }
+        }
+        if quit {
+            break;
+        }
+    }
+    if errors > 0 {
+        anyhow::bail!("{} of {} files could not be processed", errors, files.len());
+    }
+    Ok(true)
+}
+
+/// Fixes the files once, then again every time they change, until interrupted
+fn remove_spaces(paths: &[PathBuf], in_code_blocks: bool) -> anyhow::Result<bool> {
+    if paths == [Path::new("-")] {
+        remove_consecutive_spaces_stream(io::stdin().lock(), io::stdout().lock(), !in_code_blocks)?;
+        return Ok(true);
+    }
+    for path in expand(paths)? {
+        if remove_consecutive_spaces_in_file(&path, !in_code_blocks)? {
+            println!("{}", path.display());
+        }
+    }
+    Ok(true)
+}
+
+fn lsp(graph_path: &Path, options: &RuleOptions) -> anyhow::Result<bool> {
+    let (_, rules) = config_and_rules(&[graph_path.to_path_buf()], options)?;
+    let graph = match find_graph_root(graph_path) {
+        Some(root) => Some(scan_graph(&root)?),
+        None => None,
+    };
+    Server::new(rules, graph).run(&mut io::stdin().lock(), &mut io::stdout().lock())?;
+    Ok(true)
+}
+
+fn watch(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+    encoding: &EncodingOptions,
+) -> anyhow::Result<bool> {
+    let (config, rules) = config_and_rules(paths, options)?;
+    let mut watcher = Watcher::new(paths, DEBOUNCE)?;
+    let mut files = selected_files(paths, options, &config)?;
+    let polling = match watcher.has_events() {
+        true => "",
+        false => " by polling them",
+    };
+    eprintln!(
+        "Watching {} files{}, press Ctrl+C to stop",
+        files.len(),
+        polling
+    );
+    loop {
+        for report in fix_files_with_progress(&files, &rules, false, encoding, &|_| {}) {
+            if let Some(error) = report.error {
+                eprintln!("Error: {}", error);
+            } else if report.changed {
+                // Our own write must not trigger another fix
+                watcher.ignore_own_write(&report.path);
+                println!("{}: fixed", report.path.display());
+            }
+        }
+        files = config.remove_ignored(watcher.wait()?);
+    }
+}
+
+fn split_blocks(paths: &[PathBuf], limits: BlockLimits, dry_run: bool) -> anyhow::Result<bool> {
+    for edit in &split_block_files(&expand(paths)?, limits, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn explode_blocks(paths: &[PathBuf], dry_run: bool) -> anyhow::Result<bool> {
+    for edit in &explode_files(&expand(paths)?, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn clean_clippings(paths: &[PathBuf], dry_run: bool) -> anyhow::Result<bool> {
+    for edit in &clean_clipping_files(&expand(paths)?, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "network")]
+fn title_urls(
+    paths: &[PathBuf],
+    dry_run: bool,
+    offline: bool,
+    delay_ms: u64,
+) -> anyhow::Result<bool> {
+    use logseq::titles::{title_files, Titler};
+
+    let mut titler = Titler::with_default_cache();
+    titler.offline = offline;
+    titler.delay = std::time::Duration::from_millis(delay_ms);
+    for edit in &title_files(&expand(paths)?, &mut titler, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+/// How often a progress bar is redrawn at most
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar on stderr, for the runs that read many files; only drawn when stderr is a
+/// terminal, and cleared when all files are done
+struct ProgressBar {
+    label: &'static str,
+    enabled: bool,
+    /// When the bar was last drawn, and how many files it showed
+    drawn: Mutex<(Option<Instant>, usize)>,
+}
+
+impl ProgressBar {
+    fn new(label: &'static str) -> Self {
+        ProgressBar {
+            label,
+            enabled: io::stderr().is_terminal(),
+            drawn: Mutex::new((None, 0)),
+        }
+    }
+
+    fn update(&self, progress: Progress) {
+        if !self.enabled {
+            return;
+        }
+        let mut drawn = self.drawn.lock().unwrap_or_else(|error| error.into_inner());
+        let finished = progress.done == progress.total;
+        let recent = drawn
+            .0
+            .is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL);
+        // Files finishing in parallel can report out of order
+        if progress.done <= drawn.1 || (recent && !finished) {
+            return;
+        }
+        *drawn = (Some(Instant::now()), progress.done);
+        if finished {
+            eprint!("\r\x1b[2K");
+            return;
+        }
+        const WIDTH: usize = 30;
+        let filled = WIDTH * progress.done / progress.total.max(1);
+        let name = progress
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        eprint!(
+            "\r\x1b[2K{} [{}{}] {}/{} {}",
+            self.label,
+            "#".repeat(filled),
+            " ".repeat(WIDTH - filled),
+            progress.done,
+            progress.total,
+            name
+        );
+    }
+}
+
+/// Reads a graph with its cached index, warning about the files that can't be read, which are
+/// skipped
+fn scan_graph(root: &Path) -> logseq::Result<Graph> {
+    let graph = scan_graph_quietly(root)?;
+    for file in &graph.unreadable {
+        eprintln!(
+            "Warning: {}: {}, skipped",
+            file.path.display(),
+            file.message
+        );
+    }
+    Ok(graph)
+}
+
+/// Reads a graph with its cached index, with a progress bar for the files parsed, for the
+/// commands that report the files that can't be read themselves
+fn scan_graph_quietly(root: &Path) -> logseq::Result<Graph> {
+    let bar = ProgressBar::new("Scanning");
+    Graph::scan_cached_with_progress(root, &|progress| bar.update(progress))
+}
+
+fn lint(
+    paths: &[PathBuf],
+    options: &RuleOptions,
+    format: Format,
+    thresholds: &Thresholds,
+    baseline: Option<&Path>,
+    update_baseline: bool,
+) -> anyhow::Result<bool> {
+    let (config, rules) = config_and_rules(paths, options)?;
+    let files = selected_files(paths, options, &config)?;
+    let mut report = Report::default();
+    let bar = ProgressBar::new("Linting");
+    for (index, path) in files.iter().enumerate() {
+        match lint_file(path, &rules) {
+            Ok(result) => report.extend(Report::from_lint(Some(path), &result)),
+            Err(error) => report.extend(Report::from_unreadable(&[UnreadableFile::new(
+                path, &error,
+            )])),
+        }
+        bar.update(Progress {
+            done: index + 1,
+            total: files.len(),
+            path,
+        });
+    }
+    report.normalize();
+    let baseline_path =
+        baseline.map_or_else(|| config.root.join(BASELINE_FILE_NAME), Path::to_path_buf);
+    let root = match baseline_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if update_baseline {
+        let baseline = Baseline::from_report(&report, root);
+        baseline.write(&baseline_path)?;
+        println!(
+            "{}: recorded {} findings",
+            baseline_path.display(),
+            baseline.len()
+        );
+        return Ok(true);
+    }
+    if baseline_path.exists() {
+        let known = Baseline::read(&baseline_path)?.filter(&mut report, root);
+        if known > 0 {
+            eprintln!(
+                "{} known findings left out, see {}",
+                known,
+                baseline_path.display()
+            );
+        }
+    }
+    print!("{}", format.render(&report));
+    Ok(!report.fails(thresholds))
+}
+
+fn check_links(
+    graph_path: &Path,
+    format: Format,
+    apply: bool,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let mut graph = scan_graph_quietly(graph_path)?;
+    if apply {
+        let edits = apply_suggestions(&graph, &graph.suggest_links(), dry_run)?;
+        for edit in &edits {
+            let label = edit.path.to_string_lossy();
+            print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+        }
+        // Report the links that are still broken
+        if !dry_run && !edits.is_empty() {
+            graph = scan_graph_quietly(graph_path)?;
+        }
+    }
+    let portals = check_whiteboards(&graph)?;
+    let mut missing_pages = graph.check_links();
+    missing_pages.extend(portals.pages);
+    let mut missing_blocks = graph.check_block_refs();
+    missing_blocks.extend(portals.blocks);
+    let suggestions: HashMap<String, String> = missing_pages
+        .iter()
+        .filter_map(|link| Some((link.target.clone(), graph.suggest_page(&link.target)?)))
+        .collect();
+    let did_you_mean = |target: &str| match suggestions.get(target) {
+        Some(page) => format!(", did you mean [[{}]]?", page),
+        None => String::new(),
+    };
+    if format != Format::Text {
+        let mut report = Report::from_broken_links("missing-page", &missing_pages, |target| {
+            format!("Missing page [[{}]]{}", target, did_you_mean(target))
+        });
+        report.extend(Report::from_broken_links(
+            "missing-block",
+            &missing_blocks,
+            |target| format!("Missing block (({}))", target),
+        ));
+        report.extend(Report::from_unreadable(&graph.unreadable));
+        report.normalize();
+        print!("{}", format.render(&report));
+        return Ok(report.findings.is_empty());
+    }
+    for link in &missing_pages {
+        println!(
+            "{}:{}:{}: missing page [[{}]]{}",
+            link.path.display(),
+            link.line,
+            link.column,
+            link.target,
+            did_you_mean(&link.target)
+        );
+    }
+    for block_ref in &missing_blocks {
+        println!(
+            "{}:{}:{}: missing block (({}))",
+            block_ref.path.display(),
+            block_ref.line,
+            block_ref.column,
+            block_ref.target
+        );
+    }
+    for file in &graph.unreadable {
+        println!(
+            "{}: can't be read, not checked: {}",
+            file.path.display(),
+            file.message
+        );
+    }
+    Ok(missing_pages.is_empty() && missing_blocks.is_empty() && graph.unreadable.is_empty())
+}
+
+#[cfg(feature = "network")]
+fn check_web_links_command(
+    graph_path: &Path,
+    format: Format,
+    concurrency: usize,
+    retries: u32,
+    ttl_hours: u64,
+) -> anyhow::Result<bool> {
+    use logseq::weblinks::{check_web_links, LinkChecker};
+
+    let mut checker = LinkChecker::with_default_cache();
+    checker.concurrency = concurrency;
+    checker.retries = retries;
+    checker.ttl = Duration::from_secs(ttl_hours * 3600);
+    let dead = check_web_links(&scan_graph(graph_path)?, &mut checker)?;
+    let mut report = Report::from_broken_links("dead-link", &dead, |url| {
+        let status = checker.result(url).map(|result| result.describe());
+        format!("Dead link {} ({})", url, status.unwrap_or_default())
+    });
+    report.normalize();
+    print!("{}", format.render(&report));
+    Ok(report.findings.is_empty())
+}
+
+fn check_embeds(
+    graph_path: &Path,
+    limits: &EmbedLimits,
+    fix_missing: bool,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let mut issues = scan_graph(graph_path)?.audit_embeds(limits);
+    if fix_missing {
+        for edit in &unembed_missing(&issues, dry_run)? {
+            let label = edit.path.to_string_lossy();
+            print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+        }
+        if !dry_run {
+            issues.retain(|issue| issue.problem != EmbedProblem::Missing);
+        }
+    }
+    for issue in &issues {
+        let message = match issue.problem {
+            EmbedProblem::Missing => format!("embed of missing page [[{}]]", issue.target),
+            EmbedProblem::Tiny { words } => format!(
+                "embed of tiny page [[{}]] ({} words), a link would do",
+                issue.target, words
+            ),
+            EmbedProblem::Large { words, embeds } => format!(
+                "embed of large page [[{}]] ({} words, embedded {} times)",
+                issue.target, words, embeds
+            ),
+        };
+        println!(
+            "{}:{}:{}: {}",
+            issue.path.display(),
+            issue.line,
+            issue.column,
+            message
+        );
+    }
+    Ok(issues.is_empty())
+}
+
+fn check_assets_command(graph_path: &Path, delete: bool) -> anyhow::Result<bool> {
+    let report = check_assets(&scan_graph(graph_path)?)?;
+    for link in &report.missing {
+        println!(
+            "{}:{}:{}: missing asset {}",
+            link.path.display(),
+            link.line,
+            link.column,
+            link.target
+        );
+    }
+    if delete {
+        delete_orphans(&report)?;
+    }
+    for orphan in &report.orphans {
+        let action = if delete { "deleted" } else { "unused asset" };
+        println!("{}: {}", orphan.display(), action);
+    }
+    Ok(report.missing.is_empty() && (delete || report.orphans.is_empty()))
+}
+
+fn dedupe_assets_command(graph_path: &Path, dry_run: bool) -> anyhow::Result<bool> {
+    let graph = scan_graph(graph_path)?;
+    let groups = find_duplicate_assets(&graph)?;
+    let edits = dedupe_assets(&graph, &groups, dry_run)?;
+    if dry_run {
+        for edit in &edits {
+            let label = edit.path.to_string_lossy();
+            print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+        }
+    }
+    let action = if dry_run {
+        "duplicate of"
+    } else {
+        "deleted, duplicate of"
+    };
+    for group in &groups {
+        for duplicate in &group.duplicates {
+            println!(
+                "{}: {} {}",
+                duplicate.display(),
+                action,
+                group.canonical.display()
+            );
+        }
+    }
+    Ok(!dry_run || groups.is_empty())
+}
+
+fn check_highlights_command(graph_path: &Path) -> anyhow::Result<bool> {
+    let issues = check_highlights(&scan_graph(graph_path)?);
+    for issue in &issues {
+        let (line, message) = match &issue.problem {
+            HighlightProblem::NoPdf => (None, "no file-path:: to the PDF".to_string()),
+            HighlightProblem::MissingPdf { target } => (None, format!("missing PDF {}", target)),
+            HighlightProblem::InvalidPage { line, value } => (
+                Some(line),
+                format!("hl-page:: must be a page number, not {}", value),
+            ),
+            HighlightProblem::MissingAreaImage { line, image } => (
+                Some(line),
+                format!("missing area highlight image {}", image.display()),
+            ),
+        };
+        match line {
+            Some(line) => println!("{}:{}: {}", issue.path.display(), line, message),
+            None => println!("{}: {}", issue.path.display(), message),
+        }
+    }
+    Ok(issues.is_empty())
+}
+
+fn check_config_command(graph_path: &Path) -> anyhow::Result<bool> {
+    let issues = check_config(&scan_graph(graph_path)?)?;
+    for issue in &issues {
+        let message = match &issue.problem {
+            ConfigProblem::MissingFavorite(page) => format!("missing favorite page [[{}]]", page),
+            ConfigProblem::InvalidDefaultQuery { title, message } if title.is_empty() => {
+                format!("default query: {}", message)
+            }
+            ConfigProblem::InvalidDefaultQuery { title, message } => {
+                format!("default query {}: {}", title, message)
+            }
+        };
+        println!("{}:{}: {}", issue.path.display(), issue.line, message);
+    }
+    Ok(issues.is_empty())
+}
+
+fn check_aliases(graph_path: &Path) -> anyhow::Result<bool> {
+    let conflicts = scan_graph(graph_path)?.find_alias_conflicts();
+    for conflict in &conflicts {
+        let what = match conflict.kind {
+            AliasConflictKind::PageName => "is the name of",
+            AliasConflictKind::Alias => "is also an alias of",
+        };
+        println!(
+            "{}: alias {} {} {} ({})",
+            conflict.path.display(),
+            conflict.alias,
+            what,
+            conflict.other_page,
+            conflict.other_path.display()
+        );
+    }
+    Ok(conflicts.is_empty())
+}
+
+fn duplicates(graph_path: &Path) -> anyhow::Result<bool> {
+    let groups = scan_graph(graph_path)?.find_duplicates()?;
+    for group in &groups {
+        for duplicate in &group.duplicates {
+            println!(
+                "{}: duplicate of {}",
+                duplicate.display(),
+                group.canonical.display()
+            );
+        }
+    }
+    Ok(groups.is_empty())
+}
+
+fn sync_conflicts(graph_path: &Path, merge: bool, dry_run: bool) -> anyhow::Result<bool> {
+    let graph = scan_graph(graph_path)?;
+    let conflicts = find_sync_conflicts(&graph)?;
+    if !merge {
+        for conflict in &conflicts {
+            println!(
+                "{}: {} conflict of {} ({} new blocks)",
+                conflict.path.display(),
+                conflict.tool.as_str(),
+                conflict.original.display(),
+                conflict.new_blocks.len()
+            );
+            print!("{}", conflict.diff(&graph.root)?);
+        }
+        return Ok(conflicts.is_empty());
+    }
+    let resolution = resolve_sync_conflicts(&conflicts, dry_run)?;
+    for edit in &resolution.edits {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    let action = if dry_run { "would delete" } else { "deleted" };
+    for path in &resolution.deleted {
+        println!("{}: {}", path.display(), action);
+    }
+    Ok(true)
+}
+
+fn duplicate_blocks(graph_path: &Path, min_words: usize) -> anyhow::Result<bool> {
+    let groups = find_duplicate_blocks(&scan_graph(graph_path)?, min_words);
+    for group in &groups {
+        let first = &group.blocks[0];
+        let kind = if group.identical {
+            "duplicate of"
+        } else {
+            "similar to"
+        };
+        for copy in &group.blocks[1..] {
+            println!(
+                "{}:{}: {} {}:{}: {}",
+                copy.path.display(),
+                copy.line,
+                kind,
+                first.path.display(),
+                first.line,
+                group.preview
+            );
+        }
+    }
+    Ok(groups.is_empty())
+}
+
+fn duplicate_ids(graph_path: &Path, fix: bool) -> anyhow::Result<bool> {
+    let duplicates = scan_graph(graph_path)?.find_duplicate_ids();
+    for duplicate in &duplicates {
+        for copy in &duplicate.copies {
+            println!(
+                "{}:{}: duplicate id {} of {}:{} ({} references)",
+                copy.path.display(),
+                copy.line,
+                duplicate.uuid,
+                duplicate.keep.path.display(),
+                duplicate.keep.line,
+                duplicate.references
+            );
+        }
+    }
+    if !fix {
+        return Ok(duplicates.is_empty());
+    }
+    for (copy, uuid) in repair_duplicate_ids(&duplicates)? {
+        println!("{}:{}: new id {}", copy.path.display(), copy.line, uuid);
+    }
+    Ok(true)
+}
+
+fn orphans(graph_path: &Path) -> anyhow::Result<bool> {
+    let orphans = scan_graph(graph_path)?.find_orphans()?;
+    for orphan in &orphans {
+        let modified: DateTime<Local> = orphan.modified.into();
+        println!(
+            "{}: {} (last modified {})",
+            orphan.path.display(),
+            orphan.name,
+            modified.format("%Y-%m-%d %H:%M")
+        );
+    }
+    Ok(orphans.is_empty())
+}
+
+fn stubs(graph_path: &Path, delete: bool) -> anyhow::Result<bool> {
+    let stubs = find_stub_pages(&scan_graph(graph_path)?)?;
+    if delete {
+        delete_stub_pages(&stubs)?;
+    }
+    for stub in &stubs {
+        let action = if delete {
+            "deleted"
+        } else {
+            stub.kind.as_str()
+        };
+        println!("{}: {} ({})", stub.path.display(), stub.name, action);
+    }
+    Ok(delete || stubs.is_empty())
+}
+
+fn grep(
+    graph_path: &Path,
+    pattern: &str,
+    fixed_strings: bool,
+    ignore_case: bool,
+    properties: &[PropertyFilter],
+    format: SearchFormat,
+) -> anyhow::Result<bool> {
+    let mut query = SearchQuery::new(pattern, fixed_strings, ignore_case)?;
+    query.properties = properties.to_vec();
+    let hits = search_graph(&scan_graph(graph_path)?, &query);
+    print!("{}", format.render(&hits));
+    Ok(!hits.is_empty())
+}
+
+fn stats(graph_path: &Path, format: StatsFormat, top: usize) -> anyhow::Result<bool> {
+    let stats = graph_stats(&scan_graph(graph_path)?, top)?;
+    print!("{}", format.render(&stats));
+    Ok(true)
+}
+
+fn doctor(
+    graph_path: &Path,
+    options: &RuleOptions,
+    format: HealthFormat,
+    health_options: &HealthOptions,
+    min_score: Option<u8>,
+) -> anyhow::Result<bool> {
+    let (config, rules) = config_and_rules(&[graph_path.to_path_buf()], options)?;
+    let mut graph = scan_graph(graph_path)?;
+    graph.pages.retain(|page| !config.is_ignored(&page.path));
+    let report = graph_health(&graph, &rules, health_options)?;
+    print!("{}", format.render(&report));
+    Ok(match min_score {
+        Some(min_score) => report.score >= min_score,
+        None => report
+            .categories
+            .iter()
+            .all(|category| category.findings == 0),
+    })
+}
+
+fn check_journals_command(graph_path: &Path, rename: bool) -> anyhow::Result<bool> {
+    let issues = check_journals(graph_path)?;
+    if rename {
+        for (path, new_path) in rename_journals(&issues)? {
+            println!("{} -> {}", path.display(), new_path.display());
+        }
+    }
+    let mut clean = true;
+    for issue in issues
+        .iter()
+        .filter(|issue| !rename || issue.rename_to.is_none())
+    {
+        clean = false;
+        let message = match &issue.problem {
+            JournalProblem::NotADate => "file name is not a date".to_string(),
+            JournalProblem::WrongFormat(date) => {
+                format!("file name is {}, but not in the graph's date format", date)
+            }
+            JournalProblem::DateMismatch {
+                file_date,
+                content_date,
+            } => format!(
+                "file name is {}, but the journal starts with {}",
+                file_date, content_date
+            ),
+        };
+        println!("{}: {}", issue.path.display(), message);
+    }
+    Ok(clean)
+}
+
+fn journal_gaps_command(
+    graph_path: &Path,
+    create: bool,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    template: bool,
+) -> anyhow::Result<bool> {
+    let gaps: Vec<NaiveDate> = journal_gaps(graph_path)?
+        .into_iter()
+        .filter(|date| from.is_none_or(|from| *date >= from) && to.is_none_or(|to| *date <= to))
+        .collect();
+    if !create {
+        for date in &gaps {
+            println!("{}: missing journal", date);
+        }
+        return Ok(gaps.is_empty());
+    }
+    let body = match template {
+        true => {
+            let name = GraphConfig::read(graph_path)?
+                .default_journal_template
+                .ok_or_else(|| {
+                    anyhow::anyhow!("config.edn has no :default-templates {{:journals}}")
+                })?;
+            let body = template_body(&scan_graph(graph_path)?, &name)?;
+            body.ok_or_else(|| anyhow::anyhow!("template not found: {}", name))?
+        }
+        false => String::new(),
+    };
+    let title_format = journal_title_format(graph_path);
+    let created = create_journals(graph_path, &gaps, |date| {
+        render_date_variables(&body, date, &title_format)
+    })?;
+    for path in created {
+        println!("{}: created", path.display());
+    }
+    Ok(true)
+}
+
+fn journal_duplicates(graph_path: &Path, sort: bool, fix: bool) -> anyhow::Result<bool> {
+    let cleanups = plan_journal_cleanup(&scan_graph(graph_path)?, sort)?;
+    if fix {
+        apply_journal_cleanup(&cleanups)?;
+    }
+    for cleanup in &cleanups {
+        let path = cleanup.edit.path.display();
+        for line in &cleanup.removed {
+            let action = if fix { "removed copy" } else { "copy" };
+            println!("{}:{}: {} of a sibling block above", path, line, action);
+        }
+        if cleanup.removed.is_empty() {
+            let action = if fix {
+                "sorted"
+            } else {
+                "not in order of time"
+            };
+            println!("{}: entries {}", path, action);
+        }
+    }
+    Ok(fix || cleanups.is_empty())
+}
+
+fn namespaces(graph_path: &Path, create_parents: bool, reencode: bool) -> anyhow::Result<bool> {
+    let issues = check_namespaces(&scan_graph(graph_path)?)?;
+    if create_parents {
+        for stub in create_parent_stubs(&issues)? {
+            println!("{}: created", stub.display());
+        }
+    }
+    if reencode {
+        for (path, new_path) in reencode_files(&issues)? {
+            println!("{} -> {}", path.display(), new_path.display());
+        }
+    }
+    let mut clean = true;
+    for issue in &issues {
+        let message = match &issue.problem {
+            NamespaceProblem::MissingParent { .. } if create_parents => continue,
+            NamespaceProblem::InconsistentSeparator { .. } if reencode => continue,
+            NamespaceProblem::MissingParent { parent, .. } => {
+                format!("parent page {} of {} doesn't exist", parent, issue.page)
+            }
+            NamespaceProblem::InconsistentSeparator {
+                found, expected, ..
+            } => format!(
+                "file name encodes / as {}, but the graph uses {}",
+                found, expected
+            ),
+        };
+        clean = false;
+        println!("{}: {}", issue.path.display(), message);
+    }
+    Ok(clean)
+}
+
+fn normalize_names(graph_path: &Path, fix: bool) -> anyhow::Result<bool> {
+    let normalization = plan_normalization(&scan_graph(graph_path)?)?;
+    if fix {
+        normalization.apply()?;
+    }
+    for (path, new_path) in &normalization.renames {
+        match fix {
+            true => println!("{} -> {}", path.display(), new_path.display()),
+            false => println!("{}: file name isn't NFC", path.display()),
+        }
+    }
+    for edit in &normalization.edits {
+        for (line, before, after) in edit.changed_lines() {
+            println!("{}:{}:", edit.path.display(), line);
+            println!("-{}", before);
+            println!("+{}", after);
+        }
+    }
+    Ok(fix || normalization.is_empty())
+}
+
+fn file_names(graph_path: &Path, fix: bool) -> anyhow::Result<bool> {
+    let repair = plan_file_name_repair(&scan_graph(graph_path)?)?;
+    if fix {
+        repair.apply()?;
+    }
+    for (path, new_path) in &repair.renames {
+        match fix {
+            true => println!("{} -> {}", path.display(), new_path.display()),
+            false => println!(
+                "{}: file name should be {}",
+                path.display(),
+                new_path.file_name().unwrap_or_default().to_string_lossy()
+            ),
+        }
+    }
+    for edit in &repair.edits {
+        for (line, before, after) in edit.changed_lines() {
+            println!("{}:{}:", edit.path.display(), line);
+            println!("-{}", before);
+            println!("+{}", after);
+        }
+    }
+    Ok(fix || repair.is_empty())
+}
+
+fn templates(graph_path: &Path, list: bool) -> anyhow::Result<bool> {
+    let graph = scan_graph(graph_path)?;
+    if list {
+        for template in list_templates(&graph) {
+            println!(
+                "{}:{}: {}",
+                template.path.display(),
+                template.line,
+                template.name
+            );
+        }
+    }
+    let issues = check_templates(&graph);
+    for issue in &issues {
+        let (line, message) = match &issue.problem {
+            TemplateProblem::DuplicateName { path, line } => (
+                issue.line,
+                format!(
+                    "template {} is also defined at {}:{}",
+                    issue.template,
+                    path.display(),
+                    line
+                ),
+            ),
+            TemplateProblem::UnknownVariable { line, variable } => (
+                *line,
+                format!(
+                    "template {} has the unknown dynamic variable {}",
+                    issue.template, variable
+                ),
+            ),
+            TemplateProblem::IncludingParentWithoutTemplate => (
+                issue.line,
+                format!("{}:: on a block without template::", INCLUDING_PARENT_KEY),
+            ),
+            TemplateProblem::InvalidIncludingParent(value) => (
+                issue.line,
+                format!(
+                    "{}:: must be true or false, not {}",
+                    INCLUDING_PARENT_KEY, value
+                ),
+            ),
+        };
+        println!("{}:{}: {}", issue.path.display(), line, message);
+    }
+    Ok(issues.is_empty())
+}
+
+fn rename_page_command(
+    graph_path: &Path,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let rename = rename_page(&scan_graph(graph_path)?, old, new, dry_run)?;
+    if rename.from != rename.to {
+        println!("{} -> {}", rename.from.display(), rename.to.display());
+    }
+    for edit in &rename.edits {
+        for (line, before, after) in edit.changed_lines() {
+            println!("{}:{}:", edit.path.display(), line);
+            println!("-{}", before);
+            println!("+{}", after);
+        }
+    }
+    Ok(true)
+}
+
+/// Prints the changed lines of a property rename, then its conflicts and a summary
+fn print_property_rename(rename: &PropertyRename, new: &str, dry_run: bool) -> bool {
+    let mut changed = 0;
+    for edit in &rename.edits {
+        for (line, before, after) in edit.changed_lines() {
+            println!("{}:{}:", edit.path.display(), line);
+            println!("-{}", before);
+            println!("+{}", after);
+            changed += 1;
+        }
+    }
+    for (path, line) in &rename.conflicts {
+        println!(
+            "{}:{}: already has a {}:: property",
+            path.display(),
+            line,
+            new
+        );
+    }
+    let action = if dry_run { "would change" } else { "changed" };
+    println!(
+        "{} {} lines in {} files",
+        action,
+        changed,
+        rename.edits.len()
+    );
+    rename.conflicts.is_empty()
+}
+
+fn rename_property_command(
+    graph_path: &Path,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let rename = rename_property(&scan_graph(graph_path)?, old, new, dry_run)?;
+    Ok(print_property_rename(&rename, new, dry_run))
+}
+
+fn normalize_property_keys_command(
+    graph_path: &Path,
+    style: KeyStyle,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let normalization = normalize_property_keys(&scan_graph(graph_path)?, style, dry_run)?;
+    for mapping in &normalization.mapping {
+        println!(
+            "{}:: -> {}:: ({} lines)",
+            mapping.from, mapping.to, mapping.count
+        );
+    }
+    let rename = &normalization.rename;
+    for (path, line) in &rename.conflicts {
+        println!(
+            "{}:{}: another property has the same normalized key",
+            path.display(),
+            line
+        );
+    }
+    let changed: usize = normalization
+        .mapping
+        .iter()
+        .map(|mapping| mapping.count)
+        .sum();
+    let action = if dry_run { "would change" } else { "changed" };
+    println!(
+        "{} {} lines in {} files",
+        action,
+        changed,
+        rename.edits.len()
+    );
+    Ok(rename.conflicts.is_empty())
+}
+
+fn rename_property_value_command(
+    graph_path: &Path,
+    key: &str,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let rename = rename_property_value(&scan_graph(graph_path)?, key, old, new, dry_run)?;
+    Ok(print_property_rename(&rename, key, dry_run))
+}
+
+fn merge_command(
+    graph_path: &Path,
+    source: &str,
+    target: &str,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let merge = merge_pages(&scan_graph(graph_path)?, source, target, dry_run)?;
+    for edit in &merge.edits {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    let action = if dry_run { "would delete" } else { "deleted" };
+    println!("{}: {}", merge.source.display(), action);
+    Ok(true)
+}
+
+fn split_command(
+    graph_path: &Path,
+    page: &str,
+    level: usize,
+    embed: bool,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let leave = if embed {
+        SplitLeave::Embed
+    } else {
+        SplitLeave::Link
+    };
+    let split = split_page(&scan_graph(graph_path)?, page, level, leave, dry_run)?;
+    for edit in std::iter::once(&split.page).chain(&split.created) {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn inline_refs_command(
+    graph_path: &Path,
+    target: &InlineTarget,
+    keep_link: bool,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    for edit in &inline_refs(&scan_graph(graph_path)?, target, keep_link, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn toc(graph_path: &Path, options: &TocOptions, dry_run: bool) -> anyhow::Result<bool> {
+    for edit in &refresh_tocs(&scan_graph(graph_path)?, options, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+/// A language, boxed to keep [Command] small
+fn parse_locale(code: &str) -> logseq::Result<Box<Locale>> {
+    code.parse().map(Box::new)
+}
+
+fn link_dates_command(
+    graph_path: &Path,
+    options: &DateLinkOptions,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    for edit in &link_dates(&scan_graph(graph_path)?, options, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn backfill_timestamps_command(
+    graph_path: &Path,
+    git: bool,
+    dry_run: bool,
+) -> anyhow::Result<bool> {
+    let source = if git {
+        TimestampSource::Git
+    } else {
+        TimestampSource::FileTimes
+    };
+    for edit in backfill_timestamps(&scan_graph(graph_path)?, source, dry_run)? {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    Ok(true)
+}
+
+fn db_version(graph_path: &Path, dry_run: bool) -> anyhow::Result<bool> {
+    let conversion = convert_graph(&scan_graph(graph_path)?, dry_run)?;
+    for edit in &conversion.edits {
+        let label = edit.path.to_string_lossy();
+        print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+    }
+    for issue in &conversion.issues {
+        let message = match &issue.problem {
+            DbProblem::UnmappedMarker(marker) => format!("no task status for {}", marker),
+            DbProblem::DuplicateKey(key) => format!("property {}:: written more than once", key),
+            DbProblem::OrgFile => "org-mode files aren't imported, see migrate-org".to_string(),
+        };
+        println!(
+            "{}:{}:{}: {}",
+            issue.path.display(),
+            issue.line,
+            issue.column,
+            message
+        );
+    }
+    Ok(conversion.issues.is_empty())
+}
+
+fn migrate_org(graph_path: &Path, delete: bool) -> anyhow::Result<bool> {
+    for (org_path, markdown_path) in migrate_graph(graph_path, delete)? {
+        println!("{} -> {}", org_path.display(), markdown_path.display());
+    }
+    Ok(true)
+}
+
+fn import(source: &ImportSource) -> anyhow::Result<bool> {
+    let imported = match source {
+        ImportSource::Obsidian { vault, graph } => import_vault(vault, graph)?,
+        ImportSource::Roam { export, graph } => import_export(export, graph)?
+            .into_iter()
+            .map(|path| (export.clone(), path))
+            .collect(),
+    };
+    for (source, destination) in imported {
+        println!("{} -> {}", source.display(), destination.display());
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "index-sqlite")]
+fn query(
+    graph: &Path,
+    sql: &str,
+    database: Option<&Path>,
+    format: QueryFormat,
+) -> anyhow::Result<bool> {
+    use logseq::sqlite::{index_graph, Database};
+
+    let graph = scan_graph(graph)?;
+    let database = match database {
+        Some(path) => {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            Database::open(path)?
+        }
+        None => Database::in_memory()?,
+    };
+    index_graph(&graph, &database)?;
+    print!("{}", format.render(&database.query(sql)?));
+    Ok(true)
+}
+
+fn export(format: &ExportFormat) -> anyhow::Result<bool> {
+    match format {
+        ExportFormat::Markdown {
+            graph,
+            output,
+            inline_embeds,
+        } => {
+            for path in export_graph(&scan_graph(graph)?, output, *inline_embeds)? {
+                println!("{}", path.display());
+            }
+        }
+        ExportFormat::Graph { graph, format } => {
+            print!("{}", format.render(&dump_graph(&scan_graph(graph)?)));
+        }
+        ExportFormat::Dot {
+            graph,
+            format,
+            namespace,
+            tag,
+        } => {
+            let filter = LinkFilter {
+                namespace: namespace.clone(),
+                tag: tag.clone(),
+            };
+            print!(
+                "{}",
+                format.render(&link_graph(&scan_graph(graph)?, &filter))
+            );
+        }
+    }
+    Ok(true)
+}
+
+fn cards(action: &CardsAction) -> anyhow::Result<bool> {
+    let CardsAction::Export { graph, format } = action;
+    print!(
+        "{}",
+        format.render(&extract_flashcards(&scan_graph(graph)?))
+    );
+    Ok(true)
+}
+
+fn tasks(action: &TasksAction) -> anyhow::Result<bool> {
+    match action {
+        TasksAction::Report {
+            graph,
+            format,
+            stale_days,
+            today,
+            utc_offset,
+        } => {
+            let today = today.unwrap_or_else(|| dates::today(*utc_offset));
+            let entries = task_report(&scan_graph(graph)?, today, *stale_days)?;
+            print!("{}", format.render(&entries));
+            Ok(entries.is_empty())
+        }
+        TasksAction::Archive {
+            graph,
+            older_than,
+            page,
+            today,
+            dry_run,
+        } => {
+            let today = today.unwrap_or_else(|| Local::now().date_naive());
+            let destination = match page {
+                Some(name) => ArchiveDestination::Page(name.clone()),
+                None => ArchiveDestination::Journal,
+            };
+            let archive = plan_archive(&scan_graph(graph)?, today, *older_than, &destination)?;
+            if !dry_run {
+                archive.apply()?;
+            }
+            for task in &archive.tasks {
+                println!(
+                    "{}:{}: {} -> {}",
+                    task.path.display(),
+                    task.line,
+                    task.text,
+                    task.destination.display()
+                );
+            }
+            Ok(true)
+        }
+        TasksAction::BackfillDone {
+            graph,
+            git,
+            dry_run,
+        } => {
+            let source = if *git {
+                TimestampSource::Git
+            } else {
+                TimestampSource::FileTimes
+            };
+            for edit in backfill_done(&scan_graph(graph)?, source, *dry_run)? {
+                let label = edit.path.to_string_lossy();
+                print!("{}", unified_diff(&label, &edit.original, &edit.updated));
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if !matches!(cli.command, Command::Undo { .. }) {
+        start_session();
+    }
+    let result = match &cli.command {
+        Command::Fix {
+            paths,
+            watch: true,
+            strip_bom,
+            transcode,
+            rules,
+            ..
+        } => watch(paths, rules, &encoding_options(*strip_bom, *transcode)).map(Status::from),
+        Command::Fix {
+            paths,
+            interactive: true,
+            strip_bom,
+            transcode,
+            rules,
+            ..
+        } => fix_interactively(paths, rules, &encoding_options(*strip_bom, *transcode))
+            .map(Status::from),
+        Command::Undo {
+            path,
+            last,
+            dry_run,
+        } => undo_command(path, *last, *dry_run).map(Status::from),
+        Command::Fix {
+            paths,
+            diff,
+            check,
+            strip_bom,
+            transcode,
+            rules,
+            format,
+            ..
+        } => fix(
+            paths,
+            *diff,
+            *check,
+            rules,
+            &encoding_options(*strip_bom, *transcode),
+            *format,
+        ),
+        Command::SplitBlocks {
+            paths,
+            max_sentences,
+            max_chars,
+            dry_run,
+        } => {
+            let limits = BlockLimits {
+                max_sentences: *max_sentences,
+                max_chars: *max_chars,
+            };
+            split_blocks(paths, limits, *dry_run).map(Status::from)
+        }
+        Command::ExplodeBlocks { paths, dry_run } => {
+            explode_blocks(paths, *dry_run).map(Status::from)
+        }
+        Command::CleanClippings { paths, dry_run } => {
+            clean_clippings(paths, *dry_run).map(Status::from)
+        }
+        #[cfg(feature = "network")]
+        Command::TitleUrls {
+            paths,
+            dry_run,
+            offline,
+            delay_ms,
+        } => title_urls(paths, *dry_run, *offline, *delay_ms).map(Status::from),
+        Command::Lint {
+            paths,
+            rules,
+            format,
+            fail_on,
+            max_warnings,
+            baseline,
+            update_baseline,
+        } => {
+            let thresholds = Thresholds {
+                fail_on: *fail_on,
+                max_warnings: *max_warnings,
+            };
+            let baseline = baseline.as_deref();
+            lint(
+                paths,
+                rules,
+                *format,
+                &thresholds,
+                baseline,
+                *update_baseline,
+            )
+            .map(Status::from)
+        }
+        Command::Rules { format } => {
+            print!("{}", RuleSet::builtin().render_rules(*format));
+            Ok(Status::Clean)
+        }
+        Command::Explain { rule, format } => RuleSet::builtin()
+            .explain(rule, *format)
+            .map(|text| {
+                print!("{}", text);
+                Status::Clean
+            })
+            .map_err(anyhow::Error::from),
+        Command::RemoveSpaces {
+            paths,
+            in_code_blocks,
+        } => remove_spaces(paths, *in_code_blocks).map(Status::from),
+        Command::Lsp { graph, rules } => lsp(graph, rules).map(Status::from),
+        Command::CheckLinks {
+            graph,
+            format,
+            apply_suggestions,
+            dry_run,
+        } => check_links(graph, *format, *apply_suggestions, *dry_run).map(Status::from),
+        #[cfg(feature = "network")]
+        Command::CheckWebLinks {
+            graph,
+            format,
+            concurrency,
+            retries,
+            ttl_hours,
+        } => check_web_links_command(graph, *format, *concurrency, *retries, *ttl_hours)
+            .map(Status::from),
+        Command::CheckEmbeds {
+            graph,
+            tiny_words,
+            large_words,
+            max_embeds,
+            fix_missing,
+            dry_run,
+        } => {
+            let limits = EmbedLimits {
+                tiny_words: *tiny_words,
+                large_words: *large_words,
+                max_embeds: *max_embeds,
+            };
+            check_embeds(graph, &limits, *fix_missing, *dry_run).map(Status::from)
+        }
+        Command::CheckAssets {
+            graph,
+            delete_orphans,
+        } => check_assets_command(graph, *delete_orphans).map(Status::from),
+        Command::DedupeAssets { graph, dry_run } => {
+            dedupe_assets_command(graph, *dry_run).map(Status::from)
+        }
+        Command::CheckHighlights { graph } => check_highlights_command(graph).map(Status::from),
+        Command::CheckConfig { graph } => check_config_command(graph).map(Status::from),
+        Command::CheckAliases { graph } => check_aliases(graph).map(Status::from),
+        Command::Duplicates { graph } => duplicates(graph).map(Status::from),
+        Command::DuplicateIds { graph, fix } => duplicate_ids(graph, *fix).map(Status::from),
+        Command::DuplicateBlocks { graph, min_words } => {
+            duplicate_blocks(graph, *min_words).map(Status::from)
+        }
+        Command::SyncConflicts {
+            graph,
+            merge,
+            dry_run,
+        } => sync_conflicts(graph, *merge, *dry_run).map(Status::from),
+        Command::Orphans { graph } => orphans(graph).map(Status::from),
+        Command::Stubs { graph, delete } => stubs(graph, *delete).map(Status::from),
+        Command::Grep {
+            pattern,
+            graph,
+            fixed_strings,
+            ignore_case,
+            properties,
+            format,
+        } => grep(
+            graph,
+            pattern,
+            *fixed_strings,
+            *ignore_case,
+            properties,
+            *format,
+        )
+        .map(Status::from),
+        Command::Stats { graph, format, top } => stats(graph, *format, *top).map(Status::from),
+        Command::Doctor {
+            graph,
+            rules,
+            format,
+            top,
+            stale_days,
+            today,
+            min_score,
+        } => {
+            let health_options = HealthOptions {
+                today: today.unwrap_or_else(|| Local::now().date_naive()),
+                stale_days: *stale_days,
+                top: *top,
+            };
+            doctor(graph, rules, *format, &health_options, *min_score).map(Status::from)
+        }
+        Command::CheckJournals { graph, rename } => {
+            check_journals_command(graph, *rename).map(Status::from)
+        }
+        Command::JournalGaps {
+            graph,
+            create,
+            from,
+            to,
+            template,
+        } => journal_gaps_command(graph, *create, *from, *to, *template).map(Status::from),
+        Command::JournalDuplicates { graph, sort, fix } => {
+            journal_duplicates(graph, *sort, *fix).map(Status::from)
+        }
+        Command::Namespaces {
+            graph,
+            create_parents,
+            reencode,
+        } => namespaces(graph, *create_parents, *reencode).map(Status::from),
+        Command::NormalizeNames { graph, fix } => normalize_names(graph, *fix).map(Status::from),
+        Command::FileNames { graph, fix } => file_names(graph, *fix).map(Status::from),
+        Command::Templates { graph, list } => templates(graph, *list).map(Status::from),
+        Command::RenamePage {
+            graph,
+            old,
+            new,
+            dry_run,
+        } => rename_page_command(graph, old, new, *dry_run).map(Status::from),
+        Command::RenameProperty {
+            graph,
+            old,
+            new,
+            dry_run,
+        } => rename_property_command(graph, old, new, *dry_run).map(Status::from),
+        Command::RenamePropertyValue {
+            graph,
+            key,
+            old,
+            new,
+            dry_run,
+        } => rename_property_value_command(graph, key, old, new, *dry_run).map(Status::from),
+        Command::NormalizePropertyKeys {
+            graph,
+            style,
+            dry_run,
+        } => normalize_property_keys_command(graph, *style, *dry_run).map(Status::from),
+        Command::Merge {
+            graph,
+            source,
+            target,
+            dry_run,
+        } => merge_command(graph, source, target, *dry_run).map(Status::from),
+        Command::Split {
+            graph,
+            page,
+            level,
+            embed,
+            dry_run,
+        } => split_command(graph, page, *level, *embed, *dry_run).map(Status::from),
+        Command::InlineRefs {
+            graph,
+            block,
+            page,
+            keep_link,
+            dry_run,
+        } => {
+            let target = match (block, page) {
+                (Some(uuid), _) => InlineTarget::Block(uuid.clone()),
+                (None, page) => InlineTarget::Page(page.clone().unwrap_or_default()),
+            };
+            inline_refs_command(graph, &target, *keep_link, *dry_run).map(Status::from)
+        }
+        Command::Toc {
+            graph,
+            all,
+            min_headings,
+            dry_run,
+        } => {
+            let options = TocOptions {
+                all: *all,
+                min_headings: *min_headings,
+            };
+            toc(graph, &options, *dry_run).map(Status::from)
+        }
+        Command::LinkDates {
+            graph,
+            all_pages,
+            language,
+            today,
+            dry_run,
+        } => {
+            let options = DateLinkOptions {
+                locale: **language,
+                all_pages: *all_pages,
+                today: today.unwrap_or_else(|| Local::now().date_naive()),
+            };
+            link_dates_command(graph, &options, *dry_run).map(Status::from)
+        }
+        Command::BackfillTimestamps {
+            graph,
+            git,
+            dry_run,
+        } => backfill_timestamps_command(graph, *git, *dry_run).map(Status::from),
+        Command::DbVersion { graph, dry_run } => db_version(graph, *dry_run).map(Status::from),
+        Command::MigrateOrg { graph, delete } => migrate_org(graph, *delete).map(Status::from),
+        Command::Import { source } => import(source).map(Status::from),
+        #[cfg(feature = "index-sqlite")]
+        Command::Query {
+            graph,
+            sql,
+            database,
+            format,
+        } => query(graph, sql, database.as_deref(), *format).map(Status::from),
+        Command::Export { format } => export(format).map(Status::from),
+        Command::Cards { action } => cards(action).map(Status::from),
+        Command::Tasks { action } => tasks(action).map(Status::from),
+    };
+    match result {
+        Ok(status) => status.into(),
+        Err(error) => {
+            eprintln!("Error: {:#}", error);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Flashcards: blocks tagged `#card`, with their children as the answer
+
+use crate::graph::Graph;
+use crate::outline::Block;
+use crate::properties::{split_property, Value};
+use crate::refs::{page_links, tag_refs};
+use crate::{Error, Result};
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:^|\s)(?:#?\[\[card\]\]|#card)(?:\s|$)").unwrap());
+static CLOZE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{cloze\s+(.*?)\s*\}\}").unwrap());
+
+/// A block tagged `#card`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Flashcard {
+    /// File of the page with the card
+    pub path: PathBuf,
+    /// Line of the block, starting at 1
+    pub line: usize,
+    /// Name of the page with the card
+    pub page: String,
+    /// Text of the block, without the tag and properties
+    pub question: String,
+    /// The children of the block as a Markdown list, empty for cloze cards without children
+    pub answer: String,
+    /// The hidden parts of the question, from `{{cloze ...}}` macros
+    pub clozes: Vec<String>,
+    /// The UUID of the block's `id::` property, if it has one
+    pub id: Option<String>,
+}
+
+/// True if a block is tagged `#card`, `#[[card]]`, `[[card]]` or has `card` in `tags::`
+fn is_card(block: &Block) -> bool {
+    let line = block.first_line();
+    let tagged = page_links(line)
+        .into_iter()
+        .chain(tag_refs(line))
+        .any(|link| link.name.eq_ignore_ascii_case("card"));
+    tagged
+        || matches!(block.properties().value("tags"),
+            Some(Value::Refs(names)) if names.iter().any(|name| name.eq_ignore_ascii_case("card")))
+}
+
+/// The lines of a block without its properties and drawers
+fn text_lines(block: &Block) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut in_drawer = false;
+    for line in block.content.split('\n') {
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+        } else if trimmed.len() > 2 && trimmed.starts_with(':') && trimmed.ends_with(':') {
+            in_drawer = true;
+        } else if split_property(line).is_none() {
+            lines.push(line);
+        }
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Appends blocks as a Markdown list
+fn answer_lines(blocks: &[Block], level: usize, output: &mut Vec<String>) {
+    let indent = "  ".repeat(level);
+    for block in blocks {
+        let lines = text_lines(block);
+        let mut lines = lines.iter();
+        if let Some(first) = lines.next() {
+            output.push(format!("{}- {}", indent, first.trim()));
+        }
+        for line in lines {
+            output.push(format!("{}  {}", indent, line.trim()));
+        }
+        answer_lines(&block.children, level + 1, output);
+    }
+}
+
+/// Finds the flashcards of a graph, in the order of its pages.
+///
+/// A card is a block tagged `#card` (as a tag, a link or in its `tags::` property); the block is
+/// the question, and its children are the answer. The `{{cloze ...}}` macros of the question are
+/// its clozes.
+pub fn extract_flashcards(graph: &Graph) -> Vec<Flashcard> {
+    let mut cards = Vec::new();
+    for page in &graph.pages {
+        for block in page.outline.iter().filter(|block| is_card(block)) {
+            let question = text_lines(block)
+                .iter()
+                .map(|line| TAG_RE.replace_all(line.trim(), " ").trim().to_string())
+                .collect::<Vec<String>>()
+                .join("\n");
+            let mut answer = Vec::new();
+            answer_lines(&block.children, 0, &mut answer);
+            cards.push(Flashcard {
+                path: page.path.clone(),
+                line: block.line,
+                page: page.name.clone(),
+                clozes: CLOZE_RE
+                    .captures_iter(&question)
+                    .map(|captures| captures[1].to_string())
+                    .collect(),
+                question,
+                answer: answer.join("\n"),
+                id: block
+                    .properties()
+                    .get("id")
+                    .map(|id| id.raw.trim().to_string()),
+            });
+        }
+    }
+    cards
+}
+
+/// How flashcards are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CardFormat {
+    /// A JSON array of [Flashcard] objects
+    #[default]
+    Json,
+    /// CSV with a header line: `question,answer,page,path,line`
+    Csv,
+    /// Tab-separated values for Anki's "Import File", with cloze deletions as `{{c1::text}}`,
+    /// HTML line breaks, and the page as a tag
+    AnkiTsv,
+}
+
+impl FromStr for CardFormat {
+    type Err = Error;
+
+    /// Parses `json`, `csv` and `anki-tsv` (or `anki`)
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Ok(CardFormat::Json),
+            "csv" => Ok(CardFormat::Csv),
+            "anki-tsv" | "anki" => Ok(CardFormat::AnkiTsv),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected json, csv or anki-tsv", name),
+            )),
+        }
+    }
+}
+
+/// A CSV field, quoted if needed
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// An Anki field: HTML, with line breaks as `<br>`
+fn anki_field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+impl CardFormat {
+    /// Writes flashcards in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::cards::{CardFormat, Flashcard};
+    /// let card = Flashcard {
+    ///     path: "pages/Rust.md".into(),
+    ///     line: 1,
+    ///     page: "Rust".to_string(),
+    ///     question: "A {{cloze borrow}} ends at last use, with {{cloze NLL}}".to_string(),
+    ///     answer: "- since 2018, \"NLL\"".to_string(),
+    ///     clozes: vec!["borrow".to_string(), "NLL".to_string()],
+    ///     id: None,
+    /// };
+    /// assert_eq!(CardFormat::Csv.render(&[card.clone()]),
+    ///     "question,answer,page,path,line\n\"A {{cloze borrow}} ends at last use, with {{cloze NLL}}\",\"- since 2018, \"\"NLL\"\"\",Rust,pages/Rust.md,1\n");
+    /// assert_eq!("anki".parse::<CardFormat>().unwrap().render(&[card]),
+    ///     "#separator:tab\n#html:true\n#tags column:3\nA {{c1::borrow}} ends at last use, with {{c2::NLL}}\t- since 2018, \"NLL\"\tRust\n");
+    /// ```
+    pub fn render(&self, cards: &[Flashcard]) -> String {
+        match self {
+            CardFormat::Json => serde_json::to_string_pretty(cards).unwrap_or_default() + "\n",
+            CardFormat::Csv => {
+                let mut output = String::from("question,answer,page,path,line\n");
+                for card in cards {
+                    let path = card.path.to_string_lossy();
+                    let fields = [&card.question, &card.answer, &card.page, path.as_ref()];
+                    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                    output.push_str(&format!("{},{}\n", fields.join(","), card.line));
+                }
+                output
+            }
+            CardFormat::AnkiTsv => {
+                let mut output = String::from("#separator:tab\n#html:true\n#tags column:3\n");
+                for card in cards {
+                    let mut number = 0;
+                    let front = CLOZE_RE.replace_all(&card.question, |captures: &Captures| {
+                        number += 1;
+                        format!("{{{{c{}::{}}}}}", number, &captures[1])
+                    });
+                    output.push_str(&format!(
+                        "{}\t{}\t{}\n",
+                        anki_field(&front),
+                        anki_field(&card.answer),
+                        card.page.replace(char::is_whitespace, "_")
+                    ));
+                }
+                output
+            }
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! Clean up the pages of web clippers, full of the HTML of the clipped page that Logseq shows as
+//! it is: `<div>` wrappers, `<br>`, `&amp;`, and flat Markdown with headings that start at `###`.
+
+use crate::error;
+use crate::frontmatter::split_front_matter;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::markdown::flat_markdown_to_outline;
+use crate::properties::split_property;
+use crate::rename::{write_edits, FileEdit};
+use crate::Result;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+/// The name of an HTML entity, after its `&`
+const ENTITY_NAME: &str = r"(#[0-9]+|#[xX][0-9a-fA-F]+|amp|lt|gt|quot|apos|nbsp);";
+static ENTITY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!("&{}", ENTITY_NAME)).unwrap());
+static ENTITY_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!("^{}", ENTITY_NAME)).unwrap());
+static RAW_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<(script|style)\b[^>]*>").unwrap());
+static CODE_SPAN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`[^`]*`").unwrap());
+static COMMENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static LINK_TAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)<a\s[^>]*?\bhref\s*=\s*["']([^"']*)["'][^>]*>(.*?)</a\s*>"#).unwrap()
+});
+static HEADING_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<(/?)h([1-6])\b[^>]*>").unwrap());
+static BREAK_TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>").unwrap());
+static ITEM_TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<(/?)li\b[^>]*>").unwrap());
+static BLOCK_TAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?i)</?(?:p|div|section|article|header|footer|main|aside|nav|figure|figcaption",
+        r"|blockquote|ul|ol|dl|dt|dd|table|thead|tbody|tfoot|tr|hr)\b[^>]*>"
+    ))
+    .unwrap()
+});
+static STRONG_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)</?(?:b|strong)\s*>").unwrap());
+static EMPHASIS_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)</?(?:i|em)\s*>").unwrap());
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)</?[a-z][a-z0-9-]*(?:\s[^<>]*)?/?>").unwrap());
+static BULLET_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\s*)[-*+](?:\s+|$)").unwrap());
+static HEADING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^( {0,3})(#{1,6})(\s)").unwrap());
+
+/// The character of an HTML entity, from its name
+fn entity_char(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => match entity[1..].strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => entity[1..].parse().ok(),
+        }
+        .and_then(char::from_u32),
+    }
+}
+
+/// Decodes the HTML entities of a text, like `&amp;` and `&#39;`
+#[cfg(feature = "network")]
+pub(crate) fn decode_entities(text: &str) -> String {
+    ENTITY_RE
+        .replace_all(text, |captures: &Captures| {
+            entity_char(&captures[1]).map_or_else(|| captures[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Decodes the HTML entities of the text of a clipping, like [decode_entities], but keeps the
+/// ones that would turn the text into HTML: a `<` before a letter, `/` or `!` would start a tag,
+/// and a `&` before an entity name would start an entity. Cleaning the text again then leaves
+/// it as it is.
+fn decode_text_entities(text: &str) -> String {
+    ENTITY_RE
+        .replace_all(text, |captures: &Captures| {
+            let entity = captures.get(0).unwrap();
+            let next = &text[entity.end()..];
+            match entity_char(&captures[1]) {
+                Some('<')
+                    if next
+                        .starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') =>
+                {
+                    entity.as_str().to_string()
+                }
+                Some('&') if ENTITY_NAME_RE.is_match(next) => entity.as_str().to_string(),
+                code => code.map_or_else(|| entity.as_str().to_string(), String::from),
+            }
+        })
+        .into_owned()
+}
+
+/// Removes the `<script>` and `<style>` elements of a line, with their contents. `open` is the
+/// element an earlier line left open, whose contents start the line after its indentation, and
+/// becomes the one this line leaves open.
+fn strip_raw_elements<'a>(line: &'a str, open: &mut Option<&'static str>) -> Cow<'a, str> {
+    if open.is_none() && !RAW_TAG_RE.is_match(line) {
+        return Cow::Borrowed(line);
+    }
+    let rest = line.trim_start();
+    let mut kept = line[..line.len() - rest.len()].to_string();
+    let mut rest = rest;
+    loop {
+        match *open {
+            Some(name) => {
+                let Some(start) = rest.to_ascii_lowercase().find(&format!("</{}", name)) else {
+                    return Cow::Owned(kept);
+                };
+                let close = &rest[start..];
+                rest = &close[close.find('>').map_or(close.len(), |end| end + 1)..];
+                if kept.trim().is_empty() {
+                    rest = rest.trim_start();
+                }
+                *open = None;
+            }
+            None => {
+                let Some(captures) = RAW_TAG_RE.captures(rest) else {
+                    kept.push_str(rest);
+                    return Cow::Owned(kept);
+                };
+                let tag = captures.get(0).unwrap();
+                kept.push_str(&rest[..tag.start()]);
+                *open = Some(match captures[1].eq_ignore_ascii_case("script") {
+                    true => "script",
+                    false => "style",
+                });
+                rest = &rest[tag.end()..];
+            }
+        }
+    }
+}
+
+/// Markdown for the HTML of a text outside code spans, with a line break for each `<br>` and
+/// block tag
+fn html_to_markdown(text: &str) -> String {
+    let text = COMMENT_RE.replace_all(text, "");
+    let text = LINK_TAG_RE.replace_all(&text, |captures: &Captures| {
+        let label = TAG_RE.replace_all(&captures[2], "");
+        match label.trim() {
+            "" => captures[1].to_string(),
+            label => format!("[{}]({})", label, &captures[1]),
+        }
+    });
+    let text = HEADING_TAG_RE.replace_all(&text, |captures: &Captures| match &captures[1] {
+        "/" => "\n".to_string(),
+        _ => format!("\n{} ", "#".repeat(captures[2].parse().unwrap_or(1))),
+    });
+    let text = BREAK_TAG_RE.replace_all(&text, "\n");
+    let text = ITEM_TAG_RE.replace_all(&text, |captures: &Captures| match &captures[1] {
+        "/" => "\n",
+        _ => "\n- ",
+    });
+    let text = BLOCK_TAG_RE.replace_all(&text, "\n");
+    let text = STRONG_TAG_RE.replace_all(&text, "**");
+    let text = EMPHASIS_TAG_RE.replace_all(&text, "_");
+    let text = TAG_RE.replace_all(&text, "");
+    decode_text_entities(&text)
+}
+
+/// The lines of the Markdown of a line with HTML, leaving its code spans alone. Lines that were
+/// only tags, and headings or list items without text, are left out.
+fn clean_line(line: &str) -> Vec<String> {
+    let mut markdown = String::new();
+    let mut start = 0;
+    for span in CODE_SPAN_RE.find_iter(line) {
+        markdown.push_str(&html_to_markdown(&line[start..span.start()]));
+        markdown.push_str(span.as_str());
+        start = span.end();
+    }
+    markdown.push_str(&html_to_markdown(&line[start..]));
+    markdown
+        .split('\n')
+        .map(|piece| piece.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|piece| !piece.trim_start_matches(['#', '-']).is_empty())
+        .collect()
+}
+
+/// True if a line starts or ends a code block, after its bullet
+fn is_fence(line: &str) -> bool {
+    let bare = match BULLET_RE.find(line) {
+        Some(bullet) => &line[bullet.end()..],
+        None => line,
+    };
+    let bare = bare.trim_start();
+    ["```", "~~~", "#+BEGIN_", "#+END_"]
+        .iter()
+        .any(|fence| bare.starts_with(fence))
+}
+
+/// Replaces the HTML of the lines of a page, outside code, with Markdown. The lines a `<br>` or
+/// a block tag starts become new blocks after the block of the line, in an outline, or lines of
+/// their own in flat Markdown, where the lines that were only tags become blank lines. The
+/// contents of `<script>` and `<style>` elements are left out.
+fn clean_lines(lines: &[&str], outline: bool) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    let mut in_code = false;
+    // The `<script>` or `<style>` element the line is in
+    let mut raw = None;
+    // The indentation of the block the line is in
+    let mut indent = String::new();
+    for &line in lines {
+        let stripped = match in_code {
+            true => Cow::Borrowed(line),
+            false => strip_raw_elements(line, &mut raw),
+        };
+        let changed = matches!(stripped, Cow::Owned(_));
+        if changed && stripped.trim().is_empty() {
+            if !outline {
+                cleaned.push(String::new());
+            }
+            continue;
+        }
+        let line = stripped.as_ref();
+        let fence = is_fence(line);
+        if in_code || fence || !(changed || line.contains(['<', '&'])) {
+            if let Some(bullet) = BULLET_RE.captures(line).filter(|_| !in_code) {
+                indent = bullet[1].to_string();
+            }
+            in_code ^= fence;
+            cleaned.push(line.to_string());
+            continue;
+        }
+        let bullet = BULLET_RE.captures(line);
+        let (prefix, rest) = match &bullet {
+            Some(bullet) => {
+                indent = bullet[1].to_string();
+                (format!("{}- ", indent), &line[bullet[0].len()..])
+            }
+            None => {
+                let text = line.trim_start();
+                (line[..line.len() - text.len()].to_string(), text)
+            }
+        };
+        let pieces = clean_line(rest);
+        let Some((first, others)) = pieces.split_first() else {
+            match (&bullet, outline) {
+                (Some(_), _) => cleaned.push(prefix.trim_end().to_string()),
+                (None, false) => cleaned.push(String::new()),
+                (None, true) => {}
+            }
+            continue;
+        };
+        let block = |piece: &str| format!("{}- {}", indent, piece.trim_start_matches("- "));
+        cleaned.push(match &bullet {
+            Some(_) => format!("{}{}", prefix, first.trim_start_matches("- ")),
+            None => format!("{}{}", prefix, first),
+        });
+        for piece in others {
+            cleaned.push(match (&bullet, outline) {
+                (None, false) => format!("{}{}", prefix, piece),
+                _ => block(piece),
+            });
+        }
+    }
+    cleaned
+}
+
+/// Renumbers the Markdown headings of a document so that the highest one is `#` and levels
+/// follow each other: a clipping with `###` and `#####` headings gets `#` and `##` ones.
+/// Headings in code blocks are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::clippings::promote_headings;
+/// assert_eq!(
+///     promote_headings("### Title\nText\n##### Part\n```\n### code\n```\n"),
+///     "# Title\nText\n## Part\n```\n### code\n```\n"
+/// );
+/// assert_eq!(promote_headings("# Title\n## Part\n"), "# Title\n## Part\n");
+/// ```
+pub fn promote_headings(markdown: &str) -> Cow<'_, str> {
+    let mut in_code = false;
+    let mut headings: Vec<(usize, usize)> = Vec::new();
+    let lines: Vec<&str> = markdown.split('\n').collect();
+    for (index, line) in lines.iter().enumerate() {
+        if is_fence(line) {
+            in_code = !in_code;
+        } else if let Some(captures) = HEADING_RE.captures(line).filter(|_| !in_code) {
+            headings.push((index, captures[2].len()));
+        }
+    }
+    let mut levels: Vec<usize> = headings.iter().map(|&(_, level)| level).collect();
+    levels.sort_unstable();
+    levels.dedup();
+    let level_of = |level: usize| levels.iter().position(|&l| l == level).unwrap_or(0) + 1;
+    if headings.iter().all(|&(_, level)| level_of(level) == level) {
+        return Cow::Borrowed(markdown);
+    }
+    let mut promoted: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    for (index, level) in headings {
+        promoted[index] = HEADING_RE
+            .replace(lines[index], |captures: &Captures| {
+                format!(
+                    "{}{}{}",
+                    &captures[1],
+                    "#".repeat(level_of(level)),
+                    &captures[3]
+                )
+            })
+            .into_owned();
+    }
+    Cow::Owned(promoted.join("\n"))
+}
+
+/// The number of lines at the start of a page that stay as they are: its front matter, and the
+/// page properties and blank lines after it
+fn head_lines(lines: &[&str], text: &str) -> usize {
+    let mut count = match split_front_matter(text) {
+        Some((yaml, _)) => yaml.split('\n').count() + 2,
+        None => 0,
+    };
+    while lines
+        .get(count)
+        .is_some_and(|line| line.trim().is_empty() || split_property(line).is_some())
+    {
+        count += 1;
+    }
+    count
+}
+
+/// Cleans up a page made by a web clipper: its HTML tags are removed, or turned into Markdown
+/// when they have one, like headings, links, `<li>`, `<strong>` and `<em>`, and its HTML
+/// entities are decoded, except the ones that would make a tag of the text, like `&lt;b&gt;`.
+/// `<script>` and `<style>` elements are removed with their contents. `<br>` and the tags of
+/// blocks, like `<p>` and `<div>`, start new blocks. Code blocks and code spans are left alone.
+///
+/// A page in flat Markdown is then folded into an outline with [flat_markdown_to_outline], with
+/// its headings promoted so that the highest is `#` (see [promote_headings]); its front matter
+/// and page properties are kept as they are. Returns the text unchanged if the page is clean.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::clippings::clean_clipping;
+/// assert_eq!(
+///     clean_clipping("<div><h3>Tips &amp; tricks</h3><p>First<br>Second</p></div>\n"),
+///     "- # Tips & tricks\n  - First\n  - Second\n"
+/// );
+/// assert_eq!(
+///     clean_clipping("- Read <a href=\"https://example.com\">this</a><br/>today\n  `<br>`\n"),
+///     "- Read [this](https://example.com)\n- today\n  `<br>`\n"
+/// );
+/// assert_eq!(clean_clipping("- Clean\n"), "- Clean\n");
+/// ```
+pub fn clean_clipping(text: &str) -> Cow<'_, str> {
+    preserving_line_endings_if_changed(text, |text| {
+        let lines: Vec<&str> = text.split('\n').collect();
+        let head = head_lines(&lines, text);
+        let body = &lines[head..];
+        if body.is_empty() {
+            return None;
+        }
+        let outline = body
+            .iter()
+            .find(|line| !line.trim().is_empty())
+            .is_none_or(|line| BULLET_RE.is_match(line));
+        let cleaned = clean_lines(body, outline).join("\n");
+        let cleaned = match outline {
+            true => cleaned,
+            false => flat_markdown_to_outline(&promote_headings(&cleaned)),
+        };
+        let mut page: Vec<&str> = lines[..head].to_vec();
+        page.extend(cleaned.split('\n'));
+        let page = page.join("\n");
+        (page != text).then_some(page)
+    })
+}
+
+/// Cleans up the web clippings of files, see [clean_clipping]; with `dry_run`, only plans the
+/// changes. Returns the files that change.
+pub fn clean_clipping_files(paths: &[PathBuf], dry_run: bool) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for path in paths {
+        let original = error::read_to_string(path)?;
+        if let Cow::Owned(updated) = clean_clipping(&original) {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,319 @@
+//! The settings of a graph, from its `logseq/config.edn` file
+
+use crate::edn::Edn;
+use crate::graph::Graph;
+use crate::query::{check_query_map, QueryContext};
+use crate::tasks::Workflow;
+use crate::{error, Result, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use std::path::{Path, PathBuf};
+
+/// Directories of a graph that Logseq keeps for itself and never reads pages from, relative to
+/// its root: the backups of files changed outside Logseq, and deleted pages
+pub const LOGSEQ_DIRECTORIES: &[&str] = &["logseq/bak", "logseq/.recycle", ".recycle"];
+
+/// The format of new pages and journals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageFormat {
+    /// Markdown
+    Markdown,
+    /// Org-mode
+    Org,
+}
+
+/// The settings of a graph that change how its files should be checked.
+/// Settings missing from `config.edn` get Logseq's defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphConfig {
+    /// `:preferred-format`
+    pub preferred_format: PageFormat,
+    /// `:preferred-workflow`
+    pub preferred_workflow: Workflow,
+    /// `:journal/page-title-format`, e.g. `MMM do, yyyy`
+    pub journal_page_title_format: String,
+    /// `:journal/file-name-format`, e.g. `yyyy_MM_dd`
+    pub journal_file_name_format: String,
+    /// `:journals-directory`
+    pub journals_directory: String,
+    /// `:pages-directory`
+    pub pages_directory: String,
+    /// `:hidden`: paths relative to the graph root that Logseq ignores
+    pub hidden: Vec<String>,
+    /// `:default-templates {:journals ...}`: the template of new journals, if any
+    pub default_journal_template: Option<String>,
+    /// `:property-pages/enabled?`: whether properties create pages
+    pub property_pages_enabled: bool,
+    /// `:property-pages/excludelist`: properties that don't create pages
+    pub property_pages_excludelist: Vec<String>,
+    /// `:favorites`: names of the pages in the favorites of the sidebar
+    pub favorites: Vec<String>,
+    /// `:file/name-format`: `triple-lowbar` when `/` in page names becomes `___` in file
+    /// names, `legacy` for graphs created before Logseq 0.8.9
+    pub file_name_format: String,
+    /// `:feature/enable-timetracking?`: whether Logseq clocks tasks in their `:LOGBOOK:`
+    pub enable_timetracking: bool,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        GraphConfig {
+            preferred_format: PageFormat::Markdown,
+            preferred_workflow: Workflow::NowLater,
+            journal_page_title_format: "MMM do, yyyy".to_string(),
+            journal_file_name_format: "yyyy_MM_dd".to_string(),
+            journals_directory: SUBDIR_JOURNALS.to_string(),
+            pages_directory: SUBDIR_PAGES.to_string(),
+            hidden: Vec::new(),
+            default_journal_template: None,
+            property_pages_enabled: true,
+            property_pages_excludelist: Vec::new(),
+            favorites: Vec::new(),
+            file_name_format: "legacy".to_string(),
+            enable_timetracking: true,
+        }
+    }
+}
+
+/// The strings of a sequence of strings, keywords or symbols
+fn strings(value: &Edn) -> Vec<String> {
+    value
+        .as_seq()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|item| item.as_str().map(str::to_string))
+        .collect()
+}
+
+impl GraphConfig {
+    /// Parses the contents of a `config.edn` file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::config::{GraphConfig, PageFormat};
+    /// use logseq::tasks::Workflow;
+    /// let config = GraphConfig::parse(r#"{:preferred-format "Org"
+    ///  :preferred-workflow :todo
+    ///  :hidden ["/archive"]
+    ///  :default-templates {:journals "Daily"}}"#).unwrap();
+    /// assert_eq!(config.preferred_format, PageFormat::Org);
+    /// assert_eq!(config.preferred_workflow, Workflow::TodoDoing);
+    /// assert_eq!(config.hidden, vec!["/archive"]);
+    /// assert_eq!(config.default_journal_template.as_deref(), Some("Daily"));
+    /// assert_eq!(config.journal_file_name_format, "yyyy_MM_dd");
+    /// ```
+    pub fn parse(config_contents: &str) -> Result<Self> {
+        let edn = Edn::parse(config_contents)?;
+        let mut config = GraphConfig::default();
+        let text = |key: &str| edn.get(key).and_then(Edn::as_str).map(str::to_string);
+
+        if let Some(format) = text("preferred-format") {
+            if format.eq_ignore_ascii_case("org") {
+                config.preferred_format = PageFormat::Org;
+            }
+        }
+        if let Some(workflow) = text("preferred-workflow") {
+            if workflow.eq_ignore_ascii_case("todo") {
+                config.preferred_workflow = Workflow::TodoDoing;
+            }
+        }
+        if let Some(format) = text("journal/page-title-format") {
+            config.journal_page_title_format = format;
+        }
+        if let Some(format) = text("journal/file-name-format") {
+            config.journal_file_name_format = format;
+        }
+        if let Some(directory) = text("journals-directory") {
+            config.journals_directory = directory;
+        }
+        if let Some(directory) = text("pages-directory") {
+            config.pages_directory = directory;
+        }
+        if let Some(hidden) = edn.get("hidden") {
+            config.hidden = strings(hidden);
+        }
+        config.default_journal_template = edn
+            .get("default-templates")
+            .and_then(|templates| templates.get("journals"))
+            .and_then(Edn::as_str)
+            .filter(|template| !template.is_empty())
+            .map(str::to_string);
+        if let Some(enabled) = edn.get("property-pages/enabled?").and_then(Edn::as_bool) {
+            config.property_pages_enabled = enabled;
+        }
+        if let Some(excluded) = edn.get("property-pages/excludelist") {
+            config.property_pages_excludelist = strings(excluded);
+        }
+        if let Some(favorites) = edn.get("favorites") {
+            config.favorites = strings(favorites);
+        }
+        if let Some(format) = text("file/name-format") {
+            config.file_name_format = format;
+        }
+        if let Some(enabled) = edn
+            .get("feature/enable-timetracking?")
+            .and_then(Edn::as_bool)
+        {
+            config.enable_timetracking = enabled;
+        }
+        Ok(config)
+    }
+
+    /// True if Logseq ignores a file or directory of the graph at `root`: it is under one of the
+    /// `:hidden` paths, or under one of the [LOGSEQ_DIRECTORIES]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::config::GraphConfig;
+    /// use std::path::Path;
+    /// let config = GraphConfig::parse(r#"{:hidden ["/pages/drafts" "archive/"]}"#).unwrap();
+    /// let root = Path::new("graph");
+    /// assert!(config.is_hidden(root, Path::new("graph/pages/drafts/idea.md")));
+    /// assert!(config.is_hidden(root, Path::new("graph/archive/2020.md")));
+    /// assert!(config.is_hidden(root, Path::new("graph/logseq/bak/pages/a/2024.md")));
+    /// assert!(!config.is_hidden(root, Path::new("graph/pages/drafts-old.md")));
+    /// ```
+    pub fn is_hidden(&self, root: &Path, path: &Path) -> bool {
+        let root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let Ok(relative) = absolute.strip_prefix(&root) else {
+            return false;
+        };
+        self.hidden
+            .iter()
+            .map(String::as_str)
+            .chain(LOGSEQ_DIRECTORIES.iter().copied())
+            .map(|hidden| hidden.trim_matches('/'))
+            .any(|hidden| !hidden.is_empty() && relative.starts_with(hidden))
+    }
+
+    /// Reads the `logseq/config.edn` file of a graph; a graph without one gets the defaults
+    pub fn read(root: &Path) -> Result<Self> {
+        let config_path = config_path(root);
+        if !config_path.is_file() {
+            return Ok(GraphConfig::default());
+        }
+        let contents = error::read_to_string(&config_path)?;
+        GraphConfig::parse(&contents).map_err(|error| error.with_path(&config_path))
+    }
+}
+
+/// The `logseq/config.edn` file of a graph
+pub fn config_path(root: &Path) -> PathBuf {
+    root.join("logseq").join("config.edn")
+}
+
+/// What's wrong with an entry of `config.edn`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigProblem {
+    /// A page of `:favorites` that doesn't exist, usually left behind by a rename
+    MissingFavorite(String),
+    /// A query of `:default-queries` that doesn't parse, or refers to missing pages or properties
+    InvalidDefaultQuery {
+        /// The `:title` of the query, empty if it has none
+        title: String,
+        /// What's wrong with it
+        message: String,
+    },
+}
+
+/// A stale or broken entry of `config.edn`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// The `config.edn` file
+    pub path: PathBuf,
+    /// Line of the entry, starting at 1
+    pub line: usize,
+    /// What's wrong with it
+    pub problem: ConfigProblem,
+}
+
+/// The text of a query title: a string, or the first string of hiccup like `[:h3 "Today"]`
+fn title_text(value: &Edn) -> Option<String> {
+    match value {
+        Edn::String(text) => Some(text.clone()),
+        _ => value.as_seq()?.iter().find_map(title_text),
+    }
+}
+
+/// Finds lines in the text of a file, each search starting after the previous match
+struct LineFinder<'a> {
+    text: &'a str,
+    offset: usize,
+}
+
+impl LineFinder<'_> {
+    /// The line of the next `needle`, or of the last match if there is none
+    fn find(&mut self, needle: &str) -> usize {
+        if let Some(found) = self.text[self.offset..].find(needle) {
+            self.offset += found + needle.len();
+        }
+        self.text[..self.offset].matches('\n').count() + 1
+    }
+}
+
+/// Checks the `:favorites` and `:default-queries` of the `config.edn` of a graph against its
+/// pages: favorites must exist, and default queries must parse and refer to existing pages and
+/// properties (see [check_query_map]). A graph without a `config.edn` has no issues.
+pub fn check_config(graph: &Graph) -> Result<Vec<ConfigIssue>> {
+    let path = config_path(&graph.root);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let text = error::read_to_string(&path)?;
+    let edn = Edn::parse(&text).map_err(|error| error.with_path(&path))?;
+    let names = graph.page_names();
+    let mut issues = Vec::new();
+    let mut issue = |line, problem| {
+        issues.push(ConfigIssue {
+            path: path.clone(),
+            line,
+            problem,
+        })
+    };
+
+    let mut lines = LineFinder {
+        text: &text,
+        offset: 0,
+    };
+    if let Some(favorites) = edn.get("favorites") {
+        lines.find(":favorites");
+        for favorite in strings(favorites) {
+            let line = lines.find(&format!("\"{}\"", favorite));
+            if !names.contains(&favorite.to_lowercase()) {
+                issue(line, ConfigProblem::MissingFavorite(favorite));
+            }
+        }
+    }
+
+    let mut lines = LineFinder {
+        text: &text,
+        offset: 0,
+    };
+    let queries = edn
+        .get("default-queries")
+        .and_then(|queries| queries.get("journals"))
+        .and_then(Edn::as_seq)
+        .unwrap_or_default();
+    if !queries.is_empty() {
+        lines.find(":default-queries");
+    }
+    let context = QueryContext::from_graph(graph);
+    for query in queries {
+        let title = query.get("title").and_then(title_text).unwrap_or_default();
+        let line = match title.is_empty() {
+            true => lines.find(":query"),
+            false => lines.find(&format!("\"{}\"", title)),
+        };
+        for problem in check_query_map(query, Some(&context)) {
+            issue(
+                line,
+                ConfigProblem::InvalidDefaultQuery {
+                    title: title.clone(),
+                    message: problem.message,
+                },
+            );
+        }
+    }
+    Ok(issues)
+}
@@ -0,0 +1,303 @@
+//! Turn the dates written in plain text in blocks, like `next Tuesday`, `2024/03/05` or
+//! `5 March`, into links to their journals, written in the `:journal/page-title-format` of the
+//! graph. Dates are read in English and in the language of a [Locale].
+
+use crate::config::GraphConfig;
+use crate::dates::{DateFormat, Locale, ENGLISH};
+use crate::graph::Graph;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::{apply_replacements, Replacement};
+use crate::outline::parse_outline;
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Result};
+use chrono::{Datelike, Days, NaiveDate};
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+/// Links, references, timestamps, URLs and tags, where dates are left alone
+static LINKED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[.*?\]\]|\(\(.*?\)\)|\[[^\]]*\]\([^)]*\)|<[^>]*>|https?://\S+|#\S+").unwrap()
+});
+
+/// An alternation of names, longest first so that `Jan` doesn't hide `January`
+fn alternation<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    let mut names: Vec<String> = names.map(str::to_lowercase).collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    names.dedup();
+    let escaped: Vec<String> = names.iter().map(|name| regex::escape(name)).collect();
+    escaped.join("|")
+}
+
+/// The patterns of a way to write the next or last weekday, like `next {}`
+fn weekday_patterns(patterns: &[&str], weekdays: &str) -> String {
+    let patterns: Vec<String> = patterns
+        .iter()
+        .map(|pattern| {
+            let words: Vec<String> = pattern.split("{}").map(regex::escape).collect();
+            words
+                .join(&format!("(?:{})", weekdays))
+                .replace(' ', r"\s+")
+        })
+        .collect();
+    patterns.join("|")
+}
+
+/// Finds the dates written in plain text in a language, and writes them as journal links.
+/// It reads:
+///
+/// - `2024-03-05`, `2024/03/05` and `2024.03.05`
+/// - a day and a month name, abbreviated or not: `5 March`, `5th Mar 2024`, `March 5, 2024`,
+///   `5. März` or `5 de marzo`; without a year, the year of the reference date
+/// - `today`, `tomorrow` and `yesterday` from the reference date
+/// - the next and last weekday strictly after or before the reference date, like
+///   `next Tuesday` or `mardi prochain`
+pub struct DateLinker {
+    dates: Regex,
+    weekday: Regex,
+    /// Month numbers by lowercase name
+    months: HashMap<String, u32>,
+    /// Days from Monday by lowercase weekday name
+    weekdays: HashMap<String, u64>,
+    /// Days from the reference date by lowercase word, like `tomorrow`
+    relative_days: HashMap<String, i64>,
+    /// The format of the links
+    pub format: DateFormat,
+}
+
+impl DateLinker {
+    /// Reads dates in English and in a language, and writes links in a format
+    pub fn new(locale: &Locale, format: DateFormat) -> Self {
+        let locales = [locale, &ENGLISH];
+        let mut months = HashMap::new();
+        let mut weekdays = HashMap::new();
+        let mut relative_days = HashMap::new();
+        for locale in locales.iter().rev() {
+            for (index, name) in locale.months.iter().chain(&locale.short_months).enumerate() {
+                months.insert(name.to_lowercase(), index as u32 % 12 + 1);
+            }
+            for (index, name) in locale.weekdays.iter().enumerate() {
+                weekdays.insert(name.to_lowercase(), index as u64);
+            }
+            for (word, offset) in locale.relative_days.iter().zip([0, 1, -1]) {
+                relative_days.insert(word.to_lowercase(), offset);
+            }
+        }
+        let month_names = alternation(months.keys().map(String::as_str));
+        let weekday_names = alternation(weekdays.keys().map(String::as_str));
+        let day_words = alternation(relative_days.keys().map(String::as_str));
+        let next: Vec<&str> = locales
+            .iter()
+            .flat_map(|l| l.next_weekday)
+            .copied()
+            .collect();
+        let last: Vec<&str> = locales
+            .iter()
+            .flat_map(|l| l.last_weekday)
+            .copied()
+            .collect();
+        let dates = Regex::new(&format!(
+            concat!(
+                r"(?i)\b(?:(?P<year>\d{{4}})(?P<sep>[-/.])(?P<month>\d{{1,2}})(?P<sep2>[-/.])(?P<day>\d{{1,2}})",
+                r"|(?P<dm_day>\d{{1,2}})(?:st|nd|rd|th|er|º|\.)?\s+(?:de\s+)?(?P<dm_month>{months})\b\.?",
+                r"(?:,?\s+(?:de\s+)?(?P<dm_year>\d{{4}}))?",
+                r"|(?P<md_month>{months})\b\.?\s+(?P<md_day>\d{{1,2}})(?:st|nd|rd|th)?\b(?:,?\s+(?P<md_year>\d{{4}}))?",
+                r"|(?P<next>{next})|(?P<last>{last})|(?P<relative>{days}))\b"
+            ),
+            months = month_names,
+            next = weekday_patterns(&next, &weekday_names),
+            last = weekday_patterns(&last, &weekday_names),
+            days = day_words,
+        ))
+        .unwrap();
+        let weekday = Regex::new(&format!(r"(?i)\b(?:{})\b", weekday_names)).unwrap();
+        DateLinker {
+            dates,
+            weekday,
+            months,
+            weekdays,
+            relative_days,
+            format,
+        }
+    }
+
+    /// The date of a match of the dates regex
+    fn date_of(&self, captures: &Captures, reference: NaiveDate) -> Option<NaiveDate> {
+        let number = |name: &str| captures.name(name)?.as_str().parse::<u32>().ok();
+        let month = |name: &str| {
+            let text = captures.name(name)?.as_str().to_lowercase();
+            self.months.get(&text).copied()
+        };
+        if let Some(year) = number("year") {
+            if captures["sep"] != captures["sep2"] {
+                return None;
+            }
+            return NaiveDate::from_ymd_opt(year as i32, number("month")?, number("day")?);
+        }
+        if let Some(day) = number("dm_day") {
+            let year = number("dm_year").map_or(reference.year(), |year| year as i32);
+            return NaiveDate::from_ymd_opt(year, month("dm_month")?, day);
+        }
+        if let Some(day) = number("md_day") {
+            let year = number("md_year").map_or(reference.year(), |year| year as i32);
+            return NaiveDate::from_ymd_opt(year, month("md_month")?, day);
+        }
+        if let Some(word) = captures.name("relative") {
+            let offset = *self.relative_days.get(&word.as_str().to_lowercase())?;
+            return match offset {
+                0.. => reference.checked_add_days(Days::new(offset as u64)),
+                _ => reference.checked_sub_days(Days::new(offset.unsigned_abs())),
+            };
+        }
+        let (found, forward) = match (captures.name("next"), captures.name("last")) {
+            (Some(found), _) => (found, true),
+            (_, Some(found)) => (found, false),
+            _ => return None,
+        };
+        let name = self.weekday.find(found.as_str())?.as_str().to_lowercase();
+        let weekday = *self.weekdays.get(&name)?;
+        let current = reference.weekday().num_days_from_monday() as u64;
+        match forward {
+            true => reference.checked_add_days(Days::new((weekday + 6 - current) % 7 + 1)),
+            false => reference.checked_sub_days(Days::new((current + 6 - weekday) % 7 + 1)),
+        }
+    }
+
+    /// The dates written in plain text in a line, with their byte range, from a reference date
+    /// for relative dates and dates without a year. Dates in code, links, references,
+    /// timestamps, URLs and tags are left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::date_links::DateLinker;
+    /// use logseq::dates::{DateFormat, FRENCH};
+    /// let linker = DateLinker::new(&FRENCH, DateFormat::default());
+    /// let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    /// // A Wednesday
+    /// let reference = date(3, 6);
+    /// let found = linker.find("Call next Tuesday, then mardi prochain or 2024/03/05", reference);
+    /// assert_eq!(found, vec![(5..17, date(3, 12)), (24..38, date(3, 12)), (42..52, date(3, 5))]);
+    /// assert_eq!(linker.find("Le 5 mars et hier", reference), vec![(3..9, date(3, 5)), (13..17, date(3, 5))]);
+    /// assert_eq!(linker.find("`2024-03-05` [[2024-03-05]] 2024-03/05", reference), vec![]);
+    /// ```
+    pub fn find(&self, line: &str, reference: NaiveDate) -> Vec<(Range<usize>, NaiveDate)> {
+        let mut searchable = blank_code_spans(line);
+        searchable = LINKED_RE
+            .replace_all(&searchable, |captures: &Captures| {
+                " ".repeat(captures[0].len())
+            })
+            .into_owned();
+        self.dates
+            .captures_iter(&searchable)
+            .filter_map(|captures| {
+                let found = captures.get(0)?;
+                let date = self.date_of(&captures, reference)?;
+                Some((found.range(), date))
+            })
+            .collect()
+    }
+
+    /// Links the dates written in plain text in the blocks of a page, see [DateLinker::find].
+    /// Dates in properties, and on a journal, the date of the journal itself, are left alone.
+    /// Returns the text unchanged if it has no such dates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::date_links::DateLinker;
+    /// use logseq::dates::{DateFormat, ENGLISH};
+    /// let linker = DateLinker::new(&ENGLISH, DateFormat::default());
+    /// let reference = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap();
+    /// let text = "- Today, plan the review for tomorrow\n  due:: 5 March\n- Back on march 1st\n";
+    /// assert_eq!(
+    ///     linker.link(text, reference, true),
+    ///     "- Today, plan the review for [[Mar 7th, 2024]]\n  due:: 5 March\n- Back on [[Mar 1st, 2024]]\n"
+    /// );
+    /// ```
+    pub fn link<'a>(&self, text: &'a str, reference: NaiveDate, journal: bool) -> Cow<'a, str> {
+        preserving_line_endings_if_changed(text, |text| {
+            let outline = parse_outline(text);
+            let mut replacements = Vec::new();
+            for block in outline.iter() {
+                for (line, content) in lines_outside_code(block.numbered_lines()) {
+                    if split_property(content.trim()).is_some() {
+                        continue;
+                    }
+                    for (range, date) in self.find(content, reference) {
+                        if journal && date == reference {
+                            continue;
+                        }
+                        replacements.push(Replacement {
+                            line,
+                            range,
+                            content_length: content.len(),
+                            text: format!("[[{}]]", self.format.format(date)),
+                        });
+                    }
+                }
+            }
+            apply_replacements(text, replacements)
+        })
+    }
+}
+
+/// Which pages get their dates linked, and how dates are read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateLinkOptions {
+    /// The language dates are written in, besides English
+    pub locale: Locale,
+    /// Also the pages that aren't journals, where relative dates are from `today`
+    pub all_pages: bool,
+    /// The date relative dates are from outside journals
+    pub today: NaiveDate,
+}
+
+/// Plans linking the dates written in plain text in the journals of a graph, or in all of its
+/// pages with [DateLinkOptions::all_pages], see [DateLinker::link]. On a journal, relative
+/// dates are from the date of the journal. Links are written in the
+/// `:journal/page-title-format` of the graph. Nothing is written; see [link_dates].
+pub fn plan_date_links(graph: &Graph, options: &DateLinkOptions) -> Result<Vec<FileEdit>> {
+    let config = GraphConfig::read(&graph.root)?;
+    let linker = DateLinker::new(
+        &options.locale,
+        DateFormat::new(&config.journal_page_title_format),
+    );
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        if page.journal_date.is_none() && !options.all_pages {
+            continue;
+        }
+        let reference = page.journal_date.unwrap_or(options.today);
+        let original = error::read_to_string(&page.path)?;
+        if let Cow::Owned(updated) = linker.link(&original, reference, page.journal_date.is_some())
+        {
+            edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Links the dates written in plain text in a graph, see [plan_date_links]; with `dry_run`,
+/// only plans the changes
+pub fn link_dates(
+    graph: &Graph,
+    options: &DateLinkOptions,
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_date_links(graph, options)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,678 @@
+//! Parse and write the dates of journal links, like `[[Jan 3rd, 2024]]` or `[[2024-01-03]]`,
+//! in the date format of `:journal/page-title-format` and with the month and weekday names of
+//! a language
+//!
+//! Logseq writes titles with [date-fns](https://date-fns.org/docs/format) formats, like
+//! `MMM do, yyyy` or `EEEE, dd.MM.yyyy`, see [DateFormat].
+
+use crate::{Error, Result};
+use chrono::{Datelike, FixedOffset, Local, NaiveDate, Utc};
+use std::str::FromStr;
+
+/// The default `:journal/page-title-format` of Logseq
+pub const DEFAULT_TITLE_FORMAT: &str = "MMM do, yyyy";
+
+/// The names of months and weekdays of a language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    /// Full month names, from January
+    pub months: [&'static str; 12],
+    /// Abbreviated month names, from January
+    pub short_months: [&'static str; 12],
+    /// Full weekday names, from Monday
+    pub weekdays: [&'static str; 7],
+    /// Abbreviated weekday names, from Monday
+    pub short_weekdays: [&'static str; 7],
+    /// The words for today, tomorrow and yesterday
+    pub relative_days: [&'static str; 3],
+    /// How the next occurrence of a weekday is written, `{}` standing for its full name
+    pub next_weekday: &'static [&'static str],
+    /// How the last occurrence of a weekday is written, `{}` standing for its full name
+    pub last_weekday: &'static [&'static str],
+}
+
+/// English, the language of Logseq's titles
+pub const ENGLISH: Locale = Locale {
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    short_weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    relative_days: ["today", "tomorrow", "yesterday"],
+    next_weekday: &["next {}"],
+    last_weekday: &["last {}"],
+};
+
+/// German
+pub const GERMAN: Locale = Locale {
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    short_weekdays: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    relative_days: ["heute", "morgen", "gestern"],
+    next_weekday: &["nächsten {}", "nächster {}", "kommenden {}"],
+    last_weekday: &["letzten {}", "letzter {}", "vergangenen {}"],
+};
+
+/// French
+pub const FRENCH: Locale = Locale {
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    short_months: [
+        "janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov", "déc",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    short_weekdays: ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+    relative_days: ["aujourd'hui", "demain", "hier"],
+    next_weekday: &["{} prochain"],
+    last_weekday: &["{} dernier"],
+};
+
+/// Spanish
+pub const SPANISH: Locale = Locale {
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    short_months: [
+        "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+    ],
+    weekdays: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    short_weekdays: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+    relative_days: ["hoy", "mañana", "ayer"],
+    next_weekday: &["próximo {}", "{} que viene"],
+    last_weekday: &["{} pasado"],
+};
+
+/// Portuguese
+pub const PORTUGUESE: Locale = Locale {
+    months: [
+        "janeiro",
+        "fevereiro",
+        "março",
+        "abril",
+        "maio",
+        "junho",
+        "julho",
+        "agosto",
+        "setembro",
+        "outubro",
+        "novembro",
+        "dezembro",
+    ],
+    short_months: [
+        "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+    ],
+    weekdays: [
+        "segunda-feira",
+        "terça-feira",
+        "quarta-feira",
+        "quinta-feira",
+        "sexta-feira",
+        "sábado",
+        "domingo",
+    ],
+    short_weekdays: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+    relative_days: ["hoje", "amanhã", "ontem"],
+    next_weekday: &["próxima {}", "próximo {}"],
+    last_weekday: &["{} passada", "{} passado"],
+};
+
+/// Italian
+pub const ITALIAN: Locale = Locale {
+    months: [
+        "gennaio",
+        "febbraio",
+        "marzo",
+        "aprile",
+        "maggio",
+        "giugno",
+        "luglio",
+        "agosto",
+        "settembre",
+        "ottobre",
+        "novembre",
+        "dicembre",
+    ],
+    short_months: [
+        "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+    ],
+    weekdays: [
+        "lunedì",
+        "martedì",
+        "mercoledì",
+        "giovedì",
+        "venerdì",
+        "sabato",
+        "domenica",
+    ],
+    short_weekdays: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+    relative_days: ["oggi", "domani", "ieri"],
+    next_weekday: &["{} prossimo", "prossimo {}"],
+    last_weekday: &["{} scorso", "scorso {}"],
+};
+
+/// Dutch
+pub const DUTCH: Locale = Locale {
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december",
+    ],
+    short_months: [
+        "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+    ],
+    weekdays: [
+        "maandag",
+        "dinsdag",
+        "woensdag",
+        "donderdag",
+        "vrijdag",
+        "zaterdag",
+        "zondag",
+    ],
+    short_weekdays: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+    relative_days: ["vandaag", "morgen", "gisteren"],
+    next_weekday: &["volgende {}", "komende {}"],
+    last_weekday: &["vorige {}", "afgelopen {}"],
+};
+
+impl Default for Locale {
+    fn default() -> Self {
+        ENGLISH
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    /// Parses a language code: `en`, `de`, `fr`, `es`, `pt`, `it` or `nl`, with or without a
+    /// region like `pt-BR`
+    fn from_str(code: &str) -> Result<Self> {
+        let language = code.split(['-', '_']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            "en" => Ok(ENGLISH),
+            "de" => Ok(GERMAN),
+            "fr" => Ok(FRENCH),
+            "es" => Ok(SPANISH),
+            "pt" => Ok(PORTUGUESE),
+            "it" => Ok(ITALIAN),
+            "nl" => Ok(DUTCH),
+            _ => Err(Error::parse(
+                None,
+                format!(
+                    "unknown language {}, expected en, de, fr, es, pt, it or nl",
+                    code
+                ),
+            )),
+        }
+    }
+}
+
+/// A part of a date format
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// `yyyy`
+    Year,
+    /// `yy`
+    ShortYear,
+    /// `MMMM`, or `MMM` when not `full`
+    MonthName { full: bool },
+    /// `MM`, or `M` when not `padded`
+    Month { padded: bool },
+    /// `dd`, or `d` when not `padded`
+    Day { padded: bool },
+    /// `do`: `1st`, `2nd`...
+    OrdinalDay,
+    /// `EEEE`, or `E` to `EEE` when not `full`
+    Weekday { full: bool },
+    /// Anything else, and text between single quotes
+    Literal(String),
+}
+
+/// A date-fns date format, like `MMM do, yyyy`, with the language of its names. It knows
+/// `yyyy`, `yy`, `MMMM`, `MMM`, `MM`, `M`, `dd`, `d`, `do`, `EEEE` and `E` to `EEE`; text in
+/// single quotes is literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFormat {
+    tokens: Vec<Token>,
+    /// The names of months and weekdays
+    pub locale: Locale,
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        DateFormat::new(DEFAULT_TITLE_FORMAT)
+    }
+}
+
+/// The English ordinal of a day, like `1st` or `23rd`
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+/// Splits the digits at the start of a text, at most `max` of them
+fn leading_digits(text: &str, max: usize) -> Option<(u32, &str)> {
+    let count = text
+        .chars()
+        .take(max)
+        .take_while(char::is_ascii_digit)
+        .count();
+    if count == 0 {
+        return None;
+    }
+    Some((text[..count].parse().ok()?, &text[count..]))
+}
+
+/// Strips a name from the start of a text, ignoring case, returning the position of the longest
+/// name found among `names`
+fn leading_name<'t>(text: &'t str, names: &[[&str; 12]]) -> Option<(usize, &'t str)> {
+    let lower = text.to_lowercase();
+    let mut best: Option<(usize, usize)> = None;
+    for list in names {
+        for (index, name) in list.iter().enumerate().filter(|(_, name)| !name.is_empty()) {
+            let name = name.to_lowercase();
+            if lower.starts_with(&name) && best.is_none_or(|(_, length)| name.len() > length) {
+                best = Some((index, name.len()));
+            }
+        }
+    }
+    let (index, length) = best?;
+    // Lowercasing keeps the lengths of the names of the locales
+    let rest = text.get(length..)?;
+    Some((index, rest))
+}
+
+/// Pads weekday names to the size of month names, to search both the same way
+fn weekdays(names: [&'static str; 7]) -> [&'static str; 12] {
+    let mut padded = [""; 12];
+    padded[..7].copy_from_slice(&names);
+    padded
+}
+
+impl DateFormat {
+    /// Reads a date-fns format, with English names
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::dates::{DateFormat, GERMAN};
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+    /// assert_eq!(DateFormat::new("MMM do, yyyy").format(date), "Jan 3rd, 2024");
+    /// assert_eq!(DateFormat::new("EEEE, dd.MM.yyyy").format(date), "Wednesday, 03.01.2024");
+    /// let german = DateFormat::new("d. MMMM yyyy").with_locale(GERMAN);
+    /// assert_eq!(german.format(date), "3. Januar 2024");
+    /// assert_eq!(DateFormat::new("yyyy 'week' M").format(date), "2024 week 1");
+    /// ```
+    pub fn new(format: &str) -> Self {
+        let chars: Vec<char> = format.chars().collect();
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\'' {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&other| other == '\'')
+                    .map_or(chars.len(), |end| i + 1 + end);
+                if end == i + 1 {
+                    literal.push('\'');
+                } else {
+                    literal.extend(&chars[i + 1..end]);
+                }
+                i = end + 1;
+                continue;
+            }
+            let run = chars[i..].iter().take_while(|&&other| other == c).count();
+            let token = match (c, run) {
+                ('d', 1) if chars.get(i + 1) == Some(&'o') => {
+                    i += 1;
+                    Some(Token::OrdinalDay)
+                }
+                ('y', 2) => Some(Token::ShortYear),
+                ('y', _) => Some(Token::Year),
+                ('M', 1) => Some(Token::Month { padded: false }),
+                ('M', 2) => Some(Token::Month { padded: true }),
+                ('M', 3) => Some(Token::MonthName { full: false }),
+                ('M', _) => Some(Token::MonthName { full: true }),
+                ('d', 1) => Some(Token::Day { padded: false }),
+                ('d', _) => Some(Token::Day { padded: true }),
+                ('E', 4..) => Some(Token::Weekday { full: true }),
+                ('E', _) => Some(Token::Weekday { full: false }),
+                _ => None,
+            };
+            match token {
+                Some(token) => {
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(token);
+                }
+                None => literal.extend(&chars[i..i + run]),
+            }
+            i += run;
+        }
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        DateFormat {
+            tokens,
+            locale: ENGLISH,
+        }
+    }
+
+    /// The same format with the names of another language
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Writes a date in this format
+    pub fn format(&self, date: NaiveDate) -> String {
+        let month = date.month0() as usize;
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        self.tokens
+            .iter()
+            .map(|token| match token {
+                Token::Year => date.year().to_string(),
+                Token::ShortYear => format!("{:02}", date.year().rem_euclid(100)),
+                Token::MonthName { full: true } => self.locale.months[month].to_string(),
+                Token::MonthName { full: false } => self.locale.short_months[month].to_string(),
+                Token::Month { padded: true } => format!("{:02}", date.month()),
+                Token::Month { padded: false } => date.month().to_string(),
+                Token::Day { padded: true } => format!("{:02}", date.day()),
+                Token::Day { padded: false } => date.day().to_string(),
+                Token::OrdinalDay => ordinal(date.day()),
+                Token::Weekday { full: true } => self.locale.weekdays[weekday].to_string(),
+                Token::Weekday { full: false } => self.locale.short_weekdays[weekday].to_string(),
+                Token::Literal(text) => text.clone(),
+            })
+            .collect()
+    }
+
+    /// Reads a date written in this format. Names may be in English or in the language of the
+    /// format, in any case, abbreviated or not; numbers don't need their leading zeros, and
+    /// ordinals may end with any suffix. Weekdays are read but not checked against the date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::dates::{DateFormat, FRENCH};
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 3);
+    /// let format = DateFormat::new("MMM do, yyyy");
+    /// assert_eq!(format.parse("Jan 3rd, 2024"), date);
+    /// assert_eq!(format.parse("january 3, 2024"), date);
+    /// assert_eq!(format.parse("Jan 32nd, 2024"), None);
+    /// let french = DateFormat::new("EEEE d MMMM yyyy").with_locale(FRENCH);
+    /// assert_eq!(french.parse("mercredi 3 janvier 2024"), date);
+    /// assert_eq!(french.parse("Wednesday 3 January 2024"), date);
+    /// ```
+    pub fn parse(&self, text: &str) -> Option<NaiveDate> {
+        let months = [
+            self.locale.months,
+            self.locale.short_months,
+            ENGLISH.months,
+            ENGLISH.short_months,
+        ];
+        let days = [
+            weekdays(self.locale.weekdays),
+            weekdays(self.locale.short_weekdays),
+            weekdays(ENGLISH.weekdays),
+            weekdays(ENGLISH.short_weekdays),
+        ];
+        let (mut year, mut month, mut day) = (None, None, None);
+        let mut rest = text.trim();
+        for (index, token) in self.tokens.iter().enumerate() {
+            rest = match token {
+                Token::Year => {
+                    let (number, rest) = leading_digits(rest, 4)?;
+                    year = Some(number as i32);
+                    rest
+                }
+                Token::ShortYear => {
+                    let (number, rest) = leading_digits(rest, 2)?;
+                    year = Some(2000 + number as i32);
+                    rest
+                }
+                Token::MonthName { .. } => {
+                    let (found, rest) = leading_name(rest, &months)?;
+                    month = Some(found as u32 + 1);
+                    // The period of an abbreviation, unless the format has one after the name
+                    let dot_next = matches!(self.tokens.get(index + 1),
+                        Some(Token::Literal(text)) if text.starts_with('.'));
+                    match rest.strip_prefix('.') {
+                        Some(stripped) if !dot_next => stripped,
+                        _ => rest,
+                    }
+                }
+                Token::Month { .. } => {
+                    let (number, rest) = leading_digits(rest, 2)?;
+                    month = Some(number);
+                    rest
+                }
+                Token::Day { .. } => {
+                    let (number, rest) = leading_digits(rest, 2)?;
+                    day = Some(number);
+                    rest
+                }
+                Token::OrdinalDay => {
+                    let (number, rest) = leading_digits(rest, 2)?;
+                    day = Some(number);
+                    rest.trim_start_matches(char::is_alphabetic)
+                }
+                Token::Weekday { .. } => leading_name(rest, &days)?.1,
+                Token::Literal(literal) => {
+                    let length = rest
+                        .char_indices()
+                        .nth(literal.chars().count())
+                        .map_or(rest.len(), |(index, _)| index);
+                    if rest[..length].to_lowercase() != literal.to_lowercase() {
+                        return None;
+                    }
+                    &rest[length..]
+                }
+            };
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+        NaiveDate::from_ymd_opt(year?, month?, day?)
+    }
+}
+
+/// The formats journal links are also written in, whatever the title format: ISO dates and the
+/// file names of journals
+const FALLBACK_FORMATS: [&str; 3] = ["yyyy-MM-dd", "yyyy_MM_dd", "yyyyMMdd"];
+
+/// The date of a journal link like `[[Jan 3rd, 2024]]`, `#[[2024-01-03]]` or of its text
+/// alone: in the title format (see [DateFormat::parse]), in Logseq's default format, or as
+/// `yyyy-MM-dd`, `yyyy_MM_dd` or `yyyyMMdd`
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use logseq::dates::{parse_date_link, DateFormat};
+/// let date = NaiveDate::from_ymd_opt(2024, 1, 3);
+/// let format = DateFormat::new("dd.MM.yyyy");
+/// assert_eq!(parse_date_link("[[03.01.2024]]", &format), date);
+/// assert_eq!(parse_date_link("#[[Jan 3rd, 2024]]", &format), date);
+/// assert_eq!(parse_date_link("2024-01-03", &format), date);
+/// assert_eq!(parse_date_link("[[Notes]]", &format), None);
+/// ```
+pub fn parse_date_link(text: &str, format: &DateFormat) -> Option<NaiveDate> {
+    let text = text.trim();
+    let text = text.strip_prefix('#').unwrap_or(text);
+    let text = text
+        .strip_prefix("[[")
+        .and_then(|text| text.strip_suffix("]]"))
+        .unwrap_or(text)
+        .trim();
+    format
+        .parse(text)
+        .or_else(|| DateFormat::default().parse(text))
+        .or_else(|| {
+            FALLBACK_FORMATS
+                .iter()
+                .find_map(|fallback| DateFormat::new(fallback).parse(text))
+        })
+}
+
+/// Today's date: in a time zone given by its offset from UTC, or in the local time zone
+///
+/// # Examples
+///
+/// ```
+/// use chrono::FixedOffset;
+/// use logseq::dates::today;
+/// let east = today(FixedOffset::east_opt(14 * 3600));
+/// let west = today(FixedOffset::west_opt(12 * 3600));
+/// // 26 hours apart: two days between 10:00 and 12:00 UTC, one day the rest of the time
+/// assert!((1..=2).contains(&(east - west).num_days()));
+/// ```
+pub fn today(offset: Option<FixedOffset>) -> NaiveDate {
+    match offset {
+        Some(offset) => Utc::now().with_timezone(&offset).date_naive(),
+        None => Local::now().date_naive(),
+    }
+}
+
+/// Reads an offset from UTC like `+02:00`, `-0530`, `+2` or `Z`
+///
+/// # Examples
+///
+/// ```
+/// use logseq::dates::parse_utc_offset;
+/// assert_eq!(parse_utc_offset("+02:00").unwrap().local_minus_utc(), 7200);
+/// assert_eq!(parse_utc_offset("-0530").unwrap().local_minus_utc(), -19800);
+/// assert_eq!(parse_utc_offset("Z").unwrap().local_minus_utc(), 0);
+/// assert!(parse_utc_offset("+25:00").is_err());
+/// ```
+pub fn parse_utc_offset(text: &str) -> Result<FixedOffset> {
+    let invalid = || {
+        Error::parse(
+            None,
+            format!("invalid UTC offset {}, expected e.g. +02:00", text),
+        )
+    };
+    let text = text.trim();
+    if text.eq_ignore_ascii_case("z") || text.eq_ignore_ascii_case("utc") {
+        return FixedOffset::east_opt(0).ok_or_else(invalid);
+    }
+    let (sign, digits) = match text.split_at_checked(1) {
+        Some(("+", digits)) => (1, digits),
+        Some(("-", digits)) => (-1, digits),
+        _ => return Err(invalid()),
+    };
+    let digits = digits.replace(':', "");
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let (hours, minutes) = if digits.len() <= 2 {
+        (digits.as_str(), "0")
+    } else {
+        digits.split_at(digits.len() - 2)
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
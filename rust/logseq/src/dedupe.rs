@@ -0,0 +1,173 @@
+//! Find blocks duplicated across a graph, word for word or nearly, as sync conflicts and
+//! copy-pasting leave them
+
+use crate::graph::Graph;
+use crate::outline::Block;
+use crate::properties::split_property;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Simhashes of near-identical blocks differ in at most this many bits
+pub const SIMILARITY_BITS: u32 = 3;
+
+/// Where a block is
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlockLocation {
+    /// The page or journal file
+    pub path: PathBuf,
+    /// Line number of the bullet, starting at 1
+    pub line: usize,
+}
+
+/// Blocks with the same content or nearly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateBlocks {
+    /// The blocks, sorted by path and line; there are always at least two
+    pub blocks: Vec<BlockLocation>,
+    /// True if the blocks have the same words, ignoring case, punctuation and properties;
+    /// false if some of them only look alike
+    pub identical: bool,
+    /// The first line of the first block
+    pub preview: String,
+}
+
+/// The words of the content of a block, lowercase, without its properties and punctuation
+///
+/// # Examples
+///
+/// ```
+/// use logseq::dedupe::block_words;
+/// use logseq::outline::parse_outline;
+/// let outline = parse_outline("- TODO Call  the bank, today!\n  id:: 65a1b2c3-0000-4000-8000-000000000001");
+/// assert_eq!(block_words(&outline.blocks[0]), vec!["todo", "call", "the", "bank", "today"]);
+/// ```
+pub fn block_words(block: &Block) -> Vec<String> {
+    block
+        .content
+        .lines()
+        .filter(|line| split_property(line.trim()).is_none())
+        .flat_map(|line| line.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A 64-bit FNV-1a hash, stable across runs and platforms
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The simhash of some words, from their hashes and the hashes of pairs of consecutive words:
+/// texts that share most of their words and word order get hashes that differ in few bits
+///
+/// # Examples
+///
+/// ```
+/// use logseq::dedupe::simhash;
+/// let words = |text: &str| text.split(' ').map(String::from).collect::<Vec<_>>();
+/// let a = simhash(&words("the quick brown fox jumps over the lazy dog by the river bank today"));
+/// let b = simhash(&words("the quick brown fox jumps over the lazy cat by the river bank today"));
+/// let c = simhash(&words("a completely different sentence about budgets and quarterly reviews"));
+/// assert!((a ^ b).count_ones() < (a ^ c).count_ones());
+/// ```
+pub fn simhash(words: &[String]) -> u64 {
+    let pairs = words
+        .windows(2)
+        .map(|pair| format!("{} {}", pair[0], pair[1]));
+    let mut weights = [0i32; 64];
+    for feature in words.iter().cloned().chain(pairs) {
+        let hash = fnv1a(feature.as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | 1 << bit)
+}
+
+/// Finds the root of a set, compressing the path to it
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// Finds blocks of the graph with at least `min_words` words that are duplicates: the same
+/// words (see [block_words]), or simhashes (see [simhash]) that differ in at most
+/// [SIMILARITY_BITS] bits. Children aren't part of the content of their parent, so a copied
+/// subtree shows up as one group per block. Groups are sorted by their first block.
+pub fn find_duplicate_blocks(graph: &Graph, min_words: usize) -> Vec<DuplicateBlocks> {
+    let mut blocks = Vec::new();
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            let words = block_words(block);
+            if words.len() < min_words.max(1) {
+                continue;
+            }
+            let location = BlockLocation {
+                path: page.path.clone(),
+                line: block.line,
+            };
+            blocks.push((
+                location,
+                simhash(&words),
+                words.join(" "),
+                block.first_line(),
+            ));
+        }
+    }
+    blocks.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Cut into one more band than the allowed distance, hashes within the distance have at least
+    // one band in common, so only blocks with the same value in a band need to be compared
+    let bands = SIMILARITY_BITS + 1;
+    let width = 64 / bands;
+    let mut parents: Vec<usize> = (0..blocks.len()).collect();
+    for band in 0..bands {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, (_, hash, _, _)) in blocks.iter().enumerate() {
+            let value = hash >> (band * width) & ((1 << width) - 1);
+            buckets.entry(value).or_default().push(index);
+        }
+        for bucket in buckets.values() {
+            for (position, &a) in bucket.iter().enumerate() {
+                for &b in &bucket[position + 1..] {
+                    if (blocks[a].1 ^ blocks[b].1).count_ones() <= SIMILARITY_BITS {
+                        let (root_a, root_b) = (find(&mut parents, a), find(&mut parents, b));
+                        parents[root_a.max(root_b)] = root_a.min(root_b);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for index in 0..blocks.len() {
+        let root = find(&mut parents, index);
+        groups.entry(root).or_default().push(index);
+    }
+    let mut duplicates: Vec<DuplicateBlocks> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let first = &blocks[members[0]];
+            DuplicateBlocks {
+                identical: members.iter().all(|&index| blocks[index].2 == first.2),
+                preview: first.3.trim().to_string(),
+                blocks: members
+                    .iter()
+                    .map(|&index| blocks[index].0.clone())
+                    .collect(),
+            }
+        })
+        .collect();
+    duplicates.sort_by(|a, b| a.blocks[0].cmp(&b.blocks[0]));
+    duplicates
+}
@@ -0,0 +1,421 @@
+//! Line-based unified diffs, to review fixes before they are written
+
+use serde::{Deserialize, Serialize};
+
+/// Lines of context around each change
+const CONTEXT: usize = 3;
+
+/// Beyond this many edits, the changed region is shown as a whole instead of line by line
+const MAX_EDITS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    /// Same line at these indexes of the original and the new text
+    Equal(usize, usize),
+    /// Line of the original text that was removed
+    Delete(usize),
+    /// Line of the new text that was added
+    Insert(usize),
+}
+
+/// Shortest edit script between two lists of lines (Myers' algorithm)
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    ops.extend(middle_script(a, b).into_iter().map(|op| match op {
+        Op::Equal(x, y) => Op::Equal(x + prefix, y + prefix),
+        Op::Delete(x) => Op::Delete(x + prefix),
+        Op::Insert(y) => Op::Insert(y + prefix),
+    }));
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    ops.extend((0..suffix).map(|i| Op::Equal(old_end + i, new_end + i)));
+    ops
+}
+
+fn middle_script(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    // The furthest x reached on each diagonal k in -d..=d, before each round d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut found = false;
+    for d in 0..=(max.min(MAX_EDITS) as isize) {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                found = true;
+                break;
+            }
+        }
+        if found {
+            break;
+        }
+    }
+    if !found {
+        let mut ops: Vec<Op> = (0..a.len()).map(Op::Delete).collect();
+        ops.extend((0..b.len()).map(Op::Insert));
+        return ops;
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = if d == 0 { 0 } else { at(previous_k) };
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == previous_x {
+                ops.push(Op::Insert(previous_y as usize));
+            } else {
+                ops.push(Op::Delete(previous_x as usize));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    ops.reverse();
+    ops
+}
+
+/// Formats the start and length of a hunk range, like `3,2`, `3` or `2,0`
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// A unified diff between two texts, with `a/` and `b/` prefixes before a relative label.
+/// Returns an empty string if the texts are the same.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::diff::unified_diff;
+/// let diff = unified_diff("page.md", "- a\n- b  c\n- d\n", "- a\n- b c\n- d\n");
+/// assert_eq!(diff, "--- a/page.md\n+++ b/page.md\n@@ -1,3 +1,3 @@\n - a\n-- b  c\n+- b c\n - d\n");
+/// assert_eq!(unified_diff("page.md", "same", "same"), "");
+/// assert!(unified_diff("page.md", "- a", "- a\n").ends_with("\\ No newline at end of file\n+- a\n"));
+/// ```
+pub fn unified_diff(label: &str, original: &str, fixed: &str) -> String {
+    if original == fixed {
+        return String::new();
+    }
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = fixed.split_inclusive('\n').collect();
+    let ops = edit_script(&old, &new);
+
+    let mut diff = if std::path::Path::new(label).is_absolute() {
+        format!("--- {}\n+++ {}\n", label, label)
+    } else {
+        format!("--- a/{}\n+++ b/{}\n", label, label)
+    };
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], Op::Equal(..)))
+        .collect();
+    let mut group_start = 0;
+    while group_start < changes.len() {
+        // Changes closer than twice the context share a hunk
+        let mut group_end = group_start;
+        while group_end + 1 < changes.len()
+            && changes[group_end + 1] - changes[group_end] <= 2 * CONTEXT + 1
+        {
+            group_end += 1;
+        }
+        let first = changes[group_start].saturating_sub(CONTEXT);
+        let last = (changes[group_end] + CONTEXT).min(ops.len() - 1);
+        let hunk = &ops[first..=last];
+
+        let old_start = hunk
+            .iter()
+            .find_map(|op| match op {
+                Op::Equal(x, _) | Op::Delete(x) => Some(*x),
+                Op::Insert(_) => None,
+            })
+            .unwrap_or_else(|| old_position(&ops[..first]));
+        let new_start = hunk
+            .iter()
+            .find_map(|op| match op {
+                Op::Equal(_, y) | Op::Insert(y) => Some(*y),
+                Op::Delete(_) => None,
+            })
+            .unwrap_or_else(|| new_position(&ops[..first]));
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, Op::Delete(_)))
+            .count();
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for op in hunk {
+            let (prefix, line) = match *op {
+                Op::Equal(x, _) => (' ', old[x]),
+                Op::Delete(x) => ('-', old[x]),
+                Op::Insert(y) => ('+', new[y]),
+            };
+            diff.push(prefix);
+            diff.push_str(line);
+            if !line.ends_with('\n') {
+                diff.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        group_start = group_end + 1;
+    }
+    diff
+}
+
+/// A change to consecutive lines of a text, one of the changes a fix makes, see [hunks]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    /// Line of the original text where the change starts, starting at 1
+    pub line: usize,
+    /// The lines of the original text that the change removes, without their line breaks
+    pub removed: Vec<String>,
+    /// The lines that replace them
+    pub added: Vec<String>,
+    /// The change as a hunk of a unified diff, with lines of context
+    pub diff: String,
+}
+
+/// The changes from one text to another, each a run of changed lines, in the order of the text
+///
+/// # Examples
+///
+/// ```
+/// use logseq::diff::{apply_hunks, hunks};
+/// let original = "- a  b\n- c\n- d\n- e\n- f\n- g\n- h\n- i  j";
+/// let fixed = "- a b\n- c\n- d\n- e\n- f\n- g\n- h\n- i j";
+/// let hunks = hunks(original, fixed);
+/// assert_eq!(hunks.len(), 2);
+/// assert_eq!(hunks[0].line, 1);
+/// assert_eq!(hunks[0].diff, "@@ -1,4 +1,4 @@\n-- a  b\n+- a b\n - c\n - d\n - e\n");
+/// assert_eq!(apply_hunks(original, &hunks[1..]), "- a  b\n- c\n- d\n- e\n- f\n- g\n- h\n- i j");
+/// ```
+pub fn hunks(original: &str, fixed: &str) -> Vec<Hunk> {
+    let old: Vec<&str> = original.split('\n').collect();
+    let new: Vec<&str> = fixed.split('\n').collect();
+    let ops = edit_script(&old, &new);
+    let mut hunks = Vec::new();
+    let mut index = 0;
+    while index < ops.len() {
+        if let Op::Equal(..) = ops[index] {
+            index += 1;
+            continue;
+        }
+        let end = ops[index..]
+            .iter()
+            .position(|op| matches!(op, Op::Equal(..)))
+            .map_or(ops.len(), |offset| index + offset);
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for op in &ops[index..end] {
+            match *op {
+                Op::Delete(x) => removed.push(old[x].to_string()),
+                Op::Insert(y) => added.push(new[y].to_string()),
+                Op::Equal(..) => {}
+            }
+        }
+        let old_start = old_position(&ops[..index]);
+        let new_start = new_position(&ops[..index]);
+        // Context is the lines the texts share next to the change, which an earlier or a later
+        // change may have removed
+        let unchanged = |op: &&Op| matches!(op, Op::Equal(..));
+        let before_count = ops[..index]
+            .iter()
+            .rev()
+            .take(CONTEXT)
+            .take_while(unchanged)
+            .count();
+        let before = &old[old_start - before_count..old_start];
+        let after_start = old_start + removed.len();
+        // The empty line after a final line break isn't context worth showing
+        let shown = old.len() - usize::from(old.last() == Some(&""));
+        let after_count = ops[end..]
+            .iter()
+            .take(CONTEXT)
+            .take_while(unchanged)
+            .count();
+        let after = &old[after_start..(after_start + after_count).min(shown).max(after_start)];
+        let mut diff = format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(
+                old_start - before.len(),
+                before.len() + removed.len() + after.len()
+            ),
+            hunk_range(
+                new_start - before.len(),
+                before.len() + added.len() + after.len()
+            )
+        );
+        let lines = (before.iter().map(|line| (' ', *line)))
+            .chain(removed.iter().map(|line| ('-', line.as_str())))
+            .chain(added.iter().map(|line| ('+', line.as_str())))
+            .chain(after.iter().map(|line| (' ', *line)));
+        for (prefix, line) in lines {
+            diff.push(prefix);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+        hunks.push(Hunk {
+            line: old_start + 1,
+            removed,
+            added,
+            diff,
+        });
+        index = end;
+    }
+    hunks
+}
+
+/// The original text with some of the changes of [hunks] made to it
+pub fn apply_hunks<'h>(original: &str, hunks: impl IntoIterator<Item = &'h Hunk>) -> String {
+    let old: Vec<&str> = original.split('\n').collect();
+    let mut hunks: Vec<&Hunk> = hunks.into_iter().collect();
+    hunks.sort_by_key(|hunk| hunk.line);
+    let mut lines: Vec<&str> = Vec::new();
+    // Index of the original line after the ones seen so far
+    let mut next = 0;
+    for hunk in hunks {
+        let start = hunk.line - 1;
+        lines.extend(&old[next..start]);
+        lines.extend(hunk.added.iter().map(String::as_str));
+        next = start + hunk.removed.len();
+    }
+    lines.extend(&old[next..]);
+    lines.join("\n")
+}
+
+/// Number of original lines before the given operations
+fn old_position(ops: &[Op]) -> usize {
+    ops.iter().filter(|op| !matches!(op, Op::Insert(_))).count()
+}
+
+/// Number of new lines before the given operations
+fn new_position(ops: &[Op]) -> usize {
+    ops.iter().filter(|op| !matches!(op, Op::Delete(_))).count()
+}
+
+/// The fixed text with the changes to protected lines of the original undone. Lines rewritten
+/// one for one are reverted one by one; other runs of changed lines are reverted as a whole if
+/// they remove a protected line, or add lines between two protected ones. `protected` takes
+/// line numbers starting at 1.
+pub(crate) fn revert_changes(
+    original: &str,
+    fixed: &str,
+    protected: impl Fn(usize) -> bool,
+) -> String {
+    revert_changes_with(original, fixed, protected, |line| line)
+}
+
+/// The fixed text with the changes to protected lines undone like [revert_changes], comparing
+/// the lines by `key`: the changes that keep the key of a line, like its indentation when the
+/// key trims it, are kept
+pub(crate) fn revert_changes_with<'a>(
+    original: &'a str,
+    fixed: &'a str,
+    protected: impl Fn(usize) -> bool,
+    key: impl Fn(&'a str) -> &'a str,
+) -> String {
+    let old: Vec<&str> = original.split('\n').collect();
+    let new: Vec<&str> = fixed.split('\n').collect();
+    let old_keys: Vec<&str> = old.iter().map(|line| key(line)).collect();
+    let new_keys: Vec<&str> = new.iter().map(|line| key(line)).collect();
+    let ops = edit_script(&old_keys, &new_keys);
+    let mut lines = Vec::new();
+    // Index of the original line after the ones seen so far
+    let mut next_old = 0;
+    let mut index = 0;
+    while index < ops.len() {
+        if let Op::Equal(x, y) = ops[index] {
+            lines.push(new[y]);
+            next_old = x + 1;
+            index += 1;
+            continue;
+        }
+        let end = ops[index..]
+            .iter()
+            .position(|op| matches!(op, Op::Equal(..)))
+            .map_or(ops.len(), |offset| index + offset);
+        let hunk = &ops[index..end];
+        let deleted: Vec<usize> = hunk
+            .iter()
+            .filter_map(|op| match op {
+                Op::Delete(x) => Some(*x),
+                _ => None,
+            })
+            .collect();
+        let inserted: Vec<usize> = hunk
+            .iter()
+            .filter_map(|op| match op {
+                Op::Insert(y) => Some(*y),
+                _ => None,
+            })
+            .collect();
+        if deleted.len() == inserted.len() {
+            // Lines rewritten one for one are reverted one by one
+            for (x, y) in deleted.iter().zip(&inserted) {
+                lines.push(if protected(x + 1) { old[*x] } else { new[*y] });
+            }
+            next_old = deleted[deleted.len() - 1] + 1;
+            index = end;
+            continue;
+        }
+        let revert = match deleted.is_empty() {
+            true => next_old > 0 && protected(next_old) && protected(next_old + 1),
+            false => deleted.iter().any(|x| protected(x + 1)),
+        };
+        for op in hunk {
+            match (op, revert) {
+                (Op::Delete(x), true) => lines.push(old[*x]),
+                (Op::Insert(y), false) => lines.push(new[*y]),
+                _ => {}
+            }
+        }
+        if let Some(last) = deleted.last() {
+            next_old = last + 1;
+        }
+        index = end;
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,449 @@
+//! The health of a graph: every check of the crate run at once, with the findings counted by
+//! category into scores from 0 to 100, and the files with the most findings of each category.
+//!
+//! The score of a category is the share of the files checked that have none of its findings,
+//! so that a handful of problems spread over the whole graph weighs more than many problems
+//! on one page; the score of the graph is the average of its categories.
+
+use crate::assets::{check_assets, find_duplicate_assets};
+use crate::batch::lint_file;
+use crate::graph::{collect_files_matching, Graph};
+use crate::lint::RuleSet;
+use crate::stubs::find_stub_pages;
+use crate::sync_conflicts::find_sync_conflicts;
+use crate::tasks::report::{task_report, STALE_DAYS};
+use crate::{Error, Result, SUBDIR_ASSETS};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How many of the files with the most findings are listed for each category, by default
+pub const TOP_OFFENDERS: usize = 3;
+
+/// The lint rules about links and references
+const LINK_RULES: &[&str] = &[
+    "link-style",
+    "link-normalization",
+    "malformed-refs",
+    "tag-brackets",
+    "citations",
+    "footnotes",
+];
+
+/// The lint rules about tasks and their timestamps
+const TASK_RULES: &[&str] = &[
+    "task-markers",
+    "priorities",
+    "timestamps",
+    "repeaters",
+    "done-metadata",
+    "checkboxes",
+    "block-times",
+    "logbook",
+];
+
+/// The lint rules about properties
+const PROPERTY_RULES: &[&str] = &[
+    "editor-properties",
+    "property-order",
+    "front-matter",
+    "property-schema",
+    "timestamp-properties",
+    "page-property-values",
+    "page-properties",
+    "properties-drawer",
+];
+
+/// The lint rules about assets
+const ASSET_RULES: &[&str] = &["image-props"];
+
+/// What a finding is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthCategory {
+    /// Missing pages and blocks, alias conflicts, and the lint rules about links
+    Links,
+    /// Overdue and stale tasks, and the lint rules about tasks
+    Tasks,
+    /// The lint rules about properties
+    Properties,
+    /// Missing, unused and duplicate assets, and the lint rules about assets
+    Assets,
+    /// Orphan, stub and duplicate pages, sync conflict copies, and the other lint rules
+    Hygiene,
+}
+
+impl HealthCategory {
+    /// All categories, in the order of the report
+    pub const ALL: [HealthCategory; 5] = [
+        HealthCategory::Links,
+        HealthCategory::Tasks,
+        HealthCategory::Properties,
+        HealthCategory::Assets,
+        HealthCategory::Hygiene,
+    ];
+
+    /// Name of the category, e.g. `links`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthCategory::Links => "links",
+            HealthCategory::Tasks => "tasks",
+            HealthCategory::Properties => "properties",
+            HealthCategory::Assets => "assets",
+            HealthCategory::Hygiene => "hygiene",
+        }
+    }
+
+    /// The category of the findings of a lint rule; rules of other crates are hygiene
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::doctor::HealthCategory;
+    /// assert_eq!(HealthCategory::of_rule("tag-brackets"), HealthCategory::Links);
+    /// assert_eq!(HealthCategory::of_rule("consecutive-spaces"), HealthCategory::Hygiene);
+    /// ```
+    pub fn of_rule(rule: &str) -> HealthCategory {
+        [
+            (LINK_RULES, HealthCategory::Links),
+            (TASK_RULES, HealthCategory::Tasks),
+            (PROPERTY_RULES, HealthCategory::Properties),
+            (ASSET_RULES, HealthCategory::Assets),
+        ]
+        .into_iter()
+        .find(|(rules, _)| rules.contains(&rule))
+        .map_or(HealthCategory::Hygiene, |(_, category)| category)
+    }
+}
+
+/// A file and how many findings of a category it has
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Offender {
+    /// The file, relative to the root of the graph
+    pub path: PathBuf,
+    /// Findings of the category in the file
+    pub findings: usize,
+}
+
+/// The health of a graph for a category of findings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategoryHealth {
+    /// The category
+    pub category: HealthCategory,
+    /// Share of the files checked without findings of the category, from 0 to 100
+    pub score: u8,
+    /// Findings of the category
+    pub findings: usize,
+    /// Findings by check, like `missing-page` or the id of a lint rule
+    pub checks: BTreeMap<String, usize>,
+    /// The files with the most findings, most first
+    pub top_offenders: Vec<Offender>,
+}
+
+/// The health of a graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthReport {
+    /// Average of the scores of the categories, from 0 to 100
+    pub score: u8,
+    /// Pages and journals checked
+    pub pages: usize,
+    /// Files of the `assets` directory checked
+    pub assets: usize,
+    /// The categories, in the order of [HealthCategory::ALL]
+    pub categories: Vec<CategoryHealth>,
+}
+
+/// How the health of a graph is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthOptions {
+    /// The day tasks are overdue after
+    pub today: NaiveDate,
+    /// How many days a task can go untouched before it is stale
+    pub stale_days: i64,
+    /// How many of the files with the most findings are listed for each category
+    pub top: usize,
+}
+
+impl HealthOptions {
+    /// The default options, with tasks overdue after `today`
+    pub fn new(today: NaiveDate) -> Self {
+        HealthOptions {
+            today,
+            stale_days: STALE_DAYS,
+            top: TOP_OFFENDERS,
+        }
+    }
+}
+
+/// The findings of the checks, by category, then check, then file
+#[derive(Default)]
+struct Findings(HashMap<HealthCategory, BTreeMap<String, BTreeMap<PathBuf, usize>>>);
+
+impl Findings {
+    /// Counts a finding of a check in a file
+    fn add(&mut self, category: HealthCategory, check: &str, path: &Path) {
+        *self
+            .0
+            .entry(category)
+            .or_default()
+            .entry(check.to_string())
+            .or_default()
+            .entry(path.to_path_buf())
+            .or_default() += 1;
+    }
+
+    /// The health of a category, with `checked` files checked, of which the `top` files with
+    /// the most findings are listed
+    fn health(
+        &self,
+        category: HealthCategory,
+        checked: usize,
+        root: &Path,
+        top: usize,
+    ) -> CategoryHealth {
+        let checks = self.0.get(&category).cloned().unwrap_or_default();
+        let mut by_file: BTreeMap<&Path, usize> = BTreeMap::new();
+        for files in self.0.get(&category).into_iter().flat_map(BTreeMap::values) {
+            for (path, count) in files {
+                *by_file.entry(path).or_default() += count;
+            }
+        }
+        let clean = checked.saturating_sub(by_file.len());
+        let score = match checked {
+            0 => 100,
+            _ => (clean * 100 / checked) as u8,
+        };
+        let mut top_offenders: Vec<Offender> = by_file
+            .into_iter()
+            .map(|(path, findings)| Offender {
+                path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+                findings,
+            })
+            .collect();
+        // Stable, so files with as many findings stay in path order
+        top_offenders.sort_by_key(|offender| std::cmp::Reverse(offender.findings));
+        top_offenders.truncate(top);
+        CategoryHealth {
+            category,
+            score,
+            findings: checks.values().flat_map(BTreeMap::values).sum(),
+            checks: checks
+                .into_iter()
+                .map(|(check, files)| (check, files.values().sum()))
+                .collect(),
+            top_offenders,
+        }
+    }
+}
+
+/// Checks the health of a graph: the lint rules on each page, broken links and block references,
+/// alias conflicts, overdue and stale tasks, missing, unused and duplicate assets, orphan, stub
+/// and duplicate pages, and sync conflict copies. Assets count as files checked for the assets
+/// category only.
+pub fn graph_health(
+    graph: &Graph,
+    rules: &RuleSet,
+    options: &HealthOptions,
+) -> Result<HealthReport> {
+    let mut findings = Findings::default();
+    for page in &graph.pages {
+        for diagnostic in lint_file(&page.path, rules)?.diagnostics {
+            let category = HealthCategory::of_rule(diagnostic.rule);
+            findings.add(category, diagnostic.rule, &page.path);
+        }
+    }
+
+    for link in graph.check_links() {
+        findings.add(HealthCategory::Links, "missing-page", &link.path);
+    }
+    for block_ref in graph.check_block_refs() {
+        findings.add(HealthCategory::Links, "missing-block", &block_ref.path);
+    }
+    for conflict in graph.find_alias_conflicts() {
+        findings.add(HealthCategory::Links, "alias-conflict", &conflict.path);
+    }
+
+    for task in task_report(graph, options.today, options.stale_days)? {
+        let check = if task.overdue {
+            "overdue-task"
+        } else {
+            "stale-task"
+        };
+        findings.add(HealthCategory::Tasks, check, &task.path);
+    }
+
+    let assets = check_assets(graph)?;
+    for link in &assets.missing {
+        findings.add(HealthCategory::Assets, "missing-asset", &link.path);
+    }
+    for orphan in &assets.orphans {
+        findings.add(HealthCategory::Assets, "unused-asset", orphan);
+    }
+    for group in find_duplicate_assets(graph)? {
+        for duplicate in &group.duplicates {
+            findings.add(HealthCategory::Assets, "duplicate-asset", duplicate);
+        }
+    }
+    let mut asset_files = Vec::new();
+    collect_files_matching(&graph.root.join(SUBDIR_ASSETS), &|_| true, &mut asset_files)?;
+
+    for orphan in graph.find_orphans()? {
+        findings.add(HealthCategory::Hygiene, "orphan-page", &orphan.path);
+    }
+    for stub in find_stub_pages(graph)? {
+        findings.add(HealthCategory::Hygiene, "stub-page", &stub.path);
+    }
+    for group in graph.find_duplicates()? {
+        for duplicate in &group.duplicates {
+            findings.add(HealthCategory::Hygiene, "duplicate-page", duplicate);
+        }
+    }
+    for conflict in find_sync_conflicts(graph)? {
+        findings.add(HealthCategory::Hygiene, "sync-conflict", &conflict.path);
+    }
+
+    let pages = graph.pages.len();
+    let categories: Vec<CategoryHealth> = HealthCategory::ALL
+        .into_iter()
+        .map(|category| {
+            let checked = match category {
+                HealthCategory::Assets => pages + asset_files.len(),
+                _ => pages,
+            };
+            findings.health(category, checked, &graph.root, options.top)
+        })
+        .collect();
+    let total: usize = categories
+        .iter()
+        .map(|category| category.score as usize)
+        .sum();
+    Ok(HealthReport {
+        score: (total / categories.len()) as u8,
+        pages,
+        assets: asset_files.len(),
+        categories,
+    })
+}
+
+/// How a health report is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HealthFormat {
+    /// A summary for people
+    #[default]
+    Text,
+    /// A JSON [HealthReport] object, for dashboards
+    Json,
+}
+
+impl FromStr for HealthFormat {
+    type Err = Error;
+
+    /// Parses `text` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Ok(HealthFormat::Text),
+            "json" => Ok(HealthFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected text or json", name),
+            )),
+        }
+    }
+}
+
+impl HealthFormat {
+    /// Writes a health report in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::doctor::{CategoryHealth, HealthCategory, HealthFormat, HealthReport, Offender};
+    /// let report = HealthReport {
+    ///     score: 90,
+    ///     pages: 10,
+    ///     assets: 0,
+    ///     categories: vec![
+    ///         CategoryHealth {
+    ///             category: HealthCategory::Links,
+    ///             score: 80,
+    ///             findings: 3,
+    ///             checks: [("missing-page".to_string(), 3)].into(),
+    ///             top_offenders: vec![
+    ///                 Offender { path: "pages/a.md".into(), findings: 2 },
+    ///                 Offender { path: "pages/b.md".into(), findings: 1 },
+    ///             ],
+    ///         },
+    ///         CategoryHealth {
+    ///             category: HealthCategory::Tasks,
+    ///             score: 100,
+    ///             findings: 0,
+    ///             checks: Default::default(),
+    ///             top_offenders: vec![],
+    ///         },
+    ///     ],
+    /// };
+    /// assert_eq!(HealthFormat::Text.render(&report),
+    ///     "Health 90/100 (10 pages, 0 assets)\n\
+    ///      \n\
+    ///      links  80/100  3 findings: missing-page 3\n\
+    ///      \x20 pages/a.md  2\n\
+    ///      \x20 pages/b.md  1\n\
+    ///      tasks  100/100  no findings\n");
+    /// ```
+    pub fn render(&self, report: &HealthReport) -> String {
+        if *self == HealthFormat::Json {
+            return serde_json::to_string_pretty(report).unwrap_or_default() + "\n";
+        }
+        let mut output = format!(
+            "Health {}/100 ({} pages, {} assets)\n\n",
+            report.score, report.pages, report.assets
+        );
+        let width = report
+            .categories
+            .iter()
+            .map(|category| category.category.as_str().len())
+            .max()
+            .unwrap_or(0);
+        for category in &report.categories {
+            let name = category.category.as_str();
+            let summary = match category.findings {
+                0 => "no findings".to_string(),
+                findings => {
+                    // Most frequent checks first
+                    let mut checks: Vec<(&String, &usize)> = category.checks.iter().collect();
+                    checks.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+                    let checks: Vec<String> = checks
+                        .into_iter()
+                        .map(|(check, count)| format!("{} {}", check, count))
+                        .collect();
+                    let noun = if findings == 1 { "finding" } else { "findings" };
+                    format!("{} {}: {}", findings, noun, checks.join(", "))
+                }
+            };
+            output.push_str(&format!(
+                "{:width$}  {}/100  {}\n",
+                name,
+                category.score,
+                summary,
+                width = width
+            ));
+            let path_width = category
+                .top_offenders
+                .iter()
+                .map(|offender| offender.path.to_string_lossy().chars().count())
+                .max()
+                .unwrap_or(0);
+            for offender in &category.top_offenders {
+                output.push_str(&format!(
+                    "  {:width$}  {}\n",
+                    offender.path.to_string_lossy(),
+                    offender.findings,
+                    width = path_width
+                ));
+            }
+        }
+        output
+    }
+}
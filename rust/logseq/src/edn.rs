@@ -0,0 +1,381 @@
+//! A small parser for [EDN](https://github.com/edn-format/edn), the format of Logseq's `config.edn`
+
+use crate::{Error, Result};
+use std::fmt;
+
+/// An EDN value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edn {
+    /// `nil`
+    Nil,
+    /// `true` or `false`
+    Bool(bool),
+    /// An integer like `42`
+    Integer(i64),
+    /// A floating point number like `1.5`
+    Float(f64),
+    /// A string like `"text"`, with escapes resolved
+    String(String),
+    /// A character like `\a`
+    Char(char),
+    /// A keyword like `:journal/file-name-format`, without the leading colon
+    Keyword(String),
+    /// A symbol like `foo`
+    Symbol(String),
+    /// A list like `(1 2)`
+    List(Vec<Edn>),
+    /// A vector like `[1 2]`
+    Vector(Vec<Edn>),
+    /// A map like `{:a 1}`, in the order of the file
+    Map(Vec<(Edn, Edn)>),
+    /// A set like `#{1 2}`
+    Set(Vec<Edn>),
+    /// A tagged value like `#inst "2024-01-02"`
+    Tagged(String, Box<Edn>),
+}
+
+impl Edn {
+    /// Parses a single EDN value; anything but whitespace and comments after it is an error
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::edn::Edn;
+    /// let config = Edn::parse("{:preferred-format :markdown ; comment\n :hidden [\"/drafts\"], :n 3}").unwrap();
+    /// assert_eq!(config.get("preferred-format"), Some(&Edn::Keyword("markdown".to_string())));
+    /// assert_eq!(config.get("hidden"), Some(&Edn::Vector(vec![Edn::String("/drafts".to_string())])));
+    /// assert_eq!(config.get("n").and_then(Edn::as_integer), Some(3));
+    /// assert!(Edn::parse("{:a").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let value = parser
+            .value()?
+            .ok_or_else(|| parser.error("expected a value"))?;
+        parser.skip_whitespace();
+        if parser.position < parser.chars.len() {
+            return Err(parser.error("unexpected text after the value"));
+        }
+        Ok(value)
+    }
+
+    /// The value of a keyword key (given without the colon) of a map
+    pub fn get(&self, keyword: &str) -> Option<&Edn> {
+        match self {
+            Edn::Map(entries) => entries
+                .iter()
+                .find(|(key, _)| matches!(key, Edn::Keyword(name) if name == keyword))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// The text of a string, keyword or symbol
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Edn::String(text) | Edn::Keyword(text) | Edn::Symbol(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The value of a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Edn::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Edn::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The items of a list, vector or set
+    pub fn as_seq(&self) -> Option<&[Edn]> {
+        match self {
+            Edn::List(items) | Edn::Vector(items) | Edn::Set(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the items of a collection separated by `separator`
+fn write_items(f: &mut fmt::Formatter<'_>, items: &[Edn], separator: &str) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            f.write_str(separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Edn {
+    /// Writes the value as EDN that [Edn::parse] reads back
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::edn::Edn;
+    /// let text = r#"{:name "Dune \"1965\"", :tags #{"book"}, :ids [1 2.5 nil], :at #inst "2024-01-02"}"#;
+    /// let value = Edn::parse(text).unwrap();
+    /// assert_eq!(value.to_string(), text);
+    /// assert_eq!(Edn::parse(&value.to_string()).unwrap(), value);
+    /// assert_eq!(Edn::Float(2.0).to_string(), "2.0");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edn::Nil => f.write_str("nil"),
+            Edn::Bool(flag) => write!(f, "{}", flag),
+            Edn::Integer(number) => write!(f, "{}", number),
+            Edn::Float(number) if number.fract() == 0.0 && number.is_finite() => {
+                write!(f, "{:.1}", number)
+            }
+            Edn::Float(number) => write!(f, "{}", number),
+            Edn::String(text) => {
+                f.write_str("\"")?;
+                for c in text.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        '\r' => f.write_str("\\r")?,
+                        other => write!(f, "{}", other)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            Edn::Char(c) => match c {
+                '\n' => f.write_str("\\newline"),
+                ' ' => f.write_str("\\space"),
+                '\t' => f.write_str("\\tab"),
+                '\r' => f.write_str("\\return"),
+                other => write!(f, "\\{}", other),
+            },
+            Edn::Keyword(name) => write!(f, ":{}", name),
+            Edn::Symbol(name) => f.write_str(name),
+            Edn::List(items) => {
+                f.write_str("(")?;
+                write_items(f, items, " ")?;
+                f.write_str(")")
+            }
+            Edn::Vector(items) => {
+                f.write_str("[")?;
+                write_items(f, items, " ")?;
+                f.write_str("]")
+            }
+            Edn::Map(entries) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{} {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+            Edn::Set(items) => {
+                f.write_str("#{")?;
+                write_items(f, items, " ")?;
+                f.write_str("}")
+            }
+            Edn::Tagged(tag, value) => write!(f, "#{} {}", tag, value),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        let consumed = &self.chars[..self.position.min(self.chars.len())];
+        let line = consumed.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = consumed.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        Error::parse(None, format!("EDN {}:{}: {}", line, column, message))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// Skips whitespace (commas included) and `;` comments
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ',' {
+                self.position += 1;
+            } else if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Parses the next value, or returns None at the end of the text or of a collection
+    fn value(&mut self) -> Result<Option<Edn>> {
+        self.skip_whitespace();
+        let Some(c) = self.peek() else {
+            return Ok(None);
+        };
+        let value = match c {
+            ')' | ']' | '}' => return Ok(None),
+            '(' => Edn::List(self.collection(')')?),
+            '[' => Edn::Vector(self.collection(']')?),
+            '{' => self.map()?,
+            '"' => Edn::String(self.string()?),
+            '\\' => self.character()?,
+            '#' => match self.chars.get(self.position + 1) {
+                Some('{') => {
+                    self.position += 1;
+                    Edn::Set(self.collection('}')?)
+                }
+                Some('_') => {
+                    self.position += 2;
+                    self.value()?
+                        .ok_or_else(|| self.error("expected a value to discard"))?;
+                    return self.value();
+                }
+                _ => {
+                    self.position += 1;
+                    let tag = self.token();
+                    let value = self
+                        .value()?
+                        .ok_or_else(|| self.error("expected a value after the tag"))?;
+                    Edn::Tagged(tag, Box::new(value))
+                }
+            },
+            ':' => {
+                self.position += 1;
+                Edn::Keyword(self.token())
+            }
+            _ => self.atom()?,
+        };
+        Ok(Some(value))
+    }
+
+    fn collection(&mut self, close: char) -> Result<Vec<Edn>> {
+        self.position += 1;
+        let mut items = Vec::new();
+        while let Some(item) = self.value()? {
+            items.push(item);
+        }
+        if self.peek() != Some(close) {
+            return Err(self.error(&format!("expected '{}'", close)));
+        }
+        self.position += 1;
+        Ok(items)
+    }
+
+    fn map(&mut self) -> Result<Edn> {
+        let items = self.collection('}')?;
+        if items.len() % 2 != 0 {
+            return Err(self.error("map with a key but no value"));
+        }
+        let mut entries = Vec::new();
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            entries.push((key, value));
+        }
+        Ok(Edn::Map(entries))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.position += 1;
+        let mut text = String::new();
+        loop {
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.position += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    text.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        other => other,
+                    });
+                }
+                other => text.push(other),
+            }
+        }
+    }
+
+    fn character(&mut self) -> Result<Edn> {
+        self.position += 1;
+        let name = self.token();
+        let c = match name.as_str() {
+            "newline" => '\n',
+            "space" => ' ',
+            "tab" => '\t',
+            "return" => '\r',
+            _ if name.chars().count() == 1 => name.chars().next().unwrap_or_default(),
+            "" => {
+                // Characters that end a token, like `\(`
+                let c = self
+                    .peek()
+                    .ok_or_else(|| self.error("expected a character"))?;
+                self.position += 1;
+                c
+            }
+            _ => return Err(self.error(&format!("unknown character \\{}", name))),
+        };
+        Ok(Edn::Char(c))
+    }
+
+    /// Reads the characters up to the next whitespace or delimiter
+    fn token(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(|c| {
+            !(c.is_whitespace() || matches!(c, ',' | ';' | '(' | ')' | '[' | ']' | '{' | '}' | '"'))
+        }) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn atom(&mut self) -> Result<Edn> {
+        let token = self.token();
+        if token.is_empty() {
+            return Err(self.error("unexpected character"));
+        }
+        Ok(match token.as_str() {
+            "nil" => Edn::Nil,
+            "true" => Edn::Bool(true),
+            "false" => Edn::Bool(false),
+            _ if token.starts_with(|c: char| c.is_ascii_digit())
+                || (token.len() > 1 && token.starts_with(['-', '+'])) =>
+            {
+                let number = token.trim_end_matches(['N', 'M']);
+                if let Ok(integer) = number.parse::<i64>() {
+                    Edn::Integer(integer)
+                } else if let Ok(float) = number.parse::<f64>() {
+                    Edn::Float(float)
+                } else if token.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(self.error(&format!("invalid number {}", token)));
+                } else {
+                    Edn::Symbol(token)
+                }
+            }
+            _ => Edn::Symbol(token),
+        })
+    }
+}
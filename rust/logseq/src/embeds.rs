@@ -0,0 +1,192 @@
+//! Audit the `{{embed [[page]]}}` of a graph: embeds of missing pages, which Logseq renders as
+//! error boxes, embeds of pages too small to be worth it, and large pages embedded so many times
+//! that links would do
+
+use crate::dedupe::block_words;
+use crate::graph::backlinks::{build_backlinks, ReferenceKind};
+use crate::graph::{page_key, Graph, GraphPage};
+use crate::refs::blank_code_spans;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static EMBED_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{embed\s+\[\[([^\[\]]+)\]\]\s*\}\}").unwrap());
+
+/// Pages with fewer words are too small to embed, see [EmbedLimits]
+pub const TINY_WORDS: usize = 10;
+
+/// Pages with as many words are large, see [EmbedLimits]
+pub const LARGE_WORDS: usize = 500;
+
+/// Large pages embedded as many times are embedded too often, see [EmbedLimits]
+pub const MAX_EMBEDS: usize = 3;
+
+/// When an embed is worth reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbedLimits {
+    /// Embedded pages with fewer words are tiny, a link shows as much
+    pub tiny_words: usize,
+    /// Embedded pages with at least as many words are large
+    pub large_words: usize,
+    /// Large pages embedded at least as many times are embedded too often
+    pub max_embeds: usize,
+}
+
+impl Default for EmbedLimits {
+    fn default() -> Self {
+        EmbedLimits {
+            tiny_words: TINY_WORDS,
+            large_words: LARGE_WORDS,
+            max_embeds: MAX_EMBEDS,
+        }
+    }
+}
+
+/// What is wrong with an embed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedProblem {
+    /// The page doesn't exist
+    Missing,
+    /// The page has fewer words than [EmbedLimits::tiny_words]
+    Tiny {
+        /// The words of the page, without its properties
+        words: usize,
+    },
+    /// The page is large and embedded too often
+    Large {
+        /// The words of the page, without its properties
+        words: usize,
+        /// How many times the page is embedded in the graph
+        embeds: usize,
+    },
+}
+
+/// An `{{embed [[page]]}}` worth a look
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbedIssue {
+    /// File containing the embed
+    pub path: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column of the embedded `[[page]]`, starting at 1
+    pub column: usize,
+    /// Name of the embedded page, as written
+    pub target: String,
+    /// What is wrong with it
+    pub problem: EmbedProblem,
+}
+
+/// The words of a page (see [block_words]), all blocks together
+fn page_words(page: &GraphPage) -> usize {
+    page.outline
+        .iter()
+        .map(|block| block_words(block).len())
+        .sum()
+}
+
+impl Graph {
+    /// Finds the page embeds of the graph that are missing, tiny, or large and embedded too
+    /// often, according to the limits. Pages are found by name or alias; embedded blocks
+    /// (`{{embed ((uuid))}}`) are checked by [Graph::check_block_refs]. Sorted by file and line.
+    pub fn audit_embeds(&self, limits: &EmbedLimits) -> Vec<EmbedIssue> {
+        let embeds: Vec<_> = build_backlinks(self)
+            .into_values()
+            .flatten()
+            .filter(|reference| reference.kind == ReferenceKind::Embed)
+            .collect();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for embed in &embeds {
+            if let Some(page) = self.find_page(&embed.target) {
+                *counts.entry(page_key(&page.name)).or_default() += 1;
+            }
+        }
+        let mut words: HashMap<String, usize> = HashMap::new();
+        let mut issues: Vec<EmbedIssue> = embeds
+            .into_iter()
+            .filter_map(|embed| {
+                let problem = match self.find_page(&embed.target) {
+                    None => EmbedProblem::Missing,
+                    Some(page) => {
+                        let key = page_key(&page.name);
+                        let words = *words.entry(key.clone()).or_insert_with(|| page_words(page));
+                        let embeds = counts[&key];
+                        if words < limits.tiny_words {
+                            EmbedProblem::Tiny { words }
+                        } else if words >= limits.large_words && embeds >= limits.max_embeds {
+                            EmbedProblem::Large { words, embeds }
+                        } else {
+                            return None;
+                        }
+                    }
+                };
+                Some(EmbedIssue {
+                    path: embed.path,
+                    line: embed.line,
+                    column: embed.column,
+                    target: embed.target,
+                    problem,
+                })
+            })
+            .collect();
+        issues.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+        issues
+    }
+}
+
+/// Plans turning the embeds of missing pages into plain `[[links]]`, so that Logseq stops
+/// rendering them as errors; other issues are left alone. Nothing is written; see
+/// [unembed_missing].
+pub fn plan_unembed_missing(issues: &[EmbedIssue]) -> Result<Vec<FileEdit>> {
+    let mut by_file: BTreeMap<&PathBuf, HashSet<(usize, String)>> = BTreeMap::new();
+    for issue in issues
+        .iter()
+        .filter(|issue| issue.problem == EmbedProblem::Missing)
+    {
+        by_file
+            .entry(&issue.path)
+            .or_default()
+            .insert((issue.line, page_key(&issue.target)));
+    }
+    let mut edits = Vec::new();
+    for (path, missing) in by_file {
+        let original = error::read_to_string(path)?;
+        let mut updated = String::with_capacity(original.len());
+        for (index, line) in original.split_inclusive('\n').enumerate() {
+            let searchable = blank_code_spans(line);
+            let mut last = 0;
+            for captures in EMBED_RE.captures_iter(&searchable) {
+                let name = captures.get(1).unwrap();
+                if !missing.contains(&(index + 1, page_key(&line[name.range()]))) {
+                    continue;
+                }
+                let embed = captures.get(0).unwrap();
+                updated.push_str(&line[last..embed.start()]);
+                updated.push_str(&format!("[[{}]]", &line[name.range()]));
+                last = embed.end();
+            }
+            updated.push_str(&line[last..]);
+        }
+        if updated != original {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Turns the embeds of missing pages into plain links, see [plan_unembed_missing]; with
+/// `dry_run`, only plans the changes
+pub fn unembed_missing(issues: &[EmbedIssue], dry_run: bool) -> Result<Vec<FileEdit>> {
+    let edits = plan_unembed_missing(issues)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,145 @@
+//! Read files that aren't plain UTF-8: a byte order mark, or text imported in Latin-1
+
+use crate::lint::Diagnostic;
+use crate::{Error, Result};
+use std::fs;
+use std::path::Path;
+
+/// The byte order mark some Windows editors write at the start of UTF-8 files
+pub const BOM: &str = "\u{feff}";
+
+/// The id of the diagnostics about the encoding of a file
+pub const ENCODING_RULE: &str = "encoding";
+
+/// The characters of Windows-1252 for the bytes 0x80 to 0x9F, which are control characters in
+/// Latin-1 (ISO 8859-1) but are almost always meant as Windows-1252 in imported notes
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+/// How the bytes of a file encode its text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8
+    #[default]
+    Utf8,
+    /// UTF-8 that starts with a byte order mark
+    Utf8Bom,
+    /// Not valid UTF-8, read as Latin-1 (with the Windows-1252 characters for 0x80 to 0x9F)
+    Latin1,
+}
+
+/// What to do with files that aren't plain UTF-8
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodingOptions {
+    /// Remove the byte order mark of the files that have one, instead of keeping it
+    pub strip_bom: bool,
+    /// Rewrite the files that aren't valid UTF-8 from Latin-1 to UTF-8, instead of skipping them
+    pub transcode: bool,
+}
+
+/// The text of a file, and how it was encoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The text, without the byte order mark
+    pub text: String,
+    /// The encoding of the file
+    pub encoding: Encoding,
+}
+
+/// Decodes the contents of a file: UTF-8, with or without a byte order mark, or else Latin-1,
+/// which any bytes are
+///
+/// # Examples
+///
+/// ```
+/// use logseq::encoding::{decode, Encoding};
+/// assert_eq!(decode(b"\xef\xbb\xbftitle:: a").text, "title:: a");
+/// assert_eq!(decode(b"\xef\xbb\xbftitle:: a").encoding, Encoding::Utf8Bom);
+/// let latin1 = decode(b"- caf\xe9 \x93quoted\x94");
+/// assert_eq!(latin1.text, "- café “quoted”");
+/// assert_eq!(latin1.encoding, Encoding::Latin1);
+/// ```
+pub fn decode(bytes: &[u8]) -> Decoded {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => match text.strip_prefix(BOM) {
+            Some(text) => Decoded {
+                text: text.to_string(),
+                encoding: Encoding::Utf8Bom,
+            },
+            None => Decoded {
+                text: text.to_string(),
+                encoding: Encoding::Utf8,
+            },
+        },
+        Err(_) => Decoded {
+            text: bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..=0x9f => WINDOWS_1252[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })
+                .collect(),
+            encoding: Encoding::Latin1,
+        },
+    }
+}
+
+/// The error for a file that isn't valid UTF-8, with the position of the first invalid byte
+pub(crate) fn invalid_utf8(path: &Path, bytes: &[u8]) -> Error {
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(error) => error.valid_up_to(),
+    };
+    let line = bytes[..valid].iter().filter(|&&byte| byte == b'\n').count() + 1;
+    Error::parse(
+        Some(path),
+        format!(
+            "file is not valid UTF-8 (byte 0x{:02X} on line {}), it may be Latin-1; transcode it to UTF-8 to process it",
+            bytes.get(valid).copied().unwrap_or_default(),
+            line
+        ),
+    )
+}
+
+impl Encoding {
+    /// The diagnostic about a file with this encoding, or None for plain UTF-8
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        let message = match self {
+            Encoding::Utf8 => return None,
+            Encoding::Utf8Bom => "File starts with a UTF-8 byte order mark",
+            Encoding::Latin1 => "File is not valid UTF-8, it was read as Latin-1",
+        };
+        Some(Diagnostic::new(ENCODING_RULE, 1, 1, message))
+    }
+
+    /// The contents of a file with this encoding after writing a text back, with the options:
+    /// files keep their byte order mark unless it's stripped, and Latin-1 is written as UTF-8
+    pub fn encode(&self, text: &str, options: &EncodingOptions) -> String {
+        match self {
+            Encoding::Utf8Bom if !options.strip_bom => format!("{}{}", BOM, text),
+            _ => text.to_string(),
+        }
+    }
+
+    /// True if writing a file with the options changes its encoding
+    pub fn is_fixed_by(&self, options: &EncodingOptions) -> bool {
+        match self {
+            Encoding::Utf8 => false,
+            Encoding::Utf8Bom => options.strip_bom,
+            Encoding::Latin1 => options.transcode,
+        }
+    }
+}
+
+/// Reads a file for fixing: a byte order mark is removed from the text, and Latin-1 is decoded
+/// only with `transcode`; otherwise the file is skipped with an error
+pub fn read_text(path: &Path, options: &EncodingOptions) -> Result<Decoded> {
+    let bytes = fs::read(path).map_err(|source| Error::io(path, source))?;
+    let decoded = decode(&bytes);
+    if decoded.encoding == Encoding::Latin1 && !options.transcode {
+        return Err(invalid_utf8(path, &bytes));
+    }
+    Ok(decoded)
+}
@@ -0,0 +1,171 @@
+//! Errors returned by this crate
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// An error while reading, parsing or writing Logseq files
+#[derive(Debug)]
+pub enum Error {
+    /// A file couldn't be read or written
+    Io {
+        /// The file involved, if known
+        path: Option<PathBuf>,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The contents of a file or a value couldn't be parsed
+    Parse {
+        /// The file involved, if known
+        path: Option<PathBuf>,
+        /// What went wrong
+        message: String,
+    },
+}
+
+/// Result type of this crate
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// Constructs an I/O error for the given file
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    /// Constructs a parse error, optionally for a file
+    pub fn parse(path: Option<&Path>, message: impl Into<String>) -> Self {
+        Error::Parse {
+            path: path.map(Path::to_path_buf),
+            message: message.into(),
+        }
+    }
+
+    /// Sets the file involved in the error, unless it's already known
+    pub fn with_path(self, file: &Path) -> Self {
+        match self {
+            Error::Io { path: None, source } => Error::io(file, source),
+            Error::Parse {
+                path: None,
+                message,
+            } => Error::parse(Some(file), message),
+            error => error,
+        }
+    }
+
+    /// The file involved in the error, if known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::Io { path, .. } | Error::Parse { path, .. } => path.as_deref(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse {
+                path: None,
+                message,
+            } => write!(f, "{}", message),
+            Error::Parse {
+                path: Some(path),
+                message,
+            } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+/// Reads a file as UTF-8 text, without its byte order mark if it has one; invalid UTF-8 is
+/// reported as a parse error
+pub(crate) fn read_to_string(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).map_err(|source| Error::io(path, source))?;
+    match String::from_utf8(bytes) {
+        Ok(text) => match text.strip_prefix(crate::encoding::BOM) {
+            Some(text) => Ok(text.to_string()),
+            None => Ok(text),
+        },
+        Err(error) => Err(crate::encoding::invalid_utf8(path, error.as_bytes())),
+    }
+}
+
+/// Writes text to a file atomically, see [write_atomically]
+pub(crate) fn write(path: &Path, contents: &str) -> Result<()> {
+    write_atomically(path, contents, false)
+}
+
+/// The file a path names: the target of a symbolic link, so that replacing the file writes
+/// to the target and keeps the link. A dangling link is its own file.
+pub(crate) fn link_target(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// A hidden file next to `path`, to write to before replacing it
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// The backup of a file, e.g. `page.md.bak` for `page.md`
+pub(crate) fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Writes text to a temporary file in the same directory, then renames it over the original,
+/// so a crash while writing never leaves a truncated file behind.
+/// The permissions of an existing file are kept; with `backup`, its previous contents are
+/// copied to a `.bak` file first. A symbolic link is kept, and the file it links to written.
+pub(crate) fn write_atomically(path: &Path, contents: &str, backup: bool) -> Result<()> {
+    let target = link_target(path);
+    let temp = temp_path(&target);
+    let result = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(&target) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+            if backup {
+                fs::copy(&target, backup_path(&target))?;
+            }
+        }
+        fs::rename(&temp, &target)
+    })();
+    result.map_err(|source| {
+        let _ = fs::remove_file(&temp);
+        Error::io(path, source)
+    })
+}
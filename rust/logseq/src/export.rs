@@ -0,0 +1,343 @@
+//! Export Logseq pages as standard Markdown, to publish them with static site generators, or
+//! the whole graph as data, see [database]
+
+use crate::graph::{file_stem_from_page_name, Graph, GraphPage};
+use crate::outline::{Block, Outline};
+use crate::properties::{split_property, Value};
+use crate::{error, Error, Result};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub mod database;
+pub mod links;
+
+/// Syntax of Logseq Markdown that has no equivalent in standard Markdown
+struct LogseqSyntax {
+    code_span: Regex,
+    embed: Regex,
+    block_ref: Regex,
+    labeled_link: Regex,
+    page_link: Regex,
+    tag: Regex,
+    heading: Regex,
+}
+
+impl LogseqSyntax {
+    fn new() -> Self {
+        LogseqSyntax {
+            code_span: Regex::new(r"`[^`]*`").unwrap(),
+            embed: Regex::new(
+                r"\{\{embed\s+(?:\[\[([^\[\]]+)\]\]|\(\(([0-9a-fA-F-]{36})\)\))\s*\}\}",
+            )
+            .unwrap(),
+            block_ref: Regex::new(r"\(\(([0-9a-fA-F-]{36})\)\)").unwrap(),
+            labeled_link: Regex::new(r"\[([^\[\]]*)\]\(\[\[([^\[\]]+)\]\]\)").unwrap(),
+            page_link: Regex::new(r"#?\[\[([^\[\]]+)\]\]").unwrap(),
+            tag: Regex::new(r#"(^|\s)#([^\s#\[\],;!?"'()`]+)"#).unwrap(),
+            heading: Regex::new(r"^#{1,6}\s").unwrap(),
+        }
+    }
+}
+
+/// Converts the page properties into YAML front matter, keeping their types
+fn front_matter(outline: &Outline) -> Option<String> {
+    let properties = outline.page_properties();
+    if properties.is_empty() {
+        return None;
+    }
+    let quote = |text: &str| serde_json::to_string(text).unwrap_or_default();
+    let mut lines = vec!["---".to_string()];
+    for property in properties.iter() {
+        let value = match &property.value {
+            Value::Integer(number) => number.to_string(),
+            Value::Float(number) => number.to_string(),
+            Value::Bool(flag) => flag.to_string(),
+            Value::Date(date) => date.to_string(),
+            Value::Refs(names)
+                if names.len() == 1 && !property.key.eq_ignore_ascii_case("tags") =>
+            {
+                quote(&names[0])
+            }
+            Value::Refs(names) => {
+                let names: Vec<String> = names.iter().map(|name| quote(name)).collect();
+                format!("[{}]", names.join(", "))
+            }
+            Value::Text(text) => quote(text),
+        };
+        lines.push(format!("{}: {}", property.key, value));
+    }
+    lines.push("---".to_string());
+    Some(lines.join("\n"))
+}
+
+/// Exports the pages of a graph as standard Markdown: top-level blocks become paragraphs (or
+/// headings); their children become nested lists. `[[links]]` and `#tags` become relative links
+/// to the exported files, block references are replaced with the text of the block, page
+/// properties become YAML front matter, and block properties and drawers are dropped.
+pub struct MarkdownExport<'a> {
+    syntax: LogseqSyntax,
+    /// Pages by lowercase name and alias
+    pages: HashMap<String, &'a GraphPage>,
+    /// Blocks with an `id::` property, by lowercase UUID
+    blocks: HashMap<String, &'a Block>,
+    /// Inline embedded pages and blocks instead of linking to them
+    pub inline_embeds: bool,
+}
+
+impl<'a> MarkdownExport<'a> {
+    /// Prepares the export of the pages of a graph, linking embeds instead of inlining them
+    pub fn new(graph: &'a Graph) -> Self {
+        let mut pages = HashMap::new();
+        let mut blocks = HashMap::new();
+        for page in &graph.pages {
+            for name in std::iter::once(page.name.clone()).chain(page.aliases()) {
+                pages.entry(name.to_lowercase()).or_insert(page);
+            }
+            for block in page.outline.iter() {
+                if let Some(id) = block.properties().get("id") {
+                    blocks.insert(id.raw.trim().to_lowercase(), block);
+                }
+            }
+        }
+        MarkdownExport {
+            syntax: LogseqSyntax::new(),
+            pages,
+            blocks,
+            inline_embeds: false,
+        }
+    }
+
+    /// The relative link to the exported file of a page
+    fn page_url(&self, name: &str) -> String {
+        let stem = match self.pages.get(&name.to_lowercase()) {
+            Some(page) => page
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            None => file_stem_from_page_name(name),
+        };
+        let encoded = stem
+            .replace('%', "%25")
+            .replace(' ', "%20")
+            .replace('(', "%28")
+            .replace(')', "%29");
+        format!("{}.md", encoded)
+    }
+
+    /// The text of a referenced block, without its own references
+    fn block_text(&self, uuid: &str) -> Option<String> {
+        let block = self.blocks.get(&uuid.to_lowercase())?;
+        let text = self.syntax.block_ref.replace_all(block.first_line(), "");
+        Some(self.inline_text(&text, false))
+    }
+
+    /// Converts the links, tags and references of a line, outside inline code
+    fn inline(&self, line: &str) -> String {
+        let mut converted = String::new();
+        let mut position = 0;
+        for code in self.syntax.code_span.find_iter(line) {
+            converted.push_str(&self.inline_text(&line[position..code.start()], true));
+            converted.push_str(code.as_str());
+            position = code.end();
+        }
+        converted.push_str(&self.inline_text(&line[position..], true));
+        converted
+    }
+
+    fn inline_text(&self, text: &str, references: bool) -> String {
+        let syntax = &self.syntax;
+        let embedded = syntax.embed.replace_all(text, |captures: &Captures| {
+            match (captures.get(1), captures.get(2)) {
+                (Some(name), _) => format!("[{}]({})", name.as_str(), self.page_url(name.as_str())),
+                (_, Some(uuid)) => format!("(({}))", uuid.as_str()),
+                _ => captures[0].to_string(),
+            }
+        });
+        let referenced = if references {
+            syntax
+                .block_ref
+                .replace_all(&embedded, |captures: &Captures| {
+                    self.block_text(&captures[1])
+                        .unwrap_or_else(|| captures[0].to_string())
+                })
+        } else {
+            embedded
+        };
+        let labeled = syntax
+            .labeled_link
+            .replace_all(&referenced, |captures: &Captures| {
+                format!("[{}]({})", &captures[1], self.page_url(&captures[2]))
+            });
+        let linked = syntax
+            .page_link
+            .replace_all(&labeled, |captures: &Captures| {
+                let name = &captures[1];
+                let hash = if captures[0].starts_with('#') {
+                    "#"
+                } else {
+                    ""
+                };
+                format!("[{}{}]({})", hash, name, self.page_url(name))
+            });
+        syntax
+            .tag
+            .replace_all(&linked, |captures: &Captures| {
+                let name = captures[2].trim_end_matches('.');
+                let rest = &captures[2][name.len()..];
+                format!(
+                    "{}[#{}]({}){}",
+                    &captures[1],
+                    name,
+                    self.page_url(name),
+                    rest
+                )
+            })
+            .to_string()
+    }
+
+    /// The lines of a block in standard Markdown, without properties and drawers
+    fn content(&self, block: &Block) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut in_fence = false;
+        let mut in_drawer = false;
+        for line in block.content.split('\n') {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+                lines.push(line.to_string());
+                continue;
+            }
+            if in_fence {
+                lines.push(line.to_string());
+                continue;
+            }
+            if in_drawer {
+                in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+                continue;
+            }
+            if trimmed.len() > 2 && trimmed.starts_with(':') && trimmed.ends_with(':') {
+                in_drawer = true;
+                continue;
+            }
+            if split_property(line).is_none() {
+                lines.push(self.inline(line));
+            }
+        }
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+
+    /// The pages and blocks to inline in place of a block that is only an embed
+    fn embedded(&self, block: &Block, inline: bool) -> Option<Vec<&'a Block>> {
+        if !inline {
+            return None;
+        }
+        let captures = self.syntax.embed.captures(block.first_line().trim())?;
+        if captures.get(0)?.len() != block.first_line().trim().len() {
+            return None;
+        }
+        match (captures.get(1), captures.get(2)) {
+            (Some(name), _) => {
+                let page = self.pages.get(&name.as_str().to_lowercase())?;
+                Some(page.outline.blocks.iter().collect())
+            }
+            (_, Some(uuid)) => Some(vec![*self.blocks.get(&uuid.as_str().to_lowercase())?]),
+            _ => None,
+        }
+    }
+
+    /// Renders blocks as paragraphs and headings, and their children as lists.
+    /// Embeds are only inlined at the top, so that pages embedding each other end.
+    fn paragraphs(&self, blocks: &[&Block], inline: bool, output: &mut Vec<String>) {
+        for block in blocks {
+            if let Some(embedded) = self.embedded(block, inline) {
+                self.paragraphs(&embedded, false, output);
+            } else {
+                let lines = self.content(block);
+                if !lines.is_empty() {
+                    output.push(lines.join("\n"));
+                }
+            }
+            let children: Vec<&Block> = block.children.iter().collect();
+            let heading = self.syntax.heading.is_match(block.first_line());
+            if heading {
+                self.paragraphs(&children, inline, output);
+            } else if !children.is_empty() {
+                let mut list = Vec::new();
+                self.list(&children, 0, inline, &mut list);
+                output.push(list.join("\n"));
+            }
+        }
+    }
+
+    fn list(&self, blocks: &[&Block], level: usize, inline: bool, output: &mut Vec<String>) {
+        let indent = "  ".repeat(level);
+        for block in blocks {
+            match self.embedded(block, inline) {
+                Some(embedded) => self.list(&embedded, level, false, output),
+                None => {
+                    let lines = self.content(block);
+                    let mut lines = lines.iter();
+                    if let Some(first) = lines.next() {
+                        output.push(format!("{}- {}", indent, first));
+                    }
+                    for line in lines {
+                        output.push(format!("{}  {}", indent, line));
+                    }
+                }
+            }
+            let children: Vec<&Block> = block.children.iter().collect();
+            self.list(&children, level + 1, inline, output);
+        }
+    }
+
+    /// Exports an outline as standard Markdown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::export::MarkdownExport;
+    /// use logseq::graph::Graph;
+    /// use logseq::outline::parse_outline;
+    /// let graph = Graph { root: Default::default(), pages: Vec::new(), unreadable: Vec::new() };
+    /// let outline = parse_outline("tags:: book, scifi\n\n- # Dune\n- Read [[Frank Herbert]] #scifi `[[code]]`\n  collapsed:: true\n\t- [one](https://x.com)\n\t\t- two");
+    /// assert_eq!(MarkdownExport::new(&graph).outline(&outline),
+    ///     "---\ntags: [\"book\", \"scifi\"]\n---\n\n# Dune\n\nRead [Frank Herbert](Frank%20Herbert.md) [#scifi](scifi.md) `[[code]]`\n\n- [one](https://x.com)\n  - two\n");
+    /// ```
+    pub fn outline(&self, outline: &Outline) -> String {
+        let mut output: Vec<String> = front_matter(outline).into_iter().collect();
+        let blocks: Vec<&Block> = outline.blocks.iter().collect();
+        self.paragraphs(&blocks, self.inline_embeds, &mut output);
+        let mut markdown = output.join("\n\n");
+        markdown.push('\n');
+        markdown
+    }
+
+    /// Exports a page of the graph, see [MarkdownExport::outline]
+    pub fn page(&self, page: &GraphPage) -> String {
+        self.outline(&page.outline)
+    }
+}
+
+/// Exports every page and journal of a graph into a directory, keeping their file names so that
+/// the links between them work. Returns the paths of the exported files.
+pub fn export_graph(graph: &Graph, output: &Path, inline_embeds: bool) -> Result<Vec<PathBuf>> {
+    let mut export = MarkdownExport::new(graph);
+    export.inline_embeds = inline_embeds;
+    fs::create_dir_all(output).map_err(|source| Error::io(output, source))?;
+    let mut exported = Vec::new();
+    for page in &graph.pages {
+        let Some(file_name) = page.path.file_name() else {
+            continue;
+        };
+        let path = output.join(file_name);
+        error::write(&path, &export.page(page))?;
+        exported.push(path);
+    }
+    Ok(exported)
+}
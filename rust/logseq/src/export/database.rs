@@ -0,0 +1,470 @@
+//! Dump a whole graph as data: its pages, blocks, properties and references, as JSON or as
+//! EDN entities like those of the Datascript database Logseq keeps in memory, so that scripts can
+//! query the graph without parsing Markdown
+
+use crate::edn::Edn;
+use crate::graph::{page_key, Graph};
+use crate::outline::Block;
+use crate::properties::{Properties, Value};
+use crate::refs::{block_refs, lines_outside_regions, page_links, tag_refs, CODE_REGIONS};
+use crate::{Error, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// The value of a property, typed like [Value]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    /// Plain text
+    Text(String),
+    /// A whole number
+    Integer(i64),
+    /// A decimal number
+    Float(f64),
+    /// `true` or `false`
+    Bool(bool),
+    /// A date, written as `YYYY-MM-DD`
+    Date(NaiveDate),
+    /// The names of the referenced pages
+    Refs(Vec<String>),
+}
+
+impl From<&Value> for PropertyValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Text(text) => PropertyValue::Text(text.clone()),
+            Value::Integer(number) => PropertyValue::Integer(*number),
+            Value::Float(number) => PropertyValue::Float(*number),
+            Value::Bool(flag) => PropertyValue::Bool(*flag),
+            Value::Date(date) => PropertyValue::Date(*date),
+            Value::Refs(names) => PropertyValue::Refs(names.clone()),
+        }
+    }
+}
+
+impl PropertyValue {
+    fn to_edn(&self) -> Edn {
+        match self {
+            PropertyValue::Text(text) => Edn::String(text.clone()),
+            PropertyValue::Integer(number) => Edn::Integer(*number),
+            PropertyValue::Float(number) => Edn::Float(*number),
+            PropertyValue::Bool(flag) => Edn::Bool(*flag),
+            PropertyValue::Date(date) => Edn::String(date.to_string()),
+            PropertyValue::Refs(names) => {
+                Edn::Set(names.iter().map(|name| Edn::String(name.clone())).collect())
+            }
+        }
+    }
+}
+
+/// A page of the dump: a page or journal of the graph, or a page that is only referred to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageEntity {
+    /// Entity id, unique among pages and blocks, starting at 1
+    pub id: usize,
+    /// Lowercase name, see [page_key]
+    pub name: String,
+    /// Name as written in the file name, title or first reference
+    pub original_name: String,
+    /// File of the page, relative to the graph, or None for pages without a file
+    pub file: Option<PathBuf>,
+    /// Date of a journal as a `YYYYMMDD` number, like Logseq's `:block/journal-day`
+    pub journal_day: Option<u32>,
+    /// Ids of the pages of the `alias::` property
+    pub aliases: Vec<usize>,
+    /// Page properties by key
+    pub properties: BTreeMap<String, PropertyValue>,
+    /// Ids of the pages the page properties refer to, like `tags::`, aliases excluded
+    pub refs: Vec<usize>,
+}
+
+/// A block of the dump
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockEntity {
+    /// Entity id, unique among pages and blocks
+    pub id: usize,
+    /// UUID of the `id::` property, in lowercase, if the block has one
+    pub uuid: Option<String>,
+    /// Id of the page of the block
+    pub page: usize,
+    /// Id of the parent block, or of the page for top-level blocks
+    pub parent: usize,
+    /// Position among the children of the parent, starting at 0
+    pub order: usize,
+    /// Nesting level, starting at 1 for top-level blocks
+    pub level: usize,
+    /// Line of the bullet in the file, starting at 1
+    pub line: usize,
+    /// Text of the block without the bullet, properties included
+    pub content: String,
+    /// Block properties by key
+    pub properties: BTreeMap<String, PropertyValue>,
+    /// Ids of the pages and blocks the block refers to, through `[[links]]`, `#tags`,
+    /// `((block refs))`, embeds and properties
+    pub refs: Vec<usize>,
+}
+
+/// Everything in a graph, as entities that refer to each other by id
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GraphDump {
+    /// The pages and journals in path order, then the pages that are only referred to
+    pub pages: Vec<PageEntity>,
+    /// The blocks of each page, depth first
+    pub blocks: Vec<BlockEntity>,
+}
+
+/// Properties by key, as dumped
+fn property_map(properties: &Properties) -> BTreeMap<String, PropertyValue> {
+    properties
+        .iter()
+        .map(|property| (property.key.clone(), PropertyValue::from(&property.value)))
+        .collect()
+}
+
+/// The names of the pages the properties refer to, aliases excluded
+fn property_refs(properties: &Properties) -> Vec<String> {
+    properties
+        .iter()
+        .filter(|property| !property.key.eq_ignore_ascii_case("alias"))
+        .filter_map(|property| match &property.value {
+            Value::Refs(names) => Some(names.clone()),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// The names of the pages a block refers to, and the UUIDs of the blocks
+fn block_references(block: &Block) -> (Vec<String>, Vec<String>) {
+    let mut names = property_refs(&block.properties());
+    let mut uuids = Vec::new();
+    for (_, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+        let links = page_links(text).into_iter().chain(tag_refs(text));
+        names.extend(links.map(|link| link.name));
+        uuids.extend(block_refs(text).into_iter().map(|block_ref| block_ref.uuid));
+    }
+    (names, uuids)
+}
+
+/// The ids of pages by lowercase name, adding the pages that don't exist
+struct PageIds {
+    ids: HashMap<String, usize>,
+    pages: Vec<PageEntity>,
+}
+
+impl PageIds {
+    fn id(&mut self, name: &str) -> usize {
+        let key = page_key(name);
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.pages.len() + 1;
+        self.pages.push(PageEntity {
+            id,
+            name: key.clone(),
+            original_name: name.to_string(),
+            file: None,
+            journal_day: None,
+            aliases: Vec::new(),
+            properties: BTreeMap::new(),
+            refs: Vec::new(),
+        });
+        self.ids.insert(key, id);
+        id
+    }
+
+    fn ids(&mut self, names: &[String]) -> Vec<usize> {
+        let mut ids = Vec::new();
+        for name in names {
+            let id = self.id(name);
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+}
+
+/// A block of the graph with the id it gets in the dump
+struct Placed<'a> {
+    block: &'a Block,
+    id: usize,
+    page: usize,
+    parent: usize,
+    order: usize,
+}
+
+/// Gives ids to blocks and their children, depth first, after `last_id`
+fn place<'a>(
+    blocks: &'a [Block],
+    page: usize,
+    parent: usize,
+    last_id: &mut usize,
+    placed: &mut Vec<Placed<'a>>,
+) {
+    for (order, block) in blocks.iter().enumerate() {
+        *last_id += 1;
+        let id = *last_id;
+        placed.push(Placed {
+            block,
+            id,
+            page,
+            parent,
+            order,
+        });
+        place(&block.children, page, id, last_id, placed);
+    }
+}
+
+/// Dumps the pages and blocks of a graph. Pages are named by their lowercase name, so that
+/// references to a page in any case point to the same entity. Pages that are referred to
+/// without a file, aliases included, get an entity without a file, as in Logseq.
+/// References to blocks that don't exist are left out.
+pub fn dump_graph(graph: &Graph) -> GraphDump {
+    let mut pages = PageIds {
+        ids: HashMap::new(),
+        pages: Vec::new(),
+    };
+    for page in &graph.pages {
+        let id = pages.id(&page.name);
+        let entity = &mut pages.pages[id - 1];
+        entity.original_name = page.name.clone();
+        entity.file = Some(
+            page.path
+                .strip_prefix(&graph.root)
+                .unwrap_or(&page.path)
+                .to_path_buf(),
+        );
+        entity.journal_day = page
+            .journal_date
+            .map(|date| date.year() as u32 * 10000 + date.month() * 100 + date.day());
+        entity.properties = property_map(&page.outline.page_properties());
+    }
+    for page in &graph.pages {
+        let aliases = pages.ids(&page.aliases());
+        let id = pages.id(&page.name);
+        pages.pages[id - 1].aliases = aliases;
+    }
+    // Names first, so that all pages come before the blocks
+    let mut references = Vec::new();
+    for page in &graph.pages {
+        let refs = pages.ids(&property_refs(&page.outline.page_properties()));
+        let id = pages.id(&page.name);
+        pages.pages[id - 1].refs = refs;
+        for block in page.outline.iter() {
+            let (names, uuids) = block_references(block);
+            references.push((pages.ids(&names), uuids));
+        }
+    }
+
+    let mut placed = Vec::new();
+    let mut last_id = pages.pages.len();
+    for page in &graph.pages {
+        let page_id = pages.id(&page.name);
+        place(
+            &page.outline.blocks,
+            page_id,
+            page_id,
+            &mut last_id,
+            &mut placed,
+        );
+    }
+    let uuid = |block: &Block| {
+        block
+            .properties()
+            .get("id")
+            .map(|id| id.raw.trim().to_lowercase())
+    };
+    let by_uuid: HashMap<String, usize> = placed
+        .iter()
+        .filter_map(|placed| Some((uuid(placed.block)?, placed.id)))
+        .collect();
+    // `outline.iter()` and `place` both go depth first
+    let blocks = placed
+        .iter()
+        .zip(references)
+        .map(|(placed, (mut refs, uuids))| {
+            for block_id in uuids.iter().filter_map(|uuid| by_uuid.get(uuid)) {
+                if !refs.contains(block_id) {
+                    refs.push(*block_id);
+                }
+            }
+            BlockEntity {
+                id: placed.id,
+                uuid: uuid(placed.block),
+                page: placed.page,
+                parent: placed.parent,
+                order: placed.order,
+                level: placed.block.level + 1,
+                line: placed.block.line,
+                content: placed.block.content.clone(),
+                properties: property_map(&placed.block.properties()),
+                refs,
+            }
+        })
+        .collect();
+    GraphDump {
+        pages: pages.pages,
+        blocks,
+    }
+}
+
+/// How a graph dump is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A JSON object with the `pages` and `blocks` arrays of a [GraphDump]
+    #[default]
+    Json,
+    /// An EDN vector of entity maps with Logseq's attribute names, like `:block/name`, that
+    /// can be transacted into a Datascript database
+    Edn,
+}
+
+impl FromStr for DumpFormat {
+    type Err = Error;
+
+    /// Parses `json` and `edn`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Ok(DumpFormat::Json),
+            "edn" => Ok(DumpFormat::Edn),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected json or edn", name),
+            )),
+        }
+    }
+}
+
+/// A map entry with a keyword key
+fn entry(key: &str, value: Edn) -> (Edn, Edn) {
+    (Edn::Keyword(key.to_string()), value)
+}
+
+fn edn_ids(ids: &[usize]) -> Edn {
+    Edn::Vector(ids.iter().map(|&id| Edn::Integer(id as i64)).collect())
+}
+
+fn edn_properties(properties: &BTreeMap<String, PropertyValue>) -> Edn {
+    Edn::Map(
+        properties
+            .iter()
+            .map(|(key, value)| entry(key, value.to_edn()))
+            .collect(),
+    )
+}
+
+impl PageEntity {
+    fn to_edn(&self) -> Edn {
+        let mut entries = vec![
+            entry("db/id", Edn::Integer(self.id as i64)),
+            entry("block/name", Edn::String(self.name.clone())),
+            entry(
+                "block/original-name",
+                Edn::String(self.original_name.clone()),
+            ),
+        ];
+        if let Some(file) = &self.file {
+            let path = file.to_string_lossy().replace('\\', "/");
+            entries.push(entry("block/file", Edn::String(path)));
+        }
+        entries.push(entry(
+            "block/journal?",
+            Edn::Bool(self.journal_day.is_some()),
+        ));
+        if let Some(day) = self.journal_day {
+            entries.push(entry("block/journal-day", Edn::Integer(day.into())));
+        }
+        if !self.aliases.is_empty() {
+            entries.push(entry("block/alias", edn_ids(&self.aliases)));
+        }
+        if !self.properties.is_empty() {
+            entries.push(entry("block/properties", edn_properties(&self.properties)));
+        }
+        if !self.refs.is_empty() {
+            entries.push(entry("block/refs", edn_ids(&self.refs)));
+        }
+        Edn::Map(entries)
+    }
+}
+
+impl BlockEntity {
+    fn to_edn(&self) -> Edn {
+        let mut entries = vec![entry("db/id", Edn::Integer(self.id as i64))];
+        if let Some(uuid) = &self.uuid {
+            let tagged = Edn::Tagged("uuid".to_string(), Box::new(Edn::String(uuid.clone())));
+            entries.push(entry("block/uuid", tagged));
+        }
+        entries.extend([
+            entry("block/page", Edn::Integer(self.page as i64)),
+            entry("block/parent", Edn::Integer(self.parent as i64)),
+            entry("block/order", Edn::Integer(self.order as i64)),
+            entry("block/level", Edn::Integer(self.level as i64)),
+            entry("block/line", Edn::Integer(self.line as i64)),
+            entry("block/content", Edn::String(self.content.clone())),
+        ]);
+        if !self.properties.is_empty() {
+            entries.push(entry("block/properties", edn_properties(&self.properties)));
+        }
+        if !self.refs.is_empty() {
+            entries.push(entry("block/refs", edn_ids(&self.refs)));
+        }
+        Edn::Map(entries)
+    }
+}
+
+impl DumpFormat {
+    /// Writes a dump in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::export::database::{BlockEntity, DumpFormat, GraphDump, PageEntity};
+    /// let page = PageEntity {
+    ///     id: 1,
+    ///     name: "dune".to_string(),
+    ///     original_name: "Dune".to_string(),
+    ///     file: Some("pages/Dune.md".into()),
+    ///     journal_day: None,
+    ///     aliases: Vec::new(),
+    ///     properties: Default::default(),
+    ///     refs: Vec::new(),
+    /// };
+    /// let block = BlockEntity {
+    ///     id: 2,
+    ///     uuid: None,
+    ///     page: 1,
+    ///     parent: 1,
+    ///     order: 0,
+    ///     level: 1,
+    ///     line: 1,
+    ///     content: "Read [[Dune]]".to_string(),
+    ///     properties: Default::default(),
+    ///     refs: vec![1],
+    /// };
+    /// let dump = GraphDump { pages: vec![page], blocks: vec![block] };
+    /// assert_eq!("edn".parse::<DumpFormat>().unwrap().render(&dump), concat!(
+    ///     "[{:db/id 1, :block/name \"dune\", :block/original-name \"Dune\", :block/file \"pages/Dune.md\", :block/journal? false}\n",
+    ///     " {:db/id 2, :block/page 1, :block/parent 1, :block/order 0, :block/level 1, :block/line 1, :block/content \"Read [[Dune]]\", :block/refs [1]}]\n",
+    /// ));
+    /// assert!(DumpFormat::Json.render(&dump).contains("\"original_name\": \"Dune\""));
+    /// ```
+    pub fn render(&self, dump: &GraphDump) -> String {
+        match self {
+            DumpFormat::Json => serde_json::to_string_pretty(dump).unwrap_or_default() + "\n",
+            DumpFormat::Edn => {
+                let entities: Vec<String> = dump
+                    .pages
+                    .iter()
+                    .map(PageEntity::to_edn)
+                    .chain(dump.blocks.iter().map(BlockEntity::to_edn))
+                    .map(|entity| entity.to_string())
+                    .collect();
+                format!("[{}]\n", entities.join("\n "))
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Export the links between the pages of a graph as Graphviz DOT or GraphML, to draw the
+//! structure of the graph outside Logseq and see its clusters and orphans
+
+use crate::graph::{page_key, Graph, GraphPage};
+use crate::properties::Value;
+use crate::{Error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Which pages are exported; the links are the ones between these pages
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkFilter {
+    /// Only the pages of this namespace, with the namespace page itself: `project` keeps
+    /// `project` and `project/sub/page`
+    pub namespace: Option<String>,
+    /// Only the pages with this page in their `tags::`
+    pub tag: Option<String>,
+}
+
+impl LinkFilter {
+    /// True if the page is exported, comparing names like [page_key]
+    fn matches(&self, page: &GraphPage) -> bool {
+        let name = page_key(&page.name);
+        let in_namespace = self.namespace.as_ref().is_none_or(|namespace| {
+            let namespace = page_key(namespace.trim_end_matches('/'));
+            name == namespace || name.starts_with(&format!("{}/", namespace))
+        });
+        let tagged = self.tag.as_ref().is_none_or(|tag| {
+            let tag = page_key(tag.trim_start_matches('#'));
+            match page.outline.page_properties().value("tags") {
+                Some(Value::Refs(names)) => names.iter().any(|name| page_key(name) == tag),
+                _ => false,
+            }
+        });
+        in_namespace && tagged
+    }
+}
+
+/// A page of the link graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageNode {
+    /// Name of the page
+    pub name: String,
+    /// True for journals
+    pub journal: bool,
+}
+
+/// Links from a page to another one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageEdge {
+    /// Index of the page with the links in [LinkGraph::nodes]
+    pub source: usize,
+    /// Index of the page linked to in [LinkGraph::nodes]
+    pub target: usize,
+    /// How many times the source refers to the target
+    pub count: usize,
+}
+
+/// The pages of a graph, and the links between them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkGraph {
+    /// The pages, in the order of the graph
+    pub nodes: Vec<PageNode>,
+    /// The links, by source then target
+    pub edges: Vec<PageEdge>,
+}
+
+/// The pages of a graph that match a filter, with the `[[links]]`, `#tags`, embeds and
+/// reference properties between them (see [GraphPage::referenced_names]). References to an
+/// alias are links to its page; references to pages without a file, and of a page to itself,
+/// are left out.
+pub fn link_graph(graph: &Graph, filter: &LinkFilter) -> LinkGraph {
+    let pages: Vec<&GraphPage> = graph
+        .pages
+        .iter()
+        .filter(|page| filter.matches(page))
+        .collect();
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+    for (index, page) in pages.iter().enumerate() {
+        indexes.entry(page_key(&page.name)).or_insert(index);
+    }
+    for (index, page) in pages.iter().enumerate() {
+        for alias in page.aliases() {
+            indexes.entry(page_key(&alias)).or_insert(index);
+        }
+    }
+    let mut counts: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for (source, page) in pages.iter().enumerate() {
+        for name in page.referenced_names() {
+            match indexes.get(&page_key(&name)) {
+                Some(&target) if target != source => {
+                    *counts.entry((source, target)).or_default() += 1
+                }
+                _ => {}
+            }
+        }
+    }
+    LinkGraph {
+        nodes: pages
+            .iter()
+            .map(|page| PageNode {
+                name: page.name.clone(),
+                journal: page.journal_date.is_some(),
+            })
+            .collect(),
+        edges: counts
+            .into_iter()
+            .map(|((source, target), count)| PageEdge {
+                source,
+                target,
+                count,
+            })
+            .collect(),
+    }
+}
+
+/// How a link graph is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkFormat {
+    /// A Graphviz `digraph`, for `dot` and the other Graphviz layouts
+    #[default]
+    Dot,
+    /// GraphML, for Gephi, yEd or Cytoscape
+    GraphMl,
+}
+
+impl FromStr for LinkFormat {
+    type Err = Error;
+
+    /// Parses `dot` and `graphml`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "dot" => Ok(LinkFormat::Dot),
+            "graphml" => Ok(LinkFormat::GraphMl),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected dot or graphml", name),
+            )),
+        }
+    }
+}
+
+/// A DOT string
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Text escaped for XML
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl LinkFormat {
+    /// Writes a link graph in this format. Journals are drawn as boxes in DOT, and have the
+    /// `journal` attribute in GraphML; links have their number of references as `weight`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::export::links::{LinkFormat, LinkGraph, PageEdge, PageNode};
+    /// let graph = LinkGraph {
+    ///     nodes: vec![
+    ///         PageNode { name: "Jan 2nd, 2024".to_string(), journal: true },
+    ///         PageNode { name: "Dune \"book\"".to_string(), journal: false },
+    ///     ],
+    ///     edges: vec![PageEdge { source: 0, target: 1, count: 2 }],
+    /// };
+    /// assert_eq!(LinkFormat::Dot.render(&graph), concat!(
+    ///     "digraph logseq {\n",
+    ///     "  0 [label=\"Jan 2nd, 2024\", shape=box];\n",
+    ///     "  1 [label=\"Dune \\\"book\\\"\"];\n",
+    ///     "  0 -> 1 [weight=2];\n",
+    ///     "}\n",
+    /// ));
+    /// assert!(LinkFormat::GraphMl.render(&graph).contains(
+    ///     "<node id=\"n1\"><data key=\"label\">Dune &quot;book&quot;</data><data key=\"journal\">false</data></node>"
+    /// ));
+    /// ```
+    pub fn render(&self, graph: &LinkGraph) -> String {
+        let mut text = String::new();
+        match self {
+            LinkFormat::Dot => {
+                text.push_str("digraph logseq {\n");
+                for (index, node) in graph.nodes.iter().enumerate() {
+                    let shape = if node.journal { ", shape=box" } else { "" };
+                    text.push_str(&format!(
+                        "  {} [label={}{}];\n",
+                        index,
+                        dot_string(&node.name),
+                        shape
+                    ));
+                }
+                for edge in &graph.edges {
+                    text.push_str(&format!(
+                        "  {} -> {} [weight={}];\n",
+                        edge.source, edge.target, edge.count
+                    ));
+                }
+                text.push_str("}\n");
+            }
+            LinkFormat::GraphMl => {
+                text.push_str(concat!(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                    "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+                    "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n",
+                    "  <key id=\"journal\" for=\"node\" attr.name=\"journal\" attr.type=\"boolean\"/>\n",
+                    "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n",
+                    "  <graph id=\"logseq\" edgedefault=\"directed\">\n",
+                ));
+                for (index, node) in graph.nodes.iter().enumerate() {
+                    text.push_str(&format!(
+                        "    <node id=\"n{}\"><data key=\"label\">{}</data><data key=\"journal\">{}</data></node>\n",
+                        index,
+                        xml_escape(&node.name),
+                        node.journal
+                    ));
+                }
+                for edge in &graph.edges {
+                    text.push_str(&format!(
+                        "    <edge source=\"n{}\" target=\"n{}\"><data key=\"weight\">{}</data></edge>\n",
+                        edge.source, edge.target, edge.count
+                    ));
+                }
+                text.push_str("  </graph>\n</graphml>\n");
+            }
+        }
+        text
+    }
+}
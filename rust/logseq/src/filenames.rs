@@ -0,0 +1,157 @@
+//! Encode page file names the way Logseq does: `/` as `___` (or the `%2F` of legacy graphs),
+//! and `:`, `?`, `%` and the other characters that aren't allowed in file names as `%3A`,
+//! `%3F` and `%25`, see
+//! [file_stem_from_page_name](crate::graph::file_stem_from_page_name)
+//!
+//! Files created outside Logseq often aren't encoded, or are encoded in other ways, like the
+//! `%20` of downloaded files. Logseq decodes what it can and shows another name than the one
+//! links use, so that they silently break.
+
+use crate::graph::{page_key, page_name_from_file_stem, Graph};
+use crate::namespaces::Separator;
+use crate::rename::{rewrite_references, write_edits, FileEdit};
+use crate::{error, undo, Error, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The page name a file name (without extension) stands for: the name Logseq decodes from it,
+/// or, when decoding `%XX` sequences doesn't give valid UTF-8, the file name as it is, with its
+/// `%` taken literally
+///
+/// # Examples
+///
+/// ```
+/// use logseq::filenames::intended_page_name;
+/// assert_eq!(intended_page_name("what%3F"), "what?");
+/// assert_eq!(intended_page_name("project___My%20Page"), "project/My Page");
+/// assert_eq!(intended_page_name("100%AB"), "100%AB");
+/// ```
+pub fn intended_page_name(stem: &str) -> String {
+    let decoded = page_name_from_file_stem(stem);
+    if decoded.contains(char::REPLACEMENT_CHARACTER) && !stem.contains(char::REPLACEMENT_CHARACTER)
+    {
+        return stem.replace("___", "/");
+    }
+    decoded
+}
+
+/// The file name (without extension) Logseq would give the page of a file name, keeping the
+/// way it encodes `/`; the same file name if it is already encoded that way
+///
+/// # Examples
+///
+/// ```
+/// use logseq::filenames::canonical_file_stem;
+/// assert_eq!(canonical_file_stem("what?"), "what%3F");
+/// assert_eq!(canonical_file_stem("100% done"), "100%25 done");
+/// assert_eq!(canonical_file_stem("a%2fb: c"), "a%2Fb%3A c");
+/// assert_eq!(canonical_file_stem("project___sub"), "project___sub");
+/// ```
+pub fn canonical_file_stem(stem: &str) -> String {
+    let name = intended_page_name(stem);
+    Separator::of_file(stem, &name)
+        .unwrap_or(Separator::TripleLowbar)
+        .file_stem(&name)
+}
+
+/// Everything a repair of file names changes: the files it renames and the links it rewrites
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileNameRepair {
+    /// The (old, new) paths of the files whose name isn't encoded like Logseq does, sorted by
+    /// path
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    /// The files with links to the renamed pages that change, sorted by path
+    pub edits: Vec<FileEdit>,
+}
+
+impl FileNameRepair {
+    /// True if all file names are already encoded
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.edits.is_empty()
+    }
+
+    /// Applies the repair. The rewritten files are written to temporary files first, so a
+    /// failure leaves them untouched; the files are renamed last.
+    pub fn apply(&self) -> Result<()> {
+        write_edits(&self.edits)?;
+        for (path, new_path) in &self.renames {
+            undo::move_file(path, new_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans the repair of the file names of a graph: pages whose file name isn't the
+/// [canonical_file_stem] are renamed to it. Links to the name Logseq showed, when it differs
+/// from the [intended_page_name], and links to the file name as it was written, like
+/// `[[My%20Page]]`, are rewritten to the intended name, unless another page has that name.
+/// Journals, and pages named by a `title::` property, are left alone.
+/// Nothing is written; see [FileNameRepair::apply].
+/// Fails if the new name of a file is already taken by another file.
+pub fn plan_file_name_repair(graph: &Graph) -> Result<FileNameRepair> {
+    let mut repair = FileNameRepair::default();
+    // References to rewrite, as (key of the old name, new name)
+    let mut rewrites: Vec<(String, String)> = Vec::new();
+    for page in &graph.pages {
+        if page.journal_date.is_some() || page.outline.page_properties().get("title").is_some() {
+            continue;
+        }
+        let (Some(stem), Some(extension)) = (page.path.file_stem(), page.path.extension()) else {
+            continue;
+        };
+        let stem = stem.to_string_lossy();
+        let canonical = canonical_file_stem(&stem);
+        if canonical == stem {
+            continue;
+        }
+        let new_path =
+            page.path
+                .with_file_name(format!("{}.{}", canonical, extension.to_string_lossy()));
+        let same_file =
+            page_key(&new_path.to_string_lossy()) == page_key(&page.path.to_string_lossy());
+        let taken = (new_path.exists() && !same_file)
+            || repair.renames.iter().any(|(_, other)| *other == new_path);
+        if taken {
+            return Err(Error::parse(
+                Some(&page.path),
+                format!("can't rename to {}, it already exists", new_path.display()),
+            ));
+        }
+        repair.renames.push((page.path.clone(), new_path));
+
+        let name = intended_page_name(&stem);
+        for old in [page.name.as_str(), &stem.replace("___", "/")] {
+            let old_key = page_key(old);
+            let named = graph
+                .pages
+                .iter()
+                .any(|other| other.path != page.path && page_key(&other.name) == old_key);
+            if old_key != page_key(&name) && !named {
+                rewrites.push((old_key, name.clone()));
+            }
+        }
+    }
+    repair.renames.sort();
+
+    if !rewrites.is_empty() {
+        let mut texts: BTreeMap<&PathBuf, (String, String)> = BTreeMap::new();
+        for page in &graph.pages {
+            let original = error::read_to_string(&page.path)?;
+            let mut updated = original.clone();
+            for (old_key, new) in &rewrites {
+                updated = rewrite_references(page, &updated, old_key, new, false);
+            }
+            texts.insert(&page.path, (original, updated));
+        }
+        repair.edits = texts
+            .into_iter()
+            .filter(|(_, (original, updated))| original != updated)
+            .map(|(path, (original, updated))| FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            })
+            .collect();
+    }
+    Ok(repair)
+}
@@ -0,0 +1,179 @@
+//! YAML front matter, which other tools write at the top of Markdown files
+
+use crate::dates::DateFormat;
+use chrono::NaiveDate;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static KEY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)([^\s:#][^:]*?):(?:\s+(.*))?$").unwrap());
+static DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{4}-\d{2}-\d{2})(?:[T ][0-9:.]+(?:Z|[+-][0-9:]+)?)?$").unwrap()
+});
+
+/// How dates of the front matter are written as property values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateStyle {
+    /// As they are, e.g. `2024-01-02`, which Logseq reads as a date
+    #[default]
+    Iso,
+    /// As a link to the journal of the day, e.g. `[[Jan 2nd, 2024]]` in the default title
+    /// format; times are dropped
+    Journal,
+}
+
+/// Splits a text into its YAML front matter (without the `---` lines) and the rest
+///
+/// # Examples
+///
+/// ```
+/// use logseq::frontmatter::split_front_matter;
+/// assert_eq!(split_front_matter("---\ntitle: x\n---\n- block\n"), Some(("title: x", "- block\n")));
+/// assert_eq!(split_front_matter("- block\n---\n"), None);
+/// ```
+pub fn split_front_matter(text: &str) -> Option<(&str, &str)> {
+    let rest = text.strip_prefix("---\n")?;
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = rest[..offset].trim_end_matches('\n');
+            return Some((yaml, &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Removes the quotes around a YAML scalar
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 {
+        if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            return inner.replace("\\\"", "\"");
+        }
+        if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+            return inner.replace("''", "'");
+        }
+    }
+    value.to_string()
+}
+
+/// Converts YAML front matter into `(key, value)` page properties.
+///
+/// Lists become comma-separated values, nested mappings become dotted keys like
+/// `author.name`, and multi-line strings are joined into one line. `aliases` becomes `alias`,
+/// `tag` becomes `tags`, and the `#` of tags is removed. Empty values are dropped. Journal dates
+/// are titled in `title_format`.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::dates::DateFormat;
+/// use logseq::frontmatter::{front_matter_properties, DateStyle};
+/// let yaml = "title: \"Dune: Messiah\"\naliases: [Messiah]\ntags:\n  - '#books'\n  - scifi\ncreated: 2024-01-02T10:00:00Z\nauthor:\n  name: Frank\nsummary: >\n  Paul, after\n  the war\nempty:\n";
+/// let properties = front_matter_properties(yaml, DateStyle::Journal, &DateFormat::default());
+/// let lines: Vec<String> = properties.iter().map(|(key, value)| format!("{}:: {}", key, value)).collect();
+/// assert_eq!(lines, vec![
+///     "title:: Dune: Messiah",
+///     "alias:: Messiah",
+///     "tags:: books, scifi",
+///     "created:: [[Jan 2nd, 2024]]",
+///     "author.name:: Frank",
+///     "summary:: Paul, after the war",
+/// ]);
+/// ```
+pub fn front_matter_properties(
+    yaml: &str,
+    dates: DateStyle,
+    title_format: &DateFormat,
+) -> Vec<(String, String)> {
+    let mut properties: Vec<(String, Vec<String>)> = Vec::new();
+    // Keys with an empty value, which may hold a list or a mapping, with their indentation
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut lines = yaml.lines().peekable();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or((trimmed == "-").then_some(""))
+        {
+            while parents.last().is_some_and(|(parent, _)| *parent > indent) {
+                parents.pop();
+            }
+            let Some((_, key)) = parents.last() else {
+                continue;
+            };
+            match properties.iter_mut().find(|(existing, _)| existing == key) {
+                Some((_, values)) => values.push(unquote(item)),
+                None => properties.push((key.clone(), vec![unquote(item)])),
+            }
+            continue;
+        }
+        let Some(captures) = KEY_RE.captures(line) else {
+            continue;
+        };
+        while parents.last().is_some_and(|(parent, _)| *parent >= indent) {
+            parents.pop();
+        }
+        let name = unquote(&captures[2]).to_lowercase();
+        let key = match parents.last() {
+            Some((_, parent)) => format!("{}.{}", parent, name),
+            None => match name.as_str() {
+                "aliases" => "alias".to_string(),
+                "tag" => "tags".to_string(),
+                _ => name,
+            },
+        };
+        let value = captures.get(3).map_or("", |value| value.as_str()).trim();
+        let values = if value.is_empty() {
+            parents.push((indent, key.clone()));
+            continue;
+        } else if value.starts_with(['|', '>']) {
+            // A multi-line string, made of the more indented lines that follow
+            let mut text = Vec::new();
+            while let Some(next) = lines.next_if(|next| {
+                next.trim().is_empty() || next.len() - next.trim_start().len() > indent
+            }) {
+                text.push(next.trim());
+            }
+            vec![text.join(" ").trim().to_string()]
+        } else if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            items.split(',').map(unquote).collect()
+        } else if value == "~" || value == "null" {
+            Vec::new()
+        } else {
+            vec![unquote(value)]
+        };
+        properties.push((key, values));
+    }
+
+    properties
+        .into_iter()
+        .filter_map(|(key, values)| {
+            let values: Vec<String> = values
+                .into_iter()
+                .map(|value| match key.as_str() {
+                    "tags" | "alias" => value.trim_start_matches('#').to_string(),
+                    _ => value,
+                })
+                .map(|value| {
+                    let date = DATE_RE.captures(&value).and_then(|captures| {
+                        NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").ok()
+                    });
+                    match (dates, date) {
+                        (DateStyle::Journal, Some(date)) => {
+                            format!("[[{}]]", title_format.format(date))
+                        }
+                        _ => value,
+                    }
+                })
+                .filter(|value| !value.is_empty())
+                .collect();
+            (!values.is_empty()).then(|| (key, values.join(", ")))
+        })
+        .collect()
+}
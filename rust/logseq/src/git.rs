@@ -0,0 +1,141 @@
+//! Find the files changed in the git repository of a graph, to only process those, and when
+//! its files and lines were committed
+
+use crate::{Error, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs git in a directory and returns its output
+fn git(directory: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()
+        .map_err(|source| Error::io(directory, source))?;
+    if !output.status.success() {
+        return Err(Error::parse(
+            Some(directory),
+            format!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// The files of the git repository containing `path` that are modified or staged, including
+/// new files that were added; untracked and deleted files aren't included. Paths are absolute
+/// and canonical.
+///
+/// This runs `git`, which must be installed.
+pub fn changed_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let directory = if path.is_dir() {
+        path
+    } else {
+        path.parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    };
+    let toplevel = git(directory, &["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel).trim());
+    let status = git(
+        directory,
+        &["status", "--porcelain=v1", "-z", "--untracked-files=no"],
+    )?;
+    let status = String::from_utf8_lossy(&status);
+    let mut files = Vec::new();
+    let mut entries = status.split('\0');
+    while let Some(entry) = entries.next() {
+        let (Some(code), Some(file)) = (entry.get(..2), entry.get(3..)) else {
+            continue;
+        };
+        if code.starts_with(['R', 'C']) {
+            // The original path of a rename or copy follows
+            entries.next();
+        }
+        if code.contains('D') {
+            continue;
+        }
+        let file = toplevel.join(file);
+        files.push(fs::canonicalize(&file).unwrap_or(file));
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Keeps only the files that are modified or staged in the git repository containing `path`,
+/// see [changed_files]
+pub fn keep_changed(files: Vec<PathBuf>, path: &Path) -> Result<Vec<PathBuf>> {
+    let changed: HashSet<PathBuf> = changed_files(path)?.into_iter().collect();
+    Ok(files
+        .into_iter()
+        .filter(|file| {
+            let canonical = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+            changed.contains(&canonical)
+        })
+        .collect())
+}
+
+/// The times of the first and the last commit of a file, in seconds since the Unix epoch,
+/// following renames; None if the file has no commits.
+///
+/// This runs `git`, which must be installed.
+pub fn file_commit_times(path: &Path) -> Result<Option<(i64, i64)>> {
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let log = git(
+        directory,
+        &[
+            "log",
+            "--follow",
+            "--format=%at",
+            "--",
+            &name.to_string_lossy(),
+        ],
+    )?;
+    // Newest first
+    let times: Vec<i64> = String::from_utf8_lossy(&log)
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect();
+    Ok(times.last().copied().zip(times.first().copied()))
+}
+
+/// The time each line of a file was last changed, from `git blame`, in seconds since the Unix
+/// epoch and from the first line; lines that aren't committed get the current time. None if
+/// the file has no commits.
+///
+/// This runs `git`, which must be installed.
+pub fn line_commit_times(path: &Path) -> Result<Option<Vec<i64>>> {
+    if file_commit_times(path)?.is_none() {
+        return Ok(None);
+    }
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    let blame = git(
+        directory,
+        &["blame", "--line-porcelain", "--", &name.to_string_lossy()],
+    )?;
+    // Each line comes after its headers, prefixed with a tab
+    let mut times = Vec::new();
+    let mut time = 0;
+    for line in String::from_utf8_lossy(&blame).lines() {
+        if let Some(seconds) = line.strip_prefix("author-time ") {
+            time = seconds.trim().parse().unwrap_or(0);
+        } else if line.starts_with('\t') {
+            times.push(time);
+        }
+    }
+    Ok(Some(times))
+}
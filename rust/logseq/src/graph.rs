@@ -0,0 +1,775 @@
+//! Scan a whole Logseq graph directory: pages, journals and the links between them
+
+use crate::batch::{no_progress, ProgressCallback, ProgressCounter};
+use crate::config::GraphConfig;
+use crate::dates::DateFormat;
+use crate::outline::{parse_outline, Outline};
+use crate::properties::{Properties, Value};
+use crate::refs::{
+    block_refs, lines_outside_code, lines_outside_regions, page_links, tag_refs, CODE_REGIONS,
+};
+use crate::{error, Error, Result, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use chrono::{Datelike, NaiveDate};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+pub mod backlinks;
+pub mod index;
+pub mod shared;
+
+/// A page or journal file of a graph, already parsed
+pub struct GraphPage {
+    /// Full path to the file
+    pub path: PathBuf,
+    /// Name of the page, as shown in Logseq
+    pub name: String,
+    /// Date of the journal, or None for regular pages
+    pub journal_date: Option<NaiveDate>,
+    /// Parsed contents of the file
+    pub outline: Outline,
+}
+
+impl GraphPage {
+    /// Reads and parses a page file; journals are detected by their parent directory and
+    /// named in the title format of their graph, see [page_name_from_path]
+    pub fn read(path: &Path) -> Result<Self> {
+        let title_format = match journal_date_of_file(path) {
+            Some(_) => journal_title_format(&journal_graph_root(path)),
+            None => DateFormat::default(),
+        };
+        GraphPage::read_with_title_format(path, &title_format)
+    }
+
+    /// Reads and parses a page file like [GraphPage::read], naming journals in `title_format`
+    pub(crate) fn read_with_title_format(path: &Path, title_format: &DateFormat) -> Result<Self> {
+        let contents = error::read_to_string(path)?;
+        Ok(GraphPage::from_outline(
+            path,
+            parse_outline(&contents),
+            title_format,
+        ))
+    }
+
+    /// The page of a file that was already parsed, naming journals in `title_format`
+    pub(crate) fn from_outline(path: &Path, outline: Outline, title_format: &DateFormat) -> Self {
+        let journal_date = journal_date_of_file(path);
+        let name = match journal_date {
+            Some(date) => title_format.format(date),
+            None => match outline.page_properties().get("title") {
+                Some(title) if !title.raw.is_empty() => title.raw.clone(),
+                _ => page_name_from_path(path),
+            },
+        };
+        GraphPage {
+            path: path.to_path_buf(),
+            name,
+            journal_date,
+            outline,
+        }
+    }
+
+    /// Names of the page given by its `alias::` property
+    pub fn aliases(&self) -> Vec<String> {
+        match self.outline.page_properties().value("alias") {
+            Some(Value::Refs(names)) => names.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Names of the pages this page refers to through `[[links]]`, `#tags`, embeds and
+    /// properties like `tags::`, ignoring code and the page's own aliases
+    pub fn referenced_names(&self) -> Vec<String> {
+        let property_refs = |properties: Properties| -> Vec<String> {
+            properties
+                .iter()
+                .filter(|property| !property.key.eq_ignore_ascii_case("alias"))
+                .filter_map(|property| match &property.value {
+                    Value::Refs(names) => Some(names.clone()),
+                    _ => None,
+                })
+                .flatten()
+                .collect()
+        };
+        let mut names = property_refs(self.outline.page_properties());
+        for block in self.outline.iter() {
+            names.extend(property_refs(block.properties()));
+            for (_, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                let links = page_links(text).into_iter().chain(tag_refs(text));
+                names.extend(links.map(|link| link.name));
+            }
+        }
+        names
+    }
+}
+
+/// A page that nothing links to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orphan {
+    /// Full path to the file
+    pub path: PathBuf,
+    /// Name of the page
+    pub name: String,
+    /// When the file was last modified
+    pub modified: SystemTime,
+}
+
+/// A `[[link]]` to a page or a `((ref))` to a block that doesn't exist in the graph, or a dead
+/// web link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// File containing the link
+    pub path: PathBuf,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column, starting at 1
+    pub column: usize,
+    /// Name of the missing page, UUID of the missing block, or URL of the dead link
+    pub target: String,
+}
+
+/// A file of a graph that can't be read, like a page that isn't valid UTF-8: not one of its
+/// pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnreadableFile {
+    /// The file
+    pub path: PathBuf,
+    /// Why it can't be read
+    pub message: String,
+}
+
+/// The id of the findings about files that can't be read
+pub const UNREADABLE_RULE: &str = "unreadable-file";
+
+impl UnreadableFile {
+    /// The unreadable file of an error while reading `path`
+    pub fn new(path: &Path, error: &Error) -> Self {
+        let message = match error {
+            Error::Io { source, .. } => source.to_string(),
+            Error::Parse { message, .. } => message.clone(),
+        };
+        UnreadableFile {
+            path: path.to_path_buf(),
+            message,
+        }
+    }
+}
+
+/// Page files that Logseq sees as the same page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicatePages {
+    /// The name the files have in common, normalized and lowercase
+    pub key: String,
+    /// The file the others should be merged into
+    pub canonical: PathBuf,
+    /// The other files, sorted by path
+    pub duplicates: Vec<PathBuf>,
+}
+
+/// What an alias collides with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasConflictKind {
+    /// The name of another page
+    PageName,
+    /// An alias of another page
+    Alias,
+}
+
+/// An alias that Logseq can't tell apart from another page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasConflict {
+    /// The alias, as written
+    pub alias: String,
+    /// File of the page with the alias
+    pub path: PathBuf,
+    /// Name of the page with the alias
+    pub page: String,
+    /// File of the page the alias collides with
+    pub other_path: PathBuf,
+    /// Name of the page the alias collides with
+    pub other_page: String,
+    /// Whether the alias is the other page's name or one of its aliases
+    pub kind: AliasConflictKind,
+}
+
+/// The key under which Logseq identifies a page: its name in Unicode NFC form, lowercase
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::page_key;
+/// assert_eq!(page_key("Caf\u{e9}"), page_key("CAFE\u{301}"));
+/// ```
+pub fn page_key(name: &str) -> String {
+    name.nfc().collect::<String>().to_lowercase()
+}
+
+/// All pages and journals of a Logseq graph
+pub struct Graph {
+    /// Root directory of the graph
+    pub root: PathBuf,
+    /// Pages and journals, sorted by path
+    pub pages: Vec<GraphPage>,
+    /// The files of pages and journals that can't be read, left out of `pages`, sorted by path
+    pub unreadable: Vec<UnreadableFile>,
+}
+
+impl Graph {
+    /// Reads every Markdown file in the `pages` and `journals` directories of the graph, but the
+    /// ones Logseq ignores, see [GraphConfig::is_hidden]. The files that can't be read are
+    /// left out, in [Graph::unreadable].
+    pub fn scan(root: &Path) -> Result<Self> {
+        Graph::scan_with_progress(root, &no_progress)
+    }
+
+    /// Reads the graph like [Graph::scan], in parallel, telling `progress` about each file read
+    pub fn scan_with_progress(root: &Path, progress: ProgressCallback<'_>) -> Result<Self> {
+        let paths = page_files(root)?;
+        let title_format = journal_title_format(root);
+        let counter = ProgressCounter::new(paths.len(), progress);
+        let read: Vec<(&PathBuf, Result<GraphPage>)> = paths
+            .par_iter()
+            .map(|path| {
+                let page = GraphPage::read_with_title_format(path, &title_format);
+                counter.tick(path);
+                (path, page)
+            })
+            .collect();
+        let mut pages = Vec::new();
+        let mut unreadable = Vec::new();
+        for (path, page) in read {
+            match page {
+                Ok(page) => pages.push(page),
+                Err(error) => unreadable.push(UnreadableFile::new(path, &error)),
+            }
+        }
+        Ok(Graph {
+            root: root.to_path_buf(),
+            pages,
+            unreadable,
+        })
+    }
+
+    /// Lowercase names of all pages and their aliases, as Logseq compares them case-insensitively
+    pub fn page_names(&self) -> HashSet<String> {
+        self.pages
+            .iter()
+            .flat_map(|page| std::iter::once(page.name.clone()).chain(page.aliases()))
+            .map(|name| name.to_lowercase())
+            .collect()
+    }
+
+    /// Lowercase keys of all page and block properties in the graph
+    pub fn property_keys(&self) -> HashSet<String> {
+        let mut keys = HashSet::new();
+        for page in &self.pages {
+            let properties = page.outline.page_properties();
+            keys.extend(
+                properties
+                    .iter()
+                    .map(|property| property.key.to_lowercase()),
+            );
+            for block in page.outline.iter() {
+                let properties = block.properties();
+                keys.extend(
+                    properties
+                        .iter()
+                        .map(|property| property.key.to_lowercase()),
+                );
+            }
+        }
+        keys
+    }
+
+    /// Returns the page or journal with the given name or alias, compared like [page_key].
+    /// A page with that name wins over pages with that alias, like in Logseq.
+    pub fn find_page(&self, name: &str) -> Option<&GraphPage> {
+        let key = page_key(name);
+        self.pages
+            .iter()
+            .find(|page| page_key(&page.name) == key)
+            .or_else(|| {
+                self.pages
+                    .iter()
+                    .find(|page| page.aliases().iter().any(|alias| page_key(alias) == key))
+            })
+    }
+
+    /// Finds `alias::` values that are the name of another page, or an alias of another page:
+    /// Logseq then merges the backlinks of these pages. Each pair of aliases is reported once,
+    /// for the second page by path.
+    pub fn find_alias_conflicts(&self) -> Vec<AliasConflict> {
+        let names: HashMap<String, &GraphPage> = self
+            .pages
+            .iter()
+            .map(|page| (page_key(&page.name), page))
+            .collect();
+        let mut aliases: HashMap<String, &GraphPage> = HashMap::new();
+        let mut conflicts = Vec::new();
+        for page in &self.pages {
+            for alias in page.aliases() {
+                let key = page_key(&alias);
+                let (other, kind) = match (names.get(&key), aliases.get(&key)) {
+                    (Some(other), _) => (*other, AliasConflictKind::PageName),
+                    (None, Some(other)) => (*other, AliasConflictKind::Alias),
+                    (None, None) => {
+                        aliases.insert(key, page);
+                        continue;
+                    }
+                };
+                if other.path == page.path {
+                    continue;
+                }
+                conflicts.push(AliasConflict {
+                    alias,
+                    path: page.path.clone(),
+                    page: page.name.clone(),
+                    other_path: other.path.clone(),
+                    other_page: other.name.clone(),
+                    kind,
+                });
+            }
+        }
+        conflicts
+    }
+
+    /// Finds the pages that no other page or journal links to, tags or embeds.
+    /// Journals, pages in the `:favorites` of `config.edn`, and parents of
+    /// namespaced pages (`project` for `project/sub`) are never orphans.
+    pub fn find_orphans(&self) -> Result<Vec<Orphan>> {
+        let mut orphans = Vec::new();
+        for (page, referenced) in self.pages.iter().zip(self.referenced_pages()?) {
+            if referenced || page.journal_date.is_some() {
+                continue;
+            }
+            let modified = fs::metadata(&page.path)
+                .and_then(|metadata| metadata.modified())
+                .map_err(|source| Error::io(&page.path, source))?;
+            orphans.push(Orphan {
+                path: page.path.clone(),
+                name: page.name.clone(),
+                modified,
+            });
+        }
+        Ok(orphans)
+    }
+
+    /// Whether each page, in the order of [Graph::pages], is referenced by another page, is in
+    /// the `:favorites` of `config.edn` or is the parent of a namespaced page
+    pub(crate) fn referenced_pages(&self) -> Result<Vec<bool>> {
+        let config = GraphConfig::read(&self.root)?;
+        let mut indexes: HashMap<String, usize> = HashMap::new();
+        for (index, page) in self.pages.iter().enumerate() {
+            for name in std::iter::once(page.name.clone()).chain(page.aliases()) {
+                indexes.entry(name.to_lowercase()).or_insert(index);
+            }
+        }
+
+        let mut referenced = vec![false; self.pages.len()];
+        for (index, page) in self.pages.iter().enumerate() {
+            let namespaces = page
+                .name
+                .match_indices('/')
+                .map(|(slash, _)| page.name[..slash].to_string());
+            for name in page.referenced_names().into_iter().chain(namespaces) {
+                match indexes.get(&name.to_lowercase()) {
+                    Some(&target) if target != index => referenced[target] = true,
+                    _ => {}
+                }
+            }
+        }
+        for favorite in &config.favorites {
+            if let Some(&target) = indexes.get(&favorite.to_lowercase()) {
+                referenced[target] = true;
+            }
+        }
+
+        Ok(referenced)
+    }
+
+    /// Finds page files that differ only by case, Unicode normalization or percent-encoding,
+    /// like `Foo.md`, `foo.md` and `Fo%6F.md`, which Logseq sees as the same page.
+    /// The merge target is preferably a file whose name needs no decoding and is already
+    /// normalized, then the largest one.
+    pub fn find_duplicates(&self) -> Result<Vec<DuplicatePages>> {
+        let mut groups: HashMap<String, Vec<&GraphPage>> = HashMap::new();
+        for page in self.pages.iter().filter(|page| page.journal_date.is_none()) {
+            groups.entry(page_key(&page.name)).or_default().push(page);
+        }
+
+        let mut duplicates = Vec::new();
+        for (key, pages) in groups.into_iter().filter(|(_, pages)| pages.len() > 1) {
+            let mut ranked = Vec::new();
+            for page in pages {
+                let stem = page
+                    .path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let plain = !stem.contains('%') && is_nfc(&stem);
+                let size = fs::metadata(&page.path)
+                    .map_err(|source| Error::io(&page.path, source))?
+                    .len();
+                ranked.push((plain, size, page.path.clone()));
+            }
+            ranked.sort_by(|a, b| (b.0, b.1, &a.2).cmp(&(a.0, a.1, &b.2)));
+            let mut paths = ranked.into_iter().map(|(_, _, path)| path);
+            let canonical = paths.next().unwrap_or_default();
+            let mut others: Vec<PathBuf> = paths.collect();
+            others.sort();
+            duplicates.push(DuplicatePages {
+                key,
+                canonical,
+                duplicates: others,
+            });
+        }
+        duplicates.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(duplicates)
+    }
+
+    /// Finds `[[links]]` that point to pages that don't exist, ignoring code
+    pub fn check_links(&self) -> Vec<BrokenLink> {
+        let names = self.page_names();
+        let mut broken = Vec::new();
+        for page in &self.pages {
+            for block in page.outline.iter() {
+                for (line, text) in lines_outside_code(block.numbered_lines()) {
+                    for link in page_links(text) {
+                        if !names.contains(&link.name.to_lowercase()) {
+                            broken.push(BrokenLink {
+                                path: page.path.clone(),
+                                line,
+                                column: block.source_column(link.column),
+                                target: link.name,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        broken
+    }
+
+    /// Lowercase UUIDs of all `id::` properties of blocks and pages in the graph
+    pub fn block_ids(&self) -> HashSet<String> {
+        let mut ids = HashSet::new();
+        for page in &self.pages {
+            let page_id = page.outline.page_properties().get("id").cloned();
+            let block_ids = page
+                .outline
+                .iter()
+                .filter_map(|block| block.properties().get("id").cloned());
+            for property in page_id.into_iter().chain(block_ids) {
+                ids.insert(property.raw.trim().to_lowercase());
+            }
+        }
+        ids
+    }
+
+    /// Finds `((uuid))` references and `{{embed ((uuid))}}` whose `id::` doesn't exist anywhere in the graph
+    pub fn check_block_refs(&self) -> Vec<BrokenLink> {
+        let ids = self.block_ids();
+        let mut broken = Vec::new();
+        for page in &self.pages {
+            for block in page.outline.iter() {
+                for (line, text) in lines_outside_code(block.numbered_lines()) {
+                    for block_ref in block_refs(text) {
+                        if !ids.contains(&block_ref.uuid) {
+                            broken.push(BrokenLink {
+                                path: page.path.clone(),
+                                line,
+                                column: block.source_column(block_ref.column),
+                                target: block_ref.uuid,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        broken
+    }
+}
+
+/// Finds the root of the graph a file or directory belongs to: the nearest directory (the path
+/// itself included) that has a `pages` or `journals` directory
+pub fn find_graph_root(path: &Path) -> Option<PathBuf> {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    absolute
+        .ancestors()
+        .find(|directory| is_graph_root(directory))
+        .map(Path::to_path_buf)
+}
+
+/// True if a directory has a `pages` or `journals` directory
+fn is_graph_root(directory: &Path) -> bool {
+    [SUBDIR_PAGES, SUBDIR_JOURNALS]
+        .iter()
+        .any(|subdir| directory.join(subdir).is_dir())
+}
+
+/// The page and journal files of a graph, sorted, without the ones Logseq ignores
+pub(crate) fn page_files(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for subdir in [SUBDIR_PAGES, SUBDIR_JOURNALS] {
+        collect_files(&root.join(subdir), "md", &mut paths)?;
+    }
+    paths.sort();
+    Ok(remove_hidden(root, paths))
+}
+
+/// The files that Logseq doesn't ignore in the graph at `root`, see [GraphConfig::is_hidden];
+/// all of them if its `config.edn` can't be read
+fn remove_hidden(root: &Path, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let config = GraphConfig::read(root).unwrap_or_default();
+    paths
+        .into_iter()
+        .filter(|path| !config.is_hidden(root, path))
+        .collect()
+}
+
+/// Lists the Markdown files to process for a path: the file itself, or all Markdown files under a directory.
+/// For the root of a graph, only the `pages` and `journals` directories are considered.
+/// Files of a directory that Logseq ignores, see [GraphConfig::is_hidden], are left out.
+pub fn markdown_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    if !path.is_dir() {
+        paths.push(path.to_path_buf());
+        return Ok(paths);
+    }
+    let subdirs = [SUBDIR_PAGES, SUBDIR_JOURNALS].map(|subdir| path.join(subdir));
+    if subdirs.iter().any(|subdir| subdir.is_dir()) {
+        for subdir in &subdirs {
+            collect_files(subdir, "md", &mut paths)?;
+        }
+    } else {
+        collect_files(path, "md", &mut paths)?;
+    }
+    paths.sort();
+    // Backups in `logseq/bak` have `pages` and `journals` directories too, so every graph the
+    // directory may be in is asked
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    for root in absolute
+        .ancestors()
+        .filter(|directory| is_graph_root(directory))
+    {
+        paths = remove_hidden(root, paths);
+    }
+    Ok(paths)
+}
+
+/// Appends to `paths` all files with the given extension under a directory, skipping hidden entries
+pub(crate) fn collect_files(dir: &Path, extension: &str, paths: &mut Vec<PathBuf>) -> Result<()> {
+    collect_files_matching(
+        dir,
+        &|path| path.extension().is_some_and(|ext| ext == extension),
+        paths,
+    )
+}
+
+/// The file of ignore patterns, like a `.gitignore` but only for Logseq Doctor, in a graph or
+/// any of its directories
+pub const IGNORE_FILE_NAME: &str = ".lsdignore";
+
+/// The path and the error of a failed walk, if it isn't one to skip: a symbolic link to a
+/// directory it is in, or to nothing
+fn walk_error(error: ignore::Error) -> Option<(Option<PathBuf>, std::io::Error)> {
+    match error {
+        ignore::Error::Loop { .. } => None,
+        ignore::Error::WithPath { path, err } => match walk_error(*err) {
+            Some((None, source)) => Some((Some(path), source)),
+            found => found,
+        },
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error(*err)
+        }
+        ignore::Error::Io(source) if source.kind() == std::io::ErrorKind::NotFound => None,
+        ignore::Error::Io(source) => Some((None, source)),
+        // Patterns of ignore files that don't parse are left out, like git does
+        _ => None,
+    }
+}
+
+/// Appends to `paths` all files under a directory that match a predicate, sorted, walking the
+/// directories in parallel. Hidden entries like `.git` are skipped, and so are the files ignored
+/// by the `.gitignore` files of a git repository, `.ignore` files and [IGNORE_FILE_NAME] files.
+/// Symbolic links are followed, like a linked `assets` directory, but not the ones to a
+/// directory they are in.
+pub(crate) fn collect_files_matching(
+    dir: &Path,
+    matches: &(dyn Fn(&Path) -> bool + Sync),
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    let found = std::sync::Mutex::new(Vec::new());
+    let failure = std::sync::Mutex::new(None);
+    ignore::WalkBuilder::new(dir)
+        .follow_links(true)
+        .add_custom_ignore_filename(IGNORE_FILE_NAME)
+        .build_parallel()
+        .run(|| {
+            Box::new(|entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        let Some((path, source)) = walk_error(error) else {
+                            return ignore::WalkState::Continue;
+                        };
+                        let error = Error::io(path.as_deref().unwrap_or(dir), source);
+                        failure.lock().unwrap().get_or_insert(error);
+                        return ignore::WalkState::Quit;
+                    }
+                };
+                let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+                if !is_dir && matches(entry.path()) {
+                    found.lock().unwrap().push(entry.into_path());
+                }
+                ignore::WalkState::Continue
+            })
+        });
+    if let Some(error) = failure.into_inner().unwrap() {
+        return Err(error);
+    }
+    let mut found = found.into_inner().unwrap();
+    found.sort();
+    paths.extend(found);
+    Ok(())
+}
+
+/// Decodes `%XX` sequences in a file name; invalid sequences are kept as they are
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Converts a page file name (without extension) into the page name shown in Logseq
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::page_name_from_file_stem;
+/// assert_eq!(page_name_from_file_stem("project___sub page"), "project/sub page");
+/// assert_eq!(page_name_from_file_stem("what%3F"), "what?");
+/// ```
+pub fn page_name_from_file_stem(stem: &str) -> String {
+    percent_decode(&stem.replace("___", "/"))
+}
+
+/// The date of a file of the `journals` directory named like `2024_01_02.md`
+fn journal_date_of_file(path: &Path) -> Option<NaiveDate> {
+    let in_journals = path
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == SUBDIR_JOURNALS);
+    let stem = path.file_stem()?.to_string_lossy();
+    in_journals
+        .then(|| NaiveDate::parse_from_str(&stem, "%Y_%m_%d").ok())
+        .flatten()
+}
+
+/// The name of the page of a file, from its file name: the title of the date of a journal, in
+/// the `:journal/page-title-format` of the `config.edn` of its graph, or else the page name of
+/// its file stem. A `title::` property takes precedence in Logseq.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::page_name_from_path;
+/// use std::path::Path;
+/// assert_eq!(page_name_from_path(Path::new("graph/pages/project___sub.md")), "project/sub");
+/// assert_eq!(page_name_from_path(Path::new("graph/journals/2024_01_02.md")), "Jan 2nd, 2024");
+/// ```
+pub fn page_name_from_path(path: &Path) -> String {
+    if let Some(date) = journal_date_of_file(path) {
+        return journal_title_format(&journal_graph_root(path)).format(date);
+    }
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    page_name_from_file_stem(&stem)
+}
+
+/// The graph of a file of the `journals` directory: the parent of that directory
+fn journal_graph_root(path: &Path) -> PathBuf {
+    path.parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+/// The format of the titles of the journals of the graph at `root`, from the
+/// `:journal/page-title-format` of its `config.edn`; Logseq's default if it can't be read
+pub fn journal_title_format(root: &Path) -> DateFormat {
+    let config = GraphConfig::read(root).unwrap_or_default();
+    DateFormat::new(&config.journal_page_title_format)
+}
+
+/// Converts a page name into the file name (without extension) Logseq gives it:
+/// `/` becomes `___`, and characters that aren't allowed in file names are percent-encoded
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::{file_stem_from_page_name, page_name_from_file_stem};
+/// assert_eq!(file_stem_from_page_name("project/sub page"), "project___sub page");
+/// assert_eq!(file_stem_from_page_name("what?"), "what%3F");
+/// assert_eq!(page_name_from_file_stem(&file_stem_from_page_name("a: b/c*")), "a: b/c*");
+/// ```
+pub fn file_stem_from_page_name(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.replace('/', "___").chars() {
+        if matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*' | '%') || c.is_control() {
+            let mut buffer = [0; 4];
+            for byte in c.encode_utf8(&mut buffer).bytes() {
+                stem.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            stem.push(c);
+        }
+    }
+    stem
+}
+
+/// Title of a journal page in Logseq's default format, e.g. `Jan 2nd, 2024`
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::journal_title;
+/// use chrono::NaiveDate;
+/// assert_eq!(journal_title(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()), "Jan 2nd, 2024");
+/// assert_eq!(journal_title(NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()), "Mar 11th, 2024");
+/// assert_eq!(journal_title(NaiveDate::from_ymd_opt(2024, 3, 23).unwrap()), "Mar 23rd, 2024");
+/// ```
+pub fn journal_title(date: NaiveDate) -> String {
+    let day = date.day();
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{} {}{}, {}", date.format("%b"), day, suffix, date.year())
+}
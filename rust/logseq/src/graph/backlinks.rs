@@ -0,0 +1,143 @@
+//! Backlinks: every reference to each page of a graph, with where it comes from
+
+use super::{page_key, Graph, GraphPage};
+use crate::properties::{split_property, Value};
+use crate::refs::{lines_outside_regions, page_links, tag_refs, CODE_REGIONS};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// How a page is referenced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceKind {
+    /// A `[[link]]`
+    Link,
+    /// A `#tag` or `#[[tag]]`
+    Tag,
+    /// An `{{embed [[page]]}}`
+    Embed,
+    /// A property value, like `tags:: page` or `author:: [[page]]`
+    Property,
+}
+
+impl ReferenceKind {
+    /// The kind in lowercase, e.g. `link`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferenceKind::Link => "link",
+            ReferenceKind::Tag => "tag",
+            ReferenceKind::Embed => "embed",
+            ReferenceKind::Property => "property",
+        }
+    }
+}
+
+impl fmt::Display for ReferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A reference to a page, from another page (or the page itself)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// The name of the referenced page, as written
+    pub target: String,
+    /// The file with the reference
+    pub path: PathBuf,
+    /// The name of the page with the reference
+    pub page: String,
+    /// True if the page with the reference is a journal
+    pub journal: bool,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column, starting at 1
+    pub column: usize,
+    /// How the page is referenced
+    pub kind: ReferenceKind,
+}
+
+/// The names a property line refers to, except for `alias::`, which names the page itself
+fn property_refs(text: &str) -> Option<Vec<String>> {
+    let (key, raw) = split_property(text)?;
+    match Value::parse(key, raw) {
+        Value::Refs(names) if !key.eq_ignore_ascii_case("alias") => Some(names),
+        _ => Some(Vec::new()),
+    }
+}
+
+fn page_references(page: &GraphPage) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut add = |target: String, line: usize, column: usize, kind: ReferenceKind| {
+        references.push(Reference {
+            target,
+            path: page.path.clone(),
+            page: page.name.clone(),
+            journal: page.journal_date.is_some(),
+            line,
+            column,
+            kind,
+        });
+    };
+    for (index, text) in page.outline.preamble.iter().enumerate() {
+        for name in property_refs(text).unwrap_or_default() {
+            add(name, index + 1, 1, ReferenceKind::Property);
+        }
+    }
+    for block in page.outline.iter() {
+        for (line, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+            if let Some(names) = property_refs(text) {
+                for name in names {
+                    add(name, line, block.source_column(1), ReferenceKind::Property);
+                }
+                continue;
+            }
+            for link in page_links(text) {
+                let before: String = text.chars().take(link.column - 1).collect();
+                let (column, kind) = if before.ends_with('#') {
+                    (link.column - 1, ReferenceKind::Tag)
+                } else if before.trim_end().ends_with("{{embed") {
+                    (link.column, ReferenceKind::Embed)
+                } else {
+                    (link.column, ReferenceKind::Link)
+                };
+                add(link.name, line, block.source_column(column), kind);
+            }
+            for tag in tag_refs(text) {
+                add(
+                    tag.name,
+                    line,
+                    block.source_column(tag.column),
+                    ReferenceKind::Tag,
+                );
+            }
+        }
+    }
+    references
+}
+
+/// Finds every reference to every page of a graph: links, tags, embeds and property values,
+/// keyed by the [page_key] of the referenced page, in the order of the files and lines.
+/// Pages that are referenced but have no file are included; aliases aren't resolved.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::backlinks::{build_backlinks, ReferenceKind};
+/// use logseq::graph::{page_key, Graph};
+/// let backlinks = build_backlinks(&Graph::scan("tests/fixtures/graph".as_ref()).unwrap());
+/// let references = &backlinks[&page_key("Project")];
+/// assert!(references.iter().any(|reference| reference.page == "Index" && reference.kind == ReferenceKind::Link));
+/// ```
+pub fn build_backlinks(graph: &Graph) -> HashMap<String, Vec<Reference>> {
+    let mut backlinks: HashMap<String, Vec<Reference>> = HashMap::new();
+    for page in &graph.pages {
+        for reference in page_references(page) {
+            backlinks
+                .entry(page_key(&reference.target))
+                .or_default()
+                .push(reference);
+        }
+    }
+    backlinks
+}
@@ -0,0 +1,252 @@
+//! A persistent index of the parsed files of a graph, so that scanning a graph again only parses
+//! the files that changed since the last scan
+
+use super::{journal_title_format, page_files, Graph, GraphPage, UnreadableFile};
+use crate::batch::{no_progress, ProgressCallback, ProgressCounter};
+use crate::outline::{parse_outline, Outline};
+use crate::{error, Error, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The format of the index: bump it after any change to the parser or to [Outline], so that
+/// the files of older indexes are parsed again instead of read with the defaults of new fields
+const INDEX_FORMAT: u32 = 4;
+
+/// A parsed file, valid as long as its modification time and size are the same
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Modification time, in nanoseconds since the Unix epoch
+    modified: u128,
+    size: u64,
+    outline: Outline,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct IndexFile {
+    format: u32,
+    files: HashMap<PathBuf, Entry>,
+}
+
+/// The modification time (in nanoseconds since the Unix epoch) and size of a file
+fn stamp(path: &Path) -> Result<(u128, u64)> {
+    let metadata = fs::metadata(path).map_err(|source| Error::io(path, source))?;
+    let modified = metadata
+        .modified()
+        .map_err(|source| Error::io(path, source))?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_nanos();
+    Ok((modified, metadata.len()))
+}
+
+/// The parsed files of a graph, keyed by path, modification time and size
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::index::GraphIndex;
+/// let temp = assert_fs::TempDir::new().unwrap();
+/// let root = temp.path();
+/// std::fs::create_dir_all(root.join("pages")).unwrap();
+/// std::fs::write(root.join("pages/a.md"), "- [[b]]").unwrap();
+/// let cache = root.join("cache/index.json");
+///
+/// let mut index = GraphIndex::load(root, &cache);
+/// assert_eq!(index.update().unwrap(), 1);
+/// index.save(&cache).unwrap();
+///
+/// let mut index = GraphIndex::load(root, &cache);
+/// assert_eq!(index.update().unwrap(), 0);
+/// assert_eq!(index.into_graph().pages[0].name, "a");
+/// ```
+pub struct GraphIndex {
+    root: PathBuf,
+    index: IndexFile,
+    /// The files that couldn't be read by the last update, which aren't kept in the index
+    unreadable: Vec<UnreadableFile>,
+}
+
+impl GraphIndex {
+    /// An empty index of a graph, that parses all of its files on the first update
+    pub fn new(root: &Path) -> Self {
+        GraphIndex {
+            root: root.to_path_buf(),
+            index: IndexFile::default(),
+            unreadable: Vec::new(),
+        }
+    }
+
+    /// Loads the index of a graph from a cache file. A missing or unreadable cache file, or one
+    /// written in another format, gives an empty index.
+    pub fn load(root: &Path, cache: &Path) -> Self {
+        let index = fs::read_to_string(cache)
+            .ok()
+            .and_then(|json| serde_json::from_str::<IndexFile>(&json).ok())
+            .filter(|index| index.format == INDEX_FORMAT)
+            .unwrap_or_default();
+        GraphIndex {
+            root: root.to_path_buf(),
+            index,
+            unreadable: Vec::new(),
+        }
+    }
+
+    /// Parses the files that are new or changed since the index was saved, and forgets the
+    /// deleted ones. Returns the number of files parsed; the files that can't be read are left
+    /// out of the graph, see [Graph::unreadable].
+    pub fn update(&mut self) -> Result<usize> {
+        self.update_with_progress(&no_progress)
+    }
+
+    /// Updates the index like [GraphIndex::update], telling `progress` about each file parsed:
+    /// the total is the number of files to parse, not the files of the graph
+    pub fn update_with_progress(&mut self, progress: ProgressCallback<'_>) -> Result<usize> {
+        let mut stale = Vec::new();
+        let mut files = HashMap::new();
+        for path in page_files(&self.root)? {
+            let (modified, size) = stamp(&path)?;
+            match self.index.files.remove(&path) {
+                Some(entry) if entry.modified == modified && entry.size == size => {
+                    files.insert(path, entry);
+                }
+                _ => stale.push((path, modified, size)),
+            }
+        }
+        let counter = ProgressCounter::new(stale.len(), progress);
+        let read: Vec<(PathBuf, Result<Entry>)> = stale
+            .into_par_iter()
+            .map(|(path, modified, size)| {
+                let text = error::read_to_string(&path);
+                counter.tick(&path);
+                let entry = text.map(|text| Entry {
+                    modified,
+                    size,
+                    outline: parse_outline(&text),
+                });
+                (path, entry)
+            })
+            .collect();
+        let mut count = 0;
+        self.unreadable.clear();
+        for (path, entry) in read {
+            match entry {
+                Ok(entry) => {
+                    files.insert(path, entry);
+                    count += 1;
+                }
+                Err(error) => self.unreadable.push(UnreadableFile::new(&path, &error)),
+            }
+        }
+        self.unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+        self.index = IndexFile {
+            format: INDEX_FORMAT,
+            files,
+        };
+        Ok(count)
+    }
+
+    /// Forgets files, so that the next update parses them again even if their modification
+    /// time and size are the same, like when they're written twice within the resolution of
+    /// their modification time
+    pub fn invalidate(&mut self, paths: &[PathBuf]) {
+        for path in paths {
+            self.index.files.remove(path);
+        }
+    }
+
+    /// Writes the index to a cache file, creating its directory if needed
+    pub fn save(&self, cache: &Path) -> Result<()> {
+        if let Some(parent) = cache.parent() {
+            fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+        }
+        let json = serde_json::to_string(&self.index)
+            .map_err(|error| Error::parse(Some(cache), error.to_string()))?;
+        error::write(cache, &json)
+    }
+
+    /// The graph of the indexed files, keeping the index to update it again
+    pub fn to_graph(&self) -> Graph {
+        let title_format = journal_title_format(&self.root);
+        let mut pages: Vec<GraphPage> = self
+            .index
+            .files
+            .iter()
+            .map(|(path, entry)| {
+                GraphPage::from_outline(path, entry.outline.clone(), &title_format)
+            })
+            .collect();
+        pages.sort_by(|a, b| a.path.cmp(&b.path));
+        Graph {
+            root: self.root.clone(),
+            pages,
+            unreadable: self.unreadable.clone(),
+        }
+    }
+
+    /// The graph of the indexed files
+    pub fn into_graph(self) -> Graph {
+        let title_format = journal_title_format(&self.root);
+        let mut pages: Vec<GraphPage> = self
+            .index
+            .files
+            .into_iter()
+            .map(|(path, entry)| GraphPage::from_outline(&path, entry.outline, &title_format))
+            .collect();
+        pages.sort_by(|a, b| a.path.cmp(&b.path));
+        Graph {
+            root: self.root,
+            pages,
+            unreadable: self.unreadable,
+        }
+    }
+}
+
+/// The `logseq-doctor` directory of `$XDG_CACHE_HOME` (or `~/.cache`), where caches are kept
+/// outside the graph so that they aren't synced or committed. None if there is no home directory.
+pub(crate) fn cache_dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+    Some(cache_home.join("logseq-doctor"))
+}
+
+/// Where the index of a graph is cached: a file named after the graph's path in the
+/// [cache directory](cache_dir). None if there is no home directory.
+pub fn cache_path(root: &Path) -> Option<PathBuf> {
+    let cache_dir = cache_dir()?;
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    root.hash(&mut hasher);
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Some(cache_dir.join(format!("{}-{:016x}.json", name, hasher.finish())))
+}
+
+impl Graph {
+    /// Like [Graph::scan], but only parses the files that changed since the last call, with the
+    /// index cached at [cache_path]. Failing to write the cache doesn't fail the scan.
+    pub fn scan_cached(root: &Path) -> Result<Self> {
+        Graph::scan_cached_with_progress(root, &no_progress)
+    }
+
+    /// Reads the graph like [Graph::scan_cached], telling `progress` about each file parsed,
+    /// see [GraphIndex::update_with_progress]
+    pub fn scan_cached_with_progress(root: &Path, progress: ProgressCallback<'_>) -> Result<Self> {
+        let Some(cache) = cache_path(root) else {
+            return Graph::scan_with_progress(root, progress);
+        };
+        let mut index = GraphIndex::load(root, &cache);
+        index.update_with_progress(progress)?;
+        let _ = index.save(&cache);
+        Ok(index.into_graph())
+    }
+}
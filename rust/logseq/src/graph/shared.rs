@@ -0,0 +1,189 @@
+//! A graph shared between threads, like the ones of a plugin host or of a language server.
+//!
+//! Analysis (lint, stats, links, search, health) takes `&self` and runs on a snapshot of the
+//! graph, so checks run concurrently. Mutations (fix, rename) are written one at a time, and
+//! publish a new snapshot once their files are written: checks started before keep the
+//! snapshot they started with.
+
+use super::backlinks::{build_backlinks, Reference};
+use super::index::GraphIndex;
+use super::{BrokenLink, Graph};
+use crate::batch::{fix_files, lint_file, FileReport};
+use crate::doctor::{graph_health, HealthOptions, HealthReport};
+use crate::lint::RuleSet;
+use crate::rename::{rename_page, PageRename};
+use crate::report::Report;
+use crate::search::{search_graph, SearchHit, SearchQuery};
+use crate::stats::{graph_stats, GraphStats};
+use crate::{Error, Result};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A graph that threads share, `Send` and `Sync`: see the [module](self)
+///
+/// # Examples
+///
+/// ```
+/// use logseq::graph::shared::SharedGraph;
+/// use logseq::lint::RuleSet;
+/// use std::sync::Arc;
+/// let temp = assert_fs::TempDir::new().unwrap();
+/// let root = temp.path();
+/// std::fs::create_dir_all(root.join("pages")).unwrap();
+/// std::fs::write(root.join("pages/a.md"), "- Some  text\n").unwrap();
+///
+/// let graph = Arc::new(SharedGraph::open(root).unwrap());
+/// let rules = RuleSet::builtin().select(&["consecutive-spaces"]);
+/// let checker = {
+///     let graph = Arc::clone(&graph);
+///     std::thread::spawn(move || graph.snapshot().pages.len())
+/// };
+/// assert_eq!(graph.lint(&rules).unwrap().findings.len(), 1);
+/// assert!(graph.fix(&rules).unwrap()[0].changed);
+/// assert_eq!(graph.lint(&rules).unwrap().findings.len(), 0);
+/// assert_eq!(checker.join().unwrap(), 1);
+/// ```
+pub struct SharedGraph {
+    root: PathBuf,
+    /// The graph as of the last edit, swapped by edits
+    snapshot: RwLock<Arc<Graph>>,
+    /// The parsed files, held while files are written so that edits don't interleave
+    index: Mutex<GraphIndex>,
+}
+
+impl SharedGraph {
+    /// Reads the graph of a root directory, like [Graph::scan]
+    pub fn open(root: &Path) -> Result<Self> {
+        let mut index = GraphIndex::new(root);
+        index.update()?;
+        Ok(SharedGraph {
+            root: root.to_path_buf(),
+            snapshot: RwLock::new(Arc::new(index.to_graph())),
+            index: Mutex::new(index),
+        })
+    }
+
+    /// Root directory of the graph
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The graph as of the last edit. It doesn't change while it's used: edits publish a new
+    /// one.
+    pub fn snapshot(&self) -> Arc<Graph> {
+        let snapshot = self
+            .snapshot
+            .read()
+            .unwrap_or_else(|error| error.into_inner());
+        Arc::clone(&snapshot)
+    }
+}
+
+/// Analysis: reads the files and the snapshot of the graph, and changes nothing
+impl SharedGraph {
+    /// Checks the pages of the graph with `rules`, in parallel, in a report sorted like
+    /// [Report::normalize]. Files are read as they are when they're checked; the pages moved or
+    /// deleted by an edit since the snapshot are left out.
+    pub fn lint(&self, rules: &RuleSet) -> Result<Report> {
+        let graph = self.snapshot();
+        let results = graph
+            .pages
+            .par_iter()
+            .filter_map(|page| match lint_file(&page.path, rules) {
+                Ok(result) => Some(Ok((page.path.as_path(), result))),
+                Err(Error::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => None,
+                Err(error) => Some(Err(error)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut report = Report::default();
+        for (path, result) in &results {
+            report.extend(Report::from_lint(Some(path), result));
+        }
+        report.normalize();
+        Ok(report)
+    }
+
+    /// The statistics of the graph, see [graph_stats]
+    pub fn stats(&self, top: usize) -> Result<GraphStats> {
+        graph_stats(&self.snapshot(), top)
+    }
+
+    /// The health of the graph, see [graph_health]. Fails if an edit moves or deletes a page
+    /// while its file is checked; checking again reads the new snapshot.
+    pub fn health(&self, rules: &RuleSet, options: &HealthOptions) -> Result<HealthReport> {
+        graph_health(&self.snapshot(), rules, options)
+    }
+
+    /// The links to pages that don't exist, see [Graph::check_links]
+    pub fn check_links(&self) -> Vec<BrokenLink> {
+        self.snapshot().check_links()
+    }
+
+    /// The references to blocks that don't exist, see [Graph::check_block_refs]
+    pub fn check_block_refs(&self) -> Vec<BrokenLink> {
+        self.snapshot().check_block_refs()
+    }
+
+    /// The references to each page, see [build_backlinks]
+    pub fn backlinks(&self) -> HashMap<String, Vec<Reference>> {
+        build_backlinks(&self.snapshot())
+    }
+
+    /// The blocks that match a query, see [search_graph]
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        search_graph(&self.snapshot(), query)
+    }
+}
+
+/// Mutation: writes files, one edit at a time, then publishes a new snapshot
+impl SharedGraph {
+    /// Runs an edit while no other edit runs, then reads the files that changed and publishes
+    /// the new snapshot, even if the edit failed halfway. The edit returns the files it wrote,
+    /// which are read again whatever their modification time.
+    fn edit<T>(&self, edit: impl FnOnce(&Graph) -> (T, Vec<PathBuf>)) -> Result<T> {
+        let mut index = self.index.lock().unwrap_or_else(|error| error.into_inner());
+        let (outcome, written) = edit(&self.snapshot());
+        index.invalidate(&written);
+        index.update()?;
+        let graph = Arc::new(index.to_graph());
+        *self
+            .snapshot
+            .write()
+            .unwrap_or_else(|error| error.into_inner()) = graph;
+        Ok(outcome)
+    }
+
+    /// Reads the files that changed since the last snapshot, like after edits made outside
+    pub fn refresh(&self) -> Result<()> {
+        self.edit(|_| ((), Vec::new()))
+    }
+
+    /// Fixes the pages of the graph in place with `rules`, see [fix_files]
+    pub fn fix(&self, rules: &RuleSet) -> Result<Vec<FileReport>> {
+        self.edit(|graph| {
+            let paths: Vec<PathBuf> = graph.pages.iter().map(|page| page.path.clone()).collect();
+            let reports = fix_files(&paths, rules);
+            let written = reports
+                .iter()
+                .filter(|report| report.changed)
+                .map(|report| report.path.clone())
+                .collect();
+            (reports, written)
+        })
+    }
+
+    /// Renames a page and rewrites the references to it, see [rename_page]
+    pub fn rename(&self, old: &str, new: &str) -> Result<PageRename> {
+        self.edit(|graph| {
+            let renamed = rename_page(graph, old, new, false);
+            let written = match &renamed {
+                Ok(rename) => rename.edits.iter().map(|edit| edit.path.clone()).collect(),
+                Err(_) => Vec::new(),
+            };
+            (renamed, written)
+        })?
+    }
+}
@@ -0,0 +1,169 @@
+//! Check the `hls__` pages where Logseq keeps the highlights of PDF files: the PDF they belong
+//! to, the page numbers of the highlights, and the screenshots of area highlights
+
+use crate::graph::{percent_decode, Graph, GraphPage};
+use crate::outline::Block;
+use crate::SUBDIR_ASSETS;
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\]\(([^()]+)\)").unwrap());
+
+/// The prefix of the names of highlight pages
+pub const HIGHLIGHTS_PREFIX: &str = "hls__";
+
+/// What's wrong with a highlight page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HighlightProblem {
+    /// The page has no `file-path::` or `file::` property pointing to its PDF
+    NoPdf,
+    /// The PDF of the page doesn't exist, usually because it was moved or renamed
+    MissingPdf {
+        /// The path of the PDF, as written
+        target: String,
+    },
+    /// A highlight whose `hl-page::` isn't a page number
+    InvalidPage {
+        /// Line of the property, starting at 1
+        line: usize,
+        /// The value, as written
+        value: String,
+    },
+    /// The screenshot of an area highlight doesn't exist in the assets of the PDF
+    MissingAreaImage {
+        /// Line of the highlight, starting at 1
+        line: usize,
+        /// Where the screenshot should be
+        image: PathBuf,
+    },
+}
+
+/// A highlight page with a problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightIssue {
+    /// The file of the highlight page
+    pub path: PathBuf,
+    /// The name of the page
+    pub page: String,
+    /// What's wrong with it
+    pub problem: HighlightProblem,
+}
+
+/// True if a page holds the highlights of a PDF
+pub fn is_highlights_page(page: &GraphPage) -> bool {
+    let stem = page
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    stem.starts_with(HIGHLIGHTS_PREFIX) || page.name.to_lowercase().starts_with(HIGHLIGHTS_PREFIX)
+}
+
+/// The PDF of a highlight page, as written: its `file-path::` property, or else the target of
+/// the Markdown link of its `file::` property
+fn pdf_target(page: &GraphPage) -> Option<String> {
+    let properties = page.outline.page_properties();
+    if let Some(property) = properties.get("file-path") {
+        return Some(property.raw.trim().to_string()).filter(|target| !target.is_empty());
+    }
+    let file = properties.get("file")?;
+    LINK_RE
+        .captures(&file.raw)
+        .map(|captures| captures[1].trim().to_string())
+}
+
+/// Where a link target of a page points to: `../assets/` targets are in the assets of the
+/// graph, `file://` URLs and other paths are read as they are, relative to the page
+fn resolve(graph: &Graph, page: &GraphPage, target: &str) -> PathBuf {
+    let target = percent_decode(target.strip_prefix("file://").unwrap_or(target));
+    let path = Path::new(&target);
+    let mut components = path
+        .components()
+        .skip_while(|component| matches!(component, Component::CurDir | Component::ParentDir));
+    if components.next() == Some(Component::Normal(SUBDIR_ASSETS.as_ref())) {
+        return graph
+            .root
+            .join(SUBDIR_ASSETS)
+            .join(components.collect::<PathBuf>());
+    }
+    match page.path.parent() {
+        Some(directory) => directory.join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// The problems of a highlight of a page: its page number and, for area highlights, its
+/// screenshot in `assets/<PDF name>/<page>_<id>_<stamp>.png`
+fn highlight_problems(
+    block: &Block,
+    assets: &Path,
+    pdf_name: Option<&str>,
+) -> Vec<HighlightProblem> {
+    let properties = block.properties();
+    let Some(page_number) = properties.get("hl-page") else {
+        return Vec::new();
+    };
+    let value = page_number.raw.trim();
+    if !value.parse::<u32>().is_ok_and(|number| number > 0) {
+        return vec![HighlightProblem::InvalidPage {
+            line: page_number.line,
+            value: value.to_string(),
+        }];
+    }
+    let is_area = properties
+        .get("hl-type")
+        .is_some_and(|kind| kind.raw.trim().eq_ignore_ascii_case("area"));
+    let id = properties.get("id").map(|id| id.raw.trim());
+    let stamp = properties.get("hl-stamp").map(|stamp| stamp.raw.trim());
+    match (is_area, pdf_name, id, stamp) {
+        (true, Some(pdf_name), Some(id), Some(stamp)) => {
+            let image = assets
+                .join(pdf_name)
+                .join(format!("{}_{}_{}.png", value, id, stamp));
+            match image.is_file() {
+                true => Vec::new(),
+                false => vec![HighlightProblem::MissingAreaImage {
+                    line: block.line,
+                    image,
+                }],
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Checks the highlight pages of a graph (see [is_highlights_page]): their PDF must exist,
+/// every `hl-page::` must be a page number, and area highlights (`hl-type:: area`) must have
+/// their screenshot in the assets
+pub fn check_highlights(graph: &Graph) -> Vec<HighlightIssue> {
+    let assets = graph.root.join(SUBDIR_ASSETS);
+    let mut issues = Vec::new();
+    for page in graph.pages.iter().filter(|page| is_highlights_page(page)) {
+        let issue = |problem| HighlightIssue {
+            path: page.path.clone(),
+            page: page.name.clone(),
+            problem,
+        };
+        let pdf = pdf_target(page).map(|target| (resolve(graph, page, &target), target));
+        match &pdf {
+            None => issues.push(issue(HighlightProblem::NoPdf)),
+            Some((path, target)) if !path.is_file() => {
+                issues.push(issue(HighlightProblem::MissingPdf {
+                    target: target.clone(),
+                }))
+            }
+            _ => {}
+        }
+        let pdf_name = pdf
+            .as_ref()
+            .and_then(|(path, _)| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string());
+        for block in page.outline.iter() {
+            for problem in highlight_problems(block, &assets, pdf_name.as_deref()) {
+                issues.push(issue(problem));
+            }
+        }
+    }
+    issues
+}
@@ -0,0 +1,165 @@
+//! Block `id::` properties: fresh UUIDs, and the repair of ids duplicated by copy-pasting blocks
+
+use crate::graph::{Graph, GraphPage};
+use crate::properties::Property;
+use crate::refs::{block_refs, lines_outside_regions, CODE_REGIONS};
+use crate::{error, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Generates a random (version 4) UUID, in lowercase like Logseq writes them
+///
+/// # Examples
+///
+/// ```
+/// use logseq::ids::new_uuid;
+/// let uuid = new_uuid();
+/// assert_eq!(uuid.len(), 36);
+/// assert_eq!(&uuid[14..15], "4");
+/// assert!("89ab".contains(&uuid[19..20]));
+/// assert_ne!(uuid, new_uuid());
+/// ```
+pub fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    for byte in bytes.iter_mut() {
+        *byte = fastrand::u8(..);
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Where an `id::` property is written
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IdLocation {
+    /// The page or journal file
+    pub path: PathBuf,
+    /// Line number of the `id::` property, starting at 1
+    pub line: usize,
+}
+
+/// A UUID used by the `id::` property of more than one block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateId {
+    /// The UUID, lowercase
+    pub uuid: String,
+    /// The block that keeps the UUID
+    pub keep: IdLocation,
+    /// The copies, which get a new UUID when repaired
+    pub copies: Vec<IdLocation>,
+    /// Number of `((uuid))` references and embeds of the UUID in the graph
+    pub references: usize,
+}
+
+/// The `id::` properties of a page: page properties and the properties of every block
+fn id_properties(page: &GraphPage) -> Vec<Property> {
+    let mut ids: Vec<Property> = page
+        .outline
+        .page_properties()
+        .get("id")
+        .cloned()
+        .into_iter()
+        .collect();
+    ids.extend(
+        page.outline
+            .iter()
+            .filter_map(|block| block.properties().get("id").cloned()),
+    );
+    // Page properties can be the properties of the first block too
+    ids.sort_by_key(|property| property.line);
+    ids.dedup_by_key(|property| property.line);
+    ids
+}
+
+impl Graph {
+    /// Finds the UUIDs of `id::` properties used by more than one block.
+    /// References can't tell the copies apart, so the block of the oldest file (then the first
+    /// by path and line) is taken as the original and keeps the UUID.
+    pub fn find_duplicate_ids(&self) -> Vec<DuplicateId> {
+        let mut locations: BTreeMap<String, Vec<(SystemTime, IdLocation)>> = BTreeMap::new();
+        let mut references: HashMap<String, usize> = HashMap::new();
+        for page in &self.pages {
+            let modified = fs::metadata(&page.path)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            for property in id_properties(page) {
+                let uuid = property.raw.trim().to_lowercase();
+                if uuid.is_empty() {
+                    continue;
+                }
+                locations.entry(uuid).or_default().push((
+                    modified,
+                    IdLocation {
+                        path: page.path.clone(),
+                        line: property.line,
+                    },
+                ));
+            }
+            for block in page.outline.iter() {
+                for (_, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                    for block_ref in block_refs(text) {
+                        *references.entry(block_ref.uuid).or_default() += 1;
+                    }
+                }
+            }
+        }
+        locations
+            .into_iter()
+            .filter(|(_, found)| found.len() > 1)
+            .map(|(uuid, mut found)| {
+                found.sort();
+                let mut found = found.into_iter().map(|(_, location)| location);
+                let keep = found.next().unwrap_or_else(|| unreachable!());
+                DuplicateId {
+                    references: references.get(&uuid).copied().unwrap_or_default(),
+                    uuid,
+                    keep,
+                    copies: found.collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Gives every copy of a duplicated id a new UUID, changing nothing else in the files.
+/// Returns the copies with their new UUID.
+pub fn repair_duplicate_ids(duplicates: &[DuplicateId]) -> Result<Vec<(IdLocation, String)>> {
+    let mut by_file: BTreeMap<&PathBuf, Vec<(&IdLocation, &str)>> = BTreeMap::new();
+    for duplicate in duplicates {
+        for copy in &duplicate.copies {
+            by_file
+                .entry(&copy.path)
+                .or_default()
+                .push((copy, &duplicate.uuid));
+        }
+    }
+    let mut repaired = Vec::new();
+    for (path, copies) in by_file {
+        let contents = error::read_to_string(path)?;
+        let mut lines: Vec<String> = contents.split('\n').map(String::from).collect();
+        for (copy, uuid) in copies {
+            let Some(line) = lines.get_mut(copy.line - 1) else {
+                continue;
+            };
+            // The id may be written in uppercase
+            let Some(start) = line.to_ascii_lowercase().rfind(uuid) else {
+                continue;
+            };
+            let new = new_uuid();
+            line.replace_range(start..start + uuid.len(), &new);
+            repaired.push((copy.clone(), new));
+        }
+        error::write(path, &lines.join("\n"))?;
+    }
+    Ok(repaired)
+}
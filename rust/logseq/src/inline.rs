@@ -0,0 +1,139 @@
+//! Inline `((uuid))` block references: replace them with the text of the blocks they refer to,
+//! so that the text stays when the blocks go, like before deleting the page they are on
+
+use crate::graph::{page_key, Graph};
+use crate::refs::{block_refs, lines_outside_regions, CODE_REGIONS};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use std::collections::HashMap;
+
+/// The references to inline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InlineTarget {
+    /// The references to the block with this UUID
+    Block(String),
+    /// The references to any block of the page with this name or alias
+    Page(String),
+}
+
+/// What replaces a reference: the first line of the block without its heading marker, and the
+/// name of the page the block is on
+struct Inlined {
+    text: String,
+    page: String,
+}
+
+/// The blocks whose references are inlined, by lowercase UUID
+fn inlined_blocks(graph: &Graph, target: &InlineTarget) -> Result<HashMap<String, Inlined>> {
+    let pages: Vec<_> = match target {
+        InlineTarget::Block(_) => graph.pages.iter().collect(),
+        InlineTarget::Page(name) => {
+            let page = graph
+                .find_page(name)
+                .ok_or_else(|| Error::parse(None, format!("page not found: {}", name)))?;
+            vec![page]
+        }
+    };
+    let mut blocks = HashMap::new();
+    for page in pages {
+        for block in page.outline.iter() {
+            let Some(id) = block
+                .properties()
+                .get("id")
+                .map(|id| id.raw.trim().to_lowercase())
+            else {
+                continue;
+            };
+            if matches!(target, InlineTarget::Block(uuid) if uuid.to_lowercase() != id) {
+                continue;
+            }
+            let line = block.first_line();
+            let text = match block.heading_level() {
+                Some(level) => line[level..].trim_start(),
+                None => line,
+            };
+            let inlined = Inlined {
+                text: text.trim_end().to_string(),
+                page: page.name.clone(),
+            };
+            blocks.insert(id, inlined);
+        }
+    }
+    if let InlineTarget::Block(uuid) = target {
+        if blocks.is_empty() {
+            return Err(Error::parse(None, format!("block not found: {}", uuid)));
+        }
+    }
+    Ok(blocks)
+}
+
+/// Plans replacing the `((uuid))` references to a block, or to the blocks of a page, with the
+/// first line of the block; with `keep_link`, followed by a `[[link]]` to the page the block is on.
+/// References in code are left alone, and so are `{{embed ((uuid))}}`, which show the children
+/// of the block too. Nothing is written; see [inline_refs].
+/// Fails if the block or the page doesn't exist.
+pub fn plan_inline_refs(
+    graph: &Graph,
+    target: &InlineTarget,
+    keep_link: bool,
+) -> Result<Vec<FileEdit>> {
+    let blocks = inlined_blocks(graph, target)?;
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        let original = error::read_to_string(&page.path)?;
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for block in page.outline.iter() {
+            for (line, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                // The content of a line is the end of the line, after its bullet or indentation
+                let start = lines[line - 1].len() - text.len();
+                let mut updated = lines[line - 1][..start].to_string();
+                let mut last = 0;
+                for block_ref in block_refs(text).into_iter().filter(|found| !found.embed) {
+                    let Some(inlined) = blocks.get(&block_ref.uuid) else {
+                        continue;
+                    };
+                    let offset = text
+                        .char_indices()
+                        .nth(block_ref.column - 1)
+                        .map_or(text.len(), |(offset, _)| offset);
+                    updated.push_str(&text[last..offset]);
+                    updated.push_str(&inlined.text);
+                    let linked = page_key(&inlined.page) != page_key(&page.name);
+                    if keep_link && linked {
+                        updated.push_str(&format!(" [[{}]]", inlined.page));
+                    }
+                    last = offset + block_ref.uuid.len() + "(())".len();
+                }
+                if last > 0 {
+                    updated.push_str(&text[last..]);
+                    lines[line - 1] = updated;
+                    changed = true;
+                }
+            }
+        }
+        if changed {
+            edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated: lines.join("\n"),
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Inlines the references to a block or to the blocks of a page, see [plan_inline_refs]; with
+/// `dry_run`, only plans the changes
+pub fn inline_refs(
+    graph: &Graph,
+    target: &InlineTarget,
+    keep_link: bool,
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_inline_refs(graph, target, keep_link)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,6 @@
+//! Import notes from other apps into a Logseq graph, and prepare it for Logseq's database
+//! version
+
+pub mod db_version;
+pub mod obsidian;
+pub mod roam;
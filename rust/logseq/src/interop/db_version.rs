@@ -0,0 +1,238 @@
+//! Prepare a file-based graph for Logseq's database version: convert its Markdown to the
+//! conventions the database version expects, and find what can't be converted automatically
+
+use crate::graph::{collect_files, Graph};
+use crate::outline::parse_outline;
+use crate::properties::{split_list, split_property, strip_ref, REF_LIST_KEYS};
+use crate::refs::{lines_outside_regions, CODE_REGIONS};
+use crate::rename::{write_edits, FileEdit};
+use crate::tasks::split_marker;
+use crate::{error, Result, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Something the conversion leaves as it is, to be changed by hand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbProblem {
+    /// A task marker without a status in the database version, like `WAITING`
+    UnmappedMarker(String),
+    /// A property written more than once in a block once its key is normalized, like
+    /// `Status::` and `status::`
+    DuplicateKey(String),
+    /// An org-mode file, which the database version doesn't import
+    OrgFile,
+}
+
+/// A line of a file that can't be converted automatically
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbIssue {
+    /// The file
+    pub path: PathBuf,
+    /// Line of the construct, starting at 1
+    pub line: usize,
+    /// Column of the construct, starting at 1
+    pub column: usize,
+    /// What can't be converted
+    pub problem: DbProblem,
+}
+
+/// Everything the conversion of a graph changes, and what it leaves to be done by hand
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbConversion {
+    /// Files rewritten by the conversion, sorted by path
+    pub edits: Vec<FileEdit>,
+    /// Constructs that can't be converted, sorted by path and line
+    pub issues: Vec<DbIssue>,
+}
+
+/// The status of the database version for a task marker, which only knows `TODO`, `DOING`,
+/// `DONE` and `CANCELED`; None if it has no equivalent
+fn db_marker(marker: &str) -> Option<&'static str> {
+    match marker {
+        "TODO" | "LATER" => Some("TODO"),
+        "DOING" | "NOW" | "STARTED" | "IN-PROGRESS" => Some("DOING"),
+        "DONE" => Some("DONE"),
+        "CANCELED" | "CANCELLED" => Some("CANCELED"),
+        _ => None,
+    }
+}
+
+/// A property key as the database version stores it: lowercase, with `-` instead of `_`, as
+/// file-based graphs already read them
+fn normalize_key(key: &str) -> String {
+    key.to_lowercase().replace('_', "-")
+}
+
+/// Converts the properties of a block given as its lines with their numbers (starting at 1):
+/// keys are normalized (see [normalize_key]), and values of `tags::` and `alias::` become
+/// explicit `[[page]]` references
+fn convert_properties<'a>(
+    lines: &mut [String],
+    block_lines: impl Iterator<Item = (usize, &'a str)>,
+    problems: &mut Vec<(usize, usize, DbProblem)>,
+) {
+    let mut properties = Vec::new();
+    for (number, text) in block_lines {
+        let Some((key, raw)) = split_property(text) else {
+            continue;
+        };
+        // The bullet or the indentation before the text, then the spaces before the key
+        let text = text.trim_end_matches('\r');
+        let line = lines[number - 1].trim_end_matches('\r');
+        if let Some(before) = line.strip_suffix(text) {
+            let start = before.len() + text.len() - text.trim_start().len();
+            properties.push((number, start, key.to_string(), raw.to_string()));
+        }
+    }
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (_, _, key, _) in &properties {
+        *counts.entry(normalize_key(key)).or_default() += 1;
+    }
+    let mut reported = Vec::new();
+    for (number, start, key, raw) in properties {
+        let line = &lines[number - 1];
+        let normalized = normalize_key(&key);
+        if counts[&normalized] > 1 {
+            if reported.contains(&normalized) {
+                let column = line[..start].chars().count() + 1;
+                problems.push((number, column, DbProblem::DuplicateKey(normalized)));
+            } else {
+                reported.push(normalized);
+            }
+            continue;
+        }
+        let mut value = raw.trim().to_string();
+        if REF_LIST_KEYS.contains(&normalized.as_str()) {
+            let refs: Vec<String> = split_list(&raw)
+                .into_iter()
+                .map(|part| format!("[[{}]]", strip_ref(part)))
+                .collect();
+            value = refs.join(", ");
+        }
+        if normalized == key && value == raw.trim() {
+            continue;
+        }
+        let ending = if line.ends_with('\r') { "\r" } else { "" };
+        let separator = if value.is_empty() { "" } else { " " };
+        lines[number - 1] = format!(
+            "{}{}::{}{}{}",
+            &line[..start],
+            normalized,
+            separator,
+            value,
+            ending
+        );
+    }
+}
+
+/// Converts the Markdown of a page to the conventions of Logseq's database version, ignoring
+/// code blocks:
+/// - task markers become `TODO`, `DOING`, `DONE` or `CANCELED` (`LATER` becomes `TODO`, `NOW`
+///   and `IN-PROGRESS` become `DOING`, `CANCELLED` becomes `CANCELED`)
+/// - property keys are lowercase, with `-` instead of `_`
+/// - `tags::` and `alias::` are lists of `[[page]]` references, without `#`
+///
+/// Returns the converted text along with the `(line, column, problem)` of what couldn't be
+/// converted: markers like `WAITING`, and properties that become the same one once normalized.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::interop::db_version::{convert_page, DbProblem};
+/// let (text, problems) = convert_page("tags:: book, #reading\n\n- LATER Read\n  Due_Date:: 2024-01-02\n- WAITING Reply\n");
+/// assert_eq!(text, "tags:: [[book]], [[reading]]\n\n- TODO Read\n  due-date:: 2024-01-02\n- WAITING Reply\n");
+/// assert_eq!(problems, vec![(5, 3, DbProblem::UnmappedMarker("WAITING".to_string()))]);
+/// ```
+pub fn convert_page(text: &str) -> (String, Vec<(usize, usize, DbProblem)>) {
+    let outline = parse_outline(text);
+    let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+    let mut problems = Vec::new();
+    let preamble = outline
+        .preamble
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.as_str()));
+    convert_properties(&mut lines, preamble, &mut problems);
+    for block in outline.iter() {
+        if let Some((marker, _)) = split_marker(block.first_line()) {
+            match db_marker(marker) {
+                Some(converted) if converted != marker => {
+                    let start = block.indent.len() + block.marker.len_utf8() + 1;
+                    let line = &mut lines[block.line - 1];
+                    if line
+                        .get(start..)
+                        .is_some_and(|rest| rest.starts_with(marker))
+                    {
+                        line.replace_range(start..start + marker.len(), converted);
+                    }
+                }
+                Some(_) => {}
+                None => problems.push((
+                    block.line,
+                    block.source_column(1),
+                    DbProblem::UnmappedMarker(marker.to_string()),
+                )),
+            }
+        }
+        convert_properties(
+            &mut lines,
+            lines_outside_regions(block.numbered_lines(), CODE_REGIONS),
+            &mut problems,
+        );
+    }
+    problems.sort_by_key(|(line, column, _)| (*line, *column));
+    (lines.join("\n"), problems)
+}
+
+/// Plans the conversion of the pages and journals of a graph, see [convert_page], and lists
+/// what must be changed by hand, org-mode files included. Nothing is written; see
+/// [convert_graph].
+pub fn plan_db_conversion(graph: &Graph) -> Result<DbConversion> {
+    let mut conversion = DbConversion::default();
+    for page in &graph.pages {
+        let original = error::read_to_string(&page.path)?;
+        let (updated, problems) = convert_page(&original);
+        conversion
+            .issues
+            .extend(problems.into_iter().map(|(line, column, problem)| DbIssue {
+                path: page.path.clone(),
+                line,
+                column,
+                problem,
+            }));
+        if updated != original {
+            conversion.edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    let mut org_files = Vec::new();
+    for subdir in [SUBDIR_PAGES, SUBDIR_JOURNALS] {
+        collect_files(&graph.root.join(subdir), "org", &mut org_files)?;
+    }
+    conversion
+        .issues
+        .extend(org_files.into_iter().map(|path| DbIssue {
+            path,
+            line: 1,
+            column: 1,
+            problem: DbProblem::OrgFile,
+        }));
+    conversion.edits.sort_by(|a, b| a.path.cmp(&b.path));
+    conversion
+        .issues
+        .sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+    Ok(conversion)
+}
+
+/// Converts the pages and journals of a graph for the database version, see
+/// [plan_db_conversion]; with `dry_run`, only plans the changes
+pub fn convert_graph(graph: &Graph, dry_run: bool) -> Result<DbConversion> {
+    let conversion = plan_db_conversion(graph)?;
+    if !dry_run {
+        write_edits(&conversion.edits)?;
+    }
+    Ok(conversion)
+}
@@ -0,0 +1,291 @@
+//! Convert an Obsidian vault into a Logseq graph
+
+use crate::dates::DateFormat;
+use crate::frontmatter::{front_matter_properties, split_front_matter, DateStyle};
+use crate::graph::{collect_files_matching, file_stem_from_page_name, journal_title};
+use crate::markdown::flat_markdown_to_outline;
+use crate::{error, Error, Result, SUBDIR_ASSETS, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use chrono::NaiveDate;
+use regex::{Captures, Regex};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Daily notes are named after their date, in Obsidian's default format
+const DAILY_NOTE_FORMAT: &str = "%Y-%m-%d";
+
+/// The Logseq page name of a note: its file name, or the journal title of a daily note
+fn page_name(note: &str) -> String {
+    match NaiveDate::parse_from_str(note, DAILY_NOTE_FORMAT) {
+        Ok(date) => journal_title(date),
+        Err(_) => note.to_string(),
+    }
+}
+
+/// Converts the inside of a `[[link]]` or `![[embed]]`. Anchors to headings and blocks are
+/// dropped, as Logseq links to whole pages.
+fn convert_link(inner: &str, embed: bool, attachments: &HashMap<String, String>) -> String {
+    let (target, label) = match inner.split_once('|') {
+        Some((target, label)) => (target.trim(), Some(label.trim())),
+        None => (inner.trim(), None),
+    };
+    let target = target.split('#').next().unwrap_or_default();
+    let file_name = target.rsplit('/').next().unwrap_or(target);
+    if target.is_empty() {
+        // A link to a heading of the same note
+        return label.unwrap_or(inner.trim_start_matches('#')).to_string();
+    }
+    let attachment = Path::new(file_name)
+        .extension()
+        .is_some_and(|extension| extension != "md");
+    if attachment {
+        let asset = attachments
+            .get(&file_name.to_lowercase())
+            .map_or(file_name, String::as_str);
+        let path = format!("../{}/{}", SUBDIR_ASSETS, asset.replace(' ', "%20"));
+        // The label of an embedded image may be its size, like `|300`
+        let label = label
+            .filter(|label| !label.chars().all(|c| c.is_ascii_digit() || c == 'x'))
+            .unwrap_or(file_name);
+        let bang = if embed { "!" } else { "" };
+        return format!("{}[{}]({})", bang, label, path);
+    }
+    let name = page_name(file_name.strip_suffix(".md").unwrap_or(file_name));
+    match (embed, label) {
+        (true, _) => format!("{{{{embed [[{}]]}}}}", name),
+        (false, Some(label)) if label != name => format!("[{}]([[{}]])", label, name),
+        (false, _) => format!("[[{}]]", name),
+    }
+}
+
+/// The first line of a callout, `> [!type] title`, where `+` or `-` after the type makes it
+/// foldable
+pub(crate) const CALLOUT_PATTERN: &str = r"^>\s*\[!(\w+)\][+-]?\s*(.*)$";
+
+/// Syntax of the notes, compiled once
+struct ObsidianSyntax {
+    wikilink: Regex,
+    markdown_link: Regex,
+    highlight: Regex,
+    callout: Regex,
+}
+
+static OBSIDIAN_SYNTAX: LazyLock<ObsidianSyntax> = LazyLock::new(ObsidianSyntax::new);
+
+impl ObsidianSyntax {
+    fn new() -> Self {
+        ObsidianSyntax {
+            wikilink: Regex::new(r"(!?)\[\[([^\[\]]+)\]\]").unwrap(),
+            markdown_link: Regex::new(r"(!?\[[^\[\]]*\]\()([^()\s]+)\)").unwrap(),
+            highlight: Regex::new(r"==([^=\s](?:[^=]*[^=\s])?)==").unwrap(),
+            callout: Regex::new(CALLOUT_PATTERN).unwrap(),
+        }
+    }
+
+    /// Converts links, embeds and highlights of a line
+    fn inline(&self, line: &str, attachments: &HashMap<String, String>) -> String {
+        let linked = self.wikilink.replace_all(line, |captures: &Captures| {
+            convert_link(&captures[2], !captures[1].is_empty(), attachments)
+        });
+        // Markdown links to attachments point to `assets` after the import
+        let relinked = self
+            .markdown_link
+            .replace_all(&linked, |captures: &Captures| {
+                let target = &captures[2];
+                let file_name = target
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or(target)
+                    .replace("%20", " ");
+                match attachments.get(&file_name.to_lowercase()) {
+                    Some(asset) if !target.contains("://") => format!(
+                        "{}../{}/{})",
+                        &captures[1],
+                        SUBDIR_ASSETS,
+                        asset.replace(' ', "%20")
+                    ),
+                    _ => captures[0].to_string(),
+                }
+            });
+        self.highlight.replace_all(&relinked, "^^$1^^").to_string()
+    }
+}
+
+/// The Logseq admonition for a callout type; Logseq has fewer of them than Obsidian
+pub(crate) fn admonition(callout: &str) -> &'static str {
+    match callout.to_lowercase().as_str() {
+        "tip" | "hint" | "success" | "check" | "done" => "TIP",
+        "important" => "IMPORTANT",
+        "warning" | "attention" | "question" | "help" | "faq" => "WARNING",
+        "caution" | "danger" | "error" | "failure" | "fail" | "missing" | "bug" => "CAUTION",
+        "quote" | "cite" => "QUOTE",
+        _ => "NOTE",
+    }
+}
+
+/// Convert the contents of an Obsidian note to a Logseq page
+///
+/// YAML front matter becomes page properties (see [front_matter_properties]), headings and
+/// paragraphs become an outline (see [flat_markdown_to_outline]), callouts become admonitions
+/// like `#+BEGIN_NOTE`, `==highlights==` become `^^highlights^^`, and links and embeds are
+/// rewritten: `[[note|label]]` becomes `[label]([[note]])`, `![[note]]` an embed, and links to
+/// attachments point to the `assets` directory, where `attachments` maps lowercase file names
+/// to their name there.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::interop::obsidian::note_to_page;
+/// use std::collections::HashMap;
+/// let attachments = HashMap::from([("cat.png".to_string(), "cat.png".to_string())]);
+/// let note = "---\naliases: [Reading]\ntags:\n  - books\n---\n# Dune\nSee [[Herbert|the author]] and ==this==.\n![[cat.png]]\n> [!tip] Remember\n> Read [[2024-01-02]]\n";
+/// assert_eq!(note_to_page(note, &attachments),
+///     "alias:: Reading\ntags:: books\n\n- # Dune\n  - See [the author]([[Herbert]]) and ^^this^^.\n  - ![cat.png](../assets/cat.png)\n  - #+BEGIN_TIP\n    **Remember**\n    Read [[Jan 2nd, 2024]]\n    #+END_TIP\n");
+/// ```
+pub fn note_to_page(note: &str, attachments: &HashMap<String, String>) -> String {
+    let syntax = &*OBSIDIAN_SYNTAX;
+    let (properties, body) = match split_front_matter(note) {
+        Some((yaml, body)) => {
+            let properties = front_matter_properties(yaml, DateStyle::Iso, &DateFormat::default());
+            let lines = properties
+                .iter()
+                .map(|(key, value)| format!("{}:: {}", key, value))
+                .collect();
+            (lines, body)
+        }
+        None => (Vec::new(), note),
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    let mut callout: Option<&str> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+        if in_fence || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            lines.push(line.to_string());
+            continue;
+        }
+        if let Some(kind) = callout {
+            match trimmed.strip_prefix('>') {
+                Some(text) => {
+                    lines.push(syntax.inline(text.trim(), attachments));
+                    continue;
+                }
+                None => {
+                    lines.push(format!("#+END_{}", kind));
+                    callout = None;
+                }
+            }
+        }
+        if let Some(captures) = syntax.callout.captures(trimmed) {
+            let kind = admonition(&captures[1]);
+            lines.push(format!("#+BEGIN_{}", kind));
+            if !captures[2].trim().is_empty() {
+                lines.push(format!(
+                    "**{}**",
+                    syntax.inline(captures[2].trim(), attachments)
+                ));
+            }
+            callout = Some(kind);
+            continue;
+        }
+        lines.push(syntax.inline(line, attachments));
+    }
+    if let Some(kind) = callout {
+        lines.push(format!("#+END_{}", kind));
+    }
+
+    let outline = flat_markdown_to_outline(&lines.join("\n"));
+    match (properties.is_empty(), outline.is_empty()) {
+        (true, _) => outline,
+        (false, true) => format!("{}\n", properties.join("\n")),
+        (false, false) => format!("{}\n\n{}", properties.join("\n"), outline),
+    }
+}
+
+/// Import an Obsidian vault into a Logseq graph, leaving the vault untouched.
+///
+/// Notes become pages (daily notes named like `2024-01-02` become journals), converted with
+/// [note_to_page], and the other files are copied to the `assets` directory; attachments with
+/// the same name in different folders get a numbered suffix.
+/// Nothing is imported if a note or attachment would replace a file of the graph.
+/// Returns the (vault, graph) paths of the imported files.
+pub fn import_vault(vault: &Path, graph: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let is_note = |path: &Path| path.extension().is_some_and(|extension| extension == "md");
+    let mut notes = Vec::new();
+    collect_files_matching(vault, &is_note, &mut notes)?;
+    let mut files = Vec::new();
+    collect_files_matching(vault, &|path| !is_note(path), &mut files)?;
+    notes.sort();
+    files.sort();
+
+    let mut attachments: HashMap<String, String> = HashMap::new();
+    let mut used: HashSet<String> = HashSet::new();
+    let mut imports: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for file in files {
+        let name = file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) => (stem.to_string(), format!(".{}", extension)),
+            None => (name.clone(), String::new()),
+        };
+        let mut asset = name.clone();
+        let mut copy = 1;
+        while !used.insert(asset.to_lowercase()) {
+            copy += 1;
+            asset = format!("{}-{}{}", stem, copy, extension);
+        }
+        attachments
+            .entry(name.to_lowercase())
+            .or_insert(asset.clone());
+        imports.push((file, graph.join(SUBDIR_ASSETS).join(asset)));
+    }
+    let assets = imports.len();
+
+    let mut pages: HashSet<PathBuf> = HashSet::new();
+    for note in notes {
+        let stem = note.file_stem().unwrap_or_default().to_string_lossy();
+        let destination = match NaiveDate::parse_from_str(&stem, DAILY_NOTE_FORMAT) {
+            Ok(date) => graph
+                .join(SUBDIR_JOURNALS)
+                .join(format!("{}.md", date.format("%Y_%m_%d"))),
+            Err(_) => graph
+                .join(SUBDIR_PAGES)
+                .join(format!("{}.md", file_stem_from_page_name(&stem))),
+        };
+        if !pages.insert(destination.to_string_lossy().to_lowercase().into()) {
+            return Err(Error::parse(
+                Some(&note),
+                "another note of the vault has the same name",
+            ));
+        }
+        imports.push((note, destination));
+    }
+
+    if let Some((_, existing)) = imports.iter().find(|(_, destination)| destination.exists()) {
+        return Err(Error::parse(
+            Some(existing),
+            "a file with the same name already exists in the graph",
+        ));
+    }
+
+    for (index, (source, destination)) in imports.iter().enumerate() {
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+        }
+        if index < assets {
+            fs::copy(source, destination).map_err(|error| Error::io(destination, error))?;
+        } else {
+            let note = error::read_to_string(source)?;
+            error::write(destination, &note_to_page(&note, &attachments))?;
+        }
+    }
+    Ok(imports)
+}
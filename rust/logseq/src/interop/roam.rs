@@ -0,0 +1,229 @@
+//! Convert a Roam Research JSON export into Logseq pages and journals
+
+use crate::graph::{file_stem_from_page_name, journal_title};
+use crate::ids::new_uuid;
+use crate::{error, Error, Result, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use chrono::NaiveDate;
+use rayon::prelude::*;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A page of the export
+#[derive(Deserialize)]
+struct RoamPage {
+    title: String,
+    /// Daily pages have a uid like `01-02-2024`
+    uid: Option<String>,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+}
+
+/// A block of the export
+#[derive(Deserialize)]
+struct RoamBlock {
+    string: String,
+    uid: Option<String>,
+    heading: Option<usize>,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+}
+
+/// A page converted from the export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertedPage {
+    /// Name of the page in Logseq; daily pages get the title of their journal
+    pub name: String,
+    /// Date of the journal, or None for regular pages
+    pub journal_date: Option<NaiveDate>,
+    /// Logseq Markdown of the page
+    pub markdown: String,
+}
+
+/// The date of a daily page, from its uid
+fn daily_date(page: &RoamPage) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(page.uid.as_deref()?, "%m-%d-%Y").ok()
+}
+
+/// Syntax of Roam blocks, and what the conversion needs to know about the whole export
+struct RoamSyntax {
+    marker: Regex,
+    embed: Regex,
+    block_ref: Regex,
+    page_link: Regex,
+    italic: Regex,
+    /// Roam uids of the referenced blocks, mapped to the UUIDs they become
+    uuids: HashMap<String, String>,
+    /// Titles of daily pages, mapped to the titles of their journals
+    journals: HashMap<String, String>,
+}
+
+impl RoamSyntax {
+    fn new(pages: &[RoamPage]) -> Self {
+        let block_ref = Regex::new(r"\(\(([\w-]{9})\)\)").unwrap();
+        let mut referenced: HashSet<String> = HashSet::new();
+        let mut defined: HashSet<&str> = HashSet::new();
+        let mut pending: Vec<&RoamBlock> = pages.iter().flat_map(|page| &page.children).collect();
+        while let Some(block) = pending.pop() {
+            defined.extend(block.uid.as_deref());
+            referenced.extend(
+                block_ref
+                    .captures_iter(&block.string)
+                    .map(|captures| captures[1].to_string()),
+            );
+            pending.extend(&block.children);
+        }
+        let journals = pages
+            .iter()
+            .filter_map(|page| Some((page.title.clone(), journal_title(daily_date(page)?))))
+            .collect();
+        RoamSyntax {
+            marker: Regex::new(r"\{\{\[\[(TODO|DONE)\]\]\}\}\s*").unwrap(),
+            embed: Regex::new(r"\{\{\s*\[?\[?embed\]?\]?\s*:\s*(.+?)\s*\}\}").unwrap(),
+            block_ref,
+            page_link: Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap(),
+            italic: Regex::new(r"__([^_\s](?:[^_]*[^_\s])?)__").unwrap(),
+            // References to blocks missing from the export are kept as they are
+            uuids: referenced
+                .into_iter()
+                .filter(|uid| defined.contains(uid.as_str()))
+                .map(|uid| (uid, new_uuid()))
+                .collect(),
+            journals,
+        }
+    }
+
+    /// Converts the text of a block
+    fn inline(&self, text: &str) -> String {
+        let marked = self.marker.replace_all(text, "$1 ");
+        let embedded = self.embed.replace_all(&marked, "{{embed $1}}");
+        let referenced = self
+            .block_ref
+            .replace_all(&embedded, |captures: &Captures| {
+                match self.uuids.get(&captures[1]) {
+                    Some(uuid) => format!("(({}))", uuid),
+                    None => captures[0].to_string(),
+                }
+            });
+        let linked = self
+            .page_link
+            .replace_all(&referenced, |captures: &Captures| {
+                match self.journals.get(&captures[1]) {
+                    Some(journal) => format!("[[{}]]", journal),
+                    None => captures[0].to_string(),
+                }
+            });
+        self.italic.replace_all(&linked, "*$1*").trim().to_string()
+    }
+
+    /// Appends a block and its children to the lines of a page
+    fn block(&self, block: &RoamBlock, level: usize, lines: &mut Vec<String>) {
+        let indent = "\t".repeat(level);
+        let text = self.inline(&block.string);
+        let mut text_lines = text.lines();
+        let heading = match block.heading {
+            Some(level @ 1..=6) => format!("{} ", "#".repeat(level)),
+            _ => String::new(),
+        };
+        lines.push(format!(
+            "{}- {}{}",
+            indent,
+            heading,
+            text_lines.next().unwrap_or_default()
+        ));
+        if let Some(uuid) = block.uid.as_ref().and_then(|uid| self.uuids.get(uid)) {
+            lines.push(format!("{}  id:: {}", indent, uuid));
+        }
+        for line in text_lines {
+            lines.push(format!("{}  {}", indent, line));
+        }
+        for child in &block.children {
+            self.block(child, level + 1, lines);
+        }
+    }
+}
+
+/// Convert a Roam Research JSON export into Logseq pages
+///
+/// Blocks keep their nesting and headings, `{{[[TODO]]}}` and `{{[[DONE]]}}` become task
+/// markers, and `__italics__` become `*italics*`. The blocks that are referenced or embedded get
+/// a new UUID in an `id::` property, as Logseq ids are UUIDs, and their `((uid))` references are
+/// rewritten to match. Daily pages become journals, and links to them use the journal title.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::interop::roam::convert_export;
+/// let json = r#"[
+///   {"title": "January 2nd, 2024", "uid": "01-02-2024", "children": [
+///     {"string": "{{[[TODO]]}} Read [[Dune]]", "uid": "abcdefghi", "children": [
+///       {"string": "It's __great__", "heading": 2}]}]},
+///   {"title": "Dune", "children": [{"string": "See ((abcdefghi)) on [[January 2nd, 2024]]"}]}
+/// ]"#;
+/// let pages = convert_export(json).unwrap();
+/// assert_eq!(pages[0].name, "Jan 2nd, 2024");
+/// assert!(pages[0].journal_date.is_some());
+/// let lines: Vec<&str> = pages[0].markdown.lines().collect();
+/// assert_eq!(lines[0], "- TODO Read [[Dune]]");
+/// let uuid = lines[1].strip_prefix("  id:: ").unwrap();
+/// assert_eq!(lines[2], "\t- ## It's *great*");
+/// assert_eq!(pages[1].markdown, format!("- See (({})) on [[Jan 2nd, 2024]]\n", uuid));
+/// ```
+pub fn convert_export(json: &str) -> Result<Vec<ConvertedPage>> {
+    let pages: Vec<RoamPage> = serde_json::from_str(json)
+        .map_err(|error| Error::parse(None, format!("invalid Roam export: {}", error)))?;
+    let syntax = RoamSyntax::new(&pages);
+    Ok(pages
+        .par_iter()
+        .map(|page| {
+            let mut lines = Vec::new();
+            for block in &page.children {
+                syntax.block(block, 0, &mut lines);
+            }
+            let journal_date = daily_date(page);
+            ConvertedPage {
+                name: journal_date.map_or_else(|| page.title.clone(), journal_title),
+                journal_date,
+                markdown: lines.iter().map(|line| format!("{}\n", line)).collect(),
+            }
+        })
+        .collect())
+}
+
+/// Import a Roam Research JSON export into a Logseq graph, see [convert_export].
+/// Nothing is imported if a page would replace a file of the graph.
+/// Returns the paths of the new files.
+pub fn import_export(export: &Path, graph: &Path) -> Result<Vec<PathBuf>> {
+    let json = error::read_to_string(export)?;
+    let pages = convert_export(&json).map_err(|error| error.with_path(export))?;
+    let files: Vec<(PathBuf, String)> = pages
+        .into_iter()
+        .map(|page| {
+            let path = match page.journal_date {
+                Some(date) => graph
+                    .join(SUBDIR_JOURNALS)
+                    .join(format!("{}.md", date.format("%Y_%m_%d"))),
+                None => graph
+                    .join(SUBDIR_PAGES)
+                    .join(format!("{}.md", file_stem_from_page_name(&page.name))),
+            };
+            (path, page.markdown)
+        })
+        .collect();
+    if let Some((existing, _)) = files.iter().find(|(path, _)| path.exists()) {
+        return Err(Error::parse(
+            Some(existing),
+            "a file with the same name already exists in the graph",
+        ));
+    }
+    for subdir in [SUBDIR_PAGES, SUBDIR_JOURNALS] {
+        let directory = graph.join(subdir);
+        fs::create_dir_all(&directory).map_err(|source| Error::io(&directory, source))?;
+    }
+    for (path, markdown) in &files {
+        error::write(path, markdown)?;
+    }
+    Ok(files.into_iter().map(|(path, _)| path).collect())
+}
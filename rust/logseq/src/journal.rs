@@ -0,0 +1,430 @@
+//! Validate and rename the files of the `journals` directory of a graph
+
+use crate::config::GraphConfig;
+use crate::dates::{parse_date_link, DateFormat};
+use crate::graph::{collect_files, Graph};
+use crate::outline::{parse_outline, Block};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, undo, Error, Result};
+use chrono::{NaiveDate, NaiveTime};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+static TIME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\*\*(\d{1,2}:\d{2})\*\*").unwrap());
+
+/// Date formats (in `strftime` syntax) commonly used for journal file names,
+/// to recognize files that are named after a date but not in the graph's format
+const KNOWN_FORMATS: [&str; 5] = ["%Y_%m_%d", "%Y-%m-%d", "%Y%m%d", "%Y.%m.%d", "%Y_%-m_%-d"];
+
+/// Converts a date format as written in `config.edn` (e.g. `yyyy_MM_dd`) into a `strftime` one
+///
+/// # Examples
+///
+/// ```
+/// use logseq::journal::to_strftime;
+/// assert_eq!(to_strftime("yyyy_MM_dd"), "%Y_%m_%d");
+/// assert_eq!(to_strftime("yyyy-MMM-dd"), "%Y-%b-%d");
+/// assert_eq!(to_strftime("EEE d.M.yy"), "%a %-d.%-m.%y");
+/// ```
+pub fn to_strftime(format: &str) -> String {
+    let mut converted = String::new();
+    let chars: Vec<char> = format.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&other| other == c).count();
+        let directive = match (c, run) {
+            ('y', 2) => Some("%y"),
+            ('y', _) => Some("%Y"),
+            ('M', 1) => Some("%-m"),
+            ('M', 2) => Some("%m"),
+            ('M', 3) => Some("%b"),
+            ('M', _) => Some("%B"),
+            ('d', 1) => Some("%-d"),
+            ('d', _) => Some("%d"),
+            ('E', 4..) => Some("%A"),
+            ('E', _) => Some("%a"),
+            _ => None,
+        };
+        match directive {
+            Some(directive) => converted.push_str(directive),
+            None => {
+                for _ in 0..run {
+                    if c == '%' {
+                        converted.push('%');
+                    }
+                    converted.push(c);
+                }
+            }
+        }
+        i += run;
+    }
+    converted
+}
+
+/// The date of a journal given by its first block, when that block is only a date like
+/// `[[Jan 2nd, 2024]]`, `# Jan 2nd, 2024` or `2024-01-02`
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use logseq::journal::content_date;
+/// let date = NaiveDate::from_ymd_opt(2024, 1, 2);
+/// assert_eq!(content_date("- [[Jan 2nd, 2024]]\n  - Notes"), date);
+/// assert_eq!(content_date("- ## 2024-01-02"), date);
+/// assert_eq!(content_date("- Notes about [[Jan 2nd, 2024]]"), None);
+/// ```
+pub fn content_date(contents: &str) -> Option<NaiveDate> {
+    first_block_date(contents, &DateFormat::default())
+}
+
+/// The date of the first block of a journal, written in a title format or as a fallback one
+fn first_block_date(contents: &str, format: &DateFormat) -> Option<NaiveDate> {
+    let outline = parse_outline(contents);
+    let first_line = outline.blocks.first()?.first_line();
+    parse_date_link(first_line.trim_start_matches('#'), format)
+}
+
+/// Parses a date written as a journal title like `Jan 2nd, 2024` or as `2024-01-02`, as a
+/// `[[link]]` or not
+pub(crate) fn parse_date(text: &str) -> Option<NaiveDate> {
+    parse_date_link(text, &DateFormat::default())
+}
+
+/// What's wrong with a journal file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalProblem {
+    /// The file name isn't a date
+    NotADate,
+    /// The file name is a date, but not written in the graph's format
+    WrongFormat(NaiveDate),
+    /// The first block of the journal is a date other than the one of the file name
+    DateMismatch {
+        /// The date of the file name
+        file_date: NaiveDate,
+        /// The date of the first block
+        content_date: NaiveDate,
+    },
+}
+
+/// A journal file with a problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalIssue {
+    /// The journal file
+    pub path: PathBuf,
+    /// What's wrong with it
+    pub problem: JournalProblem,
+    /// The path the file should be renamed to, when it's known
+    pub rename_to: Option<PathBuf>,
+}
+
+/// Checks the names of the Markdown files in the `journals` directory of a graph against the
+/// date format of `config.edn`, and against the date their first block is about, written in
+/// its `:journal/page-title-format`
+pub fn check_journals(root: &Path) -> Result<Vec<JournalIssue>> {
+    let config = GraphConfig::read(root)?;
+    let format = to_strftime(&config.journal_file_name_format);
+    let title_format = DateFormat::new(&config.journal_page_title_format);
+    let mut paths = Vec::new();
+    collect_files(&root.join(&config.journals_directory), "md", &mut paths)?;
+    paths.sort();
+
+    let mut issues = Vec::new();
+    for path in paths {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let renamed =
+            |date: NaiveDate| Some(path.with_file_name(format!("{}.md", date.format(&format))));
+        let problem = match NaiveDate::parse_from_str(&stem, &format) {
+            Ok(file_date) => {
+                match first_block_date(&error::read_to_string(&path)?, &title_format) {
+                    Some(content_date) if content_date != file_date => {
+                        JournalProblem::DateMismatch {
+                            file_date,
+                            content_date,
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+            Err(_) => match KNOWN_FORMATS
+                .iter()
+                .find_map(|known| NaiveDate::parse_from_str(&stem, known).ok())
+            {
+                Some(date) => JournalProblem::WrongFormat(date),
+                None => JournalProblem::NotADate,
+            },
+        };
+        let rename_to = match problem {
+            JournalProblem::NotADate => None,
+            JournalProblem::WrongFormat(date) => renamed(date),
+            JournalProblem::DateMismatch { content_date, .. } => renamed(content_date),
+        };
+        issues.push(JournalIssue {
+            path,
+            problem,
+            rename_to,
+        });
+    }
+    Ok(issues)
+}
+
+/// Renames the journal files of the issues that have a known new name.
+/// Nothing is renamed if any new name is already taken, or if two files would get the same name.
+/// Returns the (old, new) paths of the renamed files.
+pub fn rename_journals(issues: &[JournalIssue]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let renames: Vec<(PathBuf, PathBuf)> = issues
+        .iter()
+        .filter_map(|issue| Some((issue.path.clone(), issue.rename_to.clone()?)))
+        .collect();
+    for (index, (path, new_path)) in renames.iter().enumerate() {
+        let taken_by_other = renames[..index].iter().any(|(_, other)| other == new_path);
+        if taken_by_other || new_path.exists() {
+            return Err(Error::parse(
+                Some(path),
+                format!("can't rename to {}, it already exists", new_path.display()),
+            ));
+        }
+    }
+    for (path, new_path) in &renames {
+        undo::move_file(path, new_path)?;
+    }
+    Ok(renames)
+}
+
+/// The dates of the journal files of a graph whose names are in the graph's date format, sorted
+pub fn journal_dates(root: &Path) -> Result<Vec<NaiveDate>> {
+    let config = GraphConfig::read(root)?;
+    let format = to_strftime(&config.journal_file_name_format);
+    let mut paths = Vec::new();
+    collect_files(&root.join(&config.journals_directory), "md", &mut paths)?;
+    let mut dates: Vec<NaiveDate> = paths
+        .iter()
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            NaiveDate::parse_from_str(&stem, &format).ok()
+        })
+        .collect();
+    dates.sort();
+    dates.dedup();
+    Ok(dates)
+}
+
+/// The days without a journal between the first and the last journal of a graph
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use logseq::journal::find_gaps;
+/// let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+/// assert_eq!(find_gaps(&[day(1), day(2), day(5)]), vec![day(3), day(4)]);
+/// assert!(find_gaps(&[]).is_empty());
+/// ```
+pub fn find_gaps(dates: &[NaiveDate]) -> Vec<NaiveDate> {
+    let mut gaps = Vec::new();
+    for pair in dates.windows(2) {
+        gaps.extend(pair[0].iter_days().skip(1).take_while(|day| *day < pair[1]));
+    }
+    gaps
+}
+
+/// The days without a journal between the first and the last journal of a graph, see
+/// [journal_dates] and [find_gaps]
+pub fn journal_gaps(root: &Path) -> Result<Vec<NaiveDate>> {
+    Ok(find_gaps(&journal_dates(root)?))
+}
+
+/// Creates the journal files of dates, named in the graph's date format, with the `contents`
+/// of each date. Nothing is created if any of the files already exists.
+/// Returns the paths of the created files.
+pub fn create_journals(
+    root: &Path,
+    dates: &[NaiveDate],
+    contents: impl Fn(NaiveDate) -> String,
+) -> Result<Vec<PathBuf>> {
+    let config = GraphConfig::read(root)?;
+    let format = to_strftime(&config.journal_file_name_format);
+    let directory = root.join(&config.journals_directory);
+    let paths: Vec<PathBuf> = dates
+        .iter()
+        .map(|date| directory.join(format!("{}.md", date.format(&format))))
+        .collect();
+    if let Some(existing) = paths.iter().find(|path| path.exists()) {
+        return Err(Error::parse(Some(existing), "the journal already exists"));
+    }
+    std::fs::create_dir_all(&directory).map_err(|source| Error::io(&directory, source))?;
+    for (date, path) in dates.iter().zip(&paths) {
+        error::write(path, &contents(*date))?;
+    }
+    Ok(paths)
+}
+
+/// The last line of a block and its descendants, starting at 1
+fn block_end(block: &Block) -> usize {
+    let last = block.iter().last().unwrap_or(block);
+    last.line + last.line_count() - 1
+}
+
+/// Adds the line ranges of the sibling blocks that are exact copies of an earlier sibling, with
+/// the same content and children, and looks for copies among the children of the others
+fn find_copies(blocks: &[Block], lines: &[&str], copies: &mut Vec<(usize, usize)>) {
+    let mut seen: HashSet<Vec<&str>> = HashSet::new();
+    for block in blocks {
+        let end = block_end(block);
+        if block.content.trim().is_empty() {
+            continue;
+        }
+        let text: Vec<&str> = lines[block.line - 1..end]
+            .iter()
+            .map(|line| line.trim_end())
+            .collect();
+        if seen.insert(text) {
+            find_copies(&block.children, lines, copies);
+        } else {
+            copies.push((block.line, end));
+        }
+    }
+}
+
+/// The time of a top-level journal entry that starts with one in bold, like `**09:15**`
+fn entry_time(block: &Block) -> Option<NaiveTime> {
+    let captures = TIME_RE.captures(block.first_line())?;
+    NaiveTime::parse_from_str(&captures[1], "%H:%M").ok()
+}
+
+/// Sorts the top-level entries of a journal that start with a time like `**09:15**`
+/// chronologically; the other blocks keep their place
+fn sort_entries(text: &str) -> String {
+    let outline = parse_outline(text);
+    let Some(first) = outline.blocks.first() else {
+        return text.to_string();
+    };
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    // Each top-level block with the lines up to the next one
+    let starts: Vec<usize> = outline.blocks.iter().map(|block| block.line - 1).collect();
+    let mut regions: Vec<String> = starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(lines.len());
+            lines[*start..end].concat()
+        })
+        .collect();
+    let timed: Vec<(usize, NaiveTime)> = outline
+        .blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| Some((index, entry_time(block)?)))
+        .collect();
+    let mut sorted = timed.clone();
+    sorted.sort_by_key(|(_, time)| *time);
+    if sorted == timed {
+        return text.to_string();
+    }
+    let no_final_newline = !text.ends_with('\n');
+    if no_final_newline {
+        regions.last_mut().unwrap().push('\n');
+    }
+    let moved: Vec<String> = sorted
+        .iter()
+        .map(|(index, _)| regions[*index].clone())
+        .collect();
+    for ((slot, _), region) in timed.iter().zip(moved) {
+        regions[*slot] = region;
+    }
+    let mut sorted_text = lines[..first.line - 1].concat() + &regions.concat();
+    if no_final_newline {
+        sorted_text.pop();
+    }
+    sorted_text
+}
+
+/// Removes the blocks of a journal that are exact copies of a sibling before them (same content,
+/// same children), which interrupted syncs leave behind, and with `sort`, sorts its timestamped
+/// top-level entries (`**09:15** ...`) chronologically.
+/// Returns the new text and the lines of the removed copies.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::journal::clean_journal;
+/// let text = "- **10:30** Call\n- **09:15** Standup\n  - Notes\n- **09:15** Standup\n  - Notes\n";
+/// let (cleaned, removed) = clean_journal(text, true);
+/// assert_eq!(cleaned, "- **09:15** Standup\n  - Notes\n- **10:30** Call\n");
+/// assert_eq!(removed, vec![4]);
+/// ```
+pub fn clean_journal(text: &str, sort: bool) -> (String, Vec<usize>) {
+    let outline = parse_outline(text);
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut copies = Vec::new();
+    find_copies(&outline.blocks, &lines, &mut copies);
+    let mut kept = Vec::new();
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        let number = index + 1;
+        if !copies
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&number))
+        {
+            kept.push(line);
+        }
+    }
+    let mut cleaned = kept.concat();
+    if sort {
+        cleaned = sort_entries(&cleaned);
+    }
+    copies.sort();
+    (
+        cleaned,
+        copies.into_iter().map(|(start, _)| start).collect(),
+    )
+}
+
+/// A journal with copied blocks, or with entries out of order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalCleanup {
+    /// Lines of the removed copies, starting at 1
+    pub removed: Vec<usize>,
+    /// The new contents of the journal
+    pub edit: FileEdit,
+}
+
+/// Finds the journals of a graph that [clean_journal] changes
+pub fn plan_journal_cleanup(graph: &Graph, sort: bool) -> Result<Vec<JournalCleanup>> {
+    let mut cleanups = Vec::new();
+    for page in graph
+        .pages
+        .iter()
+        .filter(|page| page.journal_date.is_some())
+    {
+        let original = error::read_to_string(&page.path)?;
+        let (updated, removed) = clean_journal(&original, sort);
+        if updated != original {
+            cleanups.push(JournalCleanup {
+                removed,
+                edit: FileEdit {
+                    path: page.path.clone(),
+                    original,
+                    updated,
+                },
+            });
+        }
+    }
+    Ok(cleanups)
+}
+
+/// Writes the cleaned journals; all of them are written to temporary files first, so a failure
+/// leaves the graph untouched
+pub fn apply_journal_cleanup(cleanups: &[JournalCleanup]) -> Result<()> {
+    let edits: Vec<FileEdit> = cleanups
+        .iter()
+        .map(|cleanup| cleanup.edit.clone())
+        .collect();
+    write_edits(&edits)
+}
@@ -2,10 +2,89 @@
 
 use chrono::{Local, NaiveDate};
 use regex::Regex;
+use std::borrow::Cow;
 use std::fs;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+pub mod assets;
+pub mod batch;
+pub mod cards;
+pub mod clippings;
+pub mod config;
+pub mod date_links;
+pub mod dates;
+pub mod dedupe;
+pub mod diff;
+pub mod doctor;
+pub mod edn;
+pub mod embeds;
+pub mod encoding;
+mod error;
+pub mod export;
+pub mod filenames;
+pub mod frontmatter;
+pub mod git;
+pub mod graph;
+pub mod highlights;
+pub mod ids;
+pub mod inline;
+pub mod interop;
+pub mod journal;
+pub mod line_ending;
+pub mod lint;
+pub mod lsp;
+pub mod markdown;
+pub mod merge;
+pub mod namespaces;
+pub mod normalize;
+pub mod org;
+pub mod outline;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+pub mod properties;
+pub mod property_rename;
+pub mod query;
+pub mod refs;
+pub mod rename;
+pub mod render;
+pub mod report;
+pub mod schema;
+pub mod search;
+pub mod sentences;
+pub mod split;
+#[cfg(feature = "index-sqlite")]
+pub mod sqlite;
+pub mod stats;
+pub mod stubs;
+pub mod suggest;
+pub mod sync_conflicts;
+pub mod tasks;
+pub mod templates;
+pub mod timestamps;
+#[cfg(feature = "network")]
+pub mod titles;
+pub mod toc;
+pub mod toml;
+pub mod undo;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+#[cfg(feature = "network")]
+pub mod weblinks;
+pub mod whiteboards;
+
+pub use error::{Error, Result};
+use line_ending::preserving_line_endings_if_changed;
+use outline::RegionKind;
+
+static SPACE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" {2,}").unwrap());
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"#\[\[([^ ]*?)\]\]").unwrap());
+static IMAGE_END_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!\[[^\[\]]*\]\([^()\s]+\)$").unwrap());
 
 /// Remove consecutive spaces on lines that begin with a dash, keeping leading spaces
 ///
@@ -13,7 +92,7 @@ use std::path::{Path, PathBuf};
 ///
 /// * `file_contents`: Contents of a file as a string
 ///
-/// returns: Result<String, Error>
+/// returns: Result<String, Error>, the given string itself when nothing changes
 ///
 /// # Examples
 ///
@@ -30,35 +109,333 @@ use std::path::{Path, PathBuf};
 ///
 /// let ends_with_linebreak = "- Root\n  - Child\n";
 /// assert_eq!(remove_consecutive_spaces(ends_with_linebreak.to_string()).unwrap(), ends_with_linebreak);
+///
+/// let windows = "- Root  one\r\n  - Child  two\r\n";
+/// assert_eq!(remove_consecutive_spaces(windows.to_string()).unwrap(), "- Root one\r\n  - Child two\r\n");
+/// ```
+pub fn remove_consecutive_spaces(file_contents: String) -> Result<String> {
+    match remove_consecutive_spaces_with(&file_contents, true)? {
+        Cow::Owned(fixed) => Ok(fixed),
+        Cow::Borrowed(_) => Ok(file_contents),
+    }
+}
+
+/// Remove consecutive spaces on lines that begin with a dash, optionally skipping code blocks
+///
+/// Inline code spans and `$$...$$` math (even over several lines) are always left untouched, and
+/// so are bullets with a table row (`- | a    | b |`), whose padding aligns the columns.
+/// When `skip_code_blocks` is true, lines inside fenced code blocks (```` ``` ```` or `~~~`)
+/// and indented code blocks are left untouched too.
+/// An indented code block starts after a blank line, indented at least 4 columns deeper than
+/// the content of the last bullet.
+///
+/// # Arguments
+///
+/// * `file_contents`: Contents of a file as a string
+/// * `skip_code_blocks`: Leave code blocks untouched
+///
+/// returns: Result<Cow<str>, Error>, borrowing the contents when nothing changes
+///
+/// # Examples
+///
+/// ```
+/// use logseq::remove_consecutive_spaces_with;
+/// use std::borrow::Cow;
+/// assert!(matches!(remove_consecutive_spaces_with("- a b\n", true).unwrap(), Cow::Borrowed(_)));
+///
+/// let fenced = "- yaml:\n  ```\n  -  a:   1\n  ```\n-  after";
+/// assert_eq!(remove_consecutive_spaces_with(fenced, true).unwrap(),
+///     "- yaml:\n  ```\n  -  a:   1\n  ```\n- after");
+/// assert_eq!(remove_consecutive_spaces_with(fenced, false).unwrap(),
+///     "- yaml:\n  ```\n  - a: 1\n  ```\n- after");
+///
+/// let indented = "- diff:\n\n        -  removed   line\n-  next";
+/// assert_eq!(remove_consecutive_spaces_with(indented, true).unwrap(),
+///     "- diff:\n\n        -  removed   line\n- next");
+///
+/// let spans = "-  run  `ls  -l`,  ``a `  b``  or  $$x  =  1$$  then  `unclosed  span";
+/// assert_eq!(remove_consecutive_spaces_with(spans, false).unwrap(),
+///     "- run `ls  -l`, ``a `  b`` or $$x  =  1$$ then `unclosed span");
+/// let image = "-  ![cat](../assets/cat.png){:height  300,  :width 400}  here";
+/// assert_eq!(remove_consecutive_spaces_with(image, false).unwrap(),
+///     "- ![cat](../assets/cat.png){:height  300,  :width 400} here");
+/// let math = "- $$a  &  b\\\n- c  &  d$$  e";
+/// assert_eq!(remove_consecutive_spaces_with(math, false).unwrap(), "- $$a  &  b\\\n- c  &  d$$ e");
+/// ```
+pub fn remove_consecutive_spaces_with(
+    file_contents: &str,
+    skip_code_blocks: bool,
+) -> Result<Cow<'_, str>> {
+    let mut collapser = SpaceCollapser::new(skip_code_blocks);
+    let final_result = preserving_line_endings_if_changed(file_contents, |text| {
+        let lines: Vec<Cow<str>> = text.lines().map(|line| collapser.collapse(line)).collect();
+        if lines.iter().all(|line| matches!(line, Cow::Borrowed(_))) {
+            return None;
+        }
+        let result = lines.join("\n");
+
+        // Append a line break if the original string ended with one
+        if text.ends_with('\n') {
+            Some(format!("{}\n", result))
+        } else {
+            Some(result)
+        }
+    });
+
+    Ok(final_result)
+}
+
+/// Remove consecutive spaces like [remove_consecutive_spaces_with], reading and writing one line
+/// at a time, so memory stays flat however large the text is. Each line keeps its own line
+/// ending. Returns true if anything changed.
+///
+/// # Examples
+///
 /// ```
-pub fn remove_consecutive_spaces(file_contents: String) -> anyhow::Result<String> {
-    let space_re = Regex::new(r" {2,}").unwrap();
-    let ends_with_linebreak = file_contents.ends_with('\n');
-
-    let result = file_contents
-        .lines()
-        .map(|line| {
-            if line.trim_start().starts_with('-') {
-                // Replace multiple spaces with a single space, except for leading spaces
-                let first_non_space = line.find('-').unwrap_or(0);
-                let (leading_spaces, rest) = line.split_at(first_non_space);
-                format!("{}{}", leading_spaces, space_re.replace_all(rest, " "))
-            } else {
-                // Leave line unchanged
-                line.to_string()
+/// use logseq::remove_consecutive_spaces_stream;
+/// let mut fixed = Vec::new();
+/// let changed = remove_consecutive_spaces_stream("-  a  b\r\n- c\n-  d".as_bytes(), &mut fixed, true).unwrap();
+/// assert!(changed);
+/// assert_eq!(String::from_utf8(fixed).unwrap(), "- a b\r\n- c\n- d");
+/// ```
+pub fn remove_consecutive_spaces_stream(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    skip_code_blocks: bool,
+) -> Result<bool> {
+    let io_error = |source| Error::Io { path: None, source };
+    let mut collapser = SpaceCollapser::new(skip_code_blocks);
+    let mut changed = false;
+    let mut buffer = String::new();
+    loop {
+        buffer.clear();
+        if reader.read_line(&mut buffer).map_err(io_error)? == 0 {
+            break;
+        }
+        let line = buffer.trim_end_matches('\n').trim_end_matches('\r');
+        let collapsed = collapser.collapse(line);
+        changed |= matches!(collapsed, Cow::Owned(_));
+        writer
+            .write_all(collapsed.as_bytes())
+            .and_then(|_| writer.write_all(&buffer.as_bytes()[line.len()..]))
+            .map_err(io_error)?;
+    }
+    writer.flush().map_err(io_error)?;
+    Ok(changed)
+}
+
+/// Remove consecutive spaces in a file with [remove_consecutive_spaces_stream]: the fixed lines
+/// go to a temporary file, which replaces the file only if something changed.
+/// Returns true if the file changed.
+pub fn remove_consecutive_spaces_in_file(path: &Path, skip_code_blocks: bool) -> Result<bool> {
+    let target = error::link_target(path);
+    let temp = error::temp_path(&target);
+    let result = (|| {
+        let reader = BufReader::new(File::open(path)?);
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        let changed = remove_consecutive_spaces_stream(reader, &mut writer, skip_code_blocks)
+            .map_err(|error| match error {
+                Error::Io { source, .. } => source,
+                error => io::Error::other(error.to_string()),
+            })?;
+        writer
+            .into_inner()
+            .map_err(|error| error.into_error())?
+            .sync_all()?;
+        if changed {
+            fs::set_permissions(&temp, fs::metadata(&target)?.permissions())?;
+            fs::rename(&temp, &target)?;
+        } else {
+            fs::remove_file(&temp)?;
+        }
+        Ok(changed)
+    })();
+    result.map_err(|source| {
+        let _ = fs::remove_file(&temp);
+        Error::io(path, source)
+    })
+}
+
+/// Collapses the consecutive spaces of the lines of a text, keeping track of the code blocks
+/// and math that span several lines
+struct SpaceCollapser {
+    skip_code_blocks: bool,
+    code: CodeBlockTracker,
+    in_math: bool,
+}
+
+impl SpaceCollapser {
+    fn new(skip_code_blocks: bool) -> Self {
+        SpaceCollapser {
+            skip_code_blocks,
+            code: CodeBlockTracker::default(),
+            in_math: false,
+        }
+    }
+
+    /// The next line of the text, without its line ending, with its spaces collapsed;
+    /// borrowed when there was nothing to collapse
+    fn collapse<'l>(&mut self, line: &'l str) -> Cow<'l, str> {
+        if self.skip_code_blocks && self.code.is_code(line) {
+            return Cow::Borrowed(line);
+        }
+        let segments = protected_segments(line, &mut self.in_math);
+        let first_non_space = line.find('-').unwrap_or(0);
+        let plain = |(start, end, protected): &(usize, usize, bool)| {
+            (!protected).then(|| &line[(*start).max(first_non_space)..(*end).max(first_non_space)])
+        };
+        if !line.trim_start().starts_with('-') {
+            // Leave line unchanged
+            Cow::Borrowed(line)
+        } else if lint::is_table_row(&line[first_non_space + 1..]) {
+            // Leave the padding of table rows to the tables rule
+            Cow::Borrowed(line)
+        } else if !segments
+            .iter()
+            .filter_map(plain)
+            .any(|segment| SPACE_RE.is_match(segment))
+        {
+            Cow::Borrowed(line)
+        } else {
+            // Replace multiple spaces with a single space, except for leading spaces
+            // and inside inline code and math
+            let mut collapsed = line[..first_non_space].to_string();
+            for (start, end, protected) in segments {
+                let segment = &line[start.max(first_non_space)..end.max(first_non_space)];
+                if protected {
+                    collapsed.push_str(segment);
+                } else {
+                    collapsed.push_str(&SPACE_RE.replace_all(segment, " "));
+                }
             }
-        })
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    // Append a line break if the original string ended with one
-    let final_result = if ends_with_linebreak {
-        format!("{}\n", result)
-    } else {
-        result
+            Cow::Owned(collapsed)
+        }
+    }
+}
+
+/// Splits a line into byte ranges `(start, end, protected)`, where protected ranges are inline
+/// code spans (closed by a run of as many backticks as opened them), `$$...$$` math, and the
+/// props of images, like the `{:height 300, :width 400}` of `![alt](../assets/x.png){:height 300, :width 400}`.
+/// `in_math` carries math that spans several lines over to the next line.
+pub(crate) fn protected_segments(line: &str, in_math: &mut bool) -> Vec<(usize, usize, bool)> {
+    let mut segments = Vec::new();
+    let mut push = |start: usize, end: usize, protected: bool| {
+        if start < end {
+            segments.push((start, end, protected));
+        }
     };
+    let mut plain_start = 0;
+    let mut position = 0;
+    if *in_math {
+        position = match line.find("$$") {
+            Some(end) => {
+                *in_math = false;
+                end + 2
+            }
+            None => line.len(),
+        };
+        push(0, position, true);
+        plain_start = position;
+    }
+    while position < line.len() {
+        let rest = &line[position..];
+        if rest.starts_with('`') {
+            let run = rest.len() - rest.trim_start_matches('`').len();
+            let fence = &rest[..run];
+            // The closing run must have exactly as many backticks
+            let mut search = run;
+            let mut close = None;
+            while let Some(found) = rest[search..].find(fence) {
+                let start = search + found;
+                let length = rest[start..].len() - rest[start..].trim_start_matches('`').len();
+                if length == run {
+                    close = Some(start + run);
+                    break;
+                }
+                search = start + length;
+            }
+            match close {
+                Some(end) => {
+                    push(plain_start, position, false);
+                    push(position, position + end, true);
+                    position += end;
+                    plain_start = position;
+                }
+                None => position += run,
+            }
+        } else if let Some(math) = rest.strip_prefix("$$") {
+            push(plain_start, position, false);
+            let end = math.find("$$").map(|end| end + 4);
+            *in_math = end.is_none();
+            let end = end.unwrap_or(rest.len());
+            push(position, position + end, true);
+            position += end;
+            plain_start = position;
+        } else if let Some(end) = rest
+            .find('}')
+            .filter(|_| rest.starts_with('{') && IMAGE_END_RE.is_match(&line[..position]))
+        {
+            push(plain_start, position, false);
+            push(position, position + end + 1, true);
+            position += end + 1;
+            plain_start = position;
+        } else {
+            position += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    push(plain_start, line.len(), false);
+    segments
+}
 
-    Ok(final_result)
+/// Keeps track of fenced and indented code blocks while reading a file line by line, and of the
+/// other lines that fixes leave alone (see [RegionKind])
+#[derive(Default)]
+struct CodeBlockTracker {
+    /// The region of the current line, from the line that opened it, if any
+    region: Option<RegionKind>,
+    in_indented_code: bool,
+    previous_blank: bool,
+    last_bullet_width: Option<usize>,
+}
+
+impl CodeBlockTracker {
+    /// Returns true if the line is inside a code block or another region that should be left
+    /// untouched. Lines that open a code block are not part of the code themselves, unlike the
+    /// ones that start an `<!-- lsd-ignore-start -->` span.
+    fn is_code(&mut self, line: &str) -> bool {
+        let trimmed = line.trim_start();
+        let blank = trimmed.is_empty();
+        let previous_blank = std::mem::replace(&mut self.previous_blank, blank);
+
+        if let Some(region) = self.region {
+            if region.closed_by(outline::bullet_content(line)) {
+                self.region = None;
+            }
+            return true;
+        }
+
+        let width = outline::indent_width(line);
+        let code_width = self.last_bullet_width.map_or(4, |w| w + 2 + 4);
+        if self.in_indented_code {
+            if blank || width >= code_width {
+                return true;
+            }
+            self.in_indented_code = false;
+        } else if previous_blank && !blank && width >= code_width {
+            self.in_indented_code = true;
+            return true;
+        }
+
+        let content = match trimmed.strip_prefix('-') {
+            Some(rest) => {
+                self.last_bullet_width = Some(width);
+                rest.trim_start()
+            }
+            None => trimmed,
+        };
+        self.region = RegionKind::opened_by(content);
+        self.region == Some(RegionKind::Ignored) || outline::is_ignored_line(line)
+    }
 }
 
 /// Remove unnecessary brackets from tags
@@ -67,7 +444,7 @@ pub fn remove_consecutive_spaces(file_contents: String) -> anyhow::Result<String
 ///
 /// * `file_contents`: Contents of a file as a string
 ///
-/// returns: Result<String, Error>
+/// returns: Result<Cow<str>, Error>, borrowing the contents when nothing changes
 ///
 /// # Examples
 ///
@@ -77,16 +454,25 @@ pub fn remove_consecutive_spaces(file_contents: String) -> anyhow::Result<String
 /// assert_eq!(remove_unnecessary_brackets_from_tags(&"#[[tag with spaces]]".to_string()).unwrap(), "#[[tag with spaces]]".to_string());
 /// assert_eq!(remove_unnecessary_brackets_from_tags(&"text before #[[some-tag]] then after".to_string()).unwrap(), "text before #some-tag then after".to_string());
 /// ```
-pub fn remove_unnecessary_brackets_from_tags(file_contents: &str) -> anyhow::Result<String> {
-    let tag_re = Regex::new(r"#\[\[([^ ]*?)\]\]").unwrap();
-    let result = tag_re.replace_all(file_contents, "#$1");
-    Ok(result.to_string())
+pub fn remove_unnecessary_brackets_from_tags(file_contents: &str) -> Result<Cow<'_, str>> {
+    Ok(TAG_RE.replace_all(file_contents, "#$1"))
+}
+
+/// Write text to a file atomically: it goes to a temporary file in the same directory first,
+/// which then replaces the original, so a crash while writing never truncates a page.
+/// With `backup`, the previous contents of the file are kept in a `.bak` file next to it.
+pub fn write_file(path: &Path, contents: &str, backup: bool) -> Result<()> {
+    error::write_atomically(path, contents, backup)
 }
 
 /// Subdirectory for Logseq pages
 pub const SUBDIR_PAGES: &str = "pages";
 /// Subdirectory for Logseq journals
 pub const SUBDIR_JOURNALS: &str = "journals";
+/// Subdirectory for images and other files linked from pages
+pub const SUBDIR_ASSETS: &str = "assets";
+/// Subdirectory for whiteboards
+pub const SUBDIR_WHITEBOARDS: &str = "whiteboards";
 
 /// Represents a Logseq graph
 /// Placeholder for future functionality (API client, global and graph configuration, etc.)
@@ -140,17 +526,17 @@ impl Page {
     /// Tidy up the page by calling several clean-up functions.
     /// E.g. removing consecutive spaces, unnecessary brackets from tags, etc.
     /// Returns true if the file was modified, false otherwise.
-    pub fn tidy_up(&self) -> anyhow::Result<bool> {
+    pub fn tidy_up(&self) -> Result<bool> {
         let path = self.path.clone();
 
-        let original_contents = fs::read_to_string(&path)?;
-        let no_brackets = remove_unnecessary_brackets_from_tags(&original_contents)?;
-        if no_brackets == original_contents {
-            return Ok(false);
+        let original_contents = error::read_to_string(&path)?;
+        match remove_unnecessary_brackets_from_tags(&original_contents)? {
+            Cow::Owned(no_brackets) => {
+                error::write(&path, &no_brackets)?;
+                Ok(true)
+            }
+            Cow::Borrowed(_) => Ok(false),
         }
-
-        fs::write(&path, no_brackets.as_bytes())?;
-        Ok(true)
     }
 }
 
@@ -179,7 +565,7 @@ impl Journal {
         self.graph_path.join(journal_file_name)
     }
 
-    fn _append_or_prepend(&self, markdown: String, append: bool) -> anyhow::Result<()> {
+    fn _append_or_prepend(&self, markdown: String, append: bool) -> Result<()> {
         let prepend: bool = !append;
         let path = self.as_path();
         eprint!("Journal {}: ", path.to_string_lossy());
@@ -215,9 +601,13 @@ impl Journal {
                 .write(true)
                 .create(true)
                 .truncate(true)
-                .open(&path)?;
+                .open(&path)
+                .map_err(|source| Error::io(&path, source))?;
         } else {
-            file = OpenOptions::new().append(true).open(&path)?;
+            file = OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .map_err(|source| Error::io(&path, source))?;
             eprintln!("appending");
 
             println!(); // Output all content to stdout
@@ -235,12 +625,12 @@ impl Journal {
     }
 
     /// Appends the given Markdown content to the journal file at the end of the file
-    pub fn append(&self, markdown: String) -> anyhow::Result<()> {
+    pub fn append(&self, markdown: String) -> Result<()> {
         self._append_or_prepend(markdown, true)
     }
 
     /// Prepends the given Markdown content to the journal file at the beginning of the file
-    pub fn prepend(&self, markdown: String) -> anyhow::Result<()> {
+    pub fn prepend(&self, markdown: String) -> Result<()> {
         self._append_or_prepend(markdown, false)
     }
 }
@@ -0,0 +1,132 @@
+//! Detect and preserve the line endings of a file, so fixers don't produce spurious diffs
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The line ending used in a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// Unix line ending: `\n`
+    #[default]
+    Lf,
+    /// Windows line ending: `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    /// Detects the dominant line ending of the text; ties and texts without line breaks are `Lf`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::line_ending::LineEnding;
+    /// assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+    /// assert_eq!(LineEnding::detect("a\nb"), LineEnding::Lf);
+    /// assert_eq!(LineEnding::detect("no line breaks"), LineEnding::Lf);
+    /// ```
+    pub fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// The characters of this line ending
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// The line ending of a file and whether it ends with a line break
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextStyle {
+    /// The dominant line ending
+    pub line_ending: LineEnding,
+    /// Whether the text ends with a line break
+    pub trailing_newline: bool,
+}
+
+impl TextStyle {
+    /// Detects the style of the text
+    pub fn detect(text: &str) -> Self {
+        TextStyle {
+            line_ending: LineEnding::detect(text),
+            trailing_newline: text.ends_with('\n'),
+        }
+    }
+
+    /// Rewrites the text with this style: all line breaks use the line ending,
+    /// and a final line break is added or removed as needed
+    pub fn apply(&self, text: &str) -> String {
+        let mut normalized = normalize(text);
+        if self.trailing_newline && !normalized.ends_with('\n') {
+            normalized.push('\n');
+        } else if !self.trailing_newline && normalized.ends_with('\n') {
+            normalized.pop();
+        }
+        match self.line_ending {
+            LineEnding::Lf => normalized,
+            LineEnding::CrLf => normalized.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Converts all line breaks in the text to `\n`
+pub fn normalize(text: &str) -> String {
+    text.replace("\r\n", "\n")
+}
+
+/// Runs a transformation on the text with `\n` line breaks, then restores the original style
+///
+/// # Examples
+///
+/// ```
+/// use logseq::line_ending::preserving_line_endings;
+/// let fixed = preserving_line_endings("- a\r\n- b\r\n", |text| text.replace("b", "c").trim_end().to_string());
+/// assert_eq!(fixed, "- a\r\n- c\r\n");
+/// ```
+pub fn preserving_line_endings(text: &str, transform: impl FnOnce(&str) -> String) -> String {
+    let style = TextStyle::detect(text);
+    style.apply(&transform(&normalize(text)))
+}
+
+/// Runs a transformation like [preserving_line_endings], for transformations that return None
+/// when they change nothing. The text itself is returned when it doesn't change, without
+/// copying it (unless it has `\r\n` line breaks, which the transformation never sees).
+///
+/// # Examples
+///
+/// ```
+/// use logseq::line_ending::preserving_line_endings_if_changed;
+/// use std::borrow::Cow;
+/// let unchanged = preserving_line_endings_if_changed("- a\n", |_| None);
+/// assert!(matches!(unchanged, Cow::Borrowed("- a\n")));
+/// let fixed = preserving_line_endings_if_changed("- a\r\n", |text| Some(text.replace('a', "b")));
+/// assert_eq!(fixed, "- b\r\n");
+/// ```
+pub fn preserving_line_endings_if_changed<'a>(
+    text: &'a str,
+    transform: impl FnOnce(&str) -> Option<String>,
+) -> Cow<'a, str> {
+    let style = TextStyle::detect(text);
+    let fixed = if text.contains('\r') {
+        let normalized = normalize(text);
+        style.apply(&transform(&normalized).unwrap_or(normalized))
+    } else {
+        match transform(text) {
+            Some(fixed) => style.apply(&fixed),
+            None => return Cow::Borrowed(text),
+        }
+    };
+    match fixed == text {
+        true => Cow::Borrowed(text),
+        false => Cow::Owned(fixed),
+    }
+}
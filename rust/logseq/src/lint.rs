@@ -0,0 +1,676 @@
+//! Lint engine: a registry of rules that check (and optionally fix) Logseq pages
+
+use crate::config::GraphConfig;
+use crate::diff::{apply_hunks, hunks, revert_changes, revert_changes_with, unified_diff, Hunk};
+use crate::graph::Graph;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::outline::{parse_outline, protected_regions, Block, Outline, RegionKind};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+mod admonitions;
+mod blocks;
+mod callouts;
+mod citations;
+mod config;
+mod drawers;
+mod explain;
+mod footnotes;
+mod images;
+mod links;
+mod properties;
+mod punctuation;
+mod queries;
+mod spacing;
+mod spelling;
+mod suppress;
+mod tables;
+mod tags;
+mod tasks;
+mod times;
+mod variables;
+
+pub use admonitions::{Admonitions, ADMONITION_KINDS};
+pub use blocks::{
+    ContinuationIndent, DeepBlockFix, EmptyBlocks, Headings, IndentUnit, Indentation, ListMarkers,
+    NestingDepth, TitleHeading,
+};
+pub use callouts::Callouts;
+pub use citations::{Citations, CITATION_PROPERTIES};
+pub use config::{LintConfig, CONFIG_FILE_NAME};
+pub use drawers::{Logbook, LogbookMode, PropertiesDrawer};
+pub use explain::{ExampleProblem, RuleExample, RuleInfo, RulesFormat, EXAMPLE_PAGE};
+pub use footnotes::{FootnoteStyle, Footnotes};
+pub use images::ImageProps;
+pub use links::{LinkNormalization, LinkStyle, MalformedRefs, PageLinkStyle};
+pub use properties::{
+    EditorProperties, FrontMatter, PageProperties, PagePropertyValues, PropertyOrder,
+    PropertySchema, TimestampProperties,
+};
+pub use punctuation::Punctuation;
+pub use queries::Queries;
+pub use spacing::{ConsecutiveSpaces, TrailingWhitespace};
+pub use spelling::{Dictionary, Hunspell, Spelling, DICTIONARY_PAGE};
+use suppress::Suppressions;
+pub use suppress::DISABLE_PROPERTY;
+pub(crate) use tables::is_table_row;
+pub use tables::Tables;
+pub use tags::{tag_for, TagBrackets};
+pub use tasks::{Checkboxes, DoneMetadata, Priorities, Repeaters, TaskMarkers, Timestamps};
+pub use times::{BlockTimes, BLOCK_TIME_FORMAT};
+pub use variables::DynamicVariables;
+
+/// How serious the problems of a rule are, from the least to the most serious
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, never a failure unless asked for
+    Info,
+    /// A problem, the severity of all rules unless configured otherwise
+    #[default]
+    Warning,
+    /// A problem that always fails a lint run
+    Error,
+}
+
+impl Severity {
+    /// The name of the severity: `info`, `warning` or `error`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = Error;
+
+    /// Parses `info`, `warning` and `error`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown severity {}, expected error, warning or info", name),
+            )),
+        }
+    }
+}
+
+/// A problem found by a lint rule
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Id of the rule that produced this diagnostic
+    pub rule: &'static str,
+    /// How serious the problem is: the severity of the rule in the [RuleSet] that found it
+    pub severity: Severity,
+    /// Line number in the source, starting at 1
+    pub line: usize,
+    /// Column in the line, starting at 1
+    pub column: usize,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Constructs a new diagnostic for the given rule and position
+    pub fn new(rule: &'static str, line: usize, column: usize, message: impl Into<String>) -> Self {
+        Diagnostic {
+            rule,
+            severity: Severity::default(),
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a rule is about, for `lsd explain` and editors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleDoc<'a> {
+    /// What the rule finds, in a sentence or two
+    pub description: &'a str,
+    /// Pages named `Example` that the rule finds problems in, with its default options; rules
+    /// that do nothing until configured find none
+    pub examples: &'a [&'a str],
+    /// True if the rule fixes some of the problems it finds
+    pub fixable: bool,
+}
+
+/// A lint rule that checks an outline and optionally fixes the source
+pub trait Rule: Send + Sync {
+    /// Unique id of the rule, in kebab-case
+    fn id(&self) -> &'static str;
+
+    /// What the rule finds, with examples. The default implementation is empty.
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc::default()
+    }
+
+    /// Check the outline and return the problems found
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic>;
+
+    /// Fix the problems found by this rule.
+    /// Returns the fixed text, or None if the rule has no fix or nothing was changed.
+    fn fix(&self, _text: &str, _outline: &Outline) -> Option<String> {
+        None
+    }
+
+    /// Check the outline of a page whose name is known, for rules that compare its contents
+    /// with its name. The default implementation is [Rule::check].
+    fn check_named(&self, outline: &Outline, _name: &str) -> Vec<Diagnostic> {
+        self.check(outline)
+    }
+
+    /// Fix a page whose name is known, like [Rule::check_named]. The default implementation is
+    /// [Rule::fix].
+    fn fix_named(&self, text: &str, outline: &Outline, _name: &str) -> Option<String> {
+        self.fix(text, outline)
+    }
+
+    /// The [protected regions](crate::outline::protected_regions) the fix of the rule changes,
+    /// like the front matter it converts: changes to the other ones are undone, but those of
+    /// the indentation of their lines. The default implementation is none.
+    fn editable_regions(&self) -> &'static [RegionKind] {
+        &[]
+    }
+
+    /// Set the options of the rule from its table in the config file.
+    /// Rules without options only accept an empty table.
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        match options.as_table() {
+            Some([]) => Ok(()),
+            _ => Err(Error::parse(
+                None,
+                format!("the rule {} has no options", self.id()),
+            )),
+        }
+    }
+
+    /// Give the rule the graph the checked pages belong to, for rules that need to know about
+    /// other pages. The default implementation ignores it.
+    fn use_graph(&mut self, _graph: &Graph) {}
+
+    /// Give the rule the `config.edn` of the graph the checked pages belong to, for rules that
+    /// depend on its settings, like the title format of journals. The default implementation
+    /// ignores it.
+    fn use_graph_config(&mut self, _config: &GraphConfig) {}
+}
+
+/// How many times [RuleSet::fix] applies the rules at most, in case fixes keep undoing each other
+pub const FIX_PASSES: usize = 4;
+
+/// A registry of lint rules
+#[derive(Default)]
+pub struct RuleSet {
+    rules: Vec<Box<dyn Rule>>,
+    /// The severities of the rules that aren't warnings
+    severities: HashMap<&'static str, Severity>,
+}
+
+impl RuleSet {
+    /// Constructs an empty rule set
+    pub fn new() -> Self {
+        RuleSet::default()
+    }
+
+    /// Constructs a rule set with all built-in rules
+    pub fn builtin() -> Self {
+        // Front matter first: until it's converted, its lists look like blocks to other rules
+        RuleSet::new()
+            .with(FrontMatter::default())
+            .with(PageProperties)
+            .with(ConsecutiveSpaces)
+            .with(MalformedRefs)
+            .with(TagBrackets::default())
+            .with(Checkboxes::default())
+            .with(TaskMarkers::default())
+            .with(Priorities)
+            .with(Timestamps)
+            .with(Repeaters)
+            .with(BlockTimes::default())
+            .with(DoneMetadata::default())
+            .with(TrailingWhitespace)
+            .with(Punctuation::default())
+            .with(Tables)
+            .with(EmptyBlocks)
+            .with(ListMarkers)
+            .with(Indentation::default())
+            .with(ContinuationIndent)
+            .with(NestingDepth::default())
+            .with(Headings::default())
+            .with(TitleHeading)
+            .with(EditorProperties::default())
+            .with(Logbook::default())
+            .with(PropertiesDrawer)
+            .with(PropertyOrder::default())
+            .with(PropertySchema::default())
+            .with(TimestampProperties)
+            .with(PagePropertyValues)
+            .with(PageLinkStyle::default())
+            .with(LinkNormalization)
+            .with(ImageProps::default())
+            .with(Queries::default())
+            .with(DynamicVariables)
+            .with(Callouts)
+            .with(Admonitions)
+            .with(Footnotes::default())
+            .with(Citations::default())
+            .with(Spelling::default())
+    }
+
+    /// Adds a rule to the set, replacing any rule with the same id
+    pub fn add(&mut self, rule: impl Rule + 'static) {
+        self.rules.retain(|existing| existing.id() != rule.id());
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Adds a rule to the set, builder-style
+    pub fn with(mut self, rule: impl Rule + 'static) -> Self {
+        self.add(rule);
+        self
+    }
+
+    /// Keeps only the rules whose ids are in the given list
+    pub fn select(mut self, ids: &[&str]) -> Self {
+        self.rules.retain(|rule| ids.contains(&rule.id()));
+        self
+    }
+
+    /// Removes the rules whose ids are in the given list
+    pub fn without(mut self, ids: &[&str]) -> Self {
+        self.rules.retain(|rule| !ids.contains(&rule.id()));
+        self
+    }
+
+    /// Sets the options of the rule with the given id, see [Rule::configure]
+    pub fn configure(&mut self, id: &str, options: &Toml) -> Result<()> {
+        match self.rules.iter_mut().find(|rule| rule.id() == id) {
+            Some(rule) => rule.configure(options),
+            None => Err(Error::parse(None, format!("unknown rule {}", id))),
+        }
+    }
+
+    /// Sets the severity of the diagnostics of the rule with the given id
+    pub fn set_severity(&mut self, id: &str, severity: Severity) -> Result<()> {
+        let known = self.ids().into_iter().find(|known| *known == id);
+        match known {
+            Some(id) => {
+                self.severities.insert(id, severity);
+                Ok(())
+            }
+            None => Err(Error::parse(None, format!("unknown rule {}", id))),
+        }
+    }
+
+    /// The severity of the diagnostics of the rule with the given id
+    pub fn severity(&self, id: &str) -> Severity {
+        self.severities.get(id).copied().unwrap_or_default()
+    }
+
+    /// Gives the graph to every rule of the set, see [Rule::use_graph]
+    pub fn use_graph(&mut self, graph: &Graph) {
+        for rule in &mut self.rules {
+            rule.use_graph(graph);
+        }
+    }
+
+    /// Gives the `config.edn` of the graph to every rule of the set, see [Rule::use_graph_config]
+    pub fn use_graph_config(&mut self, config: &GraphConfig) {
+        for rule in &mut self.rules {
+            rule.use_graph_config(config);
+        }
+    }
+
+    /// Returns the rule with the given id
+    pub fn get(&self, id: &str) -> Option<&dyn Rule> {
+        self.iter().find(|rule| rule.id() == id)
+    }
+
+    /// Iterates over the rules of the set
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Ids of all rules in the set
+    pub fn ids(&self) -> Vec<&'static str> {
+        self.iter().map(|rule| rule.id()).collect()
+    }
+
+    /// Check the text with every rule, returning the diagnostics sorted by position, without
+    /// the ones that comments or properties suppress
+    pub fn check(&self, text: &str) -> Vec<Diagnostic> {
+        self.lint(text).diagnostics
+    }
+
+    /// Check the text with every rule, keeping apart the diagnostics that `lsd-disable`
+    /// comments or properties suppress, and the ones of lines that `lsd-ignore` comments mark
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::RuleSet;
+    /// let text = "- a  b <!-- lsd-disable consecutive-spaces -->\n- c  d\n";
+    /// let result = RuleSet::builtin().lint(text);
+    /// assert_eq!(result.diagnostics[0].line, 2);
+    /// assert_eq!(result.suppressed[0].line, 1);
+    /// assert_eq!(RuleSet::builtin().fix(text), "- a  b <!-- lsd-disable consecutive-spaces -->\n- c d\n");
+    /// ```
+    pub fn lint(&self, text: &str) -> LintResult {
+        self.lint_page(text, None)
+    }
+
+    /// Check the text of a page like [RuleSet::lint], giving its name to the rules that compare
+    /// it with the contents, see [Rule::check_named]
+    pub fn lint_page(&self, text: &str, name: Option<&str>) -> LintResult {
+        let outline = parse_outline(text);
+        let suppressions = Suppressions::parse(&outline);
+        let mut diagnostics: Vec<Diagnostic> = self
+            .iter()
+            .flat_map(|rule| match name {
+                Some(name) => rule.check_named(&outline, name),
+                None => rule.check(&outline),
+            })
+            .map(|diagnostic| Diagnostic {
+                severity: self.severity(diagnostic.rule),
+                ..diagnostic
+            })
+            .collect();
+        diagnostics.sort_by(|a, b| (a.line, a.column, a.rule).cmp(&(b.line, b.column, b.rule)));
+        let ignored: Vec<_> = protected_regions(text)
+            .into_iter()
+            .filter(|region| region.kind == RegionKind::Ignored)
+            .collect();
+        let (suppressed, diagnostics) = diagnostics.into_iter().partition(|diagnostic| {
+            suppressions.disables(diagnostic.rule, diagnostic.line)
+                || ignored
+                    .iter()
+                    .any(|region| region.lines.contains(&diagnostic.line))
+        });
+        LintResult {
+            diagnostics,
+            suppressed,
+        }
+    }
+
+    /// Apply the fix of every rule in order, re-parsing the text after each change.
+    /// The rules are applied again while they change something (see [FIX_PASSES]), as a fix can
+    /// give an earlier rule something to fix, so fixing the result changes nothing.
+    /// Rules always see `\n` line breaks; the original line endings are restored at the end.
+    /// Changes to the blocks where a rule is suppressed are undone.
+    /// The text is returned as it is, without copying it, when no rule changes it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::RuleSet;
+    /// use std::borrow::Cow;
+    /// assert!(matches!(RuleSet::builtin().fix("- a b\n"), Cow::Borrowed(_)));
+    /// assert!(matches!(RuleSet::builtin().fix("- a  b\n"), Cow::Owned(_)));
+    /// ```
+    pub fn fix<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.fix_page(text, None)
+    }
+
+    /// Fix the text of a page like [RuleSet::fix], giving its name to the rules that compare it
+    /// with the contents, see [Rule::fix_named]
+    pub fn fix_page<'a>(&self, text: &'a str, name: Option<&str>) -> Cow<'a, str> {
+        preserving_line_endings_if_changed(text, |normalized| {
+            let mut current: Option<String> = None;
+            for _ in 0..FIX_PASSES {
+                let mut changed = false;
+                for rule in self.iter() {
+                    let text = current.as_deref().unwrap_or(normalized);
+                    if let Some(fixed) = fix_with(rule, text, name) {
+                        current = Some(fixed);
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            current
+        })
+    }
+
+    /// Apply the fix of every rule in order like [RuleSet::fix], keeping only the changes that
+    /// `accept` approves: the changes of each rule are split into [Hunk]s of consecutive lines,
+    /// and `accept` gets the id of the rule and each hunk, against the text the earlier rules
+    /// left. The rules are applied once: a change that gives an earlier rule something to fix
+    /// is left to the next run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::RuleSet;
+    /// let rules = RuleSet::builtin().select(&["consecutive-spaces", "tag-brackets"]);
+    /// let text = "- a  b\n- ok\n- c  d #[[tag]]\n";
+    /// let fixed = rules.fix_selected(text, |rule, hunk| rule == "tag-brackets" || hunk.line == 1);
+    /// assert_eq!(fixed, "- a b\n- ok\n- c  d #tag\n");
+    /// ```
+    pub fn fix_selected<'a>(
+        &self,
+        text: &'a str,
+        mut accept: impl FnMut(&'static str, &Hunk) -> bool,
+    ) -> Cow<'a, str> {
+        preserving_line_endings_if_changed(text, |normalized| {
+            let mut current: Option<String> = None;
+            for rule in self.iter() {
+                let text = current.as_deref().unwrap_or(normalized);
+                let Some(fixed) = fix_with(rule, text, None) else {
+                    continue;
+                };
+                let accepted: Vec<Hunk> = hunks(text, &fixed)
+                    .into_iter()
+                    .filter(|hunk| accept(rule.id(), hunk))
+                    .collect();
+                if !accepted.is_empty() {
+                    current = Some(apply_hunks(text, &accepted));
+                }
+            }
+            current
+        })
+    }
+
+    /// Apply the fix of a single rule of the set, like [RuleSet::fix] does; None if the rule
+    /// isn't in the set or changes nothing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::RuleSet;
+    /// let rules = RuleSet::builtin();
+    /// assert_eq!(rules.fix_rule("consecutive-spaces", "- a  b\n"), Some("- a b\n".to_string()));
+    /// assert_eq!(rules.fix_rule("consecutive-spaces", "- a b\n"), None);
+    /// ```
+    pub fn fix_rule(&self, id: &str, text: &str) -> Option<String> {
+        let rule = self.get(id)?;
+        match preserving_line_endings_if_changed(text, |normalized| {
+            fix_with(rule, normalized, None)
+        }) {
+            Cow::Owned(fixed) => Some(fixed),
+            Cow::Borrowed(_) => None,
+        }
+    }
+
+    /// Fix the text like [RuleSet::fix], keeping the original and a diff to review the changes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::RuleSet;
+    /// let outcome = RuleSet::builtin().fix_outcome("page.md", "- a  b");
+    /// assert!(outcome.changed());
+    /// assert_eq!(outcome.fixed, "- a b");
+    /// assert!(outcome.diff.contains("\n-- a  b\n"));
+    /// ```
+    pub fn fix_outcome(&self, label: &str, text: &str) -> FixOutcome {
+        self.fix_page_outcome(label, text, None)
+    }
+
+    /// Fix the text of a page like [RuleSet::fix_page], keeping the original and a diff
+    pub fn fix_page_outcome(&self, label: &str, text: &str, name: Option<&str>) -> FixOutcome {
+        match self.fix_page(text, name) {
+            Cow::Owned(fixed) => FixOutcome::new(label, text.to_string(), fixed),
+            Cow::Borrowed(_) => FixOutcome {
+                original: text.to_string(),
+                fixed: text.to_string(),
+                diff: String::new(),
+            },
+        }
+    }
+}
+
+/// The text fixed by a rule, without the changes to the blocks where the rule is suppressed and
+/// to the protected regions it doesn't edit (see [Rule::editable_regions]); None if the rule
+/// changes nothing or is suppressed for the whole page
+fn fix_with(rule: &dyn Rule, text: &str, name: Option<&str>) -> Option<String> {
+    let outline = parse_outline(text);
+    let suppressions = Suppressions::parse(&outline);
+    if suppressions.page_disables(rule.id()) {
+        return None;
+    }
+    let mut fixed = match name {
+        Some(name) => rule.fix_named(text, &outline, name)?,
+        None => rule.fix(text, &outline)?,
+    };
+    if suppressions.some_blocks_disable(rule.id()) {
+        fixed = revert_changes(text, &fixed, |line| suppressions.disables(rule.id(), line));
+    }
+    let editable = rule.editable_regions();
+    let protected: Vec<_> = protected_regions(text)
+        .into_iter()
+        .filter(|region| !editable.contains(&region.kind))
+        .collect();
+    if !protected.is_empty() {
+        fixed = revert_changes_with(
+            text,
+            &fixed,
+            |line| protected.iter().any(|region| region.lines.contains(&line)),
+            str::trim_start,
+        );
+    }
+    (fixed != text).then_some(fixed)
+}
+
+/// The diagnostics of a text, and the ones suppressed by `lsd-disable` comments or properties
+/// or in lines marked `lsd-ignore`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintResult {
+    /// Problems found, sorted by position
+    pub diagnostics: Vec<Diagnostic>,
+    /// Problems found where their rule is suppressed, sorted by position
+    pub suppressed: Vec<Diagnostic>,
+}
+
+/// The result of fixing a text: what it was, what it becomes, and the difference between them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixOutcome {
+    /// The text before fixing it
+    pub original: String,
+    /// The text after fixing it
+    pub fixed: String,
+    /// A unified diff from the original to the fixed text, empty if nothing changed
+    pub diff: String,
+}
+
+impl FixOutcome {
+    /// Compares the original and fixed text; `label` names the file in the diff headers
+    pub fn new(label: &str, original: String, fixed: String) -> Self {
+        let diff = unified_diff(label, &original, &fixed);
+        FixOutcome {
+            original,
+            fixed,
+            diff,
+        }
+    }
+
+    /// True if fixing changed the text
+    pub fn changed(&self) -> bool {
+        self.original != self.fixed
+    }
+}
+
+/// Rewrites the first line of blocks, keeping their indentation and bullet.
+/// `rewrite` returns the new content of the first line of a block, or None to keep it.
+/// Returns the new text, or None if nothing changed.
+pub(crate) fn rewrite_first_lines(
+    text: &str,
+    outline: &Outline,
+    rewrite: impl Fn(&Block) -> Option<String>,
+) -> Option<String> {
+    let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+    let mut changed = false;
+    for block in outline.iter() {
+        if let Some(content) = rewrite(block) {
+            if content != block.first_line() {
+                lines[block.line - 1] = format!("{}{} {}", block.indent, block.marker, content);
+                changed = true;
+            }
+        }
+    }
+    changed.then(|| lines.join("\n"))
+}
+
+/// Text to put in place of a byte range of the content of a line
+pub(crate) struct Replacement {
+    /// Line number in the source, starting at 1
+    pub line: usize,
+    /// Byte range in the content of the line, without the indentation or the bullet
+    pub range: std::ops::Range<usize>,
+    /// Length of the content of the line, which ends the source line
+    pub content_length: usize,
+    /// The new text
+    pub text: String,
+}
+
+/// Applies replacements to the source; they must not overlap.
+/// Returns the new text, or None if there are no replacements.
+pub(crate) fn apply_replacements(text: &str, mut replacements: Vec<Replacement>) -> Option<String> {
+    if replacements.is_empty() {
+        return None;
+    }
+    let original: Vec<&str> = text.split('\n').collect();
+    let mut lines: Vec<String> = original.iter().map(|line| line.to_string()).collect();
+    // Later ranges first, so that the offsets of earlier ones stay valid
+    replacements.sort_by_key(|replacement| (replacement.line, replacement.range.start));
+    for replacement in replacements.into_iter().rev() {
+        let offset = original[replacement.line - 1].len() - replacement.content_length;
+        let range = offset + replacement.range.start..offset + replacement.range.end;
+        lines[replacement.line - 1].replace_range(range, &replacement.text);
+    }
+    Some(lines.join("\n"))
+}
+
+/// Run the lint rules on the contents of a page
+///
+/// # Examples
+///
+/// ```
+/// use logseq::lint::{run_lints, RuleSet};
+/// let diagnostics = run_lints("- Some  text #[[tag]]", &RuleSet::builtin());
+/// assert_eq!(diagnostics.len(), 2);
+/// assert_eq!(diagnostics[0].rule, "consecutive-spaces");
+/// assert_eq!(diagnostics[0].column, 7);
+/// assert_eq!(diagnostics[1].rule, "tag-brackets");
+/// assert_eq!(RuleSet::builtin().fix("- Some  text #[[tag]]"), "- Some text #tag");
+/// ```
+pub fn run_lints(text: &str, rules: &RuleSet) -> Vec<Diagnostic> {
+    rules.check(text)
+}
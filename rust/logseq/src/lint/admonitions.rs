@@ -0,0 +1,208 @@
+//! Rules about quotes and admonitions, the `#+BEGIN_QUOTE`/`#+END_QUOTE` and
+//! `#+BEGIN_NOTE`/`#+END_NOTE` pairs of a block
+
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::refs::lines_outside_code;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static MARKER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^#\+(BEGIN|END)_([A-Z]+)\b").unwrap());
+
+/// The blocks checked: quotes, admonitions and the other blocks Logseq renders in Markdown.
+/// Source blocks and queries have rules of their own.
+pub const ADMONITION_KINDS: &[&str] = &[
+    "QUOTE",
+    "NOTE",
+    "TIP",
+    "IMPORTANT",
+    "CAUTION",
+    "WARNING",
+    "PINNED",
+    "EXAMPLE",
+    "CENTER",
+    "VERSE",
+];
+
+/// A `#+BEGIN_` or `#+END_` line of a block
+struct Marker {
+    line: usize,
+    column: usize,
+    begin: bool,
+    /// The kind in uppercase, e.g. `NOTE`
+    kind: String,
+}
+
+/// The markers of a block, ignoring code
+fn markers(block: &Block) -> Vec<Marker> {
+    lines_outside_code(block.numbered_lines())
+        .filter_map(|(line, text)| {
+            let trimmed = text.trim_start();
+            let captures = MARKER_RE.captures(trimmed)?;
+            let kind = captures[2].to_uppercase();
+            ADMONITION_KINDS.contains(&kind.as_str()).then(|| Marker {
+                line,
+                column: block.source_column(text.len() - trimmed.len() + 1),
+                begin: captures[1].eq_ignore_ascii_case("BEGIN"),
+                kind,
+            })
+        })
+        .collect()
+}
+
+/// The last line of a block with content: a missing `#+END_` goes after it
+fn last_line(block: &Block) -> usize {
+    block
+        .numbered_lines()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .last()
+        .map_or(block.line, |(line, _)| line)
+}
+
+/// A change to the lines of the source
+enum Edit {
+    /// Insert `#+END_` lines after the line, indented like continuation lines of the block
+    InsertEnds {
+        after: usize,
+        indent: String,
+        kinds: Vec<String>,
+    },
+    /// Remove the line
+    Remove(usize),
+}
+
+/// `#+BEGIN_QUOTE`, `#+BEGIN_NOTE` and the like without their `#+END_`, `#+END_` lines without
+/// a `#+BEGIN_`, and blocks closed in the wrong order, like `#+END_NOTE` before the `#+END_TIP`
+/// inside it. The fix closes the blocks left open at the end of their bullet, the level
+/// Logseq reads them at. When the `#+END_` ended up in a later bullet, the content in between
+/// is outside the block: that `#+END_` is removed. Blocks closed in the wrong order are only
+/// reported.
+pub struct Admonitions;
+
+impl Admonitions {
+    /// The problems found, with the edit that fixes each one, if any
+    fn problems(&self, outline: &Outline) -> Vec<(Diagnostic, Option<Edit>)> {
+        let mut problems = Vec::new();
+        // Blocks left open in earlier bullets, with the line of their `#+BEGIN_`
+        let mut left_open: Vec<(String, usize)> = Vec::new();
+        for block in outline.iter() {
+            let mut open: Vec<Marker> = Vec::new();
+            for marker in markers(block) {
+                if marker.begin {
+                    open.push(marker);
+                    continue;
+                }
+                let diagnostic = |message: String| {
+                    Diagnostic::new(self.id(), marker.line, marker.column, message)
+                };
+                match open.iter().rposition(|begin| begin.kind == marker.kind) {
+                    Some(index) if index + 1 == open.len() => {
+                        open.pop();
+                    }
+                    Some(index) => {
+                        let inner = &open[index + 1];
+                        let message = format!(
+                            "#+END_{} before the #+END_{} of the #+BEGIN_{} of line {}",
+                            marker.kind, inner.kind, inner.kind, inner.line
+                        );
+                        problems.push((diagnostic(message), None));
+                        open.truncate(index);
+                    }
+                    None => match left_open.iter().rposition(|(kind, _)| *kind == marker.kind) {
+                        Some(index) => {
+                            let (kind, line) = left_open.remove(index);
+                            let message = format!(
+                                "#+END_{} of the #+BEGIN_{} of line {} is in another bullet, \
+                                 the content in between is outside the block",
+                                kind, kind, line
+                            );
+                            // The first line holds the bullet, it can't go
+                            let edit =
+                                (marker.line != block.line).then_some(Edit::Remove(marker.line));
+                            problems.push((diagnostic(message), edit));
+                        }
+                        None => {
+                            let message =
+                                format!("#+END_{} without #+BEGIN_{}", marker.kind, marker.kind);
+                            problems.push((diagnostic(message), None));
+                        }
+                    },
+                }
+            }
+            if open.is_empty() {
+                continue;
+            }
+            for begin in &open {
+                let message = format!("#+BEGIN_{} without #+END_{}", begin.kind, begin.kind);
+                let diagnostic = Diagnostic::new(self.id(), begin.line, begin.column, message);
+                problems.push((diagnostic, None));
+            }
+            let edit = Edit::InsertEnds {
+                after: last_line(block),
+                indent: format!("{}  ", block.indent),
+                kinds: open.iter().rev().map(|begin| begin.kind.clone()).collect(),
+            };
+            // The edit closing all of them goes with the innermost one
+            if let Some(last) = problems.last_mut() {
+                last.1 = Some(edit);
+            }
+            left_open.extend(open.into_iter().map(|begin| (begin.kind, begin.line)));
+        }
+        problems
+    }
+}
+
+impl Rule for Admonitions {
+    fn id(&self) -> &'static str {
+        "admonitions"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`#+BEGIN_` blocks left open, `#+END_` lines without a `#+BEGIN_`, and blocks closed in the wrong order.",
+            examples: &["- #+BEGIN_NOTE\n  Buy milk\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(diagnostic, _)| diagnostic)
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let edits: Vec<Edit> = self
+            .problems(outline)
+            .into_iter()
+            .filter_map(|(_, edit)| edit)
+            .collect();
+        if edits.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = text.split('\n').collect();
+        let mut removed = vec![false; lines.len()];
+        let mut inserted: Vec<Vec<String>> = vec![Vec::new(); lines.len()];
+        for edit in edits {
+            match edit {
+                Edit::Remove(line) => removed[line - 1] = true,
+                Edit::InsertEnds {
+                    after,
+                    indent,
+                    kinds,
+                } => inserted[after - 1]
+                    .extend(kinds.iter().map(|kind| format!("{}#+END_{}", indent, kind))),
+            }
+        }
+        let mut fixed = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if !removed[index] {
+                fixed.push(line.to_string());
+            }
+            fixed.append(&mut inserted[index]);
+        }
+        Some(fixed.join("\n"))
+    }
+}
@@ -0,0 +1,865 @@
+//! Rules about the structure of the outline
+
+use crate::graph::page_key;
+use crate::ids::new_uuid;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::config::rule_options;
+use crate::lint::{rewrite_first_lines, Diagnostic, Rule, RuleDoc};
+use crate::outline::{parse_outline, Block, Outline};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use std::borrow::Cow;
+
+/// Bullets with nothing after them and no children.
+/// The single empty bullet of an empty page is how Logseq saves it, so it's left alone.
+pub struct EmptyBlocks;
+
+impl EmptyBlocks {
+    fn empty_blocks(outline: &Outline) -> Vec<&Block> {
+        let single_block = outline.blocks.len() == 1 && outline.blocks[0].children.is_empty();
+        if single_block && outline.preamble.iter().all(|line| line.trim().is_empty()) {
+            return Vec::new();
+        }
+        outline
+            .iter()
+            .filter(|block| block.children.is_empty() && block.content.trim().is_empty())
+            .collect()
+    }
+}
+
+impl Rule for EmptyBlocks {
+    fn id(&self) -> &'static str {
+        "empty-blocks"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Bullets with nothing after them and no children.",
+            examples: &["- Notes\n-\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        EmptyBlocks::empty_blocks(outline)
+            .into_iter()
+            .map(|block| {
+                let bullet_column = block.indent.chars().count() + 1;
+                Diagnostic::new(self.id(), block.line, bullet_column, "Empty block")
+            })
+            .collect()
+    }
+
+    /// Removes the empty blocks, then the parents that only had empty blocks as children
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut current = text.to_string();
+        let mut outline = outline.clone();
+        loop {
+            let removed: Vec<(usize, usize)> = EmptyBlocks::empty_blocks(&outline)
+                .into_iter()
+                .map(|block| (block.line, block.line + block.line_count()))
+                .collect();
+            if removed.is_empty() {
+                break;
+            }
+            current = current
+                .split('\n')
+                .enumerate()
+                .filter(|(index, _)| {
+                    let line = index + 1;
+                    !removed
+                        .iter()
+                        .any(|(start, end)| (*start..*end).contains(&line))
+                })
+                .map(|(_, line)| line)
+                .collect::<Vec<_>>()
+                .join("\n");
+            outline = parse_outline(&current);
+        }
+        (current != text).then_some(current)
+    }
+}
+
+/// The unit of indentation of nested blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentUnit {
+    /// One tab per level, like Logseq writes it
+    Tab,
+    /// The given number of spaces per level
+    Spaces(usize),
+}
+
+impl IndentUnit {
+    /// The indentation of a block at the given nesting level
+    pub fn indent(&self, level: usize) -> String {
+        match self {
+            IndentUnit::Tab => "\t".repeat(level),
+            IndentUnit::Spaces(count) => " ".repeat(count * level),
+        }
+    }
+
+    /// The unit of the indentation of a block at level 1
+    pub(crate) fn detect(indent: &str) -> Option<Self> {
+        if !indent.is_empty() && indent.chars().all(|c| c == '\t') {
+            Some(IndentUnit::Tab)
+        } else if !indent.is_empty() && indent.chars().all(|c| c == ' ') {
+            Some(IndentUnit::Spaces(indent.len()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Nested blocks indented with something other than one unit per level, e.g. a mix of tabs
+/// and spaces. Without a configured unit, each page keeps the unit of its first nested block.
+#[derive(Default)]
+pub struct Indentation {
+    /// The indentation unit, or None to use the one of each page
+    pub unit: Option<IndentUnit>,
+}
+
+impl Indentation {
+    fn unit(&self, outline: &Outline) -> Option<IndentUnit> {
+        self.unit.or_else(|| {
+            outline
+                .iter()
+                .find(|block| block.level == 1)
+                .and_then(|block| IndentUnit::detect(&block.indent))
+        })
+    }
+
+    /// The blocks that are not indented as expected, with their expected indentation
+    fn misindented<'a>(&self, outline: &'a Outline) -> Vec<(&'a Block, String)> {
+        let Some(unit) = self.unit(outline) else {
+            return Vec::new();
+        };
+        outline
+            .iter()
+            .map(|block| (block, unit.indent(block.level)))
+            .filter(|(block, expected)| block.indent != *expected)
+            .collect()
+    }
+}
+
+impl Rule for Indentation {
+    fn id(&self) -> &'static str {
+        "indentation"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Nested blocks indented with something other than one unit per level, like a mix of tabs and spaces.",
+            examples: &["- Parent\n	- Child\n    - Other child\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.misindented(outline)
+            .into_iter()
+            .map(|(block, expected)| {
+                let (count, unit) = match self.unit(outline) {
+                    Some(IndentUnit::Spaces(count)) => (count * block.level, "space"),
+                    _ => (block.level, "tab"),
+                };
+                let plural = if count == 1 { "" } else { "s" };
+                let message = if expected.is_empty() {
+                    "Top-level block should not be indented".to_string()
+                } else {
+                    format!(
+                        "Block at level {} should be indented with {} {}{}",
+                        block.level, count, unit, plural
+                    )
+                };
+                Diagnostic::new(self.id(), block.line, 1, message)
+            })
+            .collect()
+    }
+
+    /// Re-indents the bullet and continuation lines of each block, keeping the relative
+    /// indentation of the continuation lines (e.g. code)
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let misindented = self.misindented(outline);
+        if misindented.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        for (block, expected) in misindented {
+            for line in block.line..block.line + block.line_count() {
+                if let Some(rest) = lines[line - 1].strip_prefix(block.indent.as_str()) {
+                    lines[line - 1] = format!("{}{}", expected, rest);
+                }
+            }
+        }
+        Some(lines.join("\n"))
+    }
+
+    /// `unit = "tab"`, a number of spaces like `unit = 2`, or `"auto"` to keep the unit of each page
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["unit"])? {
+            self.unit = match (value.as_str(), value.as_integer()) {
+                (Some("tab"), _) => Some(IndentUnit::Tab),
+                (Some("auto"), _) => None,
+                (_, Some(count @ 1..=8)) => Some(IndentUnit::Spaces(count as usize)),
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.indentation.unit must be \"tab\", \"auto\" or a number of spaces from 1 to 8",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Continuation lines of a block not indented like its bullet plus two spaces, as Logseq writes
+/// them. Logseq reads them as part of the block anyway, but other Markdown editors and tools
+/// see a new paragraph, or a line of the parent block, and can break the block apart. The fix
+/// re-indents them; code is left alone.
+pub struct ContinuationIndent;
+
+impl Rule for ContinuationIndent {
+    fn id(&self) -> &'static str {
+        "continuation-indent"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description:
+                "Continuation lines of a block not indented like its bullet plus two spaces.",
+            examples: &["- First line\nsecond line\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .flat_map(|block| {
+                block.misindented_lines.iter().map(|&line| {
+                    let message =
+                        "Continuation line should be indented like its bullet, plus two spaces";
+                    Diagnostic::new(self.id(), line, 1, message)
+                })
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for block in outline.iter() {
+            for &line in &block.misindented_lines {
+                let source = &lines[line - 1];
+                lines[line - 1] = format!("{}  {}", block.indent, source.trim_start());
+                changed = true;
+            }
+        }
+        changed.then(|| lines.join("\n"))
+    }
+}
+
+/// What the fix of [NestingDepth] does with the blocks nested too deep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeepBlockFix {
+    /// Outdent them to the deepest level allowed, as siblings of their parent
+    #[default]
+    Outdent,
+    /// Outdent them like [DeepBlockFix::Outdent], with a `((ref))` to their parent at the end of
+    /// their first line, giving the parent an `id::` if it has none
+    Ref,
+}
+
+/// Blocks nested deeper than `max_depth` levels below the top-level blocks, which are hard to
+/// read, and to edit on a phone. Each subtree that goes too deep is reported at its first
+/// block; the fix outdents all its blocks to the deepest level allowed, see [DeepBlockFix].
+pub struct NestingDepth {
+    /// The deepest nesting level allowed, with 0 for top-level blocks
+    pub max_depth: usize,
+    /// What the fix does with the blocks nested too deep
+    pub fix: DeepBlockFix,
+}
+
+impl Default for NestingDepth {
+    fn default() -> Self {
+        NestingDepth {
+            max_depth: 6,
+            fix: DeepBlockFix::Outdent,
+        }
+    }
+}
+
+impl NestingDepth {
+    /// The blocks nested too deep, with their parent
+    fn deep_blocks<'a>(
+        &self,
+        blocks: &'a [Block],
+        parent: Option<&'a Block>,
+        found: &mut Vec<(&'a Block, Option<&'a Block>)>,
+    ) {
+        for block in blocks {
+            if block.level > self.max_depth {
+                found.push((block, parent));
+            }
+            self.deep_blocks(&block.children, Some(block), found);
+        }
+    }
+
+    /// Outdents the blocks nested too deep, with references to their parents for
+    /// [DeepBlockFix::Ref]
+    fn outdent(&self, text: &str) -> Option<String> {
+        let outline = parse_outline(text);
+        let mut deep = Vec::new();
+        self.deep_blocks(&outline.blocks, None, &mut deep);
+        if deep.is_empty() {
+            return None;
+        }
+        let unit = outline
+            .iter()
+            .find(|block| block.level == 1)
+            .and_then(|block| IndentUnit::detect(&block.indent))
+            .unwrap_or(IndentUnit::Tab);
+        let indent = unit.indent(self.max_depth);
+        let indent_of = |block: &Block| match block.level > self.max_depth {
+            true => indent.clone(),
+            false => block.indent.clone(),
+        };
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        // The `id::` lines to insert, with the index they are inserted at
+        let mut ids: Vec<(usize, String)> = Vec::new();
+        for (block, parent) in deep {
+            for line in block.line..block.line + block.line_count() {
+                if let Some(rest) = lines[line - 1].strip_prefix(block.indent.as_str()) {
+                    lines[line - 1] = format!("{}{}", indent, rest);
+                }
+            }
+            let Some(parent) = parent.filter(|_| self.fix == DeepBlockFix::Ref) else {
+                continue;
+            };
+            let uuid = match parent.properties().get("id") {
+                Some(id) => id.raw.trim().to_lowercase(),
+                None => match ids.iter().find(|(at, _)| *at == parent.line) {
+                    Some((_, line)) => line.rsplit(' ').next().unwrap_or_default().to_string(),
+                    None => {
+                        let uuid = new_uuid();
+                        ids.push((parent.line, format!("{}  id:: {}", indent_of(parent), uuid)));
+                        uuid
+                    }
+                },
+            };
+            let first = &mut lines[block.line - 1];
+            *first = format!("{} (({}))", first.trim_end(), uuid);
+        }
+        ids.sort_by_key(|(at, _)| std::cmp::Reverse(*at));
+        for (at, line) in ids {
+            lines.insert(at, line);
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+impl Rule for NestingDepth {
+    fn id(&self) -> &'static str {
+        "nesting-depth"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Blocks nested deeper than the maximum depth, 6 levels by default.",
+            examples: &["- 0\n\t- 1\n\t\t- 2\n\t\t\t- 3\n\t\t\t\t- 4\n\t\t\t\t\t- 5\n\t\t\t\t\t\t- 6\n\t\t\t\t\t\t\t- 7\n\t\t\t\t\t\t\t\t- 8\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut deep = Vec::new();
+        self.deep_blocks(&outline.blocks, None, &mut deep);
+        deep.into_iter()
+            .filter(|(block, _)| block.level == self.max_depth + 1)
+            .map(|(block, _)| {
+                let message = format!(
+                    "Block nested {} levels deep, more than {}",
+                    block
+                        .iter()
+                        .map(|child| child.level)
+                        .max()
+                        .unwrap_or(block.level),
+                    self.max_depth
+                );
+                Diagnostic::new(self.id(), block.line, block.source_column(1), message)
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        if self.check(outline).is_empty() {
+            return None;
+        }
+        match preserving_line_endings_if_changed(text, |text| self.outdent(text)) {
+            Cow::Owned(fixed) => Some(fixed),
+            Cow::Borrowed(_) => None,
+        }
+    }
+
+    /// `max-depth = 6`, and `fix = "outdent"` or `"ref"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["max-depth", "fix"])? {
+            if key == "max-depth" {
+                self.max_depth = match value.as_integer() {
+                    Some(depth @ 1..) => depth as usize,
+                    _ => {
+                        return Err(Error::parse(
+                            None,
+                            "rules.nesting-depth.max-depth must be a number from 1",
+                        ))
+                    }
+                };
+                continue;
+            }
+            self.fix = match value.as_str() {
+                Some("outdent") => DeepBlockFix::Outdent,
+                Some("ref") => DeepBlockFix::Ref,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.nesting-depth.fix must be \"outdent\" or \"ref\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Why a heading doesn't have the expected level
+enum HeadingProblem {
+    TooDeep,
+    NotUnderParent(usize),
+    OutlineDepth,
+}
+
+/// A heading block whose level should change
+struct MisleveledHeading<'a> {
+    block: &'a Block,
+    level: usize,
+    expected: usize,
+    problem: HeadingProblem,
+}
+
+/// `- # Heading` blocks whose level doesn't follow the outline: a heading nested under another
+/// one should be one level deeper (`##` under `#`, not `####` or `#`), and no heading should be
+/// deeper than the maximum level. With `match_depth`, the level of each heading is its depth in
+/// the outline instead: `#` for top-level blocks, `##` for their children, and so on.
+pub struct Headings {
+    /// The deepest heading level allowed, from 1 to 6
+    pub max_level: usize,
+    /// Whether heading levels should match the depth of their blocks in the outline
+    pub match_depth: bool,
+}
+
+impl Default for Headings {
+    fn default() -> Self {
+        Headings {
+            max_level: 6,
+            match_depth: false,
+        }
+    }
+}
+
+impl Headings {
+    /// The headings in the blocks and their children whose level should change, given the
+    /// expected level of the nearest heading above them
+    fn collect<'a>(
+        &self,
+        blocks: &'a [Block],
+        parent: Option<usize>,
+        found: &mut Vec<MisleveledHeading<'a>>,
+    ) {
+        for block in blocks {
+            let mut nearest = parent;
+            if let Some(level) = block.heading_level() {
+                let (expected, problem) = if self.match_depth {
+                    (
+                        (block.level + 1).min(self.max_level),
+                        HeadingProblem::OutlineDepth,
+                    )
+                } else {
+                    match parent {
+                        Some(parent) if parent >= self.max_level && level >= self.max_level => {
+                            (self.max_level, HeadingProblem::TooDeep)
+                        }
+                        Some(parent) if level != parent + 1 => (
+                            (parent + 1).min(self.max_level),
+                            HeadingProblem::NotUnderParent(parent),
+                        ),
+                        _ => (level.min(self.max_level), HeadingProblem::TooDeep),
+                    }
+                };
+                if level != expected {
+                    found.push(MisleveledHeading {
+                        block,
+                        level,
+                        expected,
+                        problem,
+                    });
+                }
+                nearest = Some(expected);
+            }
+            self.collect(&block.children, nearest, found);
+        }
+    }
+
+    fn misleveled<'a>(&self, outline: &'a Outline) -> Vec<MisleveledHeading<'a>> {
+        let mut found = Vec::new();
+        self.collect(&outline.blocks, None, &mut found);
+        found
+    }
+}
+
+impl Rule for Headings {
+    fn id(&self) -> &'static str {
+        "headings"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description:
+                "Headings whose level doesn't follow the outline, or deeper than the maximum level.",
+            examples: &["- # Example\n	- ### Section\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.misleveled(outline)
+            .into_iter()
+            .map(|heading| {
+                let message = match heading.problem {
+                    HeadingProblem::TooDeep => format!(
+                        "Heading level {} is deeper than the maximum of {}",
+                        heading.level, self.max_level
+                    ),
+                    HeadingProblem::NotUnderParent(parent) => format!(
+                        "Heading level {} under a level {} heading should be level {}",
+                        heading.level, parent, heading.expected
+                    ),
+                    HeadingProblem::OutlineDepth => format!(
+                        "Heading level {} should be {} to match the outline depth",
+                        heading.level, heading.expected
+                    ),
+                };
+                Diagnostic::new(
+                    self.id(),
+                    heading.block.line,
+                    heading.block.source_column(1),
+                    message,
+                )
+            })
+            .collect()
+    }
+
+    /// Rewrites the `#` of each heading to its expected level
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let misleveled = self.misleveled(outline);
+        rewrite_first_lines(text, outline, |block| {
+            let heading = misleveled
+                .iter()
+                .find(|heading| heading.block.line == block.line)?;
+            let rest = &block.first_line()[heading.level..];
+            Some(format!("{}{}", "#".repeat(heading.expected), rest))
+        })
+    }
+
+    /// `max-level = 3`, and `match-depth = true` to give headings the level of their depth
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["max-level", "match-depth"])? {
+            if key == "max-level" {
+                self.max_level = match value.as_integer() {
+                    Some(level @ 1..=6) => level as usize,
+                    _ => {
+                        return Err(Error::parse(
+                            None,
+                            "rules.headings.max-level must be a number from 1 to 6",
+                        ))
+                    }
+                };
+            } else {
+                self.match_depth = value.as_bool().ok_or_else(|| {
+                    Error::parse(None, "rules.headings.match-depth must be a boolean")
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A first block that is only a heading repeating the title of the page, like `- # Project`
+/// on the page Project, as imports from other tools leave them: Logseq shows the title
+/// already. The heading is removed and its children take its place, one level up. The title is
+/// the `title::` property, or the name of the file; pages are only checked when their name is
+/// known, see [Rule::check_named].
+pub struct TitleHeading;
+
+impl TitleHeading {
+    /// The first block of a page when it is a heading repeating its title
+    fn find<'a>(outline: &'a Outline, name: &str) -> Option<&'a Block> {
+        let properties = outline.page_properties();
+        let title = properties
+            .get("title")
+            .map(|title| title.raw.as_str())
+            .filter(|title| !title.is_empty())
+            .unwrap_or(name);
+        // Page properties written in the first block come before the heading
+        let in_first_block =
+            outline.preamble.iter().all(|line| line.trim().is_empty()) && !properties.is_empty();
+        let block = outline.blocks.get(usize::from(in_first_block))?;
+        let level = block.heading_level()?;
+        let text = block.first_line()[level..].trim();
+        let only_heading = block.content.trim_end().lines().count() == 1;
+        (only_heading && page_key(text) == page_key(title.trim())).then_some(block)
+    }
+}
+
+impl Rule for TitleHeading {
+    fn id(&self) -> &'static str {
+        "title-heading"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "A first block that is only a heading repeating the title of the page, which Logseq shows already.",
+            examples: &["- # Example\n	- Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, _outline: &Outline) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    fn check_named(&self, outline: &Outline, name: &str) -> Vec<Diagnostic> {
+        Self::find(outline, name)
+            .map(|block| {
+                Diagnostic::new(
+                    self.id(),
+                    block.line,
+                    block.source_column(1),
+                    "Heading repeats the page title",
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Removes the heading, and the indentation of one level from the lines of its children
+    fn fix_named(&self, text: &str, outline: &Outline, name: &str) -> Option<String> {
+        let block = Self::find(outline, name)?;
+        let lines: Vec<&str> = text.split('\n').collect();
+        let end = outline
+            .blocks
+            .iter()
+            .find(|other| other.line > block.line)
+            .map_or(lines.len(), |next| next.line - 1);
+        let unit = block
+            .children
+            .first()
+            .and_then(|child| child.indent.strip_prefix(block.indent.as_str()))
+            .unwrap_or_default();
+        let nested = format!("{}{}", block.indent, unit);
+        let mut fixed: Vec<String> = lines[..block.line - 1]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        for line in &lines[block.line..end] {
+            fixed.push(match line.strip_prefix(nested.as_str()) {
+                Some(rest) => format!("{}{}", block.indent, rest),
+                None => line.to_string(),
+            });
+        }
+        fixed.extend(lines[end..].iter().map(|line| line.to_string()));
+        Some(fixed.join("\n"))
+    }
+}
+
+/// The property Logseq gives the blocks of a numbered list
+const ORDER_LIST_PROPERTY: &str = "logseq.order-list-type:: number";
+
+/// The number and text of a Markdown numbered item, like `1. text`
+fn numbered_item(text: &str) -> Option<(u32, &str)> {
+    let digits = text.chars().take_while(char::is_ascii_digit).count();
+    let rest = text[digits..].strip_prefix(". ")?;
+    Some((text[..digits].parse().ok()?, rest.trim_start()))
+}
+
+/// Lines that replace a line of the source, and why
+struct ListEdit {
+    line: usize,
+    column: usize,
+    message: &'static str,
+    lines: Vec<String>,
+}
+
+/// Bullets written with `*` or `+` instead of `-`, and Markdown numbered items (`1. text`)
+/// instead of Logseq's numbered lists, where each item is a block with the
+/// `logseq.order-list-type:: number` property. Numbered items are converted when they are
+/// sibling blocks, or lines at the end of a block (which become its first children); Logseq
+/// numbers lists from 1, so lists that start at another number are left alone.
+pub struct ListMarkers;
+
+impl ListMarkers {
+    /// The indentation of the children of a block
+    fn child_indent(block: &Block) -> String {
+        if let Some(child) = block.children.first() {
+            return child.indent.clone();
+        }
+        match IndentUnit::detect(&block.indent) {
+            Some(IndentUnit::Spaces(count)) if block.level > 0 => {
+                format!("{}{}", block.indent, " ".repeat(count / block.level))
+            }
+            _ => format!("{}\t", block.indent),
+        }
+    }
+
+    /// The lines at the end of a block that are numbered items (numbered from 1) or blank:
+    /// (line number, text)
+    fn numbered_tail(block: &Block) -> Vec<(usize, &str)> {
+        let lines: Vec<(usize, &str)> = block.numbered_lines().skip(1).collect();
+        let is_item = |text: &str| text.trim().is_empty() || numbered_item(text.trim()).is_some();
+        let start = lines
+            .iter()
+            .rposition(|(_, text)| !is_item(text))
+            .map_or(0, |last| last + 1);
+        let fences = lines[..start]
+            .iter()
+            .filter(|(_, text)| text.trim_start().starts_with("```"))
+            .count();
+        let tail = &lines[start..];
+        // Items are numbered in order, or all `1.` like Markdown allows
+        let in_order = tail
+            .iter()
+            .filter_map(|(_, text)| numbered_item(text.trim()))
+            .enumerate()
+            .all(|(index, (number, _))| number == 1 || number as usize == index + 1);
+        let has_items = tail.iter().any(|(_, text)| !text.trim().is_empty());
+        if has_items && in_order && fences % 2 == 0 {
+            tail.to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn collect(blocks: &[Block], edits: &mut Vec<ListEdit>) {
+        let mut numbered = vec![false; blocks.len()];
+        let mut index = 0;
+        while index < blocks.len() {
+            let starts_list =
+                numbered_item(blocks[index].first_line()).is_some_and(|(number, _)| number == 1);
+            if !starts_list {
+                index += 1;
+                continue;
+            }
+            // Items are numbered in order, or all `1.` like Markdown allows
+            let mut position = 1;
+            while index < blocks.len()
+                && numbered_item(blocks[index].first_line())
+                    .is_some_and(|(number, _)| number == position || number == 1)
+                && blocks[index]
+                    .properties()
+                    .get("logseq.order-list-type")
+                    .is_none()
+            {
+                numbered[index] = true;
+                index += 1;
+                position += 1;
+            }
+            index += 1;
+        }
+
+        for (block, numbered) in blocks.iter().zip(numbered) {
+            let bullet = |content: &str| format!("{}- {}", block.indent, content);
+            let property = format!("{}  {}", block.indent, ORDER_LIST_PROPERTY);
+            if let Some((_, text)) = numbered_item(block.first_line()).filter(|_| numbered) {
+                edits.push(ListEdit {
+                    line: block.line,
+                    column: block.source_column(1),
+                    message: "Numbered item should be a block of a numbered list",
+                    lines: vec![bullet(text), property],
+                });
+            } else if block.marker != '-' {
+                edits.push(ListEdit {
+                    line: block.line,
+                    column: block.indent.chars().count() + 1,
+                    message: "Bullet should be -",
+                    lines: vec![bullet(block.first_line())],
+                });
+            }
+            let indent = ListMarkers::child_indent(block);
+            let tail = ListMarkers::numbered_tail(block);
+            for (line, text) in tail.into_iter().filter(|(_, text)| !text.trim().is_empty()) {
+                let item = numbered_item(text.trim()).map_or("", |(_, item)| item);
+                edits.push(ListEdit {
+                    line,
+                    column: block.source_column(text.len() - text.trim_start().len() + 1),
+                    message: "Numbered line should be a child block of a numbered list",
+                    lines: vec![
+                        format!("{}- {}", indent, item),
+                        format!("{}  {}", indent, ORDER_LIST_PROPERTY),
+                    ],
+                });
+            }
+            ListMarkers::collect(&block.children, edits);
+        }
+    }
+
+    fn edits(outline: &Outline) -> Vec<ListEdit> {
+        let mut edits = Vec::new();
+        ListMarkers::collect(&outline.blocks, &mut edits);
+        edits
+    }
+}
+
+impl Rule for ListMarkers {
+    fn id(&self) -> &'static str {
+        "list-markers"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Bullets written with `*` or `+`, and Markdown numbered items instead of Logseq's numbered lists.",
+            examples: &["* Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        ListMarkers::edits(outline)
+            .into_iter()
+            .map(|edit| Diagnostic::new(self.id(), edit.line, edit.column, edit.message))
+            .collect()
+    }
+
+    /// Rewrites the bullets as `-`, and numbered items as blocks of numbered lists; blank lines
+    /// around the numbered lines at the end of a block are removed
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let edits = ListMarkers::edits(outline);
+        if edits.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<Vec<String>> = text
+            .split('\n')
+            .map(|line| vec![line.to_string()])
+            .collect();
+        for edit in edits {
+            lines[edit.line - 1] = edit.lines;
+        }
+        for block in outline.iter() {
+            for (line, text) in ListMarkers::numbered_tail(block) {
+                if text.trim().is_empty() {
+                    lines[line - 1].clear();
+                }
+            }
+        }
+        Some(lines.concat().join("\n"))
+    }
+}
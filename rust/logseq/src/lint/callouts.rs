@@ -0,0 +1,138 @@
+//! Rules about blocks pasted from other apps
+
+use crate::interop::obsidian::{admonition, CALLOUT_PATTERN};
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::refs::lines_outside_code;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static CALLOUT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(CALLOUT_PATTERN).unwrap());
+
+/// An Obsidian callout inside a block, and the admonition that replaces it
+struct Callout {
+    /// Line of the `> [!type]` line
+    line: usize,
+    /// Column of its `>`
+    column: usize,
+    /// Number of lines of the callout, its first line included
+    line_count: usize,
+    /// Admonition type, e.g. `NOTE`
+    kind: &'static str,
+    /// The lines of the admonition, without the indentation of the block
+    lines: Vec<String>,
+}
+
+/// The callouts of a block: a `> [!type] title` line and the `>` lines right after it
+fn callouts(block: &Block) -> Vec<Callout> {
+    let lines: Vec<(usize, &str)> = lines_outside_code(block.numbered_lines()).collect();
+    let mut callouts = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (line, text) = lines[index];
+        let Some(captures) = CALLOUT_RE.captures(text.trim_start()) else {
+            index += 1;
+            continue;
+        };
+        let kind = admonition(&captures[1]);
+        let mut admonition_lines = vec![format!("#+BEGIN_{}", kind)];
+        let title = captures[2].trim();
+        if !title.is_empty() {
+            admonition_lines.push(format!("**{}**", title));
+        }
+        let mut end = index + 1;
+        while let Some((next_line, next_text)) = lines.get(end) {
+            let Some(body) = next_text.trim_start().strip_prefix('>') else {
+                break;
+            };
+            if *next_line != lines[end - 1].0 + 1 {
+                break;
+            }
+            admonition_lines.push(body.strip_prefix(' ').unwrap_or(body).to_string());
+            end += 1;
+        }
+        admonition_lines.push(format!("#+END_{}", kind));
+        callouts.push(Callout {
+            line,
+            column: block.source_column(text.len() - text.trim_start().len() + 1),
+            line_count: end - index,
+            kind,
+            lines: admonition_lines,
+        });
+        index = end;
+    }
+    callouts
+}
+
+/// Obsidian callouts (`> [!note] Title` followed by `>` lines), which Logseq shows as quotes:
+/// they become admonitions like `#+BEGIN_NOTE`, the title in bold, and the `>` of the body
+/// removed. Callout types without an admonition become the closest one, see
+/// [note_to_page](crate::interop::obsidian::note_to_page).
+pub struct Callouts;
+
+impl Rule for Callouts {
+    fn id(&self) -> &'static str {
+        "callouts"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description:
+                "Obsidian callouts, which Logseq shows as quotes: they become admonitions.",
+            examples: &["- > [!note] Remember\n  > Buy milk\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .flat_map(callouts)
+            .map(|callout| {
+                Diagnostic::new(
+                    self.id(),
+                    callout.line,
+                    callout.column,
+                    format!(
+                        "Obsidian callout should be a #+BEGIN_{} admonition",
+                        callout.kind
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut lines: Vec<Vec<String>> = text
+            .split('\n')
+            .map(|line| vec![line.to_string()])
+            .collect();
+        let mut changed = false;
+        for block in outline.iter() {
+            let continuation = format!("{}  ", block.indent);
+            for callout in callouts(block) {
+                // The first line keeps its bullet (or the indentation of a continuation line)
+                let first_prefix = if callout.line == block.line {
+                    format!("{}{} ", block.indent, block.marker)
+                } else {
+                    let source = &lines[callout.line - 1][0];
+                    source[..source.len() - source.trim_start().len()].to_string()
+                };
+                let mut rewritten = Vec::new();
+                for (index, line) in callout.lines.iter().enumerate() {
+                    rewritten.push(match (index, line.is_empty()) {
+                        (0, _) => format!("{}{}", first_prefix, line),
+                        (_, true) => String::new(),
+                        (_, false) => format!("{}{}", continuation, line),
+                    });
+                }
+                lines[callout.line - 1] = rewritten;
+                for line in callout.line + 1..callout.line + callout.line_count {
+                    lines[line - 1].clear();
+                }
+                changed = true;
+            }
+        }
+        changed.then(|| lines.concat().join("\n"))
+    }
+}
@@ -0,0 +1,161 @@
+//! Rules about citations: `[[@citekey]]` links to the pages Logseq's Zotero integration
+//! creates for the items of a library, and the `zotero-*` properties of these pages
+
+use crate::graph::Graph;
+use crate::lint::config::{rule_options, strings};
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::refs::{lines_outside_code, page_links};
+use crate::toml::Toml;
+use crate::Result;
+use std::collections::HashSet;
+
+/// The page properties a citation page needs by default
+pub const CITATION_PROPERTIES: &[&str] = &["title", "item-type"];
+
+/// The actions of the `zotero://` links Zotero understands
+const ZOTERO_ACTIONS: &[&str] = &["select", "open-pdf"];
+
+/// True if the page properties are those of a citation page: an `item-type::` or a
+/// `zotero-*` property
+fn is_citation_page(outline: &Outline) -> bool {
+    outline.page_properties().iter().any(|property| {
+        let key = property.key.to_lowercase();
+        key == "item-type" || key.starts_with("zotero-")
+    })
+}
+
+/// Citations and the pages of the items they cite:
+/// - citation pages, with an `item-type::` or `zotero-*` page property, without one of the
+///   `required` page properties;
+/// - `zotero-*` properties without a value, or with a `zotero://` link to something else than
+///   selecting an item or opening a PDF;
+/// - with the pages of the graph (see [Rule::use_graph]), `[[@citekey]]` links to cite keys
+///   without a page, usually items that were never imported or whose cite key changed.
+///
+/// These are only reported.
+pub struct Citations {
+    /// Keys of the page properties citation pages need
+    pub required: Vec<String>,
+    /// Lowercase names and aliases of the pages of the graph, see [Graph::page_names]
+    pub pages: Option<HashSet<String>>,
+}
+
+impl Default for Citations {
+    fn default() -> Self {
+        Citations {
+            required: CITATION_PROPERTIES
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+            pages: None,
+        }
+    }
+}
+
+impl Citations {
+    /// The problems of the page properties of a citation page, as (line, message)
+    fn page_problems(&self, outline: &Outline) -> Vec<(usize, String)> {
+        let properties = outline.page_properties();
+        let mut problems = Vec::new();
+        if is_citation_page(outline) {
+            let line = properties.iter().next().map_or(1, |property| property.line);
+            for key in &self.required {
+                if properties
+                    .get(key)
+                    .is_none_or(|property| property.raw.trim().is_empty())
+                {
+                    problems.push((line, format!("Citation page without a {}:: property", key)));
+                }
+            }
+        }
+        for property in properties.iter() {
+            if !property.key.to_lowercase().starts_with("zotero-") {
+                continue;
+            }
+            let value = property.raw.trim();
+            if value.is_empty() {
+                problems.push((
+                    property.line,
+                    format!("Property {}:: is empty", property.key),
+                ));
+                continue;
+            }
+            for (start, _) in value.match_indices("zotero://") {
+                let action = value[start + "zotero://".len()..]
+                    .split(['/', ')', ' '])
+                    .next()
+                    .unwrap_or_default();
+                if !ZOTERO_ACTIONS.contains(&action) {
+                    problems.push((
+                        property.line,
+                        format!(
+                            "Property {}:: has a zotero:// link that doesn't select an item or open a PDF",
+                            property.key
+                        ),
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for Citations {
+    fn id(&self) -> &'static str {
+        "citations"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Citation pages without their properties, and `zotero-*` properties without a value or with an unknown link.",
+            examples: &["item-type:: book\n\n- Notes\n"],
+            fixable: false,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .page_problems(outline)
+            .into_iter()
+            .map(|(line, message)| Diagnostic::new(self.id(), line, 1, message))
+            .collect();
+        let Some(pages) = &self.pages else {
+            return diagnostics;
+        };
+        let preamble = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (index + 1, 0, text.as_str()));
+        let blocks = outline.iter().flat_map(|block| {
+            lines_outside_code(block.numbered_lines())
+                .map(move |(line, text)| (line, block.source_column(1) - 1, text))
+        });
+        for (line, offset, text) in preamble.chain(blocks) {
+            for link in page_links(text) {
+                if link.name.starts_with('@') && !pages.contains(&link.name.to_lowercase()) {
+                    diagnostics.push(Diagnostic::new(
+                        self.id(),
+                        line,
+                        offset + link.column,
+                        format!("Citation [[{}]] without a page", link.name),
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// `required = ["title", "item-type"]`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["required"])? {
+            self.required = strings(value, "rules.citations.required")?;
+        }
+        Ok(())
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        self.pages = Some(graph.page_names());
+    }
+}
@@ -0,0 +1,346 @@
+//! The `.lsd.toml` file at the root of a graph: which rules run, their options and ignored paths
+
+use crate::lint::{RuleSet, Severity};
+use crate::schema::Schema;
+use crate::toml::Toml;
+use crate::{error, Error, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+/// Name of the config file, looked up in the graph root and its parents
+pub const CONFIG_FILE_NAME: &str = ".lsd.toml";
+
+/// The lint settings of a graph.
+///
+/// ```toml
+/// [lint]
+/// # Only run these rules (all built-in rules by default)
+/// enable = ["consecutive-spaces", "task-markers"]
+/// disable = ["timestamps"]
+/// # Globs relative to the directory of the config file; without a `/`, they match any file or
+/// # directory name
+/// ignore = ["pages/archive/**", "*.excalidraw.md"]
+/// # Only check the files these globs match (all files by default)
+/// include = ["pages/**", "journals/2024_*"]
+/// # Rules loaded from WebAssembly modules, relative to the directory of the config file; they
+/// # need lsd built with the `plugins` feature
+/// plugins = ["plugins/no-fixme.wasm"]
+///
+/// [rules.task-markers]
+/// workflow = "todo"
+/// # error, warning (the default) or info, see `lsd lint --fail-on`
+/// severity = "error"
+///
+/// # Types of property values, checked by the property-schema rule
+/// [schema]
+/// rating = "integer"
+/// status = "enum[reading, done]"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    /// The directory that ignored paths are relative to
+    pub root: PathBuf,
+    /// Ids of the only rules to run, or None for all built-in rules
+    pub enable: Option<Vec<String>>,
+    /// Ids of rules not to run, even if enabled
+    pub disable: Vec<String>,
+    /// Globs of the files not to check
+    pub ignore: Vec<String>,
+    /// Globs of the only files to check, or empty to check all files
+    pub include: Vec<String>,
+    /// Paths of the WebAssembly modules of the plugin rules, relative to the root
+    pub plugins: Vec<String>,
+    /// Options of the rules, as `(rule id, table)`, with their `severity`
+    pub rules: Vec<(String, Toml)>,
+    /// The `[schema]` table, see [Schema]
+    pub schema: Option<Toml>,
+}
+
+/// The entries of the options table of a rule, failing on keys the rule doesn't know
+pub(crate) fn rule_options<'a>(
+    rule: &str,
+    options: &'a Toml,
+    known: &[&str],
+) -> Result<&'a [(String, Toml)]> {
+    let entries = options
+        .as_table()
+        .ok_or_else(|| Error::parse(None, format!("rules.{} must be a table", rule)))?;
+    match entries
+        .iter()
+        .find(|(key, _)| !known.contains(&key.as_str()))
+    {
+        Some((key, _)) => Err(Error::parse(
+            None,
+            format!("unknown option {} of the rule {}", key, rule),
+        )),
+        None => Ok(entries),
+    }
+}
+
+/// The strings of an array, or an error naming the setting
+pub(crate) fn strings(value: &Toml, name: &str) -> Result<Vec<String>> {
+    value
+        .as_array()
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| Error::parse(None, format!("{} must be an array of strings", name)))
+}
+
+/// Splits the `severity` of a rule off the other options of its table
+fn split_severity(rule: &str, options: &Toml) -> Result<(Option<Severity>, Toml)> {
+    let Some(entries) = options.as_table() else {
+        return Ok((None, options.clone()));
+    };
+    let mut severity = None;
+    let mut rest = Vec::new();
+    for (key, value) in entries {
+        if key != "severity" {
+            rest.push((key.clone(), value.clone()));
+            continue;
+        }
+        let name = value.as_str().ok_or_else(|| {
+            Error::parse(None, format!("rules.{}.severity must be a string", rule))
+        })?;
+        severity = Some(name.parse()?);
+    }
+    Ok((severity, Toml::Table(rest)))
+}
+
+impl LintConfig {
+    /// The default settings: all built-in rules, nothing ignored
+    pub fn new(root: &Path) -> Self {
+        LintConfig {
+            root: root.to_path_buf(),
+            enable: None,
+            disable: Vec::new(),
+            ignore: Vec::new(),
+            include: Vec::new(),
+            plugins: Vec::new(),
+            rules: Vec::new(),
+            schema: None,
+        }
+    }
+
+    /// Parses the contents of a config file; ignored paths are relative to `root`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::LintConfig;
+    /// use std::path::Path;
+    /// let config = LintConfig::parse("[lint]\ndisable = [\"timestamps\"]\n", Path::new("graph")).unwrap();
+    /// assert_eq!(config.disable, vec!["timestamps"]);
+    /// assert!(!config.rule_set().unwrap().ids().contains(&"timestamps"));
+    /// assert!(LintConfig::parse("[lint]\ndisabled = []", Path::new("graph")).is_err());
+    /// ```
+    pub fn parse(config_contents: &str, root: &Path) -> Result<Self> {
+        let document = Toml::parse(config_contents)?;
+        let mut config = LintConfig::new(root);
+        if let Some(lint) = document.get("lint") {
+            let entries = lint
+                .as_table()
+                .ok_or_else(|| Error::parse(None, "lint must be a table"))?;
+            for (key, value) in entries {
+                match key.as_str() {
+                    "enable" => config.enable = Some(strings(value, "lint.enable")?),
+                    "disable" => config.disable = strings(value, "lint.disable")?,
+                    "ignore" => config.ignore = strings(value, "lint.ignore")?,
+                    "include" => config.include = strings(value, "lint.include")?,
+                    "plugins" => config.plugins = strings(value, "lint.plugins")?,
+                    _ => return Err(Error::parse(None, format!("unknown setting lint.{}", key))),
+                }
+            }
+        }
+        if let Some(rules) = document.get("rules") {
+            let entries = rules
+                .as_table()
+                .ok_or_else(|| Error::parse(None, "rules must be a table"))?;
+            config.rules = entries.to_vec();
+        }
+        if let Some(schema) = document.get("schema") {
+            Schema::from_toml(schema)?;
+            config.schema = Some(schema.clone());
+        }
+        glob_set(&config.ignore, "ignore")?;
+        glob_set(&config.include, "include")?;
+        Ok(config)
+    }
+
+    /// Reads a config file; ignored paths are relative to its directory
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = error::read_to_string(path)?;
+        let root = path.parent().unwrap_or(Path::new(""));
+        LintConfig::parse(&contents, root).map_err(|error| error.with_path(path))
+    }
+
+    /// Finds the config file of a file or directory, in it or its nearest parent with one
+    pub fn discover(start: &Path) -> Result<Option<Self>> {
+        let start = if start.is_file() {
+            start.parent().unwrap_or(Path::new(""))
+        } else {
+            start
+        };
+        let start = std::path::absolute(start).unwrap_or_else(|_| start.to_path_buf());
+        match start
+            .ancestors()
+            .map(|directory| directory.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+        {
+            Some(path) => LintConfig::read(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The built-in rules and those of the plugins with their options, keeping only the enabled
+    /// ones. Fails on unknown rule ids, so that typos don't go unnoticed.
+    pub fn rule_set(&self) -> Result<RuleSet> {
+        let mut rule_set = RuleSet::builtin();
+        self.add_plugins(&mut rule_set)?;
+        let known = rule_set.ids();
+        let mentioned = self
+            .enable
+            .iter()
+            .flatten()
+            .chain(&self.disable)
+            .chain(self.rules.iter().map(|(id, _)| id));
+        for id in mentioned {
+            if !known.contains(&id.as_str()) {
+                return Err(Error::parse(None, format!("unknown rule {}", id)));
+            }
+        }
+        for (id, options) in &self.rules {
+            let (severity, options) = split_severity(id, options)?;
+            rule_set.configure(id, &options)?;
+            if let Some(severity) = severity {
+                rule_set.set_severity(id, severity)?;
+            }
+        }
+        if let Some(schema) = &self.schema {
+            rule_set.configure("property-schema", schema)?;
+        }
+        if let Some(enable) = &self.enable {
+            let ids: Vec<&str> = enable.iter().map(String::as_str).collect();
+            rule_set = rule_set.select(&ids);
+        }
+        let disable: Vec<&str> = self.disable.iter().map(String::as_str).collect();
+        Ok(rule_set.without(&disable))
+    }
+
+    /// Loads the plugins into a rule set, failing on plugins with the id of a built-in rule
+    #[cfg(feature = "plugins")]
+    fn add_plugins(&self, rule_set: &mut RuleSet) -> Result<()> {
+        use crate::lint::Rule;
+        for path in &self.plugins {
+            let path = self.root.join(path);
+            let plugin = crate::plugins::WasmRule::load(&path)?;
+            if rule_set.get(plugin.id()).is_some() {
+                return Err(Error::parse(
+                    Some(&path),
+                    format!("the plugin has the id of the rule {}", plugin.id()),
+                ));
+            }
+            rule_set.add(plugin);
+        }
+        Ok(())
+    }
+
+    /// Fails if there are plugins, which need the `plugins` feature
+    #[cfg(not(feature = "plugins"))]
+    fn add_plugins(&self, _rule_set: &mut RuleSet) -> Result<()> {
+        match self.plugins.first() {
+            Some(path) => Err(Error::parse(
+                None,
+                format!(
+                    "the plugin {} needs lsd built with the plugins feature",
+                    path
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds globs of the only files to check and of files not to check, like the `include`
+    /// and `ignore` settings
+    pub fn add_globs(&mut self, include: &[String], ignore: &[String]) -> Result<()> {
+        self.include.extend_from_slice(include);
+        self.ignore.extend_from_slice(ignore);
+        glob_set(&self.include, "include")?;
+        glob_set(&self.ignore, "ignore")?;
+        Ok(())
+    }
+
+    /// True if the file is ignored: an `ignore` glob matches its path relative to the root, or
+    /// one of its parent directories, or (for globs without `/`) one of their names; or there
+    /// are `include` globs and none matches it that way
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::LintConfig;
+    /// use std::path::Path;
+    /// let mut config = LintConfig::new(Path::new("graph"));
+    /// config.ignore = vec!["pages/archive".to_string(), "*.excalidraw.md".to_string()];
+    /// assert!(config.is_ignored(Path::new("graph/pages/archive/old.md")));
+    /// assert!(config.is_ignored(Path::new("graph/pages/drawing.excalidraw.md")));
+    /// assert!(!config.is_ignored(Path::new("graph/pages/archived.md")));
+    /// config.include = vec!["journals".to_string()];
+    /// assert!(config.is_ignored(Path::new("graph/pages/archived.md")));
+    /// assert!(!config.is_ignored(Path::new("graph/journals/2024_01_01.md")));
+    /// ```
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.remove_ignored(vec![path.to_path_buf()]).is_empty()
+    }
+
+    /// The files that are not ignored, see [LintConfig::is_ignored]
+    pub fn remove_ignored(&self, paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        if self.ignore.is_empty() && self.include.is_empty() {
+            return paths;
+        }
+        let (Ok(ignore), Ok(include)) = (
+            glob_set(&self.ignore, "ignore"),
+            glob_set(&self.include, "include"),
+        ) else {
+            return paths;
+        };
+        let root = std::path::absolute(&self.root).unwrap_or_else(|_| self.root.clone());
+        let matches = |globs: &GlobSet, path: &Path| {
+            let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+            let relative = absolute
+                .strip_prefix(&root)
+                .or_else(|_| path.strip_prefix(&self.root))
+                .unwrap_or(path);
+            relative.ancestors().any(|ancestor| {
+                // A name has no `/`, so only globs without one can match it
+                globs.is_match(ancestor)
+                    || ancestor
+                        .file_name()
+                        .is_some_and(|name| globs.is_match(name))
+            })
+        };
+        paths
+            .into_iter()
+            .filter(|path| {
+                !matches(&ignore, path) && (self.include.is_empty() || matches(&include, path))
+            })
+            .collect()
+    }
+}
+
+/// The glob set of the globs of a setting, see [LintConfig::is_ignored]
+fn glob_set(patterns: &[String], setting: &str) -> Result<GlobSet> {
+    let invalid =
+        |error: globset::Error| Error::parse(None, format!("invalid {} glob: {}", setting, error));
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_end_matches('/'))
+            .literal_separator(true)
+            .build()
+            .map_err(invalid)?;
+        builder.add(glob);
+    }
+    builder.build().map_err(invalid)
+}
@@ -0,0 +1,375 @@
+//! Rules about drawers: `:LOGBOOK:`, where Logseq records the time spent on tasks, and the
+//! org-mode `:PROPERTIES:` left over in Markdown files
+
+use crate::lint::config::rule_options;
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::{Block, Outline, RegionKind};
+use crate::properties::split_property;
+use crate::refs::{lines_outside_code, lines_outside_regions};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::ops::RangeInclusive;
+use std::sync::LazyLock;
+
+static DRAWER_PROPERTY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*:([A-Za-z0-9_\-.]+):\s*(.*)$").unwrap());
+
+/// What to do with the `:LOGBOOK:` drawers of blocks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogbookMode {
+    /// Keep the drawers as they are
+    #[default]
+    Keep,
+    /// Remove repeated `CLOCK:` entries and entries that lasted no time
+    Compact,
+    /// Remove the drawers entirely
+    Strip,
+}
+
+/// A `:LOGBOOK:` drawer of a block, with line numbers of the source
+struct Drawer {
+    /// Line of `:LOGBOOK:`
+    start: usize,
+    /// Lines inside the drawer, with their text
+    body: Vec<(usize, String)>,
+    /// Line of `:END:`, or None if it's missing
+    end: Option<usize>,
+}
+
+impl Drawer {
+    /// The last line of the drawer, `:END:` included
+    fn last_line(&self) -> usize {
+        self.end
+            .or_else(|| self.body.last().map(|(line, _)| *line))
+            .unwrap_or(self.start)
+    }
+}
+
+/// Finds the drawers of a block. A drawer without `:END:` ends after its `CLOCK:` lines.
+fn drawers(block: &Block) -> Vec<Drawer> {
+    let skipped = [
+        RegionKind::CodeFence,
+        RegionKind::SourceBlock,
+        RegionKind::Ignored,
+    ];
+    let lines: Vec<(usize, &str)> =
+        lines_outside_regions(block.numbered_lines(), &skipped).collect();
+    let mut drawers = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (start, text) = lines[index];
+        index += 1;
+        if !text.trim().eq_ignore_ascii_case(":LOGBOOK:") {
+            continue;
+        }
+        let end = lines[index..]
+            .iter()
+            .position(|(_, text)| text.trim().eq_ignore_ascii_case(":END:"));
+        let body_length = match end {
+            Some(length) => length,
+            None => lines[index..]
+                .iter()
+                .take_while(|(_, text)| text.trim_start().starts_with("CLOCK:"))
+                .count(),
+        };
+        let body = lines[index..index + body_length]
+            .iter()
+            .map(|(line, text)| (*line, text.trim().to_string()))
+            .collect();
+        drawers.push(Drawer {
+            start,
+            body,
+            end: end.map(|length| lines[index + length].0),
+        });
+        index += body_length + usize::from(end.is_some());
+    }
+    drawers
+}
+
+/// True if a `CLOCK:` entry lasted no time, like `=>  00:00:00`
+fn is_empty_clock(text: &str) -> bool {
+    text.rsplit_once("=>")
+        .is_some_and(|(_, duration)| duration.trim().chars().all(|c| c == '0' || c == ':'))
+}
+
+/// `:LOGBOOK:` drawers without `:END:`, which break the rendering of the block, and optionally
+/// repeated clock entries or the whole drawers
+#[derive(Default)]
+pub struct Logbook {
+    /// What to do with drawers that are well-formed
+    pub mode: LogbookMode,
+}
+
+/// A change to the lines of the source
+enum Edit {
+    /// Remove the line
+    Remove(usize),
+    /// Insert `:END:` after the line, with the indentation of the given line
+    InsertEnd { after: usize, like: usize },
+}
+
+impl Logbook {
+    /// The problems found, with the edit that fixes each one
+    fn problems(&self, outline: &Outline) -> Vec<(Diagnostic, Vec<Edit>)> {
+        let mut problems = Vec::new();
+        for block in outline.iter() {
+            for drawer in drawers(block) {
+                let diagnostic = |line: usize, message: &str| {
+                    Diagnostic::new(self.id(), line, block.source_column(1), message)
+                };
+                if self.mode == LogbookMode::Strip {
+                    let edits = (drawer.start..=drawer.last_line())
+                        .map(Edit::Remove)
+                        .collect();
+                    problems.push((
+                        diagnostic(drawer.start, "LOGBOOK drawer can be removed"),
+                        edits,
+                    ));
+                    continue;
+                }
+                if drawer.end.is_none() {
+                    let edit = Edit::InsertEnd {
+                        after: drawer.last_line(),
+                        like: drawer.start,
+                    };
+                    problems.push((
+                        diagnostic(drawer.start, "LOGBOOK drawer without :END:"),
+                        vec![edit],
+                    ));
+                }
+                if self.mode == LogbookMode::Compact {
+                    let mut seen: Vec<&str> = Vec::new();
+                    for (line, text) in &drawer.body {
+                        let message = if is_empty_clock(text) {
+                            "CLOCK entry that lasted no time"
+                        } else if seen.contains(&text.as_str()) {
+                            "Repeated CLOCK entry"
+                        } else {
+                            seen.push(text);
+                            continue;
+                        };
+                        problems.push((diagnostic(*line, message), vec![Edit::Remove(*line)]));
+                    }
+                }
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for Logbook {
+    fn id(&self) -> &'static str {
+        "logbook"
+    }
+
+    fn editable_regions(&self) -> &'static [RegionKind] {
+        &[RegionKind::Logbook]
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`:LOGBOOK:` drawers without `:END:`, and optionally repeated clock entries or the whole drawers.",
+            examples: &["- DONE Pay rent\n  :LOGBOOK:\n  CLOCK: [2024-01-02 Tue 10:00:00]--[2024-01-02 Tue 10:05:00] =>  00:05:00\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(diagnostic, _)| diagnostic)
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let problems = self.problems(outline);
+        if problems.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = text.split('\n').collect();
+        let mut removed = vec![false; lines.len()];
+        let mut inserted: Vec<Option<String>> = vec![None; lines.len()];
+        for edit in problems.into_iter().flat_map(|(_, edits)| edits) {
+            match edit {
+                Edit::Remove(line) => removed[line - 1] = true,
+                Edit::InsertEnd { after, like } => {
+                    let like = lines[like - 1];
+                    let indent = &like[..like.len() - like.trim_start().len()];
+                    inserted[after - 1] = Some(format!("{}:END:", indent));
+                }
+            }
+        }
+        let mut fixed = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            if !removed[index] {
+                fixed.push(line.to_string());
+            }
+            if let Some(end) = inserted[index].take() {
+                fixed.push(end);
+            }
+        }
+        Some(fixed.join("\n"))
+    }
+
+    /// `mode = "keep"`, `"compact"` or `"strip"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["mode"])? {
+            self.mode = match value.as_str() {
+                Some("keep") => LogbookMode::Keep,
+                Some("compact") => LogbookMode::Compact,
+                Some("strip") => LogbookMode::Strip,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.logbook.mode must be \"keep\", \"compact\" or \"strip\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Org-mode `:PROPERTIES:` drawers, which Logseq ignores in Markdown files, usually left over
+/// from a migration. The fix turns each `:KEY: value` of the drawer into a `key:: value` line in
+/// its place, dropping keys the block or page already has. Drawers without `:END:`, or with
+/// lines that aren't properties, are only reported.
+#[derive(Default)]
+pub struct PropertiesDrawer;
+
+/// The lines of a drawer, from `:PROPERTIES:` to `:END:`, and the properties replacing them
+type Conversion = (RangeInclusive<usize>, Vec<String>);
+
+impl PropertiesDrawer {
+    /// The drawers of some lines, starting at the given column, with the lines to replace and
+    /// the properties to write, or None if the drawer can't be converted
+    fn problems(
+        &self,
+        lines: &[(usize, &str)],
+        column: usize,
+    ) -> Vec<(Diagnostic, Option<Conversion>)> {
+        let mut keys: Vec<String> = lines
+            .iter()
+            .filter_map(|(_, text)| split_property(text.trim()))
+            .map(|(key, _)| key.to_lowercase())
+            .collect();
+        let mut problems = Vec::new();
+        let mut index = 0;
+        while index < lines.len() {
+            let (start, text) = lines[index];
+            index += 1;
+            if !text.trim().eq_ignore_ascii_case(":PROPERTIES:") {
+                continue;
+            }
+            let Some(length) = lines[index..]
+                .iter()
+                .position(|(_, text)| text.trim().eq_ignore_ascii_case(":END:"))
+            else {
+                let message = "PROPERTIES drawer without :END:";
+                problems.push((Diagnostic::new(self.id(), start, column, message), None));
+                continue;
+            };
+            let end = lines[index + length].0;
+            let body = &lines[index..index + length];
+            index += length + 1;
+            let properties: Option<Vec<String>> = body
+                .iter()
+                .map(|(_, text)| DRAWER_PROPERTY_RE.captures(text))
+                .map(|captures| {
+                    let captures = captures?;
+                    Some((captures[1].to_lowercase(), captures[2].trim().to_string()))
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(|properties| {
+                    let mut lines = Vec::new();
+                    for (key, value) in properties {
+                        if !keys.contains(&key) {
+                            lines.push(format!("{}:: {}", key, value).trim_end().into());
+                            keys.push(key);
+                        }
+                    }
+                    lines
+                });
+            let (message, edit) = match properties {
+                Some(properties) => (
+                    "PROPERTIES drawer, Logseq reads key:: value properties",
+                    Some((start..=end, properties)),
+                ),
+                None => ("PROPERTIES drawer with lines that aren't properties", None),
+            };
+            problems.push((Diagnostic::new(self.id(), start, column, message), edit));
+        }
+        problems
+    }
+
+    fn outline_problems(&self, outline: &Outline) -> Vec<(Diagnostic, Option<Conversion>)> {
+        let preamble: Vec<(usize, &str)> = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text.as_str()))
+            .collect();
+        let mut problems = self.problems(&preamble, 1);
+        for block in outline.iter() {
+            let lines: Vec<(usize, &str)> = lines_outside_code(block.numbered_lines()).collect();
+            let column = block.source_column(1);
+            problems.extend(self.problems(&lines[1.min(lines.len())..], column));
+        }
+        problems
+    }
+}
+
+impl Rule for PropertiesDrawer {
+    fn id(&self) -> &'static str {
+        "properties-drawer"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Org-mode `:PROPERTIES:` drawers, which Logseq ignores in Markdown files: they become `key:: value` lines.",
+            examples: &["- Notes\n  :PROPERTIES:\n  :STATUS: done\n  :END:\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.outline_problems(outline)
+            .into_iter()
+            .map(|(diagnostic, _)| diagnostic)
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let conversions: Vec<Conversion> = self
+            .outline_problems(outline)
+            .into_iter()
+            .filter_map(|(_, conversion)| conversion)
+            .collect();
+        if conversions.is_empty() {
+            return None;
+        }
+        let lines: Vec<&str> = text.split('\n').collect();
+        let mut replaced: Vec<Option<Vec<String>>> = vec![None; lines.len()];
+        for (drawer_lines, properties) in conversions {
+            let drawer = lines[drawer_lines.start() - 1];
+            let indent = &drawer[..drawer.len() - drawer.trim_start().len()];
+            let properties = properties
+                .iter()
+                .map(|property| format!("{}{}", indent, property))
+                .collect();
+            replaced[drawer_lines.start() - 1] = Some(properties);
+            for line in drawer_lines.skip(1) {
+                replaced[line - 1] = Some(Vec::new());
+            }
+        }
+        let mut fixed = Vec::new();
+        for (line, replacement) in lines.iter().zip(replaced) {
+            match replacement {
+                Some(replacement) => fixed.extend(replacement),
+                None => fixed.push(line.to_string()),
+            }
+        }
+        Some(fixed.join("\n"))
+    }
+}
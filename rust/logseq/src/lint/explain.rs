@@ -0,0 +1,198 @@
+//! What the rules of a rule set are about, with their examples linted, for `lsd rules`,
+//! `lsd explain` and editors
+
+use crate::lint::{Rule, RuleSet, Severity};
+use crate::outline::parse_outline;
+use crate::{Error, Result};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// The name of the page of the examples of the rules, see [RuleDoc::examples](super::RuleDoc)
+pub const EXAMPLE_PAGE: &str = "Example";
+
+/// A problem a rule finds in one of its examples
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExampleProblem {
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column, starting at 1
+    pub column: usize,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+/// An example of a rule, linted and fixed by it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleExample {
+    /// The page
+    pub page: String,
+    /// What the rule finds in the page
+    pub problems: Vec<ExampleProblem>,
+    /// The page fixed by the rule, if it changes anything
+    pub fixed: Option<String>,
+}
+
+/// What a rule is about, see [RuleDoc](super::RuleDoc)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleInfo {
+    /// Id of the rule
+    pub id: String,
+    /// What the rule finds
+    pub description: String,
+    /// The severity of the rule in the rule set
+    pub severity: Severity,
+    /// True if the rule fixes some of the problems it finds
+    pub fixable: bool,
+    /// The examples of the rule, with what it finds and fixes in them
+    pub examples: Vec<RuleExample>,
+}
+
+impl RuleInfo {
+    /// The documentation of a rule, with its examples linted as pages named [EXAMPLE_PAGE]
+    pub fn of(rule: &dyn Rule, severity: Severity) -> Self {
+        let doc = rule.doc();
+        let examples = doc
+            .examples
+            .iter()
+            .map(|page| {
+                let outline = parse_outline(page);
+                let problems = rule
+                    .check_named(&outline, EXAMPLE_PAGE)
+                    .into_iter()
+                    .map(|diagnostic| ExampleProblem {
+                        line: diagnostic.line,
+                        column: diagnostic.column,
+                        message: diagnostic.message,
+                    })
+                    .collect();
+                let fixed = rule
+                    .fix_named(page, &outline, EXAMPLE_PAGE)
+                    .filter(|fixed| fixed != page);
+                RuleExample {
+                    page: page.to_string(),
+                    problems,
+                    fixed,
+                }
+            })
+            .collect();
+        RuleInfo {
+            id: rule.id().to_string(),
+            description: doc.description.to_string(),
+            severity,
+            fixable: doc.fixable,
+            examples,
+        }
+    }
+
+    /// The documentation as text: the rule, its description, then each example with the
+    /// problems found and the page fixed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{RuleInfo, RuleSet, Severity};
+    /// let rules = RuleSet::builtin();
+    /// let info = RuleInfo::of(rules.get("consecutive-spaces").unwrap(), Severity::Warning);
+    /// let text = info.to_text();
+    /// assert!(text.starts_with("consecutive-spaces (warning, fixable)\n\nConsecutive spaces"));
+    /// assert!(text.contains("\n    - Some  text\n\n  1:7: Consecutive spaces\n\nFixed:\n\n    - Some text\n"));
+    /// ```
+    pub fn to_text(&self) -> String {
+        let indented = |page: &str| -> String {
+            page.lines()
+                .map(|line| match line.is_empty() {
+                    true => "\n".to_string(),
+                    false => format!("    {}\n", line),
+                })
+                .collect()
+        };
+        let fixable = if self.fixable { ", fixable" } else { "" };
+        let mut text = format!("{} ({}{})\n", self.id, self.severity, fixable);
+        if !self.description.is_empty() {
+            text.push_str(&format!("\n{}\n", self.description));
+        }
+        for example in &self.examples {
+            text.push_str(&format!("\nExample:\n\n{}", indented(&example.page)));
+            if !example.problems.is_empty() {
+                text.push('\n');
+            }
+            for problem in &example.problems {
+                text.push_str(&format!(
+                    "  {}:{}: {}\n",
+                    problem.line, problem.column, problem.message
+                ));
+            }
+            if let Some(fixed) = &example.fixed {
+                text.push_str(&format!("\nFixed:\n\n{}", indented(fixed)));
+            }
+        }
+        text
+    }
+}
+
+/// How the documentation of rules is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RulesFormat {
+    /// Text, for people
+    #[default]
+    Text,
+    /// A JSON [RuleInfo] object for each rule, in an array when there are several
+    Json,
+}
+
+impl FromStr for RulesFormat {
+    type Err = Error;
+
+    /// Parses `text` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Ok(RulesFormat::Text),
+            "json" => Ok(RulesFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected text or json", name),
+            )),
+        }
+    }
+}
+
+impl RuleSet {
+    /// The documentation of the rules of the set, in their order
+    pub fn infos(&self) -> Vec<RuleInfo> {
+        self.iter()
+            .map(|rule| RuleInfo::of(rule, self.severity(rule.id())))
+            .collect()
+    }
+
+    /// Lists the rules of the set: in text, one line per rule with its id, its description and
+    /// whether it fixes what it finds; in JSON, their [RuleInfo]
+    pub fn render_rules(&self, format: RulesFormat) -> String {
+        let infos = self.infos();
+        match format {
+            RulesFormat::Json => serde_json::to_string(&infos).unwrap_or_default() + "\n",
+            RulesFormat::Text => {
+                let width = infos.iter().map(|info| info.id.len()).max().unwrap_or(0);
+                infos
+                    .iter()
+                    .map(|info| {
+                        let fixable = if info.fixable { " (fixable)" } else { "" };
+                        let line = format!("{:width$}  {}{}", info.id, info.description, fixable);
+                        format!("{}\n", line.trim_end())
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Explains the rule with the given id, see [RuleInfo]; fails if the set has no such rule
+    pub fn explain(&self, id: &str, format: RulesFormat) -> Result<String> {
+        let rule = self
+            .get(id)
+            .ok_or_else(|| Error::parse(None, format!("unknown rule {}", id)))?;
+        let info = RuleInfo::of(rule, self.severity(id));
+        Ok(match format {
+            RulesFormat::Json => serde_json::to_string(&info).unwrap_or_default() + "\n",
+            RulesFormat::Text => info.to_text(),
+        })
+    }
+}
@@ -0,0 +1,228 @@
+//! Rules about Markdown footnotes, `[^1]` and their `[^1]: text` definitions, which Logseq
+//! renders poorly
+
+use crate::ids::new_uuid;
+use crate::lint::config::rule_options;
+use crate::lint::{Diagnostic, IndentUnit, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+static FOOTNOTE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\^([^\]\s]+)\]").unwrap());
+static DEFINITION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[\^([^\]\s]+)\]:\s*(.*)$").unwrap());
+
+/// What footnotes become
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FootnoteStyle {
+    /// A child block of the first block referring to the footnote, with an `id::`, and
+    /// `((block references))` to it instead of the `[^1]`
+    #[default]
+    BlockRef,
+    /// The text of the footnote in parentheses at each `[^1]`
+    Inline,
+}
+
+/// A `[^1]: text` line
+struct Definition {
+    line: usize,
+    column: usize,
+    text: String,
+    /// False if the line can't be removed: the first line of a block with more lines or
+    /// children
+    removable: bool,
+}
+
+/// A `[^1]` in a block
+struct Reference<'a> {
+    label: String,
+    line: usize,
+    column: usize,
+    block: &'a Block,
+}
+
+/// The definitions of an outline, by label, and the references, in the order of the lines
+fn footnotes(outline: &Outline) -> (HashMap<String, Definition>, Vec<Reference<'_>>) {
+    let mut definitions = HashMap::new();
+    let mut references = Vec::new();
+    let mut define = |label: &str, line, column, text: &str, removable| {
+        definitions
+            .entry(label.to_string())
+            .or_insert_with(|| Definition {
+                line,
+                column,
+                text: text.trim().to_string(),
+                removable,
+            });
+    };
+    for (index, text) in outline.preamble.iter().enumerate() {
+        if let Some(captures) = DEFINITION_RE.captures(text.trim()) {
+            let column = text.len() - text.trim_start().len() + 1;
+            define(&captures[1], index + 1, column, &captures[2], true);
+        }
+    }
+    for block in outline.iter() {
+        for (line, text) in lines_outside_code(block.numbered_lines()) {
+            if let Some(captures) = DEFINITION_RE.captures(text.trim()) {
+                let removable =
+                    line != block.line || (block.line_count() == 1 && block.children.is_empty());
+                let column = block.source_column(text.len() - text.trim_start().len() + 1);
+                define(&captures[1], line, column, &captures[2], removable);
+                continue;
+            }
+            let searchable = blank_code_spans(text);
+            for captures in FOOTNOTE_RE.captures_iter(&searchable) {
+                let start = captures.get(0).unwrap().start();
+                references.push(Reference {
+                    label: captures[1].to_string(),
+                    line,
+                    column: block.source_column(text[..start].chars().count() + 1),
+                    block,
+                });
+            }
+        }
+    }
+    (definitions, references)
+}
+
+/// Markdown footnotes, `[^1]` with a `[^1]: text` line, which Logseq shows as they are. The fix
+/// turns each footnote into a child block of the first block referring to it, with
+/// `((block references))` to it, or with `style = "inline"`, puts its text in parentheses in
+/// place of each `[^1]`. Footnotes without a definition, and definitions nothing refers to, are
+/// only reported.
+#[derive(Default)]
+pub struct Footnotes {
+    /// What the footnotes become
+    pub style: FootnoteStyle,
+}
+
+impl Rule for Footnotes {
+    fn id(&self) -> &'static str {
+        "footnotes"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Markdown footnotes, which Logseq shows as they are: they become child blocks or inline text.",
+            examples: &["- Dune[^1] is a novel\n- [^1]: By Frank Herbert\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let (definitions, references) = footnotes(outline);
+        let mut diagnostics: Vec<Diagnostic> = references
+            .iter()
+            .map(|reference| {
+                let message = match definitions.contains_key(&reference.label) {
+                    true => format!(
+                        "Footnote [^{}], Logseq doesn't render footnotes",
+                        reference.label
+                    ),
+                    false => format!("Footnote [^{}] without a definition", reference.label),
+                };
+                Diagnostic::new(self.id(), reference.line, reference.column, message)
+            })
+            .collect();
+        for (label, definition) in &definitions {
+            if !references.iter().any(|reference| reference.label == *label) {
+                let message = format!("Footnote definition [^{}] that nothing refers to", label);
+                diagnostics.push(Diagnostic::new(
+                    self.id(),
+                    definition.line,
+                    definition.column,
+                    message,
+                ));
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let (definitions, references) = footnotes(outline);
+        let fixable = |label: &str| {
+            definitions
+                .get(label)
+                .is_some_and(|definition| definition.removable)
+        };
+        if !references.iter().any(|reference| fixable(&reference.label)) {
+            return None;
+        }
+        let unit = outline
+            .iter()
+            .find(|block| block.level == 1)
+            .and_then(|block| IndentUnit::detect(&block.indent))
+            .unwrap_or(IndentUnit::Tab);
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut removed = vec![false; lines.len()];
+        let mut inserted: Vec<Vec<String>> = vec![Vec::new(); lines.len()];
+        let mut uuids: HashMap<&str, String> = HashMap::new();
+        for reference in &references {
+            let label = reference.label.as_str();
+            if !fixable(label) {
+                continue;
+            }
+            let definition = &definitions[label];
+            let replacement = match self.style {
+                FootnoteStyle::Inline => format!("({})", definition.text),
+                FootnoteStyle::BlockRef => {
+                    let uuid = uuids.entry(label).or_insert_with(|| {
+                        let block = reference.block;
+                        let indent = unit.indent(block.level + 1);
+                        let last = block.line + block.line_count() - 1;
+                        inserted[last - 1].push(format!("{}- {}", indent, definition.text));
+                        let uuid = new_uuid();
+                        inserted[last - 1].push(format!("{}  id:: {}", indent, uuid));
+                        uuid
+                    });
+                    format!("(({}))", uuid)
+                }
+            };
+            let line = &mut lines[reference.line - 1];
+            let searchable = blank_code_spans(line);
+            let Some(found) = FOOTNOTE_RE
+                .find_iter(&searchable)
+                .find(|found| found.as_str() == format!("[^{}]", label))
+            else {
+                continue;
+            };
+            let after_space = line[..found.start()]
+                .chars()
+                .last()
+                .is_none_or(char::is_whitespace);
+            let space = if after_space { "" } else { " " };
+            line.replace_range(found.range(), &format!("{}{}", space, replacement));
+            removed[definition.line - 1] = true;
+        }
+        let mut fixed = Vec::new();
+        for (index, line) in lines.into_iter().enumerate() {
+            if !removed[index] {
+                fixed.push(line);
+            }
+            fixed.append(&mut inserted[index]);
+        }
+        Some(fixed.join("\n"))
+    }
+
+    /// `style = "block-ref"` or `"inline"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["style"])? {
+            self.style = match value.as_str() {
+                Some("block-ref") => FootnoteStyle::BlockRef,
+                Some("inline") => FootnoteStyle::Inline,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.footnotes.style must be \"block-ref\" or \"inline\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Rules about images and the props Logseq writes after them when they are resized, like
+//! `![alt](../assets/image.png){:height 300, :width 400}`
+
+use crate::assets::{asset_path, image_size};
+use crate::graph::Graph;
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::SUBDIR_ASSETS;
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static IMAGE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!\[[^\[\]]*\]\(([^()\s]+)\)\{").unwrap());
+static ASSET_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\.\.?/)*assets/").unwrap());
+static NUMBER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([0-9]+(?:\.[0-9]+)?)(px)?$").unwrap());
+
+/// The props that are sizes in pixels
+const SIZE_KEYS: &[&str] = &["height", "width"];
+
+/// Images are distorted when the ratio of their width and height props is this far from the
+/// ratio of the file, as a fraction
+const DISTORTION: f64 = 0.02;
+
+/// The props of an image in a line
+struct Props {
+    /// Link target of the image, as written
+    target: String,
+    /// Byte range of the props in the line, from `{` to `}`; to the end of the line when the
+    /// props are never closed
+    range: std::ops::Range<usize>,
+    /// The problems of the syntax
+    problems: Vec<String>,
+    /// The props written the way Logseq does, if there are problems and all of them can be fixed
+    fixed: Option<String>,
+    /// The props as (key without the colon, value)
+    entries: Vec<(String, String)>,
+}
+
+/// The props of the images of a line, ignoring inline code
+fn image_props(line: &str) -> Vec<Props> {
+    let searchable = blank_code_spans(line);
+    IMAGE_RE
+        .captures_iter(&searchable)
+        .map(|captures| {
+            let start = captures.get(0).unwrap().end() - 1;
+            let target = line[captures.get(1).unwrap().range()].to_string();
+            let Some(length) = searchable[start..].find('}') else {
+                return Props {
+                    target,
+                    range: start..line.len(),
+                    problems: vec!["Image props are never closed with }".to_string()],
+                    fixed: None,
+                    entries: Vec::new(),
+                };
+            };
+            let inner = &line[start + 1..start + length];
+            let (problems, fixed, entries) = parse_props(inner);
+            Props {
+                target,
+                range: start..start + length + 1,
+                problems,
+                fixed,
+                entries,
+            }
+        })
+        .collect()
+}
+
+/// The problems of the inside of `{…}`, the props written the way Logseq does if all of them
+/// can be fixed, and the props as (key, value)
+fn parse_props(inner: &str) -> (Vec<String>, Option<String>, Vec<(String, String)>) {
+    let tokens: Vec<&str> = inner
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .collect();
+    let mut problems = Vec::new();
+    let mut fixable = true;
+    let mut entries = Vec::new();
+    for pair in tokens.chunks(2) {
+        let key = pair[0];
+        let name = key
+            .trim_start_matches(':')
+            .trim_end_matches(':')
+            .to_string();
+        if !key.starts_with(':') || key.ends_with(':') {
+            problems.push(format!("Write the image prop {} as :{}", key, name));
+        }
+        let Some(value) = pair.get(1) else {
+            problems.push(format!("Image prop :{} has no value", name));
+            fixable = false;
+            break;
+        };
+        let mut value = value.to_string();
+        if SIZE_KEYS.contains(&name.as_str()) {
+            match NUMBER_RE.captures(&value) {
+                Some(captures) if captures.get(2).is_some() => {
+                    problems.push(format!(
+                        "Image prop :{} is a number of pixels, write {} instead of {}",
+                        name, &captures[1], value
+                    ));
+                    value = captures[1].to_string();
+                }
+                Some(_) => {}
+                None => {
+                    problems.push(format!(
+                        "Image prop :{} must be a number of pixels, not {}",
+                        name, value
+                    ));
+                    fixable = false;
+                }
+            }
+        }
+        entries.push((name, value));
+    }
+    let fixed = (fixable && !problems.is_empty()).then(|| {
+        let props: Vec<String> = entries
+            .iter()
+            .map(|(key, value)| format!(":{} {}", key, value))
+            .collect();
+        format!("{{{}}}", props.join(", "))
+    });
+    (problems, fixed, entries)
+}
+
+/// The props of images resized in Logseq, `![alt](../assets/x.png){:height 300, :width 400}`:
+/// props that are never closed, keys without their `:`, keys without a value, and sizes that
+/// aren't numbers of pixels. The fix writes the keys with their `:` and sizes without a `px`
+/// unit.
+///
+/// With the graph (see [Rule::use_graph]), the sizes are also checked against the image files
+/// of the `assets` directory: images shown larger than the file, and images whose width and
+/// height props distort them. These are only reported.
+#[derive(Default)]
+pub struct ImageProps {
+    /// The `assets` directory of the graph
+    pub assets: Option<PathBuf>,
+}
+
+impl ImageProps {
+    /// The problems of the sizes of an image compared to its file, if it is in the `assets`
+    /// directory and its size can be read
+    fn size_problems(&self, props: &Props) -> Vec<String> {
+        let Some(assets) = &self.assets else {
+            return Vec::new();
+        };
+        if !ASSET_RE.is_match(&props.target) {
+            return Vec::new();
+        }
+        let Some((width, height)) = std::fs::read(assets.join(asset_path(&props.target)))
+            .ok()
+            .and_then(|bytes| image_size(&bytes))
+        else {
+            return Vec::new();
+        };
+        let size = |key: &str| {
+            props
+                .entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .and_then(|(_, value)| value.parse::<f64>().ok())
+        };
+        let mut problems = Vec::new();
+        for (key, actual) in [("width", width), ("height", height)] {
+            if let Some(shown) = size(key).filter(|shown| *shown > f64::from(actual)) {
+                problems.push(format!(
+                    "Image shown at :{} {}, larger than the {} pixels of {}",
+                    key, shown, actual, props.target
+                ));
+            }
+        }
+        if let (Some(shown_width), Some(shown_height)) = (size("width"), size("height")) {
+            let ratio = (shown_width / shown_height) / (f64::from(width) / f64::from(height));
+            if (ratio - 1.0).abs() > DISTORTION {
+                problems.push(format!(
+                    "Image props :width {} and :height {} distort {}, which is {}x{} pixels",
+                    shown_width, shown_height, props.target, width, height
+                ));
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for ImageProps {
+    fn id(&self) -> &'static str {
+        "image-props"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Image size props that Logseq can't read, and with the graph, sizes that don't fit the image files.",
+            examples: &["- ![photo](../assets/photo.png){:width 300px}\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for props in image_props(text) {
+                    let column = block.source_column(text[..props.range.start].chars().count() + 1);
+                    let size_problems = self.size_problems(&props);
+                    for message in props.problems.into_iter().chain(size_problems) {
+                        diagnostics.push(Diagnostic::new(self.id(), line, column, message));
+                    }
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut replacements = Vec::new();
+        for block in outline.iter() {
+            for (line, content) in lines_outside_code(block.numbered_lines()) {
+                for props in image_props(content) {
+                    if let Some(fixed) = props.fixed {
+                        replacements.push(Replacement {
+                            line,
+                            range: props.range,
+                            content_length: content.len(),
+                            text: fixed,
+                        });
+                    }
+                }
+            }
+        }
+        apply_replacements(text, replacements)
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        self.assets = Some(graph.root.join(SUBDIR_ASSETS));
+    }
+}
@@ -0,0 +1,463 @@
+//! Rules about links to pages: `[[page]]` wikilinks and `[text](page)` Markdown links, and how
+//! page names are written in them
+
+use crate::graph::{percent_decode, Graph};
+use crate::lint::config::rule_options;
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::properties::REF_LIST_KEYS;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+static SCHEME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z][A-Za-z0-9+.-]*:").unwrap());
+static MARKDOWN_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[([^\[\]]*)\]\(([^()\[\]]+)\)").unwrap());
+static WIKILINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[([^\[\]]*)\]\(\[\[([^\[\]]+)\]\]\)|(#?)\[\[([^\[\]]+)\]\]").unwrap()
+});
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap());
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:^|\s)#([^\s#\[\],;!?"'()`]+)"#).unwrap());
+static PROPERTY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s*(?:[-*+]\s+)?)([A-Za-z0-9_][A-Za-z0-9_\-./]*)::\s+(.*)$").unwrap()
+});
+
+/// How links to pages of the graph are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// `[[page]]`, or `[text]([[page]])` to keep a different label
+    #[default]
+    Wikilink,
+    /// `[text](page)`
+    Markdown,
+}
+
+/// Links to pages written in the other style: Markdown links to pages of the graph become
+/// wikilinks, or the reverse. External links (with a scheme like `https:`), relative paths like
+/// `../assets/image.png` and images are left alone.
+///
+/// Telling a Markdown link to a page from a link to a file needs the pages of the graph, see
+/// [Rule::use_graph]; without them, Markdown links are never converted.
+#[derive(Default)]
+pub struct PageLinkStyle {
+    /// The style to convert links to
+    pub style: LinkStyle,
+    /// Lowercase names and aliases of the pages of the graph, see [Graph::page_names]
+    pub pages: Option<HashSet<String>>,
+}
+
+impl PageLinkStyle {
+    /// True if the target of a Markdown link is a page of the graph
+    fn is_page(&self, target: &str) -> bool {
+        if SCHEME_RE.is_match(target) || target.starts_with(['.', '/', '#']) {
+            return false;
+        }
+        self.pages
+            .as_ref()
+            .is_some_and(|pages| pages.contains(&percent_decode(target).to_lowercase()))
+    }
+
+    /// The replacements of the links in a line, as byte ranges of the line
+    fn convert_line(&self, line: &str) -> Vec<(usize, usize, String)> {
+        let searchable = blank_code_spans(line);
+        match self.style {
+            LinkStyle::Wikilink => MARKDOWN_RE
+                .captures_iter(&searchable)
+                .filter(|captures| captures[1].is_empty() && self.is_page(&captures[3]))
+                .map(|captures| {
+                    let found = captures.get(0).unwrap();
+                    let name = percent_decode(&captures[3]);
+                    let label = &captures[2];
+                    let replacement = if label.is_empty() || label.eq_ignore_ascii_case(&name) {
+                        format!("[[{}]]", name)
+                    } else {
+                        format!("[{}]([[{}]])", label, name)
+                    };
+                    (found.start(), found.end(), replacement)
+                })
+                .collect(),
+            LinkStyle::Markdown => {
+                // A label link `[text]([[page]])`, or a plain `[[page]]` that isn't a `#[[tag]]`
+                WIKILINK_RE
+                    .captures_iter(&searchable)
+                    .filter_map(|captures| {
+                        let found = captures.get(0).unwrap();
+                        if let Some(name) = captures.get(2) {
+                            let replacement = format!("[{}]({})", &captures[1], name.as_str());
+                            return Some((found.start(), found.end(), replacement));
+                        }
+                        // Embeds only take wikilinks
+                        let embed = searchable[..found.start()].trim_end().ends_with("embed");
+                        if !captures[3].is_empty() || embed {
+                            return None;
+                        }
+                        let name = &captures[4];
+                        Some((found.start(), found.end(), format!("[{}]({})", name, name)))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// The links to convert, and the column of each one
+    fn conversions(&self, outline: &Outline) -> Vec<(Replacement, usize)> {
+        let mut conversions = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for (start, end, replacement) in self.convert_line(text) {
+                    let column = block.source_column(text[..start].chars().count() + 1);
+                    let replacement = Replacement {
+                        line,
+                        range: start..end,
+                        content_length: text.len(),
+                        text: replacement,
+                    };
+                    conversions.push((replacement, column));
+                }
+            }
+        }
+        conversions
+    }
+}
+
+impl Rule for PageLinkStyle {
+    fn id(&self) -> &'static str {
+        "link-style"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Links to pages not written in the configured style.",
+            examples: &["- See [Other page](Other page)\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.conversions(outline)
+            .into_iter()
+            .map(|(replacement, column)| {
+                Diagnostic::new(
+                    self.id(),
+                    replacement.line,
+                    column,
+                    format!("Write the link as {}", replacement.text),
+                )
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .conversions(outline)
+            .into_iter()
+            .map(|(replacement, _)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+
+    /// `style = "wikilink"` or `"markdown"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["style"])? {
+            self.style = match value.as_str() {
+                Some("wikilink") => LinkStyle::Wikilink,
+                Some("markdown") => LinkStyle::Markdown,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.link-style.style must be \"wikilink\" or \"markdown\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        self.pages = Some(graph.page_names());
+    }
+}
+
+/// Page references written in another Unicode normalization than NFC, usually decomposed (NFD)
+/// names copied from file names on macOS: `[[links]]`, `#tags` and `tags::`/`alias::` entries,
+/// in blocks and page properties.
+/// Logseq finds the page either way, but other tools compare names byte for byte; the fix
+/// rewrites them to NFC.
+#[derive(Default)]
+pub struct LinkNormalization;
+
+impl LinkNormalization {
+    /// The byte ranges of the page references of a line that aren't NFC
+    fn denormalized(line: &str) -> Vec<(usize, usize)> {
+        let searchable = blank_code_spans(line);
+        let mut names: Vec<_> = LINK_RE
+            .captures_iter(&searchable)
+            .chain(TAG_RE.captures_iter(&searchable))
+            .map(|captures| captures.get(1).unwrap())
+            .collect();
+        if let Some(captures) = PROPERTY_RE.captures(&searchable) {
+            if REF_LIST_KEYS.contains(&captures[2].to_lowercase().as_str()) {
+                names.push(captures.get(3).unwrap());
+            }
+        }
+        let mut ranges: Vec<(usize, usize)> = names
+            .into_iter()
+            .filter(|name| !is_nfc(&line[name.start()..name.end()]))
+            .map(|name| (name.start(), name.end()))
+            .collect();
+        ranges.sort();
+        // A `tags::` value contains its own links
+        ranges.dedup_by(|inner, outer| inner.1 <= outer.1);
+        ranges
+    }
+
+    /// The references to rewrite, in the page properties and the blocks, and the column of
+    /// each one
+    fn replacements(&self, outline: &Outline) -> Vec<(Replacement, usize)> {
+        let mut replacements = Vec::new();
+        for (index, text) in outline.preamble.iter().enumerate() {
+            for (start, end) in Self::denormalized(text) {
+                let replacement = Replacement {
+                    line: index + 1,
+                    range: start..end,
+                    content_length: text.len(),
+                    text: text[start..end].nfc().collect(),
+                };
+                replacements.push((replacement, text[..start].chars().count() + 1));
+            }
+        }
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for (start, end) in Self::denormalized(text) {
+                    let column = block.source_column(text[..start].chars().count() + 1);
+                    let replacement = Replacement {
+                        line,
+                        range: start..end,
+                        content_length: text.len(),
+                        text: text[start..end].nfc().collect(),
+                    };
+                    replacements.push((replacement, column));
+                }
+            }
+        }
+        replacements
+    }
+}
+
+impl Rule for LinkNormalization {
+    fn id(&self) -> &'static str {
+        "link-normalization"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Page references in another Unicode normalization than NFC, like names copied from macOS file names.",
+            examples: &["- See [[Cafe\u{301}]]\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.replacements(outline)
+            .into_iter()
+            .map(|(replacement, column)| {
+                Diagnostic::new(
+                    self.id(),
+                    replacement.line,
+                    column,
+                    format!(
+                        "{} isn't in Unicode normalization form C (NFC)",
+                        replacement.text
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .replacements(outline)
+            .into_iter()
+            .map(|(replacement, _)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+}
+
+static UNCLOSED_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[([^\[\]]+)\]([^\[\]]|$)").unwrap());
+static UNOPENED_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(^|[^\[])\[([^\[\]]+)\]\]").unwrap());
+static OPEN_LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]]*)$").unwrap());
+static REF_PARENS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(\(*)([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})(\)*)",
+    )
+    .unwrap()
+});
+
+/// Page and block references with a bracket or parenthesis missing, like `[[page]`, `[page]]`
+/// or `((uuid)`, and nested page references like `[[a [[b]]]]`. The fix repairs the obvious
+/// cases; references that could be repaired in more than one way, and nested ones, are only
+/// reported. Inline code is left alone.
+#[derive(Default)]
+pub struct MalformedRefs;
+
+/// A malformed reference of a line: its byte range, the repaired reference if the repair is
+/// obvious, and the message
+type MalformedRef = (usize, usize, Option<String>, String);
+
+impl MalformedRefs {
+    /// The malformed references of a line, sorted by position
+    fn malformed(line: &str) -> Vec<MalformedRef> {
+        let searchable = blank_code_spans(line);
+        let mut found: Vec<MalformedRef> = Vec::new();
+        for captures in UNCLOSED_LINK_RE.captures_iter(&searchable) {
+            let name = &line[captures.get(1).unwrap().range()];
+            let start = captures.get(0).unwrap().start();
+            let end = captures.get(1).unwrap().end() + 1;
+            // A `]]` later on could be the end of this reference as well
+            match searchable[end..].contains("]]") {
+                false => found.push((
+                    start,
+                    end,
+                    Some(format!("[[{}]]", name)),
+                    format!("Page reference is missing a bracket, use [[{}]]", name),
+                )),
+                true => found.push((
+                    start,
+                    end,
+                    None,
+                    format!("Page reference [[{}] is missing a bracket", name),
+                )),
+            }
+        }
+        for captures in UNOPENED_LINK_RE.captures_iter(&searchable) {
+            let name = captures.get(2).unwrap();
+            let start = name.start() - 1;
+            let before = &searchable[..start];
+            // Inside another reference, the brackets belong to it
+            if before.matches("[[").count() == before.matches("]]").count() {
+                let name = &line[name.range()];
+                found.push((
+                    start,
+                    start + name.len() + 3,
+                    Some(format!("[[{}]]", name)),
+                    format!("Page reference is missing a bracket, use [[{}]]", name),
+                ));
+            }
+        }
+        if let Some(captures) = OPEN_LINK_RE.captures(&searchable) {
+            let found_at = captures.get(0).unwrap();
+            found.push((
+                found_at.start(),
+                found_at.end(),
+                None,
+                format!("Page reference {} is never closed", &line[found_at.range()]),
+            ));
+        }
+        for captures in REF_PARENS_RE.captures_iter(&searchable) {
+            let uuid = captures.get(2).unwrap();
+            let open = captures[1].len().min(2);
+            let close = captures[3].len().min(2);
+            if matches!((open, close), (2, 0) | (2, 1) | (1, 2) | (0, 2)) {
+                let uuid = &line[uuid.range()];
+                let start = captures.get(2).unwrap().start() - open;
+                found.push((
+                    start,
+                    start + open + uuid.len() + close,
+                    Some(format!("(({}))", uuid)),
+                    format!("Malformed block reference, use (({}))", uuid),
+                ));
+            }
+        }
+        found.extend(Self::nested(line, &searchable));
+        found.sort_by_key(|(start, _, _, _)| *start);
+        found
+    }
+
+    /// The page references of a line with other page references inside them
+    fn nested(line: &str, searchable: &str) -> Vec<MalformedRef> {
+        let mut found = Vec::new();
+        let (mut depth, mut outer, mut nested) = (0, 0, false);
+        let mut position = 0;
+        while position + 1 < searchable.len() {
+            let rest = &searchable.as_bytes()[position..];
+            if rest.starts_with(b"[[") {
+                if depth == 0 {
+                    outer = position;
+                }
+                depth += 1;
+                nested |= depth > 1;
+                position += 2;
+            } else if rest.starts_with(b"]]") && depth > 0 {
+                depth -= 1;
+                position += 2;
+                if depth == 0 && nested {
+                    let reference = &line[outer..position];
+                    let message = format!("Nested page references in {}", reference);
+                    found.push((outer, position, None, message));
+                    nested = false;
+                }
+            } else {
+                position += 1;
+            }
+        }
+        found
+    }
+
+    fn problems(&self, outline: &Outline) -> Vec<(Diagnostic, Option<Replacement>)> {
+        let mut problems = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for (start, end, repaired, message) in Self::malformed(text) {
+                    let column = block.source_column(text[..start].chars().count() + 1);
+                    let replacement = repaired.map(|repaired| Replacement {
+                        line,
+                        range: start..end,
+                        content_length: text.len(),
+                        text: repaired,
+                    });
+                    problems.push((
+                        Diagnostic::new(self.id(), line, column, message),
+                        replacement,
+                    ));
+                }
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for MalformedRefs {
+    fn id(&self) -> &'static str {
+        "malformed-refs"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Page and block references with a bracket or parenthesis missing, and nested page references.",
+            examples: &["- See [[Other page]\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(diagnostic, _)| diagnostic)
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .problems(outline)
+            .into_iter()
+            .filter_map(|(_, replacement)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+}
@@ -0,0 +1,1031 @@
+//! Rules about block properties
+
+use crate::config::GraphConfig;
+use crate::dates::DateFormat;
+use crate::frontmatter::{front_matter_properties, split_front_matter, DateStyle};
+use crate::lint::config::{rule_options, strings};
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::{Outline, RegionKind};
+use crate::properties::split_property;
+use crate::refs::lines_outside_code;
+use crate::schema::Schema;
+use crate::timestamps::{Timestamp, CREATED_AT, UPDATED_AT};
+use crate::toml::Toml;
+use crate::{Error, Result};
+
+/// Properties that only record the state of the editor, like `collapsed::` or the `card-*`
+/// flashcard schedule, which make noisy diffs in version-controlled graphs.
+///
+/// Only property lines after the first line of a block are removed: the first line is the title
+/// of the block, where Logseq never writes these properties.
+pub struct EditorProperties {
+    /// Keys of the properties to remove; a trailing `*` matches any key with that prefix
+    pub remove: Vec<String>,
+    /// Keys never removed, even if they match `remove`, with the same wildcards
+    pub keep: Vec<String>,
+}
+
+impl Default for EditorProperties {
+    fn default() -> Self {
+        EditorProperties {
+            remove: vec!["collapsed".to_string()],
+            keep: Vec::new(),
+        }
+    }
+}
+
+/// True if a property key matches one of the patterns, case-insensitively
+fn matches_any(key: &str, patterns: &[String]) -> bool {
+    let key = key.to_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == pattern,
+        }
+    })
+}
+
+impl EditorProperties {
+    /// The property lines to remove, as (line number, column of the key, key)
+    fn removable(&self, outline: &Outline) -> Vec<(usize, usize, String)> {
+        let mut found = Vec::new();
+        for block in outline.iter() {
+            let lines = lines_outside_code(block.numbered_lines())
+                .skip_while(|(line, _)| *line == block.line);
+            for (line, text) in lines {
+                let Some((key, _)) = split_property(text) else {
+                    continue;
+                };
+                if matches_any(key, &self.remove) && !matches_any(key, &self.keep) {
+                    let leading = text.len() - text.trim_start().len();
+                    found.push((line, block.source_column(leading + 1), key.to_string()));
+                }
+            }
+        }
+        found
+    }
+}
+
+impl Rule for EditorProperties {
+    fn id(&self) -> &'static str {
+        "editor-properties"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Properties that only record the state of the editor, like `collapsed::`.",
+            examples: &["- Notes\n  collapsed:: true\n	- Child\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.removable(outline)
+            .into_iter()
+            .map(|(line, column, key)| {
+                Diagnostic::new(
+                    self.id(),
+                    line,
+                    column,
+                    format!("Editor state property {}:: can be removed", key),
+                )
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let lines: Vec<usize> = self
+            .removable(outline)
+            .into_iter()
+            .map(|(line, _, _)| line)
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        let kept: Vec<&str> = text
+            .split('\n')
+            .enumerate()
+            .filter(|(index, _)| !lines.contains(&(index + 1)))
+            .map(|(_, line)| line)
+            .collect();
+        Some(kept.join("\n"))
+    }
+
+    /// `remove` and `keep` lists of property keys, e.g.
+    /// `remove = ["collapsed", "card-*", "logseq.order-list-type"]`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["remove", "keep"])? {
+            let keys = strings(value, &format!("rules.{}.{}", self.id(), key))?;
+            if key == "remove" {
+                self.remove = keys;
+            } else {
+                self.keep = keys;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Properties out of a canonical order, which Logseq and sync tools shuffle and turn into noisy
+/// diffs: the keys of `first` come first, in that order, then the others alphabetically.
+///
+/// Page properties and the properties of each block are sorted separately, and only within runs
+/// of consecutive property lines; each line keeps its bullet or indentation.
+pub struct PropertyOrder {
+    /// Keys that come first, in this order
+    pub first: Vec<String>,
+    /// Sort the other keys alphabetically, or keep them as they are
+    pub alphabetical: bool,
+}
+
+impl Default for PropertyOrder {
+    fn default() -> Self {
+        PropertyOrder {
+            first: ["title", "alias", "tags"].map(String::from).to_vec(),
+            alphabetical: true,
+        }
+    }
+}
+
+impl PropertyOrder {
+    /// Runs of consecutive property lines that are out of order, as (line number, property) in
+    /// canonical order, and the column of the first line
+    fn unordered<'a>(&self, outline: &'a Outline) -> Vec<(Vec<(usize, &'a str)>, usize)> {
+        let preamble: Vec<(usize, &str)> = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.as_str()))
+            .collect();
+        let mut groups = vec![(preamble, None)];
+        groups.extend(outline.iter().map(|block| {
+            (
+                lines_outside_code(block.numbered_lines()).collect(),
+                Some(block),
+            )
+        }));
+        let mut found = Vec::new();
+        for (lines, block) in groups {
+            let mut runs: Vec<Vec<(usize, &str)>> = Vec::new();
+            for (line, text) in lines {
+                if split_property(text).is_none() {
+                    continue;
+                }
+                match runs.last_mut() {
+                    Some(run) if run.last().is_some_and(|(last, _)| last + 1 == line) => {
+                        run.push((line, text))
+                    }
+                    _ => runs.push(vec![(line, text)]),
+                }
+            }
+            for run in runs {
+                let sorted = self.sorted(&run);
+                if sorted != run {
+                    let leading = run[0].1.len() - run[0].1.trim_start().len();
+                    let column =
+                        block.map_or(leading + 1, |block| block.source_column(leading + 1));
+                    found.push((sorted, column));
+                }
+            }
+        }
+        found
+    }
+
+    /// The property lines of a run in canonical order, keeping their line numbers
+    fn sorted<'a>(&self, run: &[(usize, &'a str)]) -> Vec<(usize, &'a str)> {
+        let rank = |text: &str| {
+            let key = split_property(text).map_or(String::new(), |(key, _)| key.to_lowercase());
+            match self
+                .first
+                .iter()
+                .position(|first| first.to_lowercase() == key)
+            {
+                Some(position) => (position, String::new()),
+                None if self.alphabetical => (self.first.len(), key),
+                None => (self.first.len(), String::new()),
+            }
+        };
+        let mut sorted = run.to_vec();
+        sorted.sort_by_cached_key(|(_, text)| rank(text));
+        sorted
+    }
+}
+
+impl Rule for PropertyOrder {
+    fn id(&self) -> &'static str {
+        "property-order"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Property lines out of the configured order.",
+            examples: &["- Notes\n  type:: book\n  id:: 65a1b2c3-0000-4000-8000-00000000000a\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.unordered(outline)
+            .into_iter()
+            .map(|(sorted, column)| {
+                let line = sorted.iter().map(|(line, _)| *line).min().unwrap_or(1);
+                let keys: Vec<&str> = sorted
+                    .iter()
+                    .filter_map(|(_, text)| split_property(text).map(|(key, _)| key))
+                    .collect();
+                Diagnostic::new(
+                    self.id(),
+                    line,
+                    column,
+                    format!("Properties should be ordered as {}", keys.join(", ")),
+                )
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let unordered = self.unordered(outline);
+        if unordered.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        for (sorted, _) in unordered {
+            let mut targets: Vec<usize> = sorted.iter().map(|(line, _)| *line).collect();
+            targets.sort_unstable();
+            for (target, (_, property)) in targets.into_iter().zip(&sorted) {
+                // The line keeps what comes before its property: the bullet or the indentation
+                let Some((_, old)) = sorted.iter().find(|(line, _)| *line == target) else {
+                    continue;
+                };
+                let line = &mut lines[target - 1];
+                if let Some(prefix) = line.strip_suffix(old) {
+                    *line = format!("{}{}", prefix, property);
+                }
+            }
+        }
+        Some(lines.join("\n"))
+    }
+
+    /// `first = ["title", "alias", "tags"]` and `alphabetical = true`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["first", "alphabetical"])? {
+            if key == "first" {
+                self.first = strings(value, "rules.property-order.first")?;
+            } else {
+                self.alphabetical = value.as_bool().ok_or_else(|| {
+                    Error::parse(None, "rules.property-order.alphabetical must be a boolean")
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// YAML front matter at the top of a page, which tools like Obsidian or Jekyll write and which
+/// Logseq doesn't edit. Its keys become page properties (see [front_matter_properties]), before
+/// the page properties already there, which win over keys of the same name.
+#[derive(Default)]
+pub struct FrontMatter {
+    /// How dates are written
+    pub dates: DateStyle,
+    /// The title format of journals, for [DateStyle::Journal]: the one of `config.edn` with
+    /// the config of the graph, see [Rule::use_graph_config]
+    pub title_format: DateFormat,
+}
+
+impl Rule for FrontMatter {
+    fn id(&self) -> &'static str {
+        "front-matter"
+    }
+
+    fn editable_regions(&self) -> &'static [RegionKind] {
+        &[RegionKind::FrontMatter]
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "YAML front matter at the top of a page, which Logseq doesn't edit: its keys become page properties.",
+            examples: &["---\ntitle: Example\ntags: [book]\n---\n- Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        // Lists of the front matter are parsed as blocks, so the closing `---` may be in one
+        let opened = outline.preamble.first().is_some_and(|line| line == "---");
+        let closed = outline.preamble.iter().skip(1).any(|line| line == "---")
+            || outline
+                .iter()
+                .any(|block| block.content.split('\n').any(|line| line == "---"));
+        if !(opened && closed) {
+            return Vec::new();
+        }
+        vec![Diagnostic::new(
+            self.id(),
+            1,
+            1,
+            "YAML front matter can be converted to page properties",
+        )]
+    }
+
+    fn fix(&self, text: &str, _outline: &Outline) -> Option<String> {
+        let (yaml, rest) = split_front_matter(text)?;
+        let rest = rest.trim_start_matches('\n');
+        let existing: Vec<&str> = rest
+            .lines()
+            .map_while(|line| split_property(line).map(|(key, _)| key))
+            .collect();
+        let mut lines: Vec<String> = front_matter_properties(yaml, self.dates, &self.title_format)
+            .into_iter()
+            .filter(|(key, _)| {
+                !existing
+                    .iter()
+                    .any(|existing| existing.eq_ignore_ascii_case(key))
+            })
+            .map(|(key, value)| format!("{}:: {}", key, value))
+            .collect();
+        // Page properties are separated from the blocks by an empty line
+        if existing.is_empty() && !lines.is_empty() && !rest.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(rest.to_string());
+        Some(lines.join("\n"))
+    }
+
+    /// `dates = "iso"` or `"journal"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["dates"])? {
+            self.dates = match value.as_str() {
+                Some("iso") => DateStyle::Iso,
+                Some("journal") => DateStyle::Journal,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.front-matter.dates must be \"iso\" or \"journal\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn use_graph_config(&mut self, config: &GraphConfig) {
+        self.title_format = DateFormat::new(&config.journal_page_title_format);
+    }
+}
+
+/// Property values that don't have the type the `[schema]` of `.lsd.toml` declares for their
+/// key, e.g. `rating:: five` when `rating = "integer"`. Empty values are left alone, since
+/// templates often leave properties to fill in.
+#[derive(Default)]
+pub struct PropertySchema {
+    /// The declared types
+    pub schema: Schema,
+}
+
+impl Rule for PropertySchema {
+    fn id(&self) -> &'static str {
+        "property-schema"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Property values that don't have the type the `[schema]` of `.lsd.toml` declares for their key.",
+            examples: &["rating:: five\n\n- Notes\n"],
+            fixable: false,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        if self.schema.is_empty() {
+            return Vec::new();
+        }
+        let preamble = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.as_str(), None));
+        let blocks = outline.iter().flat_map(|block| {
+            lines_outside_code(block.numbered_lines())
+                .map(move |(line, text)| (line, text, Some(block)))
+        });
+        let mut diagnostics = Vec::new();
+        for (line, text, block) in preamble.chain(blocks) {
+            let Some((key, raw)) = split_property(text) else {
+                continue;
+            };
+            let Some(kind) = self.schema.get(key) else {
+                continue;
+            };
+            if raw.trim().is_empty() || kind.accepts(key, raw) {
+                continue;
+            }
+            let leading = text.len() - text.trim_start().len();
+            diagnostics.push(Diagnostic::new(
+                self.id(),
+                line,
+                block.map_or(leading + 1, |block| block.source_column(leading + 1)),
+                format!("{}:: {} should be {}", key, raw.trim(), kind),
+            ));
+        }
+        diagnostics
+    }
+
+    /// The `[schema]` table: property keys with their types
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        self.schema = Schema::from_toml(options)?;
+        Ok(())
+    }
+}
+
+/// `created-at::` and `updated-at::` properties that aren't milliseconds since the Unix epoch,
+/// as Logseq writes them: values in seconds and dates like `2024-01-02T10:00:00Z` are fixed to
+/// milliseconds (see [Timestamp::parse]), other values are reported. An `updated-at::` before
+/// the `created-at::` of the same page or block is reported too.
+pub struct TimestampProperties;
+
+impl TimestampProperties {
+    /// The problems of a group of property lines (a page or a block), given as (line, text,
+    /// column of the text), as (line, column, message, replacement if fixable)
+    fn group_problems(
+        lines: Vec<(usize, &str, usize)>,
+        problems: &mut Vec<(usize, usize, String, Option<Replacement>)>,
+    ) {
+        let mut created = None;
+        let mut updated = None;
+        for (line, text, first_column) in lines {
+            let Some((key, raw)) = split_property(text) else {
+                continue;
+            };
+            let key = key.to_lowercase();
+            if key != CREATED_AT && key != UPDATED_AT {
+                continue;
+            }
+            let start = text.find("::").unwrap_or_default() + 2;
+            let start = start + text[start..].len() - text[start..].trim_start().len();
+            let column = first_column + text[..start].chars().count();
+            let replacement = |new: String| Replacement {
+                line,
+                range: start..start + raw.len(),
+                content_length: text.len(),
+                text: new,
+            };
+            let timestamp = Timestamp::parse(raw);
+            let message = match timestamp {
+                Timestamp::Millis(_) => None,
+                Timestamp::Seconds(_) => Some(format!(
+                    "{}:: is in seconds, Logseq writes milliseconds",
+                    key
+                )),
+                Timestamp::DateTime(_) => Some(format!(
+                    "{}:: is a date, Logseq writes milliseconds since 1970",
+                    key
+                )),
+                Timestamp::Invalid => Some(format!(
+                    "{}:: {} isn't a timestamp in milliseconds",
+                    key, raw
+                )),
+            };
+            if let Some(message) = message {
+                let fixed = timestamp
+                    .millis()
+                    .map(|millis| replacement(millis.to_string()));
+                problems.push((line, column, message, fixed));
+            }
+            match key == CREATED_AT {
+                true => created = timestamp.millis(),
+                false => updated = timestamp.millis().map(|millis| (millis, line, column)),
+            }
+        }
+        if let (Some(created), Some((updated, line, column))) = (created, updated) {
+            if updated < created {
+                let message = format!("{}:: is before {}::", UPDATED_AT, CREATED_AT);
+                problems.push((line, column, message, None));
+            }
+        }
+    }
+
+    /// The problems of an outline, as (line, column, message, replacement if fixable)
+    fn problems(&self, outline: &Outline) -> Vec<(usize, usize, String, Option<Replacement>)> {
+        let mut problems = Vec::new();
+        let preamble = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text.as_str(), 1))
+            .collect();
+        Self::group_problems(preamble, &mut problems);
+        for block in outline.iter() {
+            let lines = lines_outside_code(block.numbered_lines())
+                .map(|(line, text)| (line, text, block.source_column(1)))
+                .collect();
+            Self::group_problems(lines, &mut problems);
+        }
+        problems
+    }
+}
+
+impl Rule for TimestampProperties {
+    fn id(&self) -> &'static str {
+        "timestamp-properties"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`created-at::` and `updated-at::` properties that aren't milliseconds since the Unix epoch, or out of order.",
+            examples: &["created-at:: 1704189600\n\n- Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(line, column, message, _)| Diagnostic::new(self.id(), line, column, message))
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .problems(outline)
+            .into_iter()
+            .filter_map(|(_, _, _, replacement)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+}
+
+/// Common page properties with values Logseq can't use: `tags::` items that aren't page
+/// references, an `icon::` that isn't a single emoji, and a `public::` that isn't `true` or
+/// `false`. Values whose meaning is clear, like `[[unclosed`, `#two words`, `U+1F4DA` or `yes`,
+/// are fixed; the others are reported.
+pub struct PagePropertyValues;
+
+/// True if a value is a single emoji or character: one character, or the sequence of one with
+/// variation selectors, skin tones, a keycap, tag characters or zero-width joiners, or a pair
+/// of regional indicators for a flag
+fn is_single_symbol(value: &str) -> bool {
+    let chars: Vec<char> = value.chars().collect();
+    let regional = |c: &char| ('\u{1F1E6}'..='\u{1F1FF}').contains(c);
+    match chars.as_slice() {
+        [] => false,
+        [_] => true,
+        [first, second] if regional(first) && regional(second) => true,
+        [first, rest @ ..] => {
+            let mut joined = false;
+            !first.is_whitespace()
+                && rest.iter().all(|c| {
+                    let modifier = matches!(c, '\u{FE0E}' | '\u{FE0F}' | '\u{20E3}')
+                        || ('\u{1F3FB}'..='\u{1F3FF}').contains(c)
+                        || ('\u{E0020}'..='\u{E007F}').contains(c);
+                    let valid = modifier || *c == '\u{200D}' || (joined && !c.is_whitespace());
+                    joined = *c == '\u{200D}';
+                    valid
+                })
+        }
+    }
+}
+
+/// The character of a `U+1F4DA` codepoint
+fn codepoint(value: &str) -> Option<char> {
+    let hex = value
+        .strip_prefix("U+")
+        .or_else(|| value.strip_prefix("u+"))?;
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+/// Splits a `tags::` value on its commas, as byte ranges of the items, but not the commas of a
+/// `[[page, with commas]]`: an item that opens a reference extends to the next one that closes
+/// it, unless another reference opens first
+fn tag_items(raw: &str) -> Vec<std::ops::Range<usize>> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (index, _) in raw.match_indices(',') {
+        pieces.push(start..index);
+        start = index + 1;
+    }
+    pieces.push(start..raw.len());
+    let opens = |range: &std::ops::Range<usize>| {
+        let piece = raw[range.clone()].trim_start();
+        piece.strip_prefix('#').unwrap_or(piece).starts_with("[[")
+    };
+    let closes = |range: &std::ops::Range<usize>| raw[range.clone()].contains("]]");
+    let mut items = Vec::new();
+    let mut index = 0;
+    while index < pieces.len() {
+        let mut end = index;
+        if opens(&pieces[index]) && !closes(&pieces[index]) {
+            let closing = (index + 1..pieces.len())
+                .take_while(|&next| !opens(&pieces[next]))
+                .find(|&next| closes(&pieces[next]));
+            if let Some(closing) = closing {
+                end = closing;
+            }
+        }
+        items.push(pieces[index].start..pieces[end].end);
+        index = end + 1;
+    }
+    items
+}
+
+/// What's wrong with an item of a `tags::` value, with the item fixed if its meaning is clear
+fn tag_problem(item: &str) -> Option<(String, Option<String>)> {
+    let name = item.strip_prefix('#').unwrap_or(item);
+    let opens = name.starts_with("[[");
+    let closes = name.ends_with("]]") && name.len() >= 4;
+    let inner = name.trim_start_matches('[').trim_end_matches(']').trim();
+    if item.is_empty() {
+        return Some(("Empty item in tags::".to_string(), Some(String::new())));
+    }
+    if inner.is_empty() || inner.contains("[[") || inner.contains("]]") {
+        return Some((format!("tags:: item {} isn't a page reference", item), None));
+    }
+    if opens != closes {
+        let message = format!("Unbalanced brackets in tags:: item {}", item);
+        return Some((message, Some(format!("[[{}]]", inner))));
+    }
+    if !opens && item.starts_with('#') && inner.contains(char::is_whitespace) {
+        let message = format!("tags:: item {} has a space, write #[[{}]]", item, inner);
+        return Some((message, Some(format!("#[[{}]]", inner))));
+    }
+    None
+}
+
+impl PagePropertyValues {
+    /// The page property lines of an outline, as (line, text, column of the text)
+    fn page_lines(outline: &Outline) -> Vec<(usize, &str, usize)> {
+        let preamble: Vec<(usize, &str, usize)> = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (index + 1, text.as_str(), 1))
+            .filter(|(_, text, _)| split_property(text).is_some())
+            .collect();
+        if !preamble.is_empty() {
+            return preamble;
+        }
+        match outline.blocks.first() {
+            Some(first) if split_property(first.first_line()).is_some() => first
+                .numbered_lines()
+                .map(|(line, text)| (line, text, first.source_column(1)))
+                .filter(|(_, text, _)| split_property(text).is_some())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// The problems of an outline, as (line, column, message, replacement if fixable)
+    fn problems(&self, outline: &Outline) -> Vec<(usize, usize, String, Option<Replacement>)> {
+        let mut problems = Vec::new();
+        for (line, text, first_column) in Self::page_lines(outline) {
+            let Some((key, raw)) = split_property(text) else {
+                continue;
+            };
+            let key = key.to_lowercase();
+            let start = text.find("::").unwrap_or_default() + 2;
+            let start = start + text[start..].len() - text[start..].trim_start().len();
+            let column = |offset: usize| first_column + text[..start + offset].chars().count();
+            let replacement = |new: String| Replacement {
+                line,
+                range: start..start + raw.len(),
+                content_length: text.len(),
+                text: new,
+            };
+            match key.as_str() {
+                "tags" if !raw.trim().is_empty() => {
+                    let mut fixed: Vec<String> = Vec::new();
+                    let mut fixable = false;
+                    for range in tag_items(raw) {
+                        let untrimmed = &raw[range.clone()];
+                        let item = untrimmed.trim();
+                        let offset = range.start + untrimmed.len() - untrimmed.trim_start().len();
+                        match tag_problem(item) {
+                            Some((message, replacement)) => {
+                                problems.push((line, column(offset), message, None));
+                                match replacement {
+                                    Some(replacement) => {
+                                        fixable = true;
+                                        if !replacement.is_empty() {
+                                            fixed.push(replacement);
+                                        }
+                                    }
+                                    None => fixed.push(item.to_string()),
+                                }
+                            }
+                            None => fixed.push(item.to_string()),
+                        }
+                    }
+                    if fixable {
+                        if let Some(last) = problems.last_mut() {
+                            last.3 = Some(replacement(fixed.join(", ")));
+                        }
+                    }
+                }
+                "icon" if !raw.trim().is_empty() && !is_single_symbol(raw.trim()) => {
+                    let message = format!("icon:: {} isn't a single emoji", raw.trim());
+                    let fixed = codepoint(raw.trim()).map(|c| replacement(c.to_string()));
+                    problems.push((line, column(0), message, fixed));
+                }
+                "public" => {
+                    let value = raw.trim().to_lowercase();
+                    let fixed = match value.as_str() {
+                        "true" | "yes" | "on" | "1" => "true",
+                        "false" | "no" | "off" | "0" => "false",
+                        _ => "",
+                    };
+                    if raw.trim() != fixed {
+                        let message = format!("public:: {} isn't true or false", raw.trim());
+                        let fixed = (!fixed.is_empty()).then(|| replacement(fixed.to_string()));
+                        problems.push((line, column(0), message, fixed));
+                    }
+                }
+                _ => {}
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for PagePropertyValues {
+    fn id(&self) -> &'static str {
+        "page-property-values"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`tags::` items that aren't page references, an `icon::` that isn't a single emoji, and a `public::` that isn't true or false.",
+            examples: &["tags:: [[reading, #science fiction\npublic:: yes\n\n- Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(line, column, message, _)| Diagnostic::new(self.id(), line, column, message))
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .problems(outline)
+            .into_iter()
+            .filter_map(|(_, _, _, replacement)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+}
+
+/// A property line near the top of a page, outside the first block where Logseq reads page
+/// properties
+struct Misplaced {
+    line: usize,
+    column: usize,
+    key: String,
+    raw: String,
+    /// Another page property near the top has the same key and a different value
+    conflict: bool,
+    /// The fix moves the property into the first block, or drops it when the same property is
+    /// there already
+    moved: bool,
+}
+
+/// The page properties where Logseq reads them, and the ones near the top of the page that
+/// aren't there
+#[derive(Default)]
+struct Placement {
+    /// The page properties of the first block, as (line number, lowercase key)
+    placed: Vec<(usize, String)>,
+    /// The page properties are written in the first bullet, rather than before the bullets
+    in_block: bool,
+    misplaced: Vec<Misplaced>,
+    /// Line numbers the fix removes
+    removed: Vec<usize>,
+}
+
+/// True if a line is a Markdown heading, like `## Title`
+fn is_heading(text: &str) -> bool {
+    let rest = text.trim_start_matches('#');
+    rest.len() < text.len() && (rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Finds the page properties of an outline, and the misplaced ones: property lines of the
+/// preamble after its first non-property line, and blocks of properties among the headings
+/// and empty blocks that start the page. Blocks with an `id::` are left alone, since other
+/// blocks can refer to them.
+fn placement(outline: &Outline) -> Placement {
+    let mut placement = Placement::default();
+    let preamble = &outline.preamble;
+    // YAML front matter is for the front-matter rule
+    if preamble.first().is_some_and(|line| line.trim() == "---") {
+        return placement;
+    }
+    let mut values: Vec<(String, String)> = Vec::new();
+    let mut place = |line, key: &str, raw: &str| {
+        values.push((key.to_lowercase(), raw.trim().to_string()));
+        (line, key.to_lowercase())
+    };
+    let run = preamble
+        .iter()
+        .take_while(|line| split_property(line).is_some())
+        .count();
+    placement.placed = preamble[..run]
+        .iter()
+        .enumerate()
+        .filter_map(|(index, text)| {
+            let (key, raw) = split_property(text)?;
+            Some(place(index + 1, key, raw))
+        })
+        .collect();
+    let mut start = 0;
+    if let Some(first) = outline.blocks.first().filter(|_| preamble.is_empty()) {
+        if split_property(first.first_line()).is_some() {
+            placement.in_block = true;
+            placement.placed = first
+                .numbered_lines()
+                .map_while(|(line, text)| split_property(text).map(|(key, raw)| (line, key, raw)))
+                .map(|(line, key, raw)| place(line, key, raw))
+                .collect();
+            start = 1;
+        }
+    }
+
+    // Candidates as (line, column, key, raw, lines removed with it)
+    let mut candidates: Vec<(usize, usize, &str, &str, Vec<usize>)> = Vec::new();
+    let numbered = preamble
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.as_str()));
+    for (line, text) in lines_outside_code(numbered).filter(|(line, _)| *line > run) {
+        if let Some((key, raw)) = split_property(text) {
+            let column = text.len() - text.trim_start().len() + 1;
+            candidates.push((line, column, key, raw, vec![line]));
+        }
+    }
+    for block in outline.blocks.iter().skip(start) {
+        if !block.children.is_empty() {
+            break;
+        }
+        let lines: Vec<(usize, &str)> = block
+            .numbered_lines()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .collect();
+        let properties: Vec<(usize, &str, &str, &str)> = lines
+            .iter()
+            .filter_map(|(line, text)| {
+                split_property(text).map(|(key, raw)| (*line, *text, key, raw))
+            })
+            .collect();
+        if properties.is_empty() || properties.len() < lines.len() {
+            let heading = lines.len() == 1 && is_heading(block.first_line());
+            if lines.is_empty() || heading {
+                continue;
+            }
+            break;
+        }
+        if properties
+            .iter()
+            .any(|(_, _, key, _)| key.eq_ignore_ascii_case("id"))
+        {
+            break;
+        }
+        let block_lines: Vec<usize> = (block.line..block.line + block.line_count()).collect();
+        for (line, text, key, raw) in properties {
+            let column = block.source_column(text.len() - text.trim_start().len() + 1);
+            candidates.push((line, column, key, raw, block_lines.clone()));
+        }
+    }
+    candidates.sort_by_key(|(line, ..)| *line);
+
+    for (line, column, key, raw, _) in &candidates {
+        let lower = key.to_lowercase();
+        let conflict = values
+            .iter()
+            .any(|(other, value)| *other == lower && value != raw.trim());
+        if !conflict {
+            values.push((lower, raw.trim().to_string()));
+        }
+        placement.misplaced.push(Misplaced {
+            line: *line,
+            column: *column,
+            key: key.to_string(),
+            raw: raw.trim().to_string(),
+            conflict,
+            moved: false,
+        });
+    }
+    // A property moves with the lines removed with it, unless one of them is a conflict
+    for (index, (_, _, _, _, lines)) in candidates.iter().enumerate() {
+        let blocked = placement
+            .misplaced
+            .iter()
+            .any(|misplaced| misplaced.conflict && lines.contains(&misplaced.line));
+        if !blocked {
+            placement.misplaced[index].moved = true;
+            placement.removed.extend(lines);
+        }
+    }
+    placement.removed.sort_unstable();
+    placement.removed.dedup();
+    placement
+}
+
+/// Page properties outside the first block, where Logseq doesn't read them: after a heading or
+/// a paragraph before the bullets, or in blocks of their own after a heading, as imported files
+/// often have them. The fix moves them after the page properties there, or before the blocks
+/// followed by an empty line when there are none, and drops the ones written twice. Properties
+/// with another value there are only reported.
+pub struct PageProperties;
+
+impl Rule for PageProperties {
+    fn id(&self) -> &'static str {
+        "page-properties"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Page properties outside the first block, where Logseq doesn't read them: they are moved there.",
+            examples: &["# Example\n\ntags:: book\n\n- Notes\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        placement(outline)
+            .misplaced
+            .into_iter()
+            .map(|misplaced| {
+                let message = match misplaced.conflict {
+                    true => format!(
+                        "Page property {0}:: isn't in the first block, and another {0}:: has a different value",
+                        misplaced.key
+                    ),
+                    false => format!(
+                        "Page property {}:: isn't in the first block, where Logseq reads page properties",
+                        misplaced.key
+                    ),
+                };
+                Diagnostic::new(self.id(), misplaced.line, misplaced.column, message)
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let placement = placement(outline);
+        if placement.removed.is_empty() {
+            return None;
+        }
+        // Properties without a conflict that have the key of another one have its value too
+        let mut keys: Vec<String> = placement
+            .placed
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect();
+        let mut moved: Vec<String> = Vec::new();
+        for misplaced in placement
+            .misplaced
+            .iter()
+            .filter(|misplaced| misplaced.moved)
+        {
+            let key = misplaced.key.to_lowercase();
+            if !keys.contains(&key) {
+                keys.push(key);
+                let property = format!("{}:: {}", misplaced.key, misplaced.raw);
+                moved.push(property.trim_end().to_string());
+            }
+        }
+        let lines: Vec<&str> = text.split('\n').collect();
+        let kept = |number: &usize| placement.removed.binary_search(number).is_err();
+        let mut fixed: Vec<String> = Vec::new();
+        if placement.in_block {
+            let last = placement.placed.last().map_or(1, |(line, _)| *line);
+            let indent = format!("{}  ", outline.blocks[0].indent);
+            for (number, line) in (1..).zip(&lines).filter(|(number, _)| kept(number)) {
+                fixed.push(line.to_string());
+                if number == last {
+                    fixed.extend(
+                        moved
+                            .iter()
+                            .map(|property| format!("{}{}", indent, property)),
+                    );
+                }
+            }
+            return Some(fixed.join("\n"));
+        }
+        let run = placement.placed.len();
+        let preamble = outline.preamble.len().min(lines.len());
+        fixed.extend(lines[..run].iter().map(|line| line.to_string()));
+        fixed.extend(moved);
+        fixed.push(String::new());
+        // The rest of the preamble, without the empty lines left by the properties moved
+        for number in (run + 1..=preamble).filter(kept) {
+            let line = lines[number - 1];
+            let after_text = fixed.last().is_some_and(|last| !last.trim().is_empty());
+            if !line.trim().is_empty() || after_text {
+                fixed.push(line.to_string());
+            }
+        }
+        let rest = (preamble + 1..=lines.len()).filter(kept);
+        fixed.extend(rest.map(|number| lines[number - 1].to_string()));
+        Some(fixed.join("\n"))
+    }
+}
@@ -0,0 +1,198 @@
+//! Typographic punctuation pasted from word processors
+
+use crate::lint::config::{rule_options, strings};
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::properties::split_property;
+use crate::protected_segments;
+use crate::refs::{lines_outside_code, page_links, tag_refs};
+use crate::toml::Toml;
+use crate::{Error, Result};
+
+/// The kinds of punctuation [Punctuation] can convert, as named by its `convert` option
+const KINDS: [&str; 4] = ["quotes", "dashes", "spaces", "ellipses"];
+
+/// The characters that are converted: their kind, what they are called and what replaces them
+const CONVERSIONS: &[(char, &str, &str, &str)] = &[
+    ('\u{2018}', "quotes", "Curly quote ‘", "'"),
+    ('\u{2019}', "quotes", "Curly quote ’", "'"),
+    ('\u{201A}', "quotes", "Low quote ‚", "'"),
+    ('\u{201B}', "quotes", "Reversed quote ‛", "'"),
+    ('\u{201C}', "quotes", "Curly quote “", "\""),
+    ('\u{201D}', "quotes", "Curly quote ”", "\""),
+    ('\u{201E}', "quotes", "Low quote „", "\""),
+    ('\u{201F}', "quotes", "Reversed quote ‟", "\""),
+    ('\u{2010}', "dashes", "Hyphen ‐", "-"),
+    ('\u{2011}', "dashes", "Non-breaking hyphen ‑", "-"),
+    ('\u{2012}', "dashes", "Figure dash ‒", "-"),
+    ('\u{2013}', "dashes", "En dash –", "-"),
+    ('\u{2212}', "dashes", "Minus sign −", "-"),
+    ('\u{2014}', "dashes", "Em dash —", "--"),
+    ('\u{2015}', "dashes", "Horizontal bar ―", "--"),
+    ('\u{00A0}', "spaces", "No-break space", " "),
+    ('\u{2002}', "spaces", "En space", " "),
+    ('\u{2003}', "spaces", "Em space", " "),
+    ('\u{2007}', "spaces", "Figure space", " "),
+    ('\u{2009}', "spaces", "Thin space", " "),
+    ('\u{200A}', "spaces", "Hair space", " "),
+    ('\u{202F}', "spaces", "Narrow no-break space", " "),
+    ('\u{200B}', "spaces", "Zero-width space", ""),
+    ('\u{2026}', "ellipses", "Ellipsis …", "..."),
+];
+
+/// Curly quotes, dashes, unusual spaces and ellipses, as pasted from word processors, in place of
+/// their plain ASCII equivalents; no-break spaces in particular keep Logseq search from matching
+/// the words around them. Nothing is converted until the kinds are configured:
+///
+/// ```toml
+/// [rules.punctuation]
+/// convert = ["quotes", "dashes", "spaces", "ellipses"]
+/// ```
+///
+/// Code, math, properties, links and tags are left alone, as converting the names of pages would
+/// change the pages they refer to.
+#[derive(Debug, Default)]
+pub struct Punctuation {
+    /// The kinds of punctuation to convert, see the `convert` option
+    pub convert: Vec<String>,
+}
+
+/// A character to convert in a line: its byte offset, what it is called and what replaces it
+type Conversion = (usize, char, &'static str, &'static str);
+
+/// Byte ranges of the links and tags of a line
+fn reference_ranges(line: &str) -> Vec<(usize, usize)> {
+    let offset = |column: usize| {
+        line.char_indices()
+            .nth(column - 1)
+            .map_or(line.len(), |(offset, _)| offset)
+    };
+    let links = page_links(line).into_iter().map(|link| {
+        let start = offset(link.column);
+        (start, start + link.name.len() + "[[]]".len())
+    });
+    let tags = tag_refs(line).into_iter().map(|tag| {
+        let start = offset(tag.column);
+        (start, start + tag.name.len() + "#".len())
+    });
+    links.chain(tags).collect()
+}
+
+impl Punctuation {
+    /// The characters of a line to convert, outside code, math, links and tags
+    fn conversions(&self, line: &str, in_math: &mut bool) -> Vec<Conversion> {
+        let references = reference_ranges(line);
+        let mut found = Vec::new();
+        for (start, end, protected) in protected_segments(line, in_math) {
+            if protected {
+                continue;
+            }
+            for (offset, c) in line[start..end].char_indices() {
+                let offset = start + offset;
+                if references
+                    .iter()
+                    .any(|&(from, to)| (from..to).contains(&offset))
+                {
+                    continue;
+                }
+                let conversion = CONVERSIONS.iter().find(|(converted, kind, _, _)| {
+                    *converted == c && self.convert.iter().any(|convert| convert == kind)
+                });
+                if let Some(&(_, _, name, ascii)) = conversion {
+                    found.push((offset, c, name, ascii));
+                }
+            }
+        }
+        found
+    }
+
+    /// The lines of a block to convert with their line numbers, skipping code and properties
+    fn block_conversions<'a>(&self, block: &'a Block) -> Vec<(usize, &'a str, Vec<Conversion>)> {
+        let mut in_math = false;
+        lines_outside_code(block.numbered_lines())
+            .filter(|(_, text)| split_property(text).is_none())
+            .map(|(line, text)| (line, text, self.conversions(text, &mut in_math)))
+            .filter(|(_, _, conversions)| !conversions.is_empty())
+            .collect()
+    }
+}
+
+impl Rule for Punctuation {
+    fn id(&self) -> &'static str {
+        "punctuation"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Curly quotes, dashes, unusual spaces and ellipses pasted from word processors, once configured with the kinds to convert to ASCII.",
+            examples: &["- It\u{2019}s \u{201C}quoted\u{201D}\u{A0}\u{2014} see\u{2026}\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        if self.convert.is_empty() {
+            return Vec::new();
+        }
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            for (line, text, conversions) in self.block_conversions(block) {
+                let (offset, _, name, ascii) = conversions[0];
+                let message = match ascii {
+                    "" => format!("{} should be removed", name),
+                    " " => format!("{} should be a plain space", name),
+                    _ => format!("{} should be {}", name, ascii),
+                };
+                let column = block.source_column(text[..offset].chars().count() + 1);
+                diagnostics.push(Diagnostic::new(self.id(), line, column, message));
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        if self.convert.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for block in outline.iter() {
+            for (line, content, conversions) in self.block_conversions(block) {
+                // The content of a line is the end of the line, after its bullet or indentation
+                let source = &lines[line - 1];
+                let start = source.len() - content.len();
+                let mut fixed = source[..start].to_string();
+                let mut last = 0;
+                for (offset, c, _, ascii) in conversions {
+                    fixed.push_str(&content[last..offset]);
+                    fixed.push_str(ascii);
+                    last = offset + c.len_utf8();
+                }
+                fixed.push_str(&content[last..]);
+                lines[line - 1] = fixed;
+                changed = true;
+            }
+        }
+        changed.then(|| lines.join("\n"))
+    }
+
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["convert"])? {
+            let name = format!("rules.punctuation.{}", key);
+            let convert = strings(value, &name)?;
+            if let Some(unknown) = convert.iter().find(|kind| !KINDS.contains(&kind.as_str())) {
+                return Err(Error::parse(
+                    None,
+                    format!(
+                        "{} must name kinds among {}, not {}",
+                        name,
+                        KINDS.join(", "),
+                        unknown
+                    ),
+                ));
+            }
+            self.convert = convert;
+        }
+        Ok(())
+    }
+}
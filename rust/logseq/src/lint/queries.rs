@@ -0,0 +1,96 @@
+//! Rules about queries
+
+use crate::graph::Graph;
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::query::{check_advanced_query, check_simple_query, QueryContext};
+use crate::refs::{blank_code_spans, lines_outside_code};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static SIMPLE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{query\s+(.*?)\}\}").unwrap());
+
+/// Queries that Logseq can't run, or that find nothing because of a typo: simple
+/// `{{query ...}}` queries with unbalanced parentheses, unknown operators or task markers, and
+/// advanced `#+BEGIN_QUERY` queries that aren't valid EDN or have no `:query`.
+///
+/// With the pages of the graph, see [Rule::use_graph], references to pages and properties that
+/// don't exist are reported too.
+#[derive(Default)]
+pub struct Queries {
+    /// The pages and properties of the graph
+    pub context: Option<QueryContext>,
+}
+
+impl Rule for Queries {
+    fn id(&self) -> &'static str {
+        "queries"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`{{query}}` and advanced queries that Logseq can't parse.",
+            examples: &["- {{query (and [[book]] (task TODO)}}\n"],
+            fixable: false,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let context = self.context.as_ref();
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            // The line and column of the `#+BEGIN_QUERY` being read, and the lines after it
+            let mut advanced: Option<(usize, usize, Vec<&str>)> = None;
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                let trimmed = text.trim();
+                if let Some((start, column, edn)) = &mut advanced {
+                    if trimmed.eq_ignore_ascii_case("#+END_QUERY") {
+                        for problem in check_advanced_query(&edn.join("\n"), context) {
+                            diagnostics.push(Diagnostic::new(
+                                self.id(),
+                                *start,
+                                *column,
+                                problem.message,
+                            ));
+                        }
+                        advanced = None;
+                    } else {
+                        edn.push(text);
+                    }
+                    continue;
+                }
+                if trimmed.eq_ignore_ascii_case("#+BEGIN_QUERY") {
+                    let indent = text.chars().count() - text.trim_start().chars().count();
+                    advanced = Some((line, block.source_column(indent + 1), Vec::new()));
+                    continue;
+                }
+                let searchable = blank_code_spans(text);
+                for captures in SIMPLE_RE.captures_iter(&searchable) {
+                    let query = captures.get(1).unwrap();
+                    let offset = text[..query.start()].chars().count();
+                    for problem in check_simple_query(&text[query.range()], context) {
+                        diagnostics.push(Diagnostic::new(
+                            self.id(),
+                            line,
+                            block.source_column(offset + problem.column),
+                            problem.message,
+                        ));
+                    }
+                }
+            }
+            if let Some((start, column, _)) = advanced {
+                diagnostics.push(Diagnostic::new(
+                    self.id(),
+                    start,
+                    column,
+                    "Advanced query without #+END_QUERY",
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        self.context = Some(QueryContext::from_graph(graph));
+    }
+}
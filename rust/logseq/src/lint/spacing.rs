@@ -0,0 +1,116 @@
+//! Rules about whitespace inside blocks and at the end of lines
+
+use crate::lint::{is_table_row, Diagnostic, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::refs::lines_outside_code;
+use crate::{protected_segments, remove_consecutive_spaces_with};
+use std::borrow::Cow;
+
+/// Consecutive spaces inside the first line of a block, outside inline code and math.
+/// Table rows are left to [Tables](crate::lint::Tables), which pads their cells.
+pub struct ConsecutiveSpaces;
+
+impl Rule for ConsecutiveSpaces {
+    fn id(&self) -> &'static str {
+        "consecutive-spaces"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description:
+                "Consecutive spaces inside the first line of a block, outside code and math.",
+            examples: &["- Some  text\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .filter(|block| !is_table_row(block.first_line()))
+            .filter_map(|block| {
+                let first_line = block.first_line();
+                let position = protected_segments(first_line, &mut false)
+                    .into_iter()
+                    .filter(|(_, _, protected)| !protected)
+                    .find_map(|(start, end, _)| {
+                        first_line[start..end].find("  ").map(|found| start + found)
+                    });
+                position.map(|position| {
+                    Diagnostic::new(
+                        self.id(),
+                        block.line,
+                        block.source_column(first_line[..position].chars().count() + 1),
+                        "Consecutive spaces",
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, _outline: &Outline) -> Option<String> {
+        match remove_consecutive_spaces_with(text, true).ok()? {
+            Cow::Owned(fixed) => Some(fixed),
+            Cow::Borrowed(_) => None,
+        }
+    }
+}
+
+/// Spaces and tabs at the end of lines, outside code blocks.
+/// Bullets with nothing after them are left to [EmptyBlocks](crate::lint::EmptyBlocks).
+pub struct TrailingWhitespace;
+
+/// The text of a line without its trailing spaces and tabs
+fn trim_trailing(line: &str) -> &str {
+    line.trim_end_matches([' ', '\t'])
+}
+
+impl Rule for TrailingWhitespace {
+    fn id(&self) -> &'static str {
+        "trailing-whitespace"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Spaces and tabs at the end of lines, outside code blocks.",
+            examples: &["- Some text \n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let diagnostic = |line: usize, column: usize| {
+            Diagnostic::new(self.id(), line, column, "Trailing whitespace")
+        };
+        let mut diagnostics: Vec<Diagnostic> = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .filter(|(_, text)| trim_trailing(text) != text.as_str())
+            .map(|(index, text)| diagnostic(index + 1, trim_trailing(text).chars().count() + 1))
+            .collect();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                let trimmed = trim_trailing(text);
+                let empty_bullet = line == block.line && trimmed.is_empty();
+                if trimmed != text && !empty_bullet {
+                    let column = block.source_column(trimmed.chars().count() + 1);
+                    diagnostics.push(diagnostic(line, column));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let diagnostics = self.check(outline);
+        if diagnostics.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        for diagnostic in diagnostics {
+            lines[diagnostic.line - 1] = trim_trailing(lines[diagnostic.line - 1]);
+        }
+        Some(lines.join("\n"))
+    }
+}
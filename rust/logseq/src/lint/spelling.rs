@@ -0,0 +1,476 @@
+//! Spell checking of the text of blocks against pluggable dictionaries: Hunspell dictionaries,
+//! word lists, and the words of a custom dictionary page of the graph
+
+use crate::encoding::decode;
+use crate::graph::Graph;
+use crate::lint::config::{rule_options, strings};
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Links, tags, block references, macros, math, URLs, e-mail addresses and HTML tags, which
+/// aren't spell-checked
+static PROTECTED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"#?\[\[[^\[\]]*\]\]|\(\([^()]*\)\)|!?\[[^\[\]]*\]\([^()]*\)|\{\{.*?\}\}|\$[^$]+\$",
+        r"|\w+://\S+|\S+@\S+\.\w+|#[^\s#\[\],.!?;:()]+|<[^<>]+>"
+    ))
+    .unwrap()
+});
+static WORD_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\p{L}[\p{L}\p{M}]*(?:['’][\p{L}\p{M}]+)*").unwrap());
+
+/// The default name of the page whose words are added to the dictionaries
+pub const DICTIONARY_PAGE: &str = "Dictionary";
+
+/// A set of correctly spelled words
+pub trait Dictionary: Send + Sync {
+    /// True if the word, as written, is spelled correctly
+    fn contains(&self, word: &str) -> bool;
+}
+
+impl Dictionary for HashSet<String> {
+    fn contains(&self, word: &str) -> bool {
+        HashSet::contains(self, word)
+    }
+}
+
+/// How the flags of the words and affixes of a Hunspell dictionary are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+    /// One character per flag, the default
+    Char,
+    /// Two characters per flag, `FLAG long`
+    Long,
+    /// Comma-separated numbers, `FLAG num`
+    Number,
+}
+
+impl FlagType {
+    fn split(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|pair| pair.iter().collect()).collect()
+            }
+            FlagType::Number => flags
+                .split(',')
+                .map(|flag| flag.trim().to_string())
+                .collect(),
+        }
+    }
+}
+
+/// A prefix or suffix rule of a Hunspell affix file
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: String,
+    /// True if the affix combines with affixes of the other kind
+    cross: bool,
+    /// What is removed from the stem
+    strip: String,
+    /// What is added to it
+    add: String,
+    /// What the stem must start (prefixes) or end (suffixes) with
+    condition: Regex,
+}
+
+/// The regex of the condition of an affix rule: Hunspell conditions only have characters,
+/// `.` and bracket expressions
+fn condition_regex(condition: &str, suffix: bool) -> Option<Regex> {
+    let mut pattern = String::new();
+    let mut in_brackets = false;
+    for c in condition.chars() {
+        match c {
+            '.' if !in_brackets => pattern.push('.'),
+            '[' if !in_brackets => {
+                in_brackets = true;
+                pattern.push('[');
+            }
+            ']' if in_brackets => {
+                in_brackets = false;
+                pattern.push(']');
+            }
+            '^' if in_brackets && pattern.ends_with('[') => pattern.push('^'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    let anchored = match suffix {
+        true => format!("(?:{})$", pattern),
+        false => format!("^(?:{})", pattern),
+    };
+    Regex::new(&anchored).ok()
+}
+
+/// A Hunspell dictionary: the words of a `.dic` file, and the prefixes and suffixes of its
+/// `.aff` file that they take. Only affixes are applied: compounds, replacements and the other
+/// options of affix files are ignored, and so are the flags of words beside their affixes.
+#[derive(Debug, Clone, Default)]
+pub struct Hunspell {
+    /// The words, with their flags
+    words: HashMap<String, Vec<String>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+}
+
+impl Hunspell {
+    /// Parses the contents of a `.dic` file, and of its `.aff` file if there is one. A word
+    /// list with one word per line is a `.dic` file without affixes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{Dictionary, Hunspell};
+    /// let aff = "SFX S Y 2\nSFX S y ies [^aeiou]y\nSFX S 0 s [^y]\nPFX U Y 1\nPFX U 0 un .\n";
+    /// let dictionary = Hunspell::parse("3\nbook/S\nfly/S\nhappy/U\n", Some(aff));
+    /// assert!(dictionary.contains("books"));
+    /// assert!(dictionary.contains("flies"));
+    /// assert!(dictionary.contains("unhappy"));
+    /// assert!(!dictionary.contains("flys"));
+    /// assert!(!dictionary.contains("unbook"));
+    /// ```
+    pub fn parse(dic: &str, aff: Option<&str>) -> Self {
+        let mut dictionary = Hunspell::default();
+        let mut flag_type = FlagType::Char;
+        // The affix rules still to read of the last header, as (kind, flag, cross product, count)
+        let mut group: Option<(String, String, bool, usize)> = None;
+        for line in aff.unwrap_or_default().lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", "long", ..] => flag_type = FlagType::Long,
+                ["FLAG", "num", ..] => flag_type = FlagType::Number,
+                [kind @ ("PFX" | "SFX"), flag, rest @ ..] => {
+                    let remaining = group
+                        .as_ref()
+                        .filter(|(k, f, _, count)| k == kind && f == flag && *count > 0);
+                    match (remaining.cloned(), rest) {
+                        (Some((kind, flag, cross, count)), [strip, add, condition @ ..]) => {
+                            group = Some((kind.clone(), flag.clone(), cross, count - 1));
+                            let suffix = kind == "SFX";
+                            let condition = condition.first().copied().unwrap_or(".");
+                            let Some(condition) = condition_regex(condition, suffix) else {
+                                continue;
+                            };
+                            let add = add.split('/').next().unwrap_or_default();
+                            let affix = Affix {
+                                flag,
+                                cross,
+                                strip: if *strip == "0" {
+                                    String::new()
+                                } else {
+                                    strip.to_string()
+                                },
+                                add: if add == "0" {
+                                    String::new()
+                                } else {
+                                    add.to_string()
+                                },
+                                condition,
+                            };
+                            match suffix {
+                                true => dictionary.suffixes.push(affix),
+                                false => dictionary.prefixes.push(affix),
+                            }
+                        }
+                        (None, [cross, count, ..]) => {
+                            let count = count.parse().unwrap_or(0);
+                            group =
+                                Some((kind.to_string(), flag.to_string(), *cross == "Y", count));
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (index, line) in dic.lines().enumerate() {
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            let count = index == 0 && entry.chars().all(|c| c.is_ascii_digit());
+            if entry.is_empty() || count || entry.starts_with('#') {
+                continue;
+            }
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            dictionary
+                .words
+                .entry(word.to_string())
+                .or_default()
+                .extend(flag_type.split(flags));
+        }
+        dictionary
+    }
+
+    /// Reads a `.dic` file, or a word list, and the `.aff` file beside it if there is one.
+    /// Files that aren't valid UTF-8 are read as Latin-1.
+    pub fn read(path: &Path) -> Result<Self> {
+        let read = |path: &Path| {
+            std::fs::read(path)
+                .map(|bytes| decode(&bytes).text)
+                .map_err(|source| Error::io(path, source))
+        };
+        let dic = read(path)?;
+        let aff_path = path.with_extension("aff");
+        let aff = match aff_path.is_file() && aff_path != path {
+            true => Some(read(&aff_path)?),
+            false => None,
+        };
+        Ok(Hunspell::parse(&dic, aff.as_deref()))
+    }
+
+    /// Finds the `.dic` file of a language, like `en_US`, where Hunspell looks for them: the
+    /// directories of `$DICPATH`, then the usual system and user directories
+    pub fn find(language: &str) -> Option<PathBuf> {
+        let mut directories: Vec<PathBuf> = std::env::var_os("DICPATH")
+            .map(|paths| std::env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+            directories.push(home.join(".local/share/hunspell"));
+            directories.push(home.join("Library/Spelling"));
+        }
+        for directory in [
+            "/usr/share/hunspell",
+            "/usr/local/share/hunspell",
+            "/usr/share/myspell",
+            "/usr/share/myspell/dicts",
+            "/Library/Spelling",
+        ] {
+            directories.push(PathBuf::from(directory));
+        }
+        directories
+            .into_iter()
+            .map(|directory| directory.join(format!("{}.dic", language)))
+            .find(|path| path.is_file())
+    }
+
+    /// True if the word is in the dictionary with the flag
+    fn has(&self, word: &str, flags: &[&str]) -> bool {
+        self.words.get(word).is_some_and(|word_flags| {
+            flags
+                .iter()
+                .all(|flag| word_flags.iter().any(|word_flag| word_flag == flag))
+        })
+    }
+
+    /// The stems a prefix of a word comes from, with the prefix applied
+    fn unprefixed<'a>(&'a self, word: &'a str) -> impl Iterator<Item = (String, &'a Affix)> {
+        self.prefixes.iter().filter_map(move |prefix| {
+            let rest = word.strip_prefix(prefix.add.as_str())?;
+            let stem = format!("{}{}", prefix.strip, rest);
+            (!rest.is_empty() && prefix.condition.is_match(&stem)).then_some((stem, prefix))
+        })
+    }
+
+    /// The stems a suffix of a word comes from, with the suffix applied
+    fn unsuffixed<'a>(&'a self, word: &'a str) -> impl Iterator<Item = (String, &'a Affix)> {
+        self.suffixes.iter().filter_map(move |suffix| {
+            let rest = word.strip_suffix(suffix.add.as_str())?;
+            let stem = format!("{}{}", rest, suffix.strip);
+            (!rest.is_empty() && suffix.condition.is_match(&stem)).then_some((stem, suffix))
+        })
+    }
+}
+
+impl Dictionary for Hunspell {
+    fn contains(&self, word: &str) -> bool {
+        if self.words.contains_key(word) {
+            return true;
+        }
+        if self
+            .unsuffixed(word)
+            .any(|(stem, suffix)| self.has(&stem, &[&suffix.flag]))
+        {
+            return true;
+        }
+        self.unprefixed(word).any(|(stem, prefix)| {
+            self.has(&stem, &[&prefix.flag])
+                || (prefix.cross
+                    && self.unsuffixed(&stem).any(|(root, suffix)| {
+                        suffix.cross && self.has(&root, &[&prefix.flag, &suffix.flag])
+                    }))
+        })
+    }
+}
+
+/// The words of a line to check, as (byte offset, word): links, tags, code and the like are
+/// left out, and so are words in all caps, with capitals inside, or next to digits
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut searchable = blank_code_spans(line);
+    for found in PROTECTED_RE.find_iter(line) {
+        searchable.replace_range(found.range(), &" ".repeat(found.len()));
+    }
+    WORD_RE
+        .find_iter(&searchable)
+        .filter(|found| {
+            let before = searchable[..found.start()].chars().next_back();
+            let after = searchable[found.end()..].chars().next();
+            let attached = [before, after]
+                .into_iter()
+                .flatten()
+                .any(|c| c.is_ascii_digit() || "_/\\@".contains(c));
+            let mut chars = found.as_str().chars();
+            let first = chars.next().unwrap_or_default();
+            let rest: Vec<char> = chars.filter(|c| c.is_alphabetic()).collect();
+            let single = rest.is_empty() && first.is_alphabetic();
+            let capitals = rest.iter().any(|c| c.is_uppercase());
+            !attached && !single && !capitals
+        })
+        .map(|found| (found.start(), &line[found.range()]))
+        .collect()
+}
+
+/// Spelling mistakes in the text of blocks, with the words of the configured dictionaries:
+/// the Hunspell dictionary of a `language`, `wordlists` (Hunspell `.dic` files or files with
+/// one word per line), `words`, and, with the graph (see [Rule::use_graph]), the words of the
+/// custom dictionary `page`. Without dictionaries, nothing is checked.
+///
+/// Words are skipped in code, links, tags, block references, macros, math, URLs and
+/// properties, and so are acronyms, words with capitals inside and words next to digits.
+/// Capitalized and uppercase words are also looked up in lowercase. These are only reported.
+pub struct Spelling {
+    /// The dictionaries the words are looked up in
+    pub dictionaries: Vec<Box<dyn Dictionary>>,
+    /// The words of the config file and of the custom dictionary page
+    pub words: HashSet<String>,
+    /// The name of the custom dictionary page
+    pub page: String,
+}
+
+impl Default for Spelling {
+    fn default() -> Self {
+        Spelling {
+            dictionaries: Vec::new(),
+            words: HashSet::new(),
+            page: DICTIONARY_PAGE.to_string(),
+        }
+    }
+}
+
+impl Spelling {
+    /// True if a word is in a dictionary, as written or in lowercase
+    fn known(&self, word: &str) -> bool {
+        let word = word.replace('’', "'");
+        let lowercase = word.to_lowercase();
+        let possessive = ["'s", "'"]
+            .iter()
+            .find_map(|ending| word.strip_suffix(ending))
+            .filter(|stem| stem.chars().count() > 1);
+        let known = [Some(word.as_str()), Some(lowercase.as_str()), possessive]
+            .into_iter()
+            .flatten()
+            .any(|candidate| {
+                self.words.contains(candidate)
+                    || self.words.contains(&candidate.to_lowercase())
+                    || self
+                        .dictionaries
+                        .iter()
+                        .any(|dictionary| dictionary.contains(candidate))
+            });
+        known
+    }
+}
+
+impl Rule for Spelling {
+    fn id(&self) -> &'static str {
+        "spelling"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Misspelled words, once configured with dictionaries.",
+            examples: &["- Teh book\n"],
+            fixable: false,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        if self.dictionaries.is_empty() {
+            return diagnostics;
+        }
+        let preamble = outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (index + 1, 0, text.as_str()));
+        let blocks = outline.iter().flat_map(|block| {
+            lines_outside_code(block.numbered_lines())
+                .map(move |(line, text)| (line, block.source_column(1) - 1, text))
+        });
+        for (line, offset, text) in preamble.chain(blocks) {
+            if split_property(text).is_some() {
+                continue;
+            }
+            for (start, word) in words(text) {
+                if !self.known(word) {
+                    diagnostics.push(Diagnostic::new(
+                        self.id(),
+                        line,
+                        offset + text[..start].chars().count() + 1,
+                        format!("Unknown word {}", word),
+                    ));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// `language = "en_US"`, `wordlists = ["words.dic"]` (relative to the current directory),
+    /// `words = ["Logseq"]`, `page = "Dictionary"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        let known = ["language", "wordlists", "words", "page"];
+        for (key, value) in rule_options(self.id(), options, &known)? {
+            let name = format!("rules.spelling.{}", key);
+            match key.as_str() {
+                "language" => {
+                    let language = value
+                        .as_str()
+                        .ok_or_else(|| Error::parse(None, format!("{} must be a string", name)))?;
+                    let path = Hunspell::find(language).ok_or_else(|| {
+                        Error::parse(None, format!("no Hunspell dictionary for {}", language))
+                    })?;
+                    self.dictionaries.push(Box::new(Hunspell::read(&path)?));
+                }
+                "wordlists" => {
+                    for path in strings(value, &name)? {
+                        self.dictionaries
+                            .push(Box::new(Hunspell::read(Path::new(&path))?));
+                    }
+                }
+                "words" => self.words.extend(strings(value, &name)?),
+                _ => {
+                    self.page = value
+                        .as_str()
+                        .ok_or_else(|| Error::parse(None, format!("{} must be a string", name)))?
+                        .to_string();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        let Some(page) = graph.find_page(&self.page) else {
+            return;
+        };
+        let outline = &page.outline;
+        let lines = outline
+            .preamble
+            .iter()
+            .map(String::as_str)
+            .chain(outline.iter().flat_map(|block| block.content.split('\n')));
+        for line in lines.filter(|line| split_property(line).is_none()) {
+            self.words.extend(
+                words(line)
+                    .into_iter()
+                    .map(|(_, word)| word.replace('’', "'")),
+            );
+        }
+    }
+}
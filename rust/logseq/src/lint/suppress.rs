@@ -0,0 +1,127 @@
+//! Comments and properties that turn rules off for a page or a block
+
+use crate::outline::{Block, Outline};
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use regex::Regex;
+use std::ops::RangeInclusive;
+use std::sync::LazyLock;
+
+static COMMENT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<!--\s*lsd-disable(-next-block)?\b((?:[^-]|-[^-])*)-->").unwrap()
+});
+
+/// The property that turns rules off for its block and children, or for the whole page when
+/// it's a page property; without a value, it turns off every rule
+pub const DISABLE_PROPERTY: &str = "lsd-disable";
+
+/// The rules a comment or property turns off: every rule, or the listed ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rules {
+    All,
+    Only(Vec<String>),
+}
+
+impl Rules {
+    /// The rule ids of a comma or space separated list; none means every rule
+    fn parse(list: &str) -> Self {
+        let ids: Vec<String> = list
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect();
+        match ids.is_empty() {
+            true => Rules::All,
+            false => Rules::Only(ids),
+        }
+    }
+
+    fn contains(&self, rule: &str) -> bool {
+        match self {
+            Rules::All => true,
+            Rules::Only(ids) => ids.iter().any(|id| id == rule),
+        }
+    }
+}
+
+/// Where rules are turned off in a page:
+///
+/// - `<!-- lsd-disable rule-a, rule-b -->` before the first bullet, or `lsd-disable::` as a
+///   page property, for the whole page
+/// - the same comment or property in a block, for the block and its children
+/// - `<!-- lsd-disable-next-block rule-a -->` in a block, for the block after it (and its
+///   children)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Suppressions {
+    /// Rules turned off for the whole page
+    page: Vec<Rules>,
+    /// Rules turned off for ranges of lines, starting at 1
+    blocks: Vec<(RangeInclusive<usize>, Rules)>,
+}
+
+/// The lines of a block and its children
+fn subtree_lines(block: &Block) -> RangeInclusive<usize> {
+    let last = block.iter().last().unwrap_or(block);
+    block.line..=last.line + last.line_count() - 1
+}
+
+impl Suppressions {
+    /// Finds the suppression comments and properties of a page
+    pub(crate) fn parse(outline: &Outline) -> Self {
+        let mut suppressions = Suppressions::default();
+        for line in &outline.preamble {
+            for captures in COMMENT_RE.captures_iter(line) {
+                if captures.get(1).is_none() {
+                    suppressions.page.push(Rules::parse(&captures[2]));
+                }
+            }
+        }
+        if let Some(property) = outline.page_properties().get(DISABLE_PROPERTY) {
+            suppressions.page.push(Rules::parse(&property.raw));
+        }
+
+        let blocks: Vec<&Block> = outline.iter().collect();
+        for (index, block) in blocks.iter().enumerate() {
+            let lines = subtree_lines(block);
+            for (_, text) in lines_outside_code(block.numbered_lines()) {
+                if let Some((key, raw)) = split_property(text) {
+                    if key.eq_ignore_ascii_case(DISABLE_PROPERTY) {
+                        suppressions.blocks.push((lines.clone(), Rules::parse(raw)));
+                    }
+                }
+                let text = blank_code_spans(text);
+                for captures in COMMENT_RE.captures_iter(&text) {
+                    let rules = Rules::parse(&captures[2]);
+                    if captures.get(1).is_none() {
+                        suppressions.blocks.push((lines.clone(), rules));
+                    } else if let Some(next) = blocks[index + 1..]
+                        .iter()
+                        .find(|next| next.line > *lines.end())
+                    {
+                        suppressions.blocks.push((subtree_lines(next), rules));
+                    }
+                }
+            }
+        }
+        suppressions
+    }
+
+    /// True if the rule is turned off for the whole page
+    pub(crate) fn page_disables(&self, rule: &str) -> bool {
+        self.page.iter().any(|rules| rules.contains(rule))
+    }
+
+    /// True if the rule is turned off for some blocks, but not the whole page
+    pub(crate) fn some_blocks_disable(&self, rule: &str) -> bool {
+        !self.page_disables(rule) && self.blocks.iter().any(|(_, rules)| rules.contains(rule))
+    }
+
+    /// True if the rule is turned off at a line
+    pub(crate) fn disables(&self, rule: &str, line: usize) -> bool {
+        self.page_disables(rule)
+            || self
+                .blocks
+                .iter()
+                .any(|(lines, rules)| lines.contains(&line) && rules.contains(rule))
+    }
+}
@@ -0,0 +1,221 @@
+//! Rules about Markdown pipe tables inside blocks
+
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::refs::lines_outside_code;
+
+/// True if the text (a line without its bullet) is a row of a pipe table: `| a | b |`
+pub(crate) fn is_table_row(text: &str) -> bool {
+    text.trim_start().starts_with('|')
+}
+
+/// Splits a table row into its trimmed cells; `\|` doesn't separate cells
+fn split_row(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = row.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cell.push(c);
+                if let Some(escaped) = chars.next() {
+                    cell.push(escaped);
+                }
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    // Without a closing pipe, the text after the last one is a cell too
+    if !cell.trim().is_empty() {
+        cells.push(cell.trim().to_string());
+    }
+    cells
+}
+
+/// How a column is aligned, from the colons of the separator row
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// The alignments of the separator row `| --- | :-: |`, or None if the row isn't one
+fn separator_row(row: &str) -> Option<Vec<Alignment>> {
+    let cells = split_row(row);
+    if cells.is_empty() {
+        return None;
+    }
+    cells
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (true, false) => Alignment::Left,
+                (false, true) => Alignment::Right,
+                (false, false) => Alignment::None,
+            })
+        })
+        .collect()
+}
+
+/// A cell padded to a width, following the alignment of its column
+fn pad(cell: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+    let (before, after) = match alignment {
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+        Alignment::None | Alignment::Left => (0, padding),
+    };
+    format!("{}{}{}", " ".repeat(before), cell, " ".repeat(after))
+}
+
+/// Formats the rows of a table (the second one being the separator row) with aligned columns
+fn format_table(rows: &[&str], alignments: &[Alignment]) -> Vec<String> {
+    let cells: Vec<Vec<String>> = rows.iter().map(|row| split_row(row)).collect();
+    let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let alignment = |column: usize| alignments.get(column).copied().unwrap_or(Alignment::None);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            cells
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != 1)
+                .filter_map(|(_, row)| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+    cells
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let formatted: Vec<String> = if index == 1 {
+                alignments
+                    .iter()
+                    .zip(&widths)
+                    .map(|(alignment, &width)| match alignment {
+                        Alignment::None => "-".repeat(width),
+                        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+                        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+                        Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                    })
+                    .collect()
+            } else {
+                // Rows shorter than the header get empty cells
+                let count = row.len().max(alignments.len());
+                (0..count)
+                    .map(|column| {
+                        let cell = row.get(column).map(String::as_str).unwrap_or("");
+                        pad(cell, widths[column], alignment(column))
+                    })
+                    .collect()
+            };
+            format!("| {} |", formatted.join(" | "))
+        })
+        .collect()
+}
+
+/// A row of a table that isn't formatted yet: its line, text and formatted text
+type UnalignedRow<'a> = (usize, &'a str, String);
+
+/// The rows that aren't formatted yet of each table of a block
+fn unaligned_tables(block: &Block) -> Vec<Vec<UnalignedRow<'_>>> {
+    let lines: Vec<(usize, &str)> = lines_outside_code(block.numbered_lines()).collect();
+    let mut tables = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        while end < lines.len()
+            && is_table_row(lines[end].1)
+            && (end == start || lines[end].0 == lines[end - 1].0 + 1)
+        {
+            end += 1;
+        }
+        if end == start {
+            start += 1;
+            continue;
+        }
+        let table = &lines[start..end];
+        let header = table.first().map(|(_, text)| split_row(text).len());
+        let alignments = table.get(1).and_then(|(_, text)| separator_row(text));
+        if let Some(alignments) = alignments.filter(|alignments| Some(alignments.len()) == header) {
+            let texts: Vec<&str> = table.iter().map(|(_, text)| *text).collect();
+            let mut rows = Vec::new();
+            for ((line, text), formatted) in table.iter().zip(format_table(&texts, &alignments)) {
+                let prefix = &text[..text.len() - text.trim_start().len()];
+                let formatted = format!("{}{}", prefix, formatted);
+                if formatted != *text {
+                    rows.push((*line, *text, formatted));
+                }
+            }
+            if !rows.is_empty() {
+                tables.push(rows);
+            }
+        }
+        start = end;
+    }
+    tables
+}
+
+/// Pipe tables whose columns aren't aligned: every cell is padded to the width of its column
+/// (following the alignment of the separator row), with one space around each `|`, so that
+/// tables edited by hand stay readable in the raw files
+pub struct Tables;
+
+impl Rule for Tables {
+    fn id(&self) -> &'static str {
+        "tables"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Pipe tables whose columns aren't aligned.",
+            examples: &["- | Name | Rating |\n  |---|---|\n  | Dune | 5 |\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            // One diagnostic per table, on its first unaligned row
+            for rows in unaligned_tables(block) {
+                let (line, text, _) = &rows[0];
+                let indent = text.chars().count() - text.trim_start().chars().count();
+                diagnostics.push(Diagnostic::new(
+                    self.id(),
+                    *line,
+                    block.source_column(indent + 1),
+                    "Table columns are not aligned",
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = outline
+            .iter()
+            .flat_map(unaligned_tables)
+            .flatten()
+            .map(|(line, row, formatted)| Replacement {
+                line,
+                range: 0..row.len(),
+                content_length: row.len(),
+                text: formatted,
+            })
+            .collect();
+        apply_replacements(text, replacements)
+    }
+}
@@ -0,0 +1,161 @@
+//! Rules about tags
+
+use crate::lint::config::rule_options;
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static BRACKETED_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"#\[\[([^\[\]]+)\]\]").unwrap());
+static TRAILING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s\[\[([^\[\]]+)\]\]\s*$").unwrap());
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[[^\[\]]+\]\]").unwrap());
+
+/// Tags not written in their simplest form: brackets around tags that can do without them, e.g.
+/// `#[[tag]]` instead of `#tag`. Tags with spaces or punctuation keep their `#[[...]]` brackets,
+/// as `#` alone would end the tag there. Inline code is left alone.
+#[derive(Default)]
+pub struct TagBrackets {
+    /// Also turn `[[links]]` at the end of the first line of a block, after its text, into tags
+    pub trailing_links: bool,
+}
+
+/// The tag for a page name: `#name`, or `#[[name]]` if the name can't be written bare
+///
+/// # Examples
+///
+/// ```
+/// use logseq::lint::tag_for;
+/// assert_eq!(tag_for("Reading"), "#Reading");
+/// assert_eq!(tag_for("project/sub-page"), "#project/sub-page");
+/// assert_eq!(tag_for("multi word"), "#[[multi word]]");
+/// assert_eq!(tag_for("a,b"), "#[[a,b]]");
+/// assert_eq!(tag_for("v1."), "#[[v1.]]");
+/// ```
+pub fn tag_for(name: &str) -> String {
+    let bare = !name.is_empty() && !name.ends_with('.') && !name.chars().any(ends_tag);
+    if bare {
+        format!("#{}", name)
+    } else {
+        format!("#[[{}]]", name)
+    }
+}
+
+/// True if a character ends a bare tag, as in [crate::refs::tag_refs]
+fn ends_tag(c: char) -> bool {
+    c.is_whitespace() || "#[],;!?\"'()`".contains(c)
+}
+
+/// True if a bare tag would end where the text starts: at its end, or at a character that ends
+/// tags, after the dots a tag doesn't keep at its end
+fn ends_bare_tag(text: &str) -> bool {
+    text.trim_start_matches('.')
+        .chars()
+        .next()
+        .is_none_or(ends_tag)
+}
+
+impl TagBrackets {
+    /// The tags to rewrite in a line, as (byte range, tag, message)
+    fn rewrite_line(&self, text: &str, first_line: bool) -> Vec<(usize, usize, String, String)> {
+        let searchable = blank_code_spans(text);
+        let mut rewrites: Vec<(usize, usize, String, String)> = BRACKETED_RE
+            .captures_iter(&searchable)
+            .filter_map(|captures| {
+                let found = captures.get(0).unwrap();
+                let tag = tag_for(&text[captures.get(1).unwrap().range()]);
+                // Without its brackets, a tag would also take the text right after it
+                (tag != found.as_str() && ends_bare_tag(&text[found.end()..])).then(|| {
+                    let message = format!("Unnecessary brackets in tag, use {}", tag);
+                    (found.start(), found.end(), tag, message)
+                })
+            })
+            .collect();
+        if self.trailing_links && first_line && split_property(text).is_none() {
+            let mut end = searchable.len();
+            let mut trailing = Vec::new();
+            while let Some(captures) = TRAILING_RE.captures(&searchable[..end]) {
+                let name = captures.get(1).unwrap();
+                trailing.push((name.start() - 2, name.end() + 2, tag_for(name.as_str())));
+                end = name.start() - 2;
+            }
+            // A block that is only links doesn't use them as tags
+            if !LINK_RE
+                .replace_all(&searchable[..end], "")
+                .trim()
+                .is_empty()
+            {
+                for (start, end, tag) in trailing.into_iter().rev() {
+                    let message = format!("Link used as a tag, use {}", tag);
+                    rewrites.push((start, end, tag, message));
+                }
+            }
+        }
+        rewrites
+    }
+
+    fn rewrites(&self, outline: &Outline) -> Vec<(Diagnostic, Replacement)> {
+        let mut rewrites = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for (start, end, tag, message) in self.rewrite_line(text, line == block.line) {
+                    let column = block.source_column(text[..start].chars().count() + 1);
+                    rewrites.push((
+                        Diagnostic::new(self.id(), line, column, message),
+                        Replacement {
+                            line,
+                            range: start..end,
+                            content_length: text.len(),
+                            text: tag,
+                        },
+                    ));
+                }
+            }
+        }
+        rewrites
+    }
+}
+
+impl Rule for TagBrackets {
+    fn id(&self) -> &'static str {
+        "tag-brackets"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Tags written with brackets they don't need, like `#[[tag]]` for `#tag`.",
+            examples: &["- Read #[[book]]\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.rewrites(outline)
+            .into_iter()
+            .map(|(diagnostic, _)| diagnostic)
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .rewrites(outline)
+            .into_iter()
+            .map(|(_, replacement)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+
+    /// `trailing-links = true`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["trailing-links"])? {
+            self.trailing_links = value.as_bool().ok_or_else(|| {
+                Error::parse(None, "rules.tag-brackets.trailing-links must be a boolean")
+            })?;
+        }
+        Ok(())
+    }
+}
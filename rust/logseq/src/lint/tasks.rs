@@ -0,0 +1,660 @@
+//! Rules about task markers
+
+use crate::config::GraphConfig;
+use crate::graph::Graph;
+use crate::lint::config::rule_options;
+use crate::lint::{rewrite_first_lines, Diagnostic, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::refs::blank_code_spans;
+use crate::tasks::{
+    is_done, split_marker, Completion, Timestamp, Workflow, CLOSED_MARKERS, TASK_MARKERS,
+};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+static PRIORITY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[#([A-Ca-c])\]").unwrap());
+static TIMESTAMP_LINE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(SCHEDULED|DEADLINE):\s*<([^<>]*)>\s*$").unwrap());
+static REPEATER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\.\+|\+\+|\+\.|\+)?(\d*)([A-Za-z]*)(\.\+|\+\+|\+)?$").unwrap());
+
+/// Task markers that are lowercase, not at the start of the block, or not in the preferred
+/// workflow. A lowercase marker is often a word of the text, like in `- now I see`, so it's
+/// only uppercased with `fix_lowercase`.
+#[derive(Default)]
+pub struct TaskMarkers {
+    /// Convert markers to this workflow (e.g. `TODO` to `LATER`); None keeps both workflows
+    pub workflow: Option<Workflow>,
+    /// Uppercase the lowercase markers at the start of blocks, instead of only reporting them
+    pub fix_lowercase: bool,
+}
+
+impl TaskMarkers {
+    /// Returns the normalized first line of a block and why it changed, or None if it's fine.
+    /// The line is None for markers that aren't fixed.
+    fn normalize(&self, first_line: &str) -> Option<(Option<String>, String)> {
+        let (word, rest) = first_line.split_once(' ').unwrap_or((first_line, ""));
+        let mut reason = None;
+        let mut fixable = true;
+        let (marker, text) = match split_marker(first_line) {
+            Some(found) => found,
+            None => {
+                let upper = word.to_uppercase();
+                let last = first_line.rsplit_once(' ');
+                if word.chars().all(|c| !c.is_uppercase()) && TASK_MARKERS.contains(&upper.as_str())
+                {
+                    reason = Some(format!("Task marker {} should be uppercase", upper));
+                    fixable = self.fix_lowercase;
+                    (*TASK_MARKERS.iter().find(|m| **m == upper)?, rest)
+                } else if let Some((text, marker)) =
+                    last.filter(|(_, last_word)| TASK_MARKERS.contains(last_word))
+                {
+                    reason = Some(format!(
+                        "Task marker {} should be at the start of the block",
+                        marker
+                    ));
+                    (marker, text.trim_end())
+                } else {
+                    return None;
+                }
+            }
+        };
+        let converted = self.workflow.map_or(marker, |w| w.convert(marker));
+        if converted != marker && reason.is_none() {
+            reason = Some(format!("Use {} instead of {}", converted, marker));
+        }
+        let line = if text.is_empty() {
+            converted.to_string()
+        } else {
+            format!("{} {}", converted, text)
+        };
+        reason.map(|reason| (fixable.then_some(line), reason))
+    }
+}
+
+impl Rule for TaskMarkers {
+    fn id(&self) -> &'static str {
+        "task-markers"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Task markers that are lowercase, not at the start of the block, or not in the preferred workflow.",
+            examples: &["- todo Buy milk\n- Call mom LATER\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .filter_map(|block| {
+                self.normalize(block.first_line()).map(|(_, reason)| {
+                    Diagnostic::new(self.id(), block.line, block.source_column(1), reason)
+                })
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        rewrite_first_lines(text, outline, |block| {
+            self.normalize(block.first_line())
+                .and_then(|(line, _)| line)
+        })
+    }
+
+    /// `workflow = "todo"` (TODO/DOING), `"now"` (NOW/LATER) or `"any"`, and
+    /// `fix-lowercase = true`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (key, value) in rule_options(self.id(), options, &["workflow", "fix-lowercase"])? {
+            if key == "fix-lowercase" {
+                self.fix_lowercase = value.as_bool().ok_or_else(|| {
+                    Error::parse(None, "rules.task-markers.fix-lowercase must be a boolean")
+                })?;
+                continue;
+            }
+            self.workflow = match value.as_str().map(str::to_lowercase).as_deref() {
+                Some("todo") => Some(Workflow::TodoDoing),
+                Some("now") => Some(Workflow::NowLater),
+                Some("any") => None,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.task-markers.workflow must be \"todo\", \"now\" or \"any\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Priority cookies (`[#A]`, `[#B]`, `[#C]`) that are lowercase, repeated, or not right after
+/// the task marker, where Logseq writes them; the first one of a block wins. Priorities on
+/// blocks that aren't tasks are reported, but not fixed.
+pub struct Priorities;
+
+impl Priorities {
+    /// The normalized first line of a block and why it changed, or None if it's fine.
+    /// The line is None for priorities that can't be fixed.
+    fn normalize(first_line: &str) -> Option<(Option<String>, String)> {
+        let searchable = blank_code_spans(first_line);
+        let cookies: Vec<_> = PRIORITY_RE.find_iter(&searchable).collect();
+        let first = cookies.first()?;
+        let letter = first_line[first.start() + 2..first.end() - 1].to_uppercase();
+        let Some((marker, _)) = split_marker(first_line) else {
+            return Some((
+                None,
+                format!("Priority [#{}] on a block that isn't a task", letter),
+            ));
+        };
+
+        // The text after the marker without cookies, each taking a space around it along
+        let mut text = first_line[marker.len()..].to_string();
+        for cookie in cookies.iter().rev() {
+            let (mut start, mut end) = (cookie.start() - marker.len(), cookie.end() - marker.len());
+            if text[..start].ends_with(' ') {
+                start -= 1;
+            } else if text[end..].starts_with(' ') {
+                end += 1;
+            }
+            text.replace_range(start..end, "");
+        }
+        let text = text.trim_start();
+        let line = match text {
+            "" => format!("{} [#{}]", marker, letter),
+            _ => format!("{} [#{}] {}", marker, letter, text),
+        };
+        if line == first_line {
+            return None;
+        }
+        let reason = if cookies.len() > 1 {
+            format!("Duplicate priority, keep only [#{}]", letter)
+        } else if first.as_str() != format!("[#{}]", letter) {
+            format!("Priority {} should be uppercase", first.as_str())
+        } else {
+            format!("Priority [#{}] should come right after {}", letter, marker)
+        };
+        Some((Some(line), reason))
+    }
+}
+
+impl Rule for Priorities {
+    fn id(&self) -> &'static str {
+        "priorities"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Priorities that are lowercase, repeated or not right after the task marker, and priorities of blocks that aren't tasks.",
+            examples: &["- TODO Buy milk [#a]\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .filter_map(|block| {
+                Self::normalize(block.first_line()).map(|(_, reason)| {
+                    Diagnostic::new(self.id(), block.line, block.source_column(1), reason)
+                })
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        rewrite_first_lines(text, outline, |block| {
+            Self::normalize(block.first_line()).and_then(|(line, _)| line)
+        })
+    }
+}
+
+/// `SCHEDULED:` and `DEADLINE:` lines that are malformed, have an impossible date, or a weekday
+/// that doesn't match the date
+pub struct Timestamps;
+
+impl Timestamps {
+    /// Returns the line with the weekday of its timestamp fixed, or None if it's fine
+    fn fix_weekday(line: &str) -> Option<String> {
+        let timestamp = Timestamp::parse(line)?.ok()?;
+        let weekday = timestamp
+            .weekday
+            .as_ref()
+            .filter(|_| !timestamp.weekday_matches())?;
+        let date = timestamp.date.format("%Y-%m-%d");
+        Some(line.replacen(
+            &format!("{} {}", date, weekday),
+            &format!("{} {}", date, timestamp.expected_weekday()),
+            1,
+        ))
+    }
+}
+
+impl Rule for Timestamps {
+    fn id(&self) -> &'static str {
+        "timestamps"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`SCHEDULED:` and `DEADLINE:` lines that are malformed, have an impossible date, or the wrong weekday.",
+            examples: &["- TODO Pay rent\n  SCHEDULED: <2024-01-05 Mon>\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in block.numbered_lines() {
+                // Problems with repeaters are left to the repeaters rule
+                let message = match Timestamp::parse(&without_repeater_problems(text)) {
+                    None => continue,
+                    Some(Err(error)) => error.to_string(),
+                    Some(Ok(timestamp)) if !timestamp.weekday_matches() => format!(
+                        "{} is a {}, not a {}",
+                        timestamp.date.format("%Y-%m-%d"),
+                        timestamp.expected_weekday(),
+                        timestamp.weekday.unwrap_or_default()
+                    ),
+                    Some(Ok(_)) => continue,
+                };
+                let column = text.len() - text.trim_start().len() + 1;
+                diagnostics.push(Diagnostic::new(
+                    self.id(),
+                    line,
+                    block.source_column(column),
+                    message,
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for block in outline.iter() {
+            for (line, _) in block.numbered_lines() {
+                if let Some(fixed) = Self::fix_weekday(&lines[line - 1]) {
+                    lines[line - 1] = fixed;
+                    changed = true;
+                }
+            }
+        }
+        changed.then(|| lines.join("\n"))
+    }
+}
+
+/// How a repeater cookie of a timestamp is written
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RepeaterCookie {
+    /// Written the way Logseq does, like `.+1d`
+    Valid,
+    /// A typo that can be fixed, with the repeater as Logseq writes it
+    Typo(String),
+    /// A repeater Logseq can't read, and why
+    Invalid(String),
+}
+
+/// Reads a word of a timestamp that has a `+` in it as a repeater cookie
+///
+/// Fixable typos are the `+` after the interval (`1d+`), `+.` for `.+`, and units that are
+/// uppercase or spelled out (`+1D`, `+2weeks`).
+fn read_repeater(cookie: &str) -> RepeaterCookie {
+    let malformed = || RepeaterCookie::Invalid(format!("Malformed repeater {}", cookie));
+    let Some(captures) = REPEATER_RE.captures(cookie) else {
+        return malformed();
+    };
+    let kind = match (captures.get(1), captures.get(4)) {
+        (Some(kind), None) | (None, Some(kind)) => match kind.as_str() {
+            "+." => ".+",
+            kind => kind,
+        },
+        _ => return malformed(),
+    };
+    let amount = &captures[2];
+    if amount.is_empty() {
+        return RepeaterCookie::Invalid(format!("Repeater {} has no interval", cookie));
+    }
+    if amount.chars().all(|c| c == '0') {
+        return RepeaterCookie::Invalid(format!("Repeater {} has an interval of zero", cookie));
+    }
+    let unit = match captures[3].to_lowercase().as_str() {
+        "h" | "hour" | "hours" => 'h',
+        "d" | "day" | "days" => 'd',
+        "w" | "wk" | "week" | "weeks" => 'w',
+        "m" | "month" | "months" => 'm',
+        "y" | "yr" | "year" | "years" => 'y',
+        "" => {
+            return RepeaterCookie::Invalid(format!(
+                "Repeater {} has no unit, expected h, d, w, m or y",
+                cookie
+            ))
+        }
+        unit => {
+            return RepeaterCookie::Invalid(format!(
+                "Repeater {} has an invalid unit {}, expected h, d, w, m or y",
+                cookie, unit
+            ))
+        }
+    };
+    let written = format!("{}{}{}", kind, amount.trim_start_matches('0'), unit);
+    if written == cookie {
+        RepeaterCookie::Valid
+    } else {
+        RepeaterCookie::Typo(written)
+    }
+}
+
+/// The repeater cookies of a `SCHEDULED:` or `DEADLINE:` line: the words of its timestamp with
+/// a `+`, with their byte offsets in the line
+fn repeater_cookies(line: &str) -> Vec<(usize, &str, RepeaterCookie)> {
+    let Some(value) = TIMESTAMP_LINE_RE
+        .captures(line)
+        .and_then(|captures| captures.get(2))
+    else {
+        return Vec::new();
+    };
+    let mut cookies = Vec::new();
+    let mut offset = value.start();
+    for word in value.as_str().split(' ') {
+        if word.contains('+') {
+            cookies.push((offset, word, read_repeater(word)));
+        }
+        offset += word.len() + 1;
+    }
+    cookies
+}
+
+/// The line with the typos of its repeaters fixed, and the repeaters Logseq can't read removed
+fn without_repeater_problems(line: &str) -> Cow<'_, str> {
+    let cookies = repeater_cookies(line);
+    if cookies
+        .iter()
+        .all(|(_, _, cookie)| *cookie == RepeaterCookie::Valid)
+    {
+        return Cow::Borrowed(line);
+    }
+    let mut fixed = line.to_string();
+    for (offset, written, cookie) in cookies.iter().rev() {
+        let range = *offset..offset + written.len();
+        match cookie {
+            RepeaterCookie::Valid => {}
+            RepeaterCookie::Typo(repeater) => fixed.replace_range(range, repeater),
+            RepeaterCookie::Invalid(_) => {
+                fixed.replace_range(offset.saturating_sub(1)..range.end, "")
+            }
+        }
+    }
+    Cow::Owned(fixed)
+}
+
+/// Repeater cookies of `SCHEDULED:` and `DEADLINE:` timestamps (`.+1d`, `++1w`, `+1m`) that
+/// Logseq can't read, that conflict with another repeater of the task, or that are on a
+/// finished task without a `SCHEDULED:` date, so they never repeat. Typos like `1d+` or `+1D`
+/// are fixed.
+pub struct Repeaters;
+
+impl Rule for Repeaters {
+    fn id(&self) -> &'static str {
+        "repeaters"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Repeaters of `SCHEDULED:` and `DEADLINE:` timestamps that Logseq can't read, that conflict, or that never repeat.",
+            examples: &["- TODO Pay rent\n  SCHEDULED: <2024-01-05 Fri 1m+>\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            let mut repeaters: Vec<(usize, usize, String)> = Vec::new();
+            let mut scheduled = false;
+            for (line, text) in block.numbered_lines() {
+                if Timestamp::parse(text).is_none() {
+                    continue;
+                }
+                scheduled |= text.trim_start().starts_with("SCHEDULED:");
+                for (offset, written, cookie) in repeater_cookies(text) {
+                    let column = text[..offset].chars().count() + 1;
+                    let message = match cookie {
+                        RepeaterCookie::Valid => {
+                            repeaters.push((line, column, written.to_string()));
+                            continue;
+                        }
+                        RepeaterCookie::Typo(repeater) => {
+                            let message =
+                                format!("Repeater {} should be written {}", written, repeater);
+                            repeaters.push((line, column, repeater));
+                            message
+                        }
+                        RepeaterCookie::Invalid(message) => message,
+                    };
+                    diagnostics.push(Diagnostic::new(
+                        self.id(),
+                        line,
+                        block.source_column(column),
+                        message,
+                    ));
+                }
+            }
+            let Some((_, _, first)) = repeaters.first() else {
+                continue;
+            };
+            for (line, column, repeater) in &repeaters[1..] {
+                if repeater != first {
+                    diagnostics.push(Diagnostic::new(
+                        self.id(),
+                        *line,
+                        block.source_column(*column),
+                        format!("Repeater {} conflicts with {}", repeater, first),
+                    ));
+                }
+            }
+            let marker = split_marker(block.first_line()).map(|(marker, _)| marker);
+            if let Some(marker) = marker.filter(|marker| CLOSED_MARKERS.contains(marker)) {
+                if !scheduled {
+                    let (line, column, repeater) = &repeaters[0];
+                    diagnostics.push(Diagnostic::new(
+                        self.id(),
+                        *line,
+                        block.source_column(*column),
+                        format!(
+                            "Repeater {} on a {} task without a SCHEDULED date never repeats",
+                            repeater, marker
+                        ),
+                    ));
+                }
+            }
+        }
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for block in outline.iter() {
+            for (line, _) in block.numbered_lines() {
+                let source = &lines[line - 1];
+                let mut fixed = source.clone();
+                for (offset, written, cookie) in repeater_cookies(source).into_iter().rev() {
+                    if let RepeaterCookie::Typo(repeater) = cookie {
+                        fixed.replace_range(offset..offset + written.len(), &repeater);
+                    }
+                }
+                if fixed != *source {
+                    lines[line - 1] = fixed;
+                    changed = true;
+                }
+            }
+        }
+        changed.then(|| lines.join("\n"))
+    }
+}
+
+/// `DONE` tasks that don't record when they were done: with a `CLOCK:` entry in their
+/// `:LOGBOOK:`, or with a `done::` timestamp, see [Completion]. Which one is the `completion`
+/// option, or else, with the graph (see [Rule::use_graph]), its `:feature/enable-timetracking?`
+/// setting. `lsd tasks backfill-done` records the times from git.
+#[derive(Default)]
+pub struct DoneMetadata {
+    /// How completion is recorded
+    pub completion: Completion,
+    /// True if the completion was set by the options, and not from the graph
+    pub configured: bool,
+}
+
+impl Rule for DoneMetadata {
+    fn id(&self) -> &'static str {
+        "done-metadata"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "`DONE` tasks that don't record when they were done, in their `:LOGBOOK:` or a `done::` property.",
+            examples: &["- DONE Pay rent\n"],
+            fixable: false,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let message = match self.completion {
+            Completion::Logbook => "DONE task without a CLOCK entry in its :LOGBOOK:",
+            Completion::Property => "DONE task without a done:: timestamp",
+        };
+        outline
+            .iter()
+            .filter(|block| is_done(block) && !self.completion.is_recorded(block))
+            .map(|block| Diagnostic::new(self.id(), block.line, block.source_column(1), message))
+            .collect()
+    }
+
+    /// `completion = "logbook"` or `"property"`
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["completion"])? {
+            self.completion = match value.as_str().map(str::to_lowercase).as_deref() {
+                Some("logbook") => Completion::Logbook,
+                Some("property") => Completion::Property,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.done-metadata.completion must be \"logbook\" or \"property\"",
+                    ))
+                }
+            };
+            self.configured = true;
+        }
+        Ok(())
+    }
+
+    fn use_graph(&mut self, graph: &Graph) {
+        if !self.configured {
+            if let Ok(config) = GraphConfig::read(&graph.root) {
+                self.completion = Completion::of(&config);
+            }
+        }
+    }
+}
+
+/// GitHub-style checkboxes, `- [ ] task` and `- [x] task`, which are common in imported notes:
+/// they become `TODO` (or `LATER`) and `DONE` tasks. Obsidian's `[/]` (in progress) and `[-]`
+/// (canceled) become `DOING` (or `NOW`) and `CANCELED`.
+pub struct Checkboxes {
+    /// The workflow of the markers for open tasks
+    pub workflow: Workflow,
+}
+
+impl Default for Checkboxes {
+    fn default() -> Self {
+        Checkboxes {
+            workflow: Workflow::TodoDoing,
+        }
+    }
+}
+
+impl Checkboxes {
+    /// The checkbox at the start of a first line and the task marker it becomes, with the rest
+    /// of the line
+    fn convert<'a>(&self, first_line: &'a str) -> Option<(&'a str, &'static str, &'a str)> {
+        let checkbox = first_line.get(..3)?;
+        let marker = match checkbox {
+            "[ ]" => "TODO",
+            "[x]" | "[X]" => "DONE",
+            "[/]" => "DOING",
+            "[-]" => "CANCELED",
+            _ => return None,
+        };
+        let rest = &first_line[3..];
+        if !rest.is_empty() && !rest.starts_with(' ') {
+            return None;
+        }
+        Some((checkbox, self.workflow.convert(marker), rest.trim_start()))
+    }
+}
+
+impl Rule for Checkboxes {
+    fn id(&self) -> &'static str {
+        "checkboxes"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "GitHub-style checkboxes like `- [ ] task`, which become Logseq tasks.",
+            examples: &["- [ ] Buy milk\n- [x] Call back\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .filter_map(|block| {
+                self.convert(block.first_line())
+                    .map(|(checkbox, marker, _)| {
+                        Diagnostic::new(
+                            self.id(),
+                            block.line,
+                            block.source_column(1),
+                            format!("Checkbox {} should be a {} task", checkbox, marker),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        rewrite_first_lines(text, outline, |block| {
+            self.convert(block.first_line())
+                .map(|(_, marker, rest)| match rest {
+                    "" => marker.to_string(),
+                    _ => format!("{} {}", marker, rest),
+                })
+        })
+    }
+
+    /// `workflow = "todo"` (TODO/DOING) or `"now"` (LATER/NOW)
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["workflow"])? {
+            self.workflow = match value.as_str().map(str::to_lowercase).as_deref() {
+                Some("todo") => Workflow::TodoDoing,
+                Some("now") => Workflow::NowLater,
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.checkboxes.workflow must be \"todo\" or \"now\"",
+                    ))
+                }
+            };
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+//! Rules about the times that start blocks in interstitial journaling, like `**09:05** Coffee`
+
+use crate::config::GraphConfig;
+use crate::dates::DateFormat;
+use crate::lint::config::rule_options;
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::toml::Toml;
+use crate::{Error, Result};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static TIME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d{1,2})([:.])(\d{1,2})(?:\s?([aApP])[mM])?").unwrap());
+
+/// The default format of [BlockTimes]
+pub const BLOCK_TIME_FORMAT: &str = "**HH:MM**";
+
+/// A time at the start of the first line of a block
+struct BlockTime<'a> {
+    block: &'a Block,
+    /// The time as written
+    text: &'a str,
+    /// Minutes since midnight
+    minutes: u32,
+}
+
+/// Times at the start of blocks, like `9:5`, `09.05` or `9:05am`, that aren't written in the
+/// same format: `**09:05**` by default, or the `format` option, where `HH` is the hour on two
+/// digits, `H` the hour, and `MM` the minutes. Times with a dot need two digits for the hour
+/// and the minutes, or `am` or `pm`, so that `3.5 kg` is left alone.
+///
+/// In journals, a time earlier than the one of the sibling block above is reported too, since
+/// interstitial journaling writes the blocks of a day in order.
+pub struct BlockTimes {
+    /// The format of the times
+    pub format: String,
+    /// The title format of journals, to tell them by their name: the one of `config.edn` with
+    /// the config of the graph, see [Rule::use_graph_config]
+    pub title_format: DateFormat,
+}
+
+impl Default for BlockTimes {
+    fn default() -> Self {
+        BlockTimes {
+            format: BLOCK_TIME_FORMAT.to_string(),
+            title_format: DateFormat::default(),
+        }
+    }
+}
+
+impl BlockTimes {
+    /// The text of the format around the time, like the `**` of `**HH:MM**`
+    fn wrapper(&self) -> (&str, &str) {
+        let start = self.format.find('H').unwrap_or(0);
+        let end = self
+            .format
+            .rfind('M')
+            .map_or(self.format.len(), |end| end + 1);
+        (&self.format[..start], &self.format[end..])
+    }
+
+    /// The time at the start of a line, as the text of the time and minutes since midnight
+    fn parse<'a>(&self, line: &'a str) -> Option<(&'a str, u32)> {
+        let (prefix, suffix) = self.wrapper();
+        let wrapped = line
+            .strip_prefix(prefix)
+            .filter(|_| !prefix.is_empty())
+            .and_then(|rest| {
+                let found = TIME_RE.find(rest)?;
+                rest[found.end()..].starts_with(suffix).then_some((
+                    rest,
+                    prefix.len(),
+                    found.end() + suffix.len(),
+                ))
+            });
+        let (rest, start, end) =
+            wrapped.or_else(|| TIME_RE.find(line).map(|found| (line, 0, found.end())))?;
+        let length = start + end;
+        if !line[length..].is_empty() && !line[length..].starts_with(char::is_whitespace) {
+            return None;
+        }
+        let captures = TIME_RE.captures(rest)?;
+        let (hour, separator, minute) = (&captures[1], &captures[2], &captures[3]);
+        let meridiem = captures.get(4).map(|m| m.as_str().to_ascii_lowercase());
+        if separator == "." && meridiem.is_none() && (hour.len() < 2 || minute.len() < 2) {
+            return None;
+        }
+        let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+        let hour = match meridiem.as_deref() {
+            Some(_) if !(1..=12).contains(&hour) => return None,
+            Some("a") => hour % 12,
+            Some(_) => hour % 12 + 12,
+            None => hour,
+        };
+        (hour < 24 && minute < 60).then_some((&line[..length], hour * 60 + minute))
+    }
+
+    /// A time in the format of the rule
+    fn format_time(&self, minutes: u32) -> String {
+        let (hour, minute) = (minutes / 60, minutes % 60);
+        self.format
+            .replace("HH", &format!("{:02}", hour))
+            .replace("MM", &format!("{:02}", minute))
+            .replace('H', &hour.to_string())
+    }
+
+    /// True if a page name is the title of a journal
+    fn is_journal(&self, name: &str) -> bool {
+        self.title_format.parse(name).is_some()
+    }
+
+    /// The times at the start of the blocks of an outline
+    fn times<'a>(&self, outline: &'a Outline) -> Vec<BlockTime<'a>> {
+        outline
+            .iter()
+            .filter_map(|block| {
+                let (text, minutes) = self.parse(block.first_line())?;
+                Some(BlockTime {
+                    block,
+                    text,
+                    minutes,
+                })
+            })
+            .collect()
+    }
+
+    /// The times not written in the format of the rule
+    fn misformatted<'a>(&self, outline: &'a Outline) -> Vec<(BlockTime<'a>, String)> {
+        self.times(outline)
+            .into_iter()
+            .filter_map(|time| {
+                let formatted = self.format_time(time.minutes);
+                (time.text != formatted).then_some((time, formatted))
+            })
+            .collect()
+    }
+
+    /// The times of sibling blocks earlier than the time of a sibling above, with that time
+    fn out_of_order<'a>(&self, blocks: &'a [Block], found: &mut Vec<(BlockTime<'a>, u32)>) {
+        let mut latest = None;
+        for block in blocks {
+            if let Some((text, minutes)) = self.parse(block.first_line()) {
+                match latest {
+                    Some(previous) if minutes < previous => {
+                        let time = BlockTime {
+                            block,
+                            text,
+                            minutes,
+                        };
+                        found.push((time, previous));
+                    }
+                    _ => latest = Some(minutes),
+                }
+            }
+            self.out_of_order(&block.children, found);
+        }
+    }
+}
+
+impl Rule for BlockTimes {
+    fn id(&self) -> &'static str {
+        "block-times"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Times at the start of blocks written in different formats, and in journals, times out of order.",
+            examples: &["- 9:5 Coffee\n- 10.30am Standup\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.misformatted(outline)
+            .into_iter()
+            .map(|(time, formatted)| {
+                Diagnostic::new(
+                    self.id(),
+                    time.block.line,
+                    time.block.source_column(1),
+                    format!("Write the time {} as {}", time.text, formatted),
+                )
+            })
+            .collect()
+    }
+
+    fn check_named(&self, outline: &Outline, name: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = self.check(outline);
+        if !self.is_journal(name) {
+            return diagnostics;
+        }
+        let mut found = Vec::new();
+        self.out_of_order(&outline.blocks, &mut found);
+        diagnostics.extend(found.into_iter().map(|(time, previous)| {
+            Diagnostic::new(
+                self.id(),
+                time.block.line,
+                time.block.source_column(1),
+                format!(
+                    "{} is before {} of a block above",
+                    self.format_time(time.minutes),
+                    self.format_time(previous)
+                ),
+            )
+        }));
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .misformatted(outline)
+            .into_iter()
+            .map(|(time, formatted)| Replacement {
+                line: time.block.line,
+                range: 0..time.text.len(),
+                content_length: time.block.first_line().len(),
+                text: formatted,
+            })
+            .collect();
+        apply_replacements(text, replacements)
+    }
+
+    fn configure(&mut self, options: &Toml) -> Result<()> {
+        for (_, value) in rule_options(self.id(), options, &["format"])? {
+            match value.as_str() {
+                Some(format) if format.contains('H') && format.contains("MM") => {
+                    self.format = format.to_string()
+                }
+                _ => {
+                    return Err(Error::parse(
+                        None,
+                        "rules.block-times.format must be a string with H or HH, and MM",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn use_graph_config(&mut self, config: &GraphConfig) {
+        self.title_format = DateFormat::new(&config.journal_page_title_format);
+    }
+}
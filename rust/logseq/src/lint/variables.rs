@@ -0,0 +1,132 @@
+//! Rules about the dynamic variables of templates, like `<% today %>`
+
+use crate::lint::{apply_replacements, Diagnostic, Replacement, Rule, RuleDoc};
+use crate::outline::Outline;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::templates::{dynamic_variables, is_known_variable};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static OBSIDIAN_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\{\{\s*(date|time|title)(?::[^{}]*)?\s*\}\}").unwrap());
+
+/// Variables in the `{{date}}` syntax of Obsidian templates, with the ones that replace them
+const OBSIDIAN_VARIABLES: [(&str, &str); 3] = [
+    ("date", "<% today %>"),
+    ("time", "<% time %>"),
+    ("title", "<% current page %>"),
+];
+
+/// Dynamic variables that Logseq inserts as they are instead of replacing them: `<%today%>`
+/// without spaces (fixed to `<% today %>`), unknown names like `<% todya %>` (see
+/// [is_known_variable]), and `{{date}}`, `{{time}}` and `{{title}}` from Obsidian templates
+/// (fixed to `<% today %>`, `<% time %>` and `<% current page %>`; a date format like
+/// `{{date:YYYY-MM-DD}}` is dropped, Logseq uses the date format of the graph).
+pub struct DynamicVariables;
+
+impl DynamicVariables {
+    /// The problems of a line, as (byte range, message, replacement)
+    fn line_problems(line: &str) -> Vec<(std::ops::Range<usize>, String, Option<String>)> {
+        let mut problems = Vec::new();
+        for variable in dynamic_variables(line) {
+            let written = &line[variable.range.clone()];
+            if !is_known_variable(&variable.name) {
+                problems.push((
+                    variable.range,
+                    format!(
+                        "Unknown dynamic variable {}, Logseq inserts it as it is",
+                        written
+                    ),
+                    None,
+                ));
+            } else if !variable.spaced {
+                let spaced = format!(
+                    "<% {} %>",
+                    variable
+                        .name
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                );
+                problems.push((
+                    variable.range,
+                    format!("Write {} as {}, Logseq needs the spaces", written, spaced),
+                    Some(spaced),
+                ));
+            }
+        }
+
+        for captures in OBSIDIAN_RE.captures_iter(&blank_code_spans(line)) {
+            let found = captures.get(0).unwrap();
+            let name = captures[1].to_lowercase();
+            let Some((_, replacement)) = OBSIDIAN_VARIABLES.iter().find(|(key, _)| *key == name)
+            else {
+                continue;
+            };
+            problems.push((
+                found.start()..found.end(),
+                format!(
+                    "{} is an Obsidian template variable, write {} instead",
+                    found.as_str(),
+                    replacement
+                ),
+                Some(replacement.to_string()),
+            ));
+        }
+        problems.sort_by_key(|(range, _, _)| range.start);
+        problems
+    }
+
+    /// The problems of an outline, with their replacements and columns
+    fn problems(&self, outline: &Outline) -> Vec<(Replacement, String, usize, bool)> {
+        let mut problems = Vec::new();
+        for block in outline.iter() {
+            for (line, text) in lines_outside_code(block.numbered_lines()) {
+                for (range, message, replacement) in Self::line_problems(text) {
+                    let column = block.source_column(text[..range.start].chars().count() + 1);
+                    let fixable = replacement.is_some();
+                    let replacement = Replacement {
+                        line,
+                        range,
+                        content_length: text.len(),
+                        text: replacement.unwrap_or_default(),
+                    };
+                    problems.push((replacement, message, column, fixable));
+                }
+            }
+        }
+        problems
+    }
+}
+
+impl Rule for DynamicVariables {
+    fn id(&self) -> &'static str {
+        "dynamic-variables"
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: "Dynamic variables that Logseq inserts as they are, like `<%today%>` or `{{date}}` from Obsidian templates.",
+            examples: &["- Written on <%today%>\n"],
+            fixable: true,
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        self.problems(outline)
+            .into_iter()
+            .map(|(replacement, message, column, _)| {
+                Diagnostic::new(self.id(), replacement.line, column, message)
+            })
+            .collect()
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let replacements = self
+            .problems(outline)
+            .into_iter()
+            .filter(|(_, _, _, fixable)| *fixable)
+            .map(|(replacement, _, _, _)| replacement);
+        apply_replacements(text, replacements.collect())
+    }
+}
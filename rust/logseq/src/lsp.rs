@@ -0,0 +1,434 @@
+//! A minimal language server, so editors can show the problems of a page while it's typed:
+//! lint diagnostics, fixes as code actions, and go to definition for `[[page]]` links.
+//!
+//! It speaks JSON-RPC on any reader and writer (stdin and stdout for `lsd lsp`), with the files
+//! synchronized whole on every change. The graph of the links is the one of the workspace the
+//! client opens, read again when files are saved or change on disk.
+
+use crate::graph::index::GraphIndex;
+use crate::graph::{find_graph_root, percent_decode, Graph};
+use crate::lint::{Diagnostic, RuleSet, Severity};
+use crate::refs::{page_links, tag_refs};
+use crate::{Error, Result};
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// The `source` of the diagnostics published by the server
+pub const SOURCE: &str = "lsd";
+
+/// JSON-RPC error code of requests the server doesn't know
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// The id of the request registering the files the client watches for the server
+const WATCH_REGISTRATION: &str = "lsd-watch-files";
+
+/// Reads a message: a `Content-Length` header, a blank line and the JSON body.
+/// Returns None at the end of the input.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::lsp::{read_message, write_message};
+/// use serde_json::json;
+/// let mut written = Vec::new();
+/// write_message(&mut written, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+/// assert!(written.starts_with(b"Content-Length: 33\r\n\r\n"));
+/// let mut reader = written.as_slice();
+/// assert_eq!(read_message(&mut reader).unwrap().unwrap()["method"], "exit");
+/// assert_eq!(read_message(&mut reader).unwrap(), None);
+/// ```
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let io_error = |source| Error::Io { path: None, source };
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(io_error)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| Error::parse(None, "message without Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(io_error)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|error| Error::parse(None, format!("invalid message: {}", error)))
+}
+
+/// Writes a message with its `Content-Length` header, see [read_message]
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| writer.flush())
+        .map_err(|source| Error::Io { path: None, source })
+}
+
+/// The path of a `file://` URI
+pub fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    uri.strip_prefix("file://")
+        .map(|path| PathBuf::from(percent_decode(path)))
+}
+
+/// The `file://` URI of a path, made absolute
+///
+/// # Examples
+///
+/// ```
+/// use logseq::lsp::{file_uri, path_from_uri};
+/// use std::path::Path;
+/// assert_eq!(file_uri(Path::new("/graph/pages/My page.md")), "file:///graph/pages/My%20page.md");
+/// assert_eq!(path_from_uri("file:///graph/pages/My%20page.md").unwrap(), Path::new("/graph/pages/My page.md"));
+/// ```
+pub fn file_uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// The UTF-16 offset of a character of a line, as positions count them
+fn utf16_offset(line: &str, chars: usize) -> usize {
+    line.chars().take(chars).map(char::len_utf16).sum()
+}
+
+/// The character of a line at a UTF-16 offset
+fn char_index(line: &str, offset: usize) -> usize {
+    let mut units = 0;
+    line.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= offset
+        })
+        .count()
+}
+
+/// The position at the end of a text
+fn end_position(text: &str) -> Value {
+    let last = text.rsplit('\n').next().unwrap_or_default();
+    json!({
+        "line": text.matches('\n').count(),
+        "character": utf16_offset(last, last.chars().count()),
+    })
+}
+
+/// A language server for the pages of a graph
+pub struct Server {
+    rules: RuleSet,
+    graph: Option<Graph>,
+    /// The index of the graph, to read only its changed files again
+    index: Option<GraphIndex>,
+    documents: HashMap<String, String>,
+    /// True if the client can watch files for the server
+    watch_files: bool,
+    exited: bool,
+}
+
+impl Server {
+    /// Constructs a server that checks documents with `rules`, and finds the pages of links in
+    /// `graph`, if given. The graph of the workspace the client opens, if any, replaces it.
+    pub fn new(rules: RuleSet, graph: Option<Graph>) -> Self {
+        Server {
+            rules,
+            graph,
+            index: None,
+            documents: HashMap::new(),
+            watch_files: false,
+            exited: false,
+        }
+    }
+
+    /// Reads the graph of a directory, or of the graph it is in, if any; the graph stays as it
+    /// was if it can't be read
+    fn load_graph(&mut self, path: &Path) {
+        let Some(root) = find_graph_root(path) else {
+            return;
+        };
+        let mut index = GraphIndex::new(&root);
+        if index.update().is_ok() {
+            self.graph = Some(index.to_graph());
+            self.index = Some(index);
+        }
+    }
+
+    /// Reads the changed files of the graph again, and `paths` even if they look unchanged
+    fn refresh_graph(&mut self, paths: &[PathBuf]) {
+        let Some(graph) = &self.graph else {
+            return;
+        };
+        let index = self
+            .index
+            .get_or_insert_with(|| GraphIndex::new(&graph.root));
+        index.invalidate(paths);
+        if index.update().is_ok() {
+            self.graph = Some(index.to_graph());
+        }
+    }
+
+    /// Reads the graph of the workspace of an `initialize` request, and whether the client can
+    /// watch its files
+    fn initialize(&mut self, params: &Value) -> Value {
+        let uri = params["rootUri"]
+            .as_str()
+            .or_else(|| params["workspaceFolders"][0]["uri"].as_str());
+        let root = uri
+            .and_then(path_from_uri)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        if let Some(root) = root {
+            self.load_graph(&root);
+        }
+        let watched = &params["capabilities"]["workspace"]["didChangeWatchedFiles"];
+        self.watch_files = watched["dynamicRegistration"] == true;
+        json!({
+            "capabilities": {
+                "textDocumentSync": {"openClose": true, "change": 1, "save": true},
+                "codeActionProvider": true,
+                "definitionProvider": true,
+            },
+            "serverInfo": {"name": SOURCE, "version": env!("CARGO_PKG_VERSION")},
+        })
+    }
+
+    /// True once the client sent `exit`
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handles messages until the input ends or the client sends `exit`
+    pub fn run(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+        while let Some(message) = read_message(reader)? {
+            for reply in self.handle(&message) {
+                write_message(writer, &reply)?;
+            }
+            if self.exited {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a request or a notification, returning the messages to send back: the response
+    /// of a request, and diagnostics published for changed documents
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // Responses to requests of the server, which only registers the files it watches
+            return Vec::new();
+        };
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let result = match method {
+            "initialize" => self.initialize(params),
+            "initialized" if self.watch_files => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": WATCH_REGISTRATION,
+                    "method": "client/registerCapability",
+                    "params": {"registrations": [{
+                        "id": WATCH_REGISTRATION,
+                        "method": "workspace/didChangeWatchedFiles",
+                        "registerOptions": {"watchers": [{"globPattern": "**/*.md"}]},
+                    }]},
+                })];
+            }
+            "shutdown" => Value::Null,
+            "textDocument/codeAction" => self.code_actions(uri, params),
+            "textDocument/definition" => self.definition(uri, &params["position"]),
+            "exit" => {
+                self.exited = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), text.to_string());
+                if self.graph.is_none() {
+                    if let Some(path) = path_from_uri(uri) {
+                        self.load_graph(&path);
+                    }
+                }
+                return vec![self.publish_diagnostics(uri)];
+            }
+            "textDocument/didSave" => {
+                let paths: Vec<PathBuf> = path_from_uri(uri).into_iter().collect();
+                self.refresh_graph(&paths);
+                return Vec::new();
+            }
+            "workspace/didChangeWatchedFiles" => {
+                let changes = params["changes"].as_array().into_iter().flatten();
+                let paths: Vec<PathBuf> = changes
+                    .filter_map(|change| change["uri"].as_str().and_then(path_from_uri))
+                    .collect();
+                self.refresh_graph(&paths);
+                return Vec::new();
+            }
+            "textDocument/didChange" => {
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return vec![self.publish_diagnostics(uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {"uri": uri, "diagnostics": []},
+                })];
+            }
+            _ if message.get("id").is_none() => return Vec::new(),
+            _ => {
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": message["id"],
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("unknown method {}", method),
+                    },
+                })]
+            }
+        };
+        match message.get("id") {
+            Some(id) => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            None => Vec::new(),
+        }
+    }
+
+    /// The diagnostics of a document, from its lint problems
+    fn publish_diagnostics(&self, uri: &str) -> Value {
+        let text = self.documents.get(uri).map_or("", String::as_str);
+        let lines: Vec<&str> = text.split('\n').collect();
+        let diagnostics: Vec<Value> = self
+            .rules
+            .check(text)
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(diagnostic, &lines))
+            .collect();
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri, "diagnostics": diagnostics},
+        })
+    }
+
+    /// A code action replacing a whole document with its fixed text
+    fn fix_action(&self, uri: &str, title: String, kind: &str, text: &str, fixed: String) -> Value {
+        json!({
+            "title": title,
+            "kind": kind,
+            "edit": {"changes": {uri: [{
+                "range": {"start": {"line": 0, "character": 0}, "end": end_position(text)},
+                "newText": fixed,
+            }]}},
+        })
+    }
+
+    /// The fixes of the rules of the diagnostics in the request, then a fix of every rule
+    fn code_actions(&self, uri: &str, params: &Value) -> Value {
+        let Some(text) = self.documents.get(uri) else {
+            return json!([]);
+        };
+        let mut actions = Vec::new();
+        let mut seen = Vec::new();
+        let diagnostics = params["context"]["diagnostics"].as_array();
+        for diagnostic in diagnostics.into_iter().flatten() {
+            let Some(rule) = diagnostic["code"].as_str() else {
+                continue;
+            };
+            if diagnostic["source"] != SOURCE || seen.contains(&rule) {
+                continue;
+            }
+            seen.push(rule);
+            if let Some(fixed) = self.rules.fix_rule(rule, text) {
+                let title = format!("Fix {} problems", rule);
+                actions.push(self.fix_action(uri, title, "quickfix", text, fixed));
+            }
+        }
+        if let Cow::Owned(fixed) = self.rules.fix(text) {
+            let title = "Fix all auto-fixable problems".to_string();
+            actions.push(self.fix_action(uri, title, "source.fixAll", text, fixed));
+        }
+        Value::Array(actions)
+    }
+
+    /// The page of the `[[link]]` or `#tag` at a position, if it exists in the graph
+    fn definition(&self, uri: &str, position: &Value) -> Value {
+        let (Some(text), Some(graph)) = (self.documents.get(uri), &self.graph) else {
+            return Value::Null;
+        };
+        let line_number = position["line"].as_u64().unwrap_or_default() as usize;
+        let Some(line) = text.split('\n').nth(line_number) else {
+            return Value::Null;
+        };
+        let column = char_index(
+            line,
+            position["character"].as_u64().unwrap_or_default() as usize,
+        );
+        let links = page_links(line).into_iter().map(|link| {
+            let length = link.name.chars().count() + 4;
+            (link, length)
+        });
+        let tags = tag_refs(line).into_iter().map(|tag| {
+            let length = tag.name.chars().count() + 1;
+            (tag, length)
+        });
+        let Some((link, _)) = links.chain(tags).find(|(link, length)| {
+            let start = link.column - 1;
+            (start..=start + length).contains(&column)
+        }) else {
+            return Value::Null;
+        };
+        match graph.find_page(&link.name) {
+            Some(page) => json!({
+                "uri": file_uri(&page.path),
+                "range": {
+                    "start": {"line": 0, "character": 0},
+                    "end": {"line": 0, "character": 0},
+                },
+            }),
+            None => Value::Null,
+        }
+    }
+}
+
+/// A lint diagnostic as a language server diagnostic, up to the end of its line
+fn lsp_diagnostic(diagnostic: &Diagnostic, lines: &[&str]) -> Value {
+    let line_number = diagnostic.line.saturating_sub(1);
+    let line = lines.get(line_number).copied().unwrap_or_default();
+    let line = line.trim_end_matches('\r');
+    let start = utf16_offset(line, diagnostic.column.saturating_sub(1));
+    let end = utf16_offset(line, line.chars().count()).max(start);
+    json!({
+        "range": {
+            "start": {"line": line_number, "character": start},
+            "end": {"line": line_number, "character": end},
+        },
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+            Severity::Info => 3,
+        },
+        "code": diagnostic.rule,
+        "source": SOURCE,
+        "message": diagnostic.message,
+    })
+}
@@ -0,0 +1,367 @@
+//! Convert regular Markdown documents into Logseq outlines, whole files or documents pasted
+//! inside a block
+
+use crate::error;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::IndentUnit;
+use crate::outline::{indent_width, parse_outline, Block};
+use crate::properties::split_property;
+use crate::rename::{write_edits, FileEdit};
+use crate::Result;
+use regex::Regex;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static HEADING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}(#{1,6})\s+(.*?)(?:\s+#+)?\s*$").unwrap());
+static ITEM_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\s*)(?:[-*+]|\d+[.)])(?:\s+(.*))?$").unwrap());
+static BREAK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}([-*_])(\s*[-*_]){2,}\s*$").unwrap());
+static DRAWER_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^:[A-Za-z][\w-]*:$").unwrap());
+
+/// Indentation added for each level of the outline
+const INDENT: &str = "  ";
+
+/// A line of the outline of a Markdown document
+enum OutlineLine {
+    /// A bullet at a level, with its text
+    Bullet(usize, String),
+    /// A continuation line of the bullet at a level
+    Continuation(usize, String),
+    /// A line kept as it is, outside the outline
+    Verbatim(String),
+}
+
+/// Builds the lines of the outline, one bullet at a time
+struct OutlineWriter {
+    lines: Vec<OutlineLine>,
+}
+
+impl OutlineWriter {
+    fn bullet(&mut self, level: usize, text: &str) {
+        self.lines
+            .push(OutlineLine::Bullet(level, text.trim().to_string()));
+    }
+
+    fn continuation(&mut self, level: usize, text: &str) {
+        self.lines
+            .push(OutlineLine::Continuation(level, text.to_string()));
+    }
+
+    fn verbatim(&mut self, text: &str) {
+        self.lines.push(OutlineLine::Verbatim(text.to_string()));
+    }
+}
+
+/// Split a list item into its indentation width and content, for `-`, `*`, `+` and `1.` items
+fn split_list_item(line: &str) -> Option<(usize, String)> {
+    let captures = ITEM_RE.captures(line)?;
+    let width = indent_width(&captures[1]);
+    let content = captures.get(2).map_or("", |m| m.as_str());
+    Some((width, content.trim().to_string()))
+}
+
+/// Convert flat Markdown into a Logseq outline
+///
+/// Headings become bullets nested by their level, and paragraphs and lists are nested under the
+/// nearest heading. Each line of a paragraph becomes its own bullet.
+/// Front matter and thematic breaks are kept as they are; fenced code blocks and
+/// `#+BEGIN_...`/`#+END_...` blocks are kept inside a single bullet.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::markdown::flat_markdown_to_outline;
+/// let flat = "#  Header\n\nSome paragraph.\n\n## Sub\n- Parent\n  - Child\n";
+/// assert_eq!(flat_markdown_to_outline(flat),
+///     "- # Header\n  - Some paragraph.\n  - ## Sub\n    - Parent\n      - Child\n");
+/// assert_eq!(flat_markdown_to_outline("No header.\nSecond line."), "- No header.\n- Second line.\n");
+/// ```
+pub fn flat_markdown_to_outline(markdown_contents: &str) -> String {
+    let lines: Vec<String> = flat_markdown_lines(markdown_contents)
+        .into_iter()
+        .map(|line| match line {
+            OutlineLine::Bullet(level, text) => format!("{}- {}", INDENT.repeat(level), text),
+            OutlineLine::Continuation(level, text) => {
+                format!("{}{}{}", INDENT.repeat(level), INDENT, text)
+            }
+            OutlineLine::Verbatim(text) => text,
+        })
+        .collect();
+    let mut outline = lines.join("\n");
+    if !outline.is_empty() {
+        outline.push('\n');
+    }
+    outline
+}
+
+/// The lines of the outline of flat Markdown, see [flat_markdown_to_outline]
+fn flat_markdown_lines(markdown_contents: &str) -> Vec<OutlineLine> {
+    let mut writer = OutlineWriter { lines: Vec::new() };
+    let mut heading_level = 0;
+    // Indentation widths of the open list items, to compute their nesting
+    let mut list_widths: Vec<usize> = Vec::new();
+    let mut fence: Option<(String, usize)> = None;
+    let mut lines = markdown_contents.lines().peekable();
+
+    // Front matter is kept verbatim
+    if lines.peek().is_some_and(|line| line.trim_end() == "---") {
+        writer.verbatim(lines.next().unwrap_or_default());
+        for line in lines.by_ref() {
+            writer.verbatim(line);
+            if line.trim_end() == "---" {
+                break;
+            }
+        }
+    }
+
+    for line in lines {
+        if let Some((marker, level)) = &fence {
+            writer.continuation(*level, line);
+            if line.trim_start().starts_with(marker.as_str()) {
+                fence = None;
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let level = heading_level + list_widths.len();
+            writer.bullet(level, trimmed);
+            fence = Some((trimmed[..3].to_string(), level));
+            continue;
+        }
+        if trimmed.starts_with("#+BEGIN_") {
+            let level = heading_level + list_widths.len();
+            writer.bullet(level, trimmed);
+            fence = Some(("#+END_".to_string(), level));
+            continue;
+        }
+
+        if let Some(captures) = HEADING_RE.captures(line) {
+            list_widths.clear();
+            let hashes = &captures[1];
+            heading_level = hashes.len();
+            writer.bullet(heading_level - 1, &format!("{} {}", hashes, &captures[2]));
+            continue;
+        }
+
+        if BREAK_RE.is_match(line) {
+            list_widths.clear();
+            writer.verbatim(trimmed);
+            continue;
+        }
+
+        if let Some((width, content)) = split_list_item(line) {
+            while list_widths.last().is_some_and(|&w| w >= width) {
+                list_widths.pop();
+            }
+            let level = heading_level + list_widths.len();
+            list_widths.push(width);
+            writer.bullet(level, &content);
+            continue;
+        }
+
+        if !list_widths.is_empty() && indent_width(line) > 0 {
+            // An indented line right after a list item continues that item
+            let level = heading_level + list_widths.len() - 1;
+            writer.continuation(level, trimmed);
+            continue;
+        }
+
+        list_widths.clear();
+        writer.bullet(heading_level, trimmed);
+    }
+    writer.lines
+}
+
+/// The number of lines at the start of a block, after its first line, that stay with it: its
+/// properties, `SCHEDULED:` and `DEADLINE:` lines and drawers
+fn leading_lines(lines: &[&str]) -> usize {
+    let mut count = 0;
+    let mut in_drawer = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if in_drawer {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+        } else if DRAWER_RE.is_match(trimmed) {
+            in_drawer = true;
+        } else if split_property(line).is_none()
+            && !trimmed.starts_with("SCHEDULED:")
+            && !trimmed.starts_with("DEADLINE:")
+        {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+/// True if the lines of a block, after its first line and properties, are a pasted document:
+/// they have headings, or paragraphs separated by blank lines
+fn is_pasted_document(lines: &[&str]) -> bool {
+    let mut in_fence = false;
+    let mut blank = false;
+    for line in lines {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if in_fence {
+            continue;
+        } else if trimmed.is_empty() {
+            blank = true;
+            continue;
+        } else if HEADING_RE.is_match(line) {
+            return true;
+        }
+        if blank {
+            return true;
+        }
+    }
+    false
+}
+
+/// The lines replacing a block and its descendants when it is a pasted document, see
+/// [explode_pasted_documents]
+fn explode_block(block: &Block, unit: IndentUnit, source: &[&str]) -> Option<Vec<String>> {
+    let lines: Vec<&str> = block.content.split('\n').collect();
+    let first = lines[0];
+    let trimmed = first.trim_start();
+    if trimmed.starts_with("```") || BREAK_RE.is_match(first) || split_property(first).is_some() {
+        return None;
+    }
+    let kept = 1 + leading_lines(&lines[1..]);
+    if !is_pasted_document(&lines[kept..]) {
+        return None;
+    }
+    // The document, with the descendants of the block as its last list
+    let mut document: Vec<String> = std::iter::once(first)
+        .chain(lines[kept..].iter().copied())
+        .map(String::from)
+        .collect();
+    for descendant in block.iter().skip(1) {
+        let depth = descendant.level - block.level;
+        let mut content = descendant.content.split('\n');
+        let first = content.next().unwrap_or_default();
+        document.push(format!("{}- {}", INDENT.repeat(depth - 1), first));
+        document.extend(content.map(|line| format!("{}{}", INDENT.repeat(depth), line)));
+    }
+    let outline = flat_markdown_lines(&document.join("\n"));
+    let Some(OutlineLine::Bullet(..)) = outline.first() else {
+        return None;
+    };
+
+    // Levels are shifted so that the first heading and paragraphs after the first line become
+    // children of the block, and headings of the first line's level or above stay under it
+    let heading = |text: &str| HEADING_RE.captures(text).map(|captures| captures[1].len());
+    let shift = match heading(first) {
+        Some(level) => level,
+        None => outline[1..]
+            .iter()
+            .filter_map(|line| match line {
+                OutlineLine::Bullet(_, text) => heading(text),
+                _ => None,
+            })
+            .min()
+            .map_or(0, |level| level - 1),
+    };
+    let indent = |level: usize| unit.indent(block.level + (level + 1).saturating_sub(shift).max(1));
+    let mut exploded: Vec<String> = source[block.line - 1..block.line - 1 + kept]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    for line in &outline[1..] {
+        match line {
+            OutlineLine::Bullet(level, text) => {
+                exploded.push(
+                    format!("{}- {}", indent(*level), text)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+            OutlineLine::Continuation(level, text) => {
+                exploded.push(
+                    format!("{}  {}", indent(*level), text)
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+            OutlineLine::Verbatim(_) => {}
+        }
+    }
+    Some(exploded)
+}
+
+/// Explodes the blocks of a page that are pasted documents, with headings, or paragraphs
+/// separated by blank lines, into nested child blocks with [flat_markdown_to_outline]:
+/// headings nest by their level, and paragraphs, lists and the children of the block under the
+/// nearest heading. The first line of the block stays in it, with its properties; thematic
+/// breaks are dropped.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::markdown::explode_pasted_documents;
+/// let text = "- # Report\n  id:: 65a1b2c3\n  Intro.\n  \n  ## Findings\n  Found it.\n\t- One\n\t\t- Two\n- Other\n";
+/// assert_eq!(
+///     explode_pasted_documents(text),
+///     "- # Report\n  id:: 65a1b2c3\n\t- Intro.\n\t- ## Findings\n\t\t- Found it.\n\t\t- One\n\t\t\t- Two\n- Other\n"
+/// );
+/// ```
+pub fn explode_pasted_documents(text: &str) -> Cow<'_, str> {
+    preserving_line_endings_if_changed(text, |text| {
+        let outline = parse_outline(text);
+        let unit = outline
+            .iter()
+            .find(|block| block.level == 1)
+            .and_then(|block| IndentUnit::detect(&block.indent))
+            .unwrap_or(IndentUnit::Tab);
+        let source: Vec<&str> = text.split('\n').collect();
+        // The exploded blocks, as (first line index, end line index, lines)
+        let mut exploded: Vec<(usize, usize, Vec<String>)> = Vec::new();
+        for block in outline.iter() {
+            let start = block.line - 1;
+            if exploded.last().is_some_and(|(_, end, _)| start < *end) {
+                continue;
+            }
+            if let Some(lines) = explode_block(block, unit, &source) {
+                let count: usize = block.iter().map(Block::line_count).sum();
+                exploded.push((start, start + count, lines));
+            }
+        }
+        if exploded.is_empty() {
+            return None;
+        }
+        let mut lines: Vec<String> = source.iter().map(|line| line.to_string()).collect();
+        for (start, end, replacement) in exploded.into_iter().rev() {
+            lines.splice(start..end, replacement);
+        }
+        Some(lines.join("\n"))
+    })
+}
+
+/// Explodes the pasted documents of files, see [explode_pasted_documents]; with `dry_run`,
+/// only plans the changes. Returns the files that change.
+pub fn explode_files(paths: &[PathBuf], dry_run: bool) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for path in paths {
+        let original = error::read_to_string(path)?;
+        if let Cow::Owned(updated) = explode_pasted_documents(&original) {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,241 @@
+//! Merge a page into another one: usually two pages for the same thing, created with different
+//! names
+
+use crate::graph::{page_key, Graph, GraphPage};
+use crate::outline::{parse_outline, Outline};
+use crate::properties::{insert_page_properties, split_list, split_property, strip_ref, Value};
+use crate::rename::{rewrite_references, write_edits, FileEdit};
+use crate::{error, undo, Error, Result};
+use std::path::PathBuf;
+
+/// Everything a merge changes: the target page, the files that refer to the source page, and
+/// the source file, which is deleted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageMerge {
+    /// The file of the page merged into the target, deleted by the merge
+    pub source: PathBuf,
+    /// The file of the page that receives the blocks
+    pub target: PathBuf,
+    /// Files rewritten by the merge, the target included, sorted by path
+    pub edits: Vec<FileEdit>,
+}
+
+impl PageMerge {
+    /// Applies the merge. All rewritten files are written to temporary files first, so a
+    /// failure leaves the graph untouched; they then replace the originals, and the source file
+    /// is deleted last.
+    pub fn apply(&self) -> Result<()> {
+        write_edits(&self.edits)?;
+        undo::delete_file(&self.source)
+    }
+}
+
+/// Finds a page that isn't a journal by its exact name (case-insensitively)
+fn find_named_page<'a>(graph: &'a Graph, name: &str) -> Result<&'a GraphPage> {
+    graph
+        .find_page(name)
+        .filter(|page| page.journal_date.is_none() && page_key(&page.name) == page_key(name))
+        .ok_or_else(|| Error::parse(None, format!("page not found: {}", name)))
+}
+
+/// The line where the blocks of a page start, after its properties when they are the first
+/// block (0-based; the number of lines when the page has no blocks)
+fn blocks_start(page: &GraphPage, line_count: usize) -> usize {
+    let Some(first) = page.outline.blocks.first() else {
+        return line_count;
+    };
+    let only_properties = first
+        .numbered_lines()
+        .all(|(_, text)| split_property(text).is_some());
+    match only_properties && page.outline.preamble.is_empty() {
+        true => first
+            .numbered_lines()
+            .last()
+            .map_or(first.line, |(line, _)| line),
+        false => first.line - 1,
+    }
+}
+
+/// Appends values to the property on a line of a page given as lines, after a comma unless
+/// its value is empty
+fn append_values(line: &mut String, raw: &str, values: &[String]) {
+    let ending = match line.ends_with('\r') {
+        true => "\r",
+        false => "",
+    };
+    let text = line.trim_end_matches('\r').trim_end().to_string();
+    let separator = if raw.trim().is_empty() { " " } else { ", " };
+    *line = format!("{}{}{}{}", text, separator, values.join(", "), ending);
+}
+
+/// Adds names to the `alias::` of a page given as lines: to its existing `alias::`, or else as
+/// a new page property in `new`
+fn add_aliases(page: &GraphPage, lines: &mut [String], names: &[String], new: &mut Vec<String>) {
+    if names.is_empty() {
+        return;
+    }
+    match page.outline.page_properties().get("alias") {
+        Some(alias) => append_values(&mut lines[alias.line - 1], &alias.raw, names),
+        None => new.push(format!("alias:: {}", names.join(", "))),
+    }
+}
+
+/// Merges the page properties of the source, given by its `outline` with the references
+/// rewritten, into the ones of the target given as lines: the missing ones are added to `new`,
+/// and the references of a property both have, like `tags::`, are added to the target's. The
+/// target keeps its other values, and its `title::`; `alias::` is merged by [add_aliases].
+fn add_page_properties(
+    source: &GraphPage,
+    outline: &Outline,
+    target: &GraphPage,
+    lines: &mut [String],
+    new: &mut Vec<String>,
+) {
+    let existing = target.outline.page_properties();
+    let own_names = [page_key(&source.name), page_key(&target.name)];
+    for property in outline.page_properties().iter() {
+        let key = property.key.to_lowercase();
+        if key == "alias" || key == "title" {
+            continue;
+        }
+        let Some(kept) = existing.get(&property.key) else {
+            new.push(format!("{}:: {}", property.key, property.raw.trim()));
+            continue;
+        };
+        let (Value::Refs(_), Value::Refs(kept_refs)) = (&property.value, &kept.value) else {
+            continue;
+        };
+        let mut known: Vec<String> = kept_refs.iter().map(|name| page_key(name)).collect();
+        let mut added = Vec::new();
+        for part in split_list(&property.raw) {
+            let name = page_key(&strip_ref(part));
+            if !known.contains(&name) && !own_names.contains(&name) {
+                known.push(name);
+                added.push(part.to_string());
+            }
+        }
+        if !added.is_empty() {
+            append_values(&mut lines[kept.line - 1], &kept.raw, &added);
+        }
+    }
+}
+
+/// Plans the merge of the page `source` into the page `target`: the blocks of the source are
+/// appended to the target as they are (with their ids and properties), the name and aliases of
+/// the source become aliases of the target, and every reference to the source is rewritten to
+/// the target, ignoring code. The other page properties of the source are added to the target:
+/// the ones it doesn't have, and the missing references of the ones both have, like `tags::`;
+/// the target keeps its own values of the others.
+/// Nothing is written; see [PageMerge::apply].
+/// Fails if either page doesn't exist or is a journal, or if they are the same page.
+pub fn plan_merge(graph: &Graph, source: &str, target: &str) -> Result<PageMerge> {
+    let source = find_named_page(graph, source)?;
+    let target = find_named_page(graph, target)?;
+    if source.path == target.path {
+        return Err(Error::parse(
+            Some(&source.path),
+            "can't merge a page into itself",
+        ));
+    }
+
+    let source_key = page_key(&source.name);
+    let mut edits = Vec::new();
+    for other in graph.pages.iter().filter(|other| other.path != source.path) {
+        let original = error::read_to_string(&other.path)?;
+        let mut updated = rewrite_references(other, &original, &source_key, &target.name, false);
+        if other.path == target.path {
+            updated = merged_target(source, target, &original, &updated)?;
+        }
+        if updated != original {
+            edits.push(FileEdit {
+                path: other.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(PageMerge {
+        source: source.path.clone(),
+        target: target.path.clone(),
+        edits,
+    })
+}
+
+/// The text of the target of a merge, from its `original` text and the one with its references
+/// `rewritten`: with the aliases and page properties of the source, and the blocks of the
+/// source at the end
+fn merged_target(
+    source: &GraphPage,
+    target: &GraphPage,
+    original: &str,
+    rewritten: &str,
+) -> Result<String> {
+    let mut lines: Vec<String> = rewritten.split('\n').map(String::from).collect();
+    let properties = target.outline.page_properties();
+    // The existing aliases of the target stay as they are, even the name of the source
+    if let Some(alias) = properties.get("alias") {
+        lines[alias.line - 1] = original
+            .split('\n')
+            .nth(alias.line - 1)
+            .unwrap()
+            .to_string();
+    }
+    let mut known: Vec<String> = std::iter::once(target.name.clone())
+        .chain(target.aliases())
+        .map(|name| page_key(&name))
+        .collect();
+    let mut aliases = Vec::new();
+    for name in std::iter::once(source.name.clone()).chain(source.aliases()) {
+        if !known.contains(&page_key(&name)) {
+            known.push(page_key(&name));
+            aliases.push(name);
+        }
+    }
+
+    let source_text = error::read_to_string(&source.path)?;
+    let source_text = rewrite_references(
+        source,
+        &source_text,
+        &page_key(&source.name),
+        &target.name,
+        false,
+    );
+    let source_lines: Vec<&str> = source_text.split('\n').collect();
+    let mut new = Vec::new();
+    add_aliases(target, &mut lines, &aliases, &mut new);
+    add_page_properties(
+        source,
+        &parse_outline(&source_text),
+        target,
+        &mut lines,
+        &mut new,
+    );
+    insert_page_properties(&target.outline, &mut lines, &new);
+    let blocks = source_lines[blocks_start(source, source_lines.len())..].join("\n");
+    let blocks = blocks.trim_end();
+    if blocks.is_empty() {
+        return Ok(lines.join("\n"));
+    }
+    let mut merged = lines.join("\n").trim_end().to_string();
+    let ending = if original.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    if !merged.is_empty() {
+        merged.push_str(ending);
+    }
+    merged.push_str(blocks);
+    merged.push_str(ending);
+    Ok(merged)
+}
+
+/// Merges the page `source` into the page `target`, see [plan_merge]; with `dry_run`, only
+/// plans the changes
+pub fn merge_pages(graph: &Graph, source: &str, target: &str, dry_run: bool) -> Result<PageMerge> {
+    let merge = plan_merge(graph, source, target)?;
+    if !dry_run {
+        merge.apply()?;
+    }
+    Ok(merge)
+}
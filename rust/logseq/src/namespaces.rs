@@ -0,0 +1,242 @@
+//! Check the hierarchy of namespaced pages (`project/sub/page`): missing parent pages, and
+//! file names that encode `/` differently from the rest of the graph
+
+use crate::config::GraphConfig;
+use crate::graph::{file_stem_from_page_name, page_key, percent_decode, Graph};
+use crate::{error, undo, Error, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How the `/` of a namespaced page name is written in its file name, which changed across
+/// Logseq versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Separator {
+    /// `project___sub.md`, the `:triple-lowbar` file name format
+    TripleLowbar,
+    /// `project%2Fsub.md`, from legacy graphs
+    PercentEncoded,
+    /// `project.sub.md` with a `title:: project/sub` property, from the oldest graphs
+    Dot,
+}
+
+impl Separator {
+    /// How the separator is written in file names
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Separator::TripleLowbar => "___",
+            Separator::PercentEncoded => "%2F",
+            Separator::Dot => ".",
+        }
+    }
+
+    /// The separator used by the file name of a namespaced page, or None if the page isn't
+    /// namespaced or its file name doesn't encode the `/`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::namespaces::Separator;
+    /// assert_eq!(Separator::of_file("a___b", "a/b"), Some(Separator::TripleLowbar));
+    /// assert_eq!(Separator::of_file("a%2fb", "a/b"), Some(Separator::PercentEncoded));
+    /// assert_eq!(Separator::of_file("a.b", "a/b"), Some(Separator::Dot));
+    /// assert_eq!(Separator::of_file("v1.2", "v1.2"), None);
+    /// ```
+    pub fn of_file(stem: &str, name: &str) -> Option<Separator> {
+        if !name.contains('/') {
+            None
+        } else if stem.contains("___") {
+            Some(Separator::TripleLowbar)
+        } else if stem.to_ascii_uppercase().contains("%2F") {
+            Some(Separator::PercentEncoded)
+        } else if percent_decode(stem).replace('.', "/") == name {
+            Some(Separator::Dot)
+        } else {
+            None
+        }
+    }
+
+    /// The file name (without extension) of a page with this separator
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::namespaces::Separator;
+    /// assert_eq!(Separator::PercentEncoded.file_stem("a/b?"), "a%2Fb%3F");
+    /// ```
+    pub fn file_stem(&self, name: &str) -> String {
+        file_stem_from_page_name(name).replace("___", self.as_str())
+    }
+}
+
+impl fmt::Display for Separator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// What's wrong with a namespaced page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespaceProblem {
+    /// A parent of the page has no file, e.g. `project` for `project/sub`
+    MissingParent {
+        /// The name of the parent page
+        parent: String,
+        /// Where a stub page for the parent would be created
+        stub: PathBuf,
+    },
+    /// The file name encodes `/` differently from the rest of the graph
+    InconsistentSeparator {
+        /// The separator of the file name
+        found: Separator,
+        /// The separator of the graph
+        expected: Separator,
+        /// The path the file should be renamed to
+        rename_to: PathBuf,
+    },
+}
+
+/// A namespaced page with a problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceIssue {
+    /// The file of the page
+    pub path: PathBuf,
+    /// The name of the page
+    pub page: String,
+    /// What's wrong with it
+    pub problem: NamespaceProblem,
+}
+
+/// The separator namespaced file names should use: `___` for graphs with the `:triple-lowbar`
+/// file name format, otherwise the one most of the graph's files use (`___` if none do)
+fn graph_separator(config: &GraphConfig, separators: &[Separator]) -> Separator {
+    if config.file_name_format == "triple-lowbar" {
+        return Separator::TripleLowbar;
+    }
+    let candidates = [
+        Separator::TripleLowbar,
+        Separator::PercentEncoded,
+        Separator::Dot,
+    ];
+    let count = |candidate: &Separator| separators.iter().filter(|s| *s == candidate).count();
+    // max_by_key keeps the last of equal elements, so the order of preference is reversed
+    candidates
+        .into_iter()
+        .rev()
+        .max_by_key(count)
+        .unwrap_or(Separator::TripleLowbar)
+}
+
+/// Checks the namespaced pages of a graph: each parent of a namespaced page (`project` and
+/// `project/sub` for `project/sub/page`) should have a page of its own, or an alias, and every
+/// file name should encode `/` the same way, see [Separator]. Each missing parent is reported
+/// once, for the first of its children.
+pub fn check_namespaces(graph: &Graph) -> Result<Vec<NamespaceIssue>> {
+    let config = GraphConfig::read(&graph.root)?;
+    let pages: Vec<_> = graph
+        .pages
+        .iter()
+        .filter(|page| page.journal_date.is_none() && page.name.contains('/'))
+        .map(|page| {
+            let stem = page
+                .path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (page, Separator::of_file(&stem, &page.name))
+        })
+        .collect();
+    let separators: Vec<Separator> = pages.iter().filter_map(|(_, found)| *found).collect();
+    let expected = graph_separator(&config, &separators);
+    let pages_directory = graph.root.join(&config.pages_directory);
+
+    let mut issues = Vec::new();
+    let mut reported = HashSet::new();
+    for (page, found) in pages {
+        let parents = page
+            .name
+            .match_indices('/')
+            .map(|(slash, _)| page.name[..slash].to_string());
+        for parent in parents {
+            if graph.find_page(&parent).is_some() || !reported.insert(page_key(&parent)) {
+                continue;
+            }
+            let stub = pages_directory.join(format!("{}.md", expected.file_stem(&parent)));
+            issues.push(NamespaceIssue {
+                path: page.path.clone(),
+                page: page.name.clone(),
+                problem: NamespaceProblem::MissingParent { parent, stub },
+            });
+        }
+        match found {
+            Some(found) if found != expected => {
+                let rename_to = page
+                    .path
+                    .with_file_name(format!("{}.md", expected.file_stem(&page.name)));
+                issues.push(NamespaceIssue {
+                    path: page.path.clone(),
+                    page: page.name.clone(),
+                    problem: NamespaceProblem::InconsistentSeparator {
+                        found,
+                        expected,
+                        rename_to,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(issues)
+}
+
+/// Creates a stub page, with only a `title::` property, for each missing parent of the issues.
+/// Returns the paths of the created files.
+pub fn create_parent_stubs(issues: &[NamespaceIssue]) -> Result<Vec<PathBuf>> {
+    let mut created = Vec::new();
+    for issue in issues {
+        let NamespaceProblem::MissingParent { parent, stub } = &issue.problem else {
+            continue;
+        };
+        if stub.exists() {
+            continue;
+        }
+        if let Some(directory) = stub.parent() {
+            fs::create_dir_all(directory).map_err(|source| Error::io(directory, source))?;
+        }
+        error::write(stub, &format!("title:: {}\n", parent))?;
+        created.push(stub.clone());
+    }
+    Ok(created)
+}
+
+/// Renames the files of the issues whose separator isn't the graph's.
+/// Nothing is renamed if any new name is already taken, or if two files would get the same name.
+/// Returns the (old, new) paths of the renamed files.
+pub fn reencode_files(issues: &[NamespaceIssue]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let renames: Vec<(&Path, &Path)> = issues
+        .iter()
+        .filter_map(|issue| match &issue.problem {
+            NamespaceProblem::InconsistentSeparator { rename_to, .. } => {
+                Some((issue.path.as_path(), rename_to.as_path()))
+            }
+            _ => None,
+        })
+        .collect();
+    for (index, (path, new_path)) in renames.iter().enumerate() {
+        let taken_by_other = renames[..index].iter().any(|(_, other)| other == new_path);
+        if taken_by_other || new_path.exists() {
+            return Err(Error::parse(
+                Some(path),
+                format!("can't rename to {}, it already exists", new_path.display()),
+            ));
+        }
+    }
+    for (path, new_path) in &renames {
+        undo::move_file(path, new_path)?;
+    }
+    Ok(renames
+        .into_iter()
+        .map(|(path, new_path)| (path.to_path_buf(), new_path.to_path_buf()))
+        .collect())
+}
@@ -0,0 +1,76 @@
+//! Write page names in one Unicode normalization: file names and links in NFC
+//!
+//! macOS writes file names decomposed (NFD, `e` followed by a combining accent), while text
+//! typed in Logseq is usually composed (NFC, a single `é`). Logseq matches them anyway, but
+//! sync tools and other programs see two different names.
+
+use crate::graph::Graph;
+use crate::lint::{LinkNormalization, Rule};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, undo, Error, Result};
+use std::path::PathBuf;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Everything a normalization changes: the files it renames and the links it rewrites
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Normalization {
+    /// The (old, new) paths of the files whose name isn't NFC, sorted by path
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    /// The files with links, tags or aliases that aren't NFC, sorted by path
+    pub edits: Vec<FileEdit>,
+}
+
+impl Normalization {
+    /// True if the graph is already normalized
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty() && self.edits.is_empty()
+    }
+
+    /// Applies the normalization. The rewritten files are written to temporary files first, so
+    /// a failure leaves them untouched; the files are renamed last.
+    pub fn apply(&self) -> Result<()> {
+        write_edits(&self.edits)?;
+        for (path, new_path) in &self.renames {
+            undo::move_file(path, new_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans the normalization of a graph to NFC: the pages and journals whose file name isn't
+/// NFC are renamed, and the `[[links]]`, `#tags` and `tags::`/`alias::` entries that aren't
+/// are rewritten, see [LinkNormalization]. Nothing is written; see [Normalization::apply].
+/// Fails if the NFC name of a file is already taken by another file of the graph.
+pub fn plan_normalization(graph: &Graph) -> Result<Normalization> {
+    let mut normalization = Normalization::default();
+    for page in &graph.pages {
+        let Some(name) = page.path.file_name().map(|name| name.to_string_lossy()) else {
+            continue;
+        };
+        if !is_nfc(&name) {
+            let new_path = page.path.with_file_name(name.nfc().collect::<String>());
+            let taken = graph.pages.iter().any(|other| other.path == new_path)
+                || normalization
+                    .renames
+                    .iter()
+                    .any(|(_, other)| *other == new_path);
+            if taken {
+                return Err(Error::parse(
+                    Some(&page.path),
+                    format!("can't rename to {}, it already exists", new_path.display()),
+                ));
+            }
+            normalization.renames.push((page.path.clone(), new_path));
+        }
+
+        let original = error::read_to_string(&page.path)?;
+        if let Some(updated) = LinkNormalization.fix(&original, &page.outline) {
+            normalization.edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(normalization)
+}
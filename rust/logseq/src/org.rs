@@ -0,0 +1,252 @@
+//! Convert Logseq pages written in org-mode into Logseq-flavored Markdown
+
+use crate::graph::collect_files;
+use crate::{error, undo, Error, Result, SUBDIR_JOURNALS, SUBDIR_PAGES};
+use regex::{Captures, Regex};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Regular expressions used during the conversion, compiled once
+struct OrgSyntax {
+    headline: Regex,
+    drawer_property: Regex,
+    keyword: Regex,
+    link: Regex,
+    bold: Regex,
+    italic: Regex,
+    code: Regex,
+    strike: Regex,
+    tags: Regex,
+}
+
+static ORG_SYNTAX: LazyLock<OrgSyntax> = LazyLock::new(OrgSyntax::new);
+
+impl OrgSyntax {
+    fn new() -> Self {
+        OrgSyntax {
+            headline: Regex::new(r"^(\*+)\s+(.*)$").unwrap(),
+            drawer_property: Regex::new(r"^\s*:([A-Za-z0-9_\-.]+):\s*(.*)$").unwrap(),
+            keyword: Regex::new(r"^#\+([A-Za-z_]+):\s*(.*)$").unwrap(),
+            link: Regex::new(r"\[\[([^\]]+)\](?:\[([^\]]*)\])?\]").unwrap(),
+            bold: Regex::new(r"(^|[\s(])\*([^*\s](?:[^*]*[^*\s])?)\*($|[\s).,;:!?])").unwrap(),
+            italic: Regex::new(r"(^|[\s(])/([^/\s](?:[^/]*[^/\s])?)/($|[\s).,;:!?])").unwrap(),
+            code: Regex::new(r"(^|[\s(])[=~]([^=~\s](?:[^=~]*[^=~\s])?)[=~]($|[\s).,;:!?])")
+                .unwrap(),
+            strike: Regex::new(r"(^|[\s(])\+([^+\s](?:[^+]*[^+\s])?)\+($|[\s).,;:!?])").unwrap(),
+            tags: Regex::new(r"\s+:([\w@#%:]+):\s*$").unwrap(),
+        }
+    }
+
+    /// Converts links and emphasis of a line of text
+    fn inline(&self, text: &str) -> String {
+        let linked = self.link.replace_all(text, |captures: &Captures| {
+            let target = &captures[1];
+            let description = captures.get(2).map(|m| m.as_str());
+            convert_link(target, description)
+        });
+        let coded = self.code.replace_all(&linked, "$1`$2`$3");
+        let bold = self.bold.replace_all(&coded, "$1**$2**$3");
+        let italic = self.italic.replace_all(&bold, "$1*$2*$3");
+        self.strike.replace_all(&italic, "$1~~$2~~$3").to_string()
+    }
+
+    /// Converts the text of a headline, moving trailing `:tag1:tag2:` to `#tag1 #tag2`
+    fn headline_text(&self, text: &str) -> String {
+        match self.tags.captures(text) {
+            Some(captures) => {
+                let tags: Vec<String> = captures[1]
+                    .split(':')
+                    .filter(|tag| !tag.is_empty())
+                    .map(|tag| format!("#{}", tag))
+                    .collect();
+                let title = &text[..captures.get(0).unwrap().start()];
+                format!("{} {}", self.inline(title), tags.join(" "))
+            }
+            None => self.inline(text),
+        }
+    }
+}
+
+fn convert_link(target: &str, description: Option<&str>) -> String {
+    if let Some(path) = target.strip_prefix("file:") {
+        let label = description.unwrap_or_else(|| path.rsplit('/').next().unwrap_or(path));
+        return format!("[{}]({})", label, path);
+    }
+    let external = target.contains("://") || target.starts_with("mailto:");
+    match (description, external) {
+        (Some(description), true) => format!("[{}]({})", description, target),
+        (None, true) => format!("<{}>", target),
+        (Some(description), false) => format!("[{}]([[{}]])", description, target),
+        (None, false) => format!("[[{}]]", target),
+    }
+}
+
+/// Convert the contents of an org-mode page to Logseq Markdown
+///
+/// Headlines become nested bullets (TODO keywords, priorities and timestamps are kept),
+/// `:PROPERTIES:` drawers become `key:: value` properties, `#+TITLE:`-style keywords before the
+/// first headline become page properties, and links, emphasis and source blocks are converted.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::org::org_to_markdown;
+/// let org = "#+TITLE: Reading\n* TODO [#A] Read *this* book :books:\n  :PROPERTIES:\n  :ID: 123\n  :END:\n  SCHEDULED: <2024-01-02 Tue>\n** See [[https://example.com][the site]] and [[Other page]]";
+/// assert_eq!(org_to_markdown(org),
+///     "title:: Reading\n\n- TODO [#A] Read **this** book #books\n  id:: 123\n  SCHEDULED: <2024-01-02 Tue>\n\t- See [the site](https://example.com) and [[Other page]]\n");
+/// ```
+pub fn org_to_markdown(org_contents: &str) -> String {
+    let syntax = &*ORG_SYNTAX;
+    let mut output: Vec<String> = Vec::new();
+    // Indentation of the continuation lines of the current headline, None before the first one
+    let mut continuation: Option<String> = None;
+    let mut in_drawer = false;
+    let mut in_properties = false;
+    let mut in_source = false;
+    let mut had_preamble = false;
+
+    for line in org_contents.lines() {
+        let trimmed = line.trim();
+        let prefix = continuation.clone().unwrap_or_default();
+
+        if in_source {
+            if trimmed.eq_ignore_ascii_case("#+END_SRC") {
+                in_source = false;
+                output.push(format!("{}```", prefix));
+            } else {
+                output.push(format!("{}{}", prefix, line));
+            }
+            continue;
+        }
+
+        if let Some(captures) = syntax.headline.captures(line) {
+            if continuation.is_none() && had_preamble {
+                output.push(String::new());
+            }
+            let level = captures[1].len() - 1;
+            let indent = "\t".repeat(level);
+            output.push(format!(
+                "{}- {}",
+                indent,
+                syntax.headline_text(&captures[2])
+            ));
+            continuation = Some(format!("{}  ", indent));
+            in_properties = false;
+            in_drawer = false;
+            continue;
+        }
+
+        if trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+            in_properties = true;
+            continue;
+        }
+        if in_properties || in_drawer {
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                if in_drawer {
+                    output.push(format!("{}{}", prefix, trimmed));
+                }
+                in_properties = false;
+                in_drawer = false;
+                continue;
+            }
+            if in_properties {
+                if let Some(captures) = syntax.drawer_property.captures(line) {
+                    output.push(format!(
+                        "{}{}:: {}",
+                        prefix,
+                        captures[1].to_lowercase(),
+                        captures[2].trim()
+                    ));
+                    had_preamble = true;
+                }
+            } else {
+                output.push(format!("{}{}", prefix, trimmed));
+            }
+            continue;
+        }
+        if trimmed.starts_with(':') && trimmed.ends_with(':') && trimmed.len() > 2 {
+            // Other drawers, like :LOGBOOK:, are understood by Logseq in Markdown as well
+            in_drawer = true;
+            output.push(format!("{}{}", prefix, trimmed));
+            continue;
+        }
+
+        if let Some(language) = trimmed
+            .get(..11)
+            .filter(|start| start.eq_ignore_ascii_case("#+BEGIN_SRC"))
+            .map(|_| trimmed[11..].trim())
+        {
+            in_source = true;
+            output.push(format!("{}```{}", prefix, language));
+            continue;
+        }
+
+        if continuation.is_none() {
+            if let Some(captures) = syntax.keyword.captures(trimmed) {
+                output.push(format!(
+                    "{}:: {}",
+                    captures[1].to_lowercase(),
+                    captures[2].trim()
+                ));
+                had_preamble = true;
+                continue;
+            }
+        }
+
+        if trimmed.is_empty() {
+            if continuation.is_none() {
+                output.push(String::new());
+            }
+            continue;
+        }
+
+        let text = syntax.inline(trimmed);
+        if continuation.is_none() {
+            // Text before the first headline becomes a block of its own
+            output.push(format!("- {}", text));
+            continuation = Some("  ".to_string());
+        } else {
+            output.push(format!("{}{}", prefix, text));
+        }
+    }
+
+    let mut markdown = output.join("\n");
+    if !markdown.is_empty() {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// Convert every `.org` page and journal of a graph into a `.md` file next to it.
+/// Nothing is converted if any org file already has a Markdown counterpart.
+/// When `delete_originals` is true, the org files are removed after conversion.
+/// Returns the (org, markdown) paths of the converted files.
+pub fn migrate_graph(root: &Path, delete_originals: bool) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut org_files = Vec::new();
+    for subdir in [SUBDIR_PAGES, SUBDIR_JOURNALS] {
+        collect_files(&root.join(subdir), "org", &mut org_files)?;
+    }
+    org_files.sort();
+
+    if let Some(conflict) = org_files
+        .iter()
+        .find(|org_path| org_path.with_extension("md").exists())
+    {
+        return Err(Error::parse(
+            Some(conflict),
+            "a Markdown file with the same name already exists",
+        ));
+    }
+
+    let mut migrated = Vec::new();
+    for org_path in org_files {
+        let markdown_path = org_path.with_extension("md");
+        let contents = error::read_to_string(&org_path)?;
+        error::write(&markdown_path, &org_to_markdown(&contents))?;
+        if delete_originals {
+            undo::delete_file(&org_path)?;
+        }
+        migrated.push((org_path, markdown_path));
+    }
+    Ok(migrated)
+}
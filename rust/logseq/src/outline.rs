@@ -0,0 +1,456 @@
+//! Parse Logseq Markdown pages into a tree of blocks
+
+use crate::line_ending::{LineEnding, TextStyle};
+use serde::{Deserialize, Serialize};
+
+/// Width of a tab character when comparing indentation of bullets
+const TAB_WIDTH: usize = 2;
+
+/// A parsed Logseq page: the lines before the first bullet, followed by a tree of blocks
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Outline {
+    /// Lines before the first bullet (e.g. page properties written without a dash)
+    pub preamble: Vec<String>,
+    /// Top-level blocks of the page
+    pub blocks: Vec<Block>,
+    /// The dominant line ending of the page and whether it ends with a line break, for
+    /// [crate::render]
+    #[serde(default)]
+    pub style: TextStyle,
+    /// The line ending of each line of the preamble, when they aren't all the dominant one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preamble_line_endings: Vec<LineEnding>,
+}
+
+/// A single Logseq block (a bullet) with its nested children
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    /// Line number of the bullet in the source, starting at 1
+    pub line: usize,
+    /// Nesting level, 0 for top-level blocks
+    pub level: usize,
+    /// Leading whitespace of the bullet line, exactly as in the source
+    pub indent: String,
+    /// The bullet character (usually `-`)
+    pub marker: char,
+    /// Whitespace between the bullet and the content, exactly as in the source: a space, a tab,
+    /// or nothing after a bare bullet
+    #[serde(
+        default = "default_separator",
+        skip_serializing_if = "is_default_separator"
+    )]
+    pub separator: String,
+    /// Text after the bullet, including continuation lines joined with `\n`
+    pub content: String,
+    /// Line numbers of the continuation lines, outside code, that aren't indented like Logseq
+    /// writes them: the indent of the bullet plus two spaces
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub misindented_lines: Vec<usize>,
+    /// The whitespace before each continuation line, exactly as in the source, which the
+    /// content leaves out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub continuation_indents: Vec<String>,
+    /// The line ending of each line of the content, when they aren't all the dominant one of
+    /// the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_endings: Vec<LineEnding>,
+    /// Nested blocks
+    pub children: Vec<Block>,
+}
+
+fn default_separator() -> String {
+    " ".to_string()
+}
+
+fn is_default_separator(separator: &str) -> bool {
+    separator == " "
+}
+
+impl Outline {
+    /// Iterates over all blocks of the outline, depth-first, in the order they appear in the source
+    pub fn iter(&self) -> Blocks<'_> {
+        Blocks {
+            stack: self.blocks.iter().rev().collect(),
+        }
+    }
+}
+
+impl Block {
+    /// Iterates over this block and all its descendants, depth-first
+    pub fn iter(&self) -> Blocks<'_> {
+        Blocks { stack: vec![self] }
+    }
+
+    /// The first line of the block content
+    pub fn first_line(&self) -> &str {
+        self.content.lines().next().unwrap_or("")
+    }
+
+    /// Lines of the block content paired with their line numbers in the source
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.content
+            .split('\n')
+            .enumerate()
+            .map(move |(offset, text)| (self.line + offset, text))
+    }
+
+    /// Converts a column inside the block content (starting at 1) into a column of the source line,
+    /// accounting for the indentation and the bullet (or the continuation indent) before the content
+    pub fn source_column(&self, content_column: usize) -> usize {
+        self.indent.chars().count() + 2 + content_column
+    }
+
+    /// The level of a `# Heading` block, from 1 to 6, or None if the block isn't a heading
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::outline::parse_outline;
+    /// let outline = parse_outline("- ## Plans\n- #tag");
+    /// assert_eq!(outline.blocks[0].heading_level(), Some(2));
+    /// assert_eq!(outline.blocks[1].heading_level(), None);
+    /// ```
+    pub fn heading_level(&self) -> Option<usize> {
+        let line = self.first_line();
+        let level = line.chars().take_while(|&c| c == '#').count();
+        let after = line[level..].chars().next();
+        ((1..=6).contains(&level) && matches!(after, None | Some(' '))).then_some(level)
+    }
+
+    /// Number of physical lines the block content spans in the source
+    pub fn line_count(&self) -> usize {
+        self.content.split('\n').count()
+    }
+}
+
+/// Depth-first iterator over blocks, created by [`Outline::iter`] and [`Block::iter`]
+pub struct Blocks<'a> {
+    stack: Vec<&'a Block>,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = &'a Block;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.stack.pop()?;
+        self.stack.extend(block.children.iter().rev());
+        Some(block)
+    }
+}
+
+/// Width of the leading whitespace of a line, counting tabs as [`TAB_WIDTH`] columns
+pub fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Split a line into its leading whitespace, bullet marker and content, if it is a bullet
+fn split_bullet(line: &str) -> Option<(&str, char, &str, &str)> {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+    let mut chars = trimmed.chars();
+    let marker = chars.next()?;
+    if !matches!(marker, '-' | '*' | '+') {
+        return None;
+    }
+    let rest = chars.as_str();
+    if rest.is_empty() {
+        return Some((indent, marker, "", ""));
+    }
+    rest.strip_prefix(' ')
+        .or_else(|| rest.strip_prefix('\t'))
+        .map(|content| (indent, marker, &rest[..1], content))
+}
+
+fn is_fence(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// The content of a line without its bullet, if it has one, and the whitespace around it
+pub(crate) fn bullet_content(line: &str) -> &str {
+    split_bullet(line).map_or(line.trim(), |(_, _, _, content)| content.trim())
+}
+
+/// True if a line is marked `<!-- lsd-ignore -->`
+pub(crate) fn is_ignored_line(line: &str) -> bool {
+    line.contains("<!-- lsd-ignore -->")
+}
+
+/// Parse the contents of a Logseq Markdown page into an [`Outline`]
+///
+/// Lines that are not bullets are attached as continuation lines to the previous block, whatever
+/// their indentation, so a block and its continuation lines are always fixed together.
+/// Lines inside fenced code blocks, `#+BEGIN_SRC` blocks, `:LOGBOOK:` drawers and
+/// `<!-- lsd-ignore-start -->` spans are never treated as bullets, nor as misindented: they
+/// belong to the block they start in, as they are.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::parse_outline;
+/// let outline = parse_outline("- Parent\n  - Child\n    more text\n- Sibling");
+/// assert_eq!(outline.blocks.len(), 2);
+/// assert_eq!(outline.blocks[0].content, "Parent");
+/// assert_eq!(outline.blocks[0].children[0].content, "Child\nmore text");
+/// assert_eq!(outline.blocks[0].children[0].level, 1);
+/// assert_eq!(outline.blocks[1].line, 4);
+///
+/// let outline = parse_outline("- Run\n  #+BEGIN_SRC markdown\n  - not a block\n  #+END_SRC\n");
+/// assert_eq!(outline.blocks[0].content, "Run\n#+BEGIN_SRC markdown\n- not a block\n#+END_SRC");
+/// assert!(outline.blocks[0].children.is_empty());
+/// ```
+pub fn parse_outline(text: &str) -> Outline {
+    let mut preamble = Vec::new();
+    let mut flat: Vec<Block> = Vec::new();
+    let mut preamble_line_endings = Vec::new();
+    let mut widths: Vec<usize> = Vec::new();
+    // The region whose lines are left as they are, from a line that opens it to the one that
+    // closes it
+    let mut open: Option<RegionKind> = None;
+    let style = TextStyle::detect(text);
+
+    for (index, (raw_line, ending)) in lines_with_endings(text, style.line_ending).enumerate() {
+        let bullet = match open {
+            Some(_) => None,
+            None => split_bullet(raw_line),
+        };
+        match bullet {
+            Some((indent, marker, separator, content)) => {
+                let width = indent_width(indent);
+                while widths.last().is_some_and(|&w| w >= width) {
+                    widths.pop();
+                }
+                let level = widths.len();
+                widths.push(width);
+                open = RegionKind::opened_by(content);
+                flat.push(Block {
+                    line: index + 1,
+                    level,
+                    indent: indent.to_string(),
+                    marker,
+                    separator: separator.to_string(),
+                    content: content.to_string(),
+                    misindented_lines: Vec::new(),
+                    continuation_indents: Vec::new(),
+                    line_endings: vec![ending],
+                    children: Vec::new(),
+                });
+            }
+            None => {
+                let content = bullet_content(raw_line);
+                let in_code = match open {
+                    Some(kind) => {
+                        if kind.closed_by(content) {
+                            open = None;
+                        }
+                        true
+                    }
+                    None => {
+                        open = RegionKind::opened_by(content);
+                        open.is_some()
+                    }
+                };
+                match flat.last_mut() {
+                    Some(block) => {
+                        let aligned = raw_line
+                            .strip_prefix(block.indent.as_str())
+                            .is_some_and(|rest| rest.starts_with("  "));
+                        if !in_code && !aligned && !raw_line.trim().is_empty() {
+                            block.misindented_lines.push(index + 1);
+                        }
+                        let continuation = strip_continuation_indent(raw_line, &block.indent);
+                        let indent = &raw_line[..raw_line.len() - continuation.len()];
+                        block.continuation_indents.push(indent.to_string());
+                        block.content.push('\n');
+                        block.content.push_str(continuation);
+                        block.line_endings.push(ending);
+                    }
+                    None => {
+                        preamble.push(raw_line.to_string());
+                        preamble_line_endings.push(ending);
+                    }
+                }
+            }
+        }
+    }
+
+    // Most pages use a single line ending, which the style records for all their lines
+    let uniform = |endings: &mut Vec<LineEnding>| {
+        if endings.iter().all(|&ending| ending == style.line_ending) {
+            endings.clear();
+        }
+    };
+    uniform(&mut preamble_line_endings);
+    for block in &mut flat {
+        uniform(&mut block.line_endings);
+    }
+    let mut iter = flat.into_iter().peekable();
+    let blocks = build_tree(&mut iter, 0);
+    Outline {
+        preamble,
+        blocks,
+        style,
+        preamble_line_endings,
+    }
+}
+
+/// The lines of a text like [str::lines], each with its line ending; the last line, when it has
+/// none, gets `last`
+fn lines_with_endings(text: &str, last: LineEnding) -> impl Iterator<Item = (&str, LineEnding)> {
+    text.split_inclusive('\n').map(move |line| {
+        if let Some(line) = line.strip_suffix("\r\n") {
+            (line, LineEnding::CrLf)
+        } else if let Some(line) = line.strip_suffix('\n') {
+            (line, LineEnding::Lf)
+        } else {
+            (line, last)
+        }
+    })
+}
+
+/// Remove the indentation Logseq adds to continuation lines: the bullet indent plus two spaces
+fn strip_continuation_indent<'a>(line: &'a str, block_indent: &str) -> &'a str {
+    if let Some(rest) = line.strip_prefix(block_indent) {
+        if let Some(rest) = rest.strip_prefix("  ") {
+            return rest;
+        }
+        return rest.trim_start();
+    }
+    line.trim_start()
+}
+
+fn build_tree<I: Iterator<Item = Block>>(
+    iter: &mut std::iter::Peekable<I>,
+    level: usize,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    while let Some(next) = iter.peek() {
+        if next.level < level {
+            break;
+        }
+        let mut block = iter.next().expect("peeked block");
+        block.children = build_tree(iter, level + 1);
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// A kind of region of a page that lint fixes leave alone, see [protected_regions]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RegionKind {
+    /// A ```` ``` ```` or `~~~` fenced code block, fences included
+    CodeFence,
+    /// An Org-mode `#+BEGIN_SRC` ... `#+END_SRC` block
+    SourceBlock,
+    /// A `:LOGBOOK:` ... `:END:` drawer
+    Logbook,
+    /// YAML front matter between `---` lines at the top of the page
+    FrontMatter,
+    /// Lines marked `<!-- lsd-ignore -->`, or between `<!-- lsd-ignore-start -->` and
+    /// `<!-- lsd-ignore-end -->`
+    Ignored,
+}
+
+impl RegionKind {
+    /// The kind of the region a line opens, given its content without the bullet; a line
+    /// marked `<!-- lsd-ignore -->` alone isn't one, see [protected_regions]
+    pub(crate) fn opened_by(content: &str) -> Option<Self> {
+        let content = content.trim();
+        let upper = content.to_ascii_uppercase();
+        if is_fence(content) {
+            Some(RegionKind::CodeFence)
+        } else if upper.starts_with("#+BEGIN_SRC") {
+            Some(RegionKind::SourceBlock)
+        } else if upper == ":LOGBOOK:" {
+            Some(RegionKind::Logbook)
+        } else if content.contains("<!-- lsd-ignore-start -->") {
+            Some(RegionKind::Ignored)
+        } else {
+            None
+        }
+    }
+
+    /// True if a line, given by its content without the bullet, closes a region of this kind
+    pub(crate) fn closed_by(self, content: &str) -> bool {
+        let content = content.trim();
+        let upper = content.to_ascii_uppercase();
+        match self {
+            RegionKind::CodeFence => is_fence(content),
+            RegionKind::SourceBlock => upper.starts_with("#+END_SRC"),
+            RegionKind::Logbook => upper == ":END:",
+            RegionKind::FrontMatter => content == "---",
+            RegionKind::Ignored => content.contains("<!-- lsd-ignore-end -->"),
+        }
+    }
+}
+
+/// Lines of a page that lint fixes leave alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectedRegion {
+    /// What the lines are
+    pub kind: RegionKind,
+    /// Line numbers, starting at 1
+    pub lines: std::ops::RangeInclusive<usize>,
+}
+
+/// The protected regions of a page, in the order of their first line. A region that isn't
+/// closed goes on to the end of the page, but front matter, which needs its closing `---`.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::{protected_regions, RegionKind};
+/// let text = "- Run\n  ```sh\n  ls\n  ```\n- Done <!-- lsd-ignore -->\n  :LOGBOOK:\n  CLOCK: [2024-01-02 Tue 10:00]\n  :END:\n";
+/// let regions: Vec<_> = protected_regions(text).into_iter().map(|r| (r.kind, r.lines)).collect();
+/// assert_eq!(regions, vec![
+///     (RegionKind::CodeFence, 2..=4),
+///     (RegionKind::Ignored, 5..=5),
+///     (RegionKind::Logbook, 6..=8),
+/// ]);
+/// ```
+pub fn protected_regions(text: &str) -> Vec<ProtectedRegion> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut regions = Vec::new();
+    let mut open: Option<(RegionKind, usize)> = None;
+    if lines.first().is_some_and(|line| line.trim_end() == "---") {
+        if let Some(end) = lines[1..].iter().position(|line| line.trim_end() == "---") {
+            regions.push(ProtectedRegion {
+                kind: RegionKind::FrontMatter,
+                lines: 1..=end + 2,
+            });
+        }
+    }
+    let start = regions.first().map_or(0, |region| *region.lines.end());
+    for (index, raw_line) in lines.iter().enumerate().skip(start) {
+        let number = index + 1;
+        // The content of the line without the bullet, to find `- ```` ` and `- :LOGBOOK:`
+        let content = bullet_content(raw_line);
+        if let Some((kind, first)) = open {
+            if kind.closed_by(content) {
+                regions.push(ProtectedRegion {
+                    kind,
+                    lines: first..=number,
+                });
+                open = None;
+            }
+            continue;
+        }
+        open = RegionKind::opened_by(content).map(|kind| (kind, number));
+        if open.is_none() && is_ignored_line(raw_line) {
+            regions.push(ProtectedRegion {
+                kind: RegionKind::Ignored,
+                lines: number..=number,
+            });
+        }
+    }
+    if let Some((kind, first)) = open {
+        regions.push(ProtectedRegion {
+            kind,
+            lines: first..=lines.len(),
+        });
+    }
+    regions
+}
@@ -0,0 +1,273 @@
+//! Lint rules loaded from WebAssembly modules, so third parties can ship rules without
+//! recompiling logseq-doctor. Enabled by the `plugins` feature, which runs the modules with
+//! [wasmi](https://docs.rs/wasmi); list them in the `plugins` setting of `.lsd.toml`, see
+//! [LintConfig](crate::lint::LintConfig).
+//!
+//! A plugin is a `.wasm` module (or its `.wat` text) that imports nothing and exports:
+//!
+//! - `memory`: its linear memory
+//! - `alloc(len: i32) -> i32`: the address of `len` free bytes, where the host writes the
+//!   content of a block before each call of `check` or `fix`, so plugins can reuse one buffer
+//! - `id() -> i64`: the id of the rule, in kebab-case
+//! - `description() -> i64`, optional: what the rule finds, in a sentence
+//! - `check(ptr: i32, len: i32) -> i64`: the problems of the block whose content is at `ptr`,
+//!   one per line, as `line:column:message`, or `message` alone for the bullet of the block.
+//!   Lines start at 1 at the bullet, columns at 1 at the first character of the content.
+//! - `fix(ptr: i32, len: i32) -> i64`, optional: the fixed content of the block
+//!
+//! Strings are UTF-8, and those returned are packed into an `i64` as `ptr << 32 | len`. The
+//! content of a block is its text after the bullet, continuation lines included, without
+//! their indentation.
+//!
+//! Each call gets [FUEL_PER_CALL] units of fuel, so a plugin stuck in a loop fails instead of
+//! hanging the linter. A plugin that fails is reported as a problem of the block it failed on.
+
+use crate::lint::{Diagnostic, Rule, RuleDoc};
+use crate::outline::{Block, Outline};
+use crate::{Error, Result};
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use wasmi::{Config, Engine, Instance, Linker, Memory, Module, Store, TypedFunc};
+
+/// How many units of fuel, roughly instructions, a plugin runs for on each call at most
+pub const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// A lint rule run by a WebAssembly module, see the [module docs](self)
+pub struct WasmRule {
+    id: &'static str,
+    description: String,
+    plugin: Mutex<Plugin>,
+    fixable: bool,
+}
+
+/// An instance of a plugin, with the exports the host calls
+struct Plugin {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    check: TypedFunc<(i32, i32), i64>,
+    fix: Option<TypedFunc<(i32, i32), i64>>,
+}
+
+impl Plugin {
+    /// Runs an export on a string, returning the string it returns
+    fn call(&mut self, function: TypedFunc<(i32, i32), i64>, input: &str) -> Result<String> {
+        let len = i32::try_from(input.len()).map_err(failed)?;
+        self.store.set_fuel(FUEL_PER_CALL).map_err(failed)?;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(failed)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+            .map_err(failed)?;
+        let packed = function.call(&mut self.store, (ptr, len)).map_err(failed)?;
+        self.read(packed)
+    }
+
+    /// The string at a packed `ptr << 32 | len`
+    fn read(&self, packed: i64) -> Result<String> {
+        let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        let bytes = self
+            .memory
+            .data(&self.store)
+            .get(ptr..ptr + len)
+            .ok_or_else(|| Error::parse(None, "the plugin returned a string out of its memory"))?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| Error::parse(None, "the plugin returned a string that isn't UTF-8"))
+    }
+}
+
+impl WasmRule {
+    /// Loads a plugin from a `.wasm` or `.wat` file
+    pub fn load(path: &Path) -> Result<Self> {
+        let wasm = std::fs::read(path).map_err(|source| Error::io(path, source))?;
+        WasmRule::new(&wasm).map_err(|error| error.with_path(path))
+    }
+
+    /// Loads a plugin from the bytes of a `.wasm` module or from its `.wat` text
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{Rule, RuleSet};
+    /// use logseq::plugins::WasmRule;
+    /// let wat = r#"(module
+    ///     (memory (export "memory") 1)
+    ///     (data (i32.const 0) "no-empty" "Empty block")
+    ///     (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+    ///     (func (export "id") (result i64) (i64.const 8))
+    ///     (func (export "check") (param i32 i32) (result i64)
+    ///         (if (result i64) (local.get 1)
+    ///             (then (i64.const 0))
+    ///             (else (i64.const 0x8_0000000b)))))"#;
+    /// let rule = WasmRule::new(wat.as_bytes()).unwrap();
+    /// assert_eq!(rule.id(), "no-empty");
+    /// let rules = RuleSet::new().with(rule);
+    /// let diagnostics = rules.check("- a\n-\n");
+    /// assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 3));
+    /// assert_eq!(diagnostics[0].message, "Empty block");
+    /// assert!(WasmRule::new(b"(module)").is_err());
+    /// ```
+    pub fn new(wasm: &[u8]) -> Result<Self> {
+        let invalid =
+            |error: wasmi::Error| Error::parse(None, format!("invalid plugin: {}", error));
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(invalid)?;
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(FUEL_PER_CALL).map_err(invalid)?;
+        let instance = Linker::new(&engine)
+            .instantiate_and_start(&mut store, &module)
+            .map_err(invalid)?;
+        let missing =
+            |name: &str| Error::parse(None, format!("the plugin doesn't export {}", name));
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| missing("memory"))?;
+        let id = typed::<(), i64>(&instance, &store, "id").ok_or_else(|| missing("id() -> i64"))?;
+        let description = typed::<(), i64>(&instance, &store, "description");
+        let mut plugin = Plugin {
+            alloc: typed(&instance, &store, "alloc").ok_or_else(|| missing("alloc(i32) -> i32"))?,
+            check: typed(&instance, &store, "check")
+                .ok_or_else(|| missing("check(i32, i32) -> i64"))?,
+            fix: typed(&instance, &store, "fix"),
+            memory,
+            store,
+        };
+        let mut string = |function: TypedFunc<(), i64>| {
+            let packed = function.call(&mut plugin.store, ()).map_err(invalid)?;
+            plugin.read(packed)
+        };
+        let id = string(id)?;
+        let description = description.map(string).transpose()?.unwrap_or_default();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(Error::parse(
+                None,
+                format!("the id of the plugin must be in kebab-case, not {:?}", id),
+            ));
+        }
+        Ok(WasmRule {
+            // Rule ids are static, and plugins are loaded once per run
+            id: Box::leak(id.into_boxed_str()),
+            description,
+            fixable: plugin.fix.is_some(),
+            plugin: Mutex::new(plugin),
+        })
+    }
+
+    /// The problems the plugin finds in a block, or the error it failed with
+    fn check_block(&self, plugin: &mut Plugin, block: &Block) -> Result<Vec<Diagnostic>> {
+        let found = plugin.call(plugin.check, &block.content)?;
+        let at = |line: usize, column: usize, message: &str| {
+            Diagnostic::new(
+                self.id,
+                block.line + line - 1,
+                block.source_column(column),
+                message,
+            )
+        };
+        let mut diagnostics = Vec::new();
+        for problem in found.lines().filter(|line| !line.is_empty()) {
+            let mut parts = problem.splitn(3, ':');
+            let position = (parts.next(), parts.next(), parts.next());
+            diagnostics.push(match position {
+                (Some(line), Some(column), Some(message)) => {
+                    match (line.parse::<usize>(), column.parse::<usize>()) {
+                        (Ok(line @ 1..), Ok(column @ 1..)) => at(line, column, message),
+                        _ => at(1, 1, problem),
+                    }
+                }
+                _ => at(1, 1, problem),
+            });
+        }
+        Ok(diagnostics)
+    }
+}
+
+/// The exported function of an instance with the given name, if it has the given signature
+fn typed<Params: wasmi::WasmParams, Results: wasmi::WasmResults>(
+    instance: &Instance,
+    store: &Store<()>,
+    name: &str,
+) -> Option<TypedFunc<Params, Results>> {
+    instance.get_typed_func(store, name).ok()
+}
+
+/// The error of a plugin that failed while it ran
+fn failed(error: impl fmt::Display) -> Error {
+    Error::parse(None, error.to_string())
+}
+
+impl Rule for WasmRule {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn doc(&self) -> RuleDoc<'_> {
+        RuleDoc {
+            description: &self.description,
+            fixable: self.fixable,
+            ..RuleDoc::default()
+        }
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        let mut plugin = self.plugin.lock().unwrap();
+        let mut diagnostics = Vec::new();
+        for block in outline.iter() {
+            match self.check_block(&mut plugin, block) {
+                Ok(found) => diagnostics.extend(found),
+                Err(error) => {
+                    let message = format!("The plugin failed: {}", error);
+                    diagnostics.push(Diagnostic::new(
+                        self.id,
+                        block.line,
+                        block.source_column(1),
+                        message,
+                    ));
+                    break;
+                }
+            }
+        }
+        diagnostics
+    }
+
+    fn fix(&self, text: &str, outline: &Outline) -> Option<String> {
+        let mut plugin = self.plugin.lock().unwrap();
+        let fix = plugin.fix?;
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut changed = false;
+        // Later blocks first, so that the line numbers of earlier ones stay valid
+        let blocks: Vec<&Block> = outline.iter().collect();
+        for block in blocks.into_iter().rev() {
+            let Ok(fixed) = plugin.call(fix, &block.content) else {
+                return None;
+            };
+            if fixed == block.content {
+                continue;
+            }
+            let continuation = format!("{}  ", block.indent);
+            let replacement = fixed
+                .split('\n')
+                .enumerate()
+                .map(|(index, content)| match index {
+                    0 => format!(
+                        "{}{}{}{}",
+                        block.indent, block.marker, block.separator, content
+                    ),
+                    _ => {
+                        let indent = block.continuation_indents.get(index - 1);
+                        format!("{}{}", indent.unwrap_or(&continuation), content)
+                    }
+                });
+            let start = block.line - 1;
+            lines.splice(start..start + block.line_count(), replacement);
+            changed = true;
+        }
+        changed.then(|| lines.join("\n"))
+    }
+}
@@ -0,0 +1,347 @@
+//! Parse Logseq `key:: value` page and block properties
+
+use crate::outline::{Block, Outline};
+use chrono::NaiveDate;
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+/// Properties whose values are always a comma-separated list of page references
+pub(crate) const REF_LIST_KEYS: [&str; 2] = ["tags", "alias"];
+
+/// A typed property value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Plain text
+    Text(String),
+    /// A whole number
+    Integer(i64),
+    /// A decimal number
+    Float(f64),
+    /// `true` or `false`
+    Bool(bool),
+    /// A date written as `YYYY-MM-DD`
+    Date(NaiveDate),
+    /// One or more page references (`[[page]]`, `#tag` or plain names for `tags::` and `alias::`)
+    Refs(Vec<String>),
+}
+
+impl Value {
+    /// Coerces the raw text of a property into a typed value
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::properties::Value;
+    /// use chrono::NaiveDate;
+    /// assert_eq!(Value::parse("rating", "5"), Value::Integer(5));
+    /// assert_eq!(Value::parse("price", "9.5"), Value::Float(9.5));
+    /// assert_eq!(Value::parse("public", "true"), Value::Bool(true));
+    /// assert_eq!(Value::parse("started", "2024-01-02"), Value::Date(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()));
+    /// assert_eq!(Value::parse("tags", "book, [[sci fi]], #reading"),
+    ///     Value::Refs(vec!["book".to_string(), "sci fi".to_string(), "reading".to_string()]));
+    /// assert_eq!(Value::parse("author", "[[Ursula K. Le Guin]]"), Value::Refs(vec!["Ursula K. Le Guin".to_string()]));
+    /// assert_eq!(Value::parse("title", "The Dispossessed"), Value::Text("The Dispossessed".to_string()));
+    /// ```
+    pub fn parse(key: &str, raw: &str) -> Self {
+        let raw = raw.trim();
+        if REF_LIST_KEYS.contains(&key.to_lowercase().as_str()) {
+            return Value::Refs(split_list(raw).into_iter().map(strip_ref).collect());
+        }
+        if let Some(refs) = parse_refs(raw) {
+            return Value::Refs(refs);
+        }
+        if let Ok(integer) = raw.parse::<i64>() {
+            return Value::Integer(integer);
+        }
+        if let Ok(float) = raw.parse::<f64>() {
+            if raw.chars().any(|c| c.is_ascii_digit()) && !raw.contains(char::is_alphabetic) {
+                return Value::Float(float);
+            }
+        }
+        match raw {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            _ => {}
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+            return Value::Date(date);
+        }
+        Value::Text(raw.to_string())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Float(float) => write!(f, "{}", float),
+            Value::Bool(bool) => write!(f, "{}", bool),
+            Value::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Value::Refs(refs) => {
+                let formatted: Vec<String> = refs
+                    .iter()
+                    .map(|name| {
+                        if name.contains(' ') {
+                            format!("[[{}]]", name)
+                        } else {
+                            name.clone()
+                        }
+                    })
+                    .collect();
+                write!(f, "{}", formatted.join(", "))
+            }
+        }
+    }
+}
+
+pub(crate) fn split_list(raw: &str) -> Vec<&str> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+pub(crate) fn strip_ref(part: &str) -> String {
+    let part = part.trim();
+    if let Some(inner) = part.strip_prefix("#[[").and_then(|p| p.strip_suffix("]]")) {
+        return inner.to_string();
+    }
+    if let Some(inner) = part.strip_prefix("[[").and_then(|p| p.strip_suffix("]]")) {
+        return inner.to_string();
+    }
+    part.strip_prefix('#').unwrap_or(part).to_string()
+}
+
+/// Parses a value made only of comma-separated `[[refs]]` or `#tags`
+fn parse_refs(raw: &str) -> Option<Vec<String>> {
+    let parts = split_list(raw);
+    let is_ref = |part: &&str| {
+        (part.starts_with("[[") && part.ends_with("]]"))
+            || (part.starts_with('#') && part.len() > 1 && !part.contains(' '))
+    };
+    if parts.is_empty() || !parts.iter().all(is_ref) {
+        return None;
+    }
+    Some(parts.into_iter().map(strip_ref).collect())
+}
+
+/// A single `key:: value` property
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    /// The key, as written in the source
+    pub key: String,
+    /// The value, as written in the source
+    pub raw: String,
+    /// The typed value
+    pub value: Value,
+    /// Line number in the source, starting at 1 (0 for properties not read from a file)
+    pub line: usize,
+}
+
+/// An ordered collection of properties
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Properties {
+    entries: Vec<Property>,
+}
+
+static PROPERTY_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_\-./]*)::(?:\s+(.*))?$").unwrap());
+
+/// Splits a line into a property key and raw value, if it is a property line
+///
+/// # Examples
+///
+/// ```
+/// use logseq::properties::split_property;
+/// assert_eq!(split_property("id:: 65a1b2c3"), Some(("id", "65a1b2c3")));
+/// assert_eq!(split_property("  collapsed:: true"), Some(("collapsed", "true")));
+/// assert_eq!(split_property("empty::"), Some(("empty", "")));
+/// assert_eq!(split_property("not a property:: value"), None);
+/// assert_eq!(split_property("a::b"), None);
+/// ```
+pub fn split_property(line: &str) -> Option<(&str, &str)> {
+    let captures = PROPERTY_RE.captures(line.trim())?;
+    let key = captures.get(1)?.as_str();
+    let raw = captures.get(2).map_or("", |m| m.as_str());
+    Some((key, raw))
+}
+
+impl Properties {
+    /// Constructs an empty collection of properties
+    pub fn new() -> Self {
+        Properties::default()
+    }
+
+    /// Parses every property line of the text; other lines are ignored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::properties::{Properties, Value};
+    /// let properties = Properties::parse("Some block\nid:: 123\ntags:: a, b");
+    /// assert_eq!(properties.len(), 2);
+    /// assert_eq!(properties.get("ID").unwrap().line, 2);
+    /// assert_eq!(properties.value("tags"), Some(&Value::Refs(vec!["a".to_string(), "b".to_string()])));
+    /// assert_eq!(properties.to_string(), "id:: 123\ntags:: a, b");
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        Self::parse_lines(text.lines().enumerate().map(|(i, line)| (i + 1, line)))
+    }
+
+    /// Parses the property lines among the given (line number, text) pairs
+    pub fn parse_lines<'a>(lines: impl IntoIterator<Item = (usize, &'a str)>) -> Self {
+        let entries = lines
+            .into_iter()
+            .filter_map(|(line, text)| {
+                split_property(text).map(|(key, raw)| Property {
+                    key: key.to_string(),
+                    raw: raw.to_string(),
+                    value: Value::parse(key, raw),
+                    line,
+                })
+            })
+            .collect();
+        Properties { entries }
+    }
+
+    /// Returns the property with the given key (case-insensitive)
+    pub fn get(&self, key: &str) -> Option<&Property> {
+        self.entries
+            .iter()
+            .find(|property| property.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns the typed value of the property with the given key (case-insensitive)
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        self.get(key).map(|property| &property.value)
+    }
+
+    /// Sets a property, replacing the value of an existing key or appending a new one
+    pub fn insert(&mut self, key: &str, value: Value) {
+        let raw = value.to_string();
+        match self
+            .entries
+            .iter_mut()
+            .find(|property| property.key.eq_ignore_ascii_case(key))
+        {
+            Some(existing) => {
+                existing.raw = raw;
+                existing.value = value;
+            }
+            None => self.entries.push(Property {
+                key: key.to_string(),
+                raw,
+                value,
+                line: 0,
+            }),
+        }
+    }
+
+    /// Removes the property with the given key (case-insensitive), returning it
+    pub fn remove(&mut self, key: &str) -> Option<Property> {
+        let index = self
+            .entries
+            .iter()
+            .position(|property| property.key.eq_ignore_ascii_case(key))?;
+        Some(self.entries.remove(index))
+    }
+
+    /// Iterates over the properties in order
+    pub fn iter(&self) -> impl Iterator<Item = &Property> {
+        self.entries.iter()
+    }
+
+    /// Number of properties
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no properties
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Display for Properties {
+    /// Serializes the properties as `key:: value` lines, in order, without a trailing line break
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|property| {
+                if property.raw.is_empty() {
+                    format!("{}::", property.key)
+                } else {
+                    format!("{}:: {}", property.key, property.raw)
+                }
+            })
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl Block {
+    /// Properties of the block, with line numbers from the source
+    pub fn properties(&self) -> Properties {
+        Properties::parse_lines(self.numbered_lines())
+    }
+}
+
+impl Outline {
+    /// Page properties: property lines before the first bullet or, if there are none, the
+    /// properties of the first block when it starts with a property
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::outline::parse_outline;
+    /// let outline = parse_outline("alias:: other\n\n- content");
+    /// assert_eq!(outline.page_properties().get("alias").unwrap().raw, "other");
+    /// let outline = parse_outline("- title:: Page\n  public:: true\n- content");
+    /// assert_eq!(outline.page_properties().len(), 2);
+    /// assert!(parse_outline("- content\n  id:: 1").page_properties().is_empty());
+    /// ```
+    pub fn page_properties(&self) -> Properties {
+        let preamble = Properties::parse_lines(
+            self.preamble
+                .iter()
+                .enumerate()
+                .map(|(i, line)| (i + 1, line.as_str())),
+        );
+        if !preamble.is_empty() {
+            return preamble;
+        }
+        match self.blocks.first() {
+            Some(first) if split_property(first.first_line()).is_some() => first.properties(),
+            _ => Properties::new(),
+        }
+    }
+}
+
+/// Adds `key:: value` lines to the page properties of a page given as lines: after its last
+/// page property (indented like the first block when the properties are written there), or as
+/// new first lines followed by a blank line
+pub(crate) fn insert_page_properties(outline: &Outline, lines: &mut Vec<String>, new: &[String]) {
+    if new.is_empty() {
+        return;
+    }
+    let ending = match lines.first().is_some_and(|line| line.ends_with('\r')) {
+        true => "\r",
+        false => "",
+    };
+    let (at, indent) = match outline.page_properties().iter().last() {
+        Some(last) if outline.preamble.is_empty() => {
+            (last.line, format!("{}  ", outline.blocks[0].indent))
+        }
+        Some(last) => (last.line, String::new()),
+        None => {
+            lines.insert(0, ending.to_string());
+            (0, String::new())
+        }
+    };
+    for (offset, property) in new.iter().enumerate() {
+        lines.insert(at + offset, format!("{}{}{}", indent, property, ending));
+    }
+}
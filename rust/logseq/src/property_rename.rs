@@ -0,0 +1,358 @@
+//! Rename a property key, or a value of a property, on every page and block of a graph, or
+//! normalize the style of all the keys
+
+use crate::graph::{page_key, Graph, GraphPage};
+use crate::properties::{split_list, split_property, strip_ref, Properties, Property, Value};
+use crate::refs::{lines_outside_regions, CODE_REGIONS};
+use crate::rename::{write_edits, FileEdit};
+use crate::stubs::GENERATED_PROPERTIES;
+use crate::{error, Error, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Everything a property rename changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PropertyRename {
+    /// Files rewritten by the rename, sorted by path
+    pub edits: Vec<FileEdit>,
+    /// Property lines left alone because their block (or page) already has the new key, as
+    /// (file, line number starting at 1)
+    pub conflicts: Vec<(PathBuf, usize)>,
+}
+
+/// The properties of a page, apart from code: those before its first bullet, then those of
+/// each block
+fn property_groups(page: &GraphPage) -> Vec<Properties> {
+    let preamble = page
+        .outline
+        .preamble
+        .iter()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.as_str()));
+    let blocks = page.outline.iter().map(|block| {
+        Properties::parse_lines(lines_outside_regions(block.numbered_lines(), CODE_REGIONS))
+    });
+    std::iter::once(Properties::parse_lines(preamble))
+        .chain(blocks)
+        .collect()
+}
+
+/// Rewrites the property lines of every page with `rewrite`, given the page, a property and its
+/// line, and the other properties of its block
+fn rewrite_properties(
+    graph: &Graph,
+    mut rewrite: impl FnMut(&GraphPage, &Property, &str, &Properties) -> Option<String>,
+) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        let groups = property_groups(page);
+        if groups.iter().all(Properties::is_empty) {
+            continue;
+        }
+        let original = error::read_to_string(&page.path)?;
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        let mut changed = false;
+        for properties in &groups {
+            for property in properties.iter() {
+                let line = &lines[property.line - 1];
+                if let Some(updated) = rewrite(page, property, line, properties) {
+                    changed |= updated != *line;
+                    lines[property.line - 1] = updated;
+                }
+            }
+        }
+        if changed {
+            edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated: lines.join("\n"),
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Byte offset of the key of a property line
+fn key_offset(line: &str, key: &str) -> usize {
+    line.find(&format!("{}::", key)).unwrap_or_default()
+}
+
+/// Plans renaming the property key `old` (compared ignoring case) to `new`, in page properties
+/// and block properties outside code. Blocks and pages that already have `new` are left alone
+/// and reported as conflicts. Nothing is written; see [rename_property].
+/// Fails if `new` isn't a valid property key.
+pub fn plan_property_rename(graph: &Graph, old: &str, new: &str) -> Result<PropertyRename> {
+    let valid = split_property(&format!("{}:: value", new)).is_some_and(|(key, _)| key == new);
+    if !valid {
+        return Err(Error::parse(None, format!("not a property key: {}", new)));
+    }
+    let mut conflicts = Vec::new();
+    let edits = rewrite_properties(graph, |page, property, line, properties| {
+        if !property.key.eq_ignore_ascii_case(old) {
+            return None;
+        }
+        let renamed_case = old.eq_ignore_ascii_case(new);
+        if !renamed_case && properties.get(new).is_some() {
+            conflicts.push((page.path.clone(), property.line));
+            return None;
+        }
+        let start = key_offset(line, &property.key);
+        let end = start + property.key.len();
+        Some(format!("{}{}{}", &line[..start], new, &line[end..]))
+    })?;
+    Ok(PropertyRename { edits, conflicts })
+}
+
+/// Renames a property key across a graph, see [plan_property_rename]; with `dry_run`, only
+/// plans the changes
+pub fn rename_property(
+    graph: &Graph,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+) -> Result<PropertyRename> {
+    let rename = plan_property_rename(graph, old, new)?;
+    if !dry_run {
+        write_edits(&rename.edits)?;
+    }
+    Ok(rename)
+}
+
+/// A reference written like `written`, to the page `name`
+fn ref_like(written: &str, name: &str) -> String {
+    if written.starts_with("#[[")
+        || (written.starts_with('#') && name.contains(char::is_whitespace))
+    {
+        format!("#[[{}]]", name)
+    } else if written.starts_with('#') {
+        format!("#{}", name)
+    } else if written.starts_with("[[") {
+        format!("[[{}]]", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// The raw value of a property with `old` renamed to `new`: the whole value, or the
+/// references of a list of references to the page `old`. A reference that would repeat one
+/// already in the list is dropped instead.
+fn renamed_value(key: &str, raw: &str, old: &str, new: &str) -> Option<String> {
+    if raw.trim() == old {
+        return Some(new.to_string());
+    }
+    let items = split_list(raw);
+    // `#[[tags with spaces]]` aren't references to Value::parse
+    let refs = items
+        .iter()
+        .all(|item| item.starts_with("[[") || item.starts_with('#'));
+    if !refs && !matches!(Value::parse(key, raw), Value::Refs(_)) {
+        return None;
+    }
+    let old_key = page_key(old);
+    if !items
+        .iter()
+        .any(|item| page_key(&strip_ref(item)) == old_key)
+    {
+        return None;
+    }
+    let new_key = page_key(new);
+    let listed = items
+        .iter()
+        .any(|item| page_key(&strip_ref(item)) == new_key);
+    let renamed: Vec<String> = items
+        .iter()
+        .filter_map(|item| match page_key(&strip_ref(item)) == old_key {
+            true if listed => None,
+            true => Some(ref_like(item, new)),
+            false => Some(item.to_string()),
+        })
+        .collect();
+    Some(renamed.join(", "))
+}
+
+/// Plans renaming the value `old` of the property `key` (compared ignoring case) to `new`:
+/// values that are exactly `old`, and references to the page `old` in lists of references like
+/// `tags::`, which keep the way they are written. The page itself isn't renamed, see
+/// [rename_page](crate::rename::rename_page). Nothing is written; see [rename_property_value].
+pub fn plan_property_value_rename(
+    graph: &Graph,
+    key: &str,
+    old: &str,
+    new: &str,
+) -> Result<PropertyRename> {
+    let edits = rewrite_properties(graph, |_, property, line, _| {
+        if !property.key.eq_ignore_ascii_case(key) {
+            return None;
+        }
+        let value = renamed_value(&property.key, &property.raw, old, new)?;
+        let ending = match line.ends_with('\r') {
+            true => "\r",
+            false => "",
+        };
+        let start = key_offset(line, &property.key) + property.key.len() + "::".len();
+        Some(format!("{} {}{}", &line[..start], value, ending))
+    })?;
+    Ok(PropertyRename {
+        edits,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Renames a property value across a graph, see [plan_property_value_rename]; with `dry_run`,
+/// only plans the changes
+pub fn rename_property_value(
+    graph: &Graph,
+    key: &str,
+    old: &str,
+    new: &str,
+    dry_run: bool,
+) -> Result<PropertyRename> {
+    let rename = plan_property_value_rename(graph, key, old, new)?;
+    if !dry_run {
+        write_edits(&rename.edits)?;
+    }
+    Ok(rename)
+}
+
+/// Keys of the properties Logseq reads itself, besides [GENERATED_PROPERTIES], which keep their
+/// kebab-case key in every [KeyStyle]
+const LOGSEQ_KEYS: &[&str] = &[
+    "alias",
+    "tags",
+    "template",
+    "template-including-parent",
+    "heading",
+    "background-color",
+    "icon",
+    "public",
+    "exclude-from-graph-view",
+];
+
+/// The style of normalized property keys, always in lowercase like Logseq compares them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyStyle {
+    /// `book-author`
+    #[default]
+    Kebab,
+    /// `book_author`
+    Snake,
+}
+
+impl FromStr for KeyStyle {
+    type Err = Error;
+
+    /// Parses `kebab` and `snake`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "kebab" => Ok(KeyStyle::Kebab),
+            "snake" => Ok(KeyStyle::Snake),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown key style {}, expected kebab or snake", name),
+            )),
+        }
+    }
+}
+
+impl KeyStyle {
+    /// A key in this style. Keys with a `.`, like `logseq.order-list-type`, are only lowercased,
+    /// and the keys Logseq reads itself, like `created-at` or `card-next-schedule`, always are
+    /// in kebab case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::property_rename::KeyStyle;
+    /// assert_eq!(KeyStyle::Kebab.apply("Book_Author"), "book-author");
+    /// assert_eq!(KeyStyle::Snake.apply("book-author"), "book_author");
+    /// assert_eq!(KeyStyle::Snake.apply("Created_At"), "created-at");
+    /// assert_eq!(KeyStyle::Snake.apply("logseq.order-list-type"), "logseq.order-list-type");
+    /// ```
+    pub fn apply(&self, key: &str) -> String {
+        let lowercase = key.to_lowercase();
+        if lowercase.contains('.') {
+            return lowercase;
+        }
+        let kebab = lowercase.replace('_', "-");
+        let logseq = GENERATED_PROPERTIES.contains(&kebab.as_str())
+            || LOGSEQ_KEYS.contains(&kebab.as_str())
+            || kebab.starts_with("card-");
+        match self {
+            KeyStyle::Snake if !logseq => kebab.replace('-', "_"),
+            _ => kebab,
+        }
+    }
+}
+
+/// A key renamed by [plan_key_normalization]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMapping {
+    /// The key as written
+    pub from: String,
+    /// The normalized key
+    pub to: String,
+    /// How many property lines were renamed
+    pub count: usize,
+}
+
+/// Everything a normalization of property keys changes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyNormalization {
+    /// The property lines renamed, and the ones left alone because another property of their
+    /// block (or page) would get the same key
+    pub rename: PropertyRename,
+    /// The keys renamed, sorted
+    pub mapping: Vec<KeyMapping>,
+}
+
+/// Plans renaming the property keys of a graph to `style`, see [KeyStyle::apply], in page and
+/// block properties outside code. A block (or page) with two spellings of a key, like
+/// `book_author::` and `book-author::`, is left alone and reported in the conflicts.
+/// Nothing is written; see [normalize_property_keys].
+pub fn plan_key_normalization(graph: &Graph, style: KeyStyle) -> Result<KeyNormalization> {
+    let mut conflicts = Vec::new();
+    let mut counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let edits = rewrite_properties(graph, |page, property, line, properties| {
+        let normalized = style.apply(&property.key);
+        if normalized == property.key {
+            return None;
+        }
+        let spellings = properties
+            .iter()
+            .filter(|other| style.apply(&other.key) == normalized)
+            .count();
+        if spellings > 1 {
+            conflicts.push((page.path.clone(), property.line));
+            return None;
+        }
+        *counts
+            .entry((property.key.clone(), normalized.clone()))
+            .or_default() += 1;
+        let start = key_offset(line, &property.key);
+        let end = start + property.key.len();
+        Some(format!("{}{}{}", &line[..start], normalized, &line[end..]))
+    })?;
+    let mapping = counts
+        .into_iter()
+        .map(|((from, to), count)| KeyMapping { from, to, count })
+        .collect();
+    Ok(KeyNormalization {
+        rename: PropertyRename { edits, conflicts },
+        mapping,
+    })
+}
+
+/// Normalizes the property keys of a graph, see [plan_key_normalization]; with `dry_run`, only
+/// plans the changes
+pub fn normalize_property_keys(
+    graph: &Graph,
+    style: KeyStyle,
+    dry_run: bool,
+) -> Result<KeyNormalization> {
+    let normalization = plan_key_normalization(graph, style)?;
+    if !dry_run {
+        write_edits(&normalization.rename.edits)?;
+    }
+    Ok(normalization)
+}
@@ -0,0 +1,427 @@
+//! Logseq queries: simple `{{query ...}}` filters and advanced `#+BEGIN_QUERY` Datalog queries
+
+use crate::edn::Edn;
+use crate::graph::Graph;
+use std::collections::HashSet;
+
+/// The filters of simple queries
+pub const OPERATORS: &[&str] = &[
+    "and",
+    "or",
+    "not",
+    "between",
+    "page",
+    "property",
+    "page-property",
+    "full-text-search",
+    "task",
+    "todo",
+    "priority",
+    "page-tags",
+    "all-page-tags",
+    "namespace",
+    "sort-by",
+    "sample",
+];
+
+/// The task markers `(task ...)` accepts
+const MARKERS: &[&str] = &[
+    "TODO",
+    "DOING",
+    "DONE",
+    "LATER",
+    "NOW",
+    "WAITING",
+    "WAIT",
+    "CANCELED",
+    "CANCELLED",
+    "IN-PROGRESS",
+];
+
+/// A term of a simple query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    /// Column of the term in the query, starting at 1
+    pub column: usize,
+    /// What the term is
+    pub kind: TermKind,
+}
+
+/// The kinds of terms of a simple query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TermKind {
+    /// `(operator arguments...)`
+    Filter {
+        /// The operator, as written
+        operator: String,
+        /// The terms after the operator
+        arguments: Vec<Term>,
+    },
+    /// A `[[page]]` reference
+    Page(String),
+    /// A `#tag` or `#[[tag]]`
+    Tag(String),
+    /// A `"quoted text"`
+    Text(String),
+    /// Anything else, like a property value or a task marker
+    Word(String),
+}
+
+/// A problem found in a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryProblem {
+    /// Column in the query, starting at 1
+    pub column: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl QueryProblem {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        QueryProblem {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+/// What queries can refer to: the pages and property keys of a graph, all lowercase
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryContext {
+    /// Names and aliases of the pages
+    pub pages: HashSet<String>,
+    /// Keys of the page and block properties
+    pub properties: HashSet<String>,
+}
+
+impl QueryContext {
+    /// The pages and property keys of a graph
+    pub fn from_graph(graph: &Graph) -> Self {
+        QueryContext {
+            pages: graph.page_names(),
+            properties: graph.property_keys(),
+        }
+    }
+
+    fn check_page(&self, name: &str, column: usize, problems: &mut Vec<QueryProblem>) {
+        if !self.pages.contains(&name.to_lowercase()) {
+            problems.push(QueryProblem::new(
+                column,
+                format!("Query refers to missing page {}", name),
+            ));
+        }
+    }
+
+    fn check_property(&self, key: &str, column: usize, problems: &mut Vec<QueryProblem>) {
+        let key = key.trim_start_matches(':');
+        if !self.properties.contains(&key.to_lowercase()) {
+            problems.push(QueryProblem::new(
+                column,
+                format!("Query refers to unknown property {}", key),
+            ));
+        }
+    }
+}
+
+/// Parses the inside of a `{{query ...}}` macro into its terms
+///
+/// # Examples
+///
+/// ```
+/// use logseq::query::{parse_simple_query, TermKind};
+/// let terms = parse_simple_query("(and [[Dune]] (task TODO))").unwrap();
+/// let TermKind::Filter { operator, arguments } = &terms[0].kind else { panic!() };
+/// assert_eq!(operator, "and");
+/// assert_eq!(arguments[0].kind, TermKind::Page("Dune".to_string()));
+/// assert_eq!(arguments[1].column, 15);
+///
+/// let problem = parse_simple_query("(and [[Dune]] (task TODO)").unwrap_err();
+/// assert_eq!((problem.column, problem.message.as_str()), (1, "Unbalanced parentheses: ( is never closed"));
+/// ```
+pub fn parse_simple_query(query: &str) -> Result<Vec<Term>, QueryProblem> {
+    let chars: Vec<char> = query.chars().collect();
+    // The terms of the enclosing filters, with the column and operator of each one
+    let mut open: Vec<(usize, String, Vec<Term>)> = Vec::new();
+    let mut terms: Vec<Term> = Vec::new();
+    let mut position = 0;
+    while position < chars.len() {
+        let c = chars[position];
+        let column = position + 1;
+        let starts_with = |prefix: &str| {
+            prefix
+                .chars()
+                .enumerate()
+                .all(|(i, p)| chars.get(position + i) == Some(&p))
+        };
+        let kind = if c.is_whitespace() {
+            position += 1;
+            continue;
+        } else if c == '(' {
+            position += 1;
+            while chars.get(position).is_some_and(|c| c.is_whitespace()) {
+                position += 1;
+            }
+            let start = position;
+            while chars
+                .get(position)
+                .is_some_and(|c| !c.is_whitespace() && !"()[]\"".contains(*c))
+            {
+                position += 1;
+            }
+            if start == position {
+                return Err(QueryProblem::new(column, "Expected an operator after ("));
+            }
+            let operator = chars[start..position].iter().collect();
+            open.push((column, operator, std::mem::take(&mut terms)));
+            continue;
+        } else if c == ')' {
+            position += 1;
+            let Some((start, operator, outer)) = open.pop() else {
+                return Err(QueryProblem::new(
+                    column,
+                    "Unbalanced parentheses: ) closes nothing",
+                ));
+            };
+            let arguments = std::mem::replace(&mut terms, outer);
+            terms.push(Term {
+                column: start,
+                kind: TermKind::Filter {
+                    operator,
+                    arguments,
+                },
+            });
+            continue;
+        } else if starts_with("[[") || starts_with("#[[") {
+            let tag = c == '#';
+            let start = position + if tag { 3 } else { 2 };
+            let end = (start..chars.len())
+                .find(|&i| chars[i] == ']' && chars.get(i + 1) == Some(&']'))
+                .ok_or_else(|| QueryProblem::new(column, "Unclosed [[ in query"))?;
+            let name: String = chars[start..end].iter().collect();
+            position = end + 2;
+            if tag {
+                TermKind::Tag(name)
+            } else {
+                TermKind::Page(name)
+            }
+        } else if c == '"' {
+            let end = (position + 1..chars.len())
+                .find(|&i| chars[i] == '"' && chars[i - 1] != '\\')
+                .ok_or_else(|| QueryProblem::new(column, "Unterminated string in query"))?;
+            let text = chars[position + 1..end].iter().collect();
+            position = end + 1;
+            TermKind::Text(text)
+        } else {
+            let start = position;
+            while chars
+                .get(position)
+                .is_some_and(|c| !c.is_whitespace() && !"()".contains(*c))
+            {
+                position += 1;
+            }
+            let word: String = chars[start..position].iter().collect();
+            match word.strip_prefix('#') {
+                Some(tag) if !tag.is_empty() => TermKind::Tag(tag.to_string()),
+                _ => TermKind::Word(word),
+            }
+        };
+        terms.push(Term { column, kind });
+    }
+    match open.first() {
+        Some((column, _, _)) => Err(QueryProblem::new(
+            *column,
+            "Unbalanced parentheses: ( is never closed",
+        )),
+        None => Ok(terms),
+    }
+}
+
+/// The name in a term that names a page, like `[[Dune]]`, `Dune` or `"Dune"`
+fn page_name(term: &Term) -> Option<&str> {
+    match &term.kind {
+        TermKind::Page(name)
+        | TermKind::Tag(name)
+        | TermKind::Text(name)
+        | TermKind::Word(name) => Some(name),
+        TermKind::Filter { .. } => None,
+    }
+}
+
+fn check_terms(terms: &[Term], context: Option<&QueryContext>, problems: &mut Vec<QueryProblem>) {
+    for term in terms {
+        match &term.kind {
+            TermKind::Page(name) | TermKind::Tag(name) => {
+                if let Some(context) = context {
+                    context.check_page(name, term.column, problems);
+                }
+            }
+            TermKind::Filter {
+                operator,
+                arguments,
+            } => check_filter(term.column, operator, arguments, context, problems),
+            TermKind::Text(_) | TermKind::Word(_) => {}
+        }
+    }
+}
+
+fn check_filter(
+    column: usize,
+    operator: &str,
+    arguments: &[Term],
+    context: Option<&QueryContext>,
+    problems: &mut Vec<QueryProblem>,
+) {
+    let operator = operator.to_lowercase();
+    if !OPERATORS.contains(&operator.as_str()) {
+        problems.push(QueryProblem::new(
+            column,
+            format!("Unknown query operator {}", operator),
+        ));
+        return;
+    }
+    match operator.as_str() {
+        "property" | "page-property" => {
+            if let (Some(context), Some(key)) = (context, arguments.first()) {
+                if let TermKind::Word(name) | TermKind::Text(name) = &key.kind {
+                    context.check_property(name, key.column, problems);
+                }
+            }
+        }
+        "page" | "page-tags" | "all-page-tags" => {
+            if let Some(context) = context {
+                for argument in arguments {
+                    if let Some(name) = page_name(argument) {
+                        context.check_page(name, argument.column, problems);
+                    }
+                }
+            }
+            return;
+        }
+        "task" | "todo" => {
+            for argument in arguments {
+                if let TermKind::Word(marker) = &argument.kind {
+                    if !MARKERS.contains(&marker.to_uppercase().as_str()) {
+                        problems.push(QueryProblem::new(
+                            argument.column,
+                            format!("Unknown task marker {}", marker),
+                        ));
+                    }
+                }
+            }
+        }
+        // Dates like [[today]] or [[Jan 2nd, 2024]] aren't pages that need to exist
+        "between" => return,
+        _ => {}
+    }
+    check_terms(arguments, context, problems);
+}
+
+/// Checks a simple query: its syntax, its operators and task markers, and with a `context`, that
+/// the pages and properties it refers to exist
+///
+/// # Examples
+///
+/// ```
+/// use logseq::query::{check_simple_query, QueryContext};
+/// let context = QueryContext {
+///     pages: ["dune".to_string()].into(),
+///     properties: ["type".to_string()].into(),
+/// };
+/// let problems = check_simple_query("(and [[Dune]] (property type book) (tasks TODO))", Some(&context));
+/// assert_eq!(problems[0].message, "Unknown query operator tasks");
+///
+/// let problems = check_simple_query("(or [[Dunes]] (page-property genre scifi) (task TODO LATR))", Some(&context));
+/// let messages: Vec<&str> = problems.iter().map(|problem| problem.message.as_str()).collect();
+/// assert_eq!(messages, vec![
+///     "Query refers to missing page Dunes",
+///     "Query refers to unknown property genre",
+///     "Unknown task marker LATR",
+/// ]);
+/// assert!(check_simple_query("\"full text\"", Some(&context)).is_empty());
+/// ```
+pub fn check_simple_query(query: &str, context: Option<&QueryContext>) -> Vec<QueryProblem> {
+    match parse_simple_query(query) {
+        Ok(terms) => {
+            let mut problems = Vec::new();
+            check_terms(&terms, context, &mut problems);
+            problems
+        }
+        Err(problem) => vec![problem],
+    }
+}
+
+/// Finds the pages and properties a Datalog query refers to: `[?p :block/name "page"]`,
+/// `(page-ref ?b "page")`, `(property ?b :key ...)` and `(page-property ?p :key ...)`
+fn check_datalog(value: &Edn, context: &QueryContext, problems: &mut Vec<QueryProblem>) {
+    match value {
+        Edn::Vector(items) => {
+            if let [_, Edn::Keyword(attribute), Edn::String(name)] = items.as_slice() {
+                if attribute == "block/name" || attribute == "block/original-name" {
+                    context.check_page(name, 1, problems);
+                }
+            }
+        }
+        Edn::List(items) => match items.as_slice() {
+            [Edn::Symbol(rule), _, Edn::String(name), ..] if rule == "page-ref" => {
+                context.check_page(name, 1, problems);
+            }
+            [Edn::Symbol(rule), _, Edn::Keyword(key), ..]
+                if rule == "property" || rule == "page-property" =>
+            {
+                context.check_property(key, 1, problems);
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+    for item in value.as_seq().unwrap_or_default() {
+        check_datalog(item, context, problems);
+    }
+}
+
+/// Checks the EDN of an advanced query, between `#+BEGIN_QUERY` and `#+END_QUERY`: that it is
+/// valid EDN with a `:query`, and with a `context`, that the pages and properties its Datalog
+/// refers to exist. Problems are all reported at column 1.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::query::{check_advanced_query, QueryContext};
+/// let context = QueryContext { pages: ["dune".to_string()].into(), properties: Default::default() };
+/// let query = "{:title \"Books\"\n :query [:find (pull ?b [*])\n         :where (page-property ?p :type \"book\") [?p :block/name \"dune\"]]}";
+/// let problems = check_advanced_query(query, Some(&context));
+/// assert_eq!(problems[0].message, "Query refers to unknown property type");
+/// assert_eq!(problems.len(), 1);
+///
+/// assert_eq!(check_advanced_query("{:title \"Books\"}", None)[0].message, "Advanced query without :query");
+/// assert!(check_advanced_query("{:query [:find ?b :where (task ?b #{\"TODO\"}]}", None)[0]
+///     .message.starts_with("Invalid advanced query: "));
+/// ```
+pub fn check_advanced_query(edn: &str, context: Option<&QueryContext>) -> Vec<QueryProblem> {
+    let value = match Edn::parse(edn) {
+        Ok(value) => value,
+        Err(error) => {
+            return vec![QueryProblem::new(
+                1,
+                format!("Invalid advanced query: {}", error),
+            )]
+        }
+    };
+    check_query_map(&value, context)
+}
+
+/// Checks an advanced query that is already parsed, like the `:default-queries` of
+/// `config.edn`: a map with a `:query`, see [check_advanced_query]
+pub fn check_query_map(value: &Edn, context: Option<&QueryContext>) -> Vec<QueryProblem> {
+    let Some(query) = value.get("query") else {
+        return vec![QueryProblem::new(1, "Advanced query without :query")];
+    };
+    let mut problems = Vec::new();
+    match (query, context) {
+        // The query may also be a simple query, as a string
+        (Edn::String(simple), _) => problems.extend(check_simple_query(simple, context)),
+        (query, Some(context)) => check_datalog(query, context, &mut problems),
+        (_, None) => {}
+    }
+    problems
+}
@@ -0,0 +1,179 @@
+//! Find references to pages inside block content
+
+use crate::outline::{bullet_content, is_ignored_line, RegionKind};
+use regex::Regex;
+use std::sync::LazyLock;
+
+static CODE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`[^`]*`").unwrap());
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap());
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:^|\s)(#[^\s#\[\],;!?"'()`]+)"#).unwrap());
+static REF_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)(\{\{embed\s+)?\(\(([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})\)\)",
+    ).unwrap()
+});
+
+/// A `[[page]]` link found in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageLink {
+    /// Column of the opening brackets, starting at 1
+    pub column: usize,
+    /// Name of the linked page, as written
+    pub name: String,
+}
+
+/// Returns the line with inline code spans replaced by spaces, so byte offsets are kept
+pub fn blank_code_spans(line: &str) -> String {
+    CODE_RE
+        .replace_all(line, |captures: &regex::Captures| {
+            " ".repeat(captures[0].len())
+        })
+        .to_string()
+}
+
+/// Finds the `[[page]]` links of a line, ignoring inline code spans
+///
+/// # Examples
+///
+/// ```
+/// use logseq::refs::page_links;
+/// let links = page_links("see [[Some Page]] and #[[tag]] but not `[[code]]`");
+/// let names: Vec<&str> = links.iter().map(|link| link.name.as_str()).collect();
+/// assert_eq!(names, vec!["Some Page", "tag"]);
+/// assert_eq!(links[0].column, 5);
+/// ```
+pub fn page_links(line: &str) -> Vec<PageLink> {
+    let searchable = blank_code_spans(line);
+    LINK_RE
+        .captures_iter(&searchable)
+        .map(|captures| {
+            let found = captures.get(0).unwrap();
+            PageLink {
+                column: line[..found.start()].chars().count() + 1,
+                name: captures[1].to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Finds the `#tag` references of a line, ignoring inline code spans and headings.
+/// Tags written as `#[[tag]]` are found by [page_links] instead.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::refs::tag_refs;
+/// let tags = tag_refs("#todo review #[[long tag]], #project/sub. `#code` http://x.com/#anchor");
+/// let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+/// assert_eq!(names, vec!["todo", "project/sub"]);
+/// assert_eq!(tags[1].column, 29);
+/// assert!(tag_refs("## Heading").is_empty());
+/// ```
+pub fn tag_refs(line: &str) -> Vec<PageLink> {
+    let searchable = blank_code_spans(line);
+    TAG_RE
+        .captures_iter(&searchable)
+        .map(|captures| {
+            let found = captures.get(1).unwrap();
+            PageLink {
+                column: line[..found.start()].chars().count() + 1,
+                name: found.as_str()[1..].trim_end_matches('.').to_string(),
+            }
+        })
+        .filter(|tag| !tag.name.is_empty())
+        .collect()
+}
+
+/// The regions of the text of blocks that Logseq doesn't read references from
+pub const CODE_REGIONS: &[RegionKind] = &[RegionKind::CodeFence, RegionKind::SourceBlock];
+
+/// The regions of the text of blocks that checks and fixes leave alone
+const OPAQUE_REGIONS: &[RegionKind] = &[
+    RegionKind::CodeFence,
+    RegionKind::SourceBlock,
+    RegionKind::Logbook,
+    RegionKind::Ignored,
+];
+
+/// Iterates over the lines of a block content paired with their line numbers, skipping the
+/// ones that checks and fixes leave alone: fenced code blocks and `#+BEGIN_SRC` blocks,
+/// `:LOGBOOK:` drawers and lines marked `<!-- lsd-ignore -->` or between
+/// `<!-- lsd-ignore-start -->` and `<!-- lsd-ignore-end -->`, opening and closing lines included
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::parse_outline;
+/// use logseq::refs::lines_outside_code;
+/// let outline = parse_outline("- a\n  ```\n  b\n  ```\n  #+BEGIN_SRC\n  c\n  #+END_SRC\n  d <!-- lsd-ignore -->\n  e\n");
+/// let lines: Vec<_> = lines_outside_code(outline.blocks[0].numbered_lines()).collect();
+/// assert_eq!(lines, vec![(1, "a"), (9, "e")]);
+/// ```
+pub fn lines_outside_code<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+) -> impl Iterator<Item = (usize, &'a str)> {
+    lines_outside_regions(lines, OPAQUE_REGIONS)
+}
+
+/// Iterates over the lines of a block content like [lines_outside_code], but only skips the
+/// regions of the `skipped` kinds, like [CODE_REGIONS] to find the references of a block
+pub fn lines_outside_regions<'a>(
+    lines: impl Iterator<Item = (usize, &'a str)>,
+    skipped: &'a [RegionKind],
+) -> impl Iterator<Item = (usize, &'a str)> {
+    let mut open: Option<RegionKind> = None;
+    lines.filter(move |(_, text)| {
+        let content = bullet_content(text);
+        if let Some(kind) = open {
+            if kind.closed_by(content) {
+                open = None;
+            }
+            return !skipped.contains(&kind);
+        }
+        open = RegionKind::opened_by(content);
+        match open {
+            Some(kind) => !skipped.contains(&kind),
+            None => !(skipped.contains(&RegionKind::Ignored) && is_ignored_line(text)),
+        }
+    })
+}
+
+/// A `((uuid))` block reference found in a line, on its own or inside `{{embed ((uuid))}}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockRef {
+    /// Column of the opening parentheses, starting at 1
+    pub column: usize,
+    /// UUID of the referenced block, lowercase
+    pub uuid: String,
+    /// True if the reference is inside an embed macro
+    pub embed: bool,
+}
+
+/// Finds the `((uuid))` block references of a line, ignoring inline code spans
+///
+/// # Examples
+///
+/// ```
+/// use logseq::refs::block_refs;
+/// let refs = block_refs("((65a1b2c3-0000-4000-8000-00000000000a)) {{embed ((65A1B2C3-0000-4000-8000-00000000000B))}}");
+/// assert_eq!(refs.len(), 2);
+/// assert_eq!(refs[0].column, 1);
+/// assert!(!refs[0].embed);
+/// assert_eq!(refs[1].uuid, "65a1b2c3-0000-4000-8000-00000000000b");
+/// assert!(refs[1].embed);
+/// ```
+pub fn block_refs(line: &str) -> Vec<BlockRef> {
+    let searchable = blank_code_spans(line);
+    REF_RE
+        .captures_iter(&searchable)
+        .map(|captures| {
+            let uuid = captures.get(2).unwrap();
+            BlockRef {
+                column: line[..uuid.start() - 2].chars().count() + 1,
+                uuid: uuid.as_str().to_lowercase(),
+                embed: captures.get(1).is_some(),
+            }
+        })
+        .collect()
+}
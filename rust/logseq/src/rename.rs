@@ -0,0 +1,227 @@
+//! Rename a page and rewrite every reference to it across the graph
+
+use crate::graph::{file_stem_from_page_name, page_key, Graph, GraphPage};
+use crate::properties::REF_LIST_KEYS;
+use crate::refs::{blank_code_spans, lines_outside_regions, CODE_REGIONS};
+use crate::{error, undo, Error, Result};
+use regex::Regex;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static LINK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[([^\[\]]+)\]\]").unwrap());
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?:^|\s)(#[^\s#\[\],;!?"'()`]+)"#).unwrap());
+static PROPERTY_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s*(?:[-*+]\s+)?)([A-Za-z0-9_][A-Za-z0-9_\-./]*)::\s+(.*)$").unwrap()
+});
+
+/// The new contents of a file touched by a rename
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdit {
+    /// The file to rewrite
+    pub path: PathBuf,
+    /// Its current contents
+    pub original: String,
+    /// Its contents with the references to the page rewritten
+    pub updated: String,
+}
+
+impl FileEdit {
+    /// The lines that change, as (line number starting at 1, before, after)
+    pub fn changed_lines(&self) -> Vec<(usize, &str, &str)> {
+        self.original
+            .split('\n')
+            .zip(self.updated.split('\n'))
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (before, after))| (index + 1, before, after))
+            .collect()
+    }
+}
+
+/// Writes the updated contents of files to temporary files first, so that a failure leaves
+/// them all untouched, then replaces the originals
+pub(crate) fn write_edits(edits: &[FileEdit]) -> Result<()> {
+    let mut staged = Vec::new();
+    for edit in edits {
+        let target = error::link_target(&edit.path);
+        let temp = error::temp_path(&target);
+        if let Err(source) = fs::write(&temp, &edit.updated) {
+            for (temp, _) in &staged {
+                let _ = fs::remove_file(temp);
+            }
+            return Err(Error::io(&edit.path, source));
+        }
+        staged.push((temp, target));
+    }
+    for (edit, (temp, target)) in edits.iter().zip(staged) {
+        fs::rename(&temp, &target).map_err(|source| Error::io(&edit.path, source))?;
+        undo::record(&edit.path, &edit.original, &edit.updated)?;
+    }
+    Ok(())
+}
+
+/// Everything a page rename changes: the page file and the files that refer to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRename {
+    /// The current file of the page
+    pub from: PathBuf,
+    /// The new file of the page
+    pub to: PathBuf,
+    /// Files whose references to the page are rewritten, sorted by path
+    pub edits: Vec<FileEdit>,
+}
+
+impl PageRename {
+    /// Applies the rename. All rewritten files are written to temporary files first, so a
+    /// failure leaves the graph untouched; they then replace the originals, and the page file
+    /// is moved last.
+    pub fn apply(&self) -> Result<()> {
+        write_edits(&self.edits)?;
+        if self.from != self.to {
+            undo::move_file(&self.from, &self.to)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites the references to a page in one line: `[[old]]`, `#[[old]]`, `#old` and
+/// `tags::`/`alias::` entries (and `title::` when `own_page` is true)
+fn rewrite_line(line: &str, old_key: &str, new: &str, own_page: bool) -> String {
+    let searchable = blank_code_spans(line);
+    let new_tag = if new.contains(char::is_whitespace) || new.contains(',') {
+        format!("#[[{}]]", new)
+    } else {
+        format!("#{}", new)
+    };
+
+    // Byte ranges of the line to replace, and their replacements
+    let mut replacements: Vec<(usize, usize, String)> = Vec::new();
+    for captures in LINK_RE.captures_iter(&searchable) {
+        let name = captures.get(1).unwrap();
+        if page_key(name.as_str()) == old_key {
+            replacements.push((name.start(), name.end(), new.to_string()));
+        }
+    }
+    for captures in TAG_RE.captures_iter(&searchable) {
+        let tag = captures.get(1).unwrap();
+        let name = tag.as_str()[1..].trim_end_matches('.');
+        if page_key(name) == old_key {
+            replacements.push((tag.start(), tag.start() + 1 + name.len(), new_tag.clone()));
+        }
+    }
+    if let Some(captures) = PROPERTY_RE.captures(&searchable) {
+        let key = captures[2].to_lowercase();
+        let value = captures.get(3).unwrap();
+        if REF_LIST_KEYS.contains(&key.as_str()) {
+            let mut start = value.start();
+            for item in value.as_str().split(',') {
+                let trimmed = item.trim();
+                if page_key(trimmed) == old_key {
+                    let offset = start + item.find(trimmed).unwrap_or_default();
+                    replacements.push((offset, offset + trimmed.len(), new.to_string()));
+                }
+                start += item.len() + 1;
+            }
+        } else if key == "title" && own_page {
+            replacements.push((value.start(), value.end(), new.to_string()));
+        }
+    }
+
+    replacements.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+    let mut rewritten = line.to_string();
+    for (start, end, replacement) in replacements {
+        rewritten.replace_range(start..end, &replacement);
+    }
+    rewritten
+}
+
+/// Rewrites the references of a page (the preamble and blocks of its `original` text, outside
+/// code) to the page with the key `old_key`, so that they refer to `new` instead
+pub(crate) fn rewrite_references(
+    page: &GraphPage,
+    original: &str,
+    old_key: &str,
+    new: &str,
+    own_page: bool,
+) -> String {
+    let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+    let preamble = 1..=page.outline.preamble.len();
+    let block_lines = page.outline.iter().flat_map(|block| {
+        lines_outside_regions(block.numbered_lines(), CODE_REGIONS).map(|(line, _)| line)
+    });
+    for line in preamble.chain(block_lines) {
+        let text = &lines[line - 1];
+        let (text, ending) = match text.strip_suffix('\r') {
+            Some(text) => (text, "\r"),
+            None => (text.as_str(), ""),
+        };
+        lines[line - 1] = rewrite_line(text, old_key, new, own_page) + ending;
+    }
+    lines.join("\n")
+}
+
+/// Plans the rename of a page: its new file name and the rewrite of every `[[link]]`, `#tag`,
+/// embed and `tags::`/`alias::` entry that refers to it, ignoring code.
+/// Nothing is written; see [PageRename::apply].
+/// Fails if the page doesn't exist, is a journal, or if another page already has the new name.
+pub fn plan_rename(graph: &Graph, old: &str, new: &str) -> Result<PageRename> {
+    let new = new.trim();
+    let page = graph
+        .find_page(old)
+        .filter(|page| page.journal_date.is_none() && page_key(&page.name) == page_key(old))
+        .ok_or_else(|| Error::parse(None, format!("page not found: {}", old)))?;
+    if new.is_empty() {
+        return Err(Error::parse(None, "the new page name is empty"));
+    }
+    if let Some(other) = graph.find_page(new).filter(|other| other.path != page.path) {
+        return Err(Error::parse(
+            Some(&other.path),
+            format!("a page named {} already exists", new),
+        ));
+    }
+    let extension = page
+        .path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "md".to_string());
+    let to = page
+        .path
+        .with_file_name(format!("{}.{}", file_stem_from_page_name(new), extension));
+    if to != page.path
+        && to.exists()
+        && page_key(&to.to_string_lossy()) != page_key(&page.path.to_string_lossy())
+    {
+        return Err(Error::parse(Some(&to), "the new file already exists"));
+    }
+
+    let old_key = page_key(&page.name);
+    let mut edits = Vec::new();
+    for other in &graph.pages {
+        let original = error::read_to_string(&other.path)?;
+        let own_page = other.path == page.path;
+        let updated = rewrite_references(other, &original, &old_key, new, own_page);
+        if updated != original {
+            edits.push(FileEdit {
+                path: other.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(PageRename {
+        from: page.path.clone(),
+        to,
+        edits,
+    })
+}
+
+/// Renames a page and rewrites all references to it; with `dry_run`, only plans the changes
+pub fn rename_page(graph: &Graph, old: &str, new: &str, dry_run: bool) -> Result<PageRename> {
+    let rename = plan_rename(graph, old, new)?;
+    if !dry_run {
+        rename.apply()?;
+    }
+    Ok(rename)
+}
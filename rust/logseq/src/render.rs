@@ -0,0 +1,93 @@
+//! Write an [Outline] back to Logseq Markdown
+//!
+//! An outline parsed with [parse_outline](crate::outline::parse_outline) renders back to its
+//! text byte for byte: the indentation, bullets, spacing and line ending of each line are kept
+//! as they were in the source. Blocks and lines added by a transformation are written the way
+//! Logseq writes them: a space after the bullet, continuation lines indented like the bullet
+//! plus two spaces, and the dominant line ending of the page.
+
+use crate::line_ending::LineEnding;
+use crate::outline::{Block, Outline};
+
+/// Renders an outline to the text of a page
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::parse_outline;
+/// use logseq::render::render_outline;
+/// let text = "title:: Page\n\n-\tTabbed\n    * Child\n       odd  indent\r\n-\n";
+/// assert_eq!(render_outline(&parse_outline(text)), text);
+///
+/// let mut outline = parse_outline("- Parent\r\n");
+/// let mut child = outline.blocks[0].clone();
+/// child.indent = "  ".to_string();
+/// child.content = "Child\nmore".to_string();
+/// outline.blocks[0].children.push(child);
+/// assert_eq!(render_outline(&outline), "- Parent\r\n  - Child\r\n    more\r\n");
+/// ```
+pub fn render_outline(outline: &Outline) -> String {
+    let dominant = outline.style.line_ending;
+    let mut lines: Vec<(String, LineEnding)> = outline
+        .preamble
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let ending = outline.preamble_line_endings.get(index).copied();
+            (line.clone(), ending.unwrap_or(dominant))
+        })
+        .collect();
+    for block in &outline.blocks {
+        block_lines(block, dominant, &mut lines);
+    }
+    let mut text = String::new();
+    let count = lines.len();
+    for (index, (line, ending)) in lines.into_iter().enumerate() {
+        text.push_str(&line);
+        if index + 1 < count || outline.style.trailing_newline {
+            text.push_str(ending.as_str());
+        }
+    }
+    text
+}
+
+/// Renders a block and its descendants, with `\n` line breaks and no final line break
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::parse_outline;
+/// use logseq::render::render_block;
+/// let outline = parse_outline("- a\n  + b\n    text\n- c\n");
+/// assert_eq!(render_block(&outline.blocks[0]), "- a\n  + b\n    text");
+/// ```
+pub fn render_block(block: &Block) -> String {
+    let mut lines = Vec::new();
+    block_lines(block, LineEnding::Lf, &mut lines);
+    let lines: Vec<String> = lines.into_iter().map(|(line, _)| line).collect();
+    lines.join("\n")
+}
+
+/// Pushes the lines of a block and its descendants, with their line endings: the ones of the
+/// source, or `dominant` for the lines without one
+fn block_lines(block: &Block, dominant: LineEnding, lines: &mut Vec<(String, LineEnding)>) {
+    let ending = |index: usize| block.line_endings.get(index).copied().unwrap_or(dominant);
+    let mut content = block.content.split('\n');
+    let first = content.next().unwrap_or("");
+    let separator = match block.separator.as_str() {
+        "" if !first.is_empty() => " ",
+        separator => separator,
+    };
+    let bullet = format!("{}{}{}{}", block.indent, block.marker, separator, first);
+    lines.push((bullet, ending(0)));
+    for (index, line) in content.enumerate() {
+        let line = match block.continuation_indents.get(index) {
+            Some(indent) => format!("{}{}", indent, line),
+            None => format!("{}  {}", block.indent, line),
+        };
+        lines.push((line, ending(index + 1)));
+    }
+    for child in &block.children {
+        block_lines(child, dominant, lines);
+    }
+}
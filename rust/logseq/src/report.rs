@@ -0,0 +1,333 @@
+//! Machine-readable reports of what the lint rules found and fixed
+
+use crate::batch::FileReport;
+use crate::dedupe::fnv1a;
+use crate::graph::{find_graph_root, BrokenLink, UnreadableFile, UNREADABLE_RULE};
+use crate::lint::{Diagnostic, LintResult, Severity};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub mod baseline;
+pub mod format;
+
+/// A problem found in a file, and whether it was fixed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// Id of the rule that found the problem
+    pub rule: String,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// The file with the problem, if the text came from a file
+    pub path: Option<PathBuf>,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column, starting at 1
+    pub column: usize,
+    /// Human-readable description of the problem
+    pub message: String,
+    /// True if the problem was fixed automatically
+    pub fixed: bool,
+    /// Identifies the finding across runs whatever the lines around it, see
+    /// [Report::normalize]; empty until then
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub fingerprint: String,
+}
+
+impl Finding {
+    /// A finding for a diagnostic of the lint engine
+    pub fn from_diagnostic(diagnostic: &Diagnostic, path: Option<&Path>, fixed: bool) -> Self {
+        Finding {
+            rule: diagnostic.rule.to_string(),
+            severity: diagnostic.severity,
+            path: path.map(Path::to_path_buf),
+            line: diagnostic.line,
+            column: diagnostic.column,
+            message: diagnostic.message.clone(),
+            fixed,
+            fingerprint: String::new(),
+        }
+    }
+}
+
+/// When the findings of a report are a failure
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Thresholds {
+    /// The least severity of the findings that fail
+    pub fail_on: Severity,
+    /// How many warnings are allowed, if they don't fail by themselves: with a limit, more
+    /// warnings fail even when only errors do
+    pub max_warnings: Option<usize>,
+}
+
+/// What happened to a file that was processed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileSummary {
+    /// The file
+    pub path: PathBuf,
+    /// True if the file was (or, in a dry run, would be) rewritten
+    pub changed: bool,
+    /// A unified diff of the fixes, empty if nothing changed
+    pub diff: String,
+    /// The error that prevented the file from being processed, if any
+    pub error: Option<String>,
+}
+
+/// Everything found (and fixed) in one or more files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Problems found, in the order of the files, then by position
+    pub findings: Vec<Finding>,
+    /// The files that were processed, if any
+    pub files: Vec<FileSummary>,
+    /// Problems found where `lsd-disable` comments or properties suppress their rule
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suppressed: Vec<Finding>,
+}
+
+impl Report {
+    /// A report of the diagnostics of a text that was only checked
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{run_lints, RuleSet};
+    /// use logseq::report::Report;
+    /// let report = Report::from_diagnostics(None, &run_lints("- a  b", &RuleSet::builtin()));
+    /// assert_eq!(report.to_json(), r#"{"findings":[{"rule":"consecutive-spaces","severity":"warning","path":null,"line":1,"column":4,"message":"Consecutive spaces","fixed":false}],"files":[]}"#);
+    /// ```
+    pub fn from_diagnostics(path: Option<&Path>, diagnostics: &[Diagnostic]) -> Self {
+        Report {
+            findings: diagnostics
+                .iter()
+                .map(|diagnostic| Finding::from_diagnostic(diagnostic, path, false))
+                .collect(),
+            files: Vec::new(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    /// A report of the diagnostics of a text that was only checked, with the suppressed ones
+    pub fn from_lint(path: Option<&Path>, result: &LintResult) -> Self {
+        let mut report = Report::from_diagnostics(path, &result.diagnostics);
+        report.suppressed = result
+            .suppressed
+            .iter()
+            .map(|diagnostic| Finding::from_diagnostic(diagnostic, path, false))
+            .collect();
+        report
+    }
+
+    /// A report of files that were fixed; a problem is fixed when it's gone after fixing
+    pub fn from_file_reports(reports: &[FileReport]) -> Self {
+        let mut report = Report::default();
+        for file in reports {
+            for diagnostic in &file.diagnostics {
+                let fixed = !file.remaining.contains(diagnostic);
+                report.findings.push(Finding::from_diagnostic(
+                    diagnostic,
+                    Some(&file.path),
+                    fixed,
+                ));
+            }
+            for diagnostic in &file.suppressed {
+                report.suppressed.push(Finding::from_diagnostic(
+                    diagnostic,
+                    Some(&file.path),
+                    false,
+                ));
+            }
+            report.files.push(FileSummary {
+                path: file.path.clone(),
+                changed: file.changed,
+                diff: file.diff.clone(),
+                error: file.error.as_ref().map(|error| error.to_string()),
+            });
+        }
+        report
+    }
+
+    /// A report of links to missing pages or blocks, found by `rule`
+    pub fn from_broken_links(
+        rule: &str,
+        links: &[BrokenLink],
+        describe: impl Fn(&str) -> String,
+    ) -> Self {
+        Report {
+            findings: links
+                .iter()
+                .map(|link| Finding {
+                    rule: rule.to_string(),
+                    severity: Severity::default(),
+                    path: Some(link.path.clone()),
+                    line: link.line,
+                    column: link.column,
+                    message: describe(&link.target),
+                    fixed: false,
+                    fingerprint: String::new(),
+                })
+                .collect(),
+            files: Vec::new(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    /// A report of files that can't be read, and so weren't checked: errors, on their first line
+    pub fn from_unreadable(files: &[UnreadableFile]) -> Self {
+        Report {
+            findings: files
+                .iter()
+                .map(|file| Finding {
+                    rule: UNREADABLE_RULE.to_string(),
+                    severity: Severity::Error,
+                    path: Some(file.path.clone()),
+                    line: 1,
+                    column: 1,
+                    message: format!("File can't be read: {}", file.message),
+                    fixed: false,
+                    fingerprint: String::new(),
+                })
+                .collect(),
+            files: Vec::new(),
+            suppressed: Vec::new(),
+        }
+    }
+
+    /// Appends the findings and files of another report
+    pub fn extend(&mut self, other: Report) {
+        self.findings.extend(other.findings);
+        self.files.extend(other.files);
+        self.suppressed.extend(other.suppressed);
+    }
+
+    /// True if the findings are a failure with the thresholds: a finding at least as serious as
+    /// [Thresholds::fail_on], or more warnings than [Thresholds::max_warnings]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{run_lints, RuleSet, Severity};
+    /// use logseq::report::{Report, Thresholds};
+    /// let warnings = Report::from_diagnostics(None, &run_lints("- a  b", &RuleSet::builtin()));
+    /// assert!(warnings.fails(&Thresholds::default()));
+    /// let errors_only = Thresholds { fail_on: Severity::Error, max_warnings: None };
+    /// assert!(!warnings.fails(&errors_only));
+    /// assert!(!warnings.fails(&Thresholds { max_warnings: Some(1), ..Thresholds::default() }));
+    /// assert!(warnings.fails(&Thresholds { max_warnings: Some(0), ..errors_only }));
+    /// ```
+    pub fn fails(&self, thresholds: &Thresholds) -> bool {
+        let count = |severity: Severity| {
+            self.findings
+                .iter()
+                .filter(|finding| finding.severity == severity)
+                .count()
+        };
+        let warnings = count(Severity::Warning);
+        let serious = self.findings.iter().any(|finding| {
+            finding.severity >= thresholds.fail_on
+                && !(finding.severity == Severity::Warning && thresholds.max_warnings.is_some())
+        });
+        serious || thresholds.max_warnings.is_some_and(|max| warnings > max)
+    }
+
+    /// Sorts the findings by path, line, column and rule, and the files by path, so that
+    /// reports of the same files are the same whatever the order they were checked in; and
+    /// gives each finding its [fingerprint], with the content of its line in its file as it is
+    /// now, or its message for the findings without a file
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::batch::lint_file;
+    /// use logseq::lint::RuleSet;
+    /// use logseq::report::Report;
+    /// let temp = assert_fs::TempDir::new().unwrap();
+    /// let path = temp.path().join("a.md");
+    /// let rules = RuleSet::builtin();
+    /// std::fs::write(&path, "- b  c\n- a  b\n").unwrap();
+    /// let mut report = Report::from_lint(Some(&path), &lint_file(&path, &rules).unwrap());
+    /// report.normalize();
+    /// std::fs::write(&path, "- a  b\n- new\n\t- b  c\n").unwrap();
+    /// let mut moved = Report::from_lint(Some(&path), &lint_file(&path, &rules).unwrap());
+    /// moved.normalize();
+    /// assert_eq!(report.findings[0].fingerprint, moved.findings[1].fingerprint);
+    /// assert_eq!(report.findings[1].fingerprint, moved.findings[0].fingerprint);
+    /// ```
+    pub fn normalize(&mut self) {
+        let sort = |findings: &mut Vec<Finding>| {
+            findings.sort_by(|a, b| {
+                (&a.path, a.line, a.column, &a.rule).cmp(&(&b.path, b.line, b.column, &b.rule))
+            })
+        };
+        sort(&mut self.findings);
+        sort(&mut self.suppressed);
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        // The path in the graph and the lines of each file, read once
+        let mut files: HashMap<PathBuf, (String, Vec<String>)> = HashMap::new();
+        for finding in self.findings.iter_mut().chain(&mut self.suppressed) {
+            let Some(path) = &finding.path else {
+                finding.fingerprint = fingerprint_key(&finding.rule, "", &finding.message);
+                continue;
+            };
+            let (graph_path, lines) = files.entry(path.clone()).or_insert_with(|| {
+                let lines = std::fs::read_to_string(path)
+                    .map(|text| text.lines().map(String::from).collect())
+                    .unwrap_or_default();
+                (graph_path(path), lines)
+            });
+            let content = finding
+                .line
+                .checked_sub(1)
+                .and_then(|index| lines.get(index));
+            finding.fingerprint = fingerprint_key(
+                &finding.rule,
+                graph_path,
+                content.unwrap_or(&finding.message),
+            );
+        }
+    }
+
+    /// Serializes the report as a single JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// A path relative to the root of its graph, or as it is outside of graphs, with `/` separators
+fn graph_path(path: &Path) -> String {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let relative = find_graph_root(&absolute)
+        .and_then(|root| Some(absolute.strip_prefix(root).ok()?.to_path_buf()))
+        .unwrap_or_else(|| path.to_path_buf());
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The fingerprint of a finding of a rule, with the path of its file in its graph
+fn fingerprint_key(rule: &str, graph_path: &str, content: &str) -> String {
+    let content: Vec<&str> = content.split_whitespace().collect();
+    let key = format!("{}\0{}\0{}", rule, graph_path, content.join(" "));
+    format!("{:016x}", fnv1a(key.as_bytes()))
+}
+
+/// The fingerprint of a finding of a rule in a file, with the content of its line: 16
+/// hexadecimal digits of a hash of the rule, of the path relative to the root of its graph, and
+/// of the content without its indentation and with its spaces collapsed. It stays the same when
+/// lines are added or removed around the finding, or the page is indented differently.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::report::fingerprint;
+/// use std::path::Path;
+/// let found = fingerprint("consecutive-spaces", Some(Path::new("pages/a.md")), "- a  b");
+/// assert_eq!(found.len(), 16);
+/// assert_eq!(found, fingerprint("consecutive-spaces", Some(Path::new("pages/a.md")), "\t- a    b "));
+/// assert_ne!(found, fingerprint("consecutive-spaces", Some(Path::new("pages/b.md")), "- a  b"));
+/// ```
+pub fn fingerprint(rule: &str, path: Option<&Path>, content: &str) -> String {
+    fingerprint_key(rule, &path.map(graph_path).unwrap_or_default(), content)
+}
@@ -0,0 +1,171 @@
+//! Baselines of known findings: recorded once, they are left out of later reports, so that a
+//! large graph can adopt a rule without fixing all of its pages first
+
+use super::{Finding, Report};
+use crate::{error, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Name of the baseline file, next to the config file at the root of a graph
+pub const BASELINE_FILE_NAME: &str = ".lsd-baseline.json";
+
+/// Known findings of a rule in a file. Lines and columns aren't recorded, so that known
+/// findings stay known when lines are added or removed before them.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    /// Id of the rule
+    pub rule: String,
+    /// Message of the findings
+    pub message: String,
+    /// How many findings of the file have this rule and message
+    pub count: usize,
+    /// The fingerprints of the findings, sorted, if the report had them (see
+    /// [Report::normalize]): the findings are then known by their fingerprint instead of
+    /// their message
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fingerprints: Vec<String>,
+}
+
+/// The known findings of each file, by path relative to the root of the baseline
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Known findings by file, sorted by rule and message
+    pub files: BTreeMap<String, Vec<BaselineEntry>>,
+}
+
+/// The path of the file of a finding relative to the root, with `/` separators
+fn relative_path(finding: &Finding, root: &Path) -> String {
+    let Some(path) = &finding.path else {
+        return String::new();
+    };
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = absolute(path);
+    let relative = path.strip_prefix(absolute(root)).unwrap_or(&path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl Baseline {
+    /// The baseline of the findings of a report, with paths relative to `root`
+    pub fn from_report(report: &Report, root: &Path) -> Self {
+        let mut entries: BTreeMap<String, BTreeMap<(String, String), BaselineEntry>> =
+            BTreeMap::new();
+        for finding in &report.findings {
+            let key = (finding.rule.clone(), finding.message.clone());
+            let entry = entries
+                .entry(relative_path(finding, root))
+                .or_default()
+                .entry(key)
+                .or_insert_with(|| BaselineEntry {
+                    rule: finding.rule.clone(),
+                    message: finding.message.clone(),
+                    count: 0,
+                    fingerprints: Vec::new(),
+                });
+            entry.count += 1;
+            if !finding.fingerprint.is_empty() {
+                entry.fingerprints.push(finding.fingerprint.clone());
+            }
+        }
+        let files = entries
+            .into_iter()
+            .map(|(path, entries)| {
+                let entries = entries
+                    .into_values()
+                    .map(|mut entry| {
+                        entry.fingerprints.sort();
+                        entry
+                    })
+                    .collect();
+                (path, entries)
+            })
+            .collect();
+        Baseline { files }
+    }
+
+    /// Reads a baseline file
+    pub fn read(path: &Path) -> Result<Self> {
+        serde_json::from_str(&error::read_to_string(path)?)
+            .map_err(|error| Error::parse(Some(path), format!("invalid baseline: {}", error)))
+    }
+
+    /// Writes the baseline to a file, as indented JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).unwrap_or_default();
+        error::write(path, &format!("{}\n", json))
+    }
+
+    /// Number of findings in the baseline
+    pub fn len(&self) -> usize {
+        self.files.values().flatten().map(|entry| entry.count).sum()
+    }
+
+    /// True if the baseline has no findings
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes the known findings from a report, with paths relative to `root`, and returns how
+    /// many were removed. Findings are known by their fingerprint when the baseline has them,
+    /// so that rewording a message doesn't make them new; otherwise, a file with more findings
+    /// of a rule and message than the baseline knows keeps its last ones, which are likely the
+    /// new ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{run_lints, RuleSet};
+    /// use logseq::report::baseline::Baseline;
+    /// use logseq::report::Report;
+    /// use std::path::Path;
+    /// let rules = RuleSet::builtin().select(&["consecutive-spaces"]);
+    /// let known = Report::from_diagnostics(None, &run_lints("- a  b", &rules));
+    /// let baseline = Baseline::from_report(&known, Path::new("."));
+    /// let mut report = Report::from_diagnostics(None, &run_lints("- new\n- a  b\n- c  d", &rules));
+    /// assert_eq!(baseline.filter(&mut report, Path::new(".")), 1);
+    /// assert_eq!(report.findings.len(), 1);
+    /// assert_eq!(report.findings[0].line, 3);
+    /// ```
+    pub fn filter(&self, report: &mut Report, root: &Path) -> usize {
+        // The known findings by path, rule, and message or fingerprint
+        let mut by_message: BTreeMap<(String, String, String), usize> = BTreeMap::new();
+        let mut by_fingerprint: BTreeMap<(String, String, String), usize> = BTreeMap::new();
+        for (path, entries) in &self.files {
+            for entry in entries {
+                if entry.fingerprints.is_empty() {
+                    let key = (path.clone(), entry.rule.clone(), entry.message.clone());
+                    by_message.insert(key, entry.count);
+                }
+                for fingerprint in &entry.fingerprints {
+                    let key = (path.clone(), entry.rule.clone(), fingerprint.clone());
+                    *by_fingerprint.entry(key).or_default() += 1;
+                }
+            }
+        }
+        let before = report.findings.len();
+        report.findings.retain(|finding| {
+            let path = relative_path(finding, root);
+            let by_fingerprint = by_fingerprint.get_mut(&(
+                path.clone(),
+                finding.rule.clone(),
+                finding.fingerprint.clone(),
+            ));
+            let remaining = match by_fingerprint {
+                Some(count) if *count > 0 => Some(count),
+                _ => by_message.get_mut(&(path, finding.rule.clone(), finding.message.clone())),
+            };
+            match remaining {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            }
+        });
+        before - report.findings.len()
+    }
+}
@@ -0,0 +1,204 @@
+//! Output formats of a report: plain text, JSON, JSON Lines and SARIF
+
+use super::{Finding, Report};
+use crate::lint::Severity;
+use crate::{Error, Result};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The SARIF schema of the reports
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How a report is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One `path:line:column: severity rule message` line per finding; suppressed findings are
+    /// left out
+    #[default]
+    Text,
+    /// The whole report as a single JSON object
+    Json,
+    /// One JSON object per finding and per line; suppressed findings are left out
+    JsonLines,
+    /// [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html),
+    /// e.g. for GitHub code scanning
+    Sarif,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    /// Parses `text`, `json`, `jsonl` (or `json-lines`) and `sarif`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "jsonl" | "json-lines" => Ok(Format::JsonLines),
+            "sarif" => Ok(Format::Sarif),
+            _ => Err(Error::parse(
+                None,
+                format!(
+                    "unknown format {}, expected text, json, jsonl or sarif",
+                    name
+                ),
+            )),
+        }
+    }
+}
+
+impl Format {
+    /// Writes a report in this format, ending with a newline unless there's nothing to write
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::lint::{run_lints, RuleSet};
+    /// use logseq::report::format::Format;
+    /// use logseq::report::Report;
+    /// use std::path::Path;
+    /// let diagnostics = run_lints("- a  b", &RuleSet::builtin());
+    /// let report = Report::from_diagnostics(Some(Path::new("pages/a.md")), &diagnostics);
+    /// assert_eq!(Format::Text.render(&report), "pages/a.md:1:4: warning consecutive-spaces Consecutive spaces\n");
+    /// assert_eq!(
+    ///     "jsonl".parse::<Format>().unwrap().render(&report),
+    ///     "{\"rule\":\"consecutive-spaces\",\"severity\":\"warning\",\"path\":\"pages/a.md\",\"line\":1,\"column\":4,\"message\":\"Consecutive spaces\",\"fixed\":false}\n"
+    /// );
+    /// assert!(Format::Sarif.render(&report).contains("\"uri\": \"pages/a.md\""));
+    /// let report = Report::from_diagnostics(Some(Path::new("pages/My page.md")), &diagnostics);
+    /// assert!(Format::Sarif.render(&report).contains("\"uri\": \"pages/My%20page.md\""));
+    /// ```
+    pub fn render(&self, report: &Report) -> String {
+        match self {
+            Format::Text => text(report),
+            Format::Json => report.to_json() + "\n",
+            Format::JsonLines => json_lines(report),
+            Format::Sarif => sarif(report),
+        }
+    }
+}
+
+/// One `path:line:column: severity rule message` line per finding
+pub fn text(report: &Report) -> String {
+    report
+        .findings
+        .iter()
+        .map(|finding| {
+            let location = match &finding.path {
+                Some(path) => format!("{}:", path.display()),
+                None => String::new(),
+            };
+            format!(
+                "{}{}:{}: {} {} {}\n",
+                location,
+                finding.line,
+                finding.column,
+                finding.severity,
+                finding.rule,
+                finding.message
+            )
+        })
+        .collect()
+}
+
+/// One JSON object per finding and per line
+pub fn json_lines(report: &Report) -> String {
+    report
+        .findings
+        .iter()
+        .map(|finding| serde_json::to_string(finding).unwrap_or_default() + "\n")
+        .collect()
+}
+
+/// The URI of a file in a SARIF log: relative paths with forward slashes, absolute paths as
+/// `file://` URIs, with the characters that aren't allowed in URIs percent-encoded
+fn artifact_uri(path: &Path) -> String {
+    let path_text = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::new();
+    for byte in path_text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    if !path.is_absolute() {
+        uri
+    } else if uri.starts_with('/') {
+        format!("file://{}", uri)
+    } else {
+        format!("file:///{}", uri)
+    }
+}
+
+/// The SARIF level of a severity
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "note",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+fn sarif_result(finding: &Finding, suppressed: bool) -> serde_json::Value {
+    let mut result = json!({
+        "ruleId": finding.rule,
+        "level": sarif_level(finding.severity),
+        "message": {"text": finding.message},
+        "properties": {"fixed": finding.fixed},
+    });
+    if suppressed {
+        result["suppressions"] = json!([{"kind": "inSource"}]);
+    }
+    if !finding.fingerprint.is_empty() {
+        result["partialFingerprints"] = json!({"lsd/v1": finding.fingerprint});
+    }
+    if let Some(path) = &finding.path {
+        result["locations"] = json!([{
+            "physicalLocation": {
+                "artifactLocation": {"uri": artifact_uri(path)},
+                "region": {"startLine": finding.line, "startColumn": finding.column},
+            }
+        }]);
+    }
+    result
+}
+
+/// A SARIF 2.1.0 log with a single run of Logseq Doctor; the rules are the ones with findings.
+/// Suppressed findings are results with an `inSource` suppression.
+pub fn sarif(report: &Report) -> String {
+    let rules: BTreeSet<&str> = report
+        .findings
+        .iter()
+        .chain(&report.suppressed)
+        .map(|finding| finding.rule.as_str())
+        .collect();
+    let results: Vec<serde_json::Value> = report
+        .findings
+        .iter()
+        .map(|finding| sarif_result(finding, false))
+        .chain(
+            report
+                .suppressed
+                .iter()
+                .map(|finding| sarif_result(finding, true)),
+        )
+        .collect();
+    let log = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "logseq-doctor",
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({"id": id})).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }],
+    });
+    serde_json::to_string_pretty(&log).unwrap_or_default() + "\n"
+}
@@ -0,0 +1,170 @@
+//! The types that property values must have, declared in the `[schema]` table of `.lsd.toml`
+
+use crate::journal::parse_date;
+use crate::properties::Value;
+use crate::toml::Toml;
+use crate::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// The type of a property in a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyType {
+    /// Any text
+    Text,
+    /// A whole number
+    Integer,
+    /// A whole or decimal number
+    Number,
+    /// `true` or `false`
+    Boolean,
+    /// A date like `2024-01-02`, or a link to a journal like `[[Jan 2nd, 2024]]`
+    Date,
+    /// One or more page references, like `[[page]]` or `#tag`
+    Page,
+    /// One of a list of values, compared case-insensitively, with or without `[[ ]]`
+    Enum(Vec<String>),
+}
+
+impl FromStr for PropertyType {
+    type Err = Error;
+
+    /// Parses `text`, `integer`, `number`, `boolean`, `date`, `page` and `enum[a, b]`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::schema::PropertyType;
+    /// assert_eq!("integer".parse::<PropertyType>().unwrap(), PropertyType::Integer);
+    /// assert_eq!("enum[reading, done]".parse::<PropertyType>().unwrap(),
+    ///     PropertyType::Enum(vec!["reading".to_string(), "done".to_string()]));
+    /// assert!("enum[]".parse::<PropertyType>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if let Some(values) = name
+            .strip_prefix("enum[")
+            .and_then(|values| values.strip_suffix(']'))
+        {
+            let values: Vec<String> = values
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect();
+            if values.is_empty() {
+                return Err(Error::parse(None, "enum[] needs at least one value"));
+            }
+            return Ok(PropertyType::Enum(values));
+        }
+        match name.to_lowercase().as_str() {
+            "text" => Ok(PropertyType::Text),
+            "integer" => Ok(PropertyType::Integer),
+            "number" => Ok(PropertyType::Number),
+            "boolean" => Ok(PropertyType::Boolean),
+            "date" => Ok(PropertyType::Date),
+            "page" => Ok(PropertyType::Page),
+            _ => Err(Error::parse(
+                None,
+                format!(
+                    "unknown type {}, expected text, integer, number, boolean, date, page or enum[...]",
+                    name
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PropertyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyType::Text => write!(f, "text"),
+            PropertyType::Integer => write!(f, "an integer"),
+            PropertyType::Number => write!(f, "a number"),
+            PropertyType::Boolean => write!(f, "true or false"),
+            PropertyType::Date => write!(f, "a date"),
+            PropertyType::Page => write!(f, "a page reference"),
+            PropertyType::Enum(values) => write!(f, "one of {}", values.join(", ")),
+        }
+    }
+}
+
+impl PropertyType {
+    /// True if the raw value of a property has this type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::schema::PropertyType;
+    /// assert!(PropertyType::Integer.accepts("rating", "5"));
+    /// assert!(!PropertyType::Integer.accepts("rating", "five"));
+    /// assert!(PropertyType::Date.accepts("started", "[[Jan 2nd, 2024]]"));
+    /// assert!(PropertyType::Enum(vec!["Done".to_string()]).accepts("status", "[[done]]"));
+    /// ```
+    pub fn accepts(&self, key: &str, raw: &str) -> bool {
+        let value = Value::parse(key, raw);
+        match self {
+            PropertyType::Text => true,
+            PropertyType::Integer => matches!(value, Value::Integer(_)),
+            PropertyType::Number => matches!(value, Value::Integer(_) | Value::Float(_)),
+            PropertyType::Boolean => matches!(value, Value::Bool(_)),
+            PropertyType::Date => parse_date(raw.trim()).is_some(),
+            PropertyType::Page => matches!(value, Value::Refs(_)),
+            PropertyType::Enum(values) => {
+                let raw = raw.trim();
+                let raw = raw
+                    .strip_prefix("[[")
+                    .and_then(|raw| raw.strip_suffix("]]"))
+                    .unwrap_or(raw);
+                values.iter().any(|value| value.eq_ignore_ascii_case(raw))
+            }
+        }
+    }
+}
+
+/// The expected types of properties, by key
+///
+/// ```toml
+/// [schema]
+/// rating = "integer"
+/// status = "enum[reading, done]"
+/// started = "date"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// Property keys, lowercase, with their types, in the order of the file
+    pub properties: Vec<(String, PropertyType)>,
+}
+
+impl Schema {
+    /// Reads the `[schema]` table of a config file
+    pub fn from_toml(table: &Toml) -> Result<Self> {
+        let entries = table
+            .as_table()
+            .ok_or_else(|| Error::parse(None, "schema must be a table"))?;
+        let mut properties = Vec::new();
+        for (key, value) in entries {
+            let kind = value
+                .as_str()
+                .ok_or_else(|| Error::parse(None, format!("schema.{} must be a string", key)))?
+                .parse()
+                .map_err(|error: Error| Error::parse(None, format!("schema.{}: {}", key, error)))?;
+            properties.push((key.to_lowercase(), kind));
+        }
+        Ok(Schema { properties })
+    }
+
+    /// The type of a property, if the schema declares it (keys are case-insensitive)
+    pub fn get(&self, key: &str) -> Option<&PropertyType> {
+        let key = key.to_lowercase();
+        self.properties
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, kind)| kind)
+    }
+
+    /// True if the schema declares no property
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+}
@@ -0,0 +1,289 @@
+//! Search the content of the blocks of a graph, and show each block found in its outline: its
+//! page, its parents and its children
+
+use crate::graph::{Graph, GraphPage};
+use crate::outline::Block;
+use crate::properties::{Properties, Value};
+use crate::{Error, Result};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A `key` or `key=value` filter on the properties of the blocks searched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertyFilter {
+    /// Key of the property, compared ignoring case
+    pub key: String,
+    /// The value the property must have, compared ignoring case, or None for any value. A
+    /// property with a list of references, like `tags::`, only needs one of them to be it.
+    pub value: Option<String>,
+}
+
+impl FromStr for PropertyFilter {
+    type Err = Error;
+
+    /// Parses `key` and `key=value`
+    fn from_str(filter: &str) -> Result<Self> {
+        let (key, value) = match filter.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim().to_string())),
+            None => (filter.trim(), None),
+        };
+        if key.is_empty() {
+            return Err(Error::parse(
+                None,
+                format!(
+                    "invalid property filter {}, expected key or key=value",
+                    filter
+                ),
+            ));
+        }
+        Ok(PropertyFilter {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+impl PropertyFilter {
+    /// True if the properties have the key, with the value if there is one
+    fn matches(&self, properties: &Properties) -> bool {
+        let Some(property) = properties.get(&self.key) else {
+            return false;
+        };
+        let Some(expected) = &self.value else {
+            return true;
+        };
+        match Value::parse(&property.key, &property.raw) {
+            Value::Refs(names) => names.iter().any(|name| name.eq_ignore_ascii_case(expected)),
+            _ => property.raw.trim().eq_ignore_ascii_case(expected),
+        }
+    }
+}
+
+/// What blocks to look for
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    /// The pattern searched in the content of blocks
+    pub pattern: Regex,
+    /// Filters that the properties of the block, or else of its page, must all match
+    pub properties: Vec<PropertyFilter>,
+}
+
+impl SearchQuery {
+    /// A query for a regular expression, or with `fixed`, for the text itself. The pattern is
+    /// searched in the whole content of blocks, so `^` and `$` match at the start and the end
+    /// of each of their lines, and `\n` matches between two lines.
+    /// Fails if the regular expression is invalid.
+    pub fn new(pattern: &str, fixed: bool, ignore_case: bool) -> Result<Self> {
+        let pattern = match fixed {
+            true => regex::escape(pattern),
+            false => pattern.to_string(),
+        };
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .multi_line(true)
+            .build()
+            .map_err(|error| Error::parse(None, format!("invalid pattern: {}", error)))?;
+        Ok(SearchQuery {
+            pattern,
+            properties: Vec::new(),
+        })
+    }
+}
+
+/// Where the pattern was found in a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchMatch {
+    /// Line number in the source, starting at 1
+    pub line: usize,
+    /// Column in the source, starting at 1
+    pub column: usize,
+    /// The text that matched
+    pub text: String,
+}
+
+/// A block whose content matches a query, with its context in the outline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+    /// File of the page with the block
+    pub path: PathBuf,
+    /// Name of the page
+    pub page: String,
+    /// Line of the bullet of the block, starting at 1
+    pub line: usize,
+    /// The first lines of the parents of the block, from the top-level one down
+    pub parents: Vec<String>,
+    /// The whole content of the block
+    pub content: String,
+    /// The first lines of the children of the block
+    pub children: Vec<String>,
+    /// Where the pattern was found in the content
+    pub matches: Vec<SearchMatch>,
+}
+
+/// The matches of the pattern in the content of a block
+fn block_matches(block: &Block, pattern: &Regex) -> Vec<SearchMatch> {
+    pattern
+        .find_iter(&block.content)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let before = &block.content[..found.start()];
+            let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+            let column = before[line_start..].chars().count() + 1;
+            SearchMatch {
+                line: block.line + before.matches('\n').count(),
+                column: block.source_column(column),
+                text: found.as_str().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Searches the blocks of `blocks` and their descendants, with the first lines of their parents
+fn search_blocks<'a>(
+    page: &GraphPage,
+    blocks: &'a [Block],
+    parents: &mut Vec<&'a str>,
+    query: &SearchQuery,
+    page_properties: &Properties,
+    hits: &mut Vec<SearchHit>,
+) {
+    for block in blocks {
+        let properties = block.properties();
+        let filtered = query
+            .properties
+            .iter()
+            .all(|filter| filter.matches(&properties) || filter.matches(page_properties));
+        let matches = match filtered {
+            true => block_matches(block, &query.pattern),
+            false => Vec::new(),
+        };
+        if !matches.is_empty() {
+            hits.push(SearchHit {
+                path: page.path.clone(),
+                page: page.name.clone(),
+                line: block.line,
+                parents: parents.iter().map(|parent| parent.to_string()).collect(),
+                content: block.content.clone(),
+                children: block
+                    .children
+                    .iter()
+                    .map(|child| child.first_line().to_string())
+                    .collect(),
+                matches,
+            });
+        }
+        parents.push(block.first_line());
+        search_blocks(page, &block.children, parents, query, page_properties, hits);
+        parents.pop();
+    }
+}
+
+/// Finds the blocks of a graph whose content matches a query, in the order of the pages of the
+/// graph and of the blocks in their page. Unlike a search of the lines of the files, a pattern
+/// matches the content of a block without its bullet and indentation, across its lines, and
+/// each block found comes with its parents and children.
+pub fn search_graph(graph: &Graph, query: &SearchQuery) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    for page in &graph.pages {
+        let page_properties = page.outline.page_properties();
+        let blocks = &page.outline.blocks;
+        search_blocks(
+            page,
+            blocks,
+            &mut Vec::new(),
+            query,
+            &page_properties,
+            &mut hits,
+        );
+    }
+    hits
+}
+
+/// How search results are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchFormat {
+    /// Each block found as an outline, under its page and parents, for people
+    #[default]
+    Text,
+    /// A JSON array of [SearchHit] objects
+    Json,
+}
+
+impl FromStr for SearchFormat {
+    type Err = Error;
+
+    /// Parses `text` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "text" => Ok(SearchFormat::Text),
+            "json" => Ok(SearchFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected text or json", name),
+            )),
+        }
+    }
+}
+
+impl SearchFormat {
+    /// Writes search results in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::search::{SearchFormat, SearchHit, SearchMatch};
+    /// let hit = SearchHit {
+    ///     path: "pages/Books.md".into(),
+    ///     page: "Books".to_string(),
+    ///     line: 2,
+    ///     parents: vec!["Reading".to_string()],
+    ///     content: "Dune\nby Frank Herbert".to_string(),
+    ///     children: vec!["Review".to_string()],
+    ///     matches: vec![SearchMatch { line: 3, column: 6, text: "Frank".to_string() }],
+    /// };
+    /// assert_eq!(SearchFormat::Text.render(&[hit]),
+    ///     "pages/Books.md:3:6: Books\n\
+    ///      - Reading\n\
+    ///      \t- Dune\n\
+    ///      \t  by Frank Herbert\n\
+    ///      \t\t- Review\n");
+    /// ```
+    pub fn render(&self, hits: &[SearchHit]) -> String {
+        match self {
+            SearchFormat::Json => serde_json::to_string_pretty(hits).unwrap_or_default() + "\n",
+            SearchFormat::Text => {
+                let mut text = String::new();
+                for (index, hit) in hits.iter().enumerate() {
+                    if index > 0 {
+                        text.push('\n');
+                    }
+                    let (line, column) = hit
+                        .matches
+                        .first()
+                        .map_or((hit.line, 1), |found| (found.line, found.column));
+                    text.push_str(&format!(
+                        "{}:{}:{}: {}\n",
+                        hit.path.display(),
+                        line,
+                        column,
+                        hit.page
+                    ));
+                    for (level, parent) in hit.parents.iter().enumerate() {
+                        text.push_str(&format!("{}- {}\n", "\t".repeat(level), parent));
+                    }
+                    let indent = "\t".repeat(hit.parents.len());
+                    for (offset, line) in hit.content.split('\n').enumerate() {
+                        let bullet = if offset == 0 { "- " } else { "  " };
+                        text.push_str(&format!("{}{}{}\n", indent, bullet, line));
+                    }
+                    for child in &hit.children {
+                        text.push_str(&format!("{}\t- {}\n", indent, child));
+                    }
+                }
+                text
+            }
+        }
+    }
+}
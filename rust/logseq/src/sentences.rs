@@ -0,0 +1,249 @@
+//! Split over-long blocks, like big pasted paragraphs, into a parent block and child blocks at
+//! sentence boundaries
+//!
+//! This is a transformation run on demand, not a lint rule: long blocks aren't wrong, and where
+//! to cut them is a matter of taste.
+
+use crate::error;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::IndentUnit;
+use crate::outline::{parse_outline, Block};
+use crate::properties::split_property;
+use crate::refs::blank_code_spans;
+use crate::rename::{write_edits, FileEdit};
+use crate::Result;
+use regex::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static PROTECTED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[\[[^\[\]]*\]\]|\(\([^()]*\)\)|\[[^\[\]]*\]\([^()]*\)|\$[^$]+\$").unwrap()
+});
+static TRAILING_REFS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\s+(?:#\[\[[^\[\]]+\]\]|#[^\s#\[\]]+|\[\[[^\[\]]+\]\]|\(\([^()]+\)\)))+\s*$")
+        .unwrap()
+});
+
+/// Default limit of the sentences of a block
+pub const MAX_SENTENCES: usize = 5;
+
+/// Default limit of the characters of a block
+pub const MAX_CHARS: usize = 1000;
+
+/// Words that end with a period without ending a sentence, in lowercase
+const ABBREVIATIONS: &[&str] = &[
+    "e.g", "i.e", "etc", "vs", "cf", "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "fig",
+    "no", "vol", "pp",
+];
+
+/// How long blocks may be before they are split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLimits {
+    /// The most sentences a block keeps
+    pub max_sentences: usize,
+    /// The most characters a block keeps, unless it is a single sentence
+    pub max_chars: usize,
+}
+
+impl Default for BlockLimits {
+    fn default() -> Self {
+        BlockLimits {
+            max_sentences: MAX_SENTENCES,
+            max_chars: MAX_CHARS,
+        }
+    }
+}
+
+/// The byte ranges of the sentences of a line, without the spaces between them. Sentences end
+/// with `.`, `!` or `?` followed by a space and a capital letter, a digit or an opening
+/// bracket or quote; periods of abbreviations and initials, and punctuation in inline code,
+/// links, block references and math, don't end sentences.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::sentences::sentences;
+/// let line = "Dune is a novel, e.g. by F. Herbert. Is it good? [[Yes. Very]] much.";
+/// let found: Vec<&str> = sentences(line).into_iter().map(|range| &line[range]).collect();
+/// assert_eq!(found, vec!["Dune is a novel, e.g. by F. Herbert.", "Is it good?", "[[Yes. Very]] much."]);
+/// ```
+pub fn sentences(line: &str) -> Vec<Range<usize>> {
+    let mut searchable = blank_code_spans(line);
+    for found in PROTECTED_RE.find_iter(line) {
+        searchable.replace_range(found.range(), &"X".repeat(found.len()));
+    }
+    let chars: Vec<(usize, char)> = searchable.char_indices().collect();
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for (index, &(offset, c)) in chars.iter().enumerate() {
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            continue;
+        }
+        let mut next = index + 1;
+        while chars
+            .get(next)
+            .is_some_and(|(_, c)| "\"'”’)]*_".contains(*c))
+        {
+            next += 1;
+        }
+        let end = chars
+            .get(next)
+            .map_or(searchable.len(), |(offset, _)| *offset);
+        if !chars.get(next).is_some_and(|(_, c)| c.is_whitespace()) {
+            continue;
+        }
+        let Some(&(following, first)) = chars[next..].iter().find(|(_, c)| !c.is_whitespace())
+        else {
+            continue;
+        };
+        if !(first.is_uppercase() || first.is_ascii_digit() || "\"'“‘([*_".contains(first)) {
+            continue;
+        }
+        if c == '.' {
+            let word = searchable[start..offset]
+                .rsplit(char::is_whitespace)
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches(['(', '"', '\''])
+                .to_lowercase();
+            let initial = word.chars().count() == 1 && word.chars().all(char::is_alphabetic);
+            if initial || ABBREVIATIONS.contains(&word.as_str()) {
+                continue;
+            }
+        }
+        ranges.push(start..end);
+        start = following;
+    }
+    let rest = line[start..].trim_end();
+    if !rest.trim().is_empty() {
+        ranges.push(start..start + rest.len());
+    }
+    ranges
+}
+
+/// The parts a line is split into: groups of sentences within the limits, or None if the line
+/// is within the limits or is a single sentence
+fn split_line(line: &str, limits: BlockLimits) -> Option<Vec<String>> {
+    let ranges = sentences(line);
+    let long = ranges.len() > limits.max_sentences || line.chars().count() > limits.max_chars;
+    if !long || ranges.len() < 2 {
+        return None;
+    }
+    let mut parts: Vec<(usize, String)> = Vec::new();
+    for range in ranges {
+        let sentence = &line[range];
+        let length = sentence.chars().count();
+        match parts.last_mut() {
+            Some((count, part))
+                if *count < limits.max_sentences
+                    && part.chars().count() + 1 + length <= limits.max_chars =>
+            {
+                *count += 1;
+                part.push(' ');
+                part.push_str(sentence);
+            }
+            _ => parts.push((1, sentence.to_string())),
+        }
+    }
+    (parts.len() > 1).then(|| parts.into_iter().map(|(_, part)| part).collect())
+}
+
+/// True if the first line of a block can be split: plain text, not a heading, a quote, a
+/// table or a property, and the only line of the block besides its properties
+fn splittable(block: &Block) -> bool {
+    let first = block.first_line().trim_start();
+    let plain = !first.starts_with(['#', '>', '|']) && split_property(first).is_none();
+    plain
+        && block
+            .content
+            .split('\n')
+            .skip(1)
+            .all(|line| line.trim().is_empty() || split_property(line).is_some())
+}
+
+/// Splits the blocks of a page longer than the limits: the first sentences stay in the block,
+/// with the refs and tags that end it and its properties, and the next ones become its first
+/// children, each as long as the limits allow. Headings, quotes, tables, and blocks with more
+/// than one line of text are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::sentences::{split_long_blocks, BlockLimits};
+/// let limits = BlockLimits { max_sentences: 2, max_chars: 1000 };
+/// let text = "- One. Two. Three. Four. Five. #book\n  id:: 65a1b2c3\n\t- Child\n";
+/// assert_eq!(
+///     split_long_blocks(text, limits),
+///     "- One. Two. #book\n  id:: 65a1b2c3\n\t- Three. Four.\n\t- Five.\n\t- Child\n"
+/// );
+/// ```
+pub fn split_long_blocks(text: &str, limits: BlockLimits) -> Cow<'_, str> {
+    preserving_line_endings_if_changed(text, |text| {
+        let outline = parse_outline(text);
+        let unit = outline
+            .iter()
+            .find(|block| block.level == 1)
+            .and_then(|block| IndentUnit::detect(&block.indent))
+            .unwrap_or(IndentUnit::Tab);
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut inserted: Vec<Vec<String>> = vec![Vec::new(); lines.len()];
+        let mut changed = false;
+        for block in outline.iter().filter(|block| splittable(block)) {
+            let first = block.first_line();
+            let Some(prefix) = lines[block.line - 1].strip_suffix(first).map(String::from) else {
+                continue;
+            };
+            let searchable = blank_code_spans(first);
+            let body_end = TRAILING_REFS_RE
+                .find(&searchable)
+                .map_or(first.len(), |found| found.start());
+            let Some(mut parts) = split_line(&first[..body_end], limits) else {
+                continue;
+            };
+            let children = parts.split_off(1);
+            lines[block.line - 1] =
+                format!("{}{}{}", prefix, parts[0], first[body_end..].trim_end());
+            let indent = unit.indent(block.level + 1);
+            let last = block.line + block.line_count() - 1;
+            for child in children {
+                inserted[last - 1].push(format!("{}- {}", indent, child));
+            }
+            changed = true;
+        }
+        if !changed {
+            return None;
+        }
+        let mut split = Vec::new();
+        for (line, mut children) in lines.into_iter().zip(inserted) {
+            split.push(line);
+            split.append(&mut children);
+        }
+        Some(split.join("\n"))
+    })
+}
+
+/// Splits the long blocks of files, see [split_long_blocks]; with `dry_run`, only plans the
+/// changes. Returns the files that change.
+pub fn split_block_files(
+    paths: &[PathBuf],
+    limits: BlockLimits,
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for path in paths {
+        let original = error::read_to_string(path)?;
+        if let Cow::Owned(updated) = split_long_blocks(&original, limits) {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,189 @@
+//! Split a long page into namespaced sub-pages, one for each of its top-level sections
+
+use crate::graph::{file_stem_from_page_name, page_key, Graph};
+use crate::outline::Block;
+use crate::properties::split_property;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use std::collections::HashSet;
+
+/// The headings that start a section by default: `#` and `##`
+pub const SPLIT_LEVEL: usize = 2;
+
+/// What replaces a section moved to its own page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SplitLeave {
+    /// A `[[Page/Section]]` link
+    #[default]
+    Link,
+    /// An `{{embed [[Page/Section]]}}`, which shows the section where it was
+    Embed,
+}
+
+/// Everything a split changes: the page, and the sub-pages it creates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSplit {
+    /// The page, with links or embeds instead of its sections
+    pub page: FileEdit,
+    /// The new pages, one per section, with an empty original
+    pub created: Vec<FileEdit>,
+}
+
+impl PageSplit {
+    /// Applies the split. All files are written to temporary files first, so a failure leaves
+    /// the graph untouched; they then replace the page and become the new pages.
+    pub fn apply(&self) -> Result<()> {
+        let mut edits = self.created.clone();
+        edits.push(self.page.clone());
+        write_edits(&edits)
+    }
+}
+
+/// The name of a section: its heading without the `#`, and without the brackets of links
+fn section_name(block: &Block) -> String {
+    block
+        .first_line()
+        .trim_start_matches('#')
+        .replace("[[", "")
+        .replace("]]", "")
+        .trim()
+        .to_string()
+}
+
+/// The contents of the page of a section: the properties of its heading (`id::` included, so
+/// block references to the heading resolve to the page) and its children, one level up
+fn section_page(block: &Block, lines: &[&str], last_line: usize) -> String {
+    let mut contents = Vec::new();
+    let mut text = Vec::new();
+    for (_, line) in block.numbered_lines().skip(1) {
+        match split_property(line) {
+            // How the heading was shown means nothing for a page
+            Some(("collapsed" | "heading", _)) => {}
+            Some(_) => contents.push(line.trim().to_string()),
+            None => text.push(line.trim().to_string()),
+        }
+    }
+    if !text.is_empty() {
+        if !contents.is_empty() {
+            contents.push(String::new());
+        }
+        contents.push(format!("{} {}", block.marker, text.join("\n  ")));
+    } else if !contents.is_empty() && !block.children.is_empty() {
+        contents.push(String::new());
+    }
+    let children_start = block.line + block.line_count() - 1;
+    let unit = match block.children.first() {
+        Some(child) => child
+            .indent
+            .strip_prefix(block.indent.as_str())
+            .unwrap_or(""),
+        None => "",
+    };
+    for line in &lines[children_start..last_line] {
+        let line = line.strip_prefix(block.indent.as_str()).unwrap_or(line);
+        contents.push(line.strip_prefix(unit).unwrap_or(line).to_string());
+    }
+    let mut contents = contents.join("\n").trim_end().to_string();
+    contents.push('\n');
+    contents
+}
+
+/// Plans the split of a page: each top-level block that is a heading of `level` or less (see
+/// [SPLIT_LEVEL]) moves with its children to a new page named `Page/Heading`, and is replaced
+/// by a link or an embed of the new page. The `id::` properties move with the blocks, so block
+/// references keep resolving. Nothing is written; see [PageSplit::apply].
+/// Fails if the page doesn't exist, is a journal, or if a new page already exists.
+pub fn plan_split(graph: &Graph, name: &str, level: usize, leave: SplitLeave) -> Result<PageSplit> {
+    let page = graph
+        .find_page(name)
+        .filter(|page| page.journal_date.is_none() && page_key(&page.name) == page_key(name))
+        .ok_or_else(|| Error::parse(None, format!("page not found: {}", name)))?;
+    let original = error::read_to_string(&page.path)?;
+    let lines: Vec<&str> = original.split('\n').collect();
+    let extension = page
+        .path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "md".to_string());
+
+    let mut updated: Vec<String> = Vec::new();
+    let mut next_line = 0;
+    let mut created = Vec::new();
+    let mut names = HashSet::new();
+    for block in &page.outline.blocks {
+        if block.heading_level().is_none_or(|heading| heading > level) {
+            continue;
+        }
+        let section = section_name(block);
+        if section.is_empty() {
+            continue;
+        }
+        let sub_page = format!("{}/{}", page.name, section);
+        if !names.insert(page_key(&sub_page)) {
+            return Err(Error::parse(
+                Some(&page.path),
+                format!("more than one section is named {}", section),
+            ));
+        }
+        let path = page.path.with_file_name(format!(
+            "{}.{}",
+            file_stem_from_page_name(&sub_page),
+            extension
+        ));
+        if graph.find_page(&sub_page).is_some() || path.exists() {
+            return Err(Error::parse(
+                Some(&path),
+                format!("a page named {} already exists", sub_page),
+            ));
+        }
+        let last = block.iter().last().unwrap_or(block);
+        let last_line = last.line + last.line_count() - 1;
+        created.push(FileEdit {
+            path,
+            original: String::new(),
+            updated: section_page(block, &lines, last_line),
+        });
+
+        updated.extend(
+            lines[next_line..block.line - 1]
+                .iter()
+                .map(|line| line.to_string()),
+        );
+        let reference = match leave {
+            SplitLeave::Link => format!("[[{}]]", sub_page),
+            SplitLeave::Embed => format!("{{{{embed [[{}]]}}}}", sub_page),
+        };
+        updated.push(format!("{}{} {}", block.indent, block.marker, reference));
+        next_line = last_line;
+    }
+    if created.is_empty() {
+        return Err(Error::parse(
+            Some(&page.path),
+            format!("no top-level headings of level {} or less to split", level),
+        ));
+    }
+    updated.extend(lines[next_line..].iter().map(|line| line.to_string()));
+    Ok(PageSplit {
+        page: FileEdit {
+            path: page.path.clone(),
+            original,
+            updated: updated.join("\n"),
+        },
+        created,
+    })
+}
+
+/// Splits a page into sub-pages, see [plan_split]; with `dry_run`, only plans the changes
+pub fn split_page(
+    graph: &Graph,
+    name: &str,
+    level: usize,
+    leave: SplitLeave,
+    dry_run: bool,
+) -> Result<PageSplit> {
+    let split = plan_split(graph, name, level, leave)?;
+    if !dry_run {
+        split.apply()?;
+    }
+    Ok(split)
+}
@@ -0,0 +1,632 @@
+//! Index a graph into a SQLite database: its pages, blocks, references, properties and tasks, to
+//! query it with SQL, like "blocks tagged #book without an author:: property":
+//!
+//! ```sql
+//! SELECT b.content FROM blocks b
+//! JOIN refs r ON r.source = b.id JOIN pages p ON p.id = r.target
+//! WHERE p.name = 'book'
+//!   AND NOT EXISTS (SELECT 1 FROM properties WHERE entity = b.id AND key = 'author')
+//! ```
+//!
+//! The database is written with the SQLite library of the system, see [SCHEMA] for its tables.
+
+use crate::export::database::{dump_graph, PropertyValue};
+use crate::graph::Graph;
+use crate::tasks::{priority, split_marker, Timestamp, TimestampKind};
+use crate::{Error, Result};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::fmt;
+use std::path::Path;
+use std::ptr;
+use std::str::FromStr;
+
+/// The tables of the index. Pages and blocks share their ids, so that `refs.target` and
+/// `properties.entity` can be either.
+pub const SCHEMA: &str = "
+CREATE TABLE pages (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,          -- lowercase, as in links
+    original_name TEXT NOT NULL,
+    file TEXT,                   -- relative to the graph, NULL for pages without a file
+    journal_day INTEGER          -- YYYYMMDD for journals
+);
+CREATE TABLE blocks (
+    id INTEGER PRIMARY KEY,
+    uuid TEXT,                   -- of the id:: property
+    page INTEGER NOT NULL REFERENCES pages(id),
+    parent INTEGER NOT NULL,     -- a block, or the page for top-level blocks
+    position INTEGER NOT NULL,   -- among the children of the parent, from 0
+    level INTEGER NOT NULL,      -- from 1 for top-level blocks
+    line INTEGER NOT NULL,
+    content TEXT NOT NULL
+);
+CREATE TABLE refs (
+    source INTEGER NOT NULL,     -- a block, or a page for page properties
+    target INTEGER NOT NULL      -- a page or a block
+);
+CREATE TABLE aliases (
+    page INTEGER NOT NULL REFERENCES pages(id),
+    alias INTEGER NOT NULL REFERENCES pages(id)
+);
+CREATE TABLE properties (
+    entity INTEGER NOT NULL,     -- a page or a block
+    key TEXT NOT NULL COLLATE NOCASE,
+    value                        -- one row for each page of a list like tags::
+);
+CREATE TABLE tasks (
+    block INTEGER PRIMARY KEY REFERENCES blocks(id),
+    marker TEXT NOT NULL,
+    priority TEXT,
+    scheduled TEXT,              -- YYYY-MM-DD
+    deadline TEXT
+);
+CREATE INDEX refs_source ON refs(source);
+CREATE INDEX refs_target ON refs(target);
+CREATE INDEX properties_entity ON properties(entity, key);
+";
+
+#[allow(non_camel_case_types)]
+enum sqlite3 {}
+#[allow(non_camel_case_types)]
+enum sqlite3_stmt {}
+
+const SQLITE_OK: c_int = 0;
+const SQLITE_ROW: c_int = 100;
+const SQLITE_DONE: c_int = 101;
+const SQLITE_INTEGER: c_int = 1;
+const SQLITE_FLOAT: c_int = 2;
+const SQLITE_TEXT: c_int = 3;
+const SQLITE_BLOB: c_int = 4;
+const SQLITE_OPEN_READWRITE: c_int = 0x2;
+const SQLITE_OPEN_CREATE: c_int = 0x4;
+/// Tells SQLite to copy bound text before the call returns
+const SQLITE_TRANSIENT: isize = -1;
+
+#[link(name = "sqlite3")]
+extern "C" {
+    fn sqlite3_open_v2(
+        filename: *const c_char,
+        db: *mut *mut sqlite3,
+        flags: c_int,
+        vfs: *const c_char,
+    ) -> c_int;
+    fn sqlite3_close(db: *mut sqlite3) -> c_int;
+    fn sqlite3_errmsg(db: *mut sqlite3) -> *const c_char;
+    fn sqlite3_prepare_v2(
+        db: *mut sqlite3,
+        sql: *const c_char,
+        bytes: c_int,
+        statement: *mut *mut sqlite3_stmt,
+        tail: *mut *const c_char,
+    ) -> c_int;
+    fn sqlite3_bind_null(statement: *mut sqlite3_stmt, index: c_int) -> c_int;
+    fn sqlite3_bind_int64(statement: *mut sqlite3_stmt, index: c_int, value: i64) -> c_int;
+    fn sqlite3_bind_double(statement: *mut sqlite3_stmt, index: c_int, value: f64) -> c_int;
+    fn sqlite3_bind_text(
+        statement: *mut sqlite3_stmt,
+        index: c_int,
+        text: *const c_char,
+        bytes: c_int,
+        destructor: isize,
+    ) -> c_int;
+    fn sqlite3_bind_blob(
+        statement: *mut sqlite3_stmt,
+        index: c_int,
+        blob: *const c_void,
+        bytes: c_int,
+        destructor: isize,
+    ) -> c_int;
+    fn sqlite3_step(statement: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_reset(statement: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_finalize(statement: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_column_count(statement: *mut sqlite3_stmt) -> c_int;
+    fn sqlite3_column_name(statement: *mut sqlite3_stmt, index: c_int) -> *const c_char;
+    fn sqlite3_column_type(statement: *mut sqlite3_stmt, index: c_int) -> c_int;
+    fn sqlite3_column_int64(statement: *mut sqlite3_stmt, index: c_int) -> i64;
+    fn sqlite3_column_double(statement: *mut sqlite3_stmt, index: c_int) -> f64;
+    fn sqlite3_column_text(statement: *mut sqlite3_stmt, index: c_int) -> *const u8;
+    fn sqlite3_column_blob(statement: *mut sqlite3_stmt, index: c_int) -> *const c_void;
+    fn sqlite3_column_bytes(statement: *mut sqlite3_stmt, index: c_int) -> c_int;
+}
+
+/// A value of a column or of a parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    /// `NULL`
+    Null,
+    /// A whole number
+    Integer(i64),
+    /// A floating point number
+    Real(f64),
+    /// Text
+    Text(String),
+    /// Bytes
+    Blob(Vec<u8>),
+}
+
+impl fmt::Display for SqlValue {
+    /// Writes the value as text; `NULL` is empty and blobs are in hexadecimal
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlValue::Null => Ok(()),
+            SqlValue::Integer(number) => write!(f, "{}", number),
+            SqlValue::Real(number) => write!(f, "{}", number),
+            SqlValue::Text(text) => f.write_str(text),
+            SqlValue::Blob(bytes) => bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte)),
+        }
+    }
+}
+
+impl From<&str> for SqlValue {
+    fn from(text: &str) -> Self {
+        SqlValue::Text(text.to_string())
+    }
+}
+
+impl From<usize> for SqlValue {
+    fn from(number: usize) -> Self {
+        SqlValue::Integer(number as i64)
+    }
+}
+
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(SqlValue::Null, Into::into)
+    }
+}
+
+/// The result of a query: the names of its columns and its rows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rows {
+    /// Names of the columns
+    pub columns: Vec<String>,
+    /// Values of each row, one per column
+    pub rows: Vec<Vec<SqlValue>>,
+}
+
+/// A SQLite database
+pub struct Database {
+    db: *mut sqlite3,
+}
+
+/// A prepared statement of a [Database]
+struct Statement<'a> {
+    database: &'a Database,
+    statement: *mut sqlite3_stmt,
+}
+
+impl Database {
+    /// Opens a database file, creating it if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        let name = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| Error::parse(Some(path), "invalid database path"))?;
+        Self::open_name(&name).map_err(|error| error.with_path(path))
+    }
+
+    /// Opens a new database in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::open_name(c":memory:")
+    }
+
+    fn open_name(name: &CStr) -> Result<Self> {
+        let mut db = ptr::null_mut();
+        let flags = SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE;
+        // SAFETY: `name` is a valid C string; SQLite allocates a handle even on failure, which
+        // is closed when the database is dropped
+        let code = unsafe { sqlite3_open_v2(name.as_ptr(), &mut db, flags, ptr::null()) };
+        let database = Database { db };
+        if code != SQLITE_OK {
+            return Err(database.error());
+        }
+        Ok(database)
+    }
+
+    /// The last error of the database
+    fn error(&self) -> Error {
+        // SAFETY: SQLite returns a valid C string for any handle, even a null one
+        let message = unsafe { CStr::from_ptr(sqlite3_errmsg(self.db)) };
+        Error::parse(None, format!("SQLite: {}", message.to_string_lossy()))
+    }
+
+    /// Prepares the first statement of `sql`, returning it and the rest of `sql`, or None if
+    /// there are only whitespace and comments
+    fn prepare<'s>(&self, sql: &'s str) -> Result<(Option<Statement<'_>>, &'s str)> {
+        let bytes = c_int::try_from(sql.len())
+            .map_err(|_| Error::parse(None, "SQLite: statement too long"))?;
+        let mut statement = ptr::null_mut();
+        let mut tail: *const c_char = ptr::null();
+        // SAFETY: SQLite reads `bytes` bytes of `sql` and points `tail` inside of it
+        let code = unsafe {
+            sqlite3_prepare_v2(
+                self.db,
+                sql.as_ptr().cast(),
+                bytes,
+                &mut statement,
+                &mut tail,
+            )
+        };
+        if code != SQLITE_OK {
+            return Err(self.error());
+        }
+        let consumed = if tail.is_null() {
+            sql.len()
+        } else {
+            tail as usize - sql.as_ptr() as usize
+        };
+        let statement = (!statement.is_null()).then_some(Statement {
+            database: self,
+            statement,
+        });
+        Ok((statement, &sql[consumed..]))
+    }
+
+    /// Prepares a single statement
+    fn statement(&self, sql: &str) -> Result<Statement<'_>> {
+        self.prepare(sql)?
+            .0
+            .ok_or_else(|| Error::parse(None, "SQLite: empty statement"))
+    }
+
+    /// Runs statements separated by `;`, returning the rows of the last one that has columns
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::sqlite::{Database, SqlValue};
+    /// let database = Database::in_memory().unwrap();
+    /// let rows = database.query("CREATE TABLE t (n); INSERT INTO t VALUES (1), (NULL); SELECT n, 'x' AS s FROM t").unwrap();
+    /// assert_eq!(rows.columns, vec!["n", "s"]);
+    /// assert_eq!(rows.rows[0], vec![SqlValue::Integer(1), SqlValue::Text("x".to_string())]);
+    /// assert_eq!(rows.rows[1][0], SqlValue::Null);
+    /// assert!(database.query("SELECT * FROM missing").unwrap_err().to_string().contains("no such table"));
+    /// ```
+    pub fn query(&self, sql: &str) -> Result<Rows> {
+        let mut rows = Rows::default();
+        let mut rest = sql;
+        loop {
+            let (statement, tail) = self.prepare(rest)?;
+            let Some(mut statement) = statement else {
+                break;
+            };
+            let result = statement.rows()?;
+            if !result.columns.is_empty() {
+                rows = result;
+            }
+            rest = tail;
+        }
+        Ok(rows)
+    }
+
+    /// Runs statements separated by `;`, ignoring their rows
+    pub fn execute(&self, sql: &str) -> Result<()> {
+        self.query(sql).map(|_| ())
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        // SAFETY: statements borrow the database, so they were all finalized
+        unsafe { sqlite3_close(self.db) };
+    }
+}
+
+impl Statement<'_> {
+    fn check(&self, code: c_int) -> Result<()> {
+        if code == SQLITE_OK {
+            Ok(())
+        } else {
+            Err(self.database.error())
+        }
+    }
+
+    /// Binds the parameters, from `?1`
+    fn bind(&mut self, values: &[SqlValue]) -> Result<()> {
+        for (index, value) in values.iter().enumerate() {
+            let index = index as c_int + 1;
+            // SAFETY: text and blobs are copied by SQLite before the calls return
+            let code = unsafe {
+                match value {
+                    SqlValue::Null => sqlite3_bind_null(self.statement, index),
+                    SqlValue::Integer(number) => sqlite3_bind_int64(self.statement, index, *number),
+                    SqlValue::Real(number) => sqlite3_bind_double(self.statement, index, *number),
+                    SqlValue::Text(text) => sqlite3_bind_text(
+                        self.statement,
+                        index,
+                        text.as_ptr().cast(),
+                        text.len() as c_int,
+                        SQLITE_TRANSIENT,
+                    ),
+                    SqlValue::Blob(bytes) => sqlite3_bind_blob(
+                        self.statement,
+                        index,
+                        bytes.as_ptr().cast(),
+                        bytes.len() as c_int,
+                        SQLITE_TRANSIENT,
+                    ),
+                }
+            };
+            self.check(code)?;
+        }
+        Ok(())
+    }
+
+    /// The value of a column of the current row
+    fn column(&self, index: c_int) -> SqlValue {
+        // SAFETY: the statement is on a row and `index` is one of its columns; SQLite keeps
+        // text and blobs valid until the next step, and they are copied before that
+        unsafe {
+            match sqlite3_column_type(self.statement, index) {
+                SQLITE_INTEGER => SqlValue::Integer(sqlite3_column_int64(self.statement, index)),
+                SQLITE_FLOAT => SqlValue::Real(sqlite3_column_double(self.statement, index)),
+                SQLITE_TEXT => {
+                    let text = sqlite3_column_text(self.statement, index);
+                    let bytes = sqlite3_column_bytes(self.statement, index) as usize;
+                    if text.is_null() {
+                        SqlValue::Text(String::new())
+                    } else {
+                        let text = std::slice::from_raw_parts(text, bytes);
+                        SqlValue::Text(String::from_utf8_lossy(text).to_string())
+                    }
+                }
+                SQLITE_BLOB => {
+                    let blob = sqlite3_column_blob(self.statement, index);
+                    let bytes = sqlite3_column_bytes(self.statement, index) as usize;
+                    if blob.is_null() {
+                        SqlValue::Blob(Vec::new())
+                    } else {
+                        SqlValue::Blob(std::slice::from_raw_parts(blob.cast(), bytes).to_vec())
+                    }
+                }
+                _ => SqlValue::Null,
+            }
+        }
+    }
+
+    /// Runs the statement to the end, collecting its rows
+    fn rows(&mut self) -> Result<Rows> {
+        // SAFETY: the statement is valid until it is finalized when dropped
+        let count = unsafe { sqlite3_column_count(self.statement) };
+        let columns = (0..count)
+            .map(|index| {
+                // SAFETY: `index` is one of the columns
+                let name = unsafe { sqlite3_column_name(self.statement, index) };
+                if name.is_null() {
+                    String::new()
+                } else {
+                    // SAFETY: SQLite returns a valid C string
+                    unsafe { CStr::from_ptr(name) }
+                        .to_string_lossy()
+                        .to_string()
+                }
+            })
+            .collect();
+        let mut rows = Vec::new();
+        loop {
+            // SAFETY: the statement is valid
+            match unsafe { sqlite3_step(self.statement) } {
+                SQLITE_ROW => rows.push((0..count).map(|index| self.column(index)).collect()),
+                SQLITE_DONE => break,
+                _ => return Err(self.database.error()),
+            }
+        }
+        Ok(Rows { columns, rows })
+    }
+
+    /// Runs the statement with parameters, then resets it to run again
+    fn insert(&mut self, values: &[SqlValue]) -> Result<()> {
+        self.bind(values)?;
+        // SAFETY: the statement is valid
+        let code = unsafe { sqlite3_step(self.statement) };
+        // SAFETY: the statement is valid
+        unsafe { sqlite3_reset(self.statement) };
+        if code != SQLITE_DONE {
+            return Err(self.database.error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the statement is only finalized here
+        unsafe { sqlite3_finalize(self.statement) };
+    }
+}
+
+/// The values of a property, one per page for lists of references
+fn property_values(value: &PropertyValue) -> Vec<SqlValue> {
+    match value {
+        PropertyValue::Text(text) => vec![SqlValue::Text(text.clone())],
+        PropertyValue::Integer(number) => vec![SqlValue::Integer(*number)],
+        PropertyValue::Float(number) => vec![SqlValue::Real(*number)],
+        PropertyValue::Bool(flag) => vec![SqlValue::Integer(*flag as i64)],
+        PropertyValue::Date(date) => vec![SqlValue::Text(date.to_string())],
+        PropertyValue::Refs(names) => names.iter().map(|name| name.as_str().into()).collect(),
+    }
+}
+
+/// Writes the pages, blocks, references, properties and tasks of a graph into a database
+/// without the tables of the [SCHEMA], in a single transaction
+pub fn index_graph(graph: &Graph, database: &Database) -> Result<()> {
+    let dump = dump_graph(graph);
+    database.execute("BEGIN")?;
+    database.execute(SCHEMA)?;
+    let statement = |sql| database.statement(sql);
+    let mut pages = statement("INSERT INTO pages VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    let mut blocks = statement("INSERT INTO blocks VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+    let mut refs = statement("INSERT INTO refs VALUES (?1, ?2)")?;
+    let mut aliases = statement("INSERT INTO aliases VALUES (?1, ?2)")?;
+    let mut properties = statement("INSERT INTO properties VALUES (?1, ?2, ?3)")?;
+    let mut tasks = statement("INSERT INTO tasks VALUES (?1, ?2, ?3, ?4, ?5)")?;
+    for page in &dump.pages {
+        let file = page
+            .file
+            .as_ref()
+            .map(|file| file.to_string_lossy().replace('\\', "/"));
+        pages.insert(&[
+            page.id.into(),
+            page.name.as_str().into(),
+            page.original_name.as_str().into(),
+            file.as_deref().into(),
+            page.journal_day.map(|day| day as usize).into(),
+        ])?;
+        for &alias in &page.aliases {
+            aliases.insert(&[page.id.into(), alias.into()])?;
+        }
+        for &target in &page.refs {
+            refs.insert(&[page.id.into(), target.into()])?;
+        }
+        for (key, value) in &page.properties {
+            for value in property_values(value) {
+                properties.insert(&[page.id.into(), key.as_str().into(), value])?;
+            }
+        }
+    }
+    for block in &dump.blocks {
+        blocks.insert(&[
+            block.id.into(),
+            block.uuid.as_deref().into(),
+            block.page.into(),
+            block.parent.into(),
+            block.order.into(),
+            block.level.into(),
+            block.line.into(),
+            block.content.as_str().into(),
+        ])?;
+        for &target in &block.refs {
+            refs.insert(&[block.id.into(), target.into()])?;
+        }
+        for (key, value) in &block.properties {
+            for value in property_values(value) {
+                properties.insert(&[block.id.into(), key.as_str().into(), value])?;
+            }
+        }
+        let first_line = block.content.split('\n').next().unwrap_or_default();
+        if let Some((marker, _)) = split_marker(first_line) {
+            let date = |kind| {
+                block
+                    .content
+                    .split('\n')
+                    .filter_map(|line| Timestamp::parse(line)?.ok())
+                    .find(|timestamp| timestamp.kind == kind)
+                    .map(|timestamp| SqlValue::Text(timestamp.date.to_string()))
+                    .unwrap_or(SqlValue::Null)
+            };
+            tasks.insert(&[
+                block.id.into(),
+                marker.into(),
+                priority(first_line)
+                    .map(|letter| SqlValue::Text(letter.to_string()))
+                    .unwrap_or(SqlValue::Null),
+                date(TimestampKind::Scheduled),
+                date(TimestampKind::Deadline),
+            ])?;
+        }
+    }
+    drop((pages, blocks, refs, aliases, properties, tasks));
+    database.execute("COMMIT")
+}
+
+/// How the rows of a query are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryFormat {
+    /// Tab-separated values with a header line, with tabs, line breaks and backslashes of
+    /// values escaped as `\t`, `\n` and `\\`
+    #[default]
+    Tsv,
+    /// A JSON array with an object for each row
+    Json,
+}
+
+impl FromStr for QueryFormat {
+    type Err = Error;
+
+    /// Parses `tsv` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "tsv" => Ok(QueryFormat::Tsv),
+            "json" => Ok(QueryFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected tsv or json", name),
+            )),
+        }
+    }
+}
+
+/// A TSV field, escaped
+fn tsv_field(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+impl QueryFormat {
+    /// Writes the rows of a query in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::sqlite::{QueryFormat, Rows, SqlValue};
+    /// let rows = Rows {
+    ///     columns: vec!["name".to_string(), "n".to_string()],
+    ///     rows: vec![vec![SqlValue::Text("a\tb".to_string()), SqlValue::Null]],
+    /// };
+    /// assert_eq!(QueryFormat::Tsv.render(&rows), "name\tn\na\\tb\t\n");
+    /// assert_eq!("json".parse::<QueryFormat>().unwrap().render(&rows),
+    ///     "[\n  {\n    \"name\": \"a\\tb\",\n    \"n\": null\n  }\n]\n");
+    /// ```
+    pub fn render(&self, rows: &Rows) -> String {
+        match self {
+            QueryFormat::Tsv => {
+                let mut output = String::new();
+                let lines =
+                    std::iter::once(rows.columns.iter().map(|name| tsv_field(name)).collect())
+                        .chain(rows.rows.iter().map(|row| {
+                            row.iter()
+                                .map(|value| tsv_field(&value.to_string()))
+                                .collect::<Vec<String>>()
+                        }));
+                for line in lines {
+                    output.push_str(&line.join("\t"));
+                    output.push('\n');
+                }
+                output
+            }
+            QueryFormat::Json => {
+                // Written by hand to keep the columns in order
+                let quote = |text: &str| serde_json::to_string(text).unwrap_or_default();
+                let objects: Vec<String> = rows
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let fields: Vec<String> = rows
+                            .columns
+                            .iter()
+                            .zip(row)
+                            .map(|(column, value)| {
+                                let value = match value {
+                                    SqlValue::Null => "null".to_string(),
+                                    SqlValue::Integer(number) => number.to_string(),
+                                    SqlValue::Real(number) if number.is_finite() => {
+                                        number.to_string()
+                                    }
+                                    SqlValue::Real(_) => "null".to_string(),
+                                    other => quote(&other.to_string()),
+                                };
+                                format!("    {}: {}", quote(column), value)
+                            })
+                            .collect();
+                        format!("  {{\n{}\n  }}", fields.join(",\n"))
+                    })
+                    .collect();
+                if objects.is_empty() {
+                    "[]\n".to_string()
+                } else {
+                    format!("[\n{}\n]\n", objects.join(",\n"))
+                }
+            }
+        }
+    }
+}
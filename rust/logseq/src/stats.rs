@@ -0,0 +1,292 @@
+//! Statistics about a graph: how many pages, blocks, words and tasks it has, and which pages
+//! are referenced the most
+
+use crate::assets::check_assets;
+use crate::graph::backlinks::build_backlinks;
+use crate::graph::{collect_files_matching, page_key, Graph};
+use crate::properties::split_property;
+use crate::tasks::{split_marker, TASK_MARKERS};
+use crate::{Error, Result, SUBDIR_ASSETS};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::str::FromStr;
+
+/// How many of the most referenced pages are listed, by default
+pub const TOP_PAGES: usize = 10;
+
+/// A page and how many times other pages refer to it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageReferences {
+    /// Name of the page, or the name of the first reference for pages without a file
+    pub name: String,
+    /// References from other pages, through its name or its aliases
+    pub references: usize,
+}
+
+/// Counts of what's in a graph
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GraphStats {
+    /// Pages that aren't journals
+    pub pages: usize,
+    /// Journals
+    pub journals: usize,
+    /// Blocks of all pages and journals, at any level
+    pub blocks: usize,
+    /// Words of the blocks, without properties and drawers like `:LOGBOOK:`
+    pub words: usize,
+    /// Tasks by marker, e.g. `TODO`
+    pub tasks: BTreeMap<String, usize>,
+    /// Pages that nothing links to, see [Graph::find_orphans]
+    pub orphans: usize,
+    /// Files of the `assets` directory
+    pub assets: usize,
+    /// Total size of the assets, in bytes
+    pub asset_bytes: u64,
+    /// Assets that no page links to
+    pub unused_assets: usize,
+    /// The most referenced pages, most referenced first
+    pub top_pages: Vec<PageReferences>,
+}
+
+/// The number of words of a block: its lines without the properties and drawers
+fn word_count(content: &str) -> usize {
+    let mut in_drawer = false;
+    let mut words = 0;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let is_drawer_line = trimmed.len() > 2
+            && trimmed.starts_with(':')
+            && trimmed.ends_with(':')
+            && !trimmed[1..trimmed.len() - 1].contains(char::is_whitespace);
+        if is_drawer_line {
+            in_drawer = !trimmed.eq_ignore_ascii_case(":END:");
+            continue;
+        }
+        if in_drawer || split_property(line).is_some() {
+            continue;
+        }
+        words += line.split_whitespace().count();
+    }
+    words
+}
+
+/// Computes the statistics of a graph, with its `top` most referenced pages. References from a
+/// page to itself don't count, and references to an alias count for the page.
+pub fn graph_stats(graph: &Graph, top: usize) -> Result<GraphStats> {
+    let mut stats = GraphStats::default();
+    for page in &graph.pages {
+        match page.journal_date {
+            Some(_) => stats.journals += 1,
+            None => stats.pages += 1,
+        }
+        for block in page.outline.iter() {
+            stats.blocks += 1;
+            stats.words += word_count(&block.content);
+            if let Some((marker, _)) = split_marker(block.first_line()) {
+                *stats.tasks.entry(marker.to_string()).or_default() += 1;
+            }
+        }
+    }
+    stats.orphans = graph.find_orphans()?.len();
+
+    let assets_dir = graph.root.join(SUBDIR_ASSETS);
+    let mut assets = Vec::new();
+    collect_files_matching(&assets_dir, &|_| true, &mut assets)?;
+    stats.assets = assets.len();
+    for asset in &assets {
+        stats.asset_bytes += fs::metadata(asset)
+            .map_err(|source| Error::io(asset, source))?
+            .len();
+    }
+    stats.unused_assets = check_assets(graph)?.orphans.len();
+
+    // (name, references) by the key of the page, with aliases resolved
+    let mut counts: HashMap<String, PageReferences> = HashMap::new();
+    for (key, references) in build_backlinks(graph) {
+        let page = graph.find_page(&key);
+        let key = page.map_or(key, |page| page_key(&page.name));
+        let from_others = references
+            .iter()
+            .filter(|reference| page.is_none_or(|page| page.path != reference.path))
+            .count();
+        let Some(first) = references.first() else {
+            continue;
+        };
+        counts
+            .entry(key)
+            .or_insert_with(|| PageReferences {
+                name: page.map_or(first.target.clone(), |page| page.name.clone()),
+                references: 0,
+            })
+            .references += from_others;
+    }
+    let mut top_pages: Vec<PageReferences> = counts
+        .into_values()
+        .filter(|page| page.references > 0)
+        .collect();
+    top_pages
+        .sort_by(|a, b| (b.references, page_key(&a.name)).cmp(&(a.references, page_key(&b.name))));
+    top_pages.truncate(top);
+    stats.top_pages = top_pages;
+    Ok(stats)
+}
+
+/// A size in bytes for people, e.g. `1.5 MB`
+///
+/// # Examples
+///
+/// ```
+/// use logseq::stats::human_size;
+/// assert_eq!(human_size(900), "900 B");
+/// assert_eq!(human_size(1536), "1.5 KB");
+/// assert_eq!(human_size(3 * 1024 * 1024), "3.0 MB");
+/// ```
+pub fn human_size(bytes: u64) -> String {
+    let units = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+/// How graph statistics are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatsFormat {
+    /// Aligned columns, for people
+    #[default]
+    Table,
+    /// A JSON [GraphStats] object
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = Error;
+
+    /// Parses `table` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected table or json", name),
+            )),
+        }
+    }
+}
+
+/// Writes rows with their columns aligned
+fn aligned(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut output = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        output.push_str(cells.join("  ").trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+impl StatsFormat {
+    /// Writes graph statistics in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::stats::{GraphStats, PageReferences, StatsFormat};
+    /// let stats = GraphStats {
+    ///     pages: 2,
+    ///     journals: 1,
+    ///     blocks: 9,
+    ///     words: 40,
+    ///     tasks: [("DONE".to_string(), 1), ("TODO".to_string(), 2)].into(),
+    ///     asset_bytes: 2048,
+    ///     assets: 1,
+    ///     top_pages: vec![PageReferences { name: "Project".to_string(), references: 3 }],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(StatsFormat::Table.render(&stats),
+    ///     "pages     2\n\
+    ///      journals  1\n\
+    ///      blocks    9\n\
+    ///      words     40\n\
+    ///      tasks     3 (TODO 2, DONE 1)\n\
+    ///      orphans   0\n\
+    ///      assets    1 (2.0 KB), 0 unused\n\
+    ///      \n\
+    ///      PAGE     REFERENCES\n\
+    ///      Project  3\n");
+    /// ```
+    pub fn render(&self, stats: &GraphStats) -> String {
+        if *self == StatsFormat::Json {
+            return serde_json::to_string_pretty(stats).unwrap_or_default() + "\n";
+        }
+        // Known markers in the order of Logseq, then any others
+        let mut markers: Vec<&String> = stats.tasks.keys().collect();
+        markers.sort_by_key(|marker| {
+            TASK_MARKERS
+                .iter()
+                .position(|known| known == marker)
+                .unwrap_or(TASK_MARKERS.len())
+        });
+        let by_marker: Vec<String> = markers
+            .into_iter()
+            .map(|marker| format!("{} {}", marker, stats.tasks[marker]))
+            .collect();
+        let tasks = stats.tasks.values().sum::<usize>();
+        let tasks = match by_marker.is_empty() {
+            true => tasks.to_string(),
+            false => format!("{} ({})", tasks, by_marker.join(", ")),
+        };
+        let row = |label: &str, value: String| vec![label.to_string(), value];
+        let mut output = aligned(&[
+            row("pages", stats.pages.to_string()),
+            row("journals", stats.journals.to_string()),
+            row("blocks", stats.blocks.to_string()),
+            row("words", stats.words.to_string()),
+            row("tasks", tasks),
+            row("orphans", stats.orphans.to_string()),
+            row(
+                "assets",
+                format!(
+                    "{} ({}), {} unused",
+                    stats.assets,
+                    human_size(stats.asset_bytes),
+                    stats.unused_assets
+                ),
+            ),
+        ]);
+        if !stats.top_pages.is_empty() {
+            let mut rows = vec![row("PAGE", "REFERENCES".to_string())];
+            rows.extend(
+                stats
+                    .top_pages
+                    .iter()
+                    .map(|page| row(&page.name, page.references.to_string())),
+            );
+            output.push('\n');
+            output.push_str(&aligned(&rows));
+        }
+        output
+    }
+}
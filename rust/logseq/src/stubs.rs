@@ -0,0 +1,152 @@
+//! Find the pages and journals with nothing in them: empty files, files with only empty
+//! bullets, as Logseq writes for a new page or a day without notes, and files with only the
+//! properties Logseq writes by itself
+
+use crate::graph::Graph;
+use crate::outline::Outline;
+use crate::properties::split_property;
+use crate::refs::{block_refs, lines_outside_regions, CODE_REGIONS};
+use crate::{undo, Result};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Keys of the properties Logseq writes by itself, which a page can have without anyone typing
+/// in it
+pub const GENERATED_PROPERTIES: &[&str] = &[
+    "title",
+    "id",
+    "collapsed",
+    "created-at",
+    "updated-at",
+    "filters",
+];
+
+/// What a stub page has in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StubKind {
+    /// Nothing, or only whitespace
+    Empty,
+    /// Only bullets without content, usually the `-` of a new page
+    EmptyBullets,
+    /// Only [GENERATED_PROPERTIES], and maybe empty bullets
+    GeneratedProperties,
+}
+
+impl StubKind {
+    /// What the page has, e.g. `only empty bullets`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StubKind::Empty => "empty",
+            StubKind::EmptyBullets => "only empty bullets",
+            StubKind::GeneratedProperties => "only generated properties",
+        }
+    }
+}
+
+impl fmt::Display for StubKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A page or journal with nothing in it, that nothing refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StubPage {
+    /// Full path to the file
+    pub path: PathBuf,
+    /// Name of the page
+    pub name: String,
+    /// What the page has in it
+    pub kind: StubKind,
+}
+
+/// What an outline has in it, if it is a stub
+///
+/// # Examples
+///
+/// ```
+/// use logseq::outline::parse_outline;
+/// use logseq::stubs::{stub_kind, StubKind};
+/// assert_eq!(stub_kind(&parse_outline("\n")), Some(StubKind::Empty));
+/// assert_eq!(stub_kind(&parse_outline("-\n")), Some(StubKind::EmptyBullets));
+/// let generated = parse_outline("title:: a/b\n\n-\n  collapsed:: true\n");
+/// assert_eq!(stub_kind(&generated), Some(StubKind::GeneratedProperties));
+/// assert_eq!(stub_kind(&parse_outline("tags:: book\n")), None);
+/// assert_eq!(stub_kind(&parse_outline("- note\n")), None);
+/// ```
+pub fn stub_kind(outline: &Outline) -> Option<StubKind> {
+    let mut properties = false;
+    let lines = outline
+        .preamble
+        .iter()
+        .map(String::as_str)
+        .chain(outline.iter().flat_map(|block| block.content.split('\n')));
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let generated = split_property(line).is_some_and(|(key, _)| {
+            GENERATED_PROPERTIES
+                .iter()
+                .any(|generated| generated.eq_ignore_ascii_case(key))
+        });
+        if !generated {
+            return None;
+        }
+        properties = true;
+    }
+    Some(match (properties, !outline.blocks.is_empty()) {
+        (true, _) => StubKind::GeneratedProperties,
+        (false, true) => StubKind::EmptyBullets,
+        (false, false) => StubKind::Empty,
+    })
+}
+
+/// Finds the stub pages and journals of a graph, see [stub_kind], in path order. Pages that
+/// another page links to, tags or embeds, that are in the `:favorites` of `config.edn`, that
+/// are the parent of a namespaced page, or that have a block id another page refers to are
+/// left out.
+pub fn find_stub_pages(graph: &Graph) -> Result<Vec<StubPage>> {
+    let mut refs: HashSet<String> = HashSet::new();
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            for (_, text) in lines_outside_regions(block.numbered_lines(), CODE_REGIONS) {
+                refs.extend(block_refs(text).into_iter().map(|block_ref| block_ref.uuid));
+            }
+        }
+    }
+    let mut stubs = Vec::new();
+    for (page, referenced) in graph.pages.iter().zip(graph.referenced_pages()?) {
+        if referenced {
+            continue;
+        }
+        let Some(kind) = stub_kind(&page.outline) else {
+            continue;
+        };
+        let page_id = page.outline.page_properties().get("id").cloned();
+        let block_ids = page
+            .outline
+            .iter()
+            .filter_map(|block| block.properties().get("id").cloned());
+        if page_id
+            .into_iter()
+            .chain(block_ids)
+            .any(|id| refs.contains(&id.raw.trim().to_lowercase()))
+        {
+            continue;
+        }
+        stubs.push(StubPage {
+            path: page.path.clone(),
+            name: page.name.clone(),
+            kind,
+        });
+    }
+    stubs.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(stubs)
+}
+
+/// Deletes the files of stub pages
+pub fn delete_stub_pages(stubs: &[StubPage]) -> Result<()> {
+    for stub in stubs {
+        undo::delete_file(&stub.path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! Suggest the page a broken `[[link]]` was meant for, like `[[Kubernetes]]` for
+//! `[[Kubernets]]`, and fix the links with the suggestions
+
+use crate::graph::{page_key, BrokenLink, Graph};
+use crate::rename::{rewrite_references, write_edits, FileEdit};
+use crate::{error, Result};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The Levenshtein distance between two strings: the number of characters to insert, delete or
+/// replace to turn one into the other
+///
+/// # Examples
+///
+/// ```
+/// use logseq::suggest::levenshtein;
+/// assert_eq!(levenshtein("Kubernets", "Kubernetes"), 1);
+/// assert_eq!(levenshtein("kitten", "sitting"), 3);
+/// ```
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The Jaro-Winkler similarity of two strings, from 0 (nothing in common) to 1 (equal), which
+/// favors strings with the same beginning
+///
+/// # Examples
+///
+/// ```
+/// use logseq::suggest::jaro_winkler;
+/// assert_eq!(jaro_winkler("dune", "dune"), 1.0);
+/// assert!(jaro_winkler("martha", "marhta") > jaro_winkler("martha", "mahtar"));
+/// ```
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut b_matched = vec![false; b.len()];
+    let mut a_matches = Vec::new();
+    for (i, a_char) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        if let Some(j) = (start..end).find(|&j| !b_matched[j] && b[j] == *a_char) {
+            b_matched[j] = true;
+            a_matches.push(*a_char);
+        }
+    }
+    if a_matches.is_empty() {
+        return 0.0;
+    }
+    let b_matches = b
+        .iter()
+        .zip(&b_matched)
+        .filter(|(_, matched)| **matched)
+        .map(|(b_char, _)| b_char);
+    let transpositions = a_matches
+        .iter()
+        .zip(b_matches)
+        .filter(|(a_char, b_char)| a_char != b_char)
+        .count();
+    let matches = a_matches.len() as f64;
+    let jaro = (matches / a.len() as f64
+        + matches / b.len() as f64
+        + (matches - transpositions as f64 / 2.0) / matches)
+        / 3.0;
+    let prefix = a.iter().zip(&b).take(4).take_while(|(a, b)| a == b).count();
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+/// The number of edits a misspelled page name can be away from the page: one per 4 characters,
+/// at least one
+fn max_edits(name: &str) -> usize {
+    (name.chars().count() / 4).max(1)
+}
+
+/// A broken link and the page it was probably meant for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSuggestion {
+    /// The broken link
+    pub link: BrokenLink,
+    /// The name of the suggested page, or of its alias
+    pub page: String,
+}
+
+impl Graph {
+    /// The name or alias of an existing page closest to a missing page name, ignoring case: the
+    /// fewest edits (see [levenshtein]), then the most similar (see [jaro_winkler]). None if no
+    /// page is within one edit per 4 characters of the name.
+    pub fn suggest_page(&self, name: &str) -> Option<String> {
+        let key = page_key(name);
+        let limit = max_edits(&key);
+        self.pages
+            .iter()
+            .flat_map(|page| std::iter::once(page.name.clone()).chain(page.aliases()))
+            .filter_map(|candidate| {
+                let candidate_key = page_key(&candidate);
+                let distance = levenshtein(&key, &candidate_key);
+                (distance <= limit)
+                    .then(|| (distance, jaro_winkler(&key, &candidate_key), candidate))
+            })
+            .min_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)).then(a.2.cmp(&b.2)))
+            .map(|(_, _, candidate)| candidate)
+    }
+
+    /// The broken links (see [Graph::check_links]) close enough to a page to suggest it
+    pub fn suggest_links(&self) -> Vec<LinkSuggestion> {
+        self.check_links()
+            .into_iter()
+            .filter_map(|link| {
+                let page = self.suggest_page(&link.target)?;
+                Some(LinkSuggestion { link, page })
+            })
+            .collect()
+    }
+}
+
+/// Plans the replacement of broken links with their suggestions. Every reference in the file to
+/// the missing page is rewritten, `#tags` and `tags::` included, like a rename does.
+/// Nothing is written; see [apply_suggestions].
+pub fn plan_suggestions(graph: &Graph, suggestions: &[LinkSuggestion]) -> Result<Vec<FileEdit>> {
+    let mut by_file: BTreeMap<&PathBuf, BTreeMap<String, &str>> = BTreeMap::new();
+    for suggestion in suggestions {
+        by_file
+            .entry(&suggestion.link.path)
+            .or_default()
+            .insert(page_key(&suggestion.link.target), &suggestion.page);
+    }
+    let mut edits = Vec::new();
+    for (path, replacements) in by_file {
+        let Some(page) = graph.pages.iter().find(|page| &page.path == path) else {
+            continue;
+        };
+        let original = error::read_to_string(path)?;
+        let mut updated = original.clone();
+        for (old_key, new) in replacements {
+            updated = rewrite_references(page, &updated, &old_key, new, false);
+        }
+        if updated != original {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Replaces broken links with their suggestions, see [plan_suggestions]; with `dry_run`, only
+/// plans the changes
+pub fn apply_suggestions(
+    graph: &Graph,
+    suggestions: &[LinkSuggestion],
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_suggestions(graph, suggestions)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
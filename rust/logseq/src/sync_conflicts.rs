@@ -0,0 +1,232 @@
+//! Find the copies of pages that sync tools leave when a file changed on two devices, like
+//! `page.sync-conflict-20240102-103000-ABCDEFG.md` for Syncthing, and merge them back into
+//! their page.
+//!
+//! Logseq shows each copy as a page of its own, so the blocks written on one device only stay
+//! out of sight until the copy is found.
+
+use crate::diff::unified_diff;
+use crate::graph::Graph;
+use crate::outline::{parse_outline, Block, Outline};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, undo, Result};
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+static SYNCTHING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+)\.sync-conflict-\d{8}-\d{6}(?:-[A-Z0-9]+)?$").unwrap());
+static DROPBOX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+) \([^()]*conflicted copy[^()]*\)$").unwrap());
+static COPY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+) \(\d+\)$").unwrap());
+
+/// The tool that left a conflict copy, as told by its file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncTool {
+    /// `page.sync-conflict-YYYYMMDD-HHMMSS-DEVICE`
+    Syncthing,
+    /// `page (Someone's conflicted copy YYYY-MM-DD)`
+    Dropbox,
+    /// `page (1)`, from iCloud, Google Drive and file managers
+    Copy,
+}
+
+impl SyncTool {
+    /// Name of the tool, e.g. `Syncthing`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncTool::Syncthing => "Syncthing",
+            SyncTool::Dropbox => "Dropbox",
+            SyncTool::Copy => "copy",
+        }
+    }
+}
+
+/// The file stem of the page a conflict copy was made from, and the tool that made it, or None
+/// if the file stem isn't one of a conflict copy
+///
+/// # Examples
+///
+/// ```
+/// use logseq::sync_conflicts::{conflict_of, SyncTool};
+/// assert_eq!(conflict_of("Books.sync-conflict-20240102-103000-ABC1234"), Some(("Books", SyncTool::Syncthing)));
+/// assert_eq!(conflict_of("Books (Ann's conflicted copy 2024-01-02)"), Some(("Books", SyncTool::Dropbox)));
+/// assert_eq!(conflict_of("Books (1)"), Some(("Books", SyncTool::Copy)));
+/// assert_eq!(conflict_of("Books (draft)"), None);
+/// ```
+pub fn conflict_of(stem: &str) -> Option<(&str, SyncTool)> {
+    [
+        (&SYNCTHING_RE, SyncTool::Syncthing),
+        (&DROPBOX_RE, SyncTool::Dropbox),
+        (&COPY_RE, SyncTool::Copy),
+    ]
+    .into_iter()
+    .find_map(|(re, tool)| {
+        let captures = re.captures(stem)?;
+        Some((captures.get(1)?.as_str(), tool))
+    })
+}
+
+/// A conflict copy of a page in the graph
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncConflict {
+    /// The file of the copy
+    pub path: PathBuf,
+    /// The file of the page it was copied from, in the same directory
+    pub original: PathBuf,
+    /// The tool that made the copy
+    pub tool: SyncTool,
+    /// The top-level blocks of the copy, with their children, that the page doesn't have
+    pub new_blocks: Vec<String>,
+}
+
+impl SyncConflict {
+    /// A unified diff from the page to the copy
+    pub fn diff(&self, root: &Path) -> Result<String> {
+        let original = error::read_to_string(&self.original)?;
+        let copy = error::read_to_string(&self.path)?;
+        let label = self.original.strip_prefix(root).unwrap_or(&self.original);
+        Ok(unified_diff(&label.to_string_lossy(), &original, &copy))
+    }
+}
+
+/// The text of the top-level blocks of a page, each with its children, without line endings
+fn top_level_blocks(text: &str, outline: &Outline) -> Vec<String> {
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|line| line.trim_end_matches('\r'))
+        .collect();
+    let end_of = |block: &Block| {
+        let last = block.iter().last().unwrap_or(block);
+        last.line + last.line_count() - 1
+    };
+    outline
+        .blocks
+        .iter()
+        .map(|block| lines[block.line - 1..end_of(block)].join("\n"))
+        .map(|block| block.trim_end().to_string())
+        .collect()
+}
+
+/// A block compared with others regardless of trailing spaces
+fn block_key(block: &str) -> String {
+    let lines: Vec<&str> = block.lines().map(str::trim_end).collect();
+    lines.join("\n").trim().to_string()
+}
+
+/// Finds the conflict copies of the pages and journals of a graph: files with the name of a
+/// conflict copy (see [conflict_of]) next to the file of their page, with the blocks the page
+/// doesn't have
+pub fn find_sync_conflicts(graph: &Graph) -> Result<Vec<SyncConflict>> {
+    let mut conflicts = Vec::new();
+    for page in &graph.pages {
+        let Some(stem) = page.path.file_stem().map(|stem| stem.to_string_lossy()) else {
+            continue;
+        };
+        let Some((original_stem, tool)) = conflict_of(&stem) else {
+            continue;
+        };
+        let original = page.path.with_file_name(format!("{}.md", original_stem));
+        if !original.is_file() {
+            continue;
+        }
+        let original_text = error::read_to_string(&original)?;
+        let known: HashSet<String> =
+            top_level_blocks(&original_text, &parse_outline(&original_text))
+                .iter()
+                .map(|block| block_key(block))
+                .collect();
+        let text = error::read_to_string(&page.path)?;
+        let new_blocks = top_level_blocks(&text, &page.outline)
+            .into_iter()
+            .filter(|block| !block_key(block).is_empty() && !known.contains(&block_key(block)))
+            .collect();
+        conflicts.push(SyncConflict {
+            path: page.path.clone(),
+            original,
+            tool,
+            new_blocks,
+        });
+    }
+    Ok(conflicts)
+}
+
+/// What resolving conflict copies changes: their pages, with the new blocks appended, and the
+/// copies, which are deleted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictResolution {
+    /// The pages that get new blocks, sorted by path
+    pub edits: Vec<FileEdit>,
+    /// The conflict copies, deleted
+    pub deleted: Vec<PathBuf>,
+}
+
+impl ConflictResolution {
+    /// Writes the pages, then deletes the copies
+    pub fn apply(&self) -> Result<()> {
+        write_edits(&self.edits)?;
+        for path in &self.deleted {
+            undo::delete_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plans merging conflict copies into their pages: the new blocks of each copy are appended to
+/// its page, once even when several copies have them, and the copies are deleted.
+/// Nothing is written; see [ConflictResolution::apply].
+pub fn plan_conflict_resolution(conflicts: &[SyncConflict]) -> Result<ConflictResolution> {
+    let mut by_original: BTreeMap<&Path, Vec<&SyncConflict>> = BTreeMap::new();
+    for conflict in conflicts {
+        by_original
+            .entry(conflict.original.as_path())
+            .or_default()
+            .push(conflict);
+    }
+    let mut edits = Vec::new();
+    for (original, copies) in by_original {
+        let text = error::read_to_string(original)?;
+        let ending = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut appended: HashSet<String> = HashSet::new();
+        let mut updated = text.trim_end().to_string();
+        for block in copies.iter().flat_map(|copy| &copy.new_blocks) {
+            if !appended.insert(block_key(block)) {
+                continue;
+            }
+            if !updated.is_empty() {
+                updated.push_str(ending);
+            }
+            updated.push_str(&block.replace('\n', ending));
+        }
+        if appended.is_empty() {
+            continue;
+        }
+        updated.push_str(ending);
+        edits.push(FileEdit {
+            path: original.to_path_buf(),
+            original: text,
+            updated,
+        });
+    }
+    Ok(ConflictResolution {
+        edits,
+        deleted: conflicts
+            .iter()
+            .map(|conflict| conflict.path.clone())
+            .collect(),
+    })
+}
+
+/// Merges conflict copies into their pages, see [plan_conflict_resolution]; with `dry_run`, only
+/// plans the changes
+pub fn resolve_sync_conflicts(
+    conflicts: &[SyncConflict],
+    dry_run: bool,
+) -> Result<ConflictResolution> {
+    let resolution = plan_conflict_resolution(conflicts)?;
+    if !dry_run {
+        resolution.apply()?;
+    }
+    Ok(resolution)
+}
@@ -0,0 +1,402 @@
+//! Logseq tasks: markers like `TODO`/`DONE` at the start of a block, and their
+//! `SCHEDULED:`/`DEADLINE:` timestamps
+
+use crate::config::GraphConfig;
+use crate::outline::Block;
+use crate::timestamps;
+use crate::{Error, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime};
+use regex::Regex;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static PRIORITY_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[#([A-Ca-c])\]").unwrap());
+static CLOCK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"CLOCK:\s*\[(\d{4}-\d{2}-\d{2})").unwrap());
+static CLOSED_CLOCK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*CLOCK:\s*\[[^\]]+\]--\[[^\]]+\]").unwrap());
+static TIMESTAMP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+            r"^<(\d{4})-(\d{2})-(\d{2})(?: ([A-Za-z]+))?(?: (\d{1,2}):(\d{2}))?(?: (\.\+|\+\+|\+)(\d+)([hdwmy]))?>$",
+        ).unwrap()
+});
+
+pub mod archive;
+pub mod done;
+pub mod report;
+
+/// All task markers recognized by Logseq
+pub const TASK_MARKERS: [&str; 11] = [
+    "TODO",
+    "DOING",
+    "LATER",
+    "NOW",
+    "DONE",
+    "WAITING",
+    "WAIT",
+    "CANCELED",
+    "CANCELLED",
+    "STARTED",
+    "IN-PROGRESS",
+];
+
+/// Markers of tasks that are finished
+pub const CLOSED_MARKERS: [&str; 3] = ["DONE", "CANCELED", "CANCELLED"];
+
+/// The workflow used for task markers, as in Logseq's `:preferred-workflow` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workflow {
+    /// `TODO` and `DOING`
+    TodoDoing,
+    /// `LATER` and `NOW`
+    NowLater,
+}
+
+impl Workflow {
+    /// Converts a marker to this workflow; markers without an equivalent are returned as they are
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::tasks::Workflow;
+    /// assert_eq!(Workflow::NowLater.convert("TODO"), "LATER");
+    /// assert_eq!(Workflow::TodoDoing.convert("NOW"), "DOING");
+    /// assert_eq!(Workflow::TodoDoing.convert("DONE"), "DONE");
+    /// ```
+    pub fn convert<'a>(&self, marker: &'a str) -> &'a str {
+        match (self, marker) {
+            (Workflow::NowLater, "TODO") => "LATER",
+            (Workflow::NowLater, "DOING") => "NOW",
+            (Workflow::TodoDoing, "LATER") => "TODO",
+            (Workflow::TodoDoing, "NOW") => "DOING",
+            _ => marker,
+        }
+    }
+}
+
+/// Splits the first line of a block into its task marker and the rest of the text.
+/// Only uppercase markers followed by a space (or at the end of the line) count.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::tasks::split_marker;
+/// assert_eq!(split_marker("TODO buy milk"), Some(("TODO", "buy milk")));
+/// assert_eq!(split_marker("DONE"), Some(("DONE", "")));
+/// assert_eq!(split_marker("TODOS are fun"), None);
+/// assert_eq!(split_marker("todo buy milk"), None);
+/// ```
+pub fn split_marker(first_line: &str) -> Option<(&str, &str)> {
+    let (word, rest) = first_line.split_once(' ').unwrap_or((first_line, ""));
+    TASK_MARKERS
+        .iter()
+        .find(|marker| **marker == word)
+        .map(|marker| (*marker, rest))
+}
+
+/// True if a block is a `DONE` task
+pub(crate) fn is_done(block: &Block) -> bool {
+    split_marker(block.first_line()).is_some_and(|(marker, _)| marker == "DONE")
+}
+
+/// The priority of a task, from the first `[#A]`, `[#B]` or `[#C]` cookie of its first line,
+/// as an uppercase letter
+///
+/// # Examples
+///
+/// ```
+/// use logseq::tasks::priority;
+/// assert_eq!(priority("TODO [#A] pay rent"), Some('A'));
+/// assert_eq!(priority("TODO call [#b]"), Some('B'));
+/// assert_eq!(priority("TODO see [#tag]"), None);
+/// ```
+pub fn priority(first_line: &str) -> Option<char> {
+    PRIORITY_RE
+        .captures(first_line)
+        .and_then(|captures| captures[1].chars().next())
+        .map(|letter| letter.to_ascii_uppercase())
+}
+
+/// The date of the last `CLOCK:` entry of the logbook of a block, if any
+pub(crate) fn last_clock_date(block: &Block) -> Option<NaiveDate> {
+    block
+        .content
+        .lines()
+        .filter_map(|line| CLOCK_RE.captures(line))
+        .filter_map(|captures| NaiveDate::parse_from_str(&captures[1], "%Y-%m-%d").ok())
+        .max()
+}
+
+/// The property with the time a task was done, in milliseconds since the Unix epoch
+pub const DONE_PROPERTY: &str = "done";
+
+/// The local date of the `done::` timestamp of a block, if any
+pub(crate) fn done_property_date(block: &Block) -> Option<NaiveDate> {
+    let done = block.properties().get(DONE_PROPERTY)?.raw.clone();
+    let millis = timestamps::Timestamp::parse(&done).millis()?;
+    let done: DateTime<Local> = DateTime::from_timestamp_millis(millis)?.into();
+    Some(done.date_naive())
+}
+
+/// Where a graph records when a task was done
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Completion {
+    /// A `CLOCK:` entry in the `:LOGBOOK:` of the task, which Logseq writes with time tracking
+    #[default]
+    Logbook,
+    /// A `done::` property with a timestamp
+    Property,
+}
+
+impl Completion {
+    /// How a graph records completion: in logbooks when `:feature/enable-timetracking?` is on,
+    /// as it is by default, or else in the `done::` property
+    pub fn of(config: &GraphConfig) -> Self {
+        match config.enable_timetracking {
+            true => Completion::Logbook,
+            false => Completion::Property,
+        }
+    }
+
+    /// True if a block records when it was done this way
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::outline::parse_outline;
+    /// use logseq::tasks::Completion;
+    /// let outline = parse_outline("- DONE a\n  :LOGBOOK:\n  CLOCK: [2024-01-02 Tue 10:00:00]--[2024-01-02 Tue 11:00:00] =>  01:00:00\n  :END:\n- DONE b\n  done:: 1704189600000");
+    /// assert!(Completion::Logbook.is_recorded(&outline.blocks[0]));
+    /// assert!(!Completion::Logbook.is_recorded(&outline.blocks[1]));
+    /// assert!(Completion::Property.is_recorded(&outline.blocks[1]));
+    /// ```
+    pub fn is_recorded(&self, block: &Block) -> bool {
+        match self {
+            Completion::Logbook => block
+                .content
+                .lines()
+                .any(|line| CLOSED_CLOCK_RE.is_match(line)),
+            Completion::Property => block
+                .properties()
+                .get(DONE_PROPERTY)
+                .is_some_and(|done| timestamps::Timestamp::parse(&done.raw).millis().is_some()),
+        }
+    }
+}
+
+/// The local date a file was last modified
+pub(crate) fn modified_date(path: &Path) -> Result<NaiveDate> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|source| Error::io(path, source))?;
+    let modified: DateTime<Local> = modified.into();
+    Ok(modified.date_naive())
+}
+
+/// Whether a timestamp is a `SCHEDULED:` or a `DEADLINE:` one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampKind {
+    /// `SCHEDULED: <...>`
+    Scheduled,
+    /// `DEADLINE: <...>`
+    Deadline,
+}
+
+impl TimestampKind {
+    /// The keyword before the timestamp, e.g. `SCHEDULED`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimestampKind::Scheduled => "SCHEDULED",
+            TimestampKind::Deadline => "DEADLINE",
+        }
+    }
+}
+
+/// How a repeater moves the date forward once the task is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeaterKind {
+    /// `+`: shift once by the interval
+    Cumulate,
+    /// `++`: shift by the interval until the date is in the future
+    CatchUp,
+    /// `.+`: shift by the interval from today
+    Restart,
+}
+
+/// The unit of a repeater interval
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeaterUnit {
+    /// `h`
+    Hour,
+    /// `d`
+    Day,
+    /// `w`
+    Week,
+    /// `m`
+    Month,
+    /// `y`
+    Year,
+}
+
+/// A repeater like `.+1d`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repeater {
+    /// How the date is moved forward
+    pub kind: RepeaterKind,
+    /// How many units to move it by
+    pub amount: u32,
+    /// The unit of the interval
+    pub unit: RepeaterUnit,
+}
+
+impl fmt::Display for Repeater {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            RepeaterKind::Cumulate => "+",
+            RepeaterKind::CatchUp => "++",
+            RepeaterKind::Restart => ".+",
+        };
+        let unit = match self.unit {
+            RepeaterUnit::Hour => 'h',
+            RepeaterUnit::Day => 'd',
+            RepeaterUnit::Week => 'w',
+            RepeaterUnit::Month => 'm',
+            RepeaterUnit::Year => 'y',
+        };
+        write!(f, "{}{}{}", kind, self.amount, unit)
+    }
+}
+
+/// A `SCHEDULED: <2024-01-02 Tue 10:30 .+1d>` or `DEADLINE: <...>` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Scheduled or deadline
+    pub kind: TimestampKind,
+    /// The date of the timestamp
+    pub date: NaiveDate,
+    /// The weekday name as written, which may not match the date
+    pub weekday: Option<String>,
+    /// The time of day, if any
+    pub time: Option<NaiveTime>,
+    /// The repeater, if any
+    pub repeater: Option<Repeater>,
+}
+
+impl Timestamp {
+    /// Parses a `SCHEDULED:` or `DEADLINE:` line, ignoring surrounding whitespace.
+    /// Returns None if the line isn't one, or an error if its timestamp is malformed or
+    /// its date or time doesn't exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::tasks::{RepeaterKind, RepeaterUnit, Timestamp, TimestampKind};
+    /// let timestamp = Timestamp::parse("SCHEDULED: <2024-01-02 Tue .+1d>").unwrap().unwrap();
+    /// assert_eq!(timestamp.kind, TimestampKind::Scheduled);
+    /// assert_eq!(timestamp.date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+    /// assert_eq!(timestamp.weekday.as_deref(), Some("Tue"));
+    /// let repeater = timestamp.repeater.unwrap();
+    /// assert_eq!((repeater.kind, repeater.amount, repeater.unit), (RepeaterKind::Restart, 1, RepeaterUnit::Day));
+    ///
+    /// assert!(Timestamp::parse("DEADLINE: <2024-02-30 Fri>").unwrap().is_err());
+    /// assert!(Timestamp::parse("Not a timestamp").is_none());
+    /// ```
+    pub fn parse(line: &str) -> Option<Result<Self>> {
+        let line = line.trim();
+        let (kind, rest) = if let Some(rest) = line.strip_prefix("SCHEDULED:") {
+            (TimestampKind::Scheduled, rest)
+        } else if let Some(rest) = line.strip_prefix("DEADLINE:") {
+            (TimestampKind::Deadline, rest)
+        } else {
+            return None;
+        };
+        Some(Self::parse_value(kind, rest.trim()))
+    }
+
+    fn parse_value(kind: TimestampKind, value: &str) -> Result<Self> {
+        let captures = TIMESTAMP_RE
+            .captures(value)
+            .ok_or_else(|| Error::parse(None, format!("Malformed timestamp {}", value)))?;
+        let number = |index: usize| captures[index].parse::<u32>().unwrap_or(u32::MAX);
+
+        let date =
+            NaiveDate::from_ymd_opt(number(1) as i32, number(2), number(3)).ok_or_else(|| {
+                Error::parse(
+                    None,
+                    format!(
+                        "Impossible date {}-{}-{}",
+                        &captures[1], &captures[2], &captures[3]
+                    ),
+                )
+            })?;
+        let time = match captures.get(5) {
+            Some(hour) => Some(NaiveTime::from_hms_opt(number(5), number(6), 0).ok_or_else(
+                || {
+                    Error::parse(
+                        None,
+                        format!("Impossible time {}:{}", hour.as_str(), &captures[6]),
+                    )
+                },
+            )?),
+            None => None,
+        };
+        let repeater = captures.get(7).map(|kind| Repeater {
+            kind: match kind.as_str() {
+                "+" => RepeaterKind::Cumulate,
+                "++" => RepeaterKind::CatchUp,
+                _ => RepeaterKind::Restart,
+            },
+            amount: number(8),
+            unit: match &captures[9] {
+                "h" => RepeaterUnit::Hour,
+                "d" => RepeaterUnit::Day,
+                "w" => RepeaterUnit::Week,
+                "m" => RepeaterUnit::Month,
+                _ => RepeaterUnit::Year,
+            },
+        });
+
+        Ok(Timestamp {
+            kind,
+            date,
+            weekday: captures.get(4).map(|m| m.as_str().to_string()),
+            time,
+            repeater,
+        })
+    }
+
+    /// The abbreviated English name of the weekday of the date, e.g. `Tue`
+    pub fn expected_weekday(&self) -> String {
+        self.date.weekday().to_string()
+    }
+
+    /// True if the weekday is missing or matches the date
+    pub fn weekday_matches(&self) -> bool {
+        self.weekday
+            .as_ref()
+            .is_none_or(|weekday| *weekday == self.expected_weekday())
+    }
+}
+
+/// Formats the timestamp the way Logseq writes it, with the weekday of the date
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: <{} {}",
+            self.kind.as_str(),
+            self.date.format("%Y-%m-%d"),
+            self.expected_weekday()
+        )?;
+        if let Some(time) = self.time {
+            write!(f, " {}", time.format("%H:%M"))?;
+        }
+        if let Some(repeater) = self.repeater {
+            write!(f, " {}", repeater)?;
+        }
+        write!(f, ">")
+    }
+}
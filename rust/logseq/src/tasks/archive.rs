@@ -0,0 +1,222 @@
+//! Move the tasks that were done long ago out of project pages, to journals or an archive page
+
+use super::{done_property_date, last_clock_date, modified_date};
+use crate::config::GraphConfig;
+use crate::graph::{file_stem_from_page_name, Graph};
+use crate::journal::to_strftime;
+use crate::outline::Block;
+use crate::properties::split_property;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many days after being done a task is archived, by default
+pub const ARCHIVE_AFTER_DAYS: i64 = 30;
+
+/// The property added to archived tasks, linking back to the page they were on
+pub const ARCHIVED_FROM_KEY: &str = "archived-from";
+
+/// Where archived tasks go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveDestination {
+    /// The journal of the day each task was done
+    Journal,
+    /// A page with this name, e.g. `Archive`
+    Page(String),
+}
+
+/// A task moved by an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedTask {
+    /// The file the task was on
+    pub path: PathBuf,
+    /// Line of the block in that file, starting at 1
+    pub line: usize,
+    /// Name of the page the task was on
+    pub page: String,
+    /// First line of the block
+    pub text: String,
+    /// When the task was done: the last `CLOCK:` entry of its logbook or its `done::`
+    /// timestamp, whichever is later, or else when its file was last modified
+    pub done: NaiveDate,
+    /// The file the task is moved to
+    pub destination: PathBuf,
+}
+
+/// Everything an archive changes: the tasks it moves, and the files it rewrites or creates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskArchive {
+    /// The moved tasks, in the order of the graph
+    pub tasks: Vec<ArchivedTask>,
+    /// The rewritten files, sorted by path; the original contents of new files are empty
+    pub edits: Vec<FileEdit>,
+}
+
+impl TaskArchive {
+    /// Applies the archive. All files are written to temporary files first, so a failure
+    /// leaves the graph untouched.
+    pub fn apply(&self) -> Result<()> {
+        for edit in &self.edits {
+            if let Some(directory) = edit.path.parent() {
+                fs::create_dir_all(directory).map_err(|source| Error::io(directory, source))?;
+            }
+        }
+        write_edits(&self.edits)
+    }
+}
+
+/// The lines of a block and its children, without the indentation of the block, with an
+/// `archived-from::` property after its first line and its own properties
+fn moved_lines(lines: &[&str], block: &Block, end: usize, page: &str) -> Vec<String> {
+    let mut moved: Vec<String> = lines[block.line - 1..end - 1]
+        .iter()
+        .map(|line| {
+            line.strip_prefix(block.indent.as_str())
+                .unwrap_or(line.trim_start())
+        })
+        .map(str::to_string)
+        .collect();
+    let properties = moved[1..block.line_count()]
+        .iter()
+        .take_while(|line| split_property(line).is_some())
+        .count();
+    moved.insert(
+        1 + properties,
+        format!("  {}:: [[{}]]", ARCHIVED_FROM_KEY, page),
+    );
+    moved
+}
+
+/// Plans moving the `DONE` tasks of project pages (not journals) that were done more than
+/// `days` days before `today` to a destination, each with its children and an
+/// `archived-from::` property linking back to its page. Blocks are moved as they are, so their
+/// `id::` properties, and the references to them, still work. Tasks on the archive page itself
+/// stay where they are. Nothing is written; see [TaskArchive::apply].
+pub fn plan_archive(
+    graph: &Graph,
+    today: NaiveDate,
+    days: i64,
+    destination: &ArchiveDestination,
+) -> Result<TaskArchive> {
+    let config = GraphConfig::read(&graph.root)?;
+    let archive_page = match destination {
+        ArchiveDestination::Journal => None,
+        ArchiveDestination::Page(name) => Some(match graph.find_page(name) {
+            Some(page) => page.path.clone(),
+            None => graph
+                .root
+                .join(&config.pages_directory)
+                .join(format!("{}.md", file_stem_from_page_name(name))),
+        }),
+    };
+    let journal_format = to_strftime(&config.journal_file_name_format);
+    let journal_path = |date: NaiveDate| match graph
+        .pages
+        .iter()
+        .find(|page| page.journal_date == Some(date))
+    {
+        Some(page) => page.path.clone(),
+        None => graph
+            .root
+            .join(&config.journals_directory)
+            .join(format!("{}.md", date.format(&journal_format))),
+    };
+
+    let mut tasks = Vec::new();
+    let mut edits = Vec::new();
+    // The lines appended to each destination file
+    let mut appended: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for page in &graph.pages {
+        if page.journal_date.is_some() || Some(&page.path) == archive_page.as_ref() {
+            continue;
+        }
+        let mut modified = None;
+        let mut moved = Vec::new();
+        let mut blocks: Vec<&Block> = page.outline.blocks.iter().rev().collect();
+        while let Some(block) = blocks.pop() {
+            let is_done = block.first_line() == "DONE" || block.first_line().starts_with("DONE ");
+            if !is_done {
+                blocks.extend(block.children.iter().rev());
+                continue;
+            }
+            let done = match last_clock_date(block).max(done_property_date(block)) {
+                Some(date) => date,
+                None => match modified {
+                    Some(date) => date,
+                    None => *modified.insert(modified_date(&page.path)?),
+                },
+            };
+            if (today - done).num_days() <= days {
+                blocks.extend(block.children.iter().rev());
+                continue;
+            }
+            let destination = match &archive_page {
+                Some(path) => path.clone(),
+                None => journal_path(done),
+            };
+            moved.push(block);
+            tasks.push(ArchivedTask {
+                path: page.path.clone(),
+                line: block.line,
+                page: page.name.clone(),
+                text: block.first_line().to_string(),
+                done,
+                destination,
+            });
+        }
+        if moved.is_empty() {
+            continue;
+        }
+
+        let original = error::read_to_string(&page.path)?;
+        let lines: Vec<&str> = original.split('\n').collect();
+        let mut removed = vec![false; lines.len()];
+        for (block, task) in moved.iter().zip(&tasks[tasks.len() - moved.len()..]) {
+            let last = block.iter().last().unwrap_or(block);
+            let end = (last.line + last.line_count()).min(lines.len() + 1);
+            removed[block.line - 1..end - 1].fill(true);
+            appended
+                .entry(task.destination.clone())
+                .or_default()
+                .extend(moved_lines(&lines, block, end, &page.name));
+        }
+        let updated: Vec<&str> = lines
+            .iter()
+            .zip(&removed)
+            .filter(|(_, removed)| !**removed)
+            .map(|(line, _)| *line)
+            .collect();
+        edits.push(FileEdit {
+            path: page.path.clone(),
+            original: original.clone(),
+            updated: updated.join("\n"),
+        });
+    }
+
+    for (path, lines) in appended {
+        let original = if path.exists() {
+            error::read_to_string(&path)?
+        } else {
+            String::new()
+        };
+        // Logseq writes a lone `-` to empty pages
+        let existing = original.trim_end();
+        let mut updated = if existing.is_empty() || existing == "-" {
+            String::new()
+        } else {
+            format!("{}\n", existing)
+        };
+        updated.push_str(&lines.join("\n"));
+        updated.push('\n');
+        edits.push(FileEdit {
+            path,
+            original,
+            updated,
+        });
+    }
+    edits.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(TaskArchive { tasks, edits })
+}
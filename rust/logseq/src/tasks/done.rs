@@ -0,0 +1,114 @@
+//! Record when `DONE` tasks were done, for tasks that don't say it the way of their graph
+
+use super::{is_done, Completion, DONE_PROPERTY};
+use crate::config::GraphConfig;
+use crate::git::line_commit_times;
+use crate::graph::Graph;
+use crate::outline::Block;
+use crate::rename::{write_edits, FileEdit};
+use crate::timestamps::{file_times, TimestampSource};
+use crate::{error, Result};
+use chrono::{DateTime, Local};
+
+/// The lines recording that a block was done at a time, in milliseconds since the Unix epoch,
+/// and the line they go after
+fn completion_lines(block: &Block, completion: Completion, millis: i64) -> (usize, Vec<String>) {
+    let indent = format!("{}  ", block.indent);
+    match completion {
+        Completion::Property => {
+            let after = block
+                .properties()
+                .iter()
+                .map(|property| property.line)
+                .max()
+                .unwrap_or(block.line);
+            let line = format!("{}{}:: {}", indent, DONE_PROPERTY, millis);
+            (after, vec![line])
+        }
+        Completion::Logbook => {
+            let time = DateTime::from_timestamp_millis(millis)
+                .unwrap_or_default()
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %a %H:%M:%S");
+            let clock = format!("{}CLOCK: [{}]--[{}] =>  00:00:00", indent, time, time);
+            let logbook = block
+                .content
+                .split('\n')
+                .position(|line| line.trim() == ":LOGBOOK:");
+            match logbook {
+                Some(offset) => (block.line + offset, vec![clock]),
+                None => {
+                    let after = block.line + block.line_count() - 1;
+                    let end = format!("{}:END:", indent);
+                    (after, vec![format!("{}:LOGBOOK:", indent), clock, end])
+                }
+            }
+        }
+    }
+}
+
+/// Plans recording when the `DONE` tasks of a graph were done, for those that don't: a
+/// `done::` property, or a `CLOCK:` entry of no time in their `:LOGBOOK:` when the graph tracks
+/// time (see [Completion::of]). The time is when the first line of the task was last committed
+/// with [TimestampSource::Git], or else when its file was last modified. Nothing is written;
+/// see [backfill_done].
+pub fn plan_done_backfill(graph: &Graph, source: TimestampSource) -> Result<Vec<FileEdit>> {
+    let completion = Completion::of(&GraphConfig::read(&graph.root)?);
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        let tasks: Vec<&Block> = page
+            .outline
+            .iter()
+            .filter(|block| is_done(block) && !completion.is_recorded(block))
+            .collect();
+        if tasks.is_empty() {
+            continue;
+        }
+        let line_times = match source {
+            TimestampSource::Git => line_commit_times(&page.path)?,
+            TimestampSource::FileTimes => None,
+        };
+        let (_, modified) = file_times(&page.path, TimestampSource::FileTimes)?;
+        let original = error::read_to_string(&page.path)?;
+        let ending = match original
+            .split('\n')
+            .next()
+            .is_some_and(|line| line.ends_with('\r'))
+        {
+            true => "\r",
+            false => "",
+        };
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        // From the end, so that the lines of the tasks before don't move
+        for task in tasks.iter().rev() {
+            let millis = line_times
+                .as_ref()
+                .and_then(|times| times.get(task.line - 1))
+                .map_or(modified, |seconds| seconds * 1000);
+            let (after, new) = completion_lines(task, completion, millis);
+            for (offset, line) in new.into_iter().enumerate() {
+                lines.insert(after + offset, format!("{}{}", line, ending));
+            }
+        }
+        edits.push(FileEdit {
+            path: page.path.clone(),
+            original,
+            updated: lines.join("\n"),
+        });
+    }
+    Ok(edits)
+}
+
+/// Records when the `DONE` tasks of a graph were done, see [plan_done_backfill]; with
+/// `dry_run`, only plans the changes
+pub fn backfill_done(
+    graph: &Graph,
+    source: TimestampSource,
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_done_backfill(graph, source)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
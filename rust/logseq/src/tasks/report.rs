@@ -0,0 +1,236 @@
+//! Report the open tasks of a graph that are overdue, or that nobody touched for a long time
+
+use super::{
+    last_clock_date, modified_date, priority, split_marker, Timestamp, TimestampKind,
+    CLOSED_MARKERS,
+};
+use crate::config::GraphConfig;
+use crate::dates::{parse_date_link, DateFormat};
+use crate::graph::Graph;
+use crate::refs::page_links;
+use crate::{Error, Result};
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// How many days a task can go untouched before it is stale, by default
+pub const STALE_DAYS: i64 = 30;
+
+/// An open task that is overdue or stale
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskEntry {
+    /// File of the page with the task
+    pub path: PathBuf,
+    /// Line of the block, starting at 1
+    pub line: usize,
+    /// Name of the page with the task
+    pub page: String,
+    /// Task marker, e.g. `TODO`
+    pub marker: String,
+    /// First line of the block, without the marker
+    pub text: String,
+    /// `A`, `B` or `C`, from the `[#A]` cookie of the task
+    pub priority: Option<char>,
+    /// Date of the `SCHEDULED:` timestamp, or of the first journal link of the task without one
+    pub scheduled: Option<NaiveDate>,
+    /// Date of the `DEADLINE:` timestamp
+    pub deadline: Option<NaiveDate>,
+    /// When the task was last worked on: the latest of the journal it is on and the `CLOCK:`
+    /// entries of its logbook, or when its file was last modified
+    pub touched: Option<NaiveDate>,
+    /// True if the deadline or scheduled date is before today
+    pub overdue: bool,
+    /// True if the task wasn't touched for the stale delay
+    pub stale: bool,
+}
+
+impl TaskEntry {
+    /// The date the task is due: its deadline, or else its scheduled date
+    pub fn due(&self) -> Option<NaiveDate> {
+        self.deadline.or(self.scheduled)
+    }
+}
+
+/// Finds the open tasks (any marker but `DONE` and `CANCELED`) of a graph that are overdue (their
+/// deadline or scheduled date is before `today`) or stale (untouched for more than `stale_days`
+/// days), sorted with overdue tasks first by due date, then by priority, then the stale ones
+/// from the least recently touched. A task without `SCHEDULED:` or `DEADLINE:` is scheduled on
+/// the first journal it links to, like `TODO call Bob [[Jan 3rd, 2024]]`, read in the graph's
+/// `:journal/page-title-format`.
+pub fn task_report(graph: &Graph, today: NaiveDate, stale_days: i64) -> Result<Vec<TaskEntry>> {
+    let config = GraphConfig::read(&graph.root)?;
+    let title_format = DateFormat::new(&config.journal_page_title_format);
+    let mut entries = Vec::new();
+    for page in &graph.pages {
+        let mut modified = None;
+        for block in page.outline.iter() {
+            let Some((marker, text)) = split_marker(block.first_line()) else {
+                continue;
+            };
+            if CLOSED_MARKERS.contains(&marker) {
+                continue;
+            }
+            let mut scheduled = None;
+            let mut deadline = None;
+            for line in block.content.lines() {
+                if let Some(Ok(timestamp)) = Timestamp::parse(line) {
+                    match timestamp.kind {
+                        TimestampKind::Scheduled => scheduled = Some(timestamp.date),
+                        TimestampKind::Deadline => deadline = Some(timestamp.date),
+                    }
+                }
+            }
+            if scheduled.is_none() && deadline.is_none() {
+                scheduled = page_links(text)
+                    .iter()
+                    .find_map(|link| parse_date_link(&link.name, &title_format));
+            }
+            let worked_on = last_clock_date(block).max(page.journal_date);
+            let touched = match worked_on {
+                Some(date) => Some(date),
+                None => match modified {
+                    Some(date) => Some(date),
+                    None => {
+                        modified = Some(modified_date(&page.path)?);
+                        modified
+                    }
+                },
+            };
+            let overdue = deadline.or(scheduled).is_some_and(|due| due < today);
+            let stale = touched.is_some_and(|touched| (today - touched).num_days() > stale_days);
+            if overdue || stale {
+                entries.push(TaskEntry {
+                    path: page.path.clone(),
+                    line: block.line,
+                    page: page.name.clone(),
+                    marker: marker.to_string(),
+                    text: text.to_string(),
+                    priority: priority(text),
+                    scheduled,
+                    deadline,
+                    touched,
+                    overdue,
+                    stale,
+                });
+            }
+        }
+    }
+    entries.sort_by(|a, b| {
+        let key = |entry: &TaskEntry| {
+            (
+                !entry.overdue,
+                entry.due().unwrap_or(NaiveDate::MAX),
+                entry.priority.unwrap_or('Z'),
+                entry.touched.unwrap_or(NaiveDate::MAX),
+            )
+        };
+        key(a)
+            .cmp(&key(b))
+            .then_with(|| (&a.path, a.line).cmp(&(&b.path, b.line)))
+    });
+    Ok(entries)
+}
+
+/// How a task report is written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskFormat {
+    /// A table with aligned columns, for people
+    #[default]
+    Table,
+    /// A JSON array of [TaskEntry] objects
+    Json,
+}
+
+impl FromStr for TaskFormat {
+    type Err = Error;
+
+    /// Parses `table` and `json`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "table" => Ok(TaskFormat::Table),
+            "json" => Ok(TaskFormat::Json),
+            _ => Err(Error::parse(
+                None,
+                format!("unknown format {}, expected table or json", name),
+            )),
+        }
+    }
+}
+
+impl TaskFormat {
+    /// Writes a task report in this format
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use logseq::tasks::report::{TaskEntry, TaskFormat};
+    /// let date = |day| NaiveDate::from_ymd_opt(2024, 1, day);
+    /// let task = TaskEntry {
+    ///     path: "pages/Home.md".into(),
+    ///     line: 3,
+    ///     page: "Home".to_string(),
+    ///     marker: "TODO".to_string(),
+    ///     text: "[#A] pay rent".to_string(),
+    ///     priority: Some('A'),
+    ///     scheduled: None,
+    ///     deadline: date(2),
+    ///     touched: date(1),
+    ///     overdue: true,
+    ///     stale: false,
+    /// };
+    /// assert_eq!(TaskFormat::Table.render(&[task]),
+    ///     "STATUS   DUE         PRIORITY  TASK                PAGE\n\
+    ///      overdue  2024-01-02  A         TODO [#A] pay rent  Home (pages/Home.md:3)\n");
+    /// ```
+    pub fn render(&self, entries: &[TaskEntry]) -> String {
+        match self {
+            TaskFormat::Json => serde_json::to_string_pretty(entries).unwrap_or_default() + "\n",
+            TaskFormat::Table => {
+                let mut rows = vec![[
+                    "STATUS".to_string(),
+                    "DUE".to_string(),
+                    "PRIORITY".to_string(),
+                    "TASK".to_string(),
+                    "PAGE".to_string(),
+                ]];
+                for entry in entries {
+                    let status = match (entry.overdue, entry.stale) {
+                        (true, true) => "overdue, stale",
+                        (true, false) => "overdue",
+                        _ => "stale",
+                    };
+                    rows.push([
+                        status.to_string(),
+                        entry.due().map(|due| due.to_string()).unwrap_or_default(),
+                        entry.priority.map(String::from).unwrap_or_default(),
+                        format!("{} {}", entry.marker, entry.text)
+                            .trim_end()
+                            .to_string(),
+                        format!("{} ({}:{})", entry.page, entry.path.display(), entry.line),
+                    ]);
+                }
+                let widths: Vec<usize> = (0..5)
+                    .map(|column| {
+                        rows.iter()
+                            .map(|row| row[column].chars().count())
+                            .max()
+                            .unwrap_or(0)
+                    })
+                    .collect();
+                let mut output = String::new();
+                for row in &rows {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                        .collect();
+                    output.push_str(cells.join("  ").trim_end());
+                    output.push('\n');
+                }
+                output
+            }
+        }
+    }
+}
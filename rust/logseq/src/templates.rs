@@ -0,0 +1,330 @@
+//! Templates: blocks with a `template::` property, inserted with `/template` in Logseq, and the
+//! `<% today %>` dynamic variables they contain
+
+use crate::dates::DateFormat;
+use crate::error::read_to_string;
+use crate::graph::Graph;
+use crate::outline::Block;
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::Result;
+use chrono::{Days, NaiveDate};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static VARIABLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<%(\s*)([^%<>]*?)(\s*)%>").unwrap());
+static DATE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    let weekdays = "monday|tuesday|wednesday|thursday|friday|saturday|sunday";
+    let months =
+        "january|february|march|april|may|june|july|august|september|october|november|december";
+    let units = "days?|weeks?|months?|years?";
+    Regex::new(&format!(
+        r"^(?:(?:next|last|this) (?:{weekdays}|week|month|year)|(?:{weekdays})|(?:{months})(?: \d{{1,2}})?|in \d+ (?:{units})|\d+ (?:{units}) (?:ago|from now))$"
+    )).unwrap()
+});
+
+/// The property that makes a block a template, with the name of the template
+pub const TEMPLATE_KEY: &str = "template";
+
+/// The property telling whether the template block itself is inserted, or only its children
+pub const INCLUDING_PARENT_KEY: &str = "template-including-parent";
+
+/// The dynamic variables Logseq replaces, besides natural language dates
+pub const KNOWN_VARIABLES: [&str; 5] = ["today", "yesterday", "tomorrow", "time", "current page"];
+
+/// A block with a `template::` property
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Template {
+    /// File of the page with the template
+    pub path: PathBuf,
+    /// Line of the block, starting at 1
+    pub line: usize,
+    /// Name of the page with the template
+    pub page: String,
+    /// Name of the template
+    pub name: String,
+    /// False if only the children of the block are inserted, with
+    /// `template-including-parent:: false`
+    pub including_parent: bool,
+}
+
+/// A `<% name %>` dynamic variable in a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicVariable {
+    /// Byte range of the whole variable in the line, from `<%` to `%>`
+    pub range: std::ops::Range<usize>,
+    /// The name between the delimiters, trimmed
+    pub name: String,
+    /// True if the name has spaces on both sides, like `<% today %>`
+    pub spaced: bool,
+}
+
+/// Finds the dynamic variables of a line, ignoring inline code
+///
+/// # Examples
+///
+/// ```
+/// use logseq::templates::dynamic_variables;
+/// let variables = dynamic_variables("Met on <% today %> at <%time%> `<% code %>`");
+/// assert_eq!(variables.len(), 2);
+/// assert_eq!(variables[0].name, "today");
+/// assert_eq!(variables[0].range, 7..18);
+/// assert!(variables[0].spaced);
+/// assert!(!variables[1].spaced);
+/// ```
+pub fn dynamic_variables(line: &str) -> Vec<DynamicVariable> {
+    VARIABLE_RE
+        .captures_iter(&blank_code_spans(line))
+        .map(|captures| {
+            let found = captures.get(0).unwrap();
+            DynamicVariable {
+                range: found.start()..found.end(),
+                name: captures[2].to_string(),
+                spaced: !captures[1].is_empty() && !captures[3].is_empty(),
+            }
+        })
+        .collect()
+}
+
+/// True if Logseq replaces a dynamic variable: one of [KNOWN_VARIABLES], or a natural language
+/// date like `next monday`, `last week` or `in 3 days`
+///
+/// # Examples
+///
+/// ```
+/// use logseq::templates::is_known_variable;
+/// assert!(is_known_variable("Current Page"));
+/// assert!(is_known_variable("next friday"));
+/// assert!(is_known_variable("in 2 weeks"));
+/// assert!(!is_known_variable("todya"));
+/// ```
+pub fn is_known_variable(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    KNOWN_VARIABLES.contains(&name.as_str()) || DATE_RE.is_match(&name)
+}
+
+/// What's wrong with a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateProblem {
+    /// Another template has the same name (case-insensitively); Logseq inserts only one of them
+    DuplicateName {
+        /// The file of the first template with this name
+        path: PathBuf,
+        /// The line of its block
+        line: usize,
+    },
+    /// A dynamic variable that Logseq doesn't know, and inserts as it is
+    UnknownVariable {
+        /// Line of the variable, starting at 1
+        line: usize,
+        /// The variable, as written
+        variable: String,
+    },
+    /// `template-including-parent::` on a block that isn't a template
+    IncludingParentWithoutTemplate,
+    /// `template-including-parent::` with a value other than `true` or `false`
+    InvalidIncludingParent(String),
+}
+
+/// A template block with a problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateIssue {
+    /// The file of the page with the template
+    pub path: PathBuf,
+    /// The line of the block, starting at 1
+    pub line: usize,
+    /// The name of the template, empty for blocks that aren't templates
+    pub template: String,
+    /// What's wrong with it
+    pub problem: TemplateProblem,
+}
+
+/// The `template::` name and `template-including-parent::` value of a block
+fn template_properties(block: &Block) -> (Option<String>, Option<String>) {
+    let properties = block.properties();
+    let name = properties
+        .get(TEMPLATE_KEY)
+        .map(|property| property.raw.trim().to_string())
+        .filter(|name| !name.is_empty());
+    let including_parent = properties
+        .get(INCLUDING_PARENT_KEY)
+        .map(|property| property.raw.trim().to_string());
+    (name, including_parent)
+}
+
+/// Finds the templates of a graph, in the order of its pages
+pub fn list_templates(graph: &Graph) -> Vec<Template> {
+    let mut templates = Vec::new();
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            if let (Some(name), including_parent) = template_properties(block) {
+                templates.push(Template {
+                    path: page.path.clone(),
+                    line: block.line,
+                    page: page.name.clone(),
+                    name,
+                    including_parent: !including_parent
+                        .is_some_and(|value| value.eq_ignore_ascii_case("false")),
+                });
+            }
+        }
+    }
+    templates
+}
+
+/// Checks the templates of a graph: names used by more than one template, dynamic variables
+/// that Logseq won't replace (see [is_known_variable]) in templates and their children, and
+/// `template-including-parent::` properties that aren't `true` or `false` or aren't on a
+/// template
+pub fn check_templates(graph: &Graph) -> Vec<TemplateIssue> {
+    let mut issues = Vec::new();
+    let mut first_by_name: HashMap<String, (PathBuf, usize)> = HashMap::new();
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            let (name, including_parent) = template_properties(block);
+            let issue = |problem| TemplateIssue {
+                path: page.path.clone(),
+                line: block.line,
+                template: name.clone().unwrap_or_default(),
+                problem,
+            };
+            match (&name, &including_parent) {
+                (None, Some(_)) => {
+                    issues.push(issue(TemplateProblem::IncludingParentWithoutTemplate))
+                }
+                (Some(_), Some(value))
+                    if !value.eq_ignore_ascii_case("true")
+                        && !value.eq_ignore_ascii_case("false") =>
+                {
+                    issues.push(issue(TemplateProblem::InvalidIncludingParent(
+                        value.clone(),
+                    )))
+                }
+                _ => {}
+            }
+            let Some(name) = &name else {
+                continue;
+            };
+            match first_by_name.get(&name.to_lowercase()) {
+                Some((path, line)) => issues.push(issue(TemplateProblem::DuplicateName {
+                    path: path.clone(),
+                    line: *line,
+                })),
+                None => {
+                    first_by_name.insert(name.to_lowercase(), (page.path.clone(), block.line));
+                }
+            }
+            let lines = block
+                .iter()
+                .flat_map(|block| lines_outside_code(block.numbered_lines()));
+            for (line, text) in lines {
+                for variable in dynamic_variables(text) {
+                    if !is_known_variable(&variable.name) {
+                        issues.push(issue(TemplateProblem::UnknownVariable {
+                            line,
+                            variable: text[variable.range].to_string(),
+                        }));
+                    }
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// The text a template inserts: its block without the `template::` properties (unless
+/// `template-including-parent:: false`) and its children, one level up when the block isn't
+/// included. None if no template has the name.
+pub fn template_body(graph: &Graph, name: &str) -> Result<Option<String>> {
+    for page in &graph.pages {
+        for block in page.outline.iter() {
+            let (Some(template), including_parent) = template_properties(block) else {
+                continue;
+            };
+            if !template.eq_ignore_ascii_case(name) {
+                continue;
+            }
+            let text = read_to_string(&page.path)?;
+            let lines: Vec<&str> = text.lines().collect();
+            let last = block.iter().last().unwrap_or(block);
+            let end = (last.line + last.line_count() - 1).min(lines.len());
+            let children_start = block.line + block.line_count() - 1;
+            let including_parent =
+                !including_parent.is_some_and(|value| value.eq_ignore_ascii_case("false"));
+            let unit = match (including_parent, block.children.first()) {
+                (false, Some(child)) => child.indent.strip_prefix(block.indent.as_str()),
+                _ => None,
+            };
+            let mut body = Vec::new();
+            if including_parent {
+                let own_lines = block.numbered_lines().filter(|(_, line)| {
+                    !matches!(
+                        split_property(line),
+                        Some((key, _)) if key.eq_ignore_ascii_case(TEMPLATE_KEY)
+                            || key.eq_ignore_ascii_case(INCLUDING_PARENT_KEY)
+                    )
+                });
+                for (line, _) in own_lines {
+                    body.push(
+                        lines[line - 1]
+                            .strip_prefix(block.indent.as_str())
+                            .unwrap_or(lines[line - 1]),
+                    );
+                }
+            }
+            for line in &lines[children_start..end] {
+                let line = line.strip_prefix(block.indent.as_str()).unwrap_or(line);
+                body.push(
+                    unit.and_then(|unit| line.strip_prefix(unit))
+                        .unwrap_or(line),
+                );
+            }
+            return Ok(Some(body.join("\n") + "\n"));
+        }
+    }
+    Ok(None)
+}
+
+/// Replaces the date variables of a template inserted in the journal of a date: `<% today %>`
+/// and `<% current page %>` become a link to the journal, `<% yesterday %>` and
+/// `<% tomorrow %>` to the days around it, titled in `title_format`. Other variables are kept.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use logseq::dates::DateFormat;
+/// use logseq::templates::render_date_variables;
+/// let date = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+/// let text = "- Since <%yesterday%> at <% time %>";
+/// assert_eq!(render_date_variables(text, date, &DateFormat::default()),
+///     "- Since [[Jan 1st, 2024]] at <% time %>");
+/// assert_eq!(render_date_variables(text, date, &DateFormat::new("yyyy-MM-dd")),
+///     "- Since [[2024-01-01]] at <% time %>");
+/// ```
+pub fn render_date_variables(text: &str, date: NaiveDate, title_format: &DateFormat) -> String {
+    let mut rendered = String::new();
+    for line in text.split_inclusive('\n') {
+        let mut end = 0;
+        for variable in dynamic_variables(line) {
+            let day = match variable.name.to_lowercase().as_str() {
+                "today" | "current page" => Some(date),
+                "yesterday" => date.checked_sub_days(Days::new(1)),
+                "tomorrow" => date.checked_add_days(Days::new(1)),
+                _ => None,
+            };
+            if let Some(day) = day {
+                rendered.push_str(&line[end..variable.range.start]);
+                rendered.push_str(&format!("[[{}]]", title_format.format(day)));
+                end = variable.range.end;
+            }
+        }
+        rendered.push_str(&line[end..]);
+    }
+    rendered
+}
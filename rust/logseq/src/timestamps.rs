@@ -0,0 +1,169 @@
+//! The `created-at::` and `updated-at::` properties, which Logseq writes as milliseconds since
+//! the Unix epoch
+
+use crate::git::file_commit_times;
+use crate::graph::Graph;
+use crate::properties::insert_page_properties;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The property with the time a page or block was created
+pub const CREATED_AT: &str = "created-at";
+
+/// The property with the time a page or block was last changed
+pub const UPDATED_AT: &str = "updated-at";
+
+/// Formats of dates and times accepted in place of milliseconds, read as UTC
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+];
+
+/// How the value of a timestamp property is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestamp {
+    /// Milliseconds since the Unix epoch, as Logseq writes them
+    Millis(i64),
+    /// Seconds since the Unix epoch, with the value in milliseconds
+    Seconds(i64),
+    /// A date or an ISO 8601 date and time, with the value in milliseconds
+    DateTime(i64),
+    /// Anything else
+    Invalid,
+}
+
+impl Timestamp {
+    /// Reads the value of a timestamp property. Numbers of up to 10 digits are seconds (until
+    /// the year 2286), longer ones milliseconds; dates and times without a time zone are UTC.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::timestamps::Timestamp;
+    /// assert_eq!(Timestamp::parse("1704189600000"), Timestamp::Millis(1704189600000));
+    /// assert_eq!(Timestamp::parse("1704189600"), Timestamp::Seconds(1704189600000));
+    /// assert_eq!(Timestamp::parse("2024-01-02T10:00:00Z"), Timestamp::DateTime(1704189600000));
+    /// assert_eq!(Timestamp::parse("2024-01-02 10:00"), Timestamp::DateTime(1704189600000));
+    /// assert_eq!(Timestamp::parse("yesterday"), Timestamp::Invalid);
+    /// ```
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        if !raw.is_empty() && raw.chars().all(|c| c.is_ascii_digit()) {
+            return match (raw.len(), raw.parse::<i64>()) {
+                (..=10, Ok(seconds)) => Timestamp::Seconds(seconds * 1000),
+                (11..=13, Ok(millis)) => Timestamp::Millis(millis),
+                _ => Timestamp::Invalid,
+            };
+        }
+        if let Ok(time) = DateTime::parse_from_rfc3339(raw) {
+            return Timestamp::DateTime(time.timestamp_millis());
+        }
+        let naive = DATE_TIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+            .or_else(|| {
+                NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+            });
+        match naive {
+            Some(time) => Timestamp::DateTime(time.and_utc().timestamp_millis()),
+            None => Timestamp::Invalid,
+        }
+    }
+
+    /// The value in milliseconds, None if it's invalid
+    pub fn millis(&self) -> Option<i64> {
+        match self {
+            Timestamp::Millis(millis)
+            | Timestamp::Seconds(millis)
+            | Timestamp::DateTime(millis) => Some(*millis),
+            Timestamp::Invalid => None,
+        }
+    }
+}
+
+/// Where backfilled timestamps come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampSource {
+    /// The times the file was created and last modified, as the file system keeps them
+    #[default]
+    FileTimes,
+    /// The first and the last commit of the file in git, or its file times if it has none
+    Git,
+}
+
+/// Milliseconds since the Unix epoch of a system time
+fn system_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
+
+/// The (created, updated) times of a file in milliseconds
+pub(crate) fn file_times(path: &Path, source: TimestampSource) -> Result<(i64, i64)> {
+    if source == TimestampSource::Git {
+        if let Some((first, last)) = file_commit_times(path)? {
+            return Ok((first * 1000, last * 1000));
+        }
+    }
+    let metadata = fs::metadata(path).map_err(|source| Error::io(path, source))?;
+    let modified = metadata
+        .modified()
+        .map_err(|source| Error::io(path, source))?;
+    let created = metadata.created().unwrap_or(modified);
+    Ok((
+        system_millis(created.min(modified)),
+        system_millis(modified),
+    ))
+}
+
+/// Plans adding the missing `created-at::` and `updated-at::` page properties of the pages of
+/// a graph, from the times of their files. Nothing is written; see [backfill_timestamps].
+pub fn plan_backfill(graph: &Graph, source: TimestampSource) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        let properties = page.outline.page_properties();
+        let missing_created = properties.get(CREATED_AT).is_none();
+        let missing_updated = properties.get(UPDATED_AT).is_none();
+        if !missing_created && !missing_updated {
+            continue;
+        }
+        let (created, updated) = file_times(&page.path, source)?;
+        let mut new = Vec::new();
+        if missing_created {
+            new.push(format!("{}:: {}", CREATED_AT, created));
+        }
+        if missing_updated {
+            new.push(format!("{}:: {}", UPDATED_AT, updated));
+        }
+        let original = error::read_to_string(&page.path)?;
+        let mut lines: Vec<String> = original.split('\n').map(String::from).collect();
+        insert_page_properties(&page.outline, &mut lines, &new);
+        edits.push(FileEdit {
+            path: page.path.clone(),
+            original,
+            updated: lines.join("\n"),
+        });
+    }
+    Ok(edits)
+}
+
+/// Adds the missing `created-at::` and `updated-at::` page properties, see [plan_backfill];
+/// with `dry_run`, only plans the changes
+pub fn backfill_timestamps(
+    graph: &Graph,
+    source: TimestampSource,
+    dry_run: bool,
+) -> Result<Vec<FileEdit>> {
+    let edits = plan_backfill(graph, source)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
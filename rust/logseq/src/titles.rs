@@ -0,0 +1,230 @@
+//! Titles for bare URLs: `https://example.com` in a block becomes
+//! `[Example Domain](https://example.com)`, named after the `<title>` of the web page. Enabled by
+//! the `network` feature.
+//!
+//! Titles are cached in the [cache directory](crate::graph::index::cache_path), so that running
+//! again only fetches new URLs, and works offline. Requests are spaced by [FETCH_DELAY].
+
+use crate::clippings::decode_entities;
+use crate::graph::index::cache_dir;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::{apply_replacements, Replacement};
+use crate::outline::parse_outline;
+use crate::properties::split_property;
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+pub(crate) static URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>()\[\]{}"'`]+"#).unwrap());
+static TITLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap());
+
+/// The time between two requests
+pub const FETCH_DELAY: Duration = Duration::from_secs(1);
+
+/// How long to wait for a web page
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of a web page is read to find its title
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+
+/// The `<title>` of an HTML page, decoded and on one line, or None if it has none
+///
+/// # Examples
+///
+/// ```
+/// use logseq::titles::page_title;
+/// let html = "<html><head><TITLE>\n  Rust &amp; Logseq\n</TITLE></head></html>";
+/// assert_eq!(page_title(html).as_deref(), Some("Rust & Logseq"));
+/// assert_eq!(page_title("<title> </title>"), None);
+/// ```
+pub fn page_title(html: &str) -> Option<String> {
+    let captures = TITLE_RE.captures(html)?;
+    let title = decode_entities(&captures[1]);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+/// The byte ranges of the bare URLs of a line: not inside a Markdown link or image, a `[[link]]`,
+/// an `<autolink>` or inline code, without the punctuation that ends a sentence
+///
+/// # Examples
+///
+/// ```
+/// use logseq::titles::bare_urls;
+/// let line = "See https://example.com/a, [b](https://example.com/b) and `https://example.com/c`.";
+/// assert_eq!(bare_urls(line), vec![4..25]);
+/// ```
+pub fn bare_urls(line: &str) -> Vec<Range<usize>> {
+    let searchable = blank_code_spans(line);
+    URL_RE
+        .find_iter(&searchable)
+        .filter(|found| {
+            let before = &searchable[..found.start()];
+            !before.ends_with("](") && !before.ends_with(['<', '['])
+        })
+        .map(|found| {
+            let url = found
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            found.start()..found.start() + url.len()
+        })
+        .collect()
+}
+
+/// Finds the titles of web pages, from its cache or by fetching them
+pub struct Titler {
+    /// The file the titles are cached in, if any
+    cache: Option<PathBuf>,
+    /// The titles by URL, None for the pages without a title
+    titles: BTreeMap<String, Option<String>>,
+    /// Only use the cached titles, don't fetch any
+    pub offline: bool,
+    /// The time between two requests
+    pub delay: Duration,
+    last_fetch: Option<Instant>,
+    agent: ureq::Agent,
+}
+
+impl Titler {
+    /// A titler with the titles cached in a file, if any. A missing or unreadable cache file is
+    /// an empty cache.
+    pub fn new(cache: Option<PathBuf>) -> Self {
+        let titles = cache
+            .as_ref()
+            .and_then(|cache| std::fs::read_to_string(cache).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Titler {
+            cache,
+            titles,
+            offline: false,
+            delay: FETCH_DELAY,
+            last_fetch: None,
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    /// A titler with the titles cached in `titles.json` of the
+    /// [cache directory](crate::graph::index::cache_path)
+    pub fn with_default_cache() -> Self {
+        Titler::new(cache_dir().map(|dir| dir.join("titles.json")))
+    }
+
+    /// The title of a web page, from the cache or fetched. None if the page has no title or
+    /// can't be fetched; pages that can't be fetched aren't cached, to try again next time.
+    pub fn title(&mut self, url: &str) -> Option<String> {
+        if let Some(title) = self.titles.get(url) {
+            return title.clone();
+        }
+        if self.offline {
+            return None;
+        }
+        if let Some(wait) = self
+            .last_fetch
+            .and_then(|last| self.delay.checked_sub(last.elapsed()))
+        {
+            std::thread::sleep(wait);
+        }
+        self.last_fetch = Some(Instant::now());
+        let title = self.fetch(url)?;
+        self.titles.insert(url.to_string(), title.clone());
+        title
+    }
+
+    /// The title of an HTML page, None inside if it has none; None if it can't be fetched
+    fn fetch(&self, url: &str) -> Option<Option<String>> {
+        let response = self
+            .agent
+            .get(url)
+            .set(
+                "User-Agent",
+                concat!("logseq-doctor/", env!("CARGO_PKG_VERSION")),
+            )
+            .call()
+            .ok()?;
+        if !response.content_type().contains("html") {
+            return Some(None);
+        }
+        let mut html = Vec::new();
+        response
+            .into_reader()
+            .take(MAX_PAGE_BYTES)
+            .read_to_end(&mut html)
+            .ok()?;
+        Some(page_title(&String::from_utf8_lossy(&html)))
+    }
+
+    /// Writes the cache file, creating its directory if needed
+    pub fn save(&self) -> Result<()> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        if let Some(parent) = cache.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+        }
+        let json = serde_json::to_string_pretty(&self.titles)
+            .map_err(|error| Error::parse(Some(cache), error.to_string()))?;
+        error::write(cache, &json)
+    }
+}
+
+/// Turns the bare URLs of the blocks of a page into `[Title](url)` links, see [bare_urls].
+/// URLs in code and in properties, and URLs without a title, are left alone.
+pub fn title_urls<'a>(text: &'a str, titler: &mut Titler) -> Cow<'a, str> {
+    preserving_line_endings_if_changed(text, |text| {
+        let outline = parse_outline(text);
+        let mut replacements = Vec::new();
+        for block in outline.iter() {
+            for (line, content) in lines_outside_code(block.numbered_lines()) {
+                if split_property(content.trim()).is_some() {
+                    continue;
+                }
+                for range in bare_urls(content) {
+                    let url = &content[range.clone()];
+                    let Some(title) = titler.title(url) else {
+                        continue;
+                    };
+                    let title = title.replace('[', "\\[").replace(']', "\\]");
+                    replacements.push(Replacement {
+                        line,
+                        range,
+                        content_length: content.len(),
+                        text: format!("[{}]({})", title, url),
+                    });
+                }
+            }
+        }
+        apply_replacements(text, replacements)
+    })
+}
+
+/// Titles the bare URLs of files, see [title_urls], and saves the cache; with `dry_run`, only
+/// plans the changes. Returns the files that change.
+pub fn title_files(paths: &[PathBuf], titler: &mut Titler, dry_run: bool) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for path in paths {
+        let original = error::read_to_string(path)?;
+        if let Cow::Owned(updated) = title_urls(&original, titler) {
+            edits.push(FileEdit {
+                path: path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    titler.save()?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
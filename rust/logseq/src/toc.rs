@@ -0,0 +1,251 @@
+//! Tables of contents: a block at the top of a page with a `((ref))` to each of its heading
+//! blocks, nested like the headings, refreshed whenever the headings change.
+//!
+//! A page gets one when its [TOC_PROPERTY] page property is `true`, or `numbered` to number the
+//! headings like `1.2.`; `false` keeps long pages out of `--all` runs.
+
+use crate::graph::{Graph, GraphPage};
+use crate::ids::new_uuid;
+use crate::line_ending::preserving_line_endings_if_changed;
+use crate::lint::IndentUnit;
+use crate::outline::{parse_outline, Block, Outline};
+use crate::properties::split_property;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Result};
+use std::borrow::Cow;
+
+/// The page property that asks for a table of contents
+pub const TOC_PROPERTY: &str = "lsd-toc";
+
+/// The property of the block with the table of contents, to find it again when refreshing it
+pub const TOC_BLOCK_PROPERTY: &str = "lsd-toc-block";
+
+/// The first line of the block with the table of contents
+pub const TOC_TITLE: &str = "Contents";
+
+/// How many headings a page needs for `--all` to give it a table of contents, by default
+pub const MIN_HEADINGS: usize = 3;
+
+/// How the entries of a table of contents are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TocStyle {
+    /// `((ref))`
+    #[default]
+    Plain,
+    /// `1.2. ((ref))`
+    Numbered,
+}
+
+/// The style of table of contents a page asks for with its [TOC_PROPERTY] property: None for
+/// `false` or `no`, or when the page doesn't have the property
+pub fn requested_style(outline: &Outline) -> Option<TocStyle> {
+    let properties = outline.page_properties();
+    let value = properties.get(TOC_PROPERTY)?.raw.trim().to_lowercase();
+    match value.as_str() {
+        "false" | "no" => None,
+        "numbered" => Some(TocStyle::Numbered),
+        _ => Some(TocStyle::Plain),
+    }
+}
+
+/// The index of the line after a block and its children
+fn end_of(block: &Block) -> usize {
+    let last = block.iter().last().unwrap_or(block);
+    last.line + last.line_count() - 1
+}
+
+/// True if a block is the table of contents
+fn is_toc_block(block: &Block) -> bool {
+    block.level == 0 && block.properties().get(TOC_BLOCK_PROPERTY).is_some()
+}
+
+/// The heading blocks of a page outside its table of contents, with their level
+fn headings(outline: &Outline) -> Vec<(&Block, usize)> {
+    outline
+        .blocks
+        .iter()
+        .filter(|block| !is_toc_block(block))
+        .flat_map(Block::iter)
+        .filter_map(|block| Some((block, block.heading_level()?)))
+        .collect()
+}
+
+/// The lines of a table of contents with a `((ref))` to each heading, nested by level
+fn toc_lines(entries: &[(usize, &str)], style: TocStyle, unit: IndentUnit) -> Vec<String> {
+    let mut lines = vec![
+        format!("- {}", TOC_TITLE),
+        format!("  {}:: true", TOC_BLOCK_PROPERTY),
+    ];
+    // The levels of the headings the next one is nested under, and the number of each entry
+    // at the depth of its last heading
+    let mut parents: Vec<usize> = Vec::new();
+    let mut numbers: Vec<usize> = Vec::new();
+    for &(level, uuid) in entries {
+        while parents.last().is_some_and(|&parent| parent >= level) {
+            parents.pop();
+        }
+        let depth = parents.len();
+        numbers.truncate(depth + 1);
+        match numbers.get_mut(depth) {
+            Some(number) => *number += 1,
+            None => numbers.push(1),
+        }
+        parents.push(level);
+        let prefix = match style {
+            TocStyle::Plain => String::new(),
+            TocStyle::Numbered => {
+                let numbers: Vec<String> = numbers.iter().map(usize::to_string).collect();
+                format!("{}. ", numbers.join("."))
+            }
+        };
+        let indent = unit.indent(parents.len());
+        lines.push(format!("{}- {}(({}))", indent, prefix, uuid));
+    }
+    lines
+}
+
+/// Refreshes the table of contents of a page, adding an `id::` to the headings without one.
+/// The table is the block with the [TOC_BLOCK_PROPERTY] property, or a new block inserted at the
+/// top of the page, after its page properties; it's removed when the page has no headings.
+/// Returns the text unchanged if the table is up to date.
+///
+/// # Examples
+///
+/// ```
+/// use logseq::toc::{refresh_toc, TocStyle};
+/// let id = "65a1b2c3-0000-4000-8000-00000000000a";
+/// let text = format!("- # Intro\n  id:: {}\n\t- ## Details\n\t  id:: {}b\n- Notes\n", id, &id[..35]);
+/// let refreshed = refresh_toc(&text, TocStyle::Numbered);
+/// assert_eq!(refreshed, format!(
+///     "- Contents\n  lsd-toc-block:: true\n\t- 1. (({0}))\n\t\t- 1.1. (({1}b))\n{2}",
+///     id, &id[..35], text
+/// ));
+/// assert_eq!(refresh_toc(&refreshed, TocStyle::Numbered), refreshed);
+/// ```
+pub fn refresh_toc(text: &str, style: TocStyle) -> Cow<'_, str> {
+    preserving_line_endings_if_changed(text, |text| {
+        let outline = parse_outline(text);
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let unit = outline
+            .iter()
+            .find(|block| block.level == 1)
+            .and_then(|block| IndentUnit::detect(&block.indent))
+            .unwrap_or(IndentUnit::Tab);
+        // The `id::` lines to insert, with the index they are inserted at
+        let mut ids: Vec<(usize, String)> = Vec::new();
+        let mut entries: Vec<(usize, String)> = Vec::new();
+        for (block, level) in headings(&outline) {
+            let uuid = match block.properties().get("id") {
+                Some(id) => id.raw.trim().to_lowercase(),
+                None => {
+                    let uuid = new_uuid();
+                    ids.push((block.line, format!("{}  id:: {}", block.indent, uuid)));
+                    uuid
+                }
+            };
+            entries.push((level, uuid));
+        }
+        let entries: Vec<(usize, &str)> = entries
+            .iter()
+            .map(|(level, uuid)| (*level, uuid.as_str()))
+            .collect();
+        let toc = match entries.is_empty() {
+            true => Vec::new(),
+            false => toc_lines(&entries, style, unit),
+        };
+        // The lines the table replaces, as a range of line indexes
+        let existing = outline.blocks.iter().find(|block| is_toc_block(block));
+        let replaced = match existing {
+            Some(block) => block.line - 1..end_of(block),
+            None => {
+                let first = outline.blocks.first();
+                let page_properties = outline.preamble.iter().all(|line| line.trim().is_empty())
+                    && first.is_some_and(|block| {
+                        block
+                            .content
+                            .lines()
+                            .all(|line| split_property(line).is_some())
+                    });
+                let at = match (first, page_properties) {
+                    (Some(block), true) => end_of(block),
+                    (Some(block), false) => block.line - 1,
+                    (None, _) => lines.len(),
+                };
+                at..at
+            }
+        };
+        if lines[replaced.clone()] == toc[..] && ids.is_empty() {
+            return None;
+        }
+        let mut changes: Vec<_> = ids
+            .into_iter()
+            .map(|(at, id)| (at..at, vec![id]))
+            .chain([(replaced, toc)])
+            .collect();
+        // From the end, so that the lines of the next changes don't move
+        changes.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+        for (range, inserted) in changes {
+            lines.splice(range, inserted);
+        }
+        Some(lines.join("\n"))
+    })
+}
+
+/// Which pages get a table of contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TocOptions {
+    /// Also the pages without the [TOC_PROPERTY] property that have at least `min_headings`
+    /// headings
+    pub all: bool,
+    /// How many headings a page needs for `all`
+    pub min_headings: usize,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        TocOptions {
+            all: false,
+            min_headings: MIN_HEADINGS,
+        }
+    }
+}
+
+/// The style of table of contents of a page, or None if it doesn't get one
+fn page_style(page: &GraphPage, options: &TocOptions) -> Option<TocStyle> {
+    let properties = page.outline.page_properties();
+    if properties.get(TOC_PROPERTY).is_some() {
+        return requested_style(&page.outline);
+    }
+    (options.all && headings(&page.outline).len() >= options.min_headings)
+        .then_some(TocStyle::Plain)
+}
+
+/// Plans refreshing the tables of contents of the pages of a graph that ask for one, see
+/// [refresh_toc]. Nothing is written; see [refresh_tocs].
+pub fn plan_tocs(graph: &Graph, options: &TocOptions) -> Result<Vec<FileEdit>> {
+    let mut edits = Vec::new();
+    for page in &graph.pages {
+        let Some(style) = page_style(page, options) else {
+            continue;
+        };
+        let original = error::read_to_string(&page.path)?;
+        if let Cow::Owned(updated) = refresh_toc(&original, style) {
+            edits.push(FileEdit {
+                path: page.path.clone(),
+                original,
+                updated,
+            });
+        }
+    }
+    Ok(edits)
+}
+
+/// Refreshes the tables of contents of a graph, see [plan_tocs]; with `dry_run`, only plans the
+/// changes
+pub fn refresh_tocs(graph: &Graph, options: &TocOptions, dry_run: bool) -> Result<Vec<FileEdit>> {
+    let edits = plan_tocs(graph, options)?;
+    if !dry_run {
+        write_edits(&edits)?;
+    }
+    Ok(edits)
+}
@@ -0,0 +1,424 @@
+//! A small parser for the subset of [TOML](https://toml.io) used by the `.lsd.toml` config file:
+//! tables, dotted and quoted keys, strings, integers, floats, booleans, arrays and inline tables
+
+use crate::{Error, Result};
+
+/// A TOML value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Toml {
+    /// A string like `"text"` or `'text'`, with escapes resolved
+    String(String),
+    /// An integer like `42` or `1_000`
+    Integer(i64),
+    /// A floating point number like `1.5`
+    Float(f64),
+    /// `true` or `false`
+    Bool(bool),
+    /// An array like `[1, 2]`
+    Array(Vec<Toml>),
+    /// A table, from a `[header]` or an inline `{ a = 1 }`, in the order of the file
+    Table(Vec<(String, Toml)>),
+}
+
+impl Toml {
+    /// Parses a whole document into its root table
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::toml::Toml;
+    /// let document = Toml::parse("# rules\n[lint]\ndisable = [\"timestamps\"]\n\n[rules.task-markers]\nworkflow = 'todo'\n").unwrap();
+    /// let lint = document.get("lint").unwrap();
+    /// assert_eq!(lint.get("disable"), Some(&Toml::Array(vec![Toml::String("timestamps".to_string())])));
+    /// let workflow = document.get("rules").and_then(|rules| rules.get("task-markers")).and_then(|rule| rule.get("workflow"));
+    /// assert_eq!(workflow.and_then(Toml::as_str), Some("todo"));
+    /// assert!(Toml::parse("a = ").is_err());
+    /// assert!(Toml::parse("a = 1\na = 2").is_err());
+    /// ```
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let mut root = Toml::Table(Vec::new());
+        // Keys of the table that `key = value` lines go into
+        let mut current: Vec<String> = Vec::new();
+        // Tables defined by a header, which can't be defined again
+        let mut headers: Vec<Vec<String>> = Vec::new();
+        loop {
+            parser.skip_whitespace_and_comments();
+            let Some(c) = parser.peek() else {
+                break;
+            };
+            if c == '[' {
+                parser.position += 1;
+                if parser.peek() == Some('[') {
+                    return Err(parser.error("arrays of tables are not supported"));
+                }
+                let keys = parser.keys()?;
+                parser.skip_spaces();
+                parser.expect(']')?;
+                if headers.contains(&keys) {
+                    return Err(parser.error(&format!("table {} defined twice", keys.join("."))));
+                }
+                root.table_mut(&keys)
+                    .ok_or_else(|| parser.error(&format!("{} is not a table", keys.join("."))))?;
+                headers.push(keys.clone());
+                current = keys;
+            } else {
+                let (keys, value) = parser.key_value()?;
+                let table = root.table_mut(&current).unwrap_or_else(|| unreachable!());
+                insert(table, &keys, value).map_err(|message| parser.error(&message))?;
+            }
+            parser.end_of_line()?;
+        }
+        Ok(root)
+    }
+
+    /// The value of a key of a table
+    pub fn get(&self, key: &str) -> Option<&Toml> {
+        self.as_table()?
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// The text of a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Toml::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The value of a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Toml::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Toml::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The items of an array
+    pub fn as_array(&self) -> Option<&[Toml]> {
+        match self {
+            Toml::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The entries of a table
+    pub fn as_table(&self) -> Option<&[(String, Toml)]> {
+        match self {
+            Toml::Table(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// The table at the given keys, created if missing; None if a value on the way isn't a table
+    fn table_mut(&mut self, keys: &[String]) -> Option<&mut Vec<(String, Toml)>> {
+        let Toml::Table(entries) = self else {
+            return None;
+        };
+        let Some((first, rest)) = keys.split_first() else {
+            return Some(entries);
+        };
+        let index = match entries.iter().position(|(name, _)| name == first) {
+            Some(index) => index,
+            None => {
+                entries.push((first.clone(), Toml::Table(Vec::new())));
+                entries.len() - 1
+            }
+        };
+        entries[index].1.table_mut(rest)
+    }
+}
+
+/// Inserts a value at dotted keys, creating the intermediate tables
+fn insert(
+    table: &mut Vec<(String, Toml)>,
+    keys: &[String],
+    value: Toml,
+) -> std::result::Result<(), String> {
+    let (last, parents) = keys.split_last().unwrap_or_else(|| unreachable!());
+    let mut parent = Toml::Table(std::mem::take(table));
+    let result = match parent.table_mut(parents) {
+        None => Err(format!("{} is not a table", parents.join("."))),
+        Some(entries) if entries.iter().any(|(name, _)| name == last) => {
+            Err(format!("key {} defined twice", keys.join(".")))
+        }
+        Some(entries) => {
+            entries.push((last.clone(), value));
+            Ok(())
+        }
+    };
+    if let Toml::Table(entries) = parent {
+        *table = entries;
+    }
+    result
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> Error {
+        let consumed = &self.chars[..self.position.min(self.chars.len())];
+        let line = consumed.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = consumed.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        Error::parse(None, format!("TOML {}:{}: {}", line, column, message))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// Skips spaces and tabs on the current line
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.position += 1;
+        }
+    }
+
+    /// Skips whitespace, line breaks included, and `#` comments
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.position += 1;
+            } else if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Only spaces and a comment can follow a value or a header on its line
+    fn end_of_line(&mut self) -> Result<()> {
+        self.skip_spaces();
+        if self.peek() == Some('#') {
+            while self.peek().is_some_and(|c| c != '\n') {
+                self.position += 1;
+            }
+        }
+        match self.peek() {
+            None | Some('\n') => Ok(()),
+            Some('\r') if self.chars.get(self.position + 1) == Some(&'\n') => Ok(()),
+            Some(_) => Err(self.error("expected the end of the line")),
+        }
+    }
+
+    /// Parses dotted keys like `rules."task-markers".workflow`
+    fn keys(&mut self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        loop {
+            self.skip_spaces();
+            let key = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let start = self.position;
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    {
+                        self.position += 1;
+                    }
+                    if start == self.position {
+                        return Err(self.error("expected a key"));
+                    }
+                    self.chars[start..self.position].iter().collect()
+                }
+            };
+            keys.push(key);
+            self.skip_spaces();
+            if self.peek() != Some('.') {
+                return Ok(keys);
+            }
+            self.position += 1;
+        }
+    }
+
+    fn key_value(&mut self) -> Result<(Vec<String>, Toml)> {
+        let keys = self.keys()?;
+        self.skip_spaces();
+        self.expect('=')?;
+        self.skip_spaces();
+        Ok((keys, self.value()?))
+    }
+
+    fn value(&mut self) -> Result<Toml> {
+        match self.peek() {
+            Some('"') => Ok(Toml::String(self.basic_string()?)),
+            Some('\'') => Ok(Toml::String(self.literal_string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.atom(),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(offset, c)| self.chars.get(self.position + offset) == Some(&c))
+    }
+
+    fn basic_string(&mut self) -> Result<String> {
+        let multiline = self.starts_with("\"\"\"");
+        self.position += if multiline { 3 } else { 1 };
+        if multiline && self.peek() == Some('\n') {
+            self.position += 1;
+        }
+        let mut text = String::new();
+        loop {
+            if multiline && self.starts_with("\"\"\"") {
+                self.position += 3;
+                return Ok(text);
+            }
+            let c = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.position += 1;
+            match c {
+                '"' if !multiline => return Ok(text),
+                '\n' if !multiline => return Err(self.error("unterminated string")),
+                '\\' => {
+                    let escaped = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    text.push(match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '"' | '\\' => escaped,
+                        'u' | 'U' => self.unicode_escape(if escaped == 'u' { 4 } else { 8 })?,
+                        _ => return Err(self.error(&format!("invalid escape \\{}", escaped))),
+                    });
+                }
+                other => text.push(other),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char> {
+        let end = (self.position + digits).min(self.chars.len());
+        let hex: String = self.chars[self.position..end].iter().collect();
+        self.position = end;
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(&format!("invalid unicode escape {}", hex)))
+    }
+
+    fn literal_string(&mut self) -> Result<String> {
+        let multiline = self.starts_with("'''");
+        let delimiter = if multiline { "'''" } else { "'" };
+        self.position += delimiter.len();
+        if multiline && self.peek() == Some('\n') {
+            self.position += 1;
+        }
+        let start = self.position;
+        while !self.starts_with(delimiter) {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some('\n') if !multiline => return Err(self.error("unterminated string")),
+                Some(_) => self.position += 1,
+            }
+        }
+        let text = self.chars[start..self.position].iter().collect();
+        self.position += delimiter.len();
+        Ok(text)
+    }
+
+    fn array(&mut self) -> Result<Toml> {
+        self.position += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace_and_comments();
+            if self.peek() == Some(']') {
+                self.position += 1;
+                return Ok(Toml::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_whitespace_and_comments();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {}
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Toml> {
+        self.position += 1;
+        let mut entries = Vec::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Toml::Table(entries));
+        }
+        loop {
+            let (keys, value) = self.key_value()?;
+            insert(&mut entries, &keys, value).map_err(|message| self.error(&message))?;
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(Toml::Table(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+            self.skip_spaces();
+        }
+    }
+
+    fn atom(&mut self) -> Result<Toml> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))
+        {
+            self.position += 1;
+        }
+        let token: String = self.chars[start..self.position].iter().collect();
+        match token.as_str() {
+            "" => Err(self.error("expected a value")),
+            "true" => Ok(Toml::Bool(true)),
+            "false" => Ok(Toml::Bool(false)),
+            _ => {
+                let number = token.replace('_', "");
+                if let Ok(integer) = number.parse::<i64>() {
+                    Ok(Toml::Integer(integer))
+                } else if let Ok(float) = number.parse::<f64>() {
+                    Ok(Toml::Float(float))
+                } else {
+                    Err(self.error(&format!("invalid value {}", token)))
+                }
+            }
+        }
+    }
+}
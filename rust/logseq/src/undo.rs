@@ -0,0 +1,333 @@
+//! The undo journal: the changes that fixes write to files are recorded in the [UNDO_LOG] of
+//! their graph, each with the patch that reverses it, so that a fixing session can be rolled
+//! back with [undo].
+//!
+//! Recording is off until [start_session]: the `lsd` command line starts one for each run.
+//! Changes to the contents of files are recorded, and so are files moved or deleted, with the
+//! contents of the deleted ones when they are text; files created aren't recorded.
+
+use crate::diff::{apply_hunks, hunks, Hunk};
+use crate::graph::find_graph_root;
+use crate::rename::{write_edits, FileEdit};
+use crate::{error, Error, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The log of changes, relative to the root of a graph: one JSON object per line
+pub const UNDO_LOG: &str = ".lsd/undo";
+
+/// The session changes are recorded in, if any
+static SESSION: Mutex<Option<i64>> = Mutex::new(None);
+
+/// A change written to a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoEntry {
+    /// The session the change was made in: the time it started, in milliseconds since 1970
+    pub session: i64,
+    /// When the change was written, in RFC 3339 format
+    pub time: String,
+    /// The file changed: where it is after the change
+    pub path: PathBuf,
+    /// What changed
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+/// A change to a file recorded in the undo log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Change {
+    /// The contents changed: `patch` changes the new contents back to the previous ones
+    Edit {
+        /// The changes from the new contents of the file back to the previous ones
+        patch: Vec<Hunk>,
+    },
+    /// The file was moved from `from`
+    Move {
+        /// Where the file was before
+        from: PathBuf,
+    },
+    /// The file was deleted
+    Delete {
+        /// Its contents, None if they weren't text
+        contents: Option<String>,
+    },
+}
+
+/// Starts recording the changes written to files in the undo log of their graph; the changes
+/// recorded until the process ends form a session. Returns the id of the session.
+pub fn start_session() -> i64 {
+    let session = Local::now().timestamp_millis();
+    *SESSION.lock().unwrap_or_else(|error| error.into_inner()) = Some(session);
+    session
+}
+
+/// The directory whose undo log records the changes to a file: the root of its graph, or the
+/// directory of the file outside a graph
+fn journal_root(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    find_graph_root(&absolute)
+        .or_else(|| absolute.parent().map(Path::to_path_buf))
+        .unwrap_or_default()
+}
+
+/// The session recording changes, if any
+fn session() -> Option<i64> {
+    *SESSION.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+/// Appends a change to a file to the undo log of its graph, if a session was started
+fn append(path: &Path, change: Change) -> Result<()> {
+    let session = SESSION.lock().unwrap_or_else(|error| error.into_inner());
+    let Some(id) = *session else {
+        return Ok(());
+    };
+    let entry = UndoEntry {
+        session: id,
+        time: Local::now().to_rfc3339(),
+        path: absolute(path),
+        change,
+    };
+    let log = journal_root(path).join(UNDO_LOG);
+    if let Some(parent) = log.parent() {
+        fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+    }
+    let mut line = serde_json::to_string(&entry)
+        .map_err(|error| Error::parse(Some(&log), error.to_string()))?;
+    line.push('\n');
+    // The lock is held while appending, so that the lines written in parallel don't mix
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|source| Error::io(&log, source))
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Records a change written to a file, from its `previous` contents to its `current` ones, if
+/// a session was started
+pub(crate) fn record(path: &Path, previous: &str, current: &str) -> Result<()> {
+    if previous == current {
+        return Ok(());
+    }
+    let patch = hunks(current, previous);
+    append(path, Change::Edit { patch })
+}
+
+/// Moves a file, and records the move if a session was started
+pub(crate) fn move_file(from: &Path, to: &Path) -> Result<()> {
+    fs::rename(from, to).map_err(|source| Error::io(from, source))?;
+    let from = absolute(from);
+    append(to, Change::Move { from })
+}
+
+/// Deletes a file, and records the deletion with the contents of the file if a session was
+/// started
+pub(crate) fn delete_file(path: &Path) -> Result<()> {
+    let contents = session()
+        .and_then(|_| fs::read(path).ok())
+        .and_then(|bytes| String::from_utf8(bytes).ok());
+    fs::remove_file(path).map_err(|source| Error::io(path, source))?;
+    append(path, Change::Delete { contents })
+}
+
+/// Reads the undo log of a graph, in the order the changes were written; an empty list if
+/// there is none
+pub fn read_undo_log(root: &Path) -> Result<Vec<UndoEntry>> {
+    let log = root.join(UNDO_LOG);
+    if !log.is_file() {
+        return Ok(Vec::new());
+    }
+    error::read_to_string(&log)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|error| Error::parse(Some(&log), format!("line {}: {}", index + 1, error)))
+        })
+        .collect()
+}
+
+/// What rolling back sessions does
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    /// The sessions rolled back, the most recent first
+    pub sessions: Vec<i64>,
+    /// The files restored to their previous contents, and the files moved or deleted written
+    /// back where they were, with empty `original` contents
+    pub edits: Vec<FileEdit>,
+    /// The files deleted: the ones that were moved, once written back where they were
+    pub deleted: Vec<PathBuf>,
+    /// The files left alone because they changed since, or no longer exist
+    pub conflicts: Vec<PathBuf>,
+    /// The files deleted that can't be restored, since their contents weren't text
+    pub lost: Vec<PathBuf>,
+}
+
+/// A file while sessions are rolled back
+struct FileState {
+    /// Its contents on disk, None if there is no file
+    current: Option<String>,
+    /// Its contents before the changes rolled back so far, None if there was no file
+    restored: Option<String>,
+    /// Whether `restored` comes from another file moved back here, so it must be written
+    moved_back: bool,
+    /// Whether the earlier changes to it are left alone, after a conflict
+    stopped: bool,
+}
+
+impl FileState {
+    fn read(path: &Path) -> Self {
+        let current = error::read_to_string(path).ok();
+        FileState {
+            restored: current.clone(),
+            current,
+            moved_back: false,
+            stopped: false,
+        }
+    }
+
+    /// Leaves the file and its earlier changes alone, as far as possible: contents moved back
+    /// here from another file are kept, since the other file is gone
+    fn stop(&mut self) {
+        if !self.moved_back {
+            self.restored = self.current.clone();
+        }
+        self.stopped = true;
+    }
+}
+
+/// The contents a file had before a change, from its contents after it; None if the file
+/// changed since
+fn reverse(current: &str, patch: &[Hunk]) -> Option<String> {
+    let lines: Vec<&str> = current.split('\n').collect();
+    let matches = patch.iter().all(|hunk| {
+        let start = hunk.line - 1;
+        lines
+            .get(start..start + hunk.removed.len())
+            .is_some_and(|found| found.iter().eq(hunk.removed.iter()))
+    });
+    matches.then(|| apply_hunks(current, patch))
+}
+
+/// Rolls back the last `last` sessions of the undo log of a graph, the most recent first: each
+/// file is restored to its contents before the session, unless it changed since; files moved
+/// are moved back, unless there is a file where they were, and files deleted are written again,
+/// unless there is a new file in their place. The sessions rolled back leave the log, conflicts
+/// included. With `dry_run`, nothing is written and the log is kept.
+pub fn undo(root: &Path, last: usize, dry_run: bool) -> Result<UndoReport> {
+    let entries = read_undo_log(root)?;
+    let mut sessions: Vec<i64> = Vec::new();
+    for entry in entries.iter().rev() {
+        if !sessions.contains(&entry.session) {
+            sessions.push(entry.session);
+        }
+    }
+    sessions.truncate(last);
+
+    let mut files: BTreeMap<PathBuf, FileState> = BTreeMap::new();
+    let mut report = UndoReport {
+        sessions: sessions.clone(),
+        ..UndoReport::default()
+    };
+    for entry in entries
+        .iter()
+        .rev()
+        .filter(|entry| sessions.contains(&entry.session))
+    {
+        let path = &entry.path;
+        let state = files
+            .entry(path.clone())
+            .or_insert_with(|| FileState::read(path));
+        if state.stopped {
+            continue;
+        }
+        match (&entry.change, &state.restored) {
+            (Change::Edit { patch }, Some(contents)) => match reverse(contents, patch) {
+                Some(previous) => state.restored = Some(previous),
+                None => state.stop(),
+            },
+            (Change::Move { from }, Some(_)) => {
+                let before = files
+                    .entry(from.clone())
+                    .or_insert_with(|| FileState::read(from));
+                if before.stopped || before.restored.is_some() {
+                    files.get_mut(path).expect("read above").stop();
+                    continue;
+                }
+                let state = files.get_mut(path).expect("read above");
+                let contents = state.restored.take();
+                state.moved_back = false;
+                let before = files.get_mut(from).expect("read above");
+                before.restored = contents;
+                before.moved_back = true;
+            }
+            (
+                Change::Delete {
+                    contents: Some(contents),
+                },
+                None,
+            ) => {
+                state.restored = Some(contents.clone());
+            }
+            (Change::Delete { contents: None }, None) => {
+                report.lost.push(path.clone());
+                state.stop();
+            }
+            _ => state.stop(),
+        }
+    }
+
+    for (path, state) in files {
+        if state.stopped && !report.lost.contains(&path) {
+            report.conflicts.push(path.clone());
+        }
+        match (state.current, state.restored) {
+            (Some(original), Some(updated)) if original != updated => report.edits.push(FileEdit {
+                path,
+                original,
+                updated,
+            }),
+            (None, Some(updated)) => report.edits.push(FileEdit {
+                path,
+                original: String::new(),
+                updated,
+            }),
+            (Some(_), None) => report.deleted.push(path),
+            _ => {}
+        }
+    }
+    if !dry_run {
+        for edit in &report.edits {
+            if let Some(parent) = edit.path.parent() {
+                fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+            }
+        }
+        write_edits(&report.edits)?;
+        for path in &report.deleted {
+            fs::remove_file(path).map_err(|source| Error::io(path, source))?;
+        }
+        let kept: Vec<String> = entries
+            .iter()
+            .filter(|entry| !sessions.contains(&entry.session))
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect();
+        let log = root.join(UNDO_LOG);
+        if log.is_file() {
+            error::write(&log, &kept.concat())?;
+        }
+    }
+    Ok(report)
+}
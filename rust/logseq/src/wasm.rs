@@ -0,0 +1,96 @@
+//! JavaScript bindings with `wasm-bindgen`, so a Logseq plugin runs the same outline parser,
+//! linter and fixers as `lsd`. Enabled by the `wasm` feature; build for `wasm32-unknown-unknown`
+//! without the default features:
+//!
+//! ```sh
+//! cargo build -p logseq --lib --target wasm32-unknown-unknown --no-default-features --features wasm
+//! ```
+//!
+//! Values that aren't strings cross to JavaScript as JSON, to be read with `JSON.parse()`.
+//! Every function takes the contents of a `.lsd.toml` file to configure the rules, or
+//! `undefined` for the built-in rules with their default options.
+
+use crate::lint::{LintConfig, RuleSet};
+use crate::outline::parse_outline;
+use crate::report::Report;
+use crate::{Error, Result};
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+/// The rules of a `.lsd.toml` configuration, or all built-in rules
+fn rules(config: Option<String>) -> Result<RuleSet> {
+    match config {
+        Some(contents) => LintConfig::parse(&contents, Path::new("."))?.rule_set(),
+        None => Ok(RuleSet::builtin()),
+    }
+}
+
+/// Converts an error to a JavaScript `Error`
+fn js_error(error: Error) -> JsError {
+    JsError::new(&error.to_string())
+}
+
+/// Parses a page into its outline, as JSON:
+/// `{"preamble": [...], "blocks": [...], "style": {...}}` where each block has its `line`,
+/// `level`, `indent`, `marker`, `content` and `children`, and the `separator` after the bullet,
+/// the `continuation_indents` and the `line_endings` when they aren't the usual ones
+///
+/// # Examples
+///
+/// ```
+/// use logseq::wasm::parse_outline_json;
+/// assert!(parse_outline_json("- a\n  - b").starts_with(r#"{"preamble":[],"blocks":[{"line":1,"#));
+/// ```
+#[wasm_bindgen(js_name = parseOutline)]
+pub fn parse_outline_json(text: &str) -> String {
+    serde_json::to_string(&parse_outline(text)).unwrap_or_default()
+}
+
+/// The ids of the rules, as a JSON array
+#[wasm_bindgen(js_name = ruleIds)]
+pub fn rule_ids(config: Option<String>) -> std::result::Result<String, JsError> {
+    let ids = rules(config).map_err(js_error)?.ids();
+    Ok(serde_json::to_string(&ids).unwrap_or_default())
+}
+
+/// Checks a page with the rules, returning a JSON report like `lsd lint --format json`:
+/// `{"findings": [...], "files": []}`, with the `suppressed` findings when there are some
+///
+/// # Examples
+///
+/// ```
+/// use logseq::wasm::lint;
+/// let report = lint("- a  b", None).ok().unwrap_or_default();
+/// assert!(report.starts_with(r#"{"findings":[{"rule":"consecutive-spaces","#));
+/// ```
+#[wasm_bindgen]
+pub fn lint(text: &str, config: Option<String>) -> std::result::Result<String, JsError> {
+    let result = rules(config).map_err(js_error)?.lint(text);
+    Ok(Report::from_lint(None, &result).to_json())
+}
+
+/// Fixes a page with all the auto-fixable rules, returning the fixed text
+///
+/// # Examples
+///
+/// ```
+/// use logseq::wasm::fix;
+/// assert_eq!(fix("- a  b", None).ok().as_deref(), Some("- a b"));
+/// let config = "[lint]\ndisable = [\"consecutive-spaces\"]\n".to_string();
+/// assert_eq!(fix("- a  b", Some(config)).ok().as_deref(), Some("- a  b"));
+/// ```
+#[wasm_bindgen]
+pub fn fix(text: &str, config: Option<String>) -> std::result::Result<String, JsError> {
+    Ok(rules(config).map_err(js_error)?.fix(text).into_owned())
+}
+
+/// Fixes a page with a single rule, returning the fixed text, or `undefined` if the rule
+/// changes nothing
+#[wasm_bindgen(js_name = fixRule)]
+pub fn fix_rule(
+    id: &str,
+    text: &str,
+    config: Option<String>,
+) -> std::result::Result<Option<String>, JsError> {
+    Ok(rules(config).map_err(js_error)?.fix_rule(id, text))
+}
@@ -0,0 +1,304 @@
+//! Watch Markdown files for changes, to fix them while Logseq (or a sync tool) writes them
+
+use crate::graph::markdown_files;
+use crate::Result;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often [Watcher::wait] looks at the files it waits for
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often a watcher without file system events walks the directories for new files; in
+/// between, it only looks at the files it knows
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a file must stay unchanged before it is reported, as editors save several times in
+/// a row while typing
+pub const DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// What the watcher knows about a file: its modification time and size
+type Stamp = (SystemTime, u64);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The file system events of the watched paths
+struct Events {
+    /// Stops the events when dropped
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<notify::Result<Event>>,
+}
+
+/// Watches the Markdown files of some paths (see [markdown_files]), with the file system events
+/// of the platform. A changed file is then the only one looked at, and the directories are only
+/// walked again for new files. Where there are no events, like on some network drives, the
+/// watcher polls instead: see [Watcher::polling].
+///
+/// Files are reported once they have stopped changing for the debounce delay. Files written by
+/// the caller should be passed to [Watcher::ignore_own_write], so that fixing a file doesn't
+/// report it again.
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    /// The absolute forms of the paths, to find the files of events
+    absolute: Vec<(PathBuf, PathBuf)>,
+    debounce: Duration,
+    /// The files as they were last reported (or when the watch started)
+    known: HashMap<PathBuf, Stamp>,
+    /// Changed files not reported yet, with their last change and when it was seen
+    pending: HashMap<PathBuf, (Stamp, Instant)>,
+    /// None when polling
+    events: Option<Events>,
+    /// The files of the events received so far
+    touched: HashSet<PathBuf>,
+    /// True if the directories must be walked again, for new files
+    rescan: bool,
+    /// When the directories were last walked
+    scanned: Instant,
+}
+
+impl Watcher {
+    /// Starts watching the Markdown files of some paths with file system events, or by polling
+    /// if the platform has none for them; the files as they are now aren't reported
+    pub fn new(paths: &[PathBuf], debounce: Duration) -> Result<Self> {
+        let mut watcher = Self::polling(paths, debounce)?;
+        watcher.events = watch_events(paths);
+        Ok(watcher)
+    }
+
+    /// Starts watching the Markdown files of some paths by polling them: each look at the files
+    /// compares the modification times and sizes of the known files, which works the same on
+    /// every platform and network drive, and the directories are walked for new files every
+    /// [RESCAN_INTERVAL]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use logseq::watch::Watcher;
+    /// use std::time::Duration;
+    /// let temp = assert_fs::TempDir::new().unwrap();
+    /// let dir = temp.path().to_path_buf();
+    /// let page = dir.join("page.md");
+    /// std::fs::write(&page, "- a").unwrap();
+    /// let mut watcher = Watcher::polling(&[dir.clone()], Duration::ZERO).unwrap();
+    /// assert!(watcher.changes().unwrap().is_empty());
+    ///
+    /// std::fs::write(&page, "-  changed").unwrap();
+    /// assert_eq!(watcher.changes().unwrap(), vec![page.clone()]);
+    /// std::fs::write(&page, "- changed").unwrap();
+    /// watcher.ignore_own_write(&page);
+    /// assert!(watcher.changes().unwrap().is_empty());
+    /// ```
+    pub fn polling(paths: &[PathBuf], debounce: Duration) -> Result<Self> {
+        let absolute = paths
+            .iter()
+            .flat_map(|path| {
+                let absolute = std::path::absolute(path).ok();
+                let canonical = fs::canonicalize(path).ok();
+                [absolute, canonical]
+                    .into_iter()
+                    .flatten()
+                    .map(|absolute| (absolute, path.clone()))
+            })
+            .collect();
+        let mut watcher = Watcher {
+            paths: paths.to_vec(),
+            absolute,
+            debounce,
+            known: HashMap::new(),
+            pending: HashMap::new(),
+            events: None,
+            touched: HashSet::new(),
+            rescan: false,
+            scanned: Instant::now(),
+        };
+        watcher.known = watcher.scan()?;
+        Ok(watcher)
+    }
+
+    /// True if the watcher has file system events, false if it polls
+    pub fn has_events(&self) -> bool {
+        self.events.is_some()
+    }
+
+    fn scan(&self) -> Result<HashMap<PathBuf, Stamp>> {
+        let mut stamps = HashMap::new();
+        for path in &self.paths {
+            for file in markdown_files(path)? {
+                if let Some(stamp) = stamp(&file) {
+                    stamps.insert(file, stamp);
+                }
+            }
+        }
+        Ok(stamps)
+    }
+
+    /// The watched path of the file of an event, as [markdown_files] would name it
+    fn watched_path(&self, path: &Path) -> Option<PathBuf> {
+        if self.known.contains_key(path) {
+            return Some(path.to_path_buf());
+        }
+        self.absolute.iter().find_map(|(absolute, watched)| {
+            let rest = path.strip_prefix(absolute).ok()?;
+            Some(match rest.as_os_str().is_empty() {
+                true => watched.clone(),
+                false => watched.join(rest),
+            })
+        })
+    }
+
+    /// Takes in an event: its known files are looked at, and other Markdown files or
+    /// directories are looked for by walking the directories again
+    fn receive(&mut self, event: notify::Result<Event>) {
+        let Ok(event) = event else {
+            self.rescan = true;
+            return;
+        };
+        self.rescan |= event.need_rescan();
+        for path in &event.paths {
+            match self.watched_path(path) {
+                Some(path)
+                    if self.known.contains_key(&path) || self.pending.contains_key(&path) =>
+                {
+                    self.touched.insert(path);
+                }
+                Some(path) => {
+                    let markdown = path.extension().is_some_and(|extension| extension == "md");
+                    self.rescan |= markdown || path.is_dir();
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// The files that may have changed since the last look, with their stamps now
+    fn current(&mut self, now: Instant) -> Result<HashMap<PathBuf, Stamp>> {
+        let mut received = Vec::new();
+        if let Some(events) = &self.events {
+            received.extend(events.receiver.try_iter());
+        }
+        for event in received {
+            self.receive(event);
+        }
+        let rescan = match self.events {
+            Some(_) => std::mem::take(&mut self.rescan),
+            None => now.duration_since(self.scanned) >= RESCAN_INTERVAL,
+        };
+        if rescan {
+            self.touched.clear();
+            self.scanned = now;
+            let current = self.scan()?;
+            self.known.retain(|path, _| current.contains_key(path));
+            self.pending.retain(|path, _| current.contains_key(path));
+            return Ok(current);
+        }
+        let mut paths: HashSet<PathBuf> = self.pending.keys().cloned().collect();
+        match self.events {
+            Some(_) => paths.extend(self.touched.drain()),
+            None => paths.extend(self.known.keys().cloned()),
+        }
+        let mut current = HashMap::new();
+        for path in paths {
+            match stamp(&path) {
+                Some(stamp) => {
+                    current.insert(path, stamp);
+                }
+                None => {
+                    self.known.remove(&path);
+                    self.pending.remove(&path);
+                }
+            }
+        }
+        Ok(current)
+    }
+
+    /// Looks at the files that may have changed, and returns the new and modified files (sorted)
+    /// that haven't changed for the debounce delay. Deleted files are forgotten.
+    pub fn changes(&mut self) -> Result<Vec<PathBuf>> {
+        let now = Instant::now();
+        let current = self.current(now)?;
+        for (path, stamp) in &current {
+            if self.known.get(path) == Some(stamp) {
+                self.pending.remove(path);
+                continue;
+            }
+            match self.pending.get(path) {
+                Some((pending, _)) if pending == stamp => {}
+                _ => {
+                    self.pending.insert(path.clone(), (*stamp, now));
+                }
+            }
+        }
+        let mut ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, seen))| now.duration_since(*seen) >= self.debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        ready.sort();
+        for path in &ready {
+            if let Some((stamp, _)) = self.pending.remove(path) {
+                self.known.insert(path.clone(), stamp);
+            }
+        }
+        Ok(ready)
+    }
+
+    /// Takes the file as it is now as unchanged, after the caller wrote it
+    pub fn ignore_own_write(&mut self, path: &Path) {
+        self.pending.remove(path);
+        if let Some(stamp) = stamp(path) {
+            self.known.insert(path.to_path_buf(), stamp);
+        }
+    }
+
+    /// Waits until some files have changed, see [Watcher::changes]. With file system events,
+    /// the files are only looked at when there are events or files waiting for the debounce.
+    pub fn wait(&mut self) -> Result<Vec<PathBuf>> {
+        loop {
+            let changes = self.changes()?;
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+            let Some(events) = &self.events else {
+                std::thread::sleep(POLL_INTERVAL);
+                continue;
+            };
+            let event = match self.pending.is_empty() {
+                true => events
+                    .receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+                false => events.receiver.recv_timeout(POLL_INTERVAL),
+            };
+            match event {
+                Ok(event) => self.receive(event),
+                Err(RecvTimeoutError::Timeout) => {}
+                // The events stopped: poll from now on
+                Err(RecvTimeoutError::Disconnected) => self.events = None,
+            }
+        }
+    }
+}
+
+/// The file system events of some paths, or None if the platform can't watch them all
+fn watch_events(paths: &[PathBuf]) -> Option<Events> {
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender).ok()?;
+    for path in paths {
+        let mode = match path.is_dir() {
+            true => RecursiveMode::Recursive,
+            false => RecursiveMode::NonRecursive,
+        };
+        watcher.watch(path, mode).ok()?;
+    }
+    Some(Events {
+        _watcher: watcher,
+        receiver,
+    })
+}
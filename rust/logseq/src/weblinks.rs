@@ -0,0 +1,280 @@
+//! Check the `http(s)` links of a graph, reporting the ones that are dead. Enabled by the
+//! `network` feature.
+//!
+//! Links are checked with `HEAD` requests, several at a time, and retried with a backoff when
+//! the server or the network fails. Results are cached in the
+//! [cache directory](crate::graph::index::cache_path) for [CACHE_TTL], so that running again
+//! only checks new links and the ones checked long ago.
+
+use crate::graph::index::cache_dir;
+use crate::graph::{BrokenLink, Graph};
+use crate::refs::{blank_code_spans, lines_outside_code};
+use crate::titles::URL_RE;
+use crate::{error, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long the result of a link is cached
+pub const CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// How many links are checked at the same time
+pub const CONCURRENCY: usize = 8;
+
+/// How many times a link is checked again when the server or the network fails
+pub const RETRIES: u32 = 2;
+
+/// The wait before checking a link again, doubled at each retry
+pub const BACKOFF: Duration = Duration::from_millis(500);
+
+/// How long to wait for a server
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `http(s)` URLs of a line, outside inline code, without the punctuation that ends a
+/// sentence
+///
+/// # Examples
+///
+/// ```
+/// use logseq::weblinks::web_urls;
+/// let line = "See [a](https://example.com/a), <https://example.com/b>. `https://example.com/c`";
+/// assert_eq!(web_urls(line), vec![(9, "https://example.com/a"), (34, "https://example.com/b")]);
+/// ```
+pub fn web_urls(line: &str) -> Vec<(usize, &str)> {
+    let searchable = blank_code_spans(line);
+    URL_RE
+        .find_iter(&searchable)
+        .map(|found| {
+            let url = found
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            let column = line[..found.start()].chars().count() + 1;
+            (column, &line[found.start()..found.start() + url.len()])
+        })
+        .collect()
+}
+
+/// The `http(s)` links of the pages of a graph, outside code, with the URL as target
+pub fn web_links(graph: &Graph) -> Vec<BrokenLink> {
+    let mut links = Vec::new();
+    for page in &graph.pages {
+        let preamble = page
+            .outline
+            .preamble
+            .iter()
+            .enumerate()
+            .map(|(index, line)| (index + 1, 1, line.as_str()));
+        let blocks = page.outline.iter().flat_map(|block| {
+            lines_outside_code(block.numbered_lines())
+                .map(move |(line, text)| (line, block.source_column(1), text))
+        });
+        for (line, start, text) in preamble.chain(blocks) {
+            for (column, url) in web_urls(text) {
+                links.push(BrokenLink {
+                    path: page.path.clone(),
+                    line,
+                    column: start + column - 1,
+                    target: url.to_string(),
+                });
+            }
+        }
+    }
+    links
+}
+
+/// What checking a link found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkResult {
+    /// HTTP status of the response, after redirects, or None if there was no response
+    pub status: Option<u16>,
+    /// Why there was no response, like a DNS or TLS failure or a timeout
+    pub error: Option<String>,
+    /// When the link was checked, in seconds since the Unix epoch
+    pub checked: u64,
+}
+
+impl LinkResult {
+    /// True if the link is dead: a `4xx` or `5xx` status, or no response
+    pub fn is_dead(&self) -> bool {
+        self.status.is_none_or(|status| status >= 400)
+    }
+
+    /// The status, or the error, for people
+    pub fn describe(&self) -> String {
+        match (&self.status, &self.error) {
+            (Some(status), _) => format!("HTTP {}", status),
+            (None, Some(error)) => error.clone(),
+            (None, None) => "no response".to_string(),
+        }
+    }
+}
+
+/// Seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// True if checking a link again may find it alive: a network failure, too many requests, or a
+/// server error
+fn retryable(result: &LinkResult) -> bool {
+    result
+        .status
+        .is_none_or(|status| status == 429 || status >= 500)
+}
+
+/// Checks links, with their results cached
+pub struct LinkChecker {
+    /// The file the results are cached in, if any
+    cache: Option<PathBuf>,
+    /// The results by URL
+    results: BTreeMap<String, LinkResult>,
+    /// How long a result is cached
+    pub ttl: Duration,
+    /// How many links are checked at the same time
+    pub concurrency: usize,
+    /// How many times a link is checked again when the server or the network fails
+    pub retries: u32,
+    /// The wait before checking a link again, doubled at each retry
+    pub backoff: Duration,
+    agent: ureq::Agent,
+}
+
+impl LinkChecker {
+    /// A checker with the results cached in a file, if any. A missing or unreadable cache file
+    /// is an empty cache.
+    pub fn new(cache: Option<PathBuf>) -> Self {
+        let results = cache
+            .as_ref()
+            .and_then(|cache| std::fs::read_to_string(cache).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        LinkChecker {
+            cache,
+            results,
+            ttl: CACHE_TTL,
+            concurrency: CONCURRENCY,
+            retries: RETRIES,
+            backoff: BACKOFF,
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    /// A checker with the results cached in `links.json` of the
+    /// [cache directory](crate::graph::index::cache_path)
+    pub fn with_default_cache() -> Self {
+        LinkChecker::new(cache_dir().map(|dir| dir.join("links.json")))
+    }
+
+    /// The result of a link checked by [LinkChecker::check], or cached
+    pub fn result(&self, url: &str) -> Option<&LinkResult> {
+        self.results.get(url)
+    }
+
+    /// The status of a response to a request for the link, or why there was none
+    fn call(&self, method: &str, url: &str) -> (Option<u16>, Option<String>) {
+        let user_agent = concat!("logseq-doctor/", env!("CARGO_PKG_VERSION"));
+        match self
+            .agent
+            .request(method, url)
+            .set("User-Agent", user_agent)
+            .call()
+        {
+            Ok(response) => (Some(response.status()), None),
+            Err(ureq::Error::Status(status, _)) => (Some(status), None),
+            Err(ureq::Error::Transport(transport)) => (None, Some(transport.to_string())),
+        }
+    }
+
+    /// Requests the link, with a `GET` for the servers that don't answer `HEAD` requests
+    fn request(&self, url: &str) -> LinkResult {
+        let (status, error) = match self.call("HEAD", url) {
+            (Some(405 | 501), _) => self.call("GET", url),
+            response => response,
+        };
+        LinkResult {
+            status,
+            error,
+            checked: now(),
+        }
+    }
+
+    /// Checks a link, again after a backoff while it may be retried
+    fn check_one(&self, url: &str) -> LinkResult {
+        let mut result = self.request(url);
+        let mut wait = self.backoff;
+        for _ in 0..self.retries {
+            if !retryable(&result) {
+                break;
+            }
+            std::thread::sleep(wait);
+            wait *= 2;
+            result = self.request(url);
+        }
+        result
+    }
+
+    /// Checks the links that aren't cached, or were cached longer than the TTL ago,
+    /// [LinkChecker::concurrency] at a time
+    pub fn check(&mut self, urls: &[String]) {
+        let expired = now().saturating_sub(self.ttl.as_secs());
+        let mut queue: Vec<&str> = urls
+            .iter()
+            .map(String::as_str)
+            .filter(|url| {
+                self.results
+                    .get(*url)
+                    .is_none_or(|result| result.checked <= expired)
+            })
+            .collect();
+        queue.sort_unstable();
+        queue.dedup();
+        let queue = Mutex::new(queue);
+        let checked = Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for _ in 0..self.concurrency.max(1) {
+                scope.spawn(|| loop {
+                    let Some(url) = queue.lock().unwrap().pop() else {
+                        break;
+                    };
+                    let result = self.check_one(url);
+                    checked.lock().unwrap().push((url.to_string(), result));
+                });
+            }
+        });
+        self.results.extend(checked.into_inner().unwrap());
+    }
+
+    /// Writes the cache file, creating its directory if needed
+    pub fn save(&self) -> Result<()> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        if let Some(parent) = cache.parent() {
+            std::fs::create_dir_all(parent).map_err(|source| Error::io(parent, source))?;
+        }
+        let json = serde_json::to_string_pretty(&self.results)
+            .map_err(|error| Error::parse(Some(cache), error.to_string()))?;
+        error::write(cache, &json)
+    }
+}
+
+/// Checks the `http(s)` links of a graph, see [web_links], and saves the cache of the checker.
+/// Returns the dead links, with the URL as target; their results are in the checker.
+pub fn check_web_links(graph: &Graph, checker: &mut LinkChecker) -> Result<Vec<BrokenLink>> {
+    let links = web_links(graph);
+    let urls: Vec<String> = links.iter().map(|link| link.target.clone()).collect();
+    checker.check(&urls);
+    checker.save()?;
+    Ok(links
+        .into_iter()
+        .filter(|link| {
+            checker
+                .result(&link.target)
+                .is_some_and(LinkResult::is_dead)
+        })
+        .collect())
+}
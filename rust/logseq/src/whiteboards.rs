@@ -0,0 +1,216 @@
+//! Whiteboards: the `.edn` (and older `.tldr`) files of the `whiteboards` directory, and the
+//! portal shapes in them that show a page or a block of the graph
+
+use crate::edn::Edn;
+use crate::error::read_to_string;
+use crate::graph::{collect_files_matching, page_name_from_file_stem, BrokenLink, Graph};
+use crate::{Error, Result, SUBDIR_WHITEBOARDS};
+use std::path::{Path, PathBuf};
+
+/// The `type` of the shapes that show a page or a block in a whiteboard
+pub const PORTAL_SHAPE: &str = "logseq-portal";
+
+/// What a portal shape shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalTarget {
+    /// A page, by name (`blockType` `P`)
+    Page(String),
+    /// A block, by UUID (`blockType` `B`)
+    Block(String),
+}
+
+/// A portal shape of a whiteboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Portal {
+    /// Line of its target in the file, starting at 1
+    pub line: usize,
+    /// Column of its target, starting at 1
+    pub column: usize,
+    /// The page or block it shows
+    pub target: PortalTarget,
+}
+
+/// A whiteboard file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Whiteboard {
+    /// Full path to the file
+    pub path: PathBuf,
+    /// Name of the whiteboard, from its file name
+    pub name: String,
+    /// Its portal shapes, in the order of the file
+    pub portals: Vec<Portal>,
+}
+
+/// The `(pageId, blockType)` of the portal shapes of an EDN value, at any depth
+fn edn_portals(value: &Edn, portals: &mut Vec<(String, String)>) {
+    match value {
+        Edn::Map(entries) => {
+            if value.get("type").and_then(Edn::as_str) == Some(PORTAL_SHAPE) {
+                if let Some(page_id) = value.get("pageId").and_then(Edn::as_str) {
+                    let block_type = value.get("blockType").and_then(Edn::as_str);
+                    portals.push((page_id.to_string(), block_type.unwrap_or_default().into()));
+                }
+            }
+            for (_, value) in entries {
+                edn_portals(value, portals);
+            }
+        }
+        Edn::List(items) | Edn::Vector(items) | Edn::Set(items) => {
+            for item in items {
+                edn_portals(item, portals);
+            }
+        }
+        Edn::Tagged(_, value) => edn_portals(value, portals),
+        _ => {}
+    }
+}
+
+/// The `(pageId, blockType)` of the portal shapes of a tldraw JSON document, at any depth
+fn json_portals(value: &serde_json::Value, portals: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if map.get("type").and_then(|kind| kind.as_str()) == Some(PORTAL_SHAPE) {
+                if let Some(page_id) = map.get("pageId").and_then(|id| id.as_str()) {
+                    let block_type = map.get("blockType").and_then(|kind| kind.as_str());
+                    portals.push((page_id.to_string(), block_type.unwrap_or_default().into()));
+                }
+            }
+            for value in map.values() {
+                json_portals(value, portals);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                json_portals(item, portals);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parses the contents of a whiteboard file: EDN, or tldraw JSON for `.tldr` files
+///
+/// # Examples
+///
+/// ```
+/// use logseq::whiteboards::{parse_whiteboard, PortalTarget};
+/// use std::path::Path;
+/// let whiteboard = parse_whiteboard(
+///     Path::new("whiteboards/Plans.edn"),
+///     r#"{:blocks ({:block/properties {:ls-type :whiteboard-shape,
+///   :logseq.tldraw.shape {:type "logseq-portal", :blockType "P", :pageId "Roadmap"}}})}"#,
+/// ).unwrap();
+/// assert_eq!(whiteboard.name, "Plans");
+/// assert_eq!(whiteboard.portals[0].target, PortalTarget::Page("Roadmap".to_string()));
+/// assert_eq!((whiteboard.portals[0].line, whiteboard.portals[0].column), (2, 73));
+/// ```
+pub fn parse_whiteboard(path: &Path, text: &str) -> Result<Whiteboard> {
+    let mut found = Vec::new();
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("tldr"));
+    if is_json {
+        let value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|error| Error::parse(Some(path), format!("invalid whiteboard: {}", error)))?;
+        json_portals(&value, &mut found);
+    } else {
+        edn_portals(
+            &Edn::parse(text).map_err(|error| error.with_path(path))?,
+            &mut found,
+        );
+    }
+
+    // Shapes are found in the order of the file, so each target is searched after the previous
+    let mut cursor = 0;
+    let portals = found
+        .into_iter()
+        .map(|(page_id, block_type)| {
+            let (line, column) = match text[cursor..].find(&format!("\"{}\"", page_id)) {
+                Some(offset) => {
+                    let position = cursor + offset + 1;
+                    cursor = position;
+                    let before = &text[..position];
+                    let line = before.matches('\n').count() + 1;
+                    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+                    (line, before[line_start..].chars().count() + 1)
+                }
+                None => (1, 1),
+            };
+            let target = match block_type.eq_ignore_ascii_case("B") {
+                true => PortalTarget::Block(page_id.to_lowercase()),
+                false => PortalTarget::Page(page_id),
+            };
+            Portal {
+                line,
+                column,
+                target,
+            }
+        })
+        .collect();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(Whiteboard {
+        path: path.to_path_buf(),
+        name: page_name_from_file_stem(&stem),
+        portals,
+    })
+}
+
+/// Reads the whiteboards of a graph, sorted by path
+pub fn list_whiteboards(root: &Path) -> Result<Vec<Whiteboard>> {
+    let mut paths = Vec::new();
+    let is_whiteboard = |path: &Path| {
+        path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("edn") || extension.eq_ignore_ascii_case("tldr")
+        })
+    };
+    collect_files_matching(&root.join(SUBDIR_WHITEBOARDS), &is_whiteboard, &mut paths)?;
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| parse_whiteboard(path, &read_to_string(path)?))
+        .collect()
+}
+
+/// Portals of whiteboards that show something missing from the graph
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BrokenPortals {
+    /// Portals to pages that don't exist, neither as a page nor as a whiteboard
+    pub pages: Vec<BrokenLink>,
+    /// Portals to blocks whose `id::` doesn't exist anywhere in the graph
+    pub blocks: Vec<BrokenLink>,
+}
+
+/// Finds the portals of the whiteboards of a graph that show missing pages or blocks, like
+/// [Graph::check_links] and [Graph::check_block_refs] do for Markdown
+pub fn check_whiteboards(graph: &Graph) -> Result<BrokenPortals> {
+    let whiteboards = list_whiteboards(&graph.root)?;
+    let mut names = graph.page_names();
+    names.extend(
+        whiteboards
+            .iter()
+            .map(|whiteboard| whiteboard.name.to_lowercase()),
+    );
+    let ids = graph.block_ids();
+    let mut broken = BrokenPortals::default();
+    for whiteboard in &whiteboards {
+        for portal in &whiteboard.portals {
+            let link = |target: &str| BrokenLink {
+                path: whiteboard.path.clone(),
+                line: portal.line,
+                column: portal.column,
+                target: target.to_string(),
+            };
+            match &portal.target {
+                PortalTarget::Page(name) if !names.contains(&name.to_lowercase()) => {
+                    broken.pages.push(link(name))
+                }
+                PortalTarget::Block(uuid) if !ids.contains(uuid) => broken.blocks.push(link(uuid)),
+                _ => {}
+            }
+        }
+    }
+    Ok(broken)
+}
@@ -0,0 +1,1240 @@
+//! Integration tests for the lsd command line
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use std::fs;
+use std::process::Command;
+
+fn lsd(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lsd"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_lint_and_fix_a_directory() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- Some  text #[[tag]]\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["lint", root]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.contains("page.md:1:7: warning consecutive-spaces"));
+
+    let (code, stdout) = lsd(&["fix", "--diff", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("@@ -1 +1 @@\n-- Some  text #[[tag]]\n+- Some text #tag\n"));
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- Some  text #[[tag]]\n"
+    );
+
+    let (code, stdout) = lsd(&["fix", "--check", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.ends_with("page.md: would be fixed\n"));
+
+    let (code, stdout) = lsd(&["fix", root]);
+    assert_eq!(code, 3);
+    assert!(stdout.ends_with("page.md: fixed\n"));
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- Some text #tag\n"
+    );
+
+    assert_eq!(lsd(&["lint", root]), (0, String::new()));
+    assert_eq!(lsd(&["fix", "--check", root]), (0, String::new()));
+    assert_eq!(lsd(&["fix", root]), (0, String::new()));
+}
+
+#[test]
+fn test_check_links() {
+    let (code, stdout) = lsd(&["check-links", "tests/fixtures/graph"]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("Index.md:1:25: missing page [[Missing Page]]"));
+    assert!(stdout.contains("missing block ((65a1b2c3-0000-4000-8000-00000000dead))"));
+}
+
+#[test]
+fn test_check_links_suggestions() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Kubernetes.md")
+        .write_str("- Clusters\n")
+        .unwrap();
+    let notes = temp.child("pages/Notes.md");
+    notes.write_str("- See [[Kubernets]]\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["check-links", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.ends_with("missing page [[Kubernets]], did you mean [[Kubernetes]]?\n"));
+
+    let (code, stdout) = lsd(&["check-links", "--apply-suggestions", "--dry-run", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("-- See [[Kubernets]]\n+- See [[Kubernetes]]\n"));
+    assert_eq!(
+        fs::read_to_string(notes.path()).unwrap(),
+        "- See [[Kubernets]]\n"
+    );
+
+    let (code, _) = lsd(&["check-links", "--apply-suggestions", root]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        fs::read_to_string(notes.path()).unwrap(),
+        "- See [[Kubernetes]]\n"
+    );
+}
+
+#[test]
+fn test_journal_title_format_of_the_graph() {
+    let temp = TempDir::new().unwrap();
+    temp.child("logseq/config.edn")
+        .write_str(
+            "{:journal/page-title-format \"yyyy-MM-dd\"\n :default-templates {:journals \"Daily\"}}\n",
+        )
+        .unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- 10:00 Standup\n- 09:00 Coffee\n")
+        .unwrap();
+    temp.child("journals/2024_01_04.md")
+        .write_str("- Done\n")
+        .unwrap();
+    temp.child("pages/Templates.md")
+        .write_str("- Daily\n  template:: daily\n  template-including-parent:: false\n  - After <% yesterday %>\n")
+        .unwrap();
+    temp.child(".lsd.toml")
+        .write_str("[rules.front-matter]\ndates = \"journal\"\n")
+        .unwrap();
+    let page = temp.child("pages/Notes.md");
+    page.write_str("---\ncreated: 2024-01-04\n---\n- See [[2024-01-02]]\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    assert_eq!(lsd(&["check-links", root]), (0, String::new()));
+
+    let (code, stdout) = lsd(&["lint", temp.child("journals").to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("2024_01_02.md:2:3: warning block-times"));
+
+    let (code, _) = lsd(&["journal-gaps", "--create", "--template", root]);
+    assert_eq!(code, 0);
+    temp.child("journals/2024_01_03.md")
+        .assert("- After [[2024-01-02]]\n");
+
+    lsd(&["fix", page.to_str().unwrap()]);
+    page.assert("created:: [[2024-01-04]]\n\n- See [[2024-01-02]]\n");
+}
+
+#[test]
+fn test_source_blocks_and_ignored_lines_are_left_alone() {
+    let temp = TempDir::new().unwrap();
+    let text = "- Code\n  #+BEGIN_SRC markdown\n  - x  y\n      - z\n  #+END_SRC\n- a  b on 5 March <!-- lsd-ignore -->\n- span <!-- lsd-ignore-start -->\n    - odd  indent on 5 March\n  - end <!-- lsd-ignore-end -->\n";
+    let journal = temp.child("journals/2024_03_06.md");
+    journal.write_str(text).unwrap();
+    let root = temp.path().to_str().unwrap();
+    let path = journal.to_str().unwrap();
+
+    assert_eq!(lsd(&["lint", path]), (0, String::new()));
+    assert_eq!(lsd(&["fix", path]), (0, String::new()));
+    lsd(&["remove-spaces", path]);
+    assert_eq!(lsd(&["link-dates", root]).0, 0);
+    journal.assert(text);
+}
+
+#[test]
+fn test_lint_config_file() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".lsd.toml")
+        .write_str("[lint]\ndisable = [\"tag-brackets\"]\nignore = [\"pages/archive\"]\n")
+        .unwrap();
+    temp.child("pages/page.md")
+        .write_str("- Some  text #[[tag]]\n")
+        .unwrap();
+    temp.child("pages/archive/old.md")
+        .write_str("- Old  text\n")
+        .unwrap();
+    let pages = temp.child("pages");
+    let pages = pages.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["lint", pages]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("page.md:1:7: warning consecutive-spaces"));
+
+    let (_, stdout) = lsd(&["lint", "--no-config", pages]);
+    assert_eq!(stdout.lines().count(), 3);
+
+    let (_, stdout) = lsd(&["lint", "--enable", "tag-brackets,consecutive-spaces", pages]);
+    assert_eq!(stdout.lines().count(), 2);
+
+    let (code, stdout) = lsd(&["lint", "--disable", "consecutive-spaces", pages]);
+    assert_eq!((code, stdout), (0, String::new()));
+
+    let (code, _) = lsd(&["lint", "--enable", "no-such-rule", pages]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_include_and_exclude_globs() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".lsd.toml")
+        .write_str("[lint]\ninclude = [\"pages\"]\n")
+        .unwrap();
+    for path in [
+        "pages/a.md",
+        "pages/b.md",
+        "pages/sub/c.md",
+        "journals/2024_01_01.md",
+    ] {
+        temp.child(path).write_str("- Some  text\n").unwrap();
+    }
+    let root = temp.path().to_str().unwrap();
+    let files = |args: &[&str]| {
+        let (_, stdout) = lsd(&[&["lint"], args, &[root]].concat());
+        let mut files: Vec<String> = stdout
+            .lines()
+            .map(|line| line.split(':').next().unwrap().replace('\\', "/"))
+            .map(|path| path.rsplit("/pages/").next().unwrap().to_string())
+            .collect();
+        files.sort();
+        files
+    };
+    assert_eq!(files(&[]), vec!["a.md", "b.md", "sub/c.md"]);
+    assert_eq!(
+        files(&["--exclude", "sub", "--exclude", "b.md"]),
+        vec!["a.md"]
+    );
+    assert_eq!(files(&["--no-config", "--include", "2024_*.md"]).len(), 1);
+    assert_eq!(lsd(&["lint", "--include", "[", root]).0, 2);
+}
+
+#[test]
+fn test_output_formats() {
+    let (code, stdout) = lsd(&["check-links", "--format", "jsonl", "tests/fixtures/graph"]);
+    assert_eq!(code, 1);
+    let findings: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(findings
+        .iter()
+        .any(|finding| finding["rule"] == "missing-page" && finding["line"] == 1));
+    assert!(findings
+        .iter()
+        .any(|finding| finding["rule"] == "missing-block"));
+
+    let temp = TempDir::new().unwrap();
+    temp.child("page.md").write_str("- a  b\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+    let (code, stdout) = lsd(&["lint", "--format", "sarif", root]);
+    assert_eq!(code, 1);
+    let log: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let result = &log["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "consecutive-spaces");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["region"]["startColumn"],
+        4
+    );
+    assert_eq!(
+        log["runs"][0]["tool"]["driver"]["rules"][0]["id"],
+        "consecutive-spaces"
+    );
+
+    let (code, stdout) = lsd(&["fix", "--format", "json", root]);
+    assert_eq!(code, 3);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["findings"][0]["fixed"], true);
+    assert_eq!(report["files"][0]["changed"], true);
+
+    let (code, _) = lsd(&["lint", "--format", "xml", root]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_lint_baseline() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".lsd.toml").write_str("").unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- Some  text\n- task \n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["lint", "--update-baseline", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with(".lsd-baseline.json: recorded 2 findings\n"));
+    let baseline = fs::read_to_string(temp.path().join(".lsd-baseline.json")).unwrap();
+    assert!(baseline.contains("\"pages/page.md\""));
+    assert_eq!(lsd(&["lint", root]), (0, String::new()));
+
+    page.write_str("- Some  text\n- task \n- New  one\n")
+        .unwrap();
+    let (code, stdout) = lsd(&["lint", root]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("page.md:3:6: warning consecutive-spaces"));
+
+    let other = temp.child("other.json");
+    assert_eq!(
+        lsd(&["lint", "--baseline", other.path().to_str().unwrap(), root]).0,
+        1
+    );
+}
+
+#[test]
+fn test_lint_fingerprints() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".lsd.toml").write_str("").unwrap();
+    temp.child("pages/b.md")
+        .write_str("- Some  text\n")
+        .unwrap();
+    let page = temp.child("pages/a.md");
+    page.write_str("- Some  text\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (_, stdout) = lsd(&["lint", "--format", "json", root]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let findings = report["findings"].as_array().unwrap();
+    assert!(findings[0]["path"].as_str().unwrap().ends_with("a.md"));
+    assert!(findings[1]["path"].as_str().unwrap().ends_with("b.md"));
+    let fingerprint = findings[0]["fingerprint"].as_str().unwrap().to_string();
+    assert_eq!(fingerprint.len(), 16);
+    assert_ne!(findings[1]["fingerprint"], findings[0]["fingerprint"]);
+    assert_eq!(lsd(&["lint", "--format", "json", root]).1, stdout);
+
+    assert_eq!(lsd(&["lint", "--update-baseline", root]).0, 0);
+    page.write_str("- Intro\n- Some  text\n- Other  text\n")
+        .unwrap();
+    let (_, stdout) = lsd(&["lint", "--format", "json", root]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let findings = report["findings"].as_array().unwrap();
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0]["line"], 3);
+    let other = temp.child("other.json");
+    let other = other.path().to_str().unwrap();
+    let (_, stdout) = lsd(&["lint", "--format", "json", "--baseline", other, root]);
+    assert!(stdout.contains(&fingerprint));
+}
+
+#[test]
+fn test_lint_thresholds() {
+    let temp = TempDir::new().unwrap();
+    temp.child(".lsd.toml")
+        .write_str("[rules.consecutive-spaces]\nseverity = \"info\"\n[rules.tag-brackets]\nseverity = \"error\"\n")
+        .unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- Some  text\n- task \n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["lint", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("page.md:1:7: info consecutive-spaces"));
+    assert!(stdout.contains("page.md:2:7: warning trailing-whitespace"));
+    assert_eq!(lsd(&["lint", "--fail-on", "error", root]).0, 0);
+    assert_eq!(lsd(&["lint", "--max-warnings", "1", root]).0, 0);
+    assert_eq!(
+        lsd(&["lint", "--fail-on", "error", "--max-warnings", "0", root]).0,
+        1
+    );
+
+    page.write_str("- Some  text #[[tag]]\n").unwrap();
+    assert_eq!(lsd(&["lint", "--max-warnings", "5", root]).0, 1);
+    assert_eq!(lsd(&["lint", "--fail-on", "info", root]).0, 1);
+    let (_, stdout) = lsd(&["lint", "--format", "sarif", root]);
+    let log: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let levels: Vec<&str> = log["runs"][0]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| result["level"].as_str().unwrap())
+        .collect();
+    assert_eq!(levels, vec!["note", "error"]);
+    assert_eq!(lsd(&["lint", "--fail-on", "fatal", root]).0, 2);
+}
+
+#[test]
+fn test_migrate_org() {
+    let temp = TempDir::new().unwrap();
+    let org = temp.child("pages/page.org");
+    org.write_str("* Parent\n** Child with =code=\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["migrate-org", "--delete", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("page.org -> "));
+    assert!(!org.path().exists());
+    assert_eq!(
+        fs::read_to_string(temp.child("pages/page.md").path()).unwrap(),
+        "- Parent\n\t- Child with `code`\n"
+    );
+}
+
+#[test]
+fn test_import_obsidian() {
+    let vault = TempDir::new().unwrap();
+    vault
+        .child("Books/Dune.md")
+        .write_str("---\ntags: [scifi]\n---\nSee ![[images/cover.png]] and [[2024-01-02|today]]\n")
+        .unwrap();
+    vault
+        .child("2024-01-02.md")
+        .write_str("Read [[Dune]]\n")
+        .unwrap();
+    vault.child("images/cover.png").write_str("").unwrap();
+    vault.child("other/cover.png").write_str("").unwrap();
+    vault.child(".obsidian/app.json").write_str("{}").unwrap();
+    let graph = TempDir::new().unwrap();
+    let args = [
+        "import",
+        "obsidian",
+        vault.path().to_str().unwrap(),
+        "--graph",
+        graph.path().to_str().unwrap(),
+    ];
+
+    let (code, stdout) = lsd(&args);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.lines().count(), 4);
+    assert_eq!(
+        fs::read_to_string(graph.child("pages/Dune.md").path()).unwrap(),
+        "tags:: scifi\n\n- See ![cover.png](../assets/cover.png) and [today]([[Jan 2nd, 2024]])\n"
+    );
+    assert_eq!(
+        fs::read_to_string(graph.child("journals/2024_01_02.md").path()).unwrap(),
+        "- Read [[Dune]]\n"
+    );
+    assert!(graph.child("assets/cover-2.png").path().exists());
+    assert!(!graph.child("assets/app.json").path().exists());
+
+    // Importing again would replace the pages
+    let (code, _) = lsd(&args);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_import_roam() {
+    let temp = TempDir::new().unwrap();
+    let export = temp.child("roam.json");
+    export
+        .write_str(r#"[{"title": "January 2nd, 2024", "uid": "01-02-2024", "children": [{"string": "{{[[DONE]]}} Read"}]}, {"title": "a/b", "children": [{"string": "x\ny"}]}]"#)
+        .unwrap();
+    let graph = temp.child("graph");
+    let args = [
+        "import",
+        "roam",
+        export.path().to_str().unwrap(),
+        "--graph",
+        graph.path().to_str().unwrap(),
+    ];
+
+    let (code, _) = lsd(&args);
+    assert_eq!(code, 0);
+    assert_eq!(
+        fs::read_to_string(graph.child("journals/2024_01_02.md").path()).unwrap(),
+        "- DONE Read\n"
+    );
+    assert_eq!(
+        fs::read_to_string(graph.child("pages/a___b.md").path()).unwrap(),
+        "- x\n  y\n"
+    );
+    let (code, _) = lsd(&args);
+    assert_eq!(code, 2);
+
+    export.write_str("{\"not\": \"a list\"}").unwrap();
+    let (code, _) = lsd(&["import", "roam", export.path().to_str().unwrap()]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_export_markdown() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/page.md")
+        .write_str("- # Title\n- See [[other]]\n")
+        .unwrap();
+    let output = temp.child("site");
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&[
+        "export",
+        "markdown",
+        root,
+        "--output",
+        output.path().to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("page.md"));
+    assert_eq!(
+        fs::read_to_string(output.child("page.md").path()).unwrap(),
+        "# Title\n\nSee [other](other.md)\n"
+    );
+}
+
+#[test]
+fn test_lint_changed_files_only() {
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=lsd", "-c", "user.email=lsd@example.com"])
+            .args(args)
+            .current_dir(temp.path())
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    };
+    temp.child("pages/committed.md")
+        .write_str("- Some  text\n")
+        .unwrap();
+    temp.child("pages/modified.md")
+        .write_str("- text\n")
+        .unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "pages"]);
+    temp.child("pages/modified.md")
+        .write_str("- Some  text\n")
+        .unwrap();
+    temp.child("pages/staged.md")
+        .write_str("- Some  text\n")
+        .unwrap();
+    temp.child("pages/untracked.md")
+        .write_str("- Some  text\n")
+        .unwrap();
+    git(&["add", "pages/staged.md"]);
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["lint", "--changed", "--no-config", root]);
+    assert_eq!(code, 1);
+    let files: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.split(':').next().unwrap())
+        .collect();
+    assert_eq!(files.len(), 2);
+    assert!(files[0].ends_with("modified.md"));
+    assert!(files[1].ends_with("staged.md"));
+
+    let outside = TempDir::new().unwrap();
+    outside.child("pages/page.md").write_str("- a\n").unwrap();
+    let (code, _) = lsd(&["lint", "--changed", outside.path().to_str().unwrap()]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_fix_watch() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- Some  text\n").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lsd"))
+        .args(["fix", "--watch", temp.path().to_str().unwrap()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let wait_for = |page: &assert_fs::fixture::ChildPath, expected: &str| {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(20);
+        while fs::read_to_string(page.path()).unwrap() != expected {
+            assert!(std::time::Instant::now() < deadline, "not fixed in time");
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    };
+    wait_for(&page, "- Some text\n");
+    page.write_str("- Other  text\n").unwrap();
+    wait_for(&page, "- Other text\n");
+    let new = temp.child("pages/new.md");
+    new.write_str("- New  page\n").unwrap();
+    wait_for(&new, "- New page\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_cards_export() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Rust.md")
+        .write_str("- What is ownership? #card\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n\t- One owner per value\n\t\t- dropped with it\n- The {{cloze borrow checker}} checks references #[[card]]\n- Not a #cards block\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["cards", "export", root]);
+    assert_eq!(code, 0);
+    let cards: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(cards.as_array().unwrap().len(), 2);
+    assert_eq!(cards[0]["question"], "What is ownership?");
+    assert_eq!(
+        cards[0]["answer"],
+        "- One owner per value\n  - dropped with it"
+    );
+    assert_eq!(cards[0]["id"], "65a1b2c3-0000-4000-8000-000000000001");
+    assert_eq!(cards[1]["clozes"][0], "borrow checker");
+
+    let (code, stdout) = lsd(&["cards", "export", root, "--format", "anki-tsv"]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with("The {{c1::borrow checker}} checks references\t\tRust\n"));
+}
+
+#[test]
+fn test_tasks_report() {
+    let temp = TempDir::new().unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- TODO write report\n- DONE old thing\n")
+        .unwrap();
+    temp.child("pages/Work.md")
+        .write_str("- LATER [#b] pay invoice\n  DEADLINE: <2024-02-20 Tue>\n- NOW review\n  :LOGBOOK:\n  CLOCK: [2024-02-25 Sun 10:00:00]--[2024-02-25 Sun 11:00:00] =>  01:00:00\n  :END:\n- TODO plan\n  SCHEDULED: <2024-03-05 Tue>\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&[
+        "tasks",
+        "report",
+        root,
+        "--today",
+        "2024-03-01",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(code, 1);
+    let tasks: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(tasks.as_array().unwrap().len(), 2);
+    assert_eq!(tasks[0]["text"], "[#b] pay invoice");
+    assert_eq!(tasks[0]["priority"], "B");
+    assert_eq!(tasks[0]["deadline"], "2024-02-20");
+    assert_eq!(tasks[0]["overdue"], true);
+    assert_eq!(tasks[1]["page"], "Jan 2nd, 2024");
+    assert_eq!(tasks[1]["stale"], true);
+
+    let (code, stdout) = lsd(&["tasks", "report", root, "--today", "2024-03-01"]);
+    assert_eq!(code, 1);
+    assert!(stdout.starts_with("STATUS   DUE         PRIORITY  TASK"));
+    assert!(stdout.contains("overdue  2024-02-20  B         LATER [#b] pay invoice"));
+
+    let (code, _) = lsd(&["tasks", "report", root, "--today", "2024-01-03"]);
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_tasks_report_date_links() {
+    let temp = TempDir::new().unwrap();
+    temp.child("logseq/config.edn")
+        .write_str("{:journal/page-title-format \"dd.MM.yyyy\"}")
+        .unwrap();
+    temp.child("pages/Work.md")
+        .write_str("- TODO call Bob [[20.02.2024]]\n- TODO renew [[Notes]] on [[2024-03-05]]\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&[
+        "tasks",
+        "report",
+        root,
+        "--today",
+        "2024-03-01",
+        "--stale-days",
+        "100000",
+        "--format",
+        "json",
+    ]);
+    assert_eq!(code, 1);
+    let tasks: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(tasks.as_array().unwrap().len(), 1);
+    assert_eq!(tasks[0]["scheduled"], "2024-02-20");
+    assert_eq!(tasks[0]["overdue"], true);
+
+    let (code, _) = lsd(&["tasks", "report", root, "--utc-offset", "+25:00"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_rename_property() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/Book.md");
+    page.write_str("- Notes\n  status:: todo\n  state:: done\n- Other\n  status:: todo\n")
+        .unwrap();
+    let graph = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["rename-property", graph, "status", "state", "--dry-run"]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("Book.md:5:\n-  status:: todo\n+  state:: todo\n"));
+    assert!(stdout.contains("Book.md:2: already has a state:: property"));
+    assert!(stdout.ends_with("would change 1 lines in 1 files\n"));
+
+    let (code, stdout) = lsd(&["rename-property-value", graph, "status", "todo", "doing"]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with("changed 2 lines in 1 files\n"));
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- Notes\n  status:: doing\n  state:: done\n- Other\n  status:: doing\n"
+    );
+}
+
+#[test]
+fn test_tasks_backfill_done() {
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(["-c", "user.name=lsd", "-c", "user.email=lsd@example.com"])
+            .args(args)
+            .env("GIT_AUTHOR_DATE", "2024-01-02T10:00:00Z")
+            .env("GIT_COMMITTER_DATE", "2024-01-02T10:00:00Z")
+            .current_dir(temp.path())
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    };
+    temp.child("logseq/config.edn")
+        .write_str("{:feature/enable-timetracking? false}")
+        .unwrap();
+    temp.child("pages/Work.md")
+        .write_str("- DONE write report\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n- DONE recorded\n  done:: 1000\n- TODO later\n")
+        .unwrap();
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "pages"]);
+    temp.child("pages/Work.md")
+        .write_str("- DONE write report\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n- DONE recorded\n  done:: 1000\n- TODO later\n- DONE new\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["tasks", "backfill-done", root, "--git", "--dry-run"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+  done:: 1704189600000\n"));
+    let page = fs::read_to_string(temp.path().join("pages/Work.md")).unwrap();
+    assert!(!page.contains("done:: 17"));
+
+    let (code, _) = lsd(&["tasks", "backfill-done", root, "--git"]);
+    assert_eq!(code, 0);
+    let page = fs::read_to_string(temp.path().join("pages/Work.md")).unwrap();
+    assert!(page.starts_with("- DONE write report\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n  done:: 1704189600000\n- DONE recorded\n  done:: 1000\n"));
+    assert!(page.contains("- DONE new\n  done:: "));
+    let (code, stdout) = lsd(&["lint", "--no-config", "--enable", "done-metadata", root]);
+    assert_eq!((code, stdout.as_str()), (0, ""));
+}
+
+#[test]
+fn test_check_assets() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/page.md")
+        .write_str("- ![cat](../assets/cat.png)\n- {{pdf ../assets/docs/My%20File.pdf}}\n- [gone](../assets/gone.png)\n")
+        .unwrap();
+    temp.child("assets/cat.png").write_str("").unwrap();
+    temp.child("assets/docs/My File.pdf").write_str("").unwrap();
+    let unused = temp.child("assets/unused.png");
+    unused.write_str("").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["check-assets", root]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.contains("page.md:3:10: missing asset ../assets/gone.png"));
+    assert!(stdout.contains("unused.png: unused asset"));
+
+    let (_, stdout) = lsd(&["check-assets", "--delete-orphans", root]);
+    assert!(stdout.contains("unused.png: deleted"));
+    assert!(!unused.path().exists());
+    assert!(temp.child("assets/cat.png").path().exists());
+}
+
+#[test]
+fn test_stubs() {
+    let temp = TempDir::new().unwrap();
+    let stub = temp.child("pages/Stub.md");
+    stub.write_str("-\n").unwrap();
+    temp.child("pages/Page.md")
+        .write_str("- Some notes\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["stubs", root]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.contains("Stub.md: Stub (only empty bullets)"));
+
+    let (code, stdout) = lsd(&["stubs", "--delete", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Stub.md: Stub (deleted)"));
+    assert!(!stub.path().exists());
+    assert_eq!(lsd(&["stubs", root]), (0, String::new()));
+}
+
+#[test]
+fn test_stats() {
+    let temp = TempDir::new().unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- TODO read [[Book]]\n- DONE write about #book\n")
+        .unwrap();
+    temp.child("pages/Book.md")
+        .write_str("alias:: novel\n\n- A good one, see [[Book]]\n  rating:: 5\n")
+        .unwrap();
+    temp.child("pages/Shelf.md")
+        .write_str("- [[novel]] and [[Author]]\n  - ![cover](../assets/cover.png)\n")
+        .unwrap();
+    temp.child("assets/cover.png")
+        .write_binary(&[0; 100])
+        .unwrap();
+    temp.child("assets/unused.png")
+        .write_binary(&[0; 50])
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["stats", root, "--format", "json"]);
+    assert_eq!(code, 0);
+    let stats: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(stats["pages"], 2);
+    assert_eq!(stats["journals"], 1);
+    assert_eq!(stats["blocks"], 5);
+    assert_eq!(stats["words"], 16);
+    assert_eq!(stats["tasks"], serde_json::json!({"DONE": 1, "TODO": 1}));
+    assert_eq!(stats["orphans"], 1);
+    assert_eq!(stats["assets"], 2);
+    assert_eq!(stats["asset_bytes"], 150);
+    assert_eq!(stats["unused_assets"], 1);
+    // The page's own link doesn't count, its alias does
+    assert_eq!(
+        stats["top_pages"],
+        serde_json::json!([
+            {"name": "Book", "references": 3},
+            {"name": "Author", "references": 1},
+        ])
+    );
+
+    let (_, stdout) = lsd(&["stats", root, "--top", "1"]);
+    assert!(stdout.contains("tasks     2 (TODO 1, DONE 1)\n"));
+    assert!(stdout.contains("assets    2 (150 B), 1 unused\n"));
+    assert!(stdout.ends_with("PAGE  REFERENCES\nBook  3\n"));
+}
+
+#[test]
+fn test_doctor() {
+    let temp = TempDir::new().unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- TODO read [[Book]]\n  DEADLINE: <2024-01-05 Fri>\n- DONE write about #book\n")
+        .unwrap();
+    temp.child("pages/Book.md")
+        .write_str("- A good one, see [[Missing]]\n- two  spaces\n")
+        .unwrap();
+    temp.child("pages/Shelf.md")
+        .write_str("- [[Book]] ![cover](../assets/cover.png)\n")
+        .unwrap();
+    temp.child("assets/unused.png").write_str("x").unwrap();
+    let root = temp.path().to_str().unwrap();
+    let args = ["doctor", root, "--no-config", "--today", "2024-01-10"];
+
+    let (code, stdout) = lsd(&[&args[..], &["--format", "json"]].concat());
+    assert_eq!(code, 1);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["score"], 63);
+    assert_eq!(report["pages"], 3);
+    let categories = report["categories"].as_array().unwrap();
+    let names: Vec<&str> = categories
+        .iter()
+        .map(|category| category["category"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["links", "tasks", "properties", "assets", "hygiene"]);
+    assert_eq!(categories[1]["score"], 66);
+    assert_eq!(
+        categories[1]["checks"],
+        serde_json::json!({"done-metadata": 1, "overdue-task": 1})
+    );
+    assert_eq!(
+        categories[1]["top_offenders"],
+        serde_json::json!([{"path": "journals/2024_01_02.md", "findings": 2}])
+    );
+    assert_eq!(categories[2]["score"], 100);
+    assert_eq!(
+        categories[3]["checks"],
+        serde_json::json!({"missing-asset": 1, "unused-asset": 1})
+    );
+
+    let (code, stdout) = lsd(&[&args[..], &["--top", "1", "--min-score", "60"]].concat());
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("Health 63/100 (3 pages, 1 assets)\n\n"));
+    assert!(
+        stdout.contains("links       66/100  1 finding: missing-page 1\n  pages/Book.md  1\ntasks")
+    );
+    assert!(stdout.contains("properties  100/100  no findings\n"));
+    assert_eq!(lsd(&[&args[..], &["--min-score", "70"]].concat()).0, 1);
+}
+
+#[test]
+fn test_remove_spaces() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("-  a  b\r\n  ```\n  -  code  \n  ```\n")
+        .unwrap();
+    let fine = temp.child("pages/fine.md");
+    fine.write_str("- a b\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["remove-spaces", root]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.ends_with("page.md\n"));
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- a b\r\n  ```\n  -  code  \n  ```\n"
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lsd"))
+        .args(["remove-spaces", "-", "--in-code-blocks"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"```\n-  x  y\n```")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "```\n- x y\n```");
+}
+
+#[test]
+fn test_fix_interactive() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp = TempDir::new().unwrap();
+    let first = temp.child("pages/a.md");
+    first.write_str("- a  b\n- ok\n- c  d #[[tag]]\n").unwrap();
+    let second = temp.child("pages/b.md");
+    second.write_str("- e #[[f]]\n- g  h\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lsd"))
+        .args(["fix", "--interactive", temp.path().to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Make the first change, skip the second, ask for help, then make all the tag changes;
+    // the input ends at the first change of the second file
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"y\nn\n?\na\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.contains("@@ -1,2 +1,2 @@\n-- a  b\n+- a b\n - ok\n"));
+    assert!(stdout.contains("Make this consecutive-spaces change [y,n,a,d,q,?]? "));
+    assert!(stdout.contains("a - make this change and all later changes of the rule"));
+    assert!(stdout.contains("a.md: fixed"));
+    assert!(!stdout.contains("b.md: fixed"));
+    first.assert("- a b\n- ok\n- c  d #tag\n");
+    second.assert("- e #[[f]]\n- g  h\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_fix_through_symlink() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("p.md");
+    page.write_str("- a  b\n").unwrap();
+    let link = temp.child("link.md");
+    std::os::unix::fs::symlink(page.path(), link.path()).unwrap();
+
+    let (code, _) = lsd(&["fix", link.path().to_str().unwrap()]);
+    assert_eq!(code, 3);
+    page.assert("- a b\n");
+    assert!(fs::symlink_metadata(link.path())
+        .unwrap()
+        .file_type()
+        .is_symlink());
+}
+
+#[test]
+fn test_graph_with_a_latin1_page() {
+    let temp = TempDir::new().unwrap();
+    temp.child("logseq/config.edn").write_str("{}").unwrap();
+    let latin = temp.child("pages/latin.md");
+    latin.write_binary(b"- caf\xe9 [[Missing]]\n").unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- a  b\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["fix", root]);
+    assert_eq!(code, 2);
+    assert!(stdout.ends_with("page.md: fixed\n"));
+    page.assert("- a b\n");
+
+    let (code, stdout) = lsd(&["lint", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("latin.md:1:1: warning encoding"));
+
+    let (code, stdout) = lsd(&["check-links", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("latin.md: can't be read, not checked: file is not valid UTF-8"));
+    let (code, stdout) = lsd(&["check-links", "--format", "json", root]);
+    assert_eq!(code, 1);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["findings"][0]["rule"], "unreadable-file");
+    assert_eq!(report["findings"][0]["severity"], "error");
+}
+
+#[test]
+fn test_undo() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- a  b\n- ok\n").unwrap();
+    let other = temp.child("pages/other.md");
+    other.write_str("- #[[tag]]\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, _) = lsd(&["fix", root]);
+    assert_eq!(code, 3);
+    page.assert("- a b\n- ok\n");
+    other.write_str("- #tag, edited since\n").unwrap();
+    let later = temp.child("pages/later.md");
+    later.write_str("- x  y\n").unwrap();
+    let (code, _) = lsd(&["fix", later.path().to_str().unwrap()]);
+    assert_eq!(code, 3);
+    let log = fs::read_to_string(temp.child(".lsd/undo").path()).unwrap();
+    assert_eq!(log.lines().count(), 3);
+
+    let (code, stdout) = lsd(&["undo", "--dry-run", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with("@@ -1 +1 @@\n-- x y\n+- x  y\n"));
+    later.assert("- x y\n");
+
+    let (code, stdout) = lsd(&["undo", "--last", "5", root]);
+    assert_eq!(code, 1);
+    assert!(stdout.contains("later.md: restored\n"));
+    assert!(stdout.contains("page.md: restored\n"));
+    assert!(stdout.ends_with("other.md: changed since, not restored\n"));
+    page.assert("- a  b\n- ok\n");
+    later.assert("- x  y\n");
+    other.assert("- #tag, edited since\n");
+    assert_eq!(
+        fs::read_to_string(temp.child(".lsd/undo").path()).unwrap(),
+        ""
+    );
+
+    let (code, stdout) = lsd(&["undo", root]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "");
+}
+
+#[test]
+fn test_undo_changes_next_to_removed_lines() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- 1. a\n- k\n- x  y\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, _) = lsd(&["fix", root]);
+    assert_eq!(code, 3);
+    page.assert("- a\n  logseq.order-list-type:: number\n- k\n- x y\n");
+    let (code, stdout) = lsd(&["undo", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("page.md: restored\n"));
+    page.assert("- 1. a\n- k\n- x  y\n");
+}
+
+#[test]
+fn test_undo_moves_and_deletions() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.child("pages/Src.md");
+    source.write_str("- from the source\n").unwrap();
+    let target = temp.child("pages/Tgt.md");
+    target.write_str("- in the target\n").unwrap();
+    let old = temp.child("pages/Old.md");
+    old.write_str("- old page\n").unwrap();
+    let linking = temp.child("pages/linking.md");
+    linking.write_str("- see [[Old]]\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, _) = lsd(&["merge", root, "Src", "Tgt"]);
+    assert_eq!(code, 0);
+    assert!(!source.path().exists());
+    let (code, _) = lsd(&["rename-page", root, "Old", "New"]);
+    assert_eq!(code, 0);
+    assert!(!old.path().exists());
+    linking.assert("- see [[New]]\n");
+
+    let (code, stdout) = lsd(&["undo", "--dry-run", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+- old page\n"));
+    assert!(stdout.contains("New.md: would delete, moved back\n"));
+    temp.child("pages/New.md").assert("- old page\n");
+
+    let (code, stdout) = lsd(&["undo", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("Old.md: restored\n"));
+    assert!(stdout.contains("New.md: deleted, moved back\n"));
+    old.assert("- old page\n");
+    linking.assert("- see [[Old]]\n");
+    assert!(!temp.child("pages/New.md").path().exists());
+
+    let (code, _) = lsd(&["undo", root]);
+    assert_eq!(code, 0);
+    source.assert("- from the source\n");
+    target.assert("- in the target\n");
+}
+
+#[test]
+fn test_split_blocks() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    let text = "- # A heading. With sentences. And more.\n- First one. Second one, see `a. B`. Third one. [[Page]]\n  collapsed:: true\n";
+    page.write_str(text).unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["split-blocks", root, "--max-sentences", "1", "--dry-run"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+- First one. [[Page]]\n"));
+    assert_eq!(fs::read_to_string(page.path()).unwrap(), text);
+
+    lsd(&[
+        "split-blocks",
+        root,
+        "--max-sentences",
+        "5",
+        "--max-chars",
+        "30",
+    ]);
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- # A heading. With sentences. And more.\n- First one. [[Page]]\n  collapsed:: true\n\t- Second one, see `a. B`.\n\t- Third one.\n"
+    );
+}
+
+#[test]
+fn test_explode_blocks() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    let text = "- # Pasted\n  First paragraph.\n  \n  Second paragraph.\n";
+    page.write_str(text).unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["explode-blocks", root, "--dry-run"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+\t- Second paragraph.\n"));
+    assert_eq!(fs::read_to_string(page.path()).unwrap(), text);
+
+    lsd(&["explode-blocks", root]);
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- # Pasted\n\t- First paragraph.\n\t- Second paragraph.\n"
+    );
+}
+
+#[test]
+fn test_clean_clippings() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/clipped.md");
+    let text = "<h3>Clipped</h3>\n<p>Some&nbsp;text</p>\n";
+    page.write_str(text).unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["clean-clippings", root, "--dry-run"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+  - Some text\n"));
+    assert_eq!(fs::read_to_string(page.path()).unwrap(), text);
+
+    lsd(&["clean-clippings", root]);
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- # Clipped\n  - Some text\n"
+    );
+}
+
+#[test]
+fn test_link_dates() {
+    let temp = TempDir::new().unwrap();
+    let journal = temp.child("journals/2024_03_06.md");
+    journal
+        .write_str("- Meet next Friday, not `2024/03/05`\n- Rappel vendredi prochain\n")
+        .unwrap();
+    let page = temp.child("pages/Plans.md");
+    page.write_str("- Ship it tomorrow\n").unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["link-dates", root, "--dry-run"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("+- Meet [[Mar 8th, 2024]], not `2024/03/05`\n"));
+    assert!(!stdout.contains("Plans.md"));
+
+    lsd(&["link-dates", root, "--language", "fr"]);
+    assert_eq!(
+        fs::read_to_string(journal.path()).unwrap(),
+        "- Meet [[Mar 8th, 2024]], not `2024/03/05`\n- Rappel [[Mar 8th, 2024]]\n"
+    );
+    lsd(&["link-dates", root, "--all-pages", "--today", "2024-03-06"]);
+    assert_eq!(
+        fs::read_to_string(page.path()).unwrap(),
+        "- Ship it [[Mar 7th, 2024]]\n"
+    );
+    assert_eq!(lsd(&["link-dates", root, "--language", "xx"]).0, 2);
+}
+
+#[test]
+fn test_rules_and_explain() {
+    let (code, stdout) = lsd(&["rules"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("\nconsecutive-spaces    Consecutive spaces"));
+
+    let (code, stdout) = lsd(&["rules", "--format", "json"]);
+    assert_eq!(code, 0);
+    let rules: Vec<serde_json::Value> = serde_json::from_str(&stdout).unwrap();
+    let spaces = rules
+        .iter()
+        .find(|rule| rule["id"] == "consecutive-spaces")
+        .unwrap();
+    assert_eq!(spaces["fixable"], true);
+    assert_eq!(spaces["severity"], "warning");
+    assert_eq!(spaces["examples"][0]["fixed"], "- Some text\n");
+
+    let (code, stdout) = lsd(&["explain", "consecutive-spaces"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("consecutive-spaces (warning, fixable)\n"));
+    assert!(stdout.contains("  1:7: Consecutive spaces\n"));
+
+    let (code, _) = lsd(&["explain", "no-such-rule"]);
+    assert_eq!(code, 2);
+}
+
+#[test]
+fn test_grep() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Books.md")
+        .write_str("- Reading\n\t- Dune\n\t  by Frank Herbert\n\t\t- Review\n")
+        .unwrap();
+    let root = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["grep", "Frank", root]);
+    assert_eq!(code, 0);
+    assert!(stdout.ends_with(
+        "Books.md:3:7: Books\n- Reading\n\t- Dune\n\t  by Frank Herbert\n\t\t- Review\n"
+    ));
+
+    let (code, stdout) = lsd(&["grep", "frank", root, "--format", "json"]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout, "[]\n");
+}
+
+#[test]
+fn test_normalize_property_keys() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/Book.md");
+    page.write_str("- Dune\n  Book_Author:: Herbert\n- Other\n  book-author:: Le Guin\n")
+        .unwrap();
+    let graph = temp.path().to_str().unwrap();
+
+    let (code, stdout) = lsd(&["normalize-property-keys", graph]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "Book_Author:: -> book-author:: (1 lines)\nchanged 1 lines in 1 files\n"
+    );
+    page.assert("- Dune\n  book-author:: Herbert\n- Other\n  book-author:: Le Guin\n");
+}
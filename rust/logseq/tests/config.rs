@@ -0,0 +1,261 @@
+//! Integration tests for reading config.edn and .lsd.toml
+use logseq::config::{GraphConfig, PageFormat};
+use logseq::edn::Edn;
+use logseq::lint::LintConfig;
+use logseq::tasks::Workflow;
+use logseq::toml::Toml;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_parse_config() -> anyhow::Result<()> {
+    let contents = fs::read_to_string("tests/fixtures/config/config.edn")?;
+    let config = GraphConfig::parse(&contents)?;
+    assert_eq!(
+        config,
+        GraphConfig {
+            preferred_format: PageFormat::Markdown,
+            preferred_workflow: Workflow::TodoDoing,
+            journal_page_title_format: "EEE, dd.MM.yyyy".to_string(),
+            journal_file_name_format: "yyyy-MM-dd".to_string(),
+            hidden: vec!["/archive".to_string(), "/templates/old".to_string()],
+            default_journal_template: Some("Daily".to_string()),
+            property_pages_enabled: false,
+            property_pages_excludelist: vec!["created-at".to_string(), "updated-at".to_string()],
+            enable_timetracking: false,
+            ..GraphConfig::default()
+        }
+    );
+
+    let edn = Edn::parse(&contents)?;
+    assert_eq!(edn.get("commented-out"), None);
+    assert_eq!(edn.get("char"), Some(&Edn::Char('a')));
+    assert_eq!(edn.get("ratio"), Some(&Edn::Float(1.5)));
+    assert_eq!(edn.get("big"), Some(&Edn::Integer(42)));
+    assert_eq!(
+        edn.get("list"),
+        Some(&Edn::List(vec![
+            Edn::Integer(1),
+            Edn::Integer(-2),
+            Edn::Integer(3)
+        ]))
+    );
+    assert!(matches!(edn.get("when"), Some(Edn::Tagged(tag, _)) if tag == "inst"));
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = Edn::parse("{:a 1\n :b [1 2}").unwrap_err();
+    assert_eq!(error.to_string(), "EDN 2:9: expected ']'");
+    assert!(Edn::parse("{:a}").is_err());
+    assert!(Edn::parse("\"unterminated").is_err());
+    assert!(Edn::parse("1 2").is_err());
+    assert!(GraphConfig::parse("").is_err());
+}
+
+#[test]
+fn test_lint_config() -> anyhow::Result<()> {
+    let config = LintConfig::discover(Path::new("tests/fixtures/config/config.edn"))?.unwrap();
+    assert_eq!(config.disable, vec!["timestamps"]);
+    let rules = config.rule_set()?;
+    assert_eq!(
+        rules.ids(),
+        vec![
+            "front-matter",
+            "page-properties",
+            "consecutive-spaces",
+            "malformed-refs",
+            "tag-brackets",
+            "checkboxes",
+            "task-markers",
+            "priorities",
+            "repeaters",
+            "block-times",
+            "done-metadata",
+            "trailing-whitespace",
+            "punctuation",
+            "tables",
+            "empty-blocks",
+            "list-markers",
+            "indentation",
+            "continuation-indent",
+            "nesting-depth",
+            "headings",
+            "title-heading",
+            "editor-properties",
+            "logbook",
+            "properties-drawer",
+            "property-order",
+            "property-schema",
+            "timestamp-properties",
+            "page-property-values",
+            "link-style",
+            "link-normalization",
+            "image-props",
+            "queries",
+            "dynamic-variables",
+            "callouts",
+            "admonitions",
+            "footnotes",
+            "citations",
+            "spelling"
+        ]
+    );
+    let diagnostics = rules.check("- LATER task");
+    assert_eq!(diagnostics[0].message, "Use TODO instead of LATER");
+
+    let root = Path::new("tests/fixtures/config");
+    assert!(config.is_ignored(&root.join("pages/archive/2020.md")));
+    assert!(config.is_ignored(&root.join("draws/plan.excalidraw.md")));
+    assert!(!config.is_ignored(&root.join("pages/plan.md")));
+
+    let other = Toml::parse(&fs::read_to_string(root.join(".lsd.toml"))?)?;
+    let other = other.get("other").unwrap();
+    assert_eq!(
+        other.get("quoted key").and_then(Toml::as_str),
+        Some("literal \\string")
+    );
+    let numbers = other.get("numbers").unwrap();
+    assert_eq!(
+        numbers.get("integer").and_then(Toml::as_integer),
+        Some(1000)
+    );
+    assert_eq!(numbers.get("float"), Some(&Toml::Float(-1.5)));
+    assert_eq!(numbers.get("flag").and_then(Toml::as_bool), Some(true));
+    assert_eq!(
+        other.get("multiline").and_then(Toml::as_str),
+        Some("first\nsecond")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_invalid_lint_config() {
+    let error = |contents: &str| {
+        LintConfig::parse(contents, Path::new("."))
+            .and_then(|config| config.rule_set())
+            .err()
+            .map(|error| error.to_string())
+            .unwrap_or_default()
+    };
+    assert!(error("[lint]\ndisable = [\"no-such-rule\"]").contains("unknown rule no-such-rule"));
+    assert!(error("[rules.task-markers]\nflow = \"todo\"").contains("unknown option flow"));
+    assert!(error("[rules.timestamps]\nstrict = true").contains("has no options"));
+    assert!(error("[rules.indentation]\nunit = 0").contains("rules.indentation.unit must be"));
+    assert!(error("[rules.editor-properties]\nkeep = [1]")
+        .contains("rules.editor-properties.keep must be an array of strings"));
+    assert!(error("[lint]\nignore = \"pages\"").contains("must be an array of strings"));
+    assert!(error("[lint]\nignore = [\"[\"]").contains("invalid ignore glob"));
+    assert!(error("[lint]\ndisable = [\"a\"\n").contains("TOML 3:1: expected ',' or ']'"));
+    assert!(error("[schema]\nrating = \"count\"").contains("schema.rating: unknown type count"));
+    assert!(error("[schema]\nrating = 1").contains("schema.rating must be a string"));
+    assert!(error("[rules.timestamps]\nseverity = \"fatal\"").contains("unknown severity fatal"));
+    assert!(error("[rules.timestamps]\nseverity = 1")
+        .contains("rules.timestamps.severity must be a string"));
+    #[cfg(not(feature = "plugins"))]
+    assert!(error("[lint]\nplugins = [\"no-fixme.wasm\"]")
+        .contains("the plugin no-fixme.wasm needs lsd built with the plugins feature"));
+}
+
+#[test]
+fn test_rule_severity() -> anyhow::Result<()> {
+    use logseq::lint::Severity;
+
+    let config = LintConfig::parse(
+        "[rules.consecutive-spaces]\nseverity = \"error\"\n[rules.task-markers]\nworkflow = \"todo\"\nseverity = \"info\"\n",
+        Path::new("."),
+    )?;
+    let rules = config.rule_set()?;
+    assert_eq!(rules.severity("consecutive-spaces"), Severity::Error);
+    assert_eq!(rules.severity("task-markers"), Severity::Info);
+    assert_eq!(rules.severity("tag-brackets"), Severity::Warning);
+    let severities: Vec<(&str, Severity)> = rules
+        .check("- LATER a  b #[[tag]]")
+        .iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.severity))
+        .collect();
+    assert_eq!(
+        severities,
+        vec![
+            ("task-markers", Severity::Info),
+            ("consecutive-spaces", Severity::Error),
+            ("tag-brackets", Severity::Warning),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_property_schema() -> anyhow::Result<()> {
+    use logseq::lint::run_lints;
+
+    let config = LintConfig::parse(
+        "[schema]\nrating = \"integer\"\nstatus = \"enum[reading, done]\"\nstarted = \"date\"\nAuthor = \"page\"\n",
+        Path::new("."),
+    )?;
+    let rules = config.rule_set()?.select(&["property-schema"]);
+    let text = "rating:: five\nauthor:: [[Le Guin]]\n\n- A book\n  status:: Reading\n  started:: [[Jan 2nd, 2024]]\n\t- status:: abandoned\n\t  started:: someday\n\t  rating::\n\t  `rating:: code`\n";
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, 1, "rating:: five should be an integer".to_string()),
+            (
+                7,
+                4,
+                "status:: abandoned should be one of reading, done".to_string()
+            ),
+            (8, 4, "started:: someday should be a date".to_string()),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_config() -> anyhow::Result<()> {
+    use assert_fs::prelude::*;
+    use logseq::config::{check_config, ConfigProblem};
+    use logseq::graph::Graph;
+
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("pages/Inbox.md")
+        .write_str("status:: open\n\n- item\n")?;
+    temp.child("pages/Projects.md")
+        .write_str("alias:: work\n\n- item\n")?;
+    assert!(check_config(&Graph::scan(temp.path())?)?.is_empty());
+
+    temp.child("logseq/config.edn").write_str(
+        r#"{:favorites ["inbox" "Work"
+             "Old name"]
+ :default-queries
+ {:journals [{:title "Open" :query (page-property status open)}
+             {:title [:h3 "Stale"] :query [:find (pull ?b [*]) :where [?p :block/name "old name"]]}
+             {:title "Bad" :query "(and [[Inbox]]"}
+             {:title "Empty"}]}}"#,
+    )?;
+    let issues: Vec<(usize, ConfigProblem)> = check_config(&Graph::scan(temp.path())?)?
+        .into_iter()
+        .map(|issue| (issue.line, issue.problem))
+        .collect();
+    let invalid = |title: &str, message: &str| ConfigProblem::InvalidDefaultQuery {
+        title: title.to_string(),
+        message: message.to_string(),
+    };
+    assert_eq!(
+        issues,
+        vec![
+            (2, ConfigProblem::MissingFavorite("Old name".to_string())),
+            (5, invalid("Stale", "Query refers to missing page old name")),
+            (
+                6,
+                invalid("Bad", "Unbalanced parentheses: ( is never closed")
+            ),
+            (7, invalid("Empty", "Advanced query without :query")),
+        ]
+    );
+    Ok(())
+}
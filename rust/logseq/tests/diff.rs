@@ -0,0 +1,43 @@
+//! Integration tests for unified diffs
+use logseq::diff::{apply_hunks, hunks, unified_diff};
+
+#[test]
+fn test_hunks_and_context() {
+    let original: String = (1..=20).map(|i| format!("- line {}\n", i)).collect();
+    let fixed = original
+        .replace("- line 2\n", "- line two\n")
+        .replace("- line 15\n", "")
+        .replace("- line 20\n", "- line 20\n- line 21\n");
+    assert_eq!(
+        unified_diff("p.md", &original, &fixed),
+        "--- a/p.md\n+++ b/p.md\n\
+         @@ -1,5 +1,5 @@\n - line 1\n-- line 2\n+- line two\n - line 3\n - line 4\n - line 5\n\
+         @@ -12,9 +12,9 @@\n - line 12\n - line 13\n - line 14\n-- line 15\n - line 16\n - line 17\n - line 18\n - line 19\n - line 20\n+- line 21\n"
+    );
+}
+
+#[test]
+fn test_insertions_and_deletions_only() {
+    assert_eq!(
+        unified_diff("p.md", "", "- new\n"),
+        "--- a/p.md\n+++ b/p.md\n@@ -0,0 +1 @@\n+- new\n"
+    );
+    assert_eq!(
+        unified_diff("p.md", "- a\n- b\n", "- a\n"),
+        "--- a/p.md\n+++ b/p.md\n@@ -1,2 +1 @@\n - a\n-- b\n"
+    );
+}
+
+#[test]
+fn test_hunks_next_to_removed_lines() {
+    let original = "alias:: Src\n\n- t\n- x\n";
+    let fixed = "- t\n";
+    let removed = hunks(original, fixed);
+    assert_eq!(removed.len(), 2);
+    assert_eq!(removed[1].diff, "@@ -3,2 +1 @@\n - t\n-- x\n");
+    assert_eq!(apply_hunks(original, &removed), fixed);
+    let added = hunks(fixed, original);
+    assert_eq!(added.len(), 2);
+    assert_eq!(added[1].diff, "@@ -1 +3,2 @@\n - t\n+- x\n");
+    assert_eq!(apply_hunks(fixed, &added), original);
+}
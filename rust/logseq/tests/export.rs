@@ -0,0 +1,162 @@
+//! Integration tests for the Markdown exporter, the graph dump and the link graph
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::export::database::{dump_graph, DumpFormat};
+use logseq::export::links::{link_graph, LinkFilter, LinkFormat};
+use logseq::export::{export_graph, MarkdownExport};
+use logseq::graph::Graph;
+use std::fs;
+
+const UUID: &str = "65a1b2c3-0000-4000-8000-00000000000a";
+
+fn graph() -> TempDir {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Dune.md")
+        .write_str(&format!(
+            "alias:: Arrakis\n\n- The spice\n  id:: {}\n  :LOGBOOK:\n  CLOCK: [2024-01-02 Tue 10:00]\n  :END:\n\t- must flow\n",
+            UUID
+        ))
+        .unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str(&format!(
+            "- Quote: (({}))\n- {{{{embed [[Arrakis]]}}}}\n- Read [the book]([[Dune]]) on [[Jan 2nd, 2024]]\n",
+            UUID
+        ))
+        .unwrap();
+    temp
+}
+
+#[test]
+fn test_export_links_and_references() {
+    let temp = graph();
+    let graph = Graph::scan(temp.path()).unwrap();
+    let journal = graph
+        .pages
+        .iter()
+        .find(|page| page.journal_date.is_some())
+        .unwrap();
+
+    let export = MarkdownExport::new(&graph);
+    assert_eq!(
+        export.page(journal),
+        "Quote: The spice\n\n[Arrakis](Dune.md)\n\nRead [the book](Dune.md) on [Jan 2nd, 2024](2024_01_02.md)\n"
+    );
+
+    let mut inlined = MarkdownExport::new(&graph);
+    inlined.inline_embeds = true;
+    assert!(inlined
+        .page(journal)
+        .contains("\n\nThe spice\n\n- must flow\n\n"));
+}
+
+#[test]
+fn test_export_graph() {
+    let temp = graph();
+    let output = temp.child("site");
+    let graph = Graph::scan(temp.path()).unwrap();
+
+    let exported = export_graph(&graph, output.path(), false).unwrap();
+    assert_eq!(exported.len(), 2);
+    assert_eq!(
+        fs::read_to_string(output.child("Dune.md").path()).unwrap(),
+        "---\nalias: \"Arrakis\"\n---\n\nThe spice\n\n- must flow\n"
+    );
+}
+
+#[test]
+fn test_dump_graph() {
+    let temp = graph();
+    let dump = dump_graph(&Graph::scan(temp.path()).unwrap());
+
+    let pages: Vec<(usize, &str, Option<u32>)> = dump
+        .pages
+        .iter()
+        .map(|page| (page.id, page.original_name.as_str(), page.journal_day))
+        .collect();
+    assert_eq!(
+        pages,
+        vec![
+            (1, "Jan 2nd, 2024", Some(20240102)),
+            (2, "Dune", None),
+            (3, "Arrakis", None)
+        ]
+    );
+    assert_eq!(dump.pages[1].file, Some("pages/Dune.md".into()));
+    assert_eq!(dump.pages[1].aliases, vec![3]);
+    assert_eq!(dump.pages[2].file, None);
+
+    let blocks: Vec<(usize, usize, usize, Vec<usize>)> = dump
+        .blocks
+        .iter()
+        .map(|block| (block.id, block.page, block.parent, block.refs.clone()))
+        .collect();
+    assert_eq!(
+        blocks,
+        vec![
+            (4, 1, 1, vec![7]),
+            (5, 1, 1, vec![3]),
+            (6, 1, 1, vec![2, 1]),
+            (7, 2, 2, vec![]),
+            (8, 2, 7, vec![]),
+        ]
+    );
+    assert_eq!(dump.blocks[3].uuid.as_deref(), Some(UUID));
+
+    let edn = DumpFormat::Edn.render(&dump);
+    assert!(edn.contains(&format!(
+        "{{:db/id 7, :block/uuid #uuid \"{}\", :block/page 2, :block/parent 2, :block/order 0",
+        UUID
+    )));
+    let json: serde_json::Value = serde_json::from_str(&DumpFormat::Json.render(&dump)).unwrap();
+    assert_eq!(json["blocks"][4]["content"], "must flow");
+}
+
+#[test]
+fn test_link_graph() {
+    let temp = graph();
+    temp.child("pages/Books.md")
+        .write_str("- [[Dune]] and [[Missing]], see [[Books]]\n")
+        .unwrap();
+    temp.child("pages/books___Dune Messiah.md")
+        .write_str("tags:: scifi\n\n- After [[Arrakis]], #Dune\n")
+        .unwrap();
+    let graph = Graph::scan(temp.path()).unwrap();
+    let links = link_graph(&graph, &LinkFilter::default());
+    let nodes: Vec<(&str, bool)> = links
+        .nodes
+        .iter()
+        .map(|node| (node.name.as_str(), node.journal))
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![
+            ("Jan 2nd, 2024", true),
+            ("Books", false),
+            ("Dune", false),
+            ("books/Dune Messiah", false)
+        ]
+    );
+    let edges: Vec<(usize, usize, usize)> = links
+        .edges
+        .iter()
+        .map(|edge| (edge.source, edge.target, edge.count))
+        .collect();
+    assert_eq!(edges, vec![(0, 2, 2), (1, 2, 1), (3, 2, 2)]);
+
+    let filter = LinkFilter {
+        namespace: Some("Books".to_string()),
+        tag: None,
+    };
+    let links = link_graph(&graph, &filter);
+    assert_eq!(links.nodes.len(), 2);
+    assert!(links.edges.is_empty());
+    let filter = LinkFilter {
+        namespace: None,
+        tag: Some("#SciFi".to_string()),
+    };
+    let dot = LinkFormat::Dot.render(&link_graph(&graph, &filter));
+    assert_eq!(
+        dot,
+        "digraph logseq {\n  0 [label=\"books/Dune Messiah\"];\n}\n"
+    );
+}
@@ -0,0 +1,96 @@
+;; A plugin rule for the tests: finds FIXME in blocks, and fixes it into TODO.
+;; The content of a block goes at 1024, the strings it returns at 32768.
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "no-fixme")
+  (data (i32.const 16) "Blocks should not contain FIXME")
+  (data (i32.const 64) "FIXME left in the block")
+  (data (i32.const 96) "TODO")
+  (global $out (mut i32) (i32.const 32768))
+
+  (func (export "alloc") (param $len i32) (result i32)
+    (i32.const 1024))
+
+  (func (export "id") (result i64)
+    (i64.const 8))
+
+  (func (export "description") (result i64)
+    (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 31)))
+
+  ;; 1 if FIXME starts at $at, before $end
+  (func $is_fixme (param $at i32) (param $end i32) (result i32)
+    (if (i32.gt_u (i32.add (local.get $at) (i32.const 5)) (local.get $end))
+      (then (return (i32.const 0))))
+    (i32.and
+      (i32.and
+        (i32.eq (i32.load8_u (local.get $at)) (i32.const 70))
+        (i32.eq (i32.load8_u offset=1 (local.get $at)) (i32.const 73)))
+      (i32.and
+        (i32.and
+          (i32.eq (i32.load8_u offset=2 (local.get $at)) (i32.const 88))
+          (i32.eq (i32.load8_u offset=3 (local.get $at)) (i32.const 77)))
+        (i32.eq (i32.load8_u offset=4 (local.get $at)) (i32.const 69)))))
+
+  (func $push (param $byte i32)
+    (i32.store8 (global.get $out) (local.get $byte))
+    (global.set $out (i32.add (global.get $out) (i32.const 1))))
+
+  (func $push_bytes (param $ptr i32) (param $len i32)
+    (memory.copy (global.get $out) (local.get $ptr) (local.get $len))
+    (global.set $out (i32.add (global.get $out) (local.get $len))))
+
+  (func $push_number (param $number i32)
+    (if (i32.ge_u (local.get $number) (i32.const 10))
+      (then (call $push_number (i32.div_u (local.get $number) (i32.const 10)))))
+    (call $push (i32.add (i32.const 48) (i32.rem_u (local.get $number) (i32.const 10)))))
+
+  ;; The string pushed since $out was reset, packed as ptr << 32 | len
+  (func $pushed (result i64)
+    (i64.or
+      (i64.shl (i64.const 32768) (i64.const 32))
+      (i64.extend_i32_u (i32.sub (global.get $out) (i32.const 32768)))))
+
+  (func (export "check") (param $ptr i32) (param $len i32) (result i64)
+    (local $end i32)
+    (local $line i32)
+    (local $column i32)
+    (local.set $end (i32.add (local.get $ptr) (local.get $len)))
+    (local.set $line (i32.const 1))
+    (local.set $column (i32.const 1))
+    (global.set $out (i32.const 32768))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $ptr) (local.get $end)))
+        (if (call $is_fixme (local.get $ptr) (local.get $end))
+          (then
+            (call $push_number (local.get $line))
+            (call $push (i32.const 58))
+            (call $push_number (local.get $column))
+            (call $push (i32.const 58))
+            (call $push_bytes (i32.const 64) (i32.const 23))
+            (call $push (i32.const 10))))
+        (if (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 10))
+          (then
+            (local.set $line (i32.add (local.get $line) (i32.const 1)))
+            (local.set $column (i32.const 0))))
+        (local.set $column (i32.add (local.get $column) (i32.const 1)))
+        (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))
+        (br $next)))
+    (call $pushed))
+
+  (func (export "fix") (param $ptr i32) (param $len i32) (result i64)
+    (local $end i32)
+    (local.set $end (i32.add (local.get $ptr) (local.get $len)))
+    (global.set $out (i32.const 32768))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $ptr) (local.get $end)))
+        (if (call $is_fixme (local.get $ptr) (local.get $end))
+          (then
+            (call $push_bytes (i32.const 96) (i32.const 4))
+            (local.set $ptr (i32.add (local.get $ptr) (i32.const 5))))
+          (else
+            (call $push (i32.load8_u (local.get $ptr)))
+            (local.set $ptr (i32.add (local.get $ptr) (i32.const 1)))))
+        (br $next)))
+    (call $pushed)))
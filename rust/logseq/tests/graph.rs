@@ -0,0 +1,1369 @@
+//! Integration tests for graph-wide checks
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::graph::shared::SharedGraph;
+use logseq::graph::Graph;
+use logseq::lint::RuleSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixture_graph() -> Graph {
+    Graph::scan(Path::new("tests/fixtures/graph")).unwrap()
+}
+
+#[test]
+fn test_scan_names_and_aliases() {
+    let graph = fixture_graph();
+    let mut names: Vec<String> = graph.page_names().into_iter().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "blocks",
+            "index",
+            "jan 2nd, 2024",
+            "other name",
+            "project",
+            "project/sub"
+        ]
+    );
+    assert_eq!(graph.find_page("OTHER NAME").unwrap().name, "Project");
+}
+
+#[test]
+fn test_scan_skips_hidden_paths() -> anyhow::Result<()> {
+    use logseq::graph::markdown_files;
+
+    let temp = TempDir::new()?;
+    temp.child("logseq/config.edn")
+        .write_str("{:hidden [\"/pages/drafts\" \"journals/old/\"]}")?;
+    temp.child("pages/kept.md").write_str("- [[Draft]]\n")?;
+    temp.child("pages/drafts/Draft.md").write_str("- draft\n")?;
+    temp.child("journals/old/2020_01_01.md")
+        .write_str("- old\n")?;
+    temp.child("journals/2024_01_01.md").write_str("- new\n")?;
+    temp.child("logseq/bak/pages/kept/2024-01-01T00_00_00.000Z.Desktop.md")
+        .write_str("- backup\n")?;
+    let relative = |paths: Vec<PathBuf>| -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| {
+                let relative = path.strip_prefix(temp.path()).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    };
+
+    let graph = Graph::scan(temp.path())?;
+    let pages: Vec<PathBuf> = graph.pages.iter().map(|page| page.path.clone()).collect();
+    assert_eq!(
+        relative(pages),
+        vec!["journals/2024_01_01.md", "pages/kept.md"]
+    );
+    assert_eq!(graph.check_links().len(), 1);
+    assert_eq!(
+        relative(markdown_files(&temp.path().join("pages"))?),
+        vec!["pages/kept.md"]
+    );
+    assert!(markdown_files(&temp.path().join("logseq/bak"))?.is_empty());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_walk_ignores_and_symlinks() -> anyhow::Result<()> {
+    use logseq::assets::check_assets;
+    use logseq::graph::markdown_files;
+    use std::os::unix::fs::symlink;
+
+    let temp = TempDir::new()?;
+    let shared = TempDir::new()?;
+    shared.child("photo.png").write_str("png")?;
+    shared.child("unused.png").write_str("png")?;
+    symlink(shared.path(), temp.path().join("assets"))?;
+    temp.child("pages/kept.md")
+        .write_str("- ![photo](../assets/photo.png)\n")?;
+    temp.child("pages/.trash/old.md").write_str("- no\n")?;
+    temp.child("pages/scratch/notes.md").write_str("- no\n")?;
+    temp.child("pages/tmp.md").write_str("- no\n")?;
+    temp.child("pages/.lsdignore").write_str("scratch/\n")?;
+    // .gitignore files count in git repositories only
+    temp.child(".gitignore").write_str("tmp.md\n")?;
+    temp.child(".git").create_dir_all()?;
+    // A link to a directory it is in is left alone
+    symlink(temp.path().join("pages"), temp.path().join("pages/loop"))?;
+
+    let files = markdown_files(temp.path())?;
+    assert_eq!(files, vec![temp.path().join("pages/kept.md")]);
+    let report = check_assets(&Graph::scan(temp.path())?)?;
+    assert!(report.missing.is_empty());
+    assert_eq!(report.orphans, vec![temp.path().join("assets/unused.png")]);
+
+    fs::remove_dir(temp.path().join(".git"))?;
+    assert_eq!(markdown_files(temp.path())?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_check_links() {
+    let broken = fixture_graph().check_links();
+    let found: Vec<(String, usize, usize, &str)> = broken
+        .iter()
+        .map(|link| {
+            (
+                link.path.file_name().unwrap().to_string_lossy().to_string(),
+                link.line,
+                link.column,
+                link.target.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Index.md".to_string(), 1, 25, "Missing Page"),
+            ("project___sub.md".to_string(), 1, 19, "Ghost"),
+        ]
+    );
+}
+
+#[test]
+fn test_suggest_and_fix_links() -> anyhow::Result<()> {
+    use logseq::suggest::apply_suggestions;
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Kubernetes.md")
+        .write_str("- Clusters\n")?;
+    temp.child("pages/Rust.md")
+        .write_str("alias:: Rust language\n\n- Crates\n")?;
+    let notes = temp.child("pages/Notes.md");
+    notes.write_str(
+        "- [[Kubernets]] and #Kubernets in [[rust langauge]]\n- [[Python]] is missing\n",
+    )?;
+    let graph = Graph::scan(temp.path())?;
+    assert_eq!(
+        graph.suggest_page("kubernets").as_deref(),
+        Some("Kubernetes")
+    );
+    assert_eq!(graph.suggest_page("Python"), None);
+
+    let suggestions = graph.suggest_links();
+    let pages: Vec<(&str, &str)> = suggestions
+        .iter()
+        .map(|suggestion| (suggestion.link.target.as_str(), suggestion.page.as_str()))
+        .collect();
+    assert_eq!(
+        pages,
+        vec![
+            ("Kubernets", "Kubernetes"),
+            ("rust langauge", "Rust language")
+        ]
+    );
+
+    let edits = apply_suggestions(&graph, &suggestions, true)?;
+    assert_eq!(edits.len(), 1);
+    notes.assert("- [[Kubernets]] and #Kubernets in [[rust langauge]]\n- [[Python]] is missing\n");
+    apply_suggestions(&graph, &suggestions, false)?;
+    notes
+        .assert("- [[Kubernetes]] and #Kubernetes in [[Rust language]]\n- [[Python]] is missing\n");
+    Ok(())
+}
+
+#[test]
+fn test_check_block_refs() {
+    let broken = fixture_graph().check_block_refs();
+    let found: Vec<(usize, usize, &str)> = broken
+        .iter()
+        .map(|link| (link.line, link.column, link.target.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, 56, "65a1b2c3-0000-4000-8000-00000000dead"),
+            (4, 13, "65a1b2c3-0000-4000-8000-00000000beef"),
+        ]
+    );
+}
+
+#[test]
+fn test_find_orphans() -> anyhow::Result<()> {
+    let names: Vec<String> = fixture_graph()
+        .find_orphans()?
+        .into_iter()
+        .map(|orphan| orphan.name)
+        .collect();
+    assert_eq!(names, vec!["Blocks"]);
+
+    let temp = TempDir::new()?;
+    temp.child("logseq/config.edn")
+        .write_str("{:favorites [\"starred\"]}")?;
+    temp.child("pages/Starred.md")
+        .write_str("- In the favorites\n")?;
+    temp.child("pages/Tagged.md")
+        .write_str("- Links to itself [[Tagged]]\n")?;
+    temp.child("pages/Tagger.md")
+        .write_str("tags:: property-ref\n\n- #Tagged #[[Other page]]\n  - `#code-tag`\n")?;
+    temp.child("pages/property-ref.md")
+        .write_str("- Referenced by tags::\n")?;
+    temp.child("pages/Other page.md").write_str("- Linked\n")?;
+    temp.child("pages/code-tag.md")
+        .write_str("- Only in code\n")?;
+    temp.child("journals/2024_01_02.md")
+        .write_str("- Journals are never orphans\n")?;
+    let graph = Graph::scan(temp.path())?;
+    let names: Vec<String> = graph
+        .find_orphans()?
+        .into_iter()
+        .map(|orphan| orphan.name)
+        .collect();
+    assert_eq!(names, vec!["Tagger", "code-tag"]);
+    Ok(())
+}
+
+#[test]
+fn test_find_stub_pages() -> anyhow::Result<()> {
+    use logseq::stubs::{find_stub_pages, StubKind};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Empty.md").write_str("\n")?;
+    temp.child("pages/Dash.md").write_str("-\n")?;
+    temp.child("pages/Generated.md")
+        .write_str("title:: Generated\n\n-\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n")?;
+    temp.child("pages/Referred.md")
+        .write_str("-\n  id:: 65a1b2c3-0000-4000-8000-000000000002\n")?;
+    temp.child("pages/Linked.md").write_str("-\n")?;
+    temp.child("pages/Tagged.md").write_str("tags:: book\n")?;
+    temp.child("pages/Notes.md")
+        .write_str("- See [[Linked]] and ((65a1b2c3-0000-4000-8000-000000000002))\n")?;
+    temp.child("journals/2024_01_02.md").write_str("-\n")?;
+    let graph = Graph::scan(temp.path())?;
+    let stubs = find_stub_pages(&graph)?;
+    let found: Vec<(&str, StubKind)> = stubs
+        .iter()
+        .map(|stub| (stub.name.as_str(), stub.kind))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Jan 2nd, 2024", StubKind::EmptyBullets),
+            ("Dash", StubKind::EmptyBullets),
+            ("Empty", StubKind::Empty),
+            ("Generated", StubKind::GeneratedProperties),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_find_duplicates() -> anyhow::Result<()> {
+    assert!(fixture_graph().find_duplicates()?.is_empty());
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Foo.md")
+        .write_str("- Longest of the three\n")?;
+    temp.child("pages/foo.md").write_str("- Short\n")?;
+    temp.child("pages/F%6F%6F.md")
+        .write_str("- Encoded, even longer than the others\n")?;
+    temp.child("pages/Cafe\u{301}.md")
+        .write_str("- Decomposed\n")?;
+    temp.child("pages/caf\u{e9}.md").write_str("- Composed\n")?;
+    temp.child("pages/a___b.md").write_str("- Namespace\n")?;
+    temp.child("pages/A%2FB.md")
+        .write_str("- Encoded namespace\n")?;
+    temp.child("pages/Unique.md").write_str("- Alone\n")?;
+    let pages = temp.path().join("pages");
+
+    let found = Graph::scan(temp.path())?.find_duplicates()?;
+    let summary: Vec<(&str, PathBuf, Vec<PathBuf>)> = found
+        .iter()
+        .map(|group| {
+            (
+                group.key.as_str(),
+                group.canonical.strip_prefix(&pages).unwrap().to_path_buf(),
+                group
+                    .duplicates
+                    .iter()
+                    .map(|path| path.strip_prefix(&pages).unwrap().to_path_buf())
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("a/b", "a___b.md".into(), vec!["A%2FB.md".into()]),
+            (
+                "caf\u{e9}",
+                "caf\u{e9}.md".into(),
+                vec!["Cafe\u{301}.md".into()]
+            ),
+            (
+                "foo",
+                "Foo.md".into(),
+                vec!["F%6F%6F.md".into(), "foo.md".into()]
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_find_and_repair_duplicate_ids() -> anyhow::Result<()> {
+    use logseq::ids::repair_duplicate_ids;
+    use std::time::{Duration, SystemTime};
+
+    assert!(fixture_graph().find_duplicate_ids().is_empty());
+
+    let uuid = "65a1b2c3-0000-4000-8000-000000000001";
+    let temp = TempDir::new()?;
+    let original = temp.child("pages/Original.md");
+    original.write_str(&format!("- Quote\n  id:: {}\n", uuid))?;
+    let copy = temp.child("pages/Copy.md");
+    copy.write_str(&format!(
+        "- Intro\n- Quote\n  id:: {}\n- Same  spacing kept\n",
+        uuid.to_uppercase()
+    ))?;
+    temp.child("journals/2024_01_02.md")
+        .write_str(&format!("- See (({}))\n", uuid))?;
+    // The original is the oldest file, even though Copy.md comes first by path
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options()
+        .write(true)
+        .open(original.path())?
+        .set_modified(old)?;
+
+    let duplicates = Graph::scan(temp.path())?.find_duplicate_ids();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].uuid, uuid);
+    assert_eq!(duplicates[0].keep.path, original.path());
+    assert_eq!(duplicates[0].copies[0].path, copy.path());
+    assert_eq!(duplicates[0].copies[0].line, 3);
+    assert_eq!(duplicates[0].references, 1);
+
+    let repaired = repair_duplicate_ids(&duplicates)?;
+    assert_eq!(repaired.len(), 1);
+    let new_uuid = &repaired[0].1;
+    assert_ne!(new_uuid, uuid);
+    copy.assert(format!(
+        "- Intro\n- Quote\n  id:: {}\n- Same  spacing kept\n",
+        new_uuid
+    ));
+    original.assert(format!("- Quote\n  id:: {}\n", uuid));
+    assert!(Graph::scan(temp.path())?.find_duplicate_ids().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_find_duplicate_blocks() -> anyhow::Result<()> {
+    use logseq::dedupe::{find_duplicate_blocks, BlockLocation};
+
+    let temp = TempDir::new()?;
+    let long = "Notes from the quarterly budget review with the finance team about travel costs, \
+                hiring plans for the next year and the new office in Lisbon";
+    temp.child("pages/Budget.md").write_str(&format!(
+        "- {}\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n- Call the bank about the card\n",
+        long
+    ))?;
+    temp.child("pages/Budget (conflict).md")
+        .write_str(&format!(
+            "- {}\n- call  the BANK about the card!\n- Too short\n",
+            long.replace("Lisbon", "Porto")
+        ))?;
+    temp.child("journals/2024_01_02.md")
+        .write_str("- Call the bank about the card\n- Too short\n")?;
+
+    let groups = find_duplicate_blocks(&Graph::scan(temp.path())?, 5);
+    let location = |path: &str, line| BlockLocation {
+        path: temp.path().join(path),
+        line,
+    };
+    let summary: Vec<(Vec<BlockLocation>, bool)> = groups
+        .iter()
+        .map(|group| (group.blocks.clone(), group.identical))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                vec![
+                    location("journals/2024_01_02.md", 1),
+                    location("pages/Budget (conflict).md", 2),
+                    location("pages/Budget.md", 3),
+                ],
+                true
+            ),
+            (
+                vec![
+                    location("pages/Budget (conflict).md", 1),
+                    location("pages/Budget.md", 1),
+                ],
+                false
+            ),
+        ]
+    );
+    assert_eq!(groups[0].preview, "Call the bank about the card");
+    assert_eq!(
+        find_duplicate_blocks(&Graph::scan(temp.path())?, 10).len(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn test_audit_embeds() -> anyhow::Result<()> {
+    use logseq::embeds::{unembed_missing, EmbedLimits, EmbedProblem};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Small.md").write_str(
+        "- just a few words
+",
+    )?;
+    temp.child("pages/Big.md").write_str(
+        "- one two three four five six
+- seven eight nine ten eleven twelve
+",
+    )?;
+    temp.child("pages/Other.md").write_str(
+        "- {{embed [[Big]]}}
+- {{embed [[small]]}} and {{embed [[Gone]]}}
+- `{{embed [[Gone]]}}` stays
+",
+    )?;
+    temp.child("journals/2024_01_02.md").write_str(
+        "- {{embed [[Big]]}}
+",
+    )?;
+
+    let limits = EmbedLimits {
+        tiny_words: 5,
+        large_words: 12,
+        max_embeds: 2,
+    };
+    let graph = Graph::scan(temp.path())?;
+    let issues = graph.audit_embeds(&limits);
+    let found: Vec<(String, usize, usize, EmbedProblem)> = issues
+        .iter()
+        .map(|issue| {
+            let name = issue
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            (name, issue.line, issue.column, issue.problem)
+        })
+        .collect();
+    let large = EmbedProblem::Large {
+        words: 12,
+        embeds: 2,
+    };
+    assert_eq!(
+        found,
+        vec![
+            ("2024_01_02.md".to_string(), 1, 11, large),
+            ("Other.md".to_string(), 1, 11, large),
+            (
+                "Other.md".to_string(),
+                2,
+                11,
+                EmbedProblem::Tiny { words: 4 }
+            ),
+            ("Other.md".to_string(), 2, 35, EmbedProblem::Missing),
+        ]
+    );
+
+    let edits = unembed_missing(&issues, true)?;
+    assert_eq!(edits.len(), 1);
+    let other = temp.path().join("pages/Other.md");
+    assert!(fs::read_to_string(&other)?.contains("and {{embed [[Gone]]}}"));
+    unembed_missing(&issues, false)?;
+    assert_eq!(
+        fs::read_to_string(&other)?,
+        "- {{embed [[Big]]}}\n- {{embed [[small]]}} and [[Gone]]\n- `{{embed [[Gone]]}}` stays\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_inline_refs() -> anyhow::Result<()> {
+    use logseq::inline::{inline_refs, InlineTarget};
+
+    let id = "65a1b2c3-0000-4000-8000-00000000000a";
+    let temp = TempDir::new()?;
+    temp.child("pages/Source.md").write_str(&format!(
+        "- ## The plan\n  id:: {}\n- other\n  id:: 65a1b2c3-0000-4000-8000-00000000000b\n",
+        id
+    ))?;
+    let other = temp.child("pages/Other.md");
+    other.write_str(
+        "- see ((65A1B2C3-0000-4000-8000-00000000000A)) and ((65a1b2c3-0000-4000-8000-00000000000b))\n- {{embed ((65a1b2c3-0000-4000-8000-00000000000a))}} `((65a1b2c3-0000-4000-8000-00000000000a))`\n",
+    )?;
+
+    let graph = Graph::scan(temp.path())?;
+    let target = InlineTarget::Block(id.to_string());
+    let edits = inline_refs(&graph, &target, false, true)?;
+    assert_eq!(edits.len(), 1);
+    assert!(edits[0]
+        .updated
+        .starts_with("- see The plan and ((65a1b2c3"));
+    assert!(fs::read_to_string(other.path())?.starts_with("- see ((65A1B2C3"));
+
+    inline_refs(
+        &graph,
+        &InlineTarget::Page("source".to_string()),
+        true,
+        false,
+    )?;
+    assert_eq!(
+        fs::read_to_string(other.path())?,
+        "- see The plan [[Source]] and other [[Source]]\n- {{embed ((65a1b2c3-0000-4000-8000-00000000000a))}} `((65a1b2c3-0000-4000-8000-00000000000a))`\n"
+    );
+    let missing = InlineTarget::Block("65a1b2c3-0000-4000-8000-00000000000c".to_string());
+    assert!(inline_refs(&graph, &missing, false, true).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_search_graph() -> anyhow::Result<()> {
+    use logseq::search::{search_graph, SearchQuery};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Books.md").write_str(
+        "- Reading
+\t- Dune
+\t  by Frank Herbert
+\t  type:: book
+\t\t- Review
+- frank, not a book
+",
+    )?;
+    temp.child("pages/Frank.md").write_str(
+        "tags:: person, book
+\n- Frank Herbert
+",
+    )?;
+    let graph = Graph::scan(temp.path())?;
+
+    let mut query = SearchQuery::new("frank", false, true)?;
+    let hits = search_graph(&graph, &query);
+    let found: Vec<(&str, usize)> = hits
+        .iter()
+        .map(|hit| (hit.page.as_str(), hit.line))
+        .collect();
+    assert_eq!(found, vec![("Books", 2), ("Books", 6), ("Frank", 3)]);
+    assert_eq!(hits[0].parents, vec!["Reading"]);
+    assert_eq!(hits[0].content, "Dune\nby Frank Herbert\ntype:: book");
+    assert_eq!(hits[0].children, vec!["Review"]);
+    assert_eq!((hits[0].matches[0].line, hits[0].matches[0].column), (3, 7));
+
+    query.properties = vec!["type=BOOK".parse()?];
+    let hits = search_graph(&graph, &query);
+    assert_eq!(hits.len(), 1);
+    query.properties = vec!["tags=book".parse()?];
+    assert_eq!(search_graph(&graph, &query)[0].page, "Frank");
+
+    let across_lines = SearchQuery::new("Dune\nby", false, false)?;
+    assert_eq!(search_graph(&graph, &across_lines).len(), 1);
+    let fixed = SearchQuery::new("frank,", true, false)?;
+    assert_eq!(search_graph(&graph, &fixed)[0].line, 6);
+    assert!(SearchQuery::new("(", false, false).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_refresh_tocs() -> anyhow::Result<()> {
+    use logseq::toc::{refresh_tocs, TocOptions};
+
+    let temp = TempDir::new()?;
+    let guide = temp.child("pages/Guide.md");
+    guide.write_str("lsd-toc:: numbered\n\n- # Install\n\t- ## Linux\n\t- ## macOS\n- # Use\n")?;
+    let long = temp.child("pages/Long.md");
+    long.write_str("- # One\n- # Two\n- # Three\n")?;
+    let excluded = temp.child("pages/Excluded.md");
+    excluded.write_str("- lsd-toc:: false\n- # One\n- # Two\n- # Three\n")?;
+    let graph = Graph::scan(temp.path())?;
+
+    let edits = refresh_tocs(&graph, &TocOptions::default(), false)?;
+    assert_eq!(edits.len(), 1);
+    let text = fs::read_to_string(guide.path())?;
+    let ids: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("id:: "))
+        .collect();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(
+        text,
+        format!(
+            "lsd-toc:: numbered\n\n- Contents\n  lsd-toc-block:: true\n\t- 1. (({0}))\n\t\t- 1.1. (({1}))\n\t\t- 1.2. (({2}))\n\t- 2. (({3}))\n- # Install\n  id:: {0}\n\t- ## Linux\n\t  id:: {1}\n\t- ## macOS\n\t  id:: {2}\n- # Use\n  id:: {3}\n",
+            ids[0], ids[1], ids[2], ids[3]
+        )
+    );
+
+    // The table follows the headings, and is up to date afterwards
+    guide.write_str(&text.replace("\t- ## macOS\n", "- # macOS\n"))?;
+    let graph = Graph::scan(temp.path())?;
+    refresh_tocs(&graph, &TocOptions::default(), false)?;
+    let text = fs::read_to_string(guide.path())?;
+    assert!(text.contains(&format!(
+        "\t- 2. (({}))\n\t- 3. (({}))\n- # Install",
+        ids[2], ids[3]
+    )));
+    let graph = Graph::scan(temp.path())?;
+    assert!(refresh_tocs(&graph, &TocOptions::default(), true)?.is_empty());
+
+    let all = TocOptions {
+        all: true,
+        min_headings: 3,
+    };
+    let edits = refresh_tocs(&graph, &all, true)?;
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].path, long.path());
+    assert!(edits[0]
+        .updated
+        .starts_with("- Contents\n  lsd-toc-block:: true\n\t- (("));
+    Ok(())
+}
+
+#[test]
+fn test_index_only_parses_changed_files() -> anyhow::Result<()> {
+    use logseq::graph::index::GraphIndex;
+
+    let temp = TempDir::new()?;
+    temp.child("pages/a.md").write_str("- [[b]]\n")?;
+    temp.child("pages/b.md").write_str("- text\n")?;
+    temp.child("journals/2024_01_02.md")
+        .write_str("- [[a]]\n")?;
+    let cache = temp.child("cache/index.json");
+
+    let mut index = GraphIndex::load(temp.path(), cache.path());
+    assert_eq!(index.update()?, 3);
+    index.save(cache.path())?;
+
+    temp.child("pages/b.md").write_str("- more text\n")?;
+    fs::remove_file(temp.child("journals/2024_01_02.md").path())?;
+    let mut index = GraphIndex::load(temp.path(), cache.path());
+    assert_eq!(index.update()?, 1);
+    let graph = index.into_graph();
+    let names: Vec<&str> = graph.pages.iter().map(|page| page.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b"]);
+    assert_eq!(graph.pages[1].outline.blocks[0].content, "more text");
+
+    fs::write(cache.path(), "not json")?;
+    let mut index = GraphIndex::load(temp.path(), cache.path());
+    assert_eq!(index.update()?, 2);
+    Ok(())
+}
+
+#[test]
+fn test_build_backlinks() -> anyhow::Result<()> {
+    use logseq::graph::backlinks::{build_backlinks, ReferenceKind};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/a.md")
+        .write_str("alias:: Other\ntags:: topic\n\n- see [[B]] and #topic\n  - {{embed [[b]]}} #[[b]]\n- ```\n  [[b]]\n  ```\n")?;
+    temp.child("journals/2024_01_02.md")
+        .write_str("- author:: [[B]]\n")?;
+    let backlinks = build_backlinks(&Graph::scan(temp.path())?);
+
+    let kinds = |name: &str| -> Vec<(String, usize, usize, ReferenceKind, bool)> {
+        backlinks[&logseq::graph::page_key(name)]
+            .iter()
+            .map(|reference| {
+                (
+                    reference.page.clone(),
+                    reference.line,
+                    reference.column,
+                    reference.kind,
+                    reference.journal,
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        kinds("b"),
+        vec![
+            (
+                "Jan 2nd, 2024".to_string(),
+                1,
+                3,
+                ReferenceKind::Property,
+                true
+            ),
+            ("a".to_string(), 4, 7, ReferenceKind::Link, false),
+            ("a".to_string(), 5, 13, ReferenceKind::Embed, false),
+            ("a".to_string(), 5, 21, ReferenceKind::Tag, false),
+        ]
+    );
+    assert_eq!(
+        kinds("Topic"),
+        vec![
+            ("a".to_string(), 2, 1, ReferenceKind::Property, false),
+            ("a".to_string(), 4, 17, ReferenceKind::Tag, false),
+        ]
+    );
+    assert!(!backlinks.contains_key("other"));
+    Ok(())
+}
+
+#[test]
+fn test_check_namespaces() -> anyhow::Result<()> {
+    use logseq::namespaces::{
+        check_namespaces, create_parent_stubs, reencode_files, NamespaceProblem, Separator,
+    };
+
+    let temp = TempDir::new()?;
+    temp.child("pages/project___one.md").write_str("- one\n")?;
+    temp.child("pages/project___two.md").write_str("- two\n")?;
+    temp.child("pages/area%2Fsub%2Fpage.md")
+        .write_str("- legacy\n")?;
+    temp.child("pages/area.md").write_str("- area\n")?;
+    temp.child("pages/v1.2.md")
+        .write_str("- not namespaced\n")?;
+
+    let issues = check_namespaces(&Graph::scan(temp.path())?)?;
+    let problems: Vec<(String, &NamespaceProblem)> = issues
+        .iter()
+        .map(|issue| (issue.page.clone(), &issue.problem))
+        .collect();
+    let pages = temp.path().join("pages");
+    assert_eq!(
+        problems,
+        vec![
+            (
+                "area/sub/page".to_string(),
+                &NamespaceProblem::MissingParent {
+                    parent: "area/sub".to_string(),
+                    stub: pages.join("area___sub.md"),
+                }
+            ),
+            (
+                "area/sub/page".to_string(),
+                &NamespaceProblem::InconsistentSeparator {
+                    found: Separator::PercentEncoded,
+                    expected: Separator::TripleLowbar,
+                    rename_to: pages.join("area___sub___page.md"),
+                }
+            ),
+            (
+                "project/one".to_string(),
+                &NamespaceProblem::MissingParent {
+                    parent: "project".to_string(),
+                    stub: pages.join("project.md"),
+                }
+            ),
+        ]
+    );
+
+    assert_eq!(
+        create_parent_stubs(&issues)?,
+        vec![pages.join("area___sub.md"), pages.join("project.md")]
+    );
+    assert_eq!(
+        fs::read_to_string(pages.join("project.md"))?,
+        "title:: project\n"
+    );
+    assert_eq!(reencode_files(&issues)?.len(), 1);
+    assert!(pages.join("area___sub___page.md").is_file());
+    assert!(check_namespaces(&Graph::scan(temp.path())?)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_find_alias_conflicts() -> anyhow::Result<()> {
+    use logseq::graph::AliasConflictKind;
+
+    let temp = TempDir::new()?;
+    temp.child("pages/a.md")
+        .write_str("alias:: Shared, b\n\n- a\n")?;
+    temp.child("pages/b.md").write_str("- b\n")?;
+    temp.child("pages/c.md")
+        .write_str("alias:: shared, C\n\n- c\n")?;
+    let graph = Graph::scan(temp.path())?;
+
+    let conflicts = graph.find_alias_conflicts();
+    let found: Vec<(&str, &str, &str, AliasConflictKind)> = conflicts
+        .iter()
+        .map(|conflict| {
+            (
+                conflict.page.as_str(),
+                conflict.alias.as_str(),
+                conflict.other_page.as_str(),
+                conflict.kind,
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("a", "b", "b", AliasConflictKind::PageName),
+            ("c", "shared", "a", AliasConflictKind::Alias),
+        ]
+    );
+    assert_eq!(graph.find_page("B").unwrap().name, "b");
+    assert_eq!(graph.find_page("SHARED").unwrap().name, "a");
+    Ok(())
+}
+
+#[test]
+fn test_archive_done_tasks() -> anyhow::Result<()> {
+    use chrono::NaiveDate;
+    use logseq::tasks::archive::{plan_archive, ArchiveDestination};
+
+    let temp = TempDir::new()?;
+    let clock = |day| {
+        format!("\t  :LOGBOOK:\n\t  CLOCK: [2024-{0} 10:00:00]--[2024-{0} 11:00:00] =>  01:00:00\n\t  :END:\n", day)
+    };
+    temp.child("pages/Project.md").write_str(&format!(
+        "- Tasks\n\t- DONE ship v1\n\t  id:: 65a1b2c3-0000-4000-8000-000000000001\n{}\t\t- notes\n\t- DONE recent\n{}\t- TODO open\n",
+        clock("01-05 Fri"),
+        clock("02-25 Sun")
+    ))?;
+    temp.child("journals/2024_01_05.md")
+        .write_str("- Morning\n")?;
+    temp.child("pages/Chores.md")
+        .write_str("- DONE laundry\n")?;
+    temp.child("pages/Errands.md")
+        .write_str("- DONE post\n  done:: 1704189600000\n- DONE shop\n  done:: 1709200800000\n")?;
+    let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+
+    let archive = plan_archive(
+        &Graph::scan(temp.path())?,
+        today,
+        30,
+        &ArchiveDestination::Journal,
+    )?;
+    let texts: Vec<&str> = archive
+        .tasks
+        .iter()
+        .map(|task| task.text.as_str())
+        .collect();
+    assert_eq!(texts, vec!["DONE post", "DONE ship v1"]);
+    archive.apply()?;
+    assert_eq!(
+        fs::read_to_string(temp.path().join("pages/Project.md"))?,
+        format!(
+            "- Tasks\n\t- DONE recent\n{}\t- TODO open\n",
+            clock("02-25 Sun")
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(temp.path().join("journals/2024_01_05.md"))?,
+        "- Morning\n- DONE ship v1\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n  archived-from:: [[Project]]\n  :LOGBOOK:\n  CLOCK: [2024-01-05 Fri 10:00:00]--[2024-01-05 Fri 11:00:00] =>  01:00:00\n  :END:\n\t- notes\n"
+    );
+
+    assert_eq!(
+        fs::read_to_string(temp.path().join("pages/Errands.md"))?,
+        "- DONE shop\n  done:: 1709200800000\n"
+    );
+    assert_eq!(
+        fs::read_to_string(temp.path().join("journals/2024_01_02.md"))?,
+        "- DONE post\n  done:: 1704189600000\n  archived-from:: [[Errands]]\n"
+    );
+
+    // Without a logbook or a done:: timestamp, tasks are as old as their file
+    let later = NaiveDate::from_ymd_opt(2100, 1, 1).unwrap();
+    let destination = ArchiveDestination::Page("Archive".to_string());
+    let archive = plan_archive(&Graph::scan(temp.path())?, later, 30, &destination)?;
+    assert_eq!(archive.tasks.len(), 3);
+    archive.apply()?;
+    assert_eq!(fs::read_to_string(temp.path().join("pages/Chores.md"))?, "");
+    let archived = fs::read_to_string(temp.path().join("pages/Archive.md"))?;
+    assert!(archived.starts_with("- DONE laundry\n  archived-from:: [[Chores]]\n"));
+    assert!(archived.contains("- DONE recent\n  archived-from:: [[Project]]\n"));
+    Ok(())
+}
+
+#[test]
+fn test_normalize_names() -> anyhow::Result<()> {
+    use logseq::normalize::plan_normalization;
+
+    assert!(plan_normalization(&fixture_graph())?.is_empty());
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Cafe\u{301}.md")
+        .write_str("- Decomposed\n")?;
+    temp.child("pages/Menu.md")
+        .write_str("tags:: re\u{301}sume\u{301}\n\n- see [[Cafe\u{301}]] and #cafe\u{301}\n- `[[Cafe\u{301}]]` [[caf\u{e9}]]\n")?;
+    let pages = temp.path().join("pages");
+
+    let normalization = plan_normalization(&Graph::scan(temp.path())?)?;
+    assert_eq!(
+        normalization.renames,
+        vec![(pages.join("Cafe\u{301}.md"), pages.join("Caf\u{e9}.md"))]
+    );
+    assert_eq!(normalization.edits.len(), 1);
+    normalization.apply()?;
+    assert!(pages.join("Caf\u{e9}.md").exists());
+    assert!(!pages.join("Cafe\u{301}.md").exists());
+    assert_eq!(
+        fs::read_to_string(pages.join("Menu.md"))?,
+        "tags:: r\u{e9}sum\u{e9}\n\n- see [[Caf\u{e9}]] and #caf\u{e9}\n- `[[Cafe\u{301}]]` [[caf\u{e9}]]\n"
+    );
+    assert!(plan_normalization(&Graph::scan(temp.path())?)?.is_empty());
+
+    // Two spellings of the same name can't both be renamed to it
+    temp.child("pages/Cafe\u{301}.md").write_str("- Again\n")?;
+    assert!(plan_normalization(&Graph::scan(temp.path())?).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_file_name_repair() -> anyhow::Result<()> {
+    use logseq::filenames::plan_file_name_repair;
+
+    assert!(plan_file_name_repair(&fixture_graph())?.is_empty());
+
+    let temp = TempDir::new()?;
+    temp.child("pages/what?.md").write_str("- Not encoded\n")?;
+    temp.child("pages/My%20Page.md")
+        .write_str("- Downloaded\n")?;
+    temp.child("pages/100%AB.md")
+        .write_str("- Not a percent-encoding\n")?;
+    temp.child("pages/Titled: yes.md")
+        .write_str("title:: Titled: yes\n\n- Named by its title\n")?;
+    temp.child("pages/Links.md")
+        .write_str("- [[what?]], [[My%20Page]] and #[[100%AB]]\n- `[[My%20Page]]` [[My Page]]\n")?;
+    let pages = temp.path().join("pages");
+
+    let repair = plan_file_name_repair(&Graph::scan(temp.path())?)?;
+    assert_eq!(
+        repair.renames,
+        vec![
+            (pages.join("100%AB.md"), pages.join("100%25AB.md")),
+            (pages.join("My%20Page.md"), pages.join("My Page.md")),
+            (pages.join("what?.md"), pages.join("what%3F.md")),
+        ]
+    );
+    assert_eq!(repair.edits.len(), 1);
+    repair.apply()?;
+    assert!(pages.join("what%3F.md").exists());
+    assert!(!pages.join("what?.md").exists());
+    assert_eq!(
+        fs::read_to_string(pages.join("Links.md"))?,
+        "- [[what?]], [[My Page]] and #[[100%AB]]\n- `[[My%20Page]]` [[My Page]]\n"
+    );
+    let graph = Graph::scan(temp.path())?;
+    assert!(graph.find_page("100%AB").is_some());
+    assert!(plan_file_name_repair(&graph)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_check_templates() -> anyhow::Result<()> {
+    use logseq::templates::{check_templates, list_templates, TemplateProblem};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Templates.md").write_str(
+        "- Daily\n  template:: daily\n  template-including-parent:: false\n\t- Date: <% today %>, due <% next friday %>\n\t- Page: <%current page%> `<% code %>`\n- Meeting\n  template:: meeting\n  template-including-parent:: yes\n\t- On <% todya %>\n",
+    )?;
+    temp.child("pages/Other.md")
+        .write_str("- template:: Daily\n- Note\n  template-including-parent:: true\n")?;
+    let graph = Graph::scan(temp.path())?;
+    let pages = temp.path().join("pages");
+
+    let templates: Vec<(String, usize, bool)> = list_templates(&graph)
+        .into_iter()
+        .map(|template| (template.name, template.line, template.including_parent))
+        .collect();
+    assert_eq!(
+        templates,
+        vec![
+            ("Daily".to_string(), 1, true),
+            ("daily".to_string(), 1, false),
+            ("meeting".to_string(), 6, true),
+        ]
+    );
+
+    let problems: Vec<(String, usize, TemplateProblem)> = check_templates(&graph)
+        .into_iter()
+        .map(|issue| {
+            let name = issue
+                .path
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            (name, issue.line, issue.problem)
+        })
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (
+                "Other.md".to_string(),
+                2,
+                TemplateProblem::IncludingParentWithoutTemplate
+            ),
+            (
+                "Templates.md".to_string(),
+                1,
+                TemplateProblem::DuplicateName {
+                    path: pages.join("Other.md"),
+                    line: 1
+                }
+            ),
+            (
+                "Templates.md".to_string(),
+                6,
+                TemplateProblem::InvalidIncludingParent("yes".to_string())
+            ),
+            (
+                "Templates.md".to_string(),
+                6,
+                TemplateProblem::UnknownVariable {
+                    line: 9,
+                    variable: "<% todya %>".to_string()
+                }
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_highlights() -> anyhow::Result<()> {
+    use logseq::highlights::{check_highlights, HighlightProblem};
+
+    assert!(check_highlights(&fixture_graph()).is_empty());
+
+    let temp = TempDir::new()?;
+    temp.child("assets/paper_1.pdf").write_str("%PDF")?;
+    temp.child("assets/paper_1/2_6543_1700.png").write_str("")?;
+    temp.child("pages/hls__paper_1.md").write_str(
+        "file:: [paper.pdf](../assets/paper_1.pdf)\nfile-path:: ../assets/paper_1.pdf\n\n- text\n  ls-type:: annotation\n  hl-page:: 2\n  id:: 6543\n- [:span]\n  ls-type:: annotation\n  hl-page:: 2\n  hl-type:: area\n  id:: 6543\n  hl-stamp:: 1700\n- [:span]\n  hl-page:: 3\n  hl-type:: area\n  id:: 6544\n  hl-stamp:: 1800\n- bad\n  hl-page:: two\n",
+    )?;
+    temp.child("pages/hls__moved_2.md")
+        .write_str("file:: [moved.pdf](../assets/moved_2.pdf)\n\n- text\n  hl-page:: 1\n")?;
+    temp.child("pages/hls__nothing.md").write_str("- text\n")?;
+    let assets = temp.path().join("assets");
+
+    let problems: Vec<(String, HighlightProblem)> = check_highlights(&Graph::scan(temp.path())?)
+        .into_iter()
+        .map(|issue| (issue.page, issue.problem))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (
+                "hls__moved_2".to_string(),
+                HighlightProblem::MissingPdf {
+                    target: "../assets/moved_2.pdf".to_string()
+                }
+            ),
+            ("hls__nothing".to_string(), HighlightProblem::NoPdf),
+            (
+                "hls__paper_1".to_string(),
+                HighlightProblem::MissingAreaImage {
+                    line: 14,
+                    image: assets.join("paper_1/3_6544_1800.png")
+                }
+            ),
+            (
+                "hls__paper_1".to_string(),
+                HighlightProblem::InvalidPage {
+                    line: 20,
+                    value: "two".to_string()
+                }
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_check_whiteboards() -> anyhow::Result<()> {
+    use logseq::whiteboards::{check_whiteboards, list_whiteboards, PortalTarget};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Roadmap.md")
+        .write_str("- Goals\n  id:: 65a0c1d2-0000-4000-8000-000000000001\n")?;
+    temp.child("whiteboards/Plans.edn").write_str(
+        r#"{:blocks ({:block/properties {:ls-type :whiteboard-shape,
+   :logseq.tldraw.shape {:type "logseq-portal", :blockType "P", :pageId "roadmap"}}}
+  {:block/properties {:ls-type :whiteboard-shape,
+   :logseq.tldraw.shape {:type "logseq-portal", :blockType "P", :pageId "Renamed"}}}
+  {:block/properties {:ls-type :whiteboard-shape,
+   :logseq.tldraw.shape {:type "logseq-portal", :blockType "B", :pageId "65a0c1d2-0000-4000-8000-000000000001"}}}
+  {:block/properties {:ls-type :whiteboard-shape,
+   :logseq.tldraw.shape {:type "logseq-portal", :blockType "B", :pageId "65a0c1d2-0000-4000-8000-00000000dead"}}}
+  {:block/properties {:ls-type :whiteboard-shape,
+   :logseq.tldraw.shape {:type "logseq-portal", :blockType "P", :pageId "Sketches"}}}),
+ :pages ({:block/name "plans", :block/type "whiteboard"})}"#,
+    )?;
+    temp.child("whiteboards/Sketches.tldr").write_str(
+        r#"{"shapes": [{"id": "a", "type": "logseq-portal", "blockType": "P", "pageId": "Gone"}]}"#,
+    )?;
+
+    let whiteboards = list_whiteboards(temp.path())?;
+    assert_eq!(
+        whiteboards
+            .iter()
+            .map(|whiteboard| (whiteboard.name.as_str(), whiteboard.portals.len()))
+            .collect::<Vec<_>>(),
+        vec![("Plans", 5), ("Sketches", 1)]
+    );
+    assert_eq!(
+        whiteboards[0].portals[2].target,
+        PortalTarget::Block("65a0c1d2-0000-4000-8000-000000000001".to_string())
+    );
+
+    let broken = check_whiteboards(&Graph::scan(temp.path())?)?;
+    let located = |links: &[logseq::graph::BrokenLink]| -> Vec<(PathBuf, usize, usize, String)> {
+        links
+            .iter()
+            .map(|link| {
+                let path = link.path.strip_prefix(temp.path()).unwrap().to_path_buf();
+                (path, link.line, link.column, link.target.clone())
+            })
+            .collect()
+    };
+    assert_eq!(
+        located(&broken.pages),
+        vec![
+            (
+                PathBuf::from("whiteboards/Plans.edn"),
+                4,
+                74,
+                "Renamed".to_string()
+            ),
+            (
+                PathBuf::from("whiteboards/Sketches.tldr"),
+                1,
+                79,
+                "Gone".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        located(&broken.blocks),
+        vec![(
+            PathBuf::from("whiteboards/Plans.edn"),
+            8,
+            74,
+            "65a0c1d2-0000-4000-8000-00000000dead".to_string()
+        )]
+    );
+
+    temp.child("whiteboards/Broken.edn")
+        .write_str("{:blocks (")?;
+    assert!(check_whiteboards(&Graph::scan(temp.path())?).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_backfill_timestamps() -> anyhow::Result<()> {
+    use logseq::timestamps::{backfill_timestamps, TimestampSource};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Plain.md").write_str("- Block\n")?;
+    temp.child("pages/Tagged.md")
+        .write_str("tags:: a\ncreated-at:: 1000000000000\n\n- Block\n")?;
+    temp.child("pages/Done.md")
+        .write_str("- created-at:: 1\n  updated-at:: 2\n- Block\n")?;
+    let modified = UNIX_EPOCH + Duration::from_secs(1_704_189_600);
+    for name in ["Plain", "Tagged"] {
+        let path = temp.path().join(format!("pages/{}.md", name));
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(modified)?;
+    }
+
+    let graph = Graph::scan(temp.path())?;
+    let dry_run = backfill_timestamps(&graph, TimestampSource::FileTimes, true)?;
+    assert_eq!(dry_run.len(), 2);
+    temp.child("pages/Plain.md").assert("- Block\n");
+
+    backfill_timestamps(&graph, TimestampSource::FileTimes, false)?;
+    let plain = fs::read_to_string(temp.path().join("pages/Plain.md"))?;
+    assert!(plain.ends_with("\nupdated-at:: 1704189600000\n\n- Block\n"));
+    assert!(plain.starts_with("created-at:: "));
+    temp.child("pages/Tagged.md")
+        .assert("tags:: a\ncreated-at:: 1000000000000\nupdated-at:: 1704189600000\n\n- Block\n");
+    temp.child("pages/Done.md")
+        .assert("- created-at:: 1\n  updated-at:: 2\n- Block\n");
+    Ok(())
+}
+
+#[test]
+fn test_convert_db_version() -> anyhow::Result<()> {
+    use logseq::interop::db_version::{convert_graph, DbProblem};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Book.md").write_str(
+        "- Tags:: #reading, [[sci fi]]\n  Status:: open\n  status:: done\n- NOW Read\n  ```\n  my_key:: kept\n  ```\n",
+    )?;
+    temp.child("pages/Fine.md")
+        .write_str("- DONE Task\n  rating:: 5\n")?;
+    temp.child("journals/2024_01_02.md")
+        .write_str("- WAIT Reply\n  Due_Date:: 2024-01-03\n")?;
+    temp.child("pages/Old.org").write_str("* TODO Task\n")?;
+
+    let graph = Graph::scan(temp.path())?;
+    let dry_run = convert_graph(&graph, true)?;
+    assert_eq!(dry_run.edits.len(), 2);
+    assert!(fs::read_to_string(temp.path().join("pages/Book.md"))?.starts_with("- Tags::"));
+    let problems: Vec<(String, usize, usize, DbProblem)> = dry_run
+        .issues
+        .iter()
+        .map(|issue| {
+            let name = issue.path.file_name().unwrap().to_string_lossy();
+            (
+                name.to_string(),
+                issue.line,
+                issue.column,
+                issue.problem.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            (
+                "2024_01_02.md".to_string(),
+                1,
+                3,
+                DbProblem::UnmappedMarker("WAIT".to_string())
+            ),
+            (
+                "Book.md".to_string(),
+                3,
+                3,
+                DbProblem::DuplicateKey("status".to_string())
+            ),
+            ("Old.org".to_string(), 1, 1, DbProblem::OrgFile),
+        ]
+    );
+
+    convert_graph(&graph, false)?;
+    temp.child("pages/Book.md").assert(
+        "- tags:: [[reading]], [[sci fi]]\n  Status:: open\n  status:: done\n- DOING Read\n  ```\n  my_key:: kept\n  ```\n",
+    );
+    temp.child("journals/2024_01_02.md")
+        .assert("- WAIT Reply\n  due-date:: 2024-01-03\n");
+    temp.child("pages/Fine.md")
+        .assert("- DONE Task\n  rating:: 5\n");
+    Ok(())
+}
+
+#[test]
+fn test_assets_of_whiteboards_org_pages_and_custom_css() -> anyhow::Result<()> {
+    use logseq::assets::{check_assets, dedupe_assets, find_duplicate_assets};
+
+    let temp = TempDir::new()?;
+    for name in ["board.png", "org.png", "font.woff", "unused.png"] {
+        temp.child(format!("assets/{}", name)).write_str(name)?;
+    }
+    temp.child("assets/copy.png").write_str("board.png")?;
+    temp.child("pages/page.md").write_str("- Text\n")?;
+    let board = temp.child("whiteboards/Board.edn");
+    board.write_str(
+        "{:blocks ({:block/properties {:ls-type :whiteboard-shape, :logseq.tldraw.shape {:type \"image\", :src \"../assets/copy.png\"}}} {:src \"../assets/board.png\"})}\n",
+    )?;
+    temp.child("pages/notes.org")
+        .write_str("* Scan\n  [[file:../assets/org.png]] and [[../assets/gone.png][gone]]\n")?;
+    temp.child("logseq/custom.css")
+        .write_str("@font-face { src: url(\"../assets/font.woff\"); }\n")?;
+
+    let graph = Graph::scan(temp.path())?;
+    let report = check_assets(&graph)?;
+    let missing: Vec<(usize, usize, &str)> = report
+        .missing
+        .iter()
+        .map(|link| (link.line, link.column, link.target.as_str()))
+        .collect();
+    assert_eq!(missing, vec![(2, 36, "../assets/gone.png")]);
+    assert!(report.missing[0].path.ends_with("pages/notes.org"));
+    assert_eq!(report.orphans, vec![temp.path().join("assets/unused.png")]);
+
+    let groups = find_duplicate_assets(&graph)?;
+    dedupe_assets(&graph, &groups, false)?;
+    assert!(!temp.child("assets/copy.png").path().exists());
+    board.assert(
+        "{:blocks ({:block/properties {:ls-type :whiteboard-shape, :logseq.tldraw.shape {:type \"image\", :src \"../assets/board.png\"}}} {:src \"../assets/board.png\"})}\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dedupe_assets() -> anyhow::Result<()> {
+    use logseq::assets::{dedupe_assets, find_duplicate_assets};
+
+    let temp = TempDir::new()?;
+    temp.child("assets/image_1700000000000_0.png")
+        .write_binary(&[1, 2, 3])?;
+    let second = temp.child("assets/image_1700000999999_0.png");
+    second.write_binary(&[1, 2, 3])?;
+    let spaced = temp.child("assets/sub/my copy.png");
+    spaced.write_binary(&[1, 2, 3])?;
+    temp.child("assets/other.png").write_binary(&[1, 2, 4])?;
+    let page = temp.child("pages/page.md");
+    page.write_str(
+        "- ![a](../assets/image_1700000999999_0.png) and ![b](../assets/sub/my%20copy.png)\n- `![c](../assets/image_1700000999999_0.png)` stays\n- ![d](../assets/other.png)\n",
+    )?;
+
+    let graph = Graph::scan(temp.path())?;
+    let groups = find_duplicate_assets(&graph)?;
+    assert_eq!(groups.len(), 1);
+    assert!(groups[0].canonical.ends_with("image_1700000000000_0.png"));
+    assert_eq!(
+        groups[0].duplicates,
+        vec![second.path().to_path_buf(), spaced.path().to_path_buf()]
+    );
+
+    let fixed = "- ![a](../assets/image_1700000000000_0.png) and ![b](../assets/image_1700000000000_0.png)\n- `![c](../assets/image_1700000999999_0.png)` stays\n- ![d](../assets/other.png)\n";
+    let edits = dedupe_assets(&graph, &groups, true)?;
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].updated, fixed);
+    assert!(second.path().exists());
+
+    dedupe_assets(&graph, &groups, false)?;
+    page.assert(fixed);
+    assert!(!second.path().exists());
+    assert!(!spaced.path().exists());
+    assert!(temp.child("assets/other.png").path().exists());
+    Ok(())
+}
+
+#[test]
+fn test_shared_graph_checks_while_editing() {
+    fn shared<T: Send + Sync>() {}
+    shared::<SharedGraph>();
+
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Old.md")
+        .write_str("- Old  page\n")
+        .unwrap();
+    for index in 0..20 {
+        temp.child(format!("pages/p{}.md", index))
+            .write_str("- Links to [[Old]]\n")
+            .unwrap();
+    }
+    let graph = SharedGraph::open(temp.path()).unwrap();
+    let rules = RuleSet::builtin().select(&["consecutive-spaces"]);
+    std::thread::scope(|scope| {
+        let checks: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    let snapshot = graph.snapshot();
+                    // Each snapshot is consistent: all links to Old, or all to New
+                    let names = snapshot.page_names();
+                    assert!(names.contains("old") != names.contains("new"));
+                    assert!(snapshot.check_links().is_empty());
+                    graph.lint(&rules).unwrap();
+                })
+            })
+            .collect();
+        graph.rename("Old", "New").unwrap();
+        for check in checks {
+            check.join().unwrap();
+        }
+    });
+    let snapshot = graph.snapshot();
+    assert!(snapshot.find_page("New").is_some());
+    assert!(graph.check_links().is_empty());
+    assert_eq!(graph.backlinks()["new"].len(), 20);
+    assert_eq!(graph.fix(&rules).unwrap().len(), 21);
+    assert!(graph.lint(&rules).unwrap().findings.is_empty());
+}
@@ -1,3 +1,4 @@
+//! Integration tests for journals and pages
 use assert_fs::prelude::*;
 use assert_fs::TempDir;
 use chrono::NaiveDate;
@@ -39,7 +40,7 @@ impl FakeJournal {
         fs::create_dir(journal.as_path().parent().unwrap()).unwrap();
         let content_to_add = fixture("journal/add-this.md");
 
-        fs::write(&journal.as_path(), fixture(existing_fixture_path)).unwrap();
+        fs::write(journal.as_path(), fixture(existing_fixture_path)).unwrap();
 
         FakeJournal {
             _temp_path: temp,
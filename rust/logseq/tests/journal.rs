@@ -0,0 +1,162 @@
+//! Integration tests for journal file validation and links to journals
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use chrono::NaiveDate;
+use logseq::config::GraphConfig;
+use logseq::date_links::DateLinker;
+use logseq::dates::{DateFormat, ENGLISH, GERMAN, PORTUGUESE};
+use logseq::journal::{check_journals, rename_journals, JournalProblem};
+
+#[test]
+fn test_check_and_rename_journals() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("logseq/config.edn").write_str(
+        ";; :journal/file-name-format \"yyyy_MM_dd\"\n{:journal/file-name-format \"yyyy-MM-dd\"}\n",
+    )?;
+    temp.child("journals/2024-01-02.md").write_str("- Fine\n")?;
+    temp.child("journals/2024_01_03.md")
+        .write_str("- Wrong format\n")?;
+    temp.child("journals/2024-01-04.md")
+        .write_str("- [[Jan 5th, 2024]]\n\t- Moved\n")?;
+    temp.child("journals/notes.md")
+        .write_str("- Not a journal\n")?;
+    assert_eq!(
+        GraphConfig::read(temp.path())?.journal_file_name_format,
+        "yyyy-MM-dd"
+    );
+
+    let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    let issues = check_journals(temp.path())?;
+    let problems: Vec<&JournalProblem> = issues.iter().map(|issue| &issue.problem).collect();
+    assert_eq!(
+        problems,
+        vec![
+            &JournalProblem::DateMismatch {
+                file_date: date(4),
+                content_date: date(5)
+            },
+            &JournalProblem::WrongFormat(date(3)),
+            &JournalProblem::NotADate,
+        ]
+    );
+
+    let renamed = rename_journals(&issues)?;
+    assert_eq!(renamed.len(), 2);
+    temp.child("journals/2024-01-03.md")
+        .assert("- Wrong format\n");
+    temp.child("journals/2024-01-05.md")
+        .assert("- [[Jan 5th, 2024]]\n\t- Moved\n");
+    assert!(!temp.child("journals/2024_01_03.md").path().exists());
+
+    // A rename that would overwrite a file is refused
+    temp.child("journals/2024_01_02.md")
+        .write_str("- Duplicate\n")?;
+    assert!(rename_journals(&check_journals(temp.path())?).is_err());
+    temp.child("journals/2024_01_02.md").assert("- Duplicate\n");
+    Ok(())
+}
+
+#[test]
+fn test_journal_gaps_and_backfill() -> anyhow::Result<()> {
+    use logseq::dates::DateFormat;
+    use logseq::graph::Graph;
+    use logseq::journal::{create_journals, journal_gaps};
+    use logseq::templates::{render_date_variables, template_body};
+
+    let temp = TempDir::new()?;
+    temp.child("logseq/config.edn")
+        .write_str("{:default-templates {:journals \"Daily\"}}\n")?;
+    temp.child("journals/2024_02_27.md")
+        .write_str("- Start\n")?;
+    temp.child("journals/2024_03_02.md").write_str("- End\n")?;
+    temp.child("journals/notes.md")
+        .write_str("- Not a date\n")?;
+    temp.child("pages/Templates.md").write_str(
+        "- Daily\n  template:: daily\n  template-including-parent:: false\n  - Since <% yesterday %>\n    - <% time %>\n",
+    )?;
+    let day = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+
+    let gaps = journal_gaps(temp.path())?;
+    assert_eq!(gaps, vec![day(2, 28), day(2, 29), day(3, 1)]);
+
+    let body = template_body(&Graph::scan(temp.path())?, "Daily")?.unwrap();
+    assert_eq!(body, "- Since <% yesterday %>\n  - <% time %>\n");
+    let created = create_journals(temp.path(), &gaps[..1], |date| {
+        render_date_variables(&body, date, &DateFormat::default())
+    })?;
+    assert_eq!(created, vec![temp.path().join("journals/2024_02_28.md")]);
+    temp.child("journals/2024_02_28.md")
+        .assert("- Since [[Feb 27th, 2024]]\n  - <% time %>\n");
+    assert!(create_journals(temp.path(), &gaps, |_| String::new()).is_err());
+    assert!(!temp.child("journals/2024_02_29.md").path().exists());
+
+    create_journals(temp.path(), &gaps[1..], |_| String::new())?;
+    temp.child("journals/2024_03_01.md").assert("");
+    assert!(journal_gaps(temp.path())?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_journal_cleanup() -> anyhow::Result<()> {
+    use logseq::graph::Graph;
+    use logseq::journal::{apply_journal_cleanup, plan_journal_cleanup};
+
+    let temp = TempDir::new()?;
+    temp.child("journals/2024_01_02.md").write_str(
+        "- **14:00** Review\n- Untimed\n- **08:30** Coffee\n  - Black\n    id:: 65a0c1d2-0000-4000-8000-000000000001\n- **08:30** Coffee\n  - Black\n    id:: 65a0c1d2-0000-4000-8000-000000000001\n- **08:30** Coffee\n  - With milk\n",
+    )?;
+    temp.child("journals/2024_01_03.md")
+        .write_str("- Parent\n  - Same\n  - Same\n  - Other\n-\n-\n")?;
+    temp.child("journals/2024_01_04.md").write_str("- Fine\n")?;
+    temp.child("pages/Copies.md")
+        .write_str("- Same\n- Same\n")?;
+
+    let graph = Graph::scan(temp.path())?;
+    let cleanups = plan_journal_cleanup(&graph, false)?;
+    let removed: Vec<&[usize]> = cleanups
+        .iter()
+        .map(|cleanup| cleanup.removed.as_slice())
+        .collect();
+    assert_eq!(removed, vec![&[6][..], &[3][..]]);
+
+    let cleanups = plan_journal_cleanup(&graph, true)?;
+    apply_journal_cleanup(&cleanups)?;
+    temp.child("journals/2024_01_02.md").assert(
+        "- **08:30** Coffee\n  - Black\n    id:: 65a0c1d2-0000-4000-8000-000000000001\n- Untimed\n- **08:30** Coffee\n  - With milk\n- **14:00** Review\n",
+    );
+    temp.child("journals/2024_01_03.md")
+        .assert("- Parent\n  - Same\n  - Other\n-\n-\n");
+    Ok(())
+}
+
+#[test]
+fn test_date_linker_languages() {
+    let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    // A Wednesday
+    let reference = date(3, 6);
+    let german = DateLinker::new(&GERMAN, DateFormat::new("yyyy-MM-dd"));
+    assert_eq!(
+        german.link(
+            "- Am 5. März, nächsten Montag und morgen\n",
+            reference,
+            true
+        ),
+        "- Am [[2024-03-05]], [[2024-03-11]] und [[2024-03-07]]\n"
+    );
+    let portuguese = DateLinker::new(&PORTUGUESE, DateFormat::default());
+    assert_eq!(
+        portuguese.link(
+            "- 5 de março de 2023 e sexta-feira passada\n",
+            reference,
+            false
+        ),
+        "- [[Mar 5th, 2023]] e [[Mar 1st, 2024]]\n"
+    );
+    let english = DateLinker::new(&ENGLISH, DateFormat::default());
+    let text = "- [[Mar 5th, 2024]] #2024-03-05 https://example.com/2024-03-05 <2024-03-05 Tue>\n";
+    assert_eq!(english.link(text, reference, false), text);
+    assert_eq!(
+        english.link("- Due March 32\n", reference, false),
+        "- Due March 32\n"
+    );
+}
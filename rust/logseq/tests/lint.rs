@@ -0,0 +1,1672 @@
+//! Integration tests for the lint engine
+use logseq::lint::{run_lints, Diagnostic, Rule, RuleSet};
+use logseq::outline::Outline;
+
+struct NoTodo;
+
+impl Rule for NoTodo {
+    fn id(&self) -> &'static str {
+        "no-todo"
+    }
+
+    fn check(&self, outline: &Outline) -> Vec<Diagnostic> {
+        outline
+            .iter()
+            .filter(|block| block.first_line().starts_with("TODO "))
+            .map(|block| Diagnostic::new(self.id(), block.line, 1, "TODO found"))
+            .collect()
+    }
+}
+
+#[test]
+fn test_custom_rule_and_selection() {
+    let text = "- TODO  write tests\n  - #[[done]]";
+    let rules = RuleSet::builtin().with(NoTodo);
+    assert_eq!(
+        rules.ids(),
+        vec![
+            "front-matter",
+            "page-properties",
+            "consecutive-spaces",
+            "malformed-refs",
+            "tag-brackets",
+            "checkboxes",
+            "task-markers",
+            "priorities",
+            "timestamps",
+            "repeaters",
+            "block-times",
+            "done-metadata",
+            "trailing-whitespace",
+            "punctuation",
+            "tables",
+            "empty-blocks",
+            "list-markers",
+            "indentation",
+            "continuation-indent",
+            "nesting-depth",
+            "headings",
+            "title-heading",
+            "editor-properties",
+            "logbook",
+            "properties-drawer",
+            "property-order",
+            "property-schema",
+            "timestamp-properties",
+            "page-property-values",
+            "link-style",
+            "link-normalization",
+            "image-props",
+            "queries",
+            "dynamic-variables",
+            "callouts",
+            "admonitions",
+            "footnotes",
+            "citations",
+            "spelling",
+            "no-todo"
+        ]
+    );
+
+    let rules_found: Vec<&str> = run_lints(text, &rules).iter().map(|d| d.rule).collect();
+    assert_eq!(
+        rules_found,
+        vec!["no-todo", "consecutive-spaces", "tag-brackets"]
+    );
+
+    let only_todo = rules.select(&["no-todo"]);
+    assert_eq!(run_lints(text, &only_todo).len(), 1);
+    // Rules without a fix leave the text alone
+    assert_eq!(only_todo.fix(text), text);
+}
+
+#[test]
+fn test_fix_preserves_line_endings() {
+    let rules = RuleSet::builtin();
+    assert_eq!(rules.fix("- a  b\r\n  - #[[c]]\r\n"), "- a b\r\n  - #c\r\n");
+    assert_eq!(rules.fix("- a  b\n- c"), "- a b\n- c");
+}
+
+#[test]
+fn test_consecutive_spaces_in_code_and_math() {
+    let rules = RuleSet::builtin().select(&["consecutive-spaces"]);
+    let protected = "- `a  b` and $$x  =  y$$";
+    assert_eq!(run_lints(protected, &rules), vec![]);
+    assert_eq!(rules.fix(protected), protected);
+
+    let diagnostics = run_lints("- `a  b`  c", &rules);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].column, 9);
+    assert_eq!(rules.fix("- `a  b`  c"), "- `a  b` c");
+}
+
+#[test]
+fn test_trailing_whitespace() {
+    let rules = RuleSet::builtin().select(&["trailing-whitespace"]);
+    let text = "title:: Page \n- a \t\n  b  \n- \n- ```\n  code  \n  ```\n";
+    let positions: Vec<(usize, usize)> = run_lints(text, &rules)
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column))
+        .collect();
+    assert_eq!(positions, vec![(1, 13), (2, 4), (3, 4)]);
+    assert_eq!(
+        rules.fix(text),
+        "title:: Page\n- a\n  b\n- \n- ```\n  code  \n  ```\n"
+    );
+}
+
+#[test]
+fn test_tables() {
+    let text = "- | Name | Qty |\n  |:-|-:|\n  | apple  | 3 |\n  | kiwi |\n- after  | not a table\n  ```\n  |a|b|\n  |-|-|\n  ```\n";
+    let rules = RuleSet::builtin().select(&["tables", "consecutive-spaces"]);
+    let diagnostics = run_lints(text, &rules);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+        (
+            diagnostics[0].rule,
+            diagnostics[0].line,
+            diagnostics[0].column
+        ),
+        ("tables", 1, 3)
+    );
+    assert_eq!(diagnostics[0].message, "Table columns are not aligned");
+    assert_eq!(diagnostics[1].rule, "consecutive-spaces");
+
+    let fixed = rules.fix(text);
+    assert_eq!(
+        fixed,
+        "- | Name  | Qty |\n  | :---- | --: |\n  | apple |   3 |\n  | kiwi  |     |\n- after | not a table\n  ```\n  |a|b|\n  |-|-|\n  ```\n"
+    );
+    assert!(run_lints(&fixed, &rules).is_empty());
+}
+
+#[test]
+fn test_empty_blocks() {
+    let rules = RuleSet::builtin().select(&["empty-blocks"]);
+    let text = "- a\n-\n- b\n  - \n- \n  -\n    - \n- c\n";
+    let lines: Vec<usize> = run_lints(text, &rules).iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![2, 4, 7]);
+    assert_eq!(rules.fix(text), "- a\n- b\n- c\n");
+
+    // An empty page is saved by Logseq with a single empty bullet
+    assert_eq!(run_lints("- ", &rules), vec![]);
+    assert_eq!(rules.fix("- \n  - "), "- ");
+}
+
+#[test]
+fn test_list_markers() {
+    let text = "* star\n\t+ plus\n- Steps:\n  1. open\n\n  2. close\n- 1. one\n- 2. two\n- 2023. a year\n- ```\n  1. code\n  ```\n";
+    let rules = RuleSet::builtin().select(&["list-markers"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+        .collect();
+    let numbered_line = "Numbered line should be a child block of a numbered list".to_string();
+    let numbered_item = "Numbered item should be a block of a numbered list".to_string();
+    assert_eq!(
+        found,
+        vec![
+            (1, 1, "Bullet should be -".to_string()),
+            (2, 2, "Bullet should be -".to_string()),
+            (4, 3, numbered_line.clone()),
+            (6, 3, numbered_line),
+            (7, 3, numbered_item.clone()),
+            (8, 3, numbered_item),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- star\n\t- plus\n- Steps:\n\t- open\n\t  logseq.order-list-type:: number\n\t- close\n\t  logseq.order-list-type:: number\n- one\n  logseq.order-list-type:: number\n- two\n  logseq.order-list-type:: number\n- 2023. a year\n- ```\n  1. code\n  ```\n"
+    );
+}
+
+#[test]
+fn test_indentation() {
+    use logseq::lint::{IndentUnit, Indentation};
+
+    // Without a unit, the first nested block sets it
+    let rules = RuleSet::builtin().select(&["indentation"]);
+    let mixed = "- a\n\t- b\n  - c\n\t\t- d\n\t    ```\n\t      code\n\t    ```\n";
+    let diagnostics = run_lints(mixed, &rules);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].line, 3);
+    assert_eq!(
+        diagnostics[0].message,
+        "Block at level 1 should be indented with 1 tab"
+    );
+    assert_eq!(rules.fix(mixed), mixed.replace("  - c", "\t- c"));
+
+    let spaces = RuleSet::new().with(Indentation {
+        unit: Some(IndentUnit::Spaces(2)),
+    });
+    assert_eq!(
+        spaces.fix("- a\n\t- b\n\t  more\n\t\t- c\n  - d"),
+        "- a\n  - b\n    more\n    - c\n  - d"
+    );
+    assert_eq!(
+        run_lints(" - a", &spaces)[0].message,
+        "Top-level block should not be indented"
+    );
+}
+
+#[test]
+fn test_continuation_indent() {
+    let text = "- Parent\ntext at column 1\n  fine\n\t- Child\n  wrong\n\t  right\n\t\tover\n\t   more is fine\n- Code\n  ```\nnot touched\n  ```\n";
+    let rules = RuleSet::builtin().select(&["continuation-indent"]);
+    let lines: Vec<usize> = run_lints(text, &rules).iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![2, 5, 7]);
+    assert_eq!(
+        rules.fix(text),
+        "- Parent\n  text at column 1\n  fine\n\t- Child\n\t  wrong\n\t  right\n\t  over\n\t   more is fine\n- Code\n  ```\nnot touched\n  ```\n"
+    );
+}
+
+#[test]
+fn test_nesting_depth() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text = "- a\n\t- b\n\t\t- c\n\t\t\t- d\n\t\t\t  more\n\t\t\t\t- e\n\t\t- f\n\t\t\t- g\n";
+    let mut rules = RuleSet::builtin().select(&["nesting-depth"]);
+    assert!(run_lints(text, &rules).is_empty());
+    rules.configure("nesting-depth", &Toml::parse("max-depth = 2")?)?;
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (4, 6, "Block nested 4 levels deep, more than 2".to_string()),
+            (8, 6, "Block nested 3 levels deep, more than 2".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- a\n\t- b\n\t\t- c\n\t\t- d\n\t\t  more\n\t\t- e\n\t\t- f\n\t\t- g\n"
+    );
+
+    rules.configure("nesting-depth", &Toml::parse("fix = \"ref\"")?)?;
+    let fixed = rules.fix("- a\n\t- b\n\t\t- c\n\t\t\t- d\n\t\t\t\t- e\n\t\t- f\n\t\t  id:: 65a1b2c3-0000-4000-8000-00000000000a\n\t\t\t- g\n");
+    let lines: Vec<&str> = fixed.lines().collect();
+    let id = lines[3].strip_prefix("\t\t  id:: ").unwrap();
+    assert_eq!(lines[4], format!("\t\t- d (({}))", id));
+    assert!(lines[5].starts_with("\t\t  id:: "));
+    assert!(lines[6].starts_with("\t\t- e (("));
+    assert_eq!(lines[9], "\t\t- g ((65a1b2c3-0000-4000-8000-00000000000a))");
+    assert!(rules
+        .configure("nesting-depth", &Toml::parse("fix = \"delete\"")?)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_headings() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text =
+        "- # Title\n\t- #### Skipped\n\t\t- # Shallower\n\t- ## Section\n- #tag is not a heading\n";
+    let rules = RuleSet::builtin().select(&["headings"]);
+    let messages: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                2,
+                4,
+                "Heading level 4 under a level 1 heading should be level 2".to_string()
+            ),
+            (
+                3,
+                5,
+                "Heading level 1 under a level 2 heading should be level 3".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- # Title\n\t- ## Skipped\n\t\t- ### Shallower\n\t- ## Section\n- #tag is not a heading\n"
+    );
+
+    let mut rules = RuleSet::builtin().select(&["headings"]);
+    rules.configure(
+        "headings",
+        &Toml::parse("max-level = 2\nmatch-depth = true")?,
+    )?;
+    assert_eq!(
+        run_lints("- ## Top\n\t- ## Child", &rules)[0].message,
+        "Heading level 2 should be 1 to match the outline depth"
+    );
+    assert_eq!(
+        rules.fix("- ## Top\n\t- ## Child\n\t\t- # Grandchild"),
+        "- # Top\n\t- ## Child\n\t\t- ## Grandchild"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_editor_properties() {
+    use logseq::lint::EditorProperties;
+
+    let text = "- parent\n  collapsed:: true\n  id:: 65a1b2c3\n  - collapsed:: true\n  - card\n    card-last-interval:: 4\n    card-repeats:: 2\n    card-next-schedule:: 2024-01-02\n    ```\n    collapsed:: in code\n    ```\n";
+    let rules = RuleSet::builtin().select(&["editor-properties"]);
+    let diagnostics = run_lints(text, &rules);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 3));
+    assert_eq!(
+        diagnostics[0].message,
+        "Editor state property collapsed:: can be removed"
+    );
+    assert_eq!(
+        rules.fix(text),
+        text.replacen("  collapsed:: true\n", "", 1)
+    );
+
+    let cards = RuleSet::new().with(EditorProperties {
+        remove: vec!["card-*".to_string()],
+        keep: vec!["card-next-schedule".to_string()],
+    });
+    let lines: Vec<usize> = run_lints(text, &cards).iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![6, 7]);
+}
+
+#[test]
+fn test_logbook() {
+    use logseq::lint::{Logbook, LogbookMode};
+
+    let clock = "CLOCK: [2024-01-02 Tue 10:00:00]--[2024-01-02 Tue 10:30:00] =>  00:30:00";
+    let empty = "CLOCK: [2024-01-02 Tue 11:00:00]--[2024-01-02 Tue 11:00:00] =>  00:00:00";
+    let text = format!(
+        "- DONE closed\n  :LOGBOOK:\n  {clock}\n  {clock}\n  {empty}\n  :END:\n- DONE unclosed\n  :LOGBOOK:\n  {clock}\n  - child\n"
+    );
+    let keep = RuleSet::builtin().select(&["logbook"]);
+    let diagnostics = run_lints(&text, &keep);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        (diagnostics[0].line, diagnostics[0].message.as_str()),
+        (8, "LOGBOOK drawer without :END:")
+    );
+    assert_eq!(
+        keep.fix(&text),
+        text.replace(
+            &format!("{clock}\n  - child"),
+            &format!("{clock}\n  :END:\n  - child")
+        )
+    );
+
+    let compact = RuleSet::new().with(Logbook {
+        mode: LogbookMode::Compact,
+    });
+    let lines: Vec<usize> = run_lints(&text, &compact).iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![4, 5, 8]);
+    assert!(compact
+        .fix(&text)
+        .starts_with(&format!("- DONE closed\n  :LOGBOOK:\n  {clock}\n  :END:\n")));
+
+    let strip = RuleSet::new().with(Logbook {
+        mode: LogbookMode::Strip,
+    });
+    assert_eq!(
+        strip.fix(&text),
+        "- DONE closed\n- DONE unclosed\n  - child\n"
+    );
+}
+
+#[test]
+fn test_properties_drawer() {
+    let text = ":PROPERTIES:\n:TITLE: Reading\n:END:\n\n- Read the book\n  :PROPERTIES:\n  :ID: 65a1b2c3-0000-4000-8000-000000000001\n  :Author: [[Frank Herbert]]\n  :EMPTY:\n  :END:\n  SCHEDULED: <2024-01-02 Tue>\n- Done\n  status:: done\n  :PROPERTIES:\n  :STATUS: open\n  :END:\n- Broken\n  :PROPERTIES:\n  not a property\n  :END:\n\t- Unclosed\n\t  :PROPERTIES:\n\t  :A: b\n";
+    let rules = RuleSet::builtin().select(&["properties-drawer"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    let convert = "PROPERTIES drawer, Logseq reads key:: value properties".to_string();
+    assert_eq!(
+        found,
+        vec![
+            (1, 1, convert.clone()),
+            (6, 3, convert.clone()),
+            (14, 3, convert),
+            (
+                18,
+                3,
+                "PROPERTIES drawer with lines that aren't properties".to_string()
+            ),
+            (22, 4, "PROPERTIES drawer without :END:".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "title:: Reading\n\n- Read the book\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n  author:: [[Frank Herbert]]\n  empty::\n  SCHEDULED: <2024-01-02 Tue>\n- Done\n  status:: done\n- Broken\n  :PROPERTIES:\n  not a property\n  :END:\n\t- Unclosed\n\t  :PROPERTIES:\n\t  :A: b\n"
+    );
+}
+
+#[test]
+fn test_fix_files_in_parallel() {
+    use assert_fs::prelude::*;
+    use logseq::batch::fix_files;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    let dirty = temp.child("dirty.md");
+    dirty.write_str("- a  b").unwrap();
+    let clean = temp.child("clean.md");
+    clean.write_str("- a b").unwrap();
+    let missing = temp.child("missing.md");
+
+    let paths = vec![
+        dirty.path().to_path_buf(),
+        clean.path().to_path_buf(),
+        missing.path().to_path_buf(),
+    ];
+    let reports = fix_files(&paths, &RuleSet::builtin());
+    assert_eq!(reports.len(), 3);
+    assert!(reports[0].changed);
+    assert_eq!(reports[0].diagnostics.len(), 1);
+    assert!(!reports[1].changed);
+    assert!(reports[2].error.is_some());
+    assert_eq!(std::fs::read_to_string(dirty.path()).unwrap(), "- a b");
+}
+
+#[test]
+fn test_fix_file_in_place_with_backup() {
+    use assert_fs::prelude::*;
+    use logseq::batch::fix_file_in_place;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    let page = temp.child("page.md");
+    page.write_str("- a  b\n").unwrap();
+    let report = fix_file_in_place(page.path(), &RuleSet::builtin(), true).unwrap();
+    assert!(report.changed);
+    page.assert("- a b\n");
+    temp.child("page.md.bak").assert("- a  b\n");
+
+    // Only the page and its backup remain, no temporary files
+    let mut names: Vec<String> = std::fs::read_dir(temp.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["page.md", "page.md.bak"]);
+}
+
+#[test]
+fn test_fix_files_with_encoding() {
+    use assert_fs::prelude::*;
+    use logseq::batch::{fix_files_with_encoding, lint_file};
+    use logseq::encoding::EncodingOptions;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    let bom = temp.child("bom.md");
+    bom.write_binary(b"\xef\xbb\xbftitle:: Page\n\n- a  b\n")
+        .unwrap();
+    let latin1 = temp.child("latin1.md");
+    latin1.write_binary(b"- caf\xe9  cr\xe8me\n").unwrap();
+    let paths = vec![bom.path().to_path_buf(), latin1.path().to_path_buf()];
+
+    // By default, the BOM is kept and files that aren't UTF-8 are skipped
+    let reports = fix_files_with_encoding(&paths, &RuleSet::builtin(), false, &Default::default());
+    assert!(reports[0].changed);
+    assert_eq!(reports[0].remaining[0].rule, "encoding");
+    bom.assert("\u{feff}title:: Page\n\n- a b\n");
+    let error = reports[1].error.as_ref().unwrap().to_string();
+    assert!(
+        error.contains("not valid UTF-8 (byte 0xE9 on line 1)"),
+        "{}",
+        error
+    );
+    assert_eq!(
+        std::fs::read(latin1.path()).unwrap(),
+        b"- caf\xe9  cr\xe8me\n"
+    );
+
+    // Linting reads them anyway
+    let linted = lint_file(latin1.path(), &RuleSet::builtin()).unwrap();
+    let rules: Vec<&str> = linted.diagnostics.iter().map(|d| d.rule).collect();
+    assert_eq!(rules, vec!["encoding", "consecutive-spaces"]);
+
+    let options = EncodingOptions {
+        strip_bom: true,
+        transcode: true,
+    };
+    let reports = fix_files_with_encoding(&paths, &RuleSet::builtin(), false, &options);
+    assert!(reports
+        .iter()
+        .all(|report| report.changed && report.error.is_none()));
+    assert!(reports.iter().all(|report| report.remaining.is_empty()));
+    bom.assert("title:: Page\n\n- a b\n");
+    latin1.assert("- caf\u{e9} cr\u{e8}me\n");
+}
+
+#[test]
+fn test_task_markers() {
+    use logseq::lint::TaskMarkers;
+    use logseq::tasks::Workflow;
+
+    let text = "- todo buy milk\n\t- Call mom LATER\n- DOING write tests\n- Nothing to do";
+    let default = RuleSet::new().with(TaskMarkers::default());
+    assert_eq!(
+        default.fix(text),
+        "- todo buy milk\n\t- LATER Call mom\n- DOING write tests\n- Nothing to do"
+    );
+    let uppercase = RuleSet::new().with(TaskMarkers {
+        fix_lowercase: true,
+        ..TaskMarkers::default()
+    });
+    assert_eq!(
+        uppercase.fix(text),
+        "- TODO buy milk\n\t- LATER Call mom\n- DOING write tests\n- Nothing to do"
+    );
+
+    let now_later = RuleSet::new().with(TaskMarkers {
+        workflow: Some(Workflow::NowLater),
+        fix_lowercase: true,
+    });
+    let messages: Vec<String> = run_lints(text, &now_later)
+        .into_iter()
+        .map(|d| format!("{}:{}", d.line, d.message))
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "1:Task marker TODO should be uppercase",
+            "2:Task marker LATER should be at the start of the block",
+            "3:Use NOW instead of DOING",
+        ]
+    );
+    assert_eq!(
+        now_later.fix(text),
+        "- LATER buy milk\n\t- LATER Call mom\n- NOW write tests\n- Nothing to do"
+    );
+}
+
+#[test]
+fn test_task_markers_leave_prose_alone() -> anyhow::Result<()> {
+    use logseq::lint::TaskMarkers;
+    use logseq::toml::Toml;
+
+    let prose = "- now I see\n- done deal\n- wait for it\n- doing fine\n- canceled plans\n- later that day\n";
+    let rules = RuleSet::new().with(TaskMarkers::default());
+    assert_eq!(rules.fix(prose), prose);
+    assert_eq!(run_lints(prose, &rules).len(), 6);
+
+    let mut rules = RuleSet::builtin().select(&["task-markers"]);
+    rules.configure("task-markers", &Toml::parse("fix-lowercase = true")?)?;
+    assert_eq!(
+        rules.fix(prose),
+        "- NOW I see\n- DONE deal\n- WAIT for it\n- DOING fine\n- CANCELED plans\n- LATER that day\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_checkboxes() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text = "- [ ] buy milk\n\t- [x] nested and done\n\t- [X]\n- [-] dropped\n- [link](url)\n- [ ]not a checkbox\n";
+    let rules = RuleSet::builtin().select(&["checkboxes"]);
+    let diagnostics = run_lints(text, &rules);
+    let found: Vec<(usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, "Checkbox [ ] should be a TODO task"),
+            (2, "Checkbox [x] should be a DONE task"),
+            (3, "Checkbox [X] should be a DONE task"),
+            (4, "Checkbox [-] should be a CANCELED task"),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- TODO buy milk\n\t- DONE nested and done\n\t- DONE\n- CANCELED dropped\n- [link](url)\n- [ ]not a checkbox\n"
+    );
+
+    let mut rules = RuleSet::builtin().select(&["checkboxes"]);
+    rules.configure("checkboxes", &Toml::parse("workflow = \"now\"")?)?;
+    assert_eq!(rules.fix("- [ ] a\n- [/] b"), "- LATER a\n- NOW b");
+    Ok(())
+}
+
+#[test]
+fn test_priorities() {
+    let text = "- TODO [#A] fine\n- TODO pay rent [#b]\n\t- LATER [#B] call [#C] mom\n- DONE [#c]\n- Note [#A] about `[#B]`\n- TODO `[#a]` in code\n";
+    let rules = RuleSet::builtin().select(&["priorities"]);
+    let found: Vec<(usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (2, "Priority [#b] should be uppercase".to_string()),
+            (3, "Duplicate priority, keep only [#B]".to_string()),
+            (4, "Priority [#c] should be uppercase".to_string()),
+            (5, "Priority [#A] on a block that isn't a task".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- TODO [#A] fine\n- TODO [#B] pay rent\n\t- LATER [#B] call mom\n- DONE [#C]\n- Note [#A] about `[#B]`\n- TODO `[#a]` in code\n"
+    );
+    assert_eq!(rules.fix("- NOW call mom [#A]"), "- NOW [#A] call mom");
+    assert_eq!(
+        run_lints("- NOW call mom [#A]", &rules)[0].message,
+        "Priority [#A] should come right after NOW"
+    );
+}
+
+#[test]
+fn test_timestamps() {
+    use logseq::lint::Timestamps;
+
+    let text = "- TODO a\n  SCHEDULED: <2024-01-02 Wed .+1d>\n- TODO b\n  DEADLINE: <2024-02-30 Fri>\n\t- TODO c\n\t  SCHEDULED: <2024-01-02 Tue 25:00>\n- TODO d\n  DEADLINE: <tomorrow>";
+    let rules = RuleSet::new().with(Timestamps);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (2, 3, "2024-01-02 is a Tue, not a Wed".to_string()),
+            (4, 3, "Impossible date 2024-02-30".to_string()),
+            (6, 4, "Impossible time 25:00".to_string()),
+            (8, 3, "Malformed timestamp <tomorrow>".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        text.replace("2024-01-02 Wed", "2024-01-02 Tue")
+    );
+}
+
+#[test]
+fn test_repeaters() {
+    let text = "- TODO a\n  SCHEDULED: <2024-01-02 Tue 1d+>\n- TODO b\n  DEADLINE: <2024-01-02 Tue +1x>\n- TODO c\n  SCHEDULED: <2024-01-02 Tue .+1d>\n  DEADLINE: <2024-01-05 Fri ++1W>\n- DONE d\n  DEADLINE: <2024-01-02 Tue +1m>\n- TODO e\n  SCHEDULED: <2024-01-02 Tue ++2w>";
+    let rules = RuleSet::builtin().select(&["timestamps", "repeaters"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (2, 30, "Repeater 1d+ should be written +1d".to_string()),
+            (
+                4,
+                29,
+                "Repeater +1x has an invalid unit x, expected h, d, w, m or y".to_string()
+            ),
+            (7, 29, "Repeater ++1W should be written ++1w".to_string()),
+            (7, 29, "Repeater ++1w conflicts with .+1d".to_string()),
+            (
+                9,
+                29,
+                "Repeater +1m on a DONE task without a SCHEDULED date never repeats".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        text.replace("1d+", "+1d").replace("++1W", "++1w")
+    );
+}
+
+#[test]
+fn test_block_times() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text = "- 9:5 Coffee\n- **10:30** Standup\n\t- 09.45 Notes\n\t- 9.45 kg\n- 8:15pm Dinner\n- 12:01am Late\n- 3.5 kg\n";
+    let mut rules = RuleSet::builtin().select(&["block-times"]);
+    let found: Vec<(usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, "Write the time 9:5 as **09:05**".to_string()),
+            (3, "Write the time 09.45 as **09:45**".to_string()),
+            (5, "Write the time 8:15pm as **20:15**".to_string()),
+            (6, "Write the time 12:01am as **00:01**".to_string()),
+        ]
+    );
+    let journal: Vec<(usize, String)> = rules
+        .lint_page(text, Some("Jan 2nd, 2024"))
+        .diagnostics
+        .into_iter()
+        .map(|d| (d.line, d.message))
+        .filter(|(_, message)| !message.starts_with("Write"))
+        .collect();
+    assert_eq!(
+        journal,
+        vec![(
+            6,
+            "**00:01** is before **20:15** of a block above".to_string()
+        )]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- **09:05** Coffee\n- **10:30** Standup\n\t- **09:45** Notes\n\t- 9.45 kg\n- **20:15** Dinner\n- **00:01** Late\n- 3.5 kg\n"
+    );
+
+    rules.configure("block-times", &Toml::parse("format = \"[H:MM]\"")?)?;
+    assert_eq!(
+        rules.fix("- 09:05 Coffee\n- [10:30] Standup\n"),
+        "- [9:05] Coffee\n- [10:30] Standup\n"
+    );
+    assert!(rules
+        .configure("block-times", &Toml::parse("format = \"HH\"")?)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_done_metadata() {
+    use logseq::lint::DoneMetadata;
+    use logseq::toml::Toml;
+
+    let text = "- DONE a\n  :LOGBOOK:\n  CLOCK: [2024-01-02 Tue 10:00:00]--[2024-01-02 Tue 11:00:00] =>  01:00:00\n  :END:\n- DONE b\n  done:: 1704189600000\n\t- DONE c\n- TODO d\n";
+    let mut rules = RuleSet::new().with(DoneMetadata::default());
+    let found: Vec<(usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                5,
+                "DONE task without a CLOCK entry in its :LOGBOOK:".to_string()
+            ),
+            (
+                7,
+                "DONE task without a CLOCK entry in its :LOGBOOK:".to_string()
+            ),
+        ]
+    );
+
+    rules
+        .configure(
+            "done-metadata",
+            &Toml::parse("completion = \"property\"").unwrap(),
+        )
+        .unwrap();
+    let lines: Vec<usize> = run_lints(text, &rules).iter().map(|d| d.line).collect();
+    assert_eq!(lines, vec![1, 7]);
+    assert!(rules
+        .configure(
+            "done-metadata",
+            &Toml::parse("completion = \"git\"").unwrap()
+        )
+        .is_err());
+}
+
+#[test]
+fn test_title_heading() {
+    use assert_fs::prelude::*;
+    use logseq::batch::lint_file;
+
+    let text = "- # Project\n\t- Goals\n\t  more\n\t\t- Detail\n\t- Notes\n- Other\n";
+    let rules = RuleSet::builtin().select(&["title-heading"]);
+    assert!(rules.lint(text).diagnostics.is_empty());
+    let result = rules.lint_page(text, Some("project"));
+    assert_eq!(
+        result.diagnostics[0].message,
+        "Heading repeats the page title"
+    );
+    assert_eq!(
+        rules.fix_page(text, Some("project")),
+        "- Goals\n  more\n\t- Detail\n- Notes\n- Other\n"
+    );
+    assert_eq!(rules.fix_page(text, Some("Other")), text);
+    let titled = "title:: My Project\n\n- ## my project\n- Notes";
+    assert_eq!(
+        rules.fix_page(titled, Some("my_project")),
+        "title:: My Project\n\n- Notes"
+    );
+    assert_eq!(
+        rules.fix_page("- # project\n  Details", Some("project")),
+        "- # project\n  Details"
+    );
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    let page = temp.child("pages/project___plans.md");
+    page.write_str("- alias:: plans\n- # Project/Plans\n")
+        .unwrap();
+    let result = lint_file(page.path(), &rules).unwrap();
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(result.diagnostics[0].line, 2);
+}
+
+#[test]
+fn test_punctuation() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text = "- \u{201C}Quoted\u{201D} \u{2014} it\u{2019}s\u{2026}\n  a\u{00A0}b `\u{2019}code\u{2019}`\n\t- author:: O\u{2019}Brien\n\t- [[Don\u{2019}t]] #it\u{2019}s 1\u{2013}2\n";
+    let mut rules = RuleSet::builtin().select(&["punctuation"]);
+    assert!(rules.lint(text).diagnostics.is_empty());
+    let options = "convert = [\"quotes\", \"dashes\", \"spaces\", \"ellipses\"]";
+    rules.configure("punctuation", &Toml::parse(options)?)?;
+    let diagnostics = rules.lint(text).diagnostics;
+    let found: Vec<(usize, usize, &str)> = diagnostics
+        .iter()
+        .map(|d| (d.line, d.column, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, 3, "Curly quote \u{201C} should be \""),
+            (2, 4, "No-break space should be a plain space"),
+            (4, 21, "En dash \u{2013} should be -"),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- \"Quoted\" -- it's...\n  a b `\u{2019}code\u{2019}`\n\t- author:: O\u{2019}Brien\n\t- [[Don\u{2019}t]] #it\u{2019}s 1-2\n"
+    );
+
+    rules.configure("punctuation", &Toml::parse("convert = [\"spaces\"]")?)?;
+    assert_eq!(rules.lint(text).diagnostics.len(), 1);
+    let unknown = Toml::parse("convert = [\"apostrophes\"]")?;
+    assert!(rules.configure("punctuation", &unknown).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rule_docs() {
+    // Rules that find nothing until configured, or given the pages of the graph
+    let opt_in = ["punctuation", "property-schema", "link-style", "spelling"];
+    for info in RuleSet::builtin().infos() {
+        assert!(
+            !info.description.is_empty(),
+            "{} has no description",
+            info.id
+        );
+        assert!(!info.examples.is_empty(), "{} has no examples", info.id);
+        if opt_in.contains(&info.id.as_str()) {
+            continue;
+        }
+        for example in &info.examples {
+            assert!(!example.problems.is_empty(), "{} finds nothing", info.id);
+            assert_eq!(
+                example.fixed.is_some(),
+                info.fixable,
+                "{} fixes {:?}",
+                info.id,
+                example.fixed
+            );
+        }
+    }
+}
+
+#[test]
+fn test_report_of_fixed_files() {
+    use assert_fs::prelude::*;
+    use logseq::batch::fix_files_with;
+    use logseq::report::Report;
+
+    let temp = assert_fs::TempDir::new().unwrap();
+    let page = temp.child("page.md");
+    page.write_str("- a  b\n- TODO task\n").unwrap();
+    let mut rules = RuleSet::builtin();
+    rules.add(NoTodo);
+    let report = Report::from_file_reports(&fix_files_with(&[page.to_path_buf()], &rules, true));
+
+    let findings: Vec<(&str, usize, bool)> = report
+        .findings
+        .iter()
+        .map(|finding| (finding.rule.as_str(), finding.line, finding.fixed))
+        .collect();
+    assert_eq!(
+        findings,
+        vec![("consecutive-spaces", 1, true), ("no-todo", 2, false)]
+    );
+    assert_eq!(report.files.len(), 1);
+    assert!(report.files[0].changed);
+    assert!(report.to_json().contains(r#""rule":"no-todo""#));
+    page.assert("- a  b\n- TODO task\n");
+}
+
+#[test]
+fn test_property_order() {
+    use logseq::lint::PropertyOrder;
+
+    let text = "type:: book\ntags:: reading\ntitle:: Dune\n\n- tags:: scifi\n  author:: Herbert\n- quote\n  year:: 1965\n  alias:: arrakis\n  text\n  b:: 2\n  a:: 1\n";
+    let rules = RuleSet::builtin().select(&["property-order"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                1,
+                "Properties should be ordered as title, tags, type".to_string()
+            ),
+            (
+                8,
+                3,
+                "Properties should be ordered as alias, year".to_string()
+            ),
+            (11, 3, "Properties should be ordered as a, b".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "title:: Dune\ntags:: reading\ntype:: book\n\n- tags:: scifi\n  author:: Herbert\n- quote\n  alias:: arrakis\n  year:: 1965\n  text\n  a:: 1\n  b:: 2\n"
+    );
+
+    let first_only = RuleSet::new().with(PropertyOrder {
+        first: vec!["year".to_string()],
+        alphabetical: false,
+    });
+    let fixed = first_only.fix("- quote\n  b:: 2\n  year:: 1965\n  a:: 1\n");
+    assert_eq!(fixed, "- quote\n  year:: 1965\n  b:: 2\n  a:: 1\n");
+}
+
+#[test]
+fn test_link_style() {
+    use logseq::graph::Graph;
+    use logseq::lint::{LinkStyle, PageLinkStyle};
+    use std::path::Path;
+
+    let text = "- see [Index](Index) and [the sub page](project%2Fsub), [site](https://x.com), ![img](../assets/a.png), [file](notes.txt)\n  `[Index](Index)` [[Project]]\n";
+    let mut rules = RuleSet::builtin().select(&["link-style"]);
+    assert!(run_lints(text, &rules).is_empty());
+
+    rules.use_graph(&Graph::scan(Path::new("tests/fixtures/graph")).unwrap());
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, 7, "Write the link as [[Index]]".to_string()),
+            (
+                1,
+                26,
+                "Write the link as [the sub page]([[project/sub]])".to_string()
+            ),
+        ]
+    );
+    let fixed = rules.fix(text);
+    assert!(fixed.starts_with("- see [[Index]] and [the sub page]([[project/sub]]), [site]"));
+
+    let markdown = RuleSet::new().with(PageLinkStyle {
+        style: LinkStyle::Markdown,
+        pages: None,
+    });
+    assert_eq!(
+        markdown.fix("- [[Index]] #[[tag]] [sub]([[project/sub]]) {{embed [[Project]]}}\n"),
+        "- [Index](Index) #[[tag]] [sub](project/sub) {{embed [[Project]]}}\n"
+    );
+}
+
+#[test]
+fn test_link_normalization() {
+    let text = "alias:: Cafe\u{301}\n\n- [[Cafe\u{301}]] #re\u{301}sume\u{301} [[caf\u{e9}]] `[[e\u{301}]]`\n";
+    let rules = RuleSet::builtin().select(&["link-normalization"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                9,
+                "Caf\u{e9} isn't in Unicode normalization form C (NFC)".to_string()
+            ),
+            (
+                3,
+                5,
+                "Caf\u{e9} isn't in Unicode normalization form C (NFC)".to_string()
+            ),
+            (
+                3,
+                14,
+                "r\u{e9}sum\u{e9} isn't in Unicode normalization form C (NFC)".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "alias:: Caf\u{e9}\n\n- [[Caf\u{e9}]] #r\u{e9}sum\u{e9} [[caf\u{e9}]] `[[e\u{301}]]`\n"
+    );
+}
+
+#[test]
+fn test_dynamic_variables() {
+    let text = "- template:: daily\n\t- <% today %> <%time%> <% todya %> `<%today%>`\n\t- {{date:YYYY-MM-DD}} in {{title}}\n";
+    let rules = RuleSet::builtin().select(&["dynamic-variables"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                2,
+                16,
+                "Write <%time%> as <% time %>, Logseq needs the spaces".to_string()
+            ),
+            (
+                2,
+                25,
+                "Unknown dynamic variable <% todya %>, Logseq inserts it as it is".to_string()
+            ),
+            (
+                3,
+                4,
+                "{{date:YYYY-MM-DD}} is an Obsidian template variable, write <% today %> instead"
+                    .to_string()
+            ),
+            (
+                3,
+                27,
+                "{{title}} is an Obsidian template variable, write <% current page %> instead"
+                    .to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- template:: daily\n\t- <% today %> <% time %> <% todya %> `<%today%>`\n\t- <% today %> in <% current page %>\n"
+    );
+}
+
+#[test]
+fn test_queries() {
+    use logseq::graph::Graph;
+    use std::path::Path;
+
+    let text = "- {{query (and [[Index]] (task TODO)}}\n- {{query (and [[Missing]] (propery alias x))}} `{{query (x)}}`\n- {{query (page-property alias \"Other Name\")}}\n- #+BEGIN_QUERY\n  {:title \"Tasks\"\n   :query [:find (pull ?b [*]) :where [?p :block/name \"nothing\"]]}\n  #+END_QUERY\n";
+    let mut rules = RuleSet::builtin().select(&["queries"]);
+    let found = |rules: &RuleSet| -> Vec<(usize, usize, String)> {
+        run_lints(text, rules)
+            .into_iter()
+            .map(|d| (d.line, d.column, d.message))
+            .collect()
+    };
+    assert_eq!(
+        found(&rules),
+        vec![
+            (
+                1,
+                11,
+                "Unbalanced parentheses: ( is never closed".to_string()
+            ),
+            (2, 28, "Unknown query operator propery".to_string()),
+        ]
+    );
+
+    rules.use_graph(&Graph::scan(Path::new("tests/fixtures/graph")).unwrap());
+    assert_eq!(
+        found(&rules),
+        vec![
+            (
+                1,
+                11,
+                "Unbalanced parentheses: ( is never closed".to_string()
+            ),
+            (2, 16, "Query refers to missing page Missing".to_string()),
+            (2, 28, "Unknown query operator propery".to_string()),
+            (4, 3, "Query refers to missing page nothing".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_callouts() {
+    let text = "- > [!warning] Careful\n  > first line\n  >\n  > second line\n- Pasted:\n  > [!faq]-\n  > Why?\n  after\n- > plain quote\n";
+    let rules = RuleSet::builtin().select(&["callouts"]);
+    let diagnostics = run_lints(text, &rules);
+    let found: Vec<(usize, usize, &str)> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            (
+                diagnostic.line,
+                diagnostic.column,
+                diagnostic.message.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                3,
+                "Obsidian callout should be a #+BEGIN_WARNING admonition"
+            ),
+            (
+                6,
+                3,
+                "Obsidian callout should be a #+BEGIN_WARNING admonition"
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- #+BEGIN_WARNING\n  **Careful**\n  first line\n\n  second line\n  #+END_WARNING\n- Pasted:\n  #+BEGIN_WARNING\n  Why?\n  #+END_WARNING\n  after\n- > plain quote\n"
+    );
+}
+
+#[test]
+fn test_admonitions() {
+    let text = "- #+BEGIN_NOTE\n  Remember this\n\t- a child\n\t  #+END_NOTE\n- #+BEGIN_TIP\n  #+BEGIN_QUOTE\n  quoted\n  #+END_TIP\n- #+END_WARNING\n- #+begin_quote\n  unclosed\n  ```\n  #+END_QUOTE\n  ```\n- #+BEGIN_QUOTE\n  fine\n  #+END_QUOTE\n";
+    let rules = RuleSet::builtin().select(&["admonitions"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, 3, "#+BEGIN_NOTE without #+END_NOTE".to_string()),
+            (
+                4,
+                4,
+                "#+END_NOTE of the #+BEGIN_NOTE of line 1 is in another bullet, the content in between is outside the block".to_string()
+            ),
+            (
+                8,
+                3,
+                "#+END_TIP before the #+END_QUOTE of the #+BEGIN_QUOTE of line 6".to_string()
+            ),
+            (9, 3, "#+END_WARNING without #+BEGIN_WARNING".to_string()),
+            (10, 3, "#+BEGIN_QUOTE without #+END_QUOTE".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- #+BEGIN_NOTE\n  Remember this\n  #+END_NOTE\n\t- a child\n- #+BEGIN_TIP\n  #+BEGIN_QUOTE\n  quoted\n  #+END_TIP\n- #+END_WARNING\n- #+begin_quote\n  unclosed\n  ```\n  #+END_QUOTE\n  ```\n  #+END_QUOTE\n- #+BEGIN_QUOTE\n  fine\n  #+END_QUOTE\n"
+    );
+}
+
+#[test]
+fn test_footnotes() -> anyhow::Result<()> {
+    use logseq::toml::Toml;
+
+    let text = "- Dune[^1] is a novel [^2]\n  [^2]: Published in 1965\n\t- child\n- Again[^1] and [^3] `[^1]`\n- [^1]: By Frank Herbert\n- [^4]: Unused\n";
+    let mut rules = RuleSet::builtin().select(&["footnotes"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    let footnote = |label| format!("Footnote [^{}], Logseq doesn't render footnotes", label);
+    assert_eq!(
+        found,
+        vec![
+            (1, 7, footnote(1)),
+            (1, 23, footnote(2)),
+            (4, 8, footnote(1)),
+            (4, 17, "Footnote [^3] without a definition".to_string()),
+            (
+                6,
+                3,
+                "Footnote definition [^4] that nothing refers to".to_string()
+            ),
+        ]
+    );
+
+    let fixed = rules.fix(text);
+    let uuids: Vec<&str> = fixed
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("id:: "))
+        .collect();
+    assert_eq!(uuids.len(), 2);
+    assert_eq!(
+        fixed,
+        format!(
+            "- Dune (({a})) is a novel (({b}))\n\t- By Frank Herbert\n\t  id:: {a}\n\t- Published in 1965\n\t  id:: {b}\n\t- child\n- Again (({a})) and [^3] `[^1]`\n- [^4]: Unused\n",
+            a = uuids[0],
+            b = uuids[1]
+        )
+    );
+
+    rules.configure("footnotes", &Toml::parse("style = \"inline\"")?)?;
+    assert_eq!(
+        rules.fix(text),
+        "- Dune (By Frank Herbert) is a novel (Published in 1965)\n\t- child\n- Again (By Frank Herbert) and [^3] `[^1]`\n- [^4]: Unused\n"
+    );
+    assert!(rules
+        .configure("footnotes", &Toml::parse("style = \"end\"")?)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_citations() -> anyhow::Result<()> {
+    use assert_fs::prelude::*;
+    use logseq::graph::Graph;
+    use logseq::toml::Toml;
+
+    let page = "item-type:: [[journalArticle]]\nzotero-link:: zotero://items/ABCD1234\nzotero-key::\n\n- Notes\n";
+    let mut rules = RuleSet::builtin().select(&["citations"]);
+    let found: Vec<(usize, String)> = run_lints(page, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (1, "Citation page without a title:: property".to_string()),
+            (
+                2,
+                "Property zotero-link:: has a zotero:// link that doesn't select an item or open a PDF"
+                    .to_string()
+            ),
+            (3, "Property zotero-key:: is empty".to_string()),
+        ]
+    );
+    let valid = "title:: Dune\nitem-type:: [[book]]\nzotero-link:: [Local](zotero://select/library/items/ABCD1234)\n";
+    assert!(run_lints(valid, &rules).is_empty());
+    rules.configure(
+        "citations",
+        &Toml::parse("required = [\"title\", \"authors\"]")?,
+    )?;
+    assert_eq!(run_lints(valid, &rules).len(), 1);
+
+    // Cite keys without a page, with the graph
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("pages/@herbert1965.md")
+        .write_str("title:: @herbert1965\nitem-type:: [[book]]\n")?;
+    let text =
+        "- As [[@herbert1965]] and [[@Asimov1951]] wrote\n\t- `[[@code]]` [[@HERBERT1965]]\n";
+    assert!(run_lints(text, &rules).is_empty());
+    rules.use_graph(&Graph::scan(temp.path())?);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![(1, 27, "Citation [[@Asimov1951]] without a page".to_string())]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_spelling() -> anyhow::Result<()> {
+    use assert_fs::prelude::*;
+    use logseq::graph::Graph;
+    use logseq::toml::Toml;
+
+    let temp = assert_fs::TempDir::new()?;
+    temp.child("en.dic")
+        .write_str("6\nbook/S\nthe\nis\na\non\nread\n")?;
+    temp.child("en.aff").write_str("SFX S Y 1\nSFX S 0 s .\n")?;
+    temp.child("pages/Dictionary.md")
+        .write_str("- Logseq\n- outliner\n")?;
+    let text = "tags:: mispeled\n\n- The books is on teh shelf, `codde` [[Pagge]] #tagg https://exampel.com\n\t- TODO Read Logseq’s book 2nd, NASA iPhone\n  note:: wrongg\n";
+
+    let mut rules = RuleSet::builtin().select(&["spelling"]);
+    assert!(run_lints(text, &rules).is_empty());
+    let wordlist = temp.child("en.dic").path().display().to_string();
+    rules.configure(
+        "spelling",
+        &Toml::parse(&format!(
+            "wordlists = [{:?}]\nwords = [\"shelf\"]",
+            wordlist
+        ))?,
+    )?;
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, 19, "Unknown word teh".to_string()),
+            (4, 14, "Unknown word Logseq’s".to_string()),
+        ]
+    );
+    rules.use_graph(&Graph::scan(temp.path())?);
+    assert_eq!(run_lints(text, &rules).len(), 1);
+    assert!(rules
+        .configure("spelling", &Toml::parse("language = \"xx_NOPE\"")?)
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tag_brackets() {
+    use logseq::lint::TagBrackets;
+
+    let text = "- read #[[Dune]] #[[science fiction]] #[[a,b]] `#[[code]]` [[Books]] [[Sci Fi]]\n  tags:: [[x]]\n- [[Only]] [[links]]\n";
+    let rules = RuleSet::builtin().select(&["tag-brackets"]);
+    let found: Vec<(usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![(8, "Unnecessary brackets in tag, use #Dune".to_string())]
+    );
+
+    let trailing = RuleSet::new().with(TagBrackets {
+        trailing_links: true,
+    });
+    assert_eq!(
+        trailing.fix(text),
+        "- read #Dune #[[science fiction]] #[[a,b]] `#[[code]]` #Books #[[Sci Fi]]\n  tags:: [[x]]\n- [[Only]] [[links]]\n"
+    );
+}
+
+#[test]
+fn test_tag_brackets_before_text() {
+    let rules = RuleSet::builtin().select(&["tag-brackets"]);
+    for text in ["- #[[foo]]bar\n", "- #[[foo]]-x\n", "- #[[foo]].bar\n"] {
+        assert!(run_lints(text, &rules).is_empty(), "{:?}", text);
+        assert_eq!(rules.fix(text), text);
+    }
+    assert_eq!(
+        rules.fix("- #[[foo]], #[[bar]]. #[[baz]]) #[[end]]\n"),
+        "- #foo, #bar. #baz) #end\n"
+    );
+}
+
+#[test]
+fn test_malformed_refs() {
+    let text = "- read [[Dune] `[[code]`\n- and [Foundation]] too\n- see ((65a1b2c3-0000-4000-8000-000000000001) and (65a1b2c3-0000-4000-8000-000000000002))\n  id:: 65a1b2c3-0000-4000-8000-000000000003\n- [[a] or [[b]] and [[open\n- [[Books [[Dune]]]] [[ok]]\n";
+    let rules = RuleSet::builtin().select(&["malformed-refs"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                8,
+                "Page reference is missing a bracket, use [[Dune]]".to_string()
+            ),
+            (
+                2,
+                7,
+                "Page reference is missing a bracket, use [[Foundation]]".to_string()
+            ),
+            (
+                3,
+                7,
+                "Malformed block reference, use ((65a1b2c3-0000-4000-8000-000000000001))"
+                    .to_string()
+            ),
+            (
+                3,
+                51,
+                "Malformed block reference, use ((65a1b2c3-0000-4000-8000-000000000002))"
+                    .to_string()
+            ),
+            (5, 3, "Page reference [[a] is missing a bracket".to_string()),
+            (5, 21, "Page reference [[open is never closed".to_string()),
+            (
+                6,
+                3,
+                "Nested page references in [[Books [[Dune]]]]".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- read [[Dune]] `[[code]`\n- and [[Foundation]] too\n- see ((65a1b2c3-0000-4000-8000-000000000001)) and ((65a1b2c3-0000-4000-8000-000000000002))\n  id:: 65a1b2c3-0000-4000-8000-000000000003\n- [[a] or [[b]] and [[open\n- [[Books [[Dune]]]] [[ok]]\n"
+    );
+}
+
+#[test]
+fn test_front_matter() {
+    use logseq::frontmatter::DateStyle;
+    use logseq::lint::FrontMatter;
+
+    let text =
+        "---\ntitle: Dune\ntags:\n  - books\n  - scifi\ncreated: 2024-01-02\n---\n\n- content\n";
+    let rules = RuleSet::builtin().select(&["front-matter"]);
+    let diagnostics = run_lints(text, &rules);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 1));
+    assert_eq!(
+        RuleSet::builtin().fix(text),
+        "title:: Dune\ntags:: books, scifi\ncreated:: 2024-01-02\n\n- content\n"
+    );
+
+    let journal = RuleSet::new().with(FrontMatter {
+        dates: DateStyle::Journal,
+        ..FrontMatter::default()
+    });
+    assert_eq!(
+        journal.fix("---\ncreated: 2024-01-02\ntitle: Other\n---\ntitle:: Dune\n\n- content\n"),
+        "created:: [[Jan 2nd, 2024]]\ntitle:: Dune\n\n- content\n"
+    );
+    assert!(run_lints("- a\n---\n- b\n", &rules).is_empty());
+}
+
+#[test]
+fn test_page_properties() {
+    let rules = RuleSet::builtin().select(&["page-properties"]);
+    let text = "# Dune\n\nalias:: dune\ntags:: books\n\n- Review\n";
+    let diagnostics = run_lints(text, &rules);
+    let positions: Vec<(usize, usize)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column))
+        .collect();
+    assert_eq!(positions, vec![(3, 1), (4, 1)]);
+    assert_eq!(
+        rules.fix(text),
+        "alias:: dune\ntags:: books\n\n# Dune\n\n- Review\n"
+    );
+
+    // Blocks of properties after a heading join the page properties, once
+    let text = "title:: Dune\n\n- # Dune\n- title:: Dune\n  tags:: books\n- Review\n";
+    assert_eq!(run_lints(text, &rules).len(), 2);
+    assert_eq!(
+        rules.fix(text),
+        "title:: Dune\ntags:: books\n\n- # Dune\n- Review\n"
+    );
+    let text = "- title:: Dune\n- ## Dune\n- tags:: books\n- Review\n";
+    assert_eq!(
+        rules.fix(text),
+        "- title:: Dune\n  tags:: books\n- ## Dune\n- Review\n"
+    );
+
+    // Another value is only reported, and blocks with an id can be referred to
+    let text = "title:: Dune\n\n- # Dune\n- title:: Arrakis\n- id:: 65a1b2c3\n- Review\n";
+    let diagnostics = run_lints(text, &rules);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (4, 3));
+    assert!(diagnostics[0].message.contains("different value"));
+    assert_eq!(rules.fix(text), text);
+    assert!(run_lints("- # Dune\n\t- tags:: books\n", &rules).is_empty());
+    assert!(run_lints("- Review\n- tags:: books\n", &rules).is_empty());
+}
+
+#[test]
+fn test_suppressions() {
+    let rules = RuleSet::builtin().select(&["consecutive-spaces", "tag-brackets"]);
+    let text = "- a  b #[[tag]]\n  lsd-disable:: consecutive-spaces\n\t- child  c\n- <!-- lsd-disable-next-block -->\n- next  d #[[tag]]\n\t- deep  e\n- last  f\n  `<!-- lsd-disable -->`\n";
+    let result = rules.lint(text);
+    let found = |diagnostics: &[logseq::lint::Diagnostic]| -> Vec<(usize, &str)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.rule))
+            .collect()
+    };
+    assert_eq!(
+        found(&result.diagnostics),
+        vec![(1, "tag-brackets"), (7, "consecutive-spaces")]
+    );
+    assert_eq!(
+        found(&result.suppressed),
+        vec![
+            (1, "consecutive-spaces"),
+            (3, "consecutive-spaces"),
+            (5, "consecutive-spaces"),
+            (5, "tag-brackets"),
+            (6, "consecutive-spaces"),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "- a  b #tag\n  lsd-disable:: consecutive-spaces\n\t- child  c\n- <!-- lsd-disable-next-block -->\n- next  d #[[tag]]\n\t- deep  e\n- last f\n  `<!-- lsd-disable -->`\n"
+    );
+
+    let page = "<!-- lsd-disable tag-brackets, consecutive-spaces -->\n- a  b #[[tag]]\n";
+    assert!(rules.lint(page).diagnostics.is_empty());
+    assert_eq!(rules.lint(page).suppressed.len(), 2);
+    assert_eq!(rules.fix(page), page);
+    let page = "lsd-disable:: tag-brackets\n\n- a  b #[[tag]]\n";
+    assert_eq!(
+        rules.fix(page),
+        "lsd-disable:: tag-brackets\n\n- a b #[[tag]]\n"
+    );
+}
+
+#[test]
+fn test_protected_regions() {
+    let rules = RuleSet::builtin().select(&["consecutive-spaces", "tag-brackets", "indentation"]);
+    let text = concat!(
+        "- a  #[[tag]]\n",
+        "\t- z\n",
+        "  - #+BEGIN_SRC\n",
+        "    b  #[[tag]]\n",
+        "    #+END_SRC\n",
+        "- <!-- lsd-ignore-start -->\n",
+        "- c  #[[tag]]\n",
+        "- <!-- lsd-ignore-end -->\n",
+        "- d  #[[tag]] <!-- lsd-ignore -->\n",
+        "- e  #[[tag]]\n",
+    );
+    assert_eq!(
+        rules.fix(text),
+        concat!(
+            "- a #tag\n",
+            "\t- z\n",
+            "\t- #+BEGIN_SRC\n",
+            "\t  b  #[[tag]]\n",
+            "\t  #+END_SRC\n",
+            "- <!-- lsd-ignore-start -->\n",
+            "- c  #[[tag]]\n",
+            "- <!-- lsd-ignore-end -->\n",
+            "- d  #[[tag]] <!-- lsd-ignore -->\n",
+            "- e #tag\n",
+        )
+    );
+}
+
+#[test]
+fn test_timestamp_properties() {
+    let text = "created-at:: 1704189600\nupdated-at:: 1704189600000\n\n- Block\n  created-at:: 2024-01-02T10:00:00Z\n  updated-at:: 2023-12-31\n- Other\n  updated-at:: soon\n";
+    let rules = RuleSet::builtin().select(&["timestamp-properties"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                14,
+                "created-at:: is in seconds, Logseq writes milliseconds".to_string()
+            ),
+            (
+                5,
+                16,
+                "created-at:: is a date, Logseq writes milliseconds since 1970".to_string()
+            ),
+            (
+                6,
+                16,
+                "updated-at:: is a date, Logseq writes milliseconds since 1970".to_string()
+            ),
+            (6, 16, "updated-at:: is before created-at::".to_string()),
+            (
+                8,
+                16,
+                "updated-at:: soon isn't a timestamp in milliseconds".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "created-at:: 1704189600000\nupdated-at:: 1704189600000\n\n- Block\n  created-at:: 1704189600000\n  updated-at:: 1703980800000\n- Other\n  updated-at:: soon\n"
+    );
+}
+
+#[test]
+fn test_page_property_values() {
+    let text = "tags:: [[open, #two words,, [[a, b]], x]]y\nicon:: U+1F4DA\npublic:: Yes\n\n- Block\n  public:: maybe\n";
+    let rules = RuleSet::builtin().select(&["page-property-values"]);
+    let found: Vec<(usize, usize, String)> = run_lints(text, &rules)
+        .into_iter()
+        .map(|d| (d.line, d.column, d.message))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (
+                1,
+                8,
+                "Unbalanced brackets in tags:: item [[open".to_string()
+            ),
+            (
+                1,
+                16,
+                "tags:: item #two words has a space, write #[[two words]]".to_string()
+            ),
+            (1, 27, "Empty item in tags::".to_string()),
+            (1, 39, "tags:: item x]]y isn't a page reference".to_string()),
+            (2, 8, "icon:: U+1F4DA isn't a single emoji".to_string()),
+            (3, 10, "public:: Yes isn't true or false".to_string()),
+        ]
+    );
+    assert_eq!(
+        rules.fix(text),
+        "tags:: [[open]], #[[two words]], [[a, b]], x]]y\nicon:: \u{1F4DA}\npublic:: true\n\n- Block\n  public:: maybe\n"
+    );
+    for valid in [
+        "- tags:: a, [[b c]], #d\n  icon:: \u{2764}\u{FE0F}\n  public:: false\n",
+        "icon:: \u{1F1EB}\u{1F1F7}\n\n- Notes\n",
+        "icon:: \u{1F468}\u{200D}\u{1F4BB}\n",
+    ] {
+        assert!(run_lints(valid, &rules).is_empty(), "{}", valid);
+    }
+}
+
+#[test]
+fn test_image_props() -> anyhow::Result<()> {
+    use assert_fs::prelude::*;
+    use logseq::graph::Graph;
+
+    let text = "- ![a](../assets/photo.png){:height 300, :width 400}\n- ![b](../assets/photo.png){height 100, :width 200px}\n- ![c](../assets/photo.png){:width big}\n- ![d](../assets/photo.png){:height 300\n- `![e](x.png){bad}` ![f](../assets/photo.png){:width 1000, :height 100}\n";
+    let mut rules = RuleSet::builtin().select(&["image-props"]);
+    let found = |rules: &RuleSet| -> Vec<(usize, usize, String)> {
+        run_lints(text, rules)
+            .into_iter()
+            .map(|d| (d.line, d.column, d.message))
+            .collect()
+    };
+    let syntax = vec![
+        (2, 28, "Write the image prop height as :height".to_string()),
+        (
+            2,
+            28,
+            "Image prop :width is a number of pixels, write 200 instead of 200px".to_string(),
+        ),
+        (
+            3,
+            28,
+            "Image prop :width must be a number of pixels, not big".to_string(),
+        ),
+        (4, 28, "Image props are never closed with }".to_string()),
+    ];
+    assert_eq!(found(&rules), syntax);
+    assert_eq!(
+        rules.fix(text),
+        text.replace("{height 100, :width 200px}", "{:height 100, :width 200}")
+    );
+
+    // An 800x600 PNG in the assets of the graph
+    let temp = assert_fs::TempDir::new()?;
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend([0, 0, 3, 32, 0, 0, 2, 88]);
+    temp.child("assets/photo.png").write_binary(&png)?;
+    rules.use_graph(&Graph::scan(temp.path())?);
+    let mut with_sizes = syntax.clone();
+    with_sizes.insert(
+        2,
+        (
+            2,
+            28,
+            "Image props :width 200 and :height 100 distort ../assets/photo.png, which is 800x600 pixels"
+                .to_string(),
+        ),
+    );
+    with_sizes.extend([
+        (
+            5,
+            47,
+            "Image shown at :width 1000, larger than the 800 pixels of ../assets/photo.png"
+                .to_string(),
+        ),
+        (
+            5,
+            47,
+            "Image props :width 1000 and :height 100 distort ../assets/photo.png, which is 800x600 pixels"
+                .to_string(),
+        ),
+    ]);
+    assert_eq!(found(&rules), with_sizes);
+    Ok(())
+}
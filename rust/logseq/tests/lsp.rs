@@ -0,0 +1,194 @@
+//! Integration tests for the language server
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::graph::Graph;
+use logseq::lint::RuleSet;
+use logseq::lsp::{file_uri, write_message, Server};
+use serde_json::json;
+
+#[test]
+fn test_language_server() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("pages/Target page.md").write_str("- Here\n")?;
+    temp.child("pages/Notes.md").write_str("- placeholder\n")?;
+    let graph = Graph::scan(temp.path())?;
+    let mut server = Server::new(RuleSet::builtin(), Some(graph));
+    let uri = file_uri(&temp.path().join("pages/Notes.md"));
+
+    let replies =
+        server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(
+        replies[0]["result"]["capabilities"]["definitionProvider"],
+        true
+    );
+
+    let text = "- see [[Target page]]\n- a  b\n";
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {"textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": text}},
+    }));
+    assert_eq!(replies[0]["method"], "textDocument/publishDiagnostics");
+    let diagnostic = &replies[0]["params"]["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "consecutive-spaces");
+    assert_eq!(
+        diagnostic["range"]["start"],
+        json!({"line": 1, "character": 3})
+    );
+
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "textDocument/codeAction",
+        "params": {
+            "textDocument": {"uri": uri},
+            "range": diagnostic["range"],
+            "context": {"diagnostics": [diagnostic]},
+        },
+    }));
+    let actions = replies[0]["result"].as_array().unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[0]["title"], "Fix consecutive-spaces problems");
+    let edit = &actions[0]["edit"]["changes"][uri.as_str()][0];
+    assert_eq!(edit["newText"], "- see [[Target page]]\n- a b\n");
+    assert_eq!(edit["range"]["end"], json!({"line": 2, "character": 0}));
+    assert_eq!(actions[1]["kind"], "source.fixAll");
+
+    let definition = |server: &mut Server, character: usize| {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "textDocument/definition",
+            "params": {"textDocument": {"uri": uri}, "position": {"line": 0, "character": character}},
+        }))[0]["result"]
+            .clone()
+    };
+    let target = file_uri(&temp.path().join("pages/Target page.md"));
+    assert_eq!(definition(&mut server, 10)["uri"], target.as_str());
+    assert_eq!(definition(&mut server, 2), serde_json::Value::Null);
+
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {"textDocument": {"uri": uri, "version": 2}, "contentChanges": [{"text": "- fine\n"}]},
+    }));
+    assert_eq!(replies[0]["params"]["diagnostics"], json!([]));
+
+    let replies = server.handle(&json!({"jsonrpc": "2.0", "id": 4, "method": "unknown"}));
+    assert_eq!(replies[0]["error"]["code"], -32601);
+
+    // A whole session through the reader and writer
+    let mut input = Vec::new();
+    write_message(
+        &mut input,
+        &json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}),
+    )?;
+    write_message(&mut input, &json!({"jsonrpc": "2.0", "method": "exit"}))?;
+    write_message(
+        &mut input,
+        &json!({"jsonrpc": "2.0", "id": 6, "method": "shutdown"}),
+    )?;
+    let mut output = Vec::new();
+    server.run(&mut input.as_slice(), &mut output)?;
+    assert!(server.exited());
+    let output = String::from_utf8(output)?;
+    assert!(output.ends_with(r#"{"id":5,"jsonrpc":"2.0","result":null}"#));
+    Ok(())
+}
+
+#[test]
+fn test_language_server_reads_the_graph_of_the_workspace() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("pages/Target.md").write_str("- Here\n")?;
+    temp.child("pages/Notes.md").write_str("- placeholder\n")?;
+    let mut server = Server::new(RuleSet::builtin(), None);
+    let uri = file_uri(&temp.path().join("pages/Notes.md"));
+    let definition = |server: &mut Server| {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/definition",
+            "params": {"textDocument": {"uri": uri}, "position": {"line": 0, "character": 8}},
+        }))[0]["result"]["uri"]
+            .clone()
+    };
+
+    let replies = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "rootUri": file_uri(temp.path()),
+            "capabilities": {"workspace": {"didChangeWatchedFiles": {"dynamicRegistration": true}}},
+        },
+    }));
+    assert_eq!(
+        replies[0]["result"]["capabilities"]["textDocumentSync"]["save"],
+        true
+    );
+    let replies = server.handle(&json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}));
+    assert_eq!(replies[0]["method"], "client/registerCapability");
+    assert_eq!(
+        replies[0]["params"]["registrations"][0]["method"],
+        "workspace/didChangeWatchedFiles"
+    );
+
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {"textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": "- see [[Target]] and [[Later]]\n"}},
+    }));
+    let target = file_uri(&temp.path().join("pages/Target.md"));
+    assert_eq!(definition(&mut server), target.as_str());
+
+    // Pages renamed after the start are found once the client tells about the change
+    std::fs::rename(
+        temp.path().join("pages/Target.md"),
+        temp.path().join("pages/Other.md"),
+    )?;
+    temp.child("pages/Later.md").write_str("- New\n")?;
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "workspace/didChangeWatchedFiles",
+        "params": {"changes": [
+            {"uri": target, "type": 3},
+            {"uri": file_uri(&temp.path().join("pages/Other.md")), "type": 1},
+            {"uri": file_uri(&temp.path().join("pages/Later.md")), "type": 1},
+        ]},
+    }));
+    assert_eq!(definition(&mut server), serde_json::Value::Null);
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {"textDocument": {"uri": uri, "version": 2}, "contentChanges": [{"text": "- see [[Later]]\n"}]},
+    }));
+    let later = file_uri(&temp.path().join("pages/Later.md"));
+    assert_eq!(definition(&mut server), later.as_str());
+
+    // A saved page is read again
+    temp.child("pages/Notes.md")
+        .write_str("alias:: Notebook\n\n- see [[Later]]\n")?;
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didSave",
+        "params": {"textDocument": {"uri": uri}},
+    }));
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {"textDocument": {"uri": uri, "version": 3}, "contentChanges": [{"text": "- see [[Notebook]]\n"}]},
+    }));
+    assert_eq!(definition(&mut server), uri.as_str());
+
+    // Without a workspace, the graph of the first opened document is read
+    let mut server = Server::new(RuleSet::builtin(), None);
+    server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+    server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {"textDocument": {"uri": uri, "languageId": "markdown", "version": 1, "text": "- see [[Later]]\n"}},
+    }));
+    assert_eq!(definition(&mut server), later.as_str());
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Integration tests for the Markdown to outline converter
+use logseq::clippings::clean_clipping;
+use logseq::markdown::{explode_pasted_documents, flat_markdown_to_outline};
+use std::fs;
+
+#[test]
+fn test_header_hierarchy_preserved_and_whitespace_removed() {
+    let flat = fs::read_to_string("tests/fixtures/markdown/flat.md").unwrap();
+    let expected = fs::read_to_string("tests/fixtures/markdown/outline.md").unwrap();
+    assert_eq!(flat_markdown_to_outline(&flat), expected);
+}
+
+#[test]
+fn test_deeper_header_without_h1() {
+    let flat =
+        "## Some sneaky h2 without h1\nSome flat paragraph.\n\n[Link only](https://example.com).\n";
+    assert_eq!(
+        flat_markdown_to_outline(flat),
+        "  - ## Some sneaky h2 without h1\n    - Some flat paragraph.\n    - [Link only](https://example.com).\n"
+    );
+}
+
+#[test]
+fn test_front_matter_and_code() {
+    let flat = "---\ndate: 2021-10-29\n---\n\n# Some title\n\nLine1\n```sh\n- not a list\n```\n";
+    assert_eq!(
+        flat_markdown_to_outline(flat),
+        "---\ndate: 2021-10-29\n---\n- # Some title\n  - Line1\n  - ```sh\n    - not a list\n    ```\n"
+    );
+}
+
+#[test]
+fn test_admonitions_kept_in_one_block() {
+    let flat = "# Title\n#+BEGIN_NOTE\nFirst line\n\n- not a list\n#+END_NOTE\nAfter\n";
+    assert_eq!(
+        flat_markdown_to_outline(flat),
+        "- # Title\n  - #+BEGIN_NOTE\n    First line\n    \n    - not a list\n    #+END_NOTE\n  - After\n"
+    );
+}
+
+#[test]
+fn test_explode_pasted_documents() {
+    let text = "- Notes from the call\n  collapsed:: true\n  Some context.\n  \n  ## Decisions\n  1. Ship it\n     1. On Friday\n  \n  ### Later\n  ```sh\n  # not a heading\n  ```\n  ---\n\t- Follow up\n- Single line\n  and a second one\n";
+    assert_eq!(
+        explode_pasted_documents(text),
+        "- Notes from the call\n  collapsed:: true\n\t- Some context.\n\t- ## Decisions\n\t\t- Ship it\n\t\t\t- On Friday\n\t\t- ### Later\n\t\t\t- ```sh\n\t\t\t  # not a heading\n\t\t\t  ```\n\t\t\t- Follow up\n- Single line\n  and a second one\n"
+    );
+    let written = "- Title\n  Second line\n```\n\n```\n\t- Child\n";
+    assert_eq!(explode_pasted_documents(written), written);
+}
+
+#[test]
+fn test_clean_clipping() {
+    let clipped = "---\nsource: https://example.com/post\n---\ntags:: clipping\n\n<div class=\"article\">\n<h2 id=\"intro\">Why &quot;outlines&quot;?</h2>\n<p>Outlines are <strong>great</strong>.<br>Really &amp; truly.</p>\n<h4>Lists</h4>\n<ul>\n<li>One</li>\n<li>Two &#8212; <em>more</em></li>\n</ul>\nUse `<br>` for breaks.\n</div>\n";
+    assert_eq!(
+        clean_clipping(clipped),
+        "---\nsource: https://example.com/post\n---\ntags:: clipping\n\n- # Why \"outlines\"?\n  - Outlines are **great**.\n  - Really & truly.\n  - ## Lists\n    - One\n    - Two \u{2014} _more_\n    - Use `<br>` for breaks.\n"
+    );
+    let outline = "- Note<br>Next\n\t- <p>child</p>\n\t  more&nbsp;text<br>split\n\t- ```html\n\t  <div>kept</div>\n\t  ```\n";
+    assert_eq!(
+        clean_clipping(outline),
+        "- Note\n- Next\n\t- child\n\t  more text\n\t- split\n\t- ```html\n\t  <div>kept</div>\n\t  ```\n"
+    );
+    let cleaned = clean_clipping(outline).into_owned();
+    assert_eq!(clean_clipping(&cleaned), cleaned);
+}
+
+#[test]
+fn test_clean_clipping_entities_and_scripts() {
+    let clipped = "<p>Use &lt;b&gt; for 1 &lt; 2 &amp; &amp;lt; too</p>\n<style>\np { color: red; }\n</style>\n<p>Kept<script>alert('<b>');</script> text</p>\n<script type=\"text/javascript\">\nvar a = 1 < 2;\n</script>\n";
+    let cleaned = clean_clipping(clipped).into_owned();
+    assert_eq!(
+        cleaned,
+        "- Use &lt;b> for 1 < 2 & &amp;lt; too\n- Kept text\n"
+    );
+    assert_eq!(clean_clipping(&cleaned), cleaned);
+    let outline = "- Note <SCRIPT>\n  track();\n  </SCRIPT> done\n- &#60;/div&#62; stays\n";
+    let cleaned = clean_clipping(outline).into_owned();
+    assert_eq!(cleaned, "- Note\n  done\n- &#60;/div> stays\n");
+    assert_eq!(clean_clipping(&cleaned), cleaned);
+}
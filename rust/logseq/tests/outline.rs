@@ -0,0 +1,29 @@
+//! Integration tests for the outline parser
+use logseq::outline::parse_outline;
+use std::fs;
+
+#[test]
+fn test_outline_nesting_and_code_fences() {
+    let text = fs::read_to_string("tests/fixtures/outline/mixed.md").unwrap();
+    let outline = parse_outline(&text);
+    assert!(outline.preamble.is_empty());
+    assert_eq!(outline.blocks.len(), 2);
+
+    let parent = &outline.blocks[0];
+    assert_eq!(parent.content, "Parent with\na continuation line");
+    assert_eq!(parent.children.len(), 2);
+    assert_eq!(parent.children[0].children[0].content, "Grandchild");
+    assert_eq!(parent.children[0].children[0].level, 2);
+    assert_eq!(parent.children[1].content, "```yaml\n- not a bullet\n```");
+    assert_eq!(outline.blocks[1].line, 8);
+
+    let lines: Vec<usize> = outline.iter().map(|block| block.line).collect();
+    assert_eq!(lines, vec![1, 3, 4, 5, 8]);
+}
+
+#[test]
+fn test_outline_preamble() {
+    let outline = parse_outline("title:: Page\nalias:: other\n\n- First block");
+    assert_eq!(outline.preamble, vec!["title:: Page", "alias:: other", ""]);
+    assert_eq!(outline.blocks[0].first_line(), "First block");
+}
@@ -0,0 +1,112 @@
+//! Integration tests for lint rules loaded from WebAssembly modules
+#![cfg(feature = "plugins")]
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::lint::{LintConfig, Rule, RuleSet, Severity};
+use logseq::plugins::WasmRule;
+use std::path::Path;
+
+const NO_FIXME: &str = "tests/fixtures/plugins/no-fixme.wat";
+
+#[test]
+fn test_plugin_rule() -> anyhow::Result<()> {
+    let rule = WasmRule::load(Path::new(NO_FIXME))?;
+    assert_eq!(rule.id(), "no-fixme");
+    assert_eq!(rule.doc().description, "Blocks should not contain FIXME");
+    assert!(rule.doc().fixable);
+
+    let rules = RuleSet::new().with(rule);
+    let text = "- FIXME first\n- ok\n  - nested\n    second FIXME\n    id:: 1\n";
+    let found: Vec<_> = rules
+        .check(text)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message))
+        .collect();
+    let message = "FIXME left in the block".to_string();
+    assert_eq!(found, vec![(1, 3, message.clone()), (4, 12, message)]);
+    assert_eq!(
+        rules.fix(text),
+        "- TODO first\n- ok\n  - nested\n    second TODO\n    id:: 1\n"
+    );
+    assert!(rules.check("- fine\n").is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_invalid_plugins() {
+    let error = |wat: &str| WasmRule::new(wat.as_bytes()).err().unwrap().to_string();
+    assert!(error("(module").contains("invalid plugin"));
+    assert!(
+        error("(module (func (export \"id\") (result i64) (i64.const 0)))")
+            .contains("the plugin doesn't export memory")
+    );
+    let exports = "(memory (export \"memory\") 1) (data (i32.const 0) \"No Fixme\")
+        (func (export \"alloc\") (param i32) (result i32) (i32.const 1024))
+        (func (export \"id\") (result i64) (i64.const 8))";
+    assert!(error(&format!("(module {})", exports))
+        .contains("the plugin doesn't export check(i32, i32) -> i64"));
+    let check = "(func (export \"check\") (param i32 i32) (result i64) (i64.const 0))";
+    assert!(error(&format!("(module {} {})", exports, check))
+        .contains("the id of the plugin must be in kebab-case, not \"No Fixme\""));
+    assert!(WasmRule::load(Path::new("tests/fixtures/plugins/missing.wasm")).is_err());
+}
+
+#[test]
+fn test_failing_plugin() -> anyhow::Result<()> {
+    let rule = WasmRule::new(
+        br#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "stuck")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "id") (result i64) (i64.const 5))
+            (func (export "check") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                (i64.const 0)))"#,
+    )?;
+    let diagnostics = RuleSet::new().with(rule).check("  - a\n- b\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 5));
+    assert!(diagnostics[0].message.starts_with("The plugin failed: "));
+    Ok(())
+}
+
+#[test]
+fn test_plugins_of_lint_config() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("plugins/no-fixme.wat")
+        .write_str(&std::fs::read_to_string(NO_FIXME)?)?;
+    temp.child(".lsd.toml").write_str(concat!(
+        "[lint]\nplugins = [\"plugins/no-fixme.wat\"]\nenable = [\"no-fixme\", \"tag-brackets\"]\n\n",
+        "[rules.no-fixme]\nseverity = \"error\"\n",
+    ))?;
+    let config = LintConfig::discover(temp.path())?.unwrap();
+    assert_eq!(config.plugins, vec!["plugins/no-fixme.wat"]);
+    let rules = config.rule_set()?;
+    assert_eq!(rules.ids(), vec!["tag-brackets", "no-fixme"]);
+    let diagnostics = rules.check("- FIXME #[[tag]]\n");
+    assert_eq!(diagnostics[0].rule, "no-fixme");
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[1].rule, "tag-brackets");
+
+    let error = |contents: &str| {
+        LintConfig::parse(contents, temp.path())
+            .and_then(|config| config.rule_set())
+            .err()
+            .map(|error| error.to_string())
+            .unwrap_or_default()
+    };
+    temp.child("plugins/spaces.wat").write_str(
+        r#"(module
+            (memory (export "memory") 1)
+            (data (i32.const 0) "consecutive-spaces")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "id") (result i64) (i64.const 18))
+            (func (export "check") (param i32 i32) (result i64) (i64.const 0)))"#,
+    )?;
+    assert!(error("[lint]\nplugins = [\"plugins/spaces.wat\"]")
+        .contains("the plugin has the id of the rule consecutive-spaces"));
+    assert!(error("[lint]\nplugins = [\"plugins/missing.wasm\"]").contains("missing.wasm"));
+    assert!(error("[lint]\nplugins = \"plugins/no-fixme.wat\"")
+        .contains("lint.plugins must be an array of strings"));
+    Ok(())
+}
@@ -0,0 +1,288 @@
+//! Integration tests for renaming pages and properties across a graph
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::graph::Graph;
+use logseq::rename::rename_page;
+
+#[test]
+fn test_rename_page() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("pages/Old Name.md")
+        .write_str("title:: Old Name\nalias:: Former\n\n- The page\n")?;
+    temp.child("pages/Tagged.md")
+        .write_str("tags:: old name, other\n\n- About #[[Old Name]] and #other\n")?;
+    temp.child("pages/Other.md").write_str("- Not related\n")?;
+    temp.child("journals/2024_01_02.md").write_str(
+        "- See [[old name]] and {{embed [[Old Name]]}}\n  - `[[Old Name]]` in code\n  - ```\n    [[Old Name]]\n    ```\n",
+    )?;
+    let page = temp.child("pages/Old Name.md");
+
+    let graph = Graph::scan(temp.path())?;
+    let dry_run = rename_page(&graph, "old name", "new/name", true)?;
+    assert_eq!(dry_run.to, temp.path().join("pages/new___name.md"));
+    assert_eq!(dry_run.edits.len(), 3);
+    assert!(page.path().exists());
+
+    rename_page(&graph, "old name", "new/name", false)?;
+    assert!(!page.path().exists());
+    temp.child("pages/new___name.md")
+        .assert("title:: new/name\nalias:: Former\n\n- The page\n");
+    temp.child("pages/Tagged.md")
+        .assert("tags:: new/name, other\n\n- About #[[new/name]] and #other\n");
+    temp.child("journals/2024_01_02.md").assert(
+        "- See [[new/name]] and {{embed [[new/name]]}}\n  - `[[Old Name]]` in code\n  - ```\n    [[Old Name]]\n    ```\n",
+    );
+    temp.child("pages/Other.md").assert("- Not related\n");
+    Ok(())
+}
+
+#[test]
+fn test_rename_page_tags_and_conflicts() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    temp.child("pages/proj.md").write_str("- #proj\n")?;
+    temp.child("pages/Links.md")
+        .write_str("- #proj. and #project and [[proj]]\n")?;
+    temp.child("pages/Taken.md").write_str("- Taken\n")?;
+    let graph = Graph::scan(temp.path())?;
+
+    assert!(rename_page(&graph, "proj", "taken", true).is_err());
+    assert!(rename_page(&graph, "missing", "anything", true).is_err());
+
+    rename_page(&graph, "proj", "Big Project", false)?;
+    temp.child("pages/Links.md")
+        .assert("- #[[Big Project]]. and #project and [[Big Project]]\n");
+    temp.child("pages/Big Project.md")
+        .assert("- #[[Big Project]]\n");
+    Ok(())
+}
+
+#[test]
+fn test_merge_pages() -> anyhow::Result<()> {
+    use logseq::merge::merge_pages;
+
+    let temp = TempDir::new()?;
+    temp.child("pages/ML.md").write_str(
+        "alias:: Machine learning\ntags:: topic\n\n- Notes on #ML\n  id:: 65a0c1d2-0000-4000-8000-000000000001\n  - Child with [[Other]]\n",
+    )?;
+    temp.child("pages/Machine Learning.md")
+        .write_str("type:: topic\n\n- Existing block\n")?;
+    temp.child("pages/Other.md")
+        .write_str("- See [[ml]] and #ML, not `[[ML]]`\n")?;
+    let source = temp.child("pages/ML.md");
+
+    let graph = Graph::scan(temp.path())?;
+    assert!(merge_pages(&graph, "ML", "ml", true).is_err());
+    assert!(merge_pages(&graph, "ML", "missing", true).is_err());
+
+    let dry_run = merge_pages(&graph, "ML", "Machine Learning", true)?;
+    assert_eq!(dry_run.edits.len(), 2);
+    assert!(source.path().exists());
+
+    merge_pages(&graph, "ML", "Machine Learning", false)?;
+    assert!(!source.path().exists());
+    temp.child("pages/Machine Learning.md").assert(
+        "type:: topic\nalias:: ML\ntags:: topic\n\n- Existing block\n- Notes on #[[Machine Learning]]\n  id:: 65a0c1d2-0000-4000-8000-000000000001\n  - Child with [[Other]]\n",
+    );
+    temp.child("pages/Other.md")
+        .assert("- See [[Machine Learning]] and #[[Machine Learning]], not `[[ML]]`\n");
+
+    temp.child("pages/Draft.md").write_str(
+        "- first:: property\n  tags:: book, [[to read]], Final\n  status:: draft\n- Draft block\n",
+    )?;
+    temp.child("pages/Final.md")
+        .write_str("alias:: Draft\ntags:: book\nstatus:: done\n\n- Final block\n")?;
+    merge_pages(&Graph::scan(temp.path())?, "Draft", "Final", false)?;
+    temp.child("pages/Final.md").assert(
+        "alias:: Draft\ntags:: book, [[to read]]\nstatus:: done\nfirst:: property\n\n- Final block\n- Draft block\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sync_conflicts() -> anyhow::Result<()> {
+    use logseq::sync_conflicts::{find_sync_conflicts, resolve_sync_conflicts, SyncTool};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Books.md").write_str(
+        "- Dune
+	- by Herbert
+- Emma
+",
+    )?;
+    temp.child("pages/Books.sync-conflict-20240102-103000-ABC1234.md")
+        .write_str(
+            "- Dune  
+	- by Herbert
+- Hyperion
+	- by Simmons
+",
+        )?;
+    temp.child("pages/Books (1).md").write_str(
+        "- Emma
+- Hyperion
+	- by Simmons
+- Ubik
+",
+    )?;
+    temp.child("pages/Chapter (2).md").write_str(
+        "- Alone
+",
+    )?;
+
+    let graph = Graph::scan(temp.path())?;
+    let conflicts = find_sync_conflicts(&graph)?;
+    let found: Vec<(String, SyncTool, usize)> = conflicts
+        .iter()
+        .map(|conflict| {
+            let name = conflict.path.file_name().unwrap().to_string_lossy();
+            (name.to_string(), conflict.tool, conflict.new_blocks.len())
+        })
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Books (1).md".to_string(), SyncTool::Copy, 2),
+            (
+                "Books.sync-conflict-20240102-103000-ABC1234.md".to_string(),
+                SyncTool::Syncthing,
+                1
+            ),
+        ]
+    );
+    assert!(conflicts[1]
+        .diff(temp.path())?
+        .starts_with("--- a/pages/Books.md\n+++ b/pages/Books.md\n"));
+
+    let dry_run = resolve_sync_conflicts(&conflicts, true)?;
+    assert_eq!(dry_run.edits.len(), 1);
+    assert!(temp.child("pages/Books (1).md").path().exists());
+    resolve_sync_conflicts(&conflicts, false)?;
+    temp.child("pages/Books.md")
+        .assert("- Dune\n\t- by Herbert\n- Emma\n- Hyperion\n\t- by Simmons\n- Ubik\n");
+    assert!(!temp.child("pages/Books (1).md").path().exists());
+    assert!(!temp
+        .child("pages/Books.sync-conflict-20240102-103000-ABC1234.md")
+        .path()
+        .exists());
+    Ok(())
+}
+
+#[test]
+fn test_split_page() -> anyhow::Result<()> {
+    use logseq::split::{split_page, SplitLeave};
+
+    let temp = TempDir::new()?;
+    temp.child("pages/Handbook.md").write_str(
+        "tags:: docs\n\n- Intro\n- ## Onboarding\n  id:: 65a0c1d2-0000-4000-8000-000000000001\n  collapsed:: true\n  - Laptop\n    id:: 65a0c1d2-0000-4000-8000-000000000002\n    - Order it\n  - Accounts\n- ### Too deep\n- # [[Time off]]\n  - Ask first\n- Outro\n",
+    )?;
+    temp.child("pages/Refs.md")
+        .write_str("- ((65a0c1d2-0000-4000-8000-000000000002))\n")?;
+    let page = temp.child("pages/Handbook.md");
+
+    let graph = Graph::scan(temp.path())?;
+    assert!(split_page(&graph, "Refs", 2, SplitLeave::Link, true).is_err());
+    let dry_run = split_page(&graph, "Handbook", 2, SplitLeave::Link, true)?;
+    assert_eq!(dry_run.created.len(), 2);
+    assert!(!temp.child("pages/Handbook___Onboarding.md").path().exists());
+
+    split_page(&graph, "Handbook", 2, SplitLeave::Embed, false)?;
+    page.assert(
+        "tags:: docs\n\n- Intro\n- {{embed [[Handbook/Onboarding]]}}\n- ### Too deep\n- {{embed [[Handbook/Time off]]}}\n- Outro\n",
+    );
+    temp.child("pages/Handbook___Onboarding.md").assert(
+        "id:: 65a0c1d2-0000-4000-8000-000000000001\n\n- Laptop\n  id:: 65a0c1d2-0000-4000-8000-000000000002\n  - Order it\n- Accounts\n",
+    );
+    temp.child("pages/Handbook___Time off.md")
+        .assert("- Ask first\n");
+    assert!(Graph::scan(temp.path())?.check_block_refs().is_empty());
+
+    temp.child("pages/Other.md").write_str("- # Onboarding\n")?;
+    temp.child("pages/Other___Onboarding.md")
+        .write_str("- Taken\n")?;
+    let graph = Graph::scan(temp.path())?;
+    assert!(split_page(&graph, "Other", 2, SplitLeave::Link, true).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_rename_property() -> anyhow::Result<()> {
+    use logseq::property_rename::{rename_property, rename_property_value};
+
+    let temp = TempDir::new()?;
+    let book = temp.child("pages/Book.md");
+    book.write_str(
+        "Status:: reading\ntags:: book, #[[sci fi]]\n\n- Notes\n  status:: todo\n  state:: done\n- ```\n  status:: code\n  ```\n",
+    )?;
+    let other = temp.child("journals/2024_01_02.md");
+    other.write_str("- genre:: #[[Sci Fi]], [[fantasy]]\r\n  status:: reading\r\n")?;
+
+    let graph = Graph::scan(temp.path())?;
+    let dry_run = rename_property(&graph, "status", "state", true)?;
+    assert_eq!(dry_run.edits.len(), 2);
+    assert_eq!(dry_run.conflicts, vec![(book.path().to_path_buf(), 5)]);
+    assert!(rename_property(&graph, "status", "not a key", true).is_err());
+
+    rename_property(&graph, "status", "state", false)?;
+    book.assert(
+        "state:: reading\ntags:: book, #[[sci fi]]\n\n- Notes\n  status:: todo\n  state:: done\n- ```\n  status:: code\n  ```\n",
+    );
+    other.assert("- genre:: #[[Sci Fi]], [[fantasy]]\r\n  state:: reading\r\n");
+
+    let graph = Graph::scan(temp.path())?;
+    let rename = rename_property_value(&graph, "state", "reading", "read", false)?;
+    assert_eq!(rename.edits.len(), 2);
+    other.assert("- genre:: #[[Sci Fi]], [[fantasy]]\r\n  state:: read\r\n");
+    rename_property_value(&graph, "genre", "sci fi", "science fiction", false)?;
+    other.assert("- genre:: #[[science fiction]], [[fantasy]]\r\n  state:: read\r\n");
+    let graph = Graph::scan(temp.path())?;
+    rename_property_value(&graph, "tags", "Sci-Fi", "book", false)?;
+    rename_property_value(&graph, "tags", "sci fi", "book", false)?;
+    book.assert(
+        "state:: read\ntags:: book\n\n- Notes\n  status:: todo\n  state:: done\n- ```\n  status:: code\n  ```\n",
+    );
+    Ok(())
+}
+
+#[test]
+fn test_normalize_property_keys() -> anyhow::Result<()> {
+    use logseq::property_rename::{normalize_property_keys, KeyMapping, KeyStyle};
+
+    let temp = TempDir::new()?;
+    let book = temp.child("pages/Book.md");
+    book.write_str(
+        "Book-Author:: Le Guin\nCreated_At:: 1704189600000\n\n- Notes\n  book_author:: Herbert\n  Book-Author:: Herbert\n- ```\n  Book_Author:: code\n  ```\n",
+    )?;
+    let graph = Graph::scan(temp.path())?;
+
+    let normalization = normalize_property_keys(&graph, KeyStyle::Kebab, false)?;
+    let mapping = |from: &str, to: &str| KeyMapping {
+        from: from.to_string(),
+        to: to.to_string(),
+        count: 1,
+    };
+    assert_eq!(
+        normalization.mapping,
+        vec![
+            mapping("Book-Author", "book-author"),
+            mapping("Created_At", "created-at")
+        ]
+    );
+    let lines: Vec<usize> = normalization
+        .rename
+        .conflicts
+        .iter()
+        .map(|(_, line)| *line)
+        .collect();
+    assert_eq!(lines, vec![5, 6]);
+    book.assert(
+        "book-author:: Le Guin\ncreated-at:: 1704189600000\n\n- Notes\n  book_author:: Herbert\n  Book-Author:: Herbert\n- ```\n  Book_Author:: code\n  ```\n",
+    );
+
+    let graph = Graph::scan(temp.path())?;
+    let snake = normalize_property_keys(&graph, KeyStyle::Snake, true)?;
+    assert_eq!(snake.mapping, vec![mapping("book-author", "book_author")]);
+    assert!(snake.rename.edits[0]
+        .updated
+        .starts_with("book_author:: Le Guin\ncreated-at::"));
+    Ok(())
+}
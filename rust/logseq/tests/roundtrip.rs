@@ -0,0 +1,342 @@
+//! Round-trip properties of the fixers: fixing twice is the same as fixing once, and fixes never
+//! touch code blocks or block ids. Rendering a parsed page, before and after fixing, gives the
+//! page back byte for byte.
+//!
+//! Pages generated with proptest are checked on every run. The pages of `tests/fixtures/corpus`, and those of
+//! the directory in `LSD_CORPUS` if set, are checked with `cargo test --features corpus`.
+use logseq::lint::RuleSet;
+use logseq::outline::parse_outline;
+use logseq::render::render_outline;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::sample::select;
+
+/// The lines inside fenced code blocks, without their indentation
+fn code_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in text.lines() {
+        let content = line
+            .trim_start()
+            .trim_start_matches(['-', '*', '+'])
+            .trim_start();
+        let opening = ["```", "~~~"].into_iter().find(|f| content.starts_with(f));
+        match (fence, opening) {
+            (None, Some(opening)) => fence = Some(opening),
+            (Some(open), _) if content.starts_with(open) => fence = None,
+            (Some(_), _) => lines.push(line.trim_start().trim_end_matches('\r').to_string()),
+            (None, None) => {}
+        }
+    }
+    lines
+}
+
+/// The `id::` properties, which link block references to their blocks
+fn block_ids(text: &str) -> Vec<String> {
+    let mut ids: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("id:: "))
+        .map(String::from)
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// Asserts that rendering the outline of a page gives the page back
+fn assert_renders_unchanged(label: &str, text: &str) {
+    assert_eq!(
+        render_outline(&parse_outline(text)),
+        text,
+        "{}: rendering changes the page",
+        label
+    );
+}
+
+/// Asserts the round-trip properties of a page, for all the built-in rules together and for each
+/// rule alone
+fn assert_round_trip(label: &str, text: &str) {
+    let rules = RuleSet::builtin();
+    let fixed = rules.fix(text);
+    assert_renders_unchanged(label, text);
+    assert_renders_unchanged(&format!("{} fixed", label), &fixed);
+    assert_eq!(
+        rules.fix(&fixed),
+        fixed,
+        "{}: fixing twice changes the page",
+        label
+    );
+    assert_eq!(
+        code_lines(&fixed),
+        code_lines(text),
+        "{}: code changed",
+        label
+    );
+    assert_eq!(block_ids(&fixed), block_ids(text), "{}: ids changed", label);
+    for id in rules.ids() {
+        let Some(once) = rules.fix_rule(id, text) else {
+            continue;
+        };
+        assert_eq!(
+            rules.fix_rule(id, &once),
+            None,
+            "{}: fixing twice with {} changes the page",
+            label,
+            id
+        );
+        assert_eq!(
+            code_lines(&once),
+            code_lines(text),
+            "{}: {} changed code",
+            label,
+            id
+        );
+        assert_eq!(
+            block_ids(&once),
+            block_ids(text),
+            "{}: {} changed ids",
+            label,
+            id
+        );
+    }
+}
+
+/// Pieces of Logseq Markdown the generated pages are made of, many of them with something to fix
+const CONTENTS: &[&str] = &[
+    "plain text",
+    "two  spaces   here",
+    "TODO  a task",
+    "[ ] a checkbox",
+    "[x] done  box",
+    "LATER [#A] soon",
+    "DONE [#c] lowercase priority",
+    "NOW started #[[tag]]",
+    "see [[Some Page]] and #[[two words]]",
+    "[label]([[Other Page]])",
+    "## heading",
+    "# big heading",
+    "trailing spaces   ",
+    "`code  span` and $$x  =  1$$",
+    "{{query (and [[a]]  (task TODO))}}",
+    "<% today %>",
+    "> [!tip] Title",
+    "| a | b |",
+    "",
+];
+
+/// Lines that can follow the first line of a block
+const CONTINUATIONS: &[&str] = &[
+    "id:: 65a1b2c3-0000-4000-8000-000000000001",
+    "collapsed:: true",
+    "Tags:: #[[x]], y",
+    "SCHEDULED: <2024-01-02 Tue>",
+    "```\n{indent}  -  not   a  bullet\n{indent}  ```",
+    "~~~sh\n{indent}  echo  \"a   b\"\n{indent}  ~~~",
+    "> quoted  line",
+    "| --- | --- |",
+    "continued  text",
+];
+
+/// The first line of a block: one of [CONTENTS], or words with any spacing
+fn content() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => select(CONTENTS).prop_map(String::from),
+        1 => "[a-zA-Z#]{1,6}( {1,3}[a-zA-Z#]{1,6}){0,4} ?",
+    ]
+}
+
+/// A line following the first line of a block, with `{indent}` for the indentation of the block
+fn continuation() -> impl Strategy<Value = String> {
+    prop_oneof![
+        3 => select(CONTINUATIONS).prop_map(String::from),
+        1 => "[a-z]{1,6}( {1,3}[a-z]{1,6}){0,3}",
+    ]
+}
+
+/// A block of a generated page: its deepest allowed level, whether it is indented with spaces,
+/// its bullet, its first line and the following ones
+type GeneratedBlock = (usize, bool, &'static str, String, Vec<String>);
+
+fn generated_block() -> impl Strategy<Value = GeneratedBlock> {
+    (
+        0..=4usize,
+        prop::bool::weighted(0.25),
+        select(&["-", "-", "-", "*", "+"][..]),
+        content(),
+        vec(continuation(), 0..3),
+    )
+}
+
+/// A page of random blocks, nested at most one level deeper than the block before them
+fn generated_page() -> impl Strategy<Value = String> {
+    (any::<bool>(), vec(generated_block(), 1..12), any::<bool>()).prop_map(
+        |(title, blocks, trailing_newline)| {
+            let mut page = String::new();
+            if title {
+                page.push_str("title:: Generated\nalias:: [[Other]]\n\n");
+            }
+            let mut level = 0;
+            for (deepest, spaces, bullet, content, continuations) in blocks {
+                level = deepest.min(level + 1).min(3);
+                let indent = match spaces {
+                    true => "  ".repeat(level * 2),
+                    false => "\t".repeat(level),
+                };
+                page.push_str(&format!("{}{} {}\n", indent, bullet, content));
+                for continuation in continuations {
+                    let continuation = continuation.replace("{indent}", &indent);
+                    page.push_str(&format!("{}  {}\n", indent, continuation));
+                }
+            }
+            if !trailing_newline {
+                page.pop();
+            }
+            page
+        },
+    )
+}
+
+/// A block with the spacing Logseq doesn't write: its indentation, bullet, separator and first
+/// line, and its following lines with their own indentation
+type UnusualBlock = (
+    &'static str,
+    &'static str,
+    &'static str,
+    String,
+    Vec<(&'static str, String)>,
+);
+
+fn unusual_block() -> impl Strategy<Value = UnusualBlock> {
+    let continuation = prop_oneof![
+        1 => Just("   ".to_string()),
+        3 => continuation(),
+    ];
+    (
+        select(&[" ", "   ", "\t ", " \t", "", "\t\t\t"][..]),
+        select(&["-", "*", "+"][..]),
+        select(&[" ", "\t", "  ", " \t", ""][..]),
+        content(),
+        vec(
+            (select(&["", " ", "\t", "      ", " \t "][..]), continuation),
+            0..3,
+        ),
+    )
+}
+
+/// A generated page with the spacing Logseq doesn't write: tabs or several spaces after bullets,
+/// bare bullets, continuation lines indented anyhow, and lines of whitespace
+fn unusual_page() -> impl Strategy<Value = String> {
+    (
+        any::<bool>(),
+        vec(unusual_block(), 1..12),
+        0..3usize,
+        any::<bool>(),
+    )
+        .prop_map(|(front_matter, blocks, blank_lines, trailing_newline)| {
+            let mut page = String::new();
+            if front_matter {
+                page.push_str("---\ntitle:  Front matter\n---\n  \n");
+            }
+            for (indent, bullet, separator, content, continuations) in blocks {
+                let content = match separator {
+                    "" => "",
+                    _ => content.as_str(),
+                };
+                page.push_str(&format!("{}{}{}{}\n", indent, bullet, separator, content));
+                for (indent, continuation) in continuations {
+                    let continuation = continuation.replace("{indent}", indent);
+                    page.push_str(&format!("{}{}\n", indent, continuation));
+                }
+            }
+            page.push_str(&"\n".repeat(blank_lines));
+            if !trailing_newline {
+                page.pop();
+            }
+            page
+        })
+}
+
+// The number of generated pages is 256 by default, and `PROPTEST_CASES` if set. A failing page
+// is shrunk to a minimal one, and its seed saved in `proptest-regressions` to be checked first
+// on the next runs.
+proptest! {
+    #[test]
+    fn test_generated_pages_round_trip(page in generated_page()) {
+        assert_round_trip("generated page", &page);
+    }
+
+    #[test]
+    fn test_windows_line_endings_round_trip(page in generated_page()) {
+        assert_round_trip("generated page with CRLF", &page.replace('\n', "\r\n"));
+    }
+
+    #[test]
+    fn test_unusual_spacing_renders_unchanged(page in unusual_page(), crlf in vec(any::<bool>(), 64)) {
+        assert_renders_unchanged("unusual page", &page);
+        assert_renders_unchanged("unusual page with CRLF", &page.replace('\n', "\r\n"));
+        let mixed: String = page
+            .split_inclusive('\n')
+            .zip(crlf.iter().cycle())
+            .map(|(line, &crlf)| match crlf {
+                true => line.replace('\n', "\r\n"),
+                false => line.to_string(),
+            })
+            .collect();
+        assert_renders_unchanged("unusual page with mixed line endings", &mixed);
+    }
+}
+
+#[test]
+fn test_fixtures_render_unchanged() -> std::io::Result<()> {
+    let mut files = Vec::new();
+    markdown_files(
+        &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+        &mut files,
+    )?;
+    assert!(!files.is_empty());
+    for path in files {
+        let text = std::fs::read_to_string(&path)?;
+        assert_renders_unchanged(&path.display().to_string(), &text);
+    }
+    Ok(())
+}
+
+/// The Markdown files of a directory and its subdirectories
+fn markdown_files(
+    dir: &std::path::Path,
+    files: &mut Vec<std::path::PathBuf>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "corpus")]
+#[test]
+fn test_corpus_round_trip() -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    markdown_files(
+        &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus"),
+        &mut files,
+    )?;
+    if let Some(dir) = std::env::var_os("LSD_CORPUS") {
+        markdown_files(std::path::Path::new(&dir), &mut files)?;
+    }
+    files.sort();
+    assert!(!files.is_empty());
+    for path in files {
+        let text = std::fs::read_to_string(&path)?;
+        let label = path.display().to_string();
+        assert_round_trip(&label, &text);
+        if !text.contains('\r') {
+            assert_round_trip(&format!("{} with CRLF", label), &text.replace('\n', "\r\n"));
+        }
+    }
+    Ok(())
+}
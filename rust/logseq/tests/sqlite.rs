@@ -0,0 +1,81 @@
+//! Integration tests for the SQLite index of a graph
+#![cfg(feature = "index-sqlite")]
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::graph::Graph;
+use logseq::sqlite::{index_graph, Database, SqlValue};
+
+fn text(value: &str) -> SqlValue {
+    SqlValue::Text(value.to_string())
+}
+
+#[test]
+fn test_index_graph() {
+    let temp = TempDir::new().unwrap();
+    temp.child("pages/Reading.md")
+        .write_str(concat!(
+            "tags:: list\n\n",
+            "- Dune #book\n  author:: [[Frank Herbert]]\n",
+            "- Neuromancer #book\n  rating:: 5\n",
+            "- TODO [#A] read [[Dune]] again\n  SCHEDULED: <2024-01-02 Tue>\n",
+        ))
+        .unwrap();
+    let graph = Graph::scan(temp.path()).unwrap();
+    let database = Database::open(temp.child("index.sqlite").path()).unwrap();
+    index_graph(&graph, &database).unwrap();
+
+    let untagged = database
+        .query(
+            "SELECT b.content FROM blocks b
+             JOIN refs r ON r.source = b.id JOIN pages p ON p.id = r.target
+             WHERE p.name = 'book'
+               AND NOT EXISTS (SELECT 1 FROM properties WHERE entity = b.id AND key = 'AUTHOR')",
+        )
+        .unwrap();
+    assert_eq!(untagged.columns, vec!["content"]);
+    assert_eq!(
+        untagged.rows,
+        vec![vec![text("Neuromancer #book\nrating:: 5")]]
+    );
+
+    let properties = database
+        .query("SELECT key, value FROM properties ORDER BY entity, key")
+        .unwrap();
+    assert_eq!(
+        properties.rows,
+        vec![
+            vec![text("tags"), text("list")],
+            vec![text("author"), text("Frank Herbert")],
+            vec![text("rating"), SqlValue::Integer(5)],
+        ]
+    );
+
+    let tasks = database
+        .query("SELECT marker, priority, scheduled, deadline FROM tasks")
+        .unwrap();
+    assert_eq!(
+        tasks.rows,
+        vec![vec![
+            text("TODO"),
+            text("A"),
+            text("2024-01-02"),
+            SqlValue::Null
+        ]]
+    );
+
+    let missing = database
+        .query("SELECT original_name FROM pages WHERE file IS NULL ORDER BY name")
+        .unwrap();
+    assert_eq!(
+        missing.rows,
+        vec![
+            vec![text("book")],
+            vec![text("Dune")],
+            vec![text("Frank Herbert")],
+            vec![text("list")]
+        ]
+    );
+
+    let empty = database.query("SELECT '', x''").unwrap();
+    assert_eq!(empty.rows, vec![vec![text(""), SqlValue::Blob(Vec::new())]]);
+}
@@ -0,0 +1,81 @@
+//! Integration tests for the titles of bare URLs, against a local web server
+#![cfg(feature = "network")]
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::titles::{title_files, title_urls, Titler};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// Serves requests on a local port, with the path of each request in the title of its page, and
+/// returns the server's URL
+fn serve(requests: usize) -> anyhow::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().take(requests) {
+            let Ok(mut stream) = stream else { continue };
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            let _ = reader.read_line(&mut request_line);
+            let path = request_line.split(' ').nth(1).unwrap_or("/").to_string();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) <= 2 {
+                    break;
+                }
+            }
+            let (content_type, body) = match path.as_str() {
+                "/file.pdf" => ("application/pdf", "%PDF".to_string()),
+                _ => (
+                    "text/html; charset=utf-8",
+                    format!("<html><title>Page {} &amp; [more]</title></html>", path),
+                ),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+        }
+    });
+    Ok(url)
+}
+
+#[test]
+fn test_title_urls() -> anyhow::Result<()> {
+    let server = serve(3)?;
+    let temp = TempDir::new()?;
+    let cache = temp.child("cache/titles.json");
+    let text = format!(
+        "- See {0}/a, and [b]({0}/b)\n  url:: {0}/c\n  ```\n  {0}/d\n  ```\n- {0}/file.pdf `{0}/e`\n\t- Again {0}/a\n",
+        server
+    );
+
+    let mut titler = Titler::new(Some(cache.path().to_path_buf()));
+    titler.delay = Duration::ZERO;
+    let titled = format!(
+        "- See [Page /a & \\[more\\]]({0}/a), and [b]({0}/b)\n  url:: {0}/c\n  ```\n  {0}/d\n  ```\n- {0}/file.pdf `{0}/e`\n\t- Again [Page /a & \\[more\\]]({0}/a)\n",
+        server
+    );
+    assert_eq!(title_urls(&text, &mut titler), titled);
+
+    // The files are titled from the cache, without the server
+    let page = temp.child("pages/page.md");
+    page.write_str(&text)?;
+    titler.save()?;
+    let mut offline = Titler::new(Some(cache.path().to_path_buf()));
+    offline.offline = true;
+    let edits = title_files(&[page.path().to_path_buf()], &mut offline, true)?;
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].updated, titled);
+    page.assert(text.as_str());
+    title_files(&[page.path().to_path_buf()], &mut offline, false)?;
+    page.assert(titled.as_str());
+
+    let new_url = format!("- {}/new\n", server);
+    assert_eq!(title_urls(&new_url, &mut offline), new_url);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Integration tests for watching the files of a graph
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::watch::Watcher;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// The changes of a watcher once there are some, within a few seconds
+fn next_changes(watcher: &mut Watcher) -> Vec<PathBuf> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let changes = watcher.changes().unwrap();
+        if !changes.is_empty() || Instant::now() > deadline {
+            return changes;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_watch_events() {
+    let temp = TempDir::new().unwrap();
+    let page = temp.child("pages/page.md");
+    page.write_str("- a\n").unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- b\n")
+        .unwrap();
+    let root = temp.path().to_path_buf();
+    let mut watcher = Watcher::new(&[temp.to_path_buf()], Duration::ZERO).unwrap();
+    assert!(watcher.has_events());
+    assert!(watcher.changes().unwrap().is_empty());
+
+    page.write_str("-  changed\n").unwrap();
+    assert_eq!(next_changes(&mut watcher), vec![root.join("pages/page.md")]);
+
+    // New files are found by walking the directories again, hidden ones are left out
+    temp.child("logseq/config.edn")
+        .write_str("{:hidden [\"pages/hidden\"]}\n")
+        .unwrap();
+    temp.child("pages/hidden/secret.md")
+        .write_str("- c\n")
+        .unwrap();
+    temp.child("pages/sub/new.md").write_str("- d\n").unwrap();
+    assert_eq!(
+        next_changes(&mut watcher),
+        vec![root.join("pages/sub/new.md")]
+    );
+
+    std::fs::remove_file(page.path()).unwrap();
+    temp.child("journals/2024_01_02.md")
+        .write_str("- b changed\n")
+        .unwrap();
+    assert_eq!(
+        next_changes(&mut watcher),
+        vec![root.join("journals/2024_01_02.md")]
+    );
+}
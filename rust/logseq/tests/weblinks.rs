@@ -0,0 +1,113 @@
+//! Integration tests for the checks of web links, against a local web server
+#![cfg(feature = "network")]
+use assert_fs::prelude::*;
+use assert_fs::TempDir;
+use logseq::graph::Graph;
+use logseq::weblinks::{check_web_links, web_links, LinkChecker};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Serves requests on a local port with a status depending on their path, and returns the
+/// server's URL and the requests served, as `METHOD /path`
+fn serve() -> anyhow::Result<(String, Arc<Mutex<Vec<String>>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let served = requests.clone();
+    std::thread::spawn(move || {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            let _ = reader.read_line(&mut request_line);
+            let mut parts = request_line.split(' ');
+            let method = parts.next().unwrap_or("GET").to_string();
+            let path = parts.next().unwrap_or("/").to_string();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) <= 2 {
+                    break;
+                }
+            }
+            let count = counts.entry(path.clone()).or_default();
+            *count += 1;
+            let status = match (method.as_str(), path.as_str()) {
+                (_, "/gone") => "404 Not Found",
+                ("HEAD", "/no-head") => "405 Method Not Allowed",
+                (_, "/flaky") if *count == 1 => "503 Service Unavailable",
+                (_, "/down") => "500 Internal Server Error",
+                _ => "200 OK",
+            };
+            served.lock().unwrap().push(format!("{} {}", method, path));
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+        }
+    });
+    Ok((url, requests))
+}
+
+#[test]
+fn test_check_web_links() -> anyhow::Result<()> {
+    let (server, requests) = serve()?;
+    let temp = TempDir::new()?;
+    temp.child("pages/Links.md").write_str(&format!(
+        "source:: {0}/ok\n\n- [ok]({0}/ok), {0}/gone. and <{0}/no-head>\n\t- {0}/flaky `{0}/code`\n- {0}/down\n  ```\n  {0}/fenced\n  ```\n",
+        server
+    ))?;
+    let graph = Graph::scan(temp.path())?;
+    let links: Vec<(usize, usize)> = web_links(&graph)
+        .iter()
+        .map(|link| (link.line, link.column))
+        .collect();
+    let length = server.len();
+    let expected = vec![
+        (1, 10),
+        (3, 8),
+        (3, length + 14),
+        (3, 2 * length + 26),
+        (4, 4),
+        (5, 3),
+    ];
+    assert_eq!(links, expected);
+
+    let cache = temp.child("cache/links.json");
+    let mut checker = LinkChecker::new(Some(cache.path().to_path_buf()));
+    checker.backoff = Duration::ZERO;
+    checker.retries = 1;
+    let dead = check_web_links(&graph, &mut checker)?;
+    let dead: Vec<(usize, String)> = dead
+        .iter()
+        .map(|link| {
+            let result = checker.result(&link.target).unwrap();
+            (link.line, result.describe())
+        })
+        .collect();
+    assert_eq!(
+        dead,
+        vec![(3, "HTTP 404".to_string()), (5, "HTTP 500".to_string())]
+    );
+    let served = requests.lock().unwrap().clone();
+    let count = |request: &str| served.iter().filter(|served| *served == request).count();
+    assert_eq!(count("HEAD /ok"), 1);
+    assert_eq!(count("GET /no-head"), 1);
+    assert_eq!(count("HEAD /flaky"), 2);
+    assert_eq!(count("HEAD /down"), 2);
+    assert_eq!(count("HEAD /gone"), 1);
+
+    // Cached results aren't checked again until they expire
+    let mut cached = LinkChecker::new(Some(cache.path().to_path_buf()));
+    assert_eq!(check_web_links(&graph, &mut cached)?.len(), 2);
+    assert_eq!(requests.lock().unwrap().len(), served.len());
+    cached.ttl = Duration::ZERO;
+    cached.retries = 0;
+    assert_eq!(check_web_links(&graph, &mut cached)?.len(), 2);
+    assert_eq!(requests.lock().unwrap().len(), served.len() + 6);
+    Ok(())
+}
@@ -25,3 +25,12 @@ def test_nothing_to_remove(datadir: Path) -> None:
     assert not result.output
     assert result.exit_code == 0
     assert file.read_text() == content_before
+
+
+def test_tidy_up_directory(datadir: Path) -> None:
+    actual1: Path = datadir / "empty-bullets-1.md"
+    actual2: Path = datadir / "empty-bullets-2.md"
+    result = CliRunner().invoke(app, ["tidy-up", str(datadir)])
+    assert result.output == f"{actual1}: empty bullets{os.linesep}{actual2}: empty bullets{os.linesep}"
+    assert result.exit_code == 0
+    assert actual1.read_text() == (datadir / "empty-bullets-1-clean.md").read_text().strip()
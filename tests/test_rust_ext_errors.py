@@ -0,0 +1,47 @@
+from __future__ import annotations
+
+from pathlib import Path
+
+import pytest
+from logseq_doctor import rust_ext
+
+
+def test_errors_share_a_base_class() -> None:
+    assert issubclass(rust_ext.ParseError, rust_ext.LsdError)
+    assert issubclass(rust_ext.IoError, rust_ext.LsdError)
+    assert issubclass(rust_ext.LsdError, Exception)
+
+
+def test_missing_file_raises_io_error(tmp_path: Path) -> None:
+    missing = tmp_path / "missing.md"
+    with pytest.raises(rust_ext.IoError, match="missing.md"):
+        rust_ext.fix_file_in_place(missing)
+    with pytest.raises(rust_ext.LsdError):
+        rust_ext.tidy_up(missing)
+
+
+def test_invalid_value_raises_parse_error(tmp_path: Path) -> None:
+    with pytest.raises(rust_ext.ParseError, match="unknown format yaml"):
+        rust_ext.export_flashcards(tmp_path, format="yaml")
+
+
+@pytest.mark.parametrize("contents", ["- a  b", b"- a  b"])
+def test_text_accepts_str_and_bytes(contents: str | bytes) -> None:
+    assert rust_ext.remove_consecutive_spaces(contents) == "- a b"
+    fixed, diff = rust_ext.fix_text(contents)
+    assert fixed == "- a b"
+    assert "+- a b" in diff
+    assert rust_ext.flat_markdown_to_outline(contents) == "- a  b\n"
+
+
+def test_invalid_utf8_bytes_raise_parse_error() -> None:
+    with pytest.raises(rust_ext.ParseError, match="Invalid UTF-8"):
+        rust_ext.run_lints(b"- caf\xe9")
+
+
+@pytest.mark.parametrize("contents", [42, None, ["- a"], bytearray(b"- a")])
+def test_text_rejects_other_types(contents: object) -> None:
+    with pytest.raises(TypeError, match="expected str or bytes"):
+        rust_ext.run_lints(contents)
+    with pytest.raises(TypeError, match="expected str or bytes"):
+        rust_ext.fix_text(contents)
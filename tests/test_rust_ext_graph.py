@@ -0,0 +1,80 @@
+from __future__ import annotations
+
+from datetime import date
+from pathlib import Path
+
+import pytest
+from logseq_doctor import rust_ext
+
+
+@pytest.fixture()
+def graph_path(tmp_path: Path) -> Path:
+    (tmp_path / "pages").mkdir()
+    (tmp_path / "journals").mkdir()
+    (tmp_path / "pages" / "Books.md").write_text(
+        "alias:: Reading\n\n- Dune #sci-fi\n  rating:: 5\n  id:: 65a1b2c3-0000-4000-8000-000000000001\n"
+        "  - Frank Herbert\n- TODO read [[Foundation]]\n",
+    )
+    (tmp_path / "journals" / "2024_01_02.md").write_text("- Started [[Books]]\n")
+    return tmp_path
+
+
+def test_read_graph(graph_path: Path) -> None:
+    progress: list[tuple[int, int]] = []
+    graph = rust_ext.read_graph(graph_path, progress=lambda done, total, _path: progress.append((done, total)))
+    assert isinstance(graph, rust_ext.LogseqGraph)
+    assert Path(graph.root) == graph_path
+    assert len(graph) == 2  # noqa: PLR2004
+    assert progress[-1] == (2, 2)
+    assert "reading" in graph
+    assert "Foundation" not in graph
+    assert [page.name for page in graph.pages()] == ["Jan 2nd, 2024", "Books"]
+
+    journal = graph.journals()[0]
+    assert journal.journal_date == date(2024, 1, 2)
+    assert Path(journal.path) == graph_path / "journals" / "2024_01_02.md"
+
+
+def test_pages_and_blocks(graph_path: Path) -> None:
+    page = rust_ext.read_graph(graph_path).page("Reading")
+    assert isinstance(page, rust_ext.Page)
+    assert page.name == "Books"
+    assert page.aliases == ["Reading"]
+    assert page.properties == {"alias": "Reading"}
+    assert page.journal_date is None
+    assert page.referenced_names() == ["sci-fi", "Foundation"]
+
+    dune, todo = page.blocks()
+    assert isinstance(dune, rust_ext.Block)
+    assert (dune.line, dune.level, dune.first_line) == (3, 0, "Dune #sci-fi")
+    assert dune.properties == {"rating": "5", "id": "65a1b2c3-0000-4000-8000-000000000001"}
+    assert dune.uuid == "65a1b2c3-0000-4000-8000-000000000001"
+    assert dune.child_count == 1
+    (author,) = dune.children
+    assert (author.content, author.level, author.line) == ("Frank Herbert", 1, 6)
+    assert author.page.path == page.path
+    assert todo.first_line == "TODO read [[Foundation]]"
+    assert [block.line for block in page.all_blocks()] == [3, 6, 7]
+
+
+def test_iter_blocks(graph_path: Path) -> None:
+    graph = rust_ext.read_graph(graph_path)
+    assert [block.first_line for block in graph.iter_blocks()] == [
+        "Started [[Books]]",
+        "Dune #sci-fi",
+        "Frank Herbert",
+        "TODO read [[Foundation]]",
+    ]
+
+    nested = graph.iter_blocks(filter=lambda block: block.level > 0)
+    assert [block.content for block in nested] == ["Frank Herbert"]
+    assert list(graph.iter_blocks(filter=lambda _block: False)) == []
+
+
+def test_iter_blocks_filter_exception_propagates(graph_path: Path) -> None:
+    def broken(block: rust_ext.Block) -> bool:
+        raise KeyError(block.line)
+
+    blocks = rust_ext.read_graph(graph_path).iter_blocks(filter=broken)
+    with pytest.raises(KeyError):
+        next(blocks)
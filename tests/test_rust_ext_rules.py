@@ -0,0 +1,76 @@
+from __future__ import annotations
+
+from collections.abc import Iterator
+
+import pytest
+from logseq_doctor import rust_ext
+
+
+@pytest.fixture()
+def no_fixme() -> Iterator[str]:
+    def rule(block: rust_ext.Block) -> list[object] | str | None:
+        """Blocks should not contain FIXME."""
+        if "FIXME" not in block.content:
+            return None
+        if block.level:
+            return [(block.line, 3, "Nested FIXME")]
+        return "FIXME left in the block"
+
+    rust_ext.register_rule("no-fixme", rule)
+    yield "no-fixme"
+    rust_ext.unregister_rule("no-fixme")
+
+
+def test_registered_rule_finds_problems(no_fixme: str) -> None:
+    report = rust_ext.run_lints("- FIXME one\n  - FIXME two\n- fine\n", rules=[no_fixme])
+    found = [(finding.rule, finding.line, finding.column, finding.message) for finding in report.findings]
+    assert found == [
+        (no_fixme, 1, 3, "FIXME left in the block"),
+        (no_fixme, 2, 3, "Nested FIXME"),
+    ]
+    info = next(info for info in rust_ext.list_rules() if info.id == no_fixme)
+    assert info.description == "Blocks should not contain FIXME."
+
+
+def test_registering_again_replaces_the_rule(no_fixme: str) -> None:
+    rust_ext.register_rule(no_fixme, lambda _block: "Replaced")
+    report = rust_ext.run_lints("- text\n", rules=[no_fixme])
+    assert [finding.message for finding in report.findings] == ["Replaced"]
+
+
+def test_unregister_rule(no_fixme: str) -> None:
+    assert rust_ext.unregister_rule(no_fixme)
+    assert not rust_ext.unregister_rule(no_fixme)
+    assert no_fixme not in [info.id for info in rust_ext.list_rules()]
+    assert rust_ext.run_lints("- FIXME\n", rules=[no_fixme]).findings == []
+
+
+def test_register_rule_rejects_builtin_ids_and_non_callables() -> None:
+    with pytest.raises(ValueError, match="consecutive-spaces is a built-in rule"):
+        rust_ext.register_rule("consecutive-spaces", lambda _block: None)
+    with pytest.raises(TypeError, match="callback must be callable"):
+        rust_ext.register_rule("not-callable", "nope")
+
+
+def test_exception_of_a_rule_propagates() -> None:
+    def broken(block: rust_ext.Block) -> None:
+        msg = f"broken on line {block.line}"
+        raise RuntimeError(msg)
+
+    rust_ext.register_rule("broken", broken)
+    try:
+        with pytest.raises(RuntimeError, match="broken on line 1"):
+            rust_ext.run_lints("- a\n- b\n")
+        with pytest.raises(RuntimeError, match="broken on line 1"):
+            rust_ext.run_lints("- a\n", rules=["broken"])
+    finally:
+        rust_ext.unregister_rule("broken")
+
+
+def test_rule_with_an_invalid_result_raises_type_error() -> None:
+    rust_ext.register_rule("bad-result", lambda _block: [42])
+    try:
+        with pytest.raises(TypeError, match="rule bad-result must return messages"):
+            rust_ext.run_lints("- a\n", rules=["bad-result"])
+    finally:
+        rust_ext.unregister_rule("bad-result")